@@ -11,9 +11,10 @@ use termion::raw::IntoRawMode;
 
 use web_audio_api::context::{AudioContext, BaseAudioContext, OfflineAudioContext};
 use web_audio_api::node::{
-    AudioBufferSourceNode, AudioNode, AudioScheduledSourceNode, OscillatorType,
+    AudioBufferSourceNode, AudioNode, AudioScheduledSourceNode, BiquadFilterNode,
+    BiquadFilterOptions, OscillatorType,
 };
-use web_audio_api::AudioBuffer;
+use web_audio_api::{AudioBuffer, PeriodicWave, PeriodicWaveOptions};
 
 // benchmark adapted from https://github.com/padenot/webaudio-benchmark
 // missing the "Convolution Reverb" as we don't have the node implemented yet
@@ -474,6 +475,42 @@ fn main() {
         benchmark(&mut stdout, name, &mut context, &mut results);
     }
 
+    // 100 filters with an automated frequency, coefficients computed for every frame and
+    // computed every 8 frames then interpolated (the default)
+    for &(name, coefficient_interval) in [
+        ("Biquad automation x100 (coefficients per frame)", 1),
+        ("Biquad automation x100 (decimated coefficients)", 8),
+    ]
+    .iter()
+    {
+        let sample_rate = 44100.;
+        let mut context = OfflineAudioContext::new(1, DURATION * sample_rate as usize, sample_rate);
+
+        let osc = context.create_oscillator();
+        osc.set_type(OscillatorType::Sawtooth);
+        osc.frequency().set_value(110.);
+        osc.start();
+
+        let duration = DURATION as f64;
+
+        for i in 0..100 {
+            let options = BiquadFilterOptions {
+                coefficient_interval,
+                ..BiquadFilterOptions::default()
+            };
+            let filter = BiquadFilterNode::new(&context, options);
+            filter.connect(&context.destination());
+            osc.connect(&filter);
+
+            filter.frequency().set_value_at_time(100. + i as f32, 0.);
+            filter
+                .frequency()
+                .exponential_ramp_to_value_at_time(10_000., duration);
+        }
+
+        benchmark(&mut stdout, name, &mut context, &mut results);
+    }
+
     {
         let name = "Stereo panning";
 
@@ -528,6 +565,166 @@ fn main() {
         benchmark(&mut stdout, name, &mut context, &mut results);
     }
 
+    {
+        let name = "Oscillator bank (100 oscillators of each wavetable type)";
+
+        let adjusted_duration = DURATION / 10;
+        let mut context =
+            OfflineAudioContext::new(1, adjusted_duration * sample_rate as usize, sample_rate);
+
+        let gain = context.create_gain();
+        gain.connect(&context.destination());
+        gain.gain().set_value(0.001);
+
+        let wave = context.create_periodic_wave(PeriodicWaveOptions {
+            real: Some(vec![0.; 65]),
+            imag: Some((0..65).map(|k| 1. / k.max(1) as f32).collect()),
+            disable_normalization: false,
+        });
+
+        // the sines read the sine table, the others one or two band-limited tables
+        for i in 0..100 {
+            for type_ in [
+                OscillatorType::Sine,
+                OscillatorType::Sawtooth,
+                OscillatorType::Triangle,
+                OscillatorType::Custom,
+            ] {
+                let osc = context.create_oscillator();
+                osc.connect(&gain);
+                if type_ == OscillatorType::Custom {
+                    osc.set_periodic_wave(wave.clone());
+                } else {
+                    osc.set_type(type_);
+                }
+                osc.frequency().set_value(50. + 20. * i as f32);
+                osc.start();
+            }
+        }
+
+        benchmark(&mut stdout, name, &mut context, &mut results);
+    }
+
+    {
+        let name = "Large graph (1000 oscillators with filters)";
+
+        let adjusted_duration = DURATION / 40;
+        let mut context =
+            OfflineAudioContext::new(1, adjusted_duration * sample_rate as usize, sample_rate);
+
+        let gain = context.create_gain();
+        gain.connect(&context.destination());
+        gain.gain().set_value(0.001);
+
+        for i in 0..1000 {
+            let biquad = context.create_biquad_filter();
+            biquad.connect(&gain);
+
+            let osc = context.create_oscillator();
+            osc.connect(&biquad);
+            osc.set_type(OscillatorType::Sawtooth);
+            osc.frequency().set_value(100. + i as f32);
+            osc.start();
+        }
+
+        benchmark(&mut stdout, name, &mut context, &mut results);
+    }
+
+    for freeze in [false, true] {
+        let name = if freeze {
+            "Effect chains (64 x 12 nodes, frozen)"
+        } else {
+            "Effect chains (64 x 12 nodes)"
+        };
+
+        let adjusted_duration = DURATION / 8;
+        let mut context =
+            OfflineAudioContext::new(1, adjusted_duration * sample_rate as usize, sample_rate);
+
+        let gain = context.create_gain();
+        gain.connect(&context.destination());
+        gain.gain().set_value(0.01);
+
+        for i in 0..64 {
+            let osc = context.create_oscillator();
+            osc.frequency().set_value(100. + i as f32);
+            osc.start();
+
+            let chain: Vec<Box<dyn AudioNode>> = (0..12)
+                .map(|j| -> Box<dyn AudioNode> {
+                    if j % 2 == 0 {
+                        Box::new(context.create_gain())
+                    } else {
+                        Box::new(context.create_biquad_filter())
+                    }
+                })
+                .collect();
+            osc.connect(&*chain[0]);
+            chain.windows(2).for_each(|w| {
+                w[0].connect(&*w[1]);
+            });
+            chain[11].connect(&gain);
+
+            if freeze {
+                let nodes: Vec<&dyn AudioNode> = chain.iter().map(|n| &**n).collect();
+                context.freeze_chain(&nodes);
+            }
+        }
+
+        benchmark(&mut stdout, name, &mut context, &mut results);
+    }
+
+    {
+        let name = "Batch of short renders (200 contexts, custom wave w/ 256 harmonics)";
+
+        write!(
+            stdout,
+            "{}{}> Running benchmark: {}",
+            clear::CurrentLine,
+            cursor::Left(200),
+            name
+        )
+        .unwrap();
+        stdout.flush().unwrap();
+
+        // the wavetable is synthesized in each context, like a job rendering one asset per
+        // context, so the table build time is part of the measure
+        let real = vec![0.; 257];
+        let imag: Vec<f32> = (0..257)
+            .map(|k| if k == 0 { 0. } else { 1. / k as f32 })
+            .collect();
+        let length = sample_rate as usize / 10;
+
+        let start = Instant::now();
+        let mut samples = Vec::with_capacity(200 * length);
+        for _ in 0..200 {
+            let mut context = OfflineAudioContext::new(1, length, sample_rate);
+
+            let options = PeriodicWaveOptions {
+                real: Some(real.clone()),
+                imag: Some(imag.clone()),
+                disable_normalization: false,
+            };
+            let wave = PeriodicWave::new(&context, options);
+
+            let osc = context.create_oscillator();
+            osc.set_periodic_wave(wave);
+            osc.frequency().set_value(110.);
+            osc.connect(&context.destination());
+            osc.start();
+
+            let buffer = context.start_rendering_sync();
+            samples.extend_from_slice(buffer.get_channel_data(0));
+        }
+        let duration = start.elapsed();
+
+        results.push(BenchResult {
+            name,
+            duration,
+            buffer: AudioBuffer::from(vec![samples], sample_rate),
+        });
+    }
+
     write!(
         stdout,
         "{}{}> All done!\r\n\r\n",
@@ -0,0 +1,156 @@
+use web_audio_api::node::{
+    BiquadFilter, BiquadFilterType, ChannelConfig, Oscillator, OscillatorType,
+};
+use web_audio_api::render::{
+    AudioParamValues, AudioProcessor, AudioRenderQuantum, Graph, NodeIndex, RenderScope,
+};
+use web_audio_api::{
+    AudioParamDescriptor, AudioParamProcessor, AutomationRate, ParamEvent, RENDER_QUANTUM_SIZE,
+};
+
+// Drive the render graph manually, without an AudioContext or a backend.
+//
+// Only the render core and the DSP of the nodes are used here, so this also runs on targets
+// without `std`, e.g. when building with `default-features = false, features = ["alloc"]`. On
+// such a target the `render` call would live in the DMA / audio interrupt handler.
+
+/// Band-limited sawtooth source, with an automated frequency
+struct Synth {
+    oscillator: Oscillator,
+    frequency: AudioParamProcessor,
+}
+
+impl AudioProcessor for Synth {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        let output = &mut outputs[0];
+        output.set_number_of_channels(1);
+
+        let Self {
+            oscillator,
+            frequency,
+        } = self;
+        let frequency_values = frequency.compute(scope);
+
+        for (o, &frequency) in output.channel_data_mut(0).iter_mut().zip(frequency_values) {
+            *o =
+                oscillator.next_sample(OscillatorType::Sawtooth, frequency, 0.5, scope.sample_rate);
+        }
+
+        true
+    }
+}
+
+/// Destination, filters its input with a fixed lowpass and applies a gain
+struct Lowpass {
+    filter: BiquadFilter,
+    gain: f32,
+}
+
+impl AudioProcessor for Lowpass {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+        *output = input.clone();
+
+        // gain (of the shelving and peaking types), detune, frequency and Q of each frame
+        let zeroes = [0.; RENDER_QUANTUM_SIZE];
+        let frequency = [1_000.; RENDER_QUANTUM_SIZE];
+        let q = [1.; RENDER_QUANTUM_SIZE];
+        self.filter.filter(
+            input,
+            output,
+            &zeroes,
+            &zeroes,
+            &frequency,
+            &q,
+            scope.sample_rate,
+        );
+
+        output
+            .channels_mut()
+            .iter_mut()
+            .for_each(|channel| channel.iter_mut().for_each(|s| *s *= self.gain));
+
+        false
+    }
+}
+
+fn main() {
+    let sample_rate = 48_000.;
+
+    // the node with index 0 is the destination of the graph
+    let mut graph = Graph::new();
+    let destination = NodeIndex(0);
+    let source = NodeIndex(1);
+
+    // sweep from 110 Hz to 880 Hz over the rendered second
+    let mut frequency = AudioParamProcessor::new(AudioParamDescriptor {
+        automation_rate: AutomationRate::A,
+        default_value: 440.,
+        min_value: 0.,
+        max_value: sample_rate / 2.,
+    });
+    frequency.schedule(ParamEvent::SetValueAtTime {
+        value: 110.,
+        start_time: 0.,
+    });
+    frequency.schedule(ParamEvent::ExponentialRampToValueAtTime {
+        value: 880.,
+        end_time: 1.,
+    });
+
+    graph.add_node(
+        destination,
+        Box::new(Lowpass {
+            filter: BiquadFilter::new(BiquadFilterType::Lowpass, 8),
+            gain: 0.5,
+        }),
+        1,
+        1,
+        ChannelConfig::default(),
+    );
+    graph.add_node(
+        source,
+        Box::new(Synth {
+            oscillator: Oscillator::new(),
+            frequency,
+        }),
+        0,
+        1,
+        ChannelConfig::default(),
+    );
+    // connect output 0 of the source to input 0 of the destination
+    graph.add_edge((source, 0), (destination, 0));
+
+    // render one second of audio, quantum by quantum
+    let number_of_quanta = sample_rate as usize / RENDER_QUANTUM_SIZE;
+    let mut peak = 0_f32;
+
+    for i in 0..number_of_quanta {
+        let scope = RenderScope::new((i * RENDER_QUANTUM_SIZE) as u64, sample_rate);
+        let output = graph.render(&scope);
+
+        // hand the samples over to the audio hardware here
+        peak = output
+            .channel_data(0)
+            .iter()
+            .fold(peak, |p, s| p.max(s.abs()));
+    }
+
+    println!(
+        "rendered {} quanta of {} samples, peak {:.3}",
+        number_of_quanta, RENDER_QUANTUM_SIZE, peak
+    );
+}
@@ -0,0 +1,210 @@
+use std::{thread, time};
+
+use web_audio_api::context::{
+    AudioContext, AudioContextRegistration, AudioParamId, BaseAudioContext,
+};
+use web_audio_api::node::{AudioNode, AudioScheduledSourceNode, ChannelConfig};
+use web_audio_api::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use web_audio_api::{AudioParam, AudioParamDescriptor, AutomationRate};
+
+// A more complete custom node than the `worklet` example: a stereo ping-pong delay with two
+// audio params and an internal buffer.
+//
+// `cargo run --release --example ping_pong_delay`
+
+/// Maximum delay time of the node, in seconds
+const MAX_DELAY_TIME: f32 = 2.;
+
+/// Echoes bouncing between the left and the right channels
+struct PingPongDelayNode {
+    /// handle to the audio context, owned by the node, dropping it releases the processor
+    registration: AudioContextRegistration,
+    /// channel configuration (for up/down-mixing of inputs), required for all audio nodes
+    channel_config: ChannelConfig,
+    /// time between two echoes, in seconds
+    delay_time: AudioParam,
+    /// gain applied to each echo
+    feedback: AudioParam,
+}
+
+impl AudioNode for PingPongDelayNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    // the input is mixed to mono before entering the delay lines
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    // a single stereo output
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+
+    fn params(&self) -> Vec<(&'static str, &AudioParam)> {
+        vec![
+            ("delayTime", &self.delay_time),
+            ("feedback", &self.feedback),
+        ]
+    }
+}
+
+impl PingPongDelayNode {
+    fn new<C: BaseAudioContext>(context: &C) -> Self {
+        // the buffer is allocated on the control thread, the render thread must not allocate
+        let length = (MAX_DELAY_TIME * context.sample_rate()) as usize + 1;
+
+        context.register(move |registration| {
+            // the params are created inside the closure, for the registration of the node
+            let delay_time_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: MAX_DELAY_TIME,
+                default_value: 0.3,
+                automation_rate: AutomationRate::K,
+            };
+            let (delay_time, delay_time_id) =
+                context.create_audio_param(delay_time_opts, &registration);
+
+            let feedback_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: 0.95,
+                default_value: 0.5,
+                automation_rate: AutomationRate::A,
+            };
+            let (feedback, feedback_id) = context.create_audio_param(feedback_opts, &registration);
+
+            // the ids of the params are moved into the processor, the only place where the
+            // values of the params are available
+            let render = PingPongDelayProcessor {
+                delay_time: delay_time_id,
+                feedback: feedback_id,
+                left: vec![0.; length],
+                right: vec![0.; length],
+                write_index: 0,
+                silent_frames: length,
+            };
+
+            // the node owns the registration and the params
+            let node = PingPongDelayNode {
+                registration,
+                channel_config: ChannelConfig::default(),
+                delay_time,
+                feedback,
+            };
+
+            // exactly one processor per node
+            (node, Box::new(render))
+        })
+    }
+
+    fn delay_time(&self) -> &AudioParam {
+        &self.delay_time
+    }
+
+    fn feedback(&self) -> &AudioParam {
+        &self.feedback
+    }
+}
+
+struct PingPongDelayProcessor {
+    delay_time: AudioParamId,
+    feedback: AudioParamId,
+    /// delay lines of the left and right channels, of the same length
+    left: Vec<f32>,
+    right: Vec<f32>,
+    write_index: usize,
+    /// number of frames written to the delay lines since they hold audible values
+    silent_frames: usize,
+}
+
+impl AudioProcessor for PingPongDelayProcessor {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+        output.set_number_of_channels(2);
+
+        let length = self.left.len();
+        // k-rate param, a single value for the render quantum
+        let delay_time = params.get(&self.delay_time)[0];
+        let delay = ((delay_time * scope.sample_rate) as usize).clamp(1, length - 1);
+        let feedback = params.get(&self.feedback);
+
+        let channels = input.channels();
+        let gain = 1. / channels.len() as f32;
+
+        let (left_out, right_out) = output.channels_mut().split_at_mut(1);
+        let left_out = &mut left_out[0];
+        let right_out = &mut right_out[0];
+
+        for i in 0..left_out.len() {
+            // mix the input down to mono
+            let mono: f32 = channels.iter().map(|c| c[i]).sum::<f32>() * gain;
+
+            let read_index = (self.write_index + length - delay) % length;
+            let left = self.left[read_index];
+            let right = self.right[read_index];
+
+            // the input enters on the left, each echo bounces to the other channel
+            let fb = feedback[if feedback.len() == 1 { 0 } else { i }];
+            let left_in = mono + fb * right;
+            let right_in = fb * left;
+            self.left[self.write_index] = left_in;
+            self.right[self.write_index] = right_in;
+            self.write_index = (self.write_index + 1) % length;
+
+            left_out[i] = left;
+            right_out[i] = right;
+
+            if left_in.abs() > 1e-6 || right_in.abs() > 1e-6 {
+                self.silent_frames = 0;
+            } else {
+                self.silent_frames += 1;
+            }
+        }
+
+        // keep the node alive while the delay lines hold audible echoes, the processor can be
+        // released once the node is dropped and the echoes have faded out
+        self.silent_frames < length
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let context = AudioContext::default();
+
+    let delay = PingPongDelayNode::new(&context);
+    delay.delay_time().set_value(0.25);
+    delay.feedback().set_value(0.6);
+    delay.connect(&context.destination());
+
+    // short blips, each one echoing from left to right
+    let now = context.current_time();
+    for (i, frequency) in [440., 550., 660., 880.].iter().enumerate() {
+        let start = now + i as f64 * 2.;
+
+        let env = context.create_gain();
+        env.gain().set_value_at_time(0., start);
+        env.gain().linear_ramp_to_value_at_time(0.5, start + 0.01);
+        env.gain().linear_ramp_to_value_at_time(0., start + 0.1);
+        env.connect(&delay);
+
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(*frequency);
+        osc.connect(&env);
+        osc.start_at(start);
+        osc.stop_at(start + 0.1);
+    }
+
+    thread::sleep(time::Duration::from_secs(10));
+}
@@ -0,0 +1,66 @@
+use std::{thread, time};
+use web_audio_api::context::{AudioContext, BaseAudioContext};
+use web_audio_api::node::{AudioNode, BiquadFilterNode, GainNode, OscillatorNode, OscillatorType};
+use web_audio_api::voice::{VoiceGraph, VoicePool};
+
+// Plays overlapping chords on a pool of 6 voices, the oldest notes are stolen
+//
+// `cargo run --release --example polyphonic_synth`
+
+type Voice = (OscillatorNode, BiquadFilterNode, GainNode);
+
+fn midi_to_frequency(note: u8) -> f32 {
+    440. * 2_f32.powf((f32::from(note) - 69.) / 12.)
+}
+
+fn main() {
+    let context = AudioContext::default();
+
+    let mut pool = VoicePool::new(&context, 6, |ctx| {
+        let osc = ctx.create_oscillator();
+        osc.set_type(OscillatorType::Sawtooth);
+
+        let filter = ctx.create_biquad_filter();
+        filter.q().set_value(4.);
+        osc.connect(&filter);
+
+        let env = ctx.create_gain();
+        env.gain().set_value(0.);
+        filter.connect(&env);
+
+        VoiceGraph::new((osc, filter, env), |(_, _, env): &Voice| env)
+            .with_source(|(osc, _, _)| osc)
+            .on_trigger(|(osc, filter, env), note: &u8, when| {
+                osc.frequency()
+                    .set_value_at_time(midi_to_frequency(*note), when);
+                filter
+                    .frequency()
+                    .set_value_at_time(4000., when)
+                    .exponential_ramp_to_value_at_time(800., when + 0.5);
+                env.gain()
+                    .set_value_at_time(0., when)
+                    .linear_ramp_to_value_at_time(0.1, when + 0.02);
+            })
+            .on_release(|(_, _, env), when| {
+                env.gain()
+                    .cancel_and_hold_at_time(when)
+                    .set_target_at_time(0., when, 0.2);
+                1.
+            })
+    });
+    pool.output().connect(&context.destination());
+
+    let chords: [[u8; 3]; 4] = [[60, 64, 67], [57, 60, 64], [53, 57, 60], [55, 59, 62]];
+
+    loop {
+        for chord in chords.iter() {
+            let now = context.current_time();
+            let notes: Vec<_> = chord.iter().map(|note| pool.note_on(*note, now)).collect();
+            thread::sleep(time::Duration::from_millis(800));
+
+            // the release tails overlap with the next chord
+            let now = context.current_time();
+            notes.into_iter().for_each(|id| pool.note_off(id, now));
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! Example showing how to record the input device without an AudioContext
+//!
+//! Records 5 seconds of the default input device and writes it to `record_input.wav`
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use web_audio_api::media::{record_input, CaptureOptions};
+use web_audio_api::AudioBuffer;
+
+/// Write the buffer as a 16 bits PCM WAV file
+fn write_wav(buffer: &AudioBuffer, path: &str) -> std::io::Result<()> {
+    let channels = buffer.number_of_channels() as u16;
+    let sample_rate = buffer.sample_rate() as u32;
+    let data_len = (buffer.length() * channels as usize * 2) as u32;
+
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(b"RIFF")?;
+    out.write_all(&(36 + data_len).to_le_bytes())?;
+    out.write_all(b"WAVEfmt ")?;
+    out.write_all(&16_u32.to_le_bytes())?;
+    out.write_all(&1_u16.to_le_bytes())?; // PCM
+    out.write_all(&channels.to_le_bytes())?;
+    out.write_all(&sample_rate.to_le_bytes())?;
+    out.write_all(&(sample_rate * channels as u32 * 2).to_le_bytes())?;
+    out.write_all(&(channels * 2).to_le_bytes())?;
+    out.write_all(&16_u16.to_le_bytes())?;
+    out.write_all(b"data")?;
+    out.write_all(&data_len.to_le_bytes())?;
+
+    for i in 0..buffer.length() {
+        for c in 0..buffer.number_of_channels() {
+            let sample = buffer.get_channel_data(c)[i].clamp(-1., 1.);
+            out.write_all(&((sample * i16::MAX as f32) as i16).to_le_bytes())?;
+        }
+    }
+
+    out.flush()
+}
+
+fn main() {
+    env_logger::init();
+
+    let options = CaptureOptions {
+        duration: Some(5.),
+        ..CaptureOptions::default()
+    };
+
+    println!("Recording for 5 seconds");
+    let handle = record_input(options);
+    while !handle.is_finished() {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    println!(
+        "{} underruns, {} overruns",
+        handle.underruns(),
+        handle.overruns()
+    );
+    let recording = handle.wait();
+
+    println!(
+        "Recorded {} frames of {} channels at {} Hz",
+        recording.length(),
+        recording.number_of_channels(),
+        recording.sample_rate()
+    );
+
+    write_wav(&recording, "record_input.wav").unwrap();
+    println!("Written to record_input.wav");
+}
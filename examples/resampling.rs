@@ -66,6 +66,7 @@ fn main() {
     let audio_context_38000 = AudioContext::new(AudioContextOptions {
         sample_rate: Some(38000.),
         latency_hint: AudioContextLatencyCategory::Interactive,
+        ..AudioContextOptions::default()
     });
     let file_38000 = File::open("samples/sample-38000.wav").unwrap();
     let buffer_38000 = audio_context_38000
@@ -75,6 +76,7 @@ fn main() {
     let audio_context_44100 = AudioContext::new(AudioContextOptions {
         sample_rate: Some(44100.),
         latency_hint: AudioContextLatencyCategory::Interactive,
+        ..AudioContextOptions::default()
     });
     let file_44100 = File::open("samples/sample-44100.wav").unwrap();
     let buffer_44100 = audio_context_44100
@@ -84,6 +86,7 @@ fn main() {
     let audio_context_48000 = AudioContext::new(AudioContextOptions {
         sample_rate: Some(48000.),
         latency_hint: AudioContextLatencyCategory::Interactive,
+        ..AudioContextOptions::default()
     });
     let file_48000 = File::open("samples/sample-48000.wav").unwrap();
     let buffer_48000 = audio_context_48000
@@ -0,0 +1,57 @@
+use std::fs::File;
+use web_audio_api::context::{AudioContext, BaseAudioContext};
+use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+use web_audio_api::node::{DynamicsCompressorNode, DynamicsCompressorOptions};
+
+// Duck a music bed whenever a voice is talking over it, the voice drives the
+// compressor through its sidechain input but is routed to the output on its own
+fn main() {
+    env_logger::init();
+
+    println!("> music bed ducked by the voice through a sidechain compressor");
+
+    let context = AudioContext::default();
+
+    let options = DynamicsCompressorOptions {
+        threshold: -40.,
+        knee: 6.,
+        ratio: 8.,
+        attack: 0.01,
+        release: 0.3,
+        sidechain: true,
+        ..DynamicsCompressorOptions::default()
+    };
+    let ducker = DynamicsCompressorNode::new(&context, options);
+    ducker.connect(&context.destination());
+
+    // music bed on the main input
+    let file = File::open("samples/sample.wav").unwrap();
+    let music = context.decode_audio_data_sync(file).unwrap();
+
+    let bed = context.create_buffer_source();
+    bed.set_buffer(music);
+    bed.set_loop(true);
+    bed.connect(&ducker);
+    bed.start();
+
+    // voice on the sidechain input, and straight to the output
+    let file = File::open("samples/think-mono-48000.wav").unwrap();
+    let voice = context.decode_audio_data_sync(file).unwrap();
+
+    for i in 0..4 {
+        std::thread::sleep(std::time::Duration::from_secs(3));
+        println!(
+            "+ voice #{}, reduction before: {:.1} dB",
+            i,
+            ducker.reduction()
+        );
+
+        let src = context.create_buffer_source();
+        src.set_buffer(voice.clone());
+        src.connect(&context.destination());
+        src.connect_at(&ducker, 0, 1);
+        src.start();
+    }
+
+    std::thread::sleep(std::time::Duration::from_secs(3));
+}
@@ -10,13 +10,6 @@ fn main() {
     let file = File::open("samples/sample.wav").unwrap();
     let audio_buffer = context.decode_audio_data_sync(file).unwrap();
 
-    // @fixme - if only one node in the graph it is never removed even when returning
-    // false, se we put this dummy node in the graph so that other ones are properly
-    // removed
-    let src = context.create_buffer_source();
-    src.set_buffer(audio_buffer.clone());
-    src.connect(&context.destination());
-
     {
         println!("++ play until end");
         let src = context.create_buffer_source();
@@ -0,0 +1,54 @@
+//! Example showing the crate running in the browser
+//!
+//! Build with `wasm-pack build --target web` and load the generated module in an
+//! `AudioWorkletProcessor`. The headless tests run in node with
+//! `cargo test --target wasm32-unknown-unknown`, given `wasm-bindgen-test-runner` (from
+//! `wasm-bindgen-cli`) is installed.
+use wasm_bindgen::prelude::*;
+
+use web_audio_api::context::{BaseAudioContext, ManualAudioContext, OfflineAudioContext};
+use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+use web_audio_api::wasm::WasmRenderHandle;
+
+/// Render one second of a filtered sawtooth, returns the samples of the first channel
+#[wasm_bindgen]
+pub fn render_offline(sample_rate: f32) -> Vec<f32> {
+    let mut context = OfflineAudioContext::new(2, sample_rate as usize, sample_rate);
+
+    let biquad = context.create_biquad_filter();
+    biquad.frequency().set_value(800.);
+    biquad.connect(&context.destination());
+
+    let osc = context.create_oscillator();
+    osc.set_type(web_audio_api::node::OscillatorType::Sawtooth);
+    osc.frequency().set_value(220.);
+    osc.connect(&biquad);
+    osc.start();
+
+    // no render thread is involved, the rendering runs on the calling thread
+    let buffer = context.start_rendering_sync();
+    buffer.get_channel_data(0).to_vec()
+}
+
+/// Set up a sine wave with a tremolo, rendered by the `AudioWorkletProcessor` pulling the
+/// returned handle
+#[wasm_bindgen]
+pub fn create_tremolo(number_of_channels: usize, sample_rate: f32) -> WasmRenderHandle {
+    let (context, handle) = ManualAudioContext::new(number_of_channels, sample_rate);
+
+    let gain = context.create_gain();
+    gain.connect(&context.destination());
+
+    let lfo = context.create_oscillator();
+    lfo.frequency().set_value(4.);
+    lfo.connect(gain.gain());
+    lfo.start();
+
+    let osc = context.create_oscillator();
+    osc.frequency().set_value(440.);
+    osc.connect(&gain);
+    osc.start();
+
+    // the nodes keep playing once their handles and the context are dropped
+    handle.into()
+}
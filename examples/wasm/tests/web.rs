@@ -0,0 +1,29 @@
+//! Headless tests, run in node with `cargo test --target wasm32-unknown-unknown`
+use wasm_bindgen_test::*;
+
+use web_audio_api_wasm_example::{create_tremolo, render_offline};
+
+#[wasm_bindgen_test]
+fn offline_render_produces_a_buffer() {
+    let samples = render_offline(44_100.);
+
+    assert_eq!(samples.len(), 44_100);
+    assert!(samples.iter().all(|v| v.is_finite()));
+    assert!(samples.iter().any(|v| *v != 0.));
+}
+
+#[wasm_bindgen_test]
+fn worklet_pulls_the_output() {
+    let mut handle = create_tremolo(2, 48_000.);
+    assert_eq!(handle.number_of_channels(), 2);
+
+    // a render quantum of the worklet, interleaved
+    let mut output = vec![0.; 128 * 2];
+    for _ in 0..10 {
+        handle.render(&mut output);
+    }
+
+    assert!(output.iter().any(|v| *v != 0.));
+    // both channels carry the same mono signal
+    assert!(output.chunks(2).all(|frame| frame[0] == frame[1]));
+}
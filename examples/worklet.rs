@@ -36,6 +36,11 @@ impl AudioNode for WhiteNoiseNode {
     fn number_of_outputs(&self) -> usize {
         1
     }
+
+    // list the audio params by name, for generic access with `param`
+    fn params(&self) -> Vec<(&'static str, &AudioParam)> {
+        vec![("amplitude", &self.amplitude)]
+    }
 }
 
 impl WhiteNoiseNode {
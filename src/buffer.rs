@@ -1,8 +1,11 @@
 //! General purpose audio signal data structures
+use std::f32::consts::PI;
 use std::sync::Arc;
 
+use realfft::RealFftPlanner;
+
+use crate::media::{DecodeError, MediaDecoder};
 use crate::render::AudioRenderQuantum;
-use crate::media::MediaDecoder;
 use crate::{
     assert_valid_channel_number, assert_valid_number_of_channels, assert_valid_sample_rate,
 };
@@ -133,7 +136,8 @@ impl AudioBuffer {
     ///
     /// # Errors
     ///
-    /// This method returns an Error in various cases (IO, mime sniffing, decoding).
+    /// This method returns a [`DecodeError`] telling apart an empty input, an unsupported
+    /// format, a corrupt or truncated input, and an IO error.
     ///
     /// # Example
     ///
@@ -153,9 +157,10 @@ impl AudioBuffer {
     pub fn from_encoded<R: std::io::Read + Send + Sync + 'static>(
         input: R,
         sample_rate: f32,
-    ) -> Result<AudioBuffer, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<AudioBuffer, DecodeError> {
         // Set up a media decoder, consume the stream in full and construct a single buffer out of it
-        let mut buffer = MediaDecoder::try_new(input)?
+        let mut decoder = MediaDecoder::try_new(input)?;
+        let mut buffer = std::iter::from_fn(|| decoder.next_buffer())
             .collect::<Result<Vec<_>, _>>()?
             .into_iter()
             .reduce(|mut accum, item| {
@@ -278,6 +283,112 @@ impl AudioBuffer {
         self.channel_data(channel_number).as_slice()
     }
 
+    /// Number of handles sharing the samples of the given channel
+    #[cfg(test)]
+    pub(crate) fn channel_strong_count(&self, channel_number: usize) -> usize {
+        Arc::strong_count(&self.channels[channel_number].data)
+    }
+
+    /// Create an `AudioBuffer` holding the frames in the `start_frame..end_frame` range
+    ///
+    /// The samples are not copied, the returned buffer shares them with this one. A channel
+    /// mutated in either buffer, e.g. with [`copy_to_channel`](Self::copy_to_channel), is
+    /// copied first, so the other buffer is never affected.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if:
+    /// - `start_frame` is greater than `end_frame`
+    /// - `end_frame` is greater than the length of the buffer
+    pub fn slice(&self, start_frame: usize, end_frame: usize) -> AudioBuffer {
+        if start_frame > end_frame || end_frame > self.length() {
+            panic!(
+                "IndexSizeError - Invalid slice range {}..{} (length: {})",
+                start_frame,
+                end_frame,
+                self.length()
+            );
+        }
+
+        let channels = self
+            .channels
+            .iter()
+            .map(|channel| channel.slice(start_frame, end_frame))
+            .collect();
+
+        Self {
+            channels,
+            sample_rate: self.sample_rate,
+        }
+    }
+
+    /// Estimate the frequency of the strongest spectral component of the given channel
+    ///
+    /// The channel data is zero-padded to the next power of two and Hann windowed before
+    /// computing its spectrum. The position of the peak bin is refined with parabolic
+    /// interpolation between the bin and its neighbours.
+    ///
+    /// Returns `0.` for an empty buffer.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if:
+    /// - the given channel number is greater than or equal to the given number of channels.
+    pub fn peak_frequency(&self, channel: usize, sample_rate: f32) -> f32 {
+        let data = self.get_channel_data(channel);
+        let length = data.len();
+
+        if length == 0 {
+            return 0.;
+        }
+
+        let fft_size = length.next_power_of_two().max(2);
+        let mut planner = RealFftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(fft_size);
+        let mut input = fft.make_input_vec();
+        let mut spectrum = fft.make_output_vec();
+
+        // apply Hann window, the padding remains zero
+        input
+            .iter_mut()
+            .zip(data.iter())
+            .enumerate()
+            .for_each(|(i, (o, v))| {
+                let w = 0.5 - 0.5 * (2. * PI * i as f32 / length as f32).cos();
+                *o = v * w;
+            });
+
+        fft.process(&mut input, &mut spectrum).unwrap();
+
+        let magnitudes: Vec<f32> = spectrum.iter().map(|c| c.norm()).collect();
+        let (peak, _) =
+            magnitudes.iter().enumerate().fold(
+                (0, 0.),
+                |(peak, max), (i, &m)| {
+                    if m > max {
+                        (i, m)
+                    } else {
+                        (peak, max)
+                    }
+                },
+            );
+
+        // parabolic interpolation of the peak position
+        let offset = if peak > 0 && peak < magnitudes.len() - 1 {
+            let (a, b, c) = (magnitudes[peak - 1], magnitudes[peak], magnitudes[peak + 1]);
+            let denominator = a - 2. * b + c;
+            if denominator != 0. {
+                0.5 * (a - c) / denominator
+            } else {
+                0.
+            }
+        } else {
+            0.
+        };
+
+        (peak as f32 + offset) * sample_rate / fft_size as f32
+    }
+
     /// Create a multi-channel audiobuffer directly from `ChannelData`s.
     // @todo - remove in favor of `AudioBuffer::from`
     pub(crate) fn from_channels(channels: Vec<ChannelData>, sample_rate: f32) -> Self {
@@ -323,7 +434,7 @@ impl AudioBuffer {
         data.iter_mut()
             .zip(other.channels.iter())
             .for_each(|(channel, other_channel)| {
-                let cur_channel_data = Arc::make_mut(&mut channel.data);
+                let cur_channel_data = channel.make_mut();
                 cur_channel_data.extend(other_channel.as_slice());
             })
     }
@@ -338,7 +449,7 @@ impl AudioBuffer {
             .iter_mut()
             .zip(other.channels())
             .for_each(|(channel, other_channel)| {
-                let cur_channel_data = Arc::make_mut(&mut channel.data);
+                let cur_channel_data = channel.make_mut();
                 cur_channel_data.extend_from_slice(&other_channel[..]);
             })
     }
@@ -348,7 +459,7 @@ impl AudioBuffer {
         let channels: Vec<_> = self
             .channels_mut()
             .iter_mut()
-            .map(|channel_data| Arc::make_mut(&mut channel_data.data).split_off(index))
+            .map(|channel_data| channel_data.make_mut().split_off(index))
             .map(ChannelData::from)
             .collect();
 
@@ -419,8 +530,8 @@ impl AudioBuffer {
             let k_inv = 1. - k;
 
             for (channel, resampled_data) in resampled.iter_mut().enumerate() {
-                let prev_sample = self.channels[channel].data[prev_index];
-                let next_sample = self.channels[channel].data[next_index];
+                let prev_sample = self.channels[channel].as_slice()[prev_index];
+                let next_sample = self.channels[channel].as_slice()[next_index];
 
                 let value = k_inv * prev_sample + k * next_sample;
                 resampled_data.push(value);
@@ -431,7 +542,7 @@ impl AudioBuffer {
             .iter_mut()
             .zip(resampled)
             .for_each(|(channel_data, resampled_data)| {
-                channel_data.data = Arc::new(resampled_data);
+                *channel_data = ChannelData::from(resampled_data);
             });
 
         self.sample_rate = sample_rate;
@@ -440,10 +551,19 @@ impl AudioBuffer {
 
 /// Single channel audio samples, basically wraps a `Arc<Vec<f32>>`
 ///
-/// ChannelData has copy-on-write semantics, so it is cheap to clone.
-#[derive(Clone, Debug, PartialEq)]
+/// ChannelData has copy-on-write semantics, so it is cheap to clone. It may be a view on a
+/// region of the samples, which is copied to its own allocation when mutated.
+#[derive(Clone, Debug)]
 pub(crate) struct ChannelData {
     data: Arc<Vec<f32>>,
+    /// offset and length of the view, `None` for all the samples
+    view: Option<(usize, usize)>,
+}
+
+impl PartialEq for ChannelData {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
 }
 
 impl ChannelData {
@@ -451,31 +571,53 @@ impl ChannelData {
         let buffer = vec![0.; length];
         let data = Arc::new(buffer);
 
-        Self { data }
+        Self { data, view: None }
     }
 
     pub fn from(data: Vec<f32>) -> Self {
         Self {
             data: Arc::new(data),
+            view: None,
+        }
+    }
+
+    /// View on the samples in the `start..end` range, sharing their allocation
+    pub fn slice(&self, start: usize, end: usize) -> Self {
+        let offset = self.view.map_or(0, |(offset, _)| offset);
+
+        Self {
+            data: self.data.clone(),
+            view: Some((offset + start, end - start)),
         }
     }
 
     pub fn len(&self) -> usize {
-        self.data.len()
+        self.view.map_or(self.data.len(), |(_, length)| length)
     }
 
     // clippy wants to keep it, so keep it :)
     #[allow(dead_code)]
     pub fn is_empty(&self) -> bool {
-        self.data.is_empty()
+        self.len() == 0
     }
 
     pub fn as_slice(&self) -> &[f32] {
-        &self.data[..]
+        match self.view {
+            Some((offset, length)) => &self.data[offset..offset + length],
+            None => &self.data[..],
+        }
     }
 
     pub fn as_mut_slice(&mut self) -> &mut [f32] {
-        &mut Arc::make_mut(&mut self.data)[..]
+        &mut self.make_mut()[..]
+    }
+
+    /// Samples of the channel, copied if they are shared or if this is a view
+    fn make_mut(&mut self) -> &mut Vec<f32> {
+        if let Some((offset, length)) = self.view.take() {
+            self.data = Arc::new(self.data[offset..offset + length].to_vec());
+        }
+        Arc::make_mut(&mut self.data)
     }
 }
 
@@ -520,7 +662,8 @@ mod tests {
 
     #[test]
     fn test_from_encoded_concurrent() {
-        let handles = (0..5).into_iter()
+        let handles = (0..5)
+            .into_iter()
             .map(|_| {
                 std::thread::spawn(move || {
                     let file = std::fs::File::open("samples/sample.wav").unwrap();
@@ -529,22 +672,20 @@ mod tests {
                 })
             })
             .collect::<Vec<_>>();
-        
-        handles
-            .into_iter()
-            .for_each(|handle| {
-                let audio_buffer = handle.join().unwrap();
-
-                assert_eq!(audio_buffer.sample_rate(), 44100.);
-                assert_eq!(audio_buffer.length(), 142_187);
-                assert_eq!(audio_buffer.number_of_channels(), 2);
-                assert_float_eq!(audio_buffer.duration(), 3.224, abs_all <= 0.001);
-
-                let left_start = &audio_buffer.get_channel_data(0)[0..100];
-                let right_start = &audio_buffer.get_channel_data(1)[0..100];
-                // assert distinct two channel data
-                assert!(left_start != right_start);
-            });
+
+        handles.into_iter().for_each(|handle| {
+            let audio_buffer = handle.join().unwrap();
+
+            assert_eq!(audio_buffer.sample_rate(), 44100.);
+            assert_eq!(audio_buffer.length(), 142_187);
+            assert_eq!(audio_buffer.number_of_channels(), 2);
+            assert_float_eq!(audio_buffer.duration(), 3.224, abs_all <= 0.001);
+
+            let left_start = &audio_buffer.get_channel_data(0)[0..100];
+            let right_start = &audio_buffer.get_channel_data(1)[0..100];
+            // assert distinct two channel data
+            assert!(left_start != right_start);
+        });
     }
 
     #[test]
@@ -750,6 +891,110 @@ mod tests {
     }
 
     // internal API
+    fn ramp_buffer() -> AudioBuffer {
+        let left = (0..10).map(|i| i as f32).collect();
+        let right = (0..10).map(|i| -(i as f32)).collect();
+        AudioBuffer::from(vec![left, right], 48_000.)
+    }
+
+    #[test]
+    fn test_slice() {
+        let buffer = ramp_buffer();
+
+        let slice = buffer.slice(2, 5);
+        assert_eq!(slice.number_of_channels(), 2);
+        assert_eq!(slice.length(), 3);
+        assert_float_eq!(slice.sample_rate(), 48_000., abs <= 0.);
+        assert_float_eq!(slice.duration(), 3. / 48_000., abs <= 0.);
+        assert_float_eq!(slice.get_channel_data(0), &[2., 3., 4.][..], abs_all <= 0.);
+        assert_float_eq!(
+            slice.get_channel_data(1),
+            &[-2., -3., -4.][..],
+            abs_all <= 0.
+        );
+
+        let mut dest = [0.; 5];
+        slice.copy_from_channel_with_offset(&mut dest, 0, 1);
+        assert_float_eq!(dest, [3., 4., 0., 0., 0.], abs_all <= 0.);
+
+        // boundaries
+        let full = buffer.slice(0, 10);
+        assert_float_eq!(
+            full.get_channel_data(0),
+            buffer.get_channel_data(0),
+            abs_all <= 0.
+        );
+        assert_eq!(buffer.slice(0, 0).length(), 0);
+        assert_eq!(buffer.slice(10, 10).length(), 0);
+        assert_float_eq!(
+            buffer.slice(9, 10).get_channel_data(1),
+            &[-9.][..],
+            abs_all <= 0.
+        );
+
+        // slice of a slice
+        let nested = buffer.slice(4, 9).slice(1, 3);
+        assert_float_eq!(nested.get_channel_data(0), &[5., 6.][..], abs_all <= 0.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_slice_end() {
+        ramp_buffer().slice(2, 11);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_slice_range() {
+        ramp_buffer().slice(5, 4);
+    }
+
+    #[test]
+    fn test_slice_shares_samples() {
+        let buffer = ramp_buffer();
+        let slice = buffer.slice(2, 5).slice(1, 2);
+
+        for (channel, sliced) in buffer.channels().iter().zip(slice.channels()) {
+            assert!(Arc::ptr_eq(&channel.data, &sliced.data));
+        }
+    }
+
+    #[test]
+    fn test_slice_copy_on_write() {
+        let mut buffer = ramp_buffer();
+        let mut slice = buffer.slice(2, 5);
+
+        slice.copy_to_channel(&[10., 11.], 0);
+        assert_float_eq!(
+            slice.get_channel_data(0),
+            &[10., 11., 4.][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            buffer.get_channel_data(0)[2..5],
+            [2., 3., 4.],
+            abs_all <= 0.
+        );
+        // the untouched channel is still shared
+        assert!(!Arc::ptr_eq(
+            &buffer.channels()[0].data,
+            &slice.channels()[0].data
+        ));
+        assert!(Arc::ptr_eq(
+            &buffer.channels()[1].data,
+            &slice.channels()[1].data
+        ));
+
+        // mutating the parent does not affect the slice either
+        buffer.copy_to_channel_with_offset(&[20.], 1, 2);
+        assert_float_eq!(buffer.get_channel_data(1)[2], 20., abs <= 0.);
+        assert_float_eq!(
+            slice.get_channel_data(1),
+            &[-2., -3., -4.][..],
+            abs_all <= 0.
+        );
+    }
+
     #[test]
     fn test_silent() {
         let options = AudioBufferOptions {
@@ -901,4 +1146,20 @@ mod tests {
             assert_float_eq!(buffer.sample_rate, target_sr as f32, abs_all <= 0.);
         });
     }
+
+    #[test]
+    fn test_peak_frequency() {
+        let sample_rate = 48_000.;
+        let frequency = 1_234.5;
+
+        let data: Vec<f32> = (0..sample_rate as usize / 2)
+            .map(|i| (2. * PI * frequency * i as f32 / sample_rate).sin())
+            .collect();
+        let buffer = AudioBuffer::from(vec![data], sample_rate);
+
+        assert_float_eq!(buffer.peak_frequency(0, sample_rate), frequency, abs <= 1.);
+
+        let empty = AudioBuffer::from(vec![vec![]], sample_rate);
+        assert_float_eq!(empty.peak_frequency(0, sample_rate), 0., abs <= 0.);
+    }
 }
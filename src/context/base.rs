@@ -1,16 +1,22 @@
 //! The `BaseAudioContext` interface
 
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
 use crate::buffer::{AudioBuffer, AudioBufferOptions};
 use crate::context::{
     AudioContextRegistration, AudioContextState, AudioNodeId, AudioParamId,
-    ConcreteBaseAudioContext, DESTINATION_NODE_ID,
+    ConcreteBaseAudioContext, Shutdown, Transaction,
 };
-use crate::media::MediaDecoder;
+use crate::media::{DecodeError, MediaDecoder};
 use crate::node::{AudioNode, ChannelConfigOptions};
 use crate::param::AudioParamDescriptor;
 use crate::periodic_wave::{PeriodicWave, PeriodicWaveOptions};
-use crate::render::AudioProcessor;
-use crate::{node, AudioListener};
+use crate::render::{AudioBufferSnapshot, AudioProcessor};
+use crate::{node, AudioListener, RENDER_QUANTUM_SIZE};
 
 /// The interface representing an audio-processing graph built from audio modules linked together,
 /// each represented by an `AudioNode`.
@@ -25,6 +31,34 @@ pub trait BaseAudioContext {
     /// Construct a new pair of [`AudioNode`] and [`AudioProcessor`]
     ///
     /// The `AudioNode` lives in the user-facing control thread. The Processor is sent to the render thread.
+    ///
+    /// This is the entry point of the nodes implemented outside of this crate, see the
+    /// `worklet` and `ping_pong_delay` examples. The [`AudioNode`] and [`AudioProcessor`] traits
+    /// are meant to be implemented for them, the context traits are not.
+    ///
+    /// # Lifecycle
+    ///
+    /// - The closure is called once, with a new [`AudioContextRegistration`], and returns exactly
+    ///   one processor for the node. The processor is added to the audio graph when the closure
+    ///   returns.
+    /// - The node owns the registration: [`AudioNode::registration`] returns it, and it cannot
+    ///   be cloned. Dropping the node drops the registration, upon which the processor is
+    ///   released once it has no inputs connected and [`AudioProcessor::process`] returns
+    ///   `false`. The processor is never released while the registration is alive, unless the
+    ///   context is closed.
+    /// - The params created with [`create_audio_param`](Self::create_audio_param) for the
+    ///   registration, inside the closure, are owned by the node: they are connected to its
+    ///   processor, and released along with it. Their [`AudioParamId`] must be moved into the
+    ///   processor, it is the only way to read their values.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, this function panics on misuse of the contract:
+    /// - an `AudioParamId` of the node is dropped by the closure instead of being moved into
+    ///   the processor
+    /// - the registration is dropped by the closure, which would release the processor of a
+    ///   node still being built
+    /// - a param is created for the registration outside of the closure
     fn register<
         T: AudioNode,
         F: FnOnce(AudioContextRegistration) -> (T, Box<dyn AudioProcessor>),
@@ -47,11 +81,13 @@ pub trait BaseAudioContext {
     /// should not wrap the source in a `BufReader`.
     ///
     /// This function operates synchronously, which may be undesirable on the control thread. The
-    /// example shows how to avoid this. An async version is currently not implemented.
+    /// example shows how to avoid this, or use the async
+    /// [`decode_audio_data`](Self::decode_audio_data) instead.
     ///
     /// # Errors
     ///
-    /// This method returns an Error in various cases (IO, mime sniffing, decoding).
+    /// This method returns a [`DecodeError`] telling apart an empty input, an unsupported
+    /// format, a corrupt or truncated input, and an IO error.
     ///
     /// # Example
     ///
@@ -72,22 +108,99 @@ pub trait BaseAudioContext {
     fn decode_audio_data_sync<R: std::io::Read + Send + Sync + 'static>(
         &self,
         input: R,
-    ) -> Result<AudioBuffer, Box<dyn std::error::Error + Send + Sync>> {
-        // Set up a media decoder, consume the stream in full and construct a single buffer out of it
-        let mut buffer = MediaDecoder::try_new(input)?
-            .collect::<Result<Vec<_>, _>>()?
-            .into_iter()
-            .reduce(|mut accum, item| {
-                accum.extend(&item);
-                accum
-            })
-            // if there are no samples decoded, return an empty buffer
-            .unwrap_or_else(|| AudioBuffer::from(vec![vec![]], self.sample_rate()));
+    ) -> Result<AudioBuffer, DecodeError> {
+        decode_to_buffer(input, self.sample_rate(), self.base().shutdown())
+    }
 
-        // resample to desired rate (no-op if already matching)
-        buffer.resample(self.sample_rate());
+    /// Decode an [`AudioBuffer`] from a given input stream, asynchronously.
+    ///
+    /// The decoding takes place on a separate thread, the returned future resolves when it is
+    /// done. It can be awaited by any executor. See
+    /// [`decode_audio_data_sync`](Self::decode_audio_data_sync) for the supported formats and
+    /// the possible errors.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::io::Cursor;
+    /// use web_audio_api::context::{BaseAudioContext, OfflineAudioContext};
+    ///
+    /// let input = Cursor::new(vec![0; 32]); // or a File, TcpStream, ...
+    ///
+    /// let context = OfflineAudioContext::new(2, 44_100, 44_100.);
+    /// let decode_future = context.decode_audio_data(input);
+    ///
+    /// // pass `decode_future` to your async runtime, or `.await` it inside an async fn
+    /// ```
+    fn decode_audio_data<R: std::io::Read + Send + Sync + 'static>(
+        &self,
+        input: R,
+    ) -> DecodeAudioDataFuture {
+        let sample_rate = self.sample_rate();
+        let shutdown = Arc::clone(self.base().shutdown());
+        DecodeAudioDataFuture::spawn(self.base().shutdown(), move || {
+            decode_to_buffer(input, sample_rate, &shutdown)
+        })
+    }
 
-        Ok(buffer)
+    /// Decode an [`AudioBuffer`] from a given input stream, asynchronously, with progress
+    /// reporting and cancellation.
+    ///
+    /// The decoding takes place on a separate thread, which calls `on_progress` with the
+    /// fraction (between 0 and 1) of the input that is decoded. Progress is derived from the
+    /// number of frames announced by the container. When that number is unknown, progress is
+    /// only reported on completion.
+    ///
+    /// The returned [`DecodeHandle`] is a future resolving to the decoded buffer. Calling
+    /// [`DecodeHandle::cancel`] stops the decoding before the next packet, the future then
+    /// resolves to a [`DecodeError::Cancelled`] error.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use web_audio_api::context::{BaseAudioContext, OfflineAudioContext};
+    ///
+    /// let file = std::fs::File::open("samples/sample.wav").unwrap();
+    ///
+    /// let context = OfflineAudioContext::new(2, 44_100, 44_100.);
+    /// let handle = context.decode_audio_data_with_progress(file, |progress| {
+    ///     println!("decoded {:.0}%", progress * 100.);
+    /// });
+    ///
+    /// // the user navigated away
+    /// handle.cancel();
+    /// ```
+    fn decode_audio_data_with_progress<
+        R: std::io::Read + Send + Sync + 'static,
+        F: FnMut(f64) + Send + 'static,
+    >(
+        &self,
+        input: R,
+        mut on_progress: F,
+    ) -> DecodeHandle {
+        let sample_rate = self.sample_rate();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let thread_cancelled = Arc::clone(&cancelled);
+        let shutdown = Arc::clone(self.base().shutdown());
+
+        let future = DecodeAudioDataFuture::spawn(self.base().shutdown(), move || {
+            let abort = || {
+                if thread_cancelled.load(Ordering::SeqCst) {
+                    Some(DecodeError::Cancelled)
+                } else {
+                    closed_error(&shutdown)
+                }
+            };
+            // the progress is not reported anymore once the context is closed
+            let mut progress = |value| {
+                if !shutdown.is_closed() {
+                    on_progress(value);
+                }
+            };
+            decode_to_buffer_with_progress(input, sample_rate, &abort, &mut progress)
+        });
+
+        DecodeHandle { future, cancelled }
     }
 
     /// Create an new "in-memory" `AudioBuffer` with the given number of channels,
@@ -129,6 +242,47 @@ pub trait BaseAudioContext {
         node::AudioBufferSourceNode::new(self.base(), node::AudioBufferSourceOptions::default())
     }
 
+    /// Plays the given `AudioBuffer` through the context destination, starting at `when`
+    ///
+    /// This is a shorthand for creating an `AudioBufferSourceNode`, setting its buffer,
+    /// connecting it to the destination and starting it. The returned node can be kept around to
+    /// control the playback, or dropped to let it play to the end.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use web_audio_api::context::{AudioContext, BaseAudioContext};
+    ///
+    /// let context = AudioContext::default();
+    ///
+    /// let file = std::fs::File::open("samples/sample.wav").unwrap();
+    /// let buffer = context.decode_audio_data_sync(file).unwrap();
+    ///
+    /// // play the sample one second from now
+    /// let _src = context.play_audio_buffer(buffer, context.current_time() + 1.);
+    /// ```
+    fn play_audio_buffer(&self, buffer: AudioBuffer, when: f64) -> node::AudioBufferSourceNode {
+        self.play_audio_buffer_with_offset(buffer, when, 0.)
+    }
+
+    /// Plays the given `AudioBuffer` through the context destination, starting at `when`, from
+    /// `offset` seconds into the buffer
+    ///
+    /// See [`play_audio_buffer`](Self::play_audio_buffer)
+    fn play_audio_buffer_with_offset(
+        &self,
+        buffer: AudioBuffer,
+        when: f64,
+        offset: f64,
+    ) -> node::AudioBufferSourceNode {
+        let src = self.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&self.destination());
+        src.start_at_with_offset(when, offset);
+
+        src
+    }
+
     /// Creates an `ConstantSourceNode`, a source representing a constant value
     #[must_use]
     fn create_constant_source(&self) -> node::ConstantSourceNode {
@@ -155,6 +309,27 @@ pub trait BaseAudioContext {
         node::ChannelSplitterNode::new(self.base(), opts)
     }
 
+    /// Creates a `ConvolutionChainNode`, convolving its input with the given impulse responses
+    /// in series
+    ///
+    /// # Panics
+    ///
+    /// Panics when `ir_buffers` is empty or contains an invalid impulse response
+    #[must_use]
+    fn create_convolution_chain(&self, ir_buffers: Vec<AudioBuffer>) -> node::ConvolutionChainNode {
+        let opts = node::ConvolutionChainOptions {
+            ir_buffers,
+            ..node::ConvolutionChainOptions::default()
+        };
+        node::ConvolutionChainNode::new(self.base(), opts)
+    }
+
+    /// Creates a `ConvolverNode`, a processing node which applies linear convolution
+    #[must_use]
+    fn create_convolver(&self) -> node::ConvolverNode {
+        node::ConvolverNode::new(self.base(), node::ConvolverOptions::default())
+    }
+
     /// Creates a `DelayNode`, delaying the audio signal
     #[must_use]
     fn create_delay(&self, max_delay_time: f64) -> node::DelayNode {
@@ -165,6 +340,24 @@ pub trait BaseAudioContext {
         node::DelayNode::new(self.base(), opts)
     }
 
+    /// Creates a `DynamicsCompressorNode`, lowering the volume of the loudest parts of its input
+    #[must_use]
+    fn create_dynamics_compressor(&self) -> node::DynamicsCompressorNode {
+        node::DynamicsCompressorNode::new(self.base(), node::DynamicsCompressorOptions::default())
+    }
+
+    /// Creates an `EnvelopeFollowerNode`, outputting the envelope of its input as a control signal
+    #[must_use]
+    fn create_envelope_follower(&self) -> node::EnvelopeFollowerNode {
+        node::EnvelopeFollowerNode::new(self.base(), node::EnvelopeFollowerOptions::default())
+    }
+
+    /// Creates an `Eq3Node`, a three band equalizer with bass, mid and treble controls
+    #[must_use]
+    fn create_eq3(&self) -> node::Eq3Node {
+        node::Eq3Node::new(self.base(), node::Eq3Options::default())
+    }
+
     /// Creates an `GainNode`, to control audio volume
     #[must_use]
     fn create_gain(&self) -> node::GainNode {
@@ -189,6 +382,33 @@ pub trait BaseAudioContext {
         node::IIRFilterNode::new(self.base(), options)
     }
 
+    /// Creates a `LoudnessMeterNode`, measuring the loudness of its input as defined in EBU R128
+    #[must_use]
+    fn create_loudness_meter(&self) -> node::LoudnessMeterNode {
+        node::LoudnessMeterNode::new(self.base(), node::LoudnessMeterOptions::default())
+    }
+
+    /// Creates a `MorphOscillatorNode`, an oscillator morphing between two periodic waves
+    #[must_use]
+    fn create_morph_oscillator(
+        &self,
+        wave_a: PeriodicWave,
+        wave_b: PeriodicWave,
+    ) -> node::MorphOscillatorNode {
+        let options = node::MorphOscillatorOptions {
+            wave_a: Some(wave_a),
+            wave_b: Some(wave_b),
+            ..node::MorphOscillatorOptions::default()
+        };
+        node::MorphOscillatorNode::new(self.base(), options)
+    }
+
+    /// Creates a `NoiseGateNode`, attenuating its input when the level is below a threshold
+    #[must_use]
+    fn create_noise_gate(&self) -> node::NoiseGateNode {
+        node::NoiseGateNode::new(self.base(), node::NoiseGateOptions::default())
+    }
+
     /// Creates an `OscillatorNode`, a source representing a periodic waveform.
     #[must_use]
     fn create_oscillator(&self) -> node::OscillatorNode {
@@ -210,12 +430,35 @@ pub trait BaseAudioContext {
         PeriodicWave::new(self.base(), options)
     }
 
+    /// Creates a `PluckedStringNode`, a plucked string physical model
+    #[must_use]
+    fn create_plucked_string(&self) -> node::PluckedStringNode {
+        node::PluckedStringNode::new(self.base(), node::PluckedStringOptions::default())
+    }
+
     /// Creates an `StereoPannerNode` to pan a stereo output
     #[must_use]
     fn create_stereo_panner(&self) -> node::StereoPannerNode {
         node::StereoPannerNode::new(self.base(), node::StereoPannerOptions::default())
     }
 
+    /// Creates a `TestSignalNode`, a source of precise signals for audio measurements
+    ///
+    /// The `frequency_hz` argument only applies to the periodic signal types.
+    #[must_use]
+    fn create_test_signal(
+        &self,
+        signal_type: node::TestSignalType,
+        frequency_hz: f32,
+    ) -> node::TestSignalNode {
+        let options = node::TestSignalOptions {
+            signal_type,
+            frequency: frequency_hz,
+            ..node::TestSignalOptions::default()
+        };
+        node::TestSignalNode::new(self.base(), options)
+    }
+
     /// Creates a `WaveShaperNode`
     #[must_use]
     fn create_wave_shaper(&self) -> node::WaveShaperNode {
@@ -227,7 +470,7 @@ pub trait BaseAudioContext {
     #[must_use]
     fn destination(&self) -> node::AudioDestinationNode {
         let registration = AudioContextRegistration {
-            id: AudioNodeId(DESTINATION_NODE_ID),
+            id: self.base().destination_id(),
             context: self.base().clone(),
         };
         let channel_config = self.base().destination_channel_config();
@@ -254,15 +497,201 @@ pub trait BaseAudioContext {
 
     /// This is the time in seconds of the sample frame immediately following the last sample-frame
     /// in the block of audio most recently processed by the context’s rendering graph.
+    ///
+    /// The time follows the rendered frames, not the frames consumed by the output. An
+    /// `AudioContext` renders a few quanta ahead of the output callbacks, see
+    /// [`AudioContext::base_latency`](crate::context::AudioContext::base_latency).
     #[must_use]
     fn current_time(&self) -> f64 {
         self.base().current_time()
     }
 
-    /// Create an `AudioParam`.
+    /// Estimate of the current time with a resolution finer than a render quantum, e.g. to draw a
+    /// playhead or to run a lookahead scheduler
     ///
-    /// Call this inside the `register` closure when setting up your `AudioNode`
+    /// [`current_time`](Self::current_time) only advances in steps of
+    /// [`RENDER_QUANTUM_SIZE`](crate::RENDER_QUANTUM_SIZE) frames, once per render quantum. For an
+    /// `AudioContext`, this method adds the wall clock time elapsed since the last output
+    /// callback, without going past the next render quantum boundary nor going backwards. Other
+    /// contexts return `current_time`.
+    ///
+    /// This is an estimate for the control thread only: scheduled events (`start`, `stop`,
+    /// `AudioParam` automation) are still applied on the frames of the render quanta.
     #[must_use]
+    fn current_time_interpolated(&self) -> f64 {
+        self.current_time()
+    }
+
+    /// Convert a number of sample-frames to a duration in seconds, at the context sample rate
+    #[must_use]
+    fn frames_to_seconds(&self, frames: u64) -> f64 {
+        frames as f64 / self.sample_rate() as f64
+    }
+
+    /// Convert a duration in seconds to the nearest number of sample-frames, at the context
+    /// sample rate
+    ///
+    /// Negative durations are converted to zero frames.
+    #[must_use]
+    fn seconds_to_frames(&self, seconds: f64) -> u64 {
+        (seconds * self.sample_rate() as f64).round() as u64
+    }
+
+    /// Convert a number of render quanta to a duration in seconds, at the context sample rate
+    #[must_use]
+    fn quanta_to_seconds(&self, quanta: u64) -> f64 {
+        self.frames_to_seconds(quanta * RENDER_QUANTUM_SIZE as u64)
+    }
+
+    /// Convert a duration in seconds to the nearest number of render quanta, at the context
+    /// sample rate
+    ///
+    /// Negative durations are converted to zero quanta.
+    #[must_use]
+    fn seconds_to_quanta(&self, seconds: f64) -> u64 {
+        (seconds * self.sample_rate() as f64 / RENDER_QUANTUM_SIZE as f64).round() as u64
+    }
+
+    /// Number of scheduled sources (`AudioScheduledSourceNode`s) that are currently producing
+    /// audio, as opposed to merely being registered or waiting for their start time
+    ///
+    /// This value is maintained by the render thread and can be used for voice management.
+    #[must_use]
+    fn active_source_count(&self) -> usize {
+        self.base().active_sources().count()
+    }
+
+    /// Maximum number of simultaneously active scheduled sources since the creation of the context,
+    /// or since the last call to [`reset_max_active_source_count`](Self::reset_max_active_source_count)
+    #[must_use]
+    fn max_active_source_count(&self) -> usize {
+        self.base().active_sources().high_water_mark()
+    }
+
+    /// Reset the [`max_active_source_count`](Self::max_active_source_count) to the current number
+    /// of active sources
+    fn reset_max_active_source_count(&self) {
+        self.base().active_sources().reset_high_water_mark();
+    }
+
+    /// Copy of the last render quantum of the `AudioDestinationNode`, e.g. to display an
+    /// output scope or meter without adding nodes to the graph
+    ///
+    /// The render thread only publishes its output after a call to
+    /// [`subscribe_output_frames`](Self::subscribe_output_frames). Returns `None` when there
+    /// is no subscription, or when no quantum has been rendered since. Use
+    /// [`AudioBufferSnapshot::frame`] to detect missed or duplicate quanta between two calls.
+    #[must_use]
+    fn last_output_frame(&self) -> Option<AudioBufferSnapshot> {
+        self.base().output_snapshots().read()
+    }
+
+    /// Let the render thread publish its output for [`last_output_frame`](Self::last_output_frame)
+    ///
+    /// The output is copied once per render quantum as long as there is at least one
+    /// subscription.
+    fn subscribe_output_frames(&self) {
+        self.base().output_snapshots().subscribe();
+    }
+
+    /// Cancel a call to [`subscribe_output_frames`](Self::subscribe_output_frames)
+    fn unsubscribe_output_frames(&self) {
+        self.base().output_snapshots().unsubscribe();
+    }
+
+    /// Mute or unmute the output of the context, without clicks
+    ///
+    /// Ramps the output of the `AudioDestinationNode`, see
+    /// [`AudioNode::set_muted`](crate::node::AudioNode::set_muted).
+    fn set_muted(&self, muted: bool) {
+        self.destination().set_muted(muted);
+    }
+
+    /// Mute or unmute the output of the context, with a gain ramp of the given duration in
+    /// seconds
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ramp duration is negative or not finite
+    fn set_muted_with_ramp(&self, muted: bool, ramp_duration: f64) {
+        self.destination().set_muted_with_ramp(muted, ramp_duration);
+    }
+
+    /// Render the given nodes, connected in series, as a single step
+    ///
+    /// Meant for effect chains that do not change after their construction: the nodes are
+    /// rendered back to back and their buffers are handed over directly, saving the overhead of
+    /// the separate nodes. The output is identical.
+    ///
+    /// Each node must have a single input and output, and all but the last one must only be
+    /// connected to the next one, which has no other incoming connection. Otherwise the call is
+    /// ignored and a warning is logged. The chain is split again automatically when one of its
+    /// connections or channel configs changes.
+    fn freeze_chain(&self, nodes: &[&dyn AudioNode]) {
+        let ids: Vec<_> = nodes.iter().map(|node| node.registration().id()).collect();
+        self.base().freeze_chain(&ids);
+    }
+
+    /// Build a part of the audio graph as a whole
+    ///
+    /// The nodes created and connected through the [`Transaction`] passed to `f` reach the
+    /// render thread at once, when `f` returns `Ok`. When it returns `Err` or panics, they are
+    /// discarded and never reach the audio graph, see [`Transaction`] for the changes made to
+    /// the other nodes. The nodes returned by `f` can be used as usual afterwards.
+    ///
+    /// ```
+    /// use web_audio_api::context::{BaseAudioContext, OfflineAudioContext};
+    /// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+    ///
+    /// let context = OfflineAudioContext::new(1, 128, 44_100.);
+    ///
+    /// let result: Result<_, String> = context.transaction(|txn| {
+    ///     let osc = txn.create_oscillator();
+    ///     let gain = txn.create_gain();
+    ///     osc.connect(&gain);
+    ///     gain.connect(&txn.destination());
+    ///     osc.start();
+    ///     Ok((osc, gain))
+    /// });
+    /// let (osc, gain) = result.unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the error returned by `f`
+    ///
+    /// # Panics
+    ///
+    /// Panics if a transaction is already open on the calling thread. Changing a node created
+    /// by a transaction that returned `Err` or panicked panics with an `InvalidStateError`.
+    fn transaction<T, E, F: FnOnce(&Transaction<'_>) -> Result<T, E>>(&self, f: F) -> Result<T, E> {
+        let txn = Transaction::begin(self.base());
+        let result = f(&txn);
+        if result.is_ok() {
+            txn.commit();
+        }
+        result
+    }
+
+    /// Retrieve a read-only mirror of a live node, given its id
+    ///
+    /// Returns `None` if there is no such node, or when all handles to the node were dropped.
+    #[must_use]
+    fn node_by_id(&self, id: AudioNodeId) -> Option<Box<dyn node::AudioNodeMirror>> {
+        self.base().node_mirror(id)
+    }
+
+    /// Create an `AudioParam`.
+    ///
+    /// Call this inside the `register` closure when setting up your `AudioNode`. The
+    /// `AudioParam` is stored in the node, the [`AudioParamId`] is moved into the processor to
+    /// read the values of the param, see the lifecycle contract of
+    /// [`register`](Self::register).
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, this function panics when called outside of the `register` closure of
+    /// the node owning `dest`
     fn create_audio_param(
         &self,
         opts: AudioParamDescriptor,
@@ -274,18 +703,234 @@ pub trait BaseAudioContext {
             (node, Box::new(proc))
         });
 
-        // Connect the param to the node, once the node is registered inside the audio graph.
-        self.base().queue_audio_param_connect(&param, dest.id());
+        // Route the automation events of the param and connect it to the node, once the node
+        // is registered inside the audio graph.
+        self.base().register_audio_param_events(&param);
+        self.base()
+            .queue_audio_param_connect(param.registration().id(), dest.id());
+        self.base().mirror_audio_param(&param, dest.id());
 
-        let proc_id = AudioParamId(param.registration().id().0);
+        let proc_id = self.base().audio_param_id(param.registration().id(), dest);
         (param, proc_id)
     }
 
+    /// Create a [`ControlInput`](crate::ControlInput), a control signal keeping its channels
+    ///
+    /// Call this inside the `register` closure when setting up your `AudioNode`. The values are
+    /// read with [`AudioParamValues::get_multichannel`](crate::render::AudioParamValues::get_multichannel).
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `number_of_channels` is zero or greater than 32
+    fn create_control_input(
+        &self,
+        number_of_channels: usize,
+        dest: &AudioContextRegistration,
+    ) -> (crate::ControlInput, AudioParamId) {
+        crate::assert_valid_number_of_channels(number_of_channels);
+
+        let input = self.register(move |registration| {
+            let (node, proc) = crate::param::control_input_pair(number_of_channels, registration);
+
+            (node, Box::new(proc))
+        });
+
+        // connect it to the node once the node is registered inside the audio graph
+        self.base()
+            .queue_audio_param_connect(input.registration().id(), dest.id());
+
+        let proc_id = self.base().audio_param_id(input.registration().id(), dest);
+        (input, proc_id)
+    }
+
     #[cfg(test)]
     fn mock_registration(&self) -> AudioContextRegistration {
         AudioContextRegistration {
-            id: AudioNodeId(0),
+            id: super::MOCK_NODE_ID,
             context: self.base().clone(),
         }
     }
 }
+
+/// Decode the input stream and resample the result to the given sample rate
+fn decode_to_buffer<R: std::io::Read + Send + Sync + 'static>(
+    input: R,
+    sample_rate: f32,
+    shutdown: &Shutdown,
+) -> Result<AudioBuffer, DecodeError> {
+    let abort = || closed_error(shutdown);
+    decode_to_buffer_with_progress(input, sample_rate, &abort, &mut |_| {})
+}
+
+/// Error stopping the decoding once the context is closed
+fn closed_error(shutdown: &Shutdown) -> Option<DecodeError> {
+    if shutdown.is_closed() {
+        Some(DecodeError::Closed)
+    } else {
+        None
+    }
+}
+
+/// Decode the input stream, reporting progress and checking for cancellation between packets,
+/// and resample the result to the given sample rate
+///
+/// The decoding stops with the error returned by `abort`, if any.
+fn decode_to_buffer_with_progress<R: std::io::Read + Send + Sync + 'static>(
+    input: R,
+    sample_rate: f32,
+    abort: &dyn Fn() -> Option<DecodeError>,
+    on_progress: &mut dyn FnMut(f64),
+) -> Result<AudioBuffer, DecodeError> {
+    let decoded = decode_native_with_progress(input, abort, on_progress)?;
+
+    // if there are no samples decoded, return an empty buffer
+    let mut buffer = decoded.unwrap_or_else(|| AudioBuffer::from(vec![vec![]], sample_rate));
+
+    // resample to desired rate (no-op if already matching)
+    buffer.resample(sample_rate);
+
+    Ok(buffer)
+}
+
+/// Decode the input stream in full, at the sample rate of the media
+///
+/// Returns `None` if the stream does not contain any sample.
+pub(crate) fn decode_native<R: std::io::Read + Send + Sync + 'static>(
+    input: R,
+) -> Result<Option<AudioBuffer>, DecodeError> {
+    decode_native_with_progress(input, &|| None, &mut |_| {})
+}
+
+fn decode_native_with_progress<R: std::io::Read + Send + Sync + 'static>(
+    input: R,
+    abort: &dyn Fn() -> Option<DecodeError>,
+    on_progress: &mut dyn FnMut(f64),
+) -> Result<Option<AudioBuffer>, DecodeError> {
+    // Set up a media decoder, consume the stream in full and construct a single buffer out of it
+    let mut decoder = MediaDecoder::try_new(input)?;
+    let number_of_frames = decoder.number_of_frames();
+
+    let mut decoded: Option<AudioBuffer> = None;
+    let mut decoded_frames = 0;
+
+    loop {
+        if let Some(error) = abort() {
+            return Err(error);
+        }
+
+        let item = match decoder.next_buffer() {
+            Some(item) => item?,
+            None => break,
+        };
+
+        decoded_frames += item.length() as u64;
+        if let Some(total) = number_of_frames.filter(|&total| total > 0) {
+            on_progress((decoded_frames as f64 / total as f64).min(1.));
+        }
+
+        match decoded.as_mut() {
+            Some(accum) => accum.extend(&item),
+            None => decoded = Some(item),
+        }
+    }
+
+    on_progress(1.);
+
+    Ok(decoded)
+}
+
+type DecodeResult = Result<AudioBuffer, DecodeError>;
+
+/// Shared state between the [`DecodeAudioDataFuture`] and its decoder thread
+#[derive(Default)]
+pub(super) struct DecodeState {
+    result: Option<DecodeResult>,
+    waker: Option<Waker>,
+    /// set by the first result, the decoder thread or the closing of the context
+    resolved: bool,
+}
+
+impl DecodeState {
+    /// Store the result and wake the future, only the first result is kept
+    pub(super) fn resolve(&mut self, result: DecodeResult) {
+        if self.resolved {
+            return;
+        }
+
+        self.resolved = true;
+        self.result = Some(result);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Future returned by [`BaseAudioContext::decode_audio_data`], resolving to the decoded
+/// [`AudioBuffer`]
+pub struct DecodeAudioDataFuture {
+    state: Arc<Mutex<DecodeState>>,
+}
+
+impl DecodeAudioDataFuture {
+    /// Run the decoding function on a new thread
+    ///
+    /// The future resolves with a [`DecodeError::Closed`] error as soon as the context is
+    /// closed, the result of the decoder thread is discarded then.
+    fn spawn<F: FnOnce() -> DecodeResult + Send + 'static>(shutdown: &Shutdown, decode: F) -> Self {
+        let state = Arc::new(Mutex::new(DecodeState::default()));
+        if !shutdown.track_decode(&state) {
+            state.lock().unwrap().resolve(Err(DecodeError::Closed));
+            return Self { state };
+        }
+
+        let thread_state = Arc::clone(&state);
+        std::thread::spawn(move || {
+            let result = decode();
+            thread_state.lock().unwrap().resolve(result);
+        });
+
+        Self { state }
+    }
+}
+
+impl Future for DecodeAudioDataFuture {
+    type Output = DecodeResult;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Handle to the decoding started by
+/// [`BaseAudioContext::decode_audio_data_with_progress`]
+///
+/// The handle is a future resolving to the decoded [`AudioBuffer`].
+pub struct DecodeHandle {
+    future: DecodeAudioDataFuture,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl DecodeHandle {
+    /// Stop the decoding, the handle will resolve to a [`DecodeError::Cancelled`] error
+    ///
+    /// Cancellation is checked between the decoding of two packets. It has no effect when the
+    /// decoding has already completed.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Future for DecodeHandle {
+    type Output = DecodeResult;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.future).poll(cx)
+    }
+}
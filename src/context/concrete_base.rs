@@ -1,20 +1,31 @@
 //! The `ConcreteBaseAudioContext` type
 
 use crate::context::{
-    AudioContextRegistration, AudioContextState, AudioNodeId, BaseAudioContext,
-    DESTINATION_NODE_ID, LISTENER_NODE_ID, LISTENER_PARAM_IDS,
+    AudioContextRegistration, AudioContextState, AudioNodeId, AudioParamId, BaseAudioContext,
+    BuiltinNode, BuiltinNodeIds, DecodeState,
 };
+use crate::control::{ActiveSourceCounter, EndedEvents, Scheduler};
+use crate::media::DecodeError;
 use crate::message::ControlMessage;
-use crate::node::{AudioDestinationNode, AudioNode, ChannelConfig, ChannelConfigOptions};
-use crate::param::{AudioParam, AudioParamEvent};
-use crate::render::AudioProcessor;
+use crate::node::{
+    AudioDestinationNode, AudioNode, AudioNodeMirror, ChannelConfig, ChannelConfigOptions,
+    ChannelConfigQueue, NodeMirror,
+};
+use crate::param::{
+    AudioParam, AudioParamDescriptor, AudioParamEvent, AudioParamMirror, AudioParamRaw,
+};
+use crate::render::{
+    snapshot_buffer, AudioProcessor, EdgeKind, OutputTap, OverloadPolicy, SnapshotBuffer,
+};
 use crate::spatial::AudioListenerParams;
 
 use crate::AudioListener;
 
-use crossbeam_channel::Sender;
-use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
-use std::sync::{Arc, Mutex};
+use crossbeam_channel::{SendError, Sender};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::thread::{self, ThreadId};
 
 /// The struct that corresponds to the Javascript `BaseAudioContext` object.
 ///
@@ -54,16 +65,180 @@ struct ConcreteBaseAudioContextInner {
     render_channel: Sender<ControlMessage>,
     /// control messages that cannot be sent immediately
     queued_messages: Mutex<Vec<ControlMessage>>,
-    /// number of frames played
+    /// control messages staged by the open transactions, by thread
+    staged_messages: Mutex<HashMap<ThreadId, Vec<ControlMessage>>>,
+    /// nodes registered by a rolled-back transaction, until they are dropped
+    discarded_nodes: Mutex<HashSet<u64>>,
+    /// number of frames played, advanced by the render thread with `Release` ordering after it
+    /// applied the control messages
     frames_played: Arc<AtomicU64>,
     /// control msg to add the AudioListener, to be sent when the first panner is created
     queued_audio_listener_msgs: Mutex<Vec<ControlMessage>>,
-    /// AudioListener fields
-    listener_params: Option<AudioListenerParams>,
+    /// AudioListener fields, the listener is only created on first use
+    listener_params: Mutex<Option<AudioListenerParams>>,
+    /// master gain AudioParam of the destination node
+    master_gain: Option<AudioParamRaw>,
     /// Denotes if this AudioContext is offline or not
     offline: bool,
     /// Describes the current state of the `ConcreteBaseAudioContext`
-    state: AtomicU8,
+    state: Arc<AtomicU8>,
+    /// Number of scheduled sources currently producing audio
+    active_sources: ActiveSourceCounter,
+    /// ids assigned to the nodes created by the context itself
+    builtin_ids: Mutex<BuiltinNodeIds>,
+    /// read-only mirrors of the live nodes
+    node_mirrors: Mutex<NodeMirrors>,
+    /// last rendered quantum of the destination node
+    output_snapshots: Arc<SnapshotBuffer>,
+    /// channel configs changed since the last control message
+    channel_config_queue: Arc<ChannelConfigQueue>,
+    /// closing of the context, observed by the threads working on its behalf
+    shutdown: Arc<Shutdown>,
+    /// `ended` events of the scheduled sources, run by a single dispatcher
+    ended_events: EndedEvents,
+    /// misuse checks of the `register` closures
+    #[cfg(debug_assertions)]
+    registration_checks: RegistrationChecks,
+}
+
+impl Drop for ConcreteBaseAudioContextInner {
+    fn drop(&mut self) {
+        // the last handle is gone, nothing can observe the context anymore
+        self.shutdown.close();
+    }
+}
+
+/// Closing of a context, shared with the threads working on its behalf: the callbacks of the
+/// `ended` and `xrun` events, and the decoders
+///
+/// See [`ConcreteBaseAudioContext::close`] for the teardown order.
+#[derive(Default)]
+pub(crate) struct Shutdown {
+    closed: AtomicBool,
+    /// outstanding decodes, resolved with a [`DecodeError::Closed`] error on close
+    decodes: Mutex<Vec<Weak<Mutex<DecodeState>>>>,
+}
+
+impl Shutdown {
+    /// Returns true once the context is closed or dropped
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Track an outstanding decode, returns false if the context is already closed
+    pub(super) fn track_decode(&self, decode: &Arc<Mutex<DecodeState>>) -> bool {
+        let mut decodes = self.decodes.lock().unwrap();
+        // checked under the lock, so `close` resolves all the decodes tracked before
+        if self.is_closed() {
+            return false;
+        }
+
+        decodes.retain(|decode| decode.strong_count() > 0);
+        decodes.push(Arc::downgrade(decode));
+        true
+    }
+
+    /// Stop the event callbacks and resolve the outstanding decodes, only the first call has
+    /// an effect
+    fn close(&self) {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let decodes = std::mem::take(&mut *self.decodes.lock().unwrap());
+        for decode in decodes.iter().filter_map(Weak::upgrade) {
+            decode.lock().unwrap().resolve(Err(DecodeError::Closed));
+        }
+    }
+}
+
+/// Checks of the lifecycle contract of [`BaseAudioContext::register`], in debug builds
+#[cfg(debug_assertions)]
+#[derive(Default)]
+struct RegistrationChecks {
+    /// nodes whose `register` closure is running
+    ///
+    /// A panicking closure leaves its entry behind, it is inert since ids are never reused.
+    open: Mutex<Vec<OpenRegistration>>,
+}
+
+/// Node whose `register` closure is running
+#[cfg(debug_assertions)]
+struct OpenRegistration {
+    id: u64,
+    /// params created for the node, with the witnesses of their `AudioParamId`s
+    params: Vec<(u64, Weak<()>)>,
+}
+
+#[cfg(debug_assertions)]
+impl RegistrationChecks {
+    fn open(&self, id: u64) {
+        let params = Vec::new();
+        self.open
+            .lock()
+            .unwrap()
+            .push(OpenRegistration { id, params });
+    }
+
+    /// Track the `AudioParamId` of a param created for the node `dest`
+    fn track_param(&self, dest: u64, param: u64, held: &Arc<()>) {
+        let mut open = self.open.lock().unwrap();
+        if let Some(open) = open.iter_mut().find(|open| open.id == dest) {
+            open.params.push((param, Arc::downgrade(held)));
+            return;
+        }
+
+        // not poisoning the lock, the nodes dropped while unwinding use it
+        drop(open);
+        panic!(
+            "InvalidStateError - param {} of node {} is not created inside its register closure",
+            param, dest
+        );
+    }
+
+    /// Check the params created when the `register` closure of node `id` returns
+    fn close(&self, id: u64) {
+        let params = {
+            let mut open = self.open.lock().unwrap();
+            let index = open.iter().position(|open| open.id == id).unwrap();
+            open.remove(index).params
+        };
+
+        // the values of a param are only available to the processor holding its id
+        if let Some((param, _)) = params.iter().find(|(_, held)| held.strong_count() == 0) {
+            panic!(
+                "InvalidStateError - the AudioParamId of param {} was dropped instead of being moved into the processor of node {}",
+                param, id
+            );
+        }
+    }
+
+    /// Check the registration of node `id` is not dropped by its `register` closure
+    fn dropped(&self, id: u64) {
+        // the processor is released along with the registration, while the node is still
+        // being built. Not checked when unwinding from a panic of the closure.
+        if thread::panicking() {
+            return;
+        }
+
+        let open = self.open.lock().unwrap();
+        if open.iter().any(|open| open.id == id) {
+            drop(open);
+            panic!(
+                "InvalidStateError - the registration of node {} was dropped inside its register closure",
+                id
+            );
+        }
+    }
+}
+
+/// Registry of the [`AudioNodeMirror`]s of the live nodes
+#[derive(Default)]
+struct NodeMirrors {
+    /// mirrors of the registered nodes, by node id
+    nodes: HashMap<u64, NodeMirror>,
+    /// mirrors of the `AudioParam`s of nodes that are not registered yet, by node id
+    pending_params: HashMap<u64, Vec<AudioParamMirror>>,
 }
 
 impl BaseAudioContext for ConcreteBaseAudioContext {
@@ -78,37 +253,7 @@ impl BaseAudioContext for ConcreteBaseAudioContext {
         &self,
         f: F,
     ) -> T {
-        // create unique identifier for this node
-        let id = self.inner.node_id_inc.fetch_add(1, Ordering::SeqCst);
-        let node_id = AudioNodeId(id);
-        let registration = AudioContextRegistration {
-            id: node_id,
-            context: self.clone(),
-        };
-
-        // create the node and its renderer
-        let (node, render) = (f)(registration);
-
-        // pass the renderer to the audio graph
-        let message = ControlMessage::RegisterNode {
-            id,
-            node: render,
-            inputs: node.number_of_inputs(),
-            outputs: node.number_of_outputs(),
-            channel_config: node.channel_config().clone(),
-        };
-
-        // if this is the AudioListener or its params, do not add it to the graph just yet
-        if id == LISTENER_NODE_ID || LISTENER_PARAM_IDS.contains(&id) {
-            let mut queued_audio_listener_msgs =
-                self.inner.queued_audio_listener_msgs.lock().unwrap();
-            queued_audio_listener_msgs.push(message);
-        } else {
-            self.inner.render_channel.send(message).unwrap();
-            self.resolve_queued_control_msgs(id);
-        }
-
-        node
+        self.register_node(None, f)
     }
 }
 
@@ -121,85 +266,254 @@ impl ConcreteBaseAudioContext {
         render_channel: Sender<ControlMessage>,
         offline: bool,
     ) -> Self {
+        // the sample rate is validated by the contexts, time computations rely on it
+        debug_assert!(sample_rate.is_finite() && sample_rate > 0.);
+
+        let (output_snapshots, snapshot_writer) = snapshot_buffer(sample_rate);
+        let shutdown = Arc::new(Shutdown::default());
         let base_inner = ConcreteBaseAudioContextInner {
             sample_rate,
             max_channel_count,
             render_channel,
             queued_messages: Mutex::new(Vec::new()),
+            staged_messages: Mutex::new(HashMap::new()),
+            discarded_nodes: Mutex::new(HashSet::new()),
             node_id_inc: AtomicU64::new(0),
             destination_channel_config: ChannelConfigOptions::default().into(),
             frames_played,
             queued_audio_listener_msgs: Mutex::new(Vec::new()),
-            listener_params: None,
+            listener_params: Mutex::new(None),
+            master_gain: None,
             offline,
-            state: AtomicU8::new(AudioContextState::Suspended as u8),
+            state: Arc::new(AtomicU8::new(AudioContextState::Suspended as u8)),
+            active_sources: ActiveSourceCounter::default(),
+            builtin_ids: Mutex::new(BuiltinNodeIds::default()),
+            node_mirrors: Mutex::new(NodeMirrors::default()),
+            output_snapshots,
+            channel_config_queue: Arc::default(),
+            ended_events: EndedEvents::new(Arc::clone(&shutdown)),
+            shutdown,
+            #[cfg(debug_assertions)]
+            registration_checks: RegistrationChecks::default(),
         };
         let base = Self {
             inner: Arc::new(base_inner),
         };
 
-        let (listener_params, destination_channel_config) = {
+        // the render thread reads the state at the start of each render quantum
+        let state = Arc::clone(&base.inner.state);
+        base.send_control_msg(ControlMessage::SetContextState { state });
+
+        let (master_gain, destination_channel_config) = {
             // Register magical nodes. We should not store the nodes inside our context since that
             // will create a cyclic reference, but we can reconstruct a new instance on the fly
-            // when requested
-            let dest = AudioDestinationNode::new(&base, max_channel_count);
+            // when requested. The destination is registered first: the render graph outputs the
+            // node with index 0, see `NodeIndex`
+            let (dest, master_gain) =
+                AudioDestinationNode::new(&base, max_channel_count, snapshot_writer);
             let destination_channel_config = dest.into_channel_config();
-            let listener = crate::spatial::AudioListenerNode::new(&base);
-
-            let listener_params = listener.into_fields();
-            let AudioListener {
-                position_x,
-                position_y,
-                position_z,
-                forward_x,
-                forward_y,
-                forward_z,
-                up_x,
-                up_y,
-                up_z,
-            } = listener_params;
-
-            let listener_params = AudioListenerParams {
-                position_x: position_x.into_raw_parts(),
-                position_y: position_y.into_raw_parts(),
-                position_z: position_z.into_raw_parts(),
-                forward_x: forward_x.into_raw_parts(),
-                forward_y: forward_y.into_raw_parts(),
-                forward_z: forward_z.into_raw_parts(),
-                up_x: up_x.into_raw_parts(),
-                up_y: up_y.into_raw_parts(),
-                up_z: up_z.into_raw_parts(),
-            };
+            let master_gain = master_gain.into_raw_parts();
 
-            (listener_params, destination_channel_config)
+            (master_gain, destination_channel_config)
         }; // nodes will drop now, so base.inner has no copies anymore
 
         let mut base = base;
         let mut inner_mut = Arc::get_mut(&mut base.inner).unwrap();
-        inner_mut.listener_params = Some(listener_params);
+        inner_mut.master_gain = Some(master_gain);
         inner_mut.destination_channel_config = destination_channel_config;
 
-        // validate if the hardcoded node IDs line up
-        debug_assert_eq!(
-            base.inner.node_id_inc.load(Ordering::Relaxed),
-            LISTENER_PARAM_IDS.end,
-        );
-
         base
     }
 
+    /// Register a node created by the context itself, see [`BaseAudioContext::register`]
+    ///
+    /// The assigned id is recorded so the node can be recognized later on. The `AudioListener`
+    /// and its params are not added to the audio graph until the first `PannerNode` is created.
+    pub(crate) fn register_builtin<
+        T: AudioNode,
+        F: FnOnce(AudioContextRegistration) -> (T, Box<dyn AudioProcessor>),
+    >(
+        &self,
+        kind: BuiltinNode,
+        f: F,
+    ) -> T {
+        self.register_node(Some(kind), f)
+    }
+
+    /// Shared implementation of [`BaseAudioContext::register`] and [`Self::register_builtin`]
+    fn register_node<
+        T: AudioNode,
+        F: FnOnce(AudioContextRegistration) -> (T, Box<dyn AudioProcessor>),
+    >(
+        &self,
+        builtin: Option<BuiltinNode>,
+        f: F,
+    ) -> T {
+        // create unique identifier for this node
+        let id = self.inner.node_id_inc.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(kind) = builtin {
+            let mut ids = self.inner.builtin_ids.lock().unwrap();
+            match kind {
+                BuiltinNode::Destination => ids.destination = Some(id),
+                BuiltinNode::MasterGain => ids.master_gain = Some(id),
+                BuiltinNode::Listener => ids.listener = Some(id),
+                BuiltinNode::ListenerParam => ids.listener_params.push(id),
+            }
+        }
+
+        let node_id = AudioNodeId(id);
+        let registration = AudioContextRegistration {
+            id: node_id,
+            context: self.clone(),
+        };
+
+        // create the node and its renderer
+        #[cfg(debug_assertions)]
+        self.inner.registration_checks.open(id);
+        let (node, render) = (f)(registration);
+        #[cfg(debug_assertions)]
+        self.inner.registration_checks.close(id);
+
+        // mirror the node for inspection, along with the params created in the closure
+        {
+            let mut mirrors = self.inner.node_mirrors.lock().unwrap();
+            let params = mirrors.pending_params.remove(&id).unwrap_or_default();
+            let mirror = NodeMirror::new::<T>(node_id, node.channel_config().clone(), params);
+            mirrors.nodes.insert(id, mirror);
+        }
+
+        // pass the renderer to the audio graph, along with a copy of the channel config which
+        // is only updated by the batched changes of the control thread
+        let channel_config = node.channel_config();
+        channel_config.register(id, &self.inner.channel_config_queue);
+        let message = ControlMessage::RegisterNode {
+            id,
+            node: render,
+            inputs: node.number_of_inputs(),
+            outputs: node.number_of_outputs(),
+            channel_config: channel_config.options().into(),
+        };
+
+        // if this is the AudioListener or its params, do not add it to the graph just yet
+        if matches!(
+            builtin,
+            Some(BuiltinNode::Listener | BuiltinNode::ListenerParam)
+        ) {
+            let mut queued_audio_listener_msgs =
+                self.inner.queued_audio_listener_msgs.lock().unwrap();
+            queued_audio_listener_msgs.push(message);
+        } else {
+            self.send_control_msg(message);
+            self.resolve_queued_control_msgs(id);
+        }
+
+        node
+    }
+
+    /// Create an `AudioParam` of a built-in node, see [`BaseAudioContext::create_audio_param`]
+    pub(crate) fn create_builtin_audio_param(
+        &self,
+        kind: BuiltinNode,
+        opts: AudioParamDescriptor,
+        dest: &AudioContextRegistration,
+    ) -> (AudioParam, AudioParamId) {
+        let param = self.register_builtin(kind, move |registration| {
+            let (node, proc) = crate::param::audio_param_pair(opts, registration);
+
+            (node, Box::new(proc))
+        });
+
+        // Route the automation events of the param and connect it to the node, once the node
+        // is registered inside the audio graph.
+        self.register_audio_param_events(&param);
+        self.queue_audio_param_connect(param.registration().id(), dest.id());
+        self.mirror_audio_param(&param, dest.id());
+
+        let proc_id = self.audio_param_id(param.registration().id(), dest);
+        (param, proc_id)
+    }
+
+    /// Identifier of the param `param`, for the processor of the node `dest`
+    ///
+    /// In debug builds, the identifier is checked to be moved into the processor when the
+    /// `register` closure of `dest` returns.
+    pub(super) fn audio_param_id(
+        &self,
+        param: &AudioNodeId,
+        dest: &AudioContextRegistration,
+    ) -> AudioParamId {
+        #[cfg(debug_assertions)]
+        {
+            let held = Arc::new(());
+            self.inner
+                .registration_checks
+                .track_param(dest.id().0, param.0, &held);
+            AudioParamId {
+                id: param.0,
+                _held: held,
+            }
+        }
+        #[cfg(not(debug_assertions))]
+        {
+            let _ = dest;
+            AudioParamId { id: param.0 }
+        }
+    }
+
+    /// Returns true if the node with the given id was created by the context itself
+    pub(super) fn is_builtin_node(&self, id: u64) -> bool {
+        self.inner.builtin_ids.lock().unwrap().contains(id)
+    }
+
+    /// Id of the `AudioDestinationNode`
+    pub(super) fn destination_id(&self) -> AudioNodeId {
+        let id = self.inner.builtin_ids.lock().unwrap().destination;
+        AudioNodeId(id.expect("AudioDestinationNode is registered on construction"))
+    }
+
+    /// Id of the node wrapping the `AudioListener`
+    fn listener_id(&self) -> AudioNodeId {
+        let id = self.inner.builtin_ids.lock().unwrap().listener;
+        AudioNodeId(id.expect("AudioListener is created before use"))
+    }
+
+    /// Create the `AudioListener` and its params, if not already
+    ///
+    /// They are only added to the audio graph along with the first `PannerNode`, see
+    /// [`Self::ensure_audio_listener_present`].
+    fn realize_listener(&self) -> MutexGuard<'_, Option<AudioListenerParams>> {
+        let mut params = self.inner.listener_params.lock().unwrap();
+        if params.is_none() {
+            let listener = crate::spatial::AudioListenerNode::new(self);
+            *params = Some(listener.into_fields().into());
+        }
+        params
+    }
+
     /// Inform render thread that the control thread `AudioNode` no langer has any handles
     pub(super) fn mark_node_dropped(&self, id: u64) {
+        #[cfg(debug_assertions)]
+        self.inner.registration_checks.dropped(id);
+
         // do not drop magic nodes
-        let magic =
-            id == DESTINATION_NODE_ID || id == LISTENER_NODE_ID || LISTENER_PARAM_IDS.contains(&id);
+        let magic = self.is_builtin_node(id);
 
         if !magic {
+            // the mirror is only valid while the node is alive
+            self.inner.node_mirrors.lock().unwrap().nodes.remove(&id);
+
+            // a discarded node never reached the render thread
+            if self.inner.discarded_nodes.lock().unwrap().remove(&id) {
+                return;
+            }
+
             let message = ControlMessage::FreeWhenFinished { id };
 
             // Sending the message will fail when the render thread has already shut down.
             // This is fine
-            let _r = self.inner.render_channel.send(message);
+            self.flush_channel_configs();
+            let _r = self.dispatch(message);
         }
     }
 
@@ -208,13 +522,28 @@ impl ConcreteBaseAudioContext {
         self.inner.destination_channel_config.clone()
     }
 
+    /// Returns the master gain `AudioParam` of the destination node
+    pub(super) fn master_gain(&self) -> AudioParam {
+        let id = self.inner.builtin_ids.lock().unwrap().master_gain;
+        let registration = AudioContextRegistration {
+            id: AudioNodeId(id.expect("master gain is registered on construction")),
+            context: self.clone(),
+        };
+        let params = self.inner.master_gain.as_ref().unwrap();
+        AudioParam::from_raw_parts(registration, params.clone())
+    }
+
     /// Returns the `AudioListener` which is used for 3D spatialization
+    ///
+    /// The listener is created on first use. Its params can be automated right away, the events
+    /// are applied once the listener is added to the audio graph along with the first panner.
     pub(super) fn listener(&self) -> AudioListener {
-        let mut ids = LISTENER_PARAM_IDS.map(|i| AudioContextRegistration {
-            id: AudioNodeId(i),
+        let params = self.realize_listener();
+        let params = params.as_ref().unwrap();
+        let mut ids = params.ids.iter().map(|&id| AudioContextRegistration {
+            id,
             context: self.clone(),
         });
-        let params = self.inner.listener_params.as_ref().unwrap();
 
         AudioListener {
             position_x: AudioParam::from_raw_parts(ids.next().unwrap(), params.position_x.clone()),
@@ -232,12 +561,39 @@ impl ConcreteBaseAudioContext {
     /// Returns state of current context
     #[must_use]
     pub(super) fn state(&self) -> AudioContextState {
-        self.inner.state.load(Ordering::SeqCst).into()
+        self.inner.state.load(Ordering::Acquire).into()
     }
 
     /// Updates state of current context
     pub(super) fn set_state(&self, state: AudioContextState) {
-        self.inner.state.store(state as u8, Ordering::SeqCst);
+        self.inner.state.store(state as u8, Ordering::Release);
+    }
+
+    /// Close the context, the owner of the render side releases it afterwards
+    ///
+    /// The teardown happens in this order:
+    /// 1. the `ended` and `xrun` callbacks that have not started yet are discarded, and no new
+    ///    ones are accepted
+    /// 2. the outstanding decodes resolve with a [`DecodeError::Closed`] error, their decoder
+    ///    threads stop before the next packet
+    /// 3. the render side processes a last quantum in the `Closed` state, then frees the
+    ///    processors, which ends the threads feeding the media stream sources
+    /// 4. the owner of the render side drops it, along with the render channel
+    ///
+    /// The control messages sent after the render channel is released are dropped.
+    pub(super) fn close(&self) {
+        self.inner.shutdown.close();
+        self.set_state(AudioContextState::Closed);
+    }
+
+    /// Closing of the context, to be observed by the threads working on its behalf
+    pub(crate) fn shutdown(&self) -> &Arc<Shutdown> {
+        &self.inner.shutdown
+    }
+
+    /// Queue of the `ended` events of the scheduled sources
+    pub(crate) fn ended_events(&self) -> &EndedEvents {
+        &self.inner.ended_events
     }
 
     /// The sample rate (in sample-frames per second) at which the `AudioContext` handles audio.
@@ -254,7 +610,18 @@ impl ConcreteBaseAudioContext {
     // Currently, we have no other choice than casting an u64 into f64, with possible loss of precision
     #[allow(clippy::cast_precision_loss)]
     pub(super) fn current_time(&self) -> f64 {
-        self.inner.frames_played.load(Ordering::SeqCst) as f64 / self.inner.sample_rate as f64
+        self.inner.frames_played.load(Ordering::Acquire) as f64 / self.inner.sample_rate as f64
+    }
+
+    /// Last rendered quantum of the destination node
+    pub(super) fn output_snapshots(&self) -> &SnapshotBuffer {
+        &self.inner.output_snapshots
+    }
+
+    /// Shared count of scheduled sources currently producing audio
+    #[must_use]
+    pub(crate) fn active_sources(&self) -> &ActiveSourceCounter {
+        &self.inner.active_sources
     }
 
     /// Maximum available channels for the audio destination
@@ -263,6 +630,193 @@ impl ConcreteBaseAudioContext {
         self.inner.max_channel_count
     }
 
+    /// Send a control message to the render thread, preceded by the pending channel config
+    /// changes
+    fn send_control_msg(&self, message: ControlMessage) {
+        self.flush_channel_configs();
+        // Sending the message will fail when the context is closed and the render thread has
+        // already shut down. The message is dropped then
+        let _r = self.dispatch(message);
+    }
+
+    /// Send a control message to the render thread, or stage it if a transaction is open on the
+    /// calling thread
+    ///
+    /// # Panics
+    ///
+    /// Panics if the message concerns a node discarded by a rolled-back transaction
+    fn dispatch(&self, message: ControlMessage) -> Result<(), SendError<ControlMessage>> {
+        {
+            let discarded = self.inner.discarded_nodes.lock().unwrap();
+            let concerned =
+                !discarded.is_empty() && message.concerns_node(&|id| discarded.contains(&id));
+            drop(discarded); // do not poison the lock
+            assert!(
+                !concerned,
+                "InvalidStateError - the node was discarded by a rolled-back transaction"
+            );
+        }
+        {
+            let mut staged = self.inner.staged_messages.lock().unwrap();
+            if !staged.is_empty() {
+                if let Some(messages) = staged.get_mut(&thread::current().id()) {
+                    messages.push(message);
+                    return Ok(());
+                }
+            }
+        }
+
+        self.inner.render_channel.send(message)
+    }
+
+    /// Send the channel config changes of all nodes to the render thread, in a single message
+    /// so they are applied in the same render quantum
+    ///
+    /// The changes are held back while a transaction is open on the calling thread: they may
+    /// concern nodes that are staged, they are sent along with the transaction.
+    pub(crate) fn flush_channel_configs(&self) {
+        if self.in_transaction() {
+            return;
+        }
+
+        if let Some(message) = self.take_channel_configs() {
+            // Sending the message will fail when the render thread has already shut down.
+            // This is fine
+            let _r = self.inner.render_channel.send(message);
+        }
+    }
+
+    /// Collect the channel config changes of all nodes in a single control message
+    fn take_channel_configs(&self) -> Option<ControlMessage> {
+        let queued = std::mem::take(&mut *self.inner.channel_config_queue.lock().unwrap());
+        if queued.is_empty() {
+            return None;
+        }
+
+        let configs = queued
+            .into_iter()
+            .map(|(id, config)| {
+                // mark as sent first, so concurrent changes are queued again
+                config.dequeue();
+                (id, config.options())
+            })
+            .collect();
+        Some(ControlMessage::SetChannelConfig { configs })
+    }
+
+    /// Returns true if a transaction is open on the calling thread
+    fn in_transaction(&self) -> bool {
+        let staged = self.inner.staged_messages.lock().unwrap();
+        !staged.is_empty() && staged.contains_key(&thread::current().id())
+    }
+
+    /// Stage the control messages of the calling thread, see [`Transaction`](super::Transaction)
+    ///
+    /// # Panics
+    ///
+    /// Panics if a transaction is already open on the calling thread
+    pub(super) fn begin_transaction(&self) {
+        let id = thread::current().id();
+        let mut staged = self.inner.staged_messages.lock().unwrap();
+        let open = staged.contains_key(&id);
+        if !open {
+            staged.insert(id, Vec::new());
+        }
+        drop(staged); // do not poison the lock
+        assert!(
+            !open,
+            "InvalidStateError - a transaction is already open on this thread"
+        );
+    }
+
+    /// Send the control messages staged by the calling thread at once, followed by the channel
+    /// config changes
+    pub(super) fn commit_transaction(&self) {
+        let messages = self.take_staged_messages();
+        self.send_staged_messages(messages);
+    }
+
+    /// Discard the control messages staged by the calling thread for the nodes it registered,
+    /// and send the others
+    ///
+    /// The changes made to the nodes registered before the transaction (start and stop times,
+    /// automation events, connections between them, releases...) are already visible on the
+    /// control side, so they are passed on to the render thread as well. The nodes registered
+    /// by the transaction are discarded: they never reach the audio graph, and using them
+    /// afterwards panics.
+    pub(super) fn rollback_transaction(&self) {
+        let messages = self.take_staged_messages();
+
+        let mut registered = HashSet::new();
+        let mut released = HashSet::new();
+        for message in &messages {
+            match message {
+                ControlMessage::RegisterNode { id, .. } => {
+                    registered.insert(*id);
+                }
+                ControlMessage::FreeWhenFinished { id } => {
+                    released.insert(*id);
+                }
+                _ => (),
+            }
+        }
+
+        // the nodes still held by the caller are dead until they are dropped
+        {
+            let mut mirrors = self.inner.node_mirrors.lock().unwrap();
+            let mut discarded = self.inner.discarded_nodes.lock().unwrap();
+            for id in registered.difference(&released) {
+                mirrors.nodes.remove(id);
+                discarded.insert(*id);
+            }
+        }
+
+        let messages = messages
+            .into_iter()
+            .filter(|message| !message.concerns_node(&|id| registered.contains(&id)))
+            .collect();
+        self.send_staged_messages(messages);
+    }
+
+    /// Send the given staged control messages at once, followed by the channel config changes
+    fn send_staged_messages(&self, mut messages: Vec<ControlMessage>) {
+        // the channel configs may concern the staged nodes, they follow their registration
+        if let Some(message) = self.take_channel_configs() {
+            messages.push(message);
+        }
+        if messages.is_empty() {
+            return;
+        }
+
+        // a single message is handled by the render thread in a single render quantum
+        let _r = self
+            .inner
+            .render_channel
+            .send(ControlMessage::Batch { messages });
+    }
+
+    /// Close the transaction of the calling thread, and return its staged messages
+    fn take_staged_messages(&self) -> Vec<ControlMessage> {
+        let mut staged = self.inner.staged_messages.lock().unwrap();
+        staged.remove(&thread::current().id()).unwrap_or_default()
+    }
+
+    /// Send the control messages of `f` right away, even if a transaction is open on the
+    /// calling thread
+    fn without_transaction<R>(&self, f: impl FnOnce() -> R) -> R {
+        let id = thread::current().id();
+        let suspended = self.inner.staged_messages.lock().unwrap().remove(&id);
+        let result = f();
+        if let Some(messages) = suspended {
+            self.inner
+                .staged_messages
+                .lock()
+                .unwrap()
+                .insert(id, messages);
+        }
+        result
+    }
+
     /// Release queued control messages to the render thread that were blocking on the availability
     /// of the Node with the given `id`
     fn resolve_queued_control_msgs(&self, id: u64) {
@@ -272,7 +826,7 @@ impl ConcreteBaseAudioContext {
         while i < queued.len() {
             if matches!(&queued[i], ControlMessage::ConnectNode {to, ..} if *to == id) {
                 let m = queued.remove(i);
-                self.inner.render_channel.send(m).unwrap();
+                self.send_control_msg(m);
             } else {
                 i += 1;
             }
@@ -291,20 +845,112 @@ impl ConcreteBaseAudioContext {
             from: from.0,
             to: to.0,
             output,
-            input,
+            kind: EdgeKind::Audio { input },
         };
-        self.inner.render_channel.send(message).unwrap();
+        self.send_control_msg(message);
     }
 
-    /// Schedule a connection of an `AudioParam` to the `AudioNode` it belongs to
+    /// Connects a single channel of the output of the `from` audio node to the input of the
+    /// `to` audio node
+    pub(crate) fn connect_channel(
+        &self,
+        from: &AudioNodeId,
+        to: &AudioNodeId,
+        output: usize,
+        channel: usize,
+        input: usize,
+    ) {
+        let message = ControlMessage::ConnectNode {
+            from: from.0,
+            to: to.0,
+            output,
+            kind: EdgeKind::AudioChannel { input, channel },
+        };
+        self.send_control_msg(message);
+    }
+
+    /// Add the `AudioParam` to the mirror of the `AudioNode` it belongs to
+    ///
+    /// Params are usually created before their node is registered, in which case they are
+    /// attached to its mirror on registration.
+    pub(super) fn mirror_audio_param(&self, param: &AudioParam, audio_node: &AudioNodeId) {
+        let mut mirrors = self.inner.node_mirrors.lock().unwrap();
+        match mirrors.nodes.get_mut(&audio_node.0) {
+            Some(node) => node.push_param(param.mirror()),
+            None => mirrors
+                .pending_params
+                .entry(audio_node.0)
+                .or_default()
+                .push(param.mirror()),
+        }
+    }
+
+    /// Returns the mirror of the live node with the given id
+    pub(super) fn node_mirror(&self, id: AudioNodeId) -> Option<Box<dyn AudioNodeMirror>> {
+        let mirrors = self.inner.node_mirrors.lock().unwrap();
+        mirrors
+            .nodes
+            .get(&id.0)
+            .map(|mirror| Box::new(mirror.clone()) as Box<dyn AudioNodeMirror>)
+    }
+
+    /// Returns the label of the live node with the given id, see [`AudioNode::label`]
+    pub(crate) fn node_label(&self, id: &AudioNodeId) -> Option<String> {
+        let mirrors = self.inner.node_mirrors.lock().unwrap();
+        mirrors
+            .nodes
+            .get(&id.0)
+            .map(|mirror| mirror.label().to_owned())
+    }
+
+    /// Set the label of the live node with the given id, see [`AudioNode::set_label`]
+    pub(crate) fn set_node_label(&self, id: &AudioNodeId, label: Option<String>) {
+        let mut mirrors = self.inner.node_mirrors.lock().unwrap();
+        if let Some(mirror) = mirrors.nodes.get_mut(&id.0) {
+            mirror.set_label(label);
+        }
+    }
+
+    /// Let the render thread deliver the automation events addressed to this `AudioParam`
+    ///
+    /// Events are then passed to the render thread by param id, see
+    /// [`Self::pass_audio_param_event`].
+    pub(super) fn register_audio_param_events(&self, param: &AudioParam) {
+        let id = param.registration().id().0;
+        let message = ControlMessage::RegisterAudioParam { id };
+        self.send_audio_param_msg(id, message);
+    }
+
+    /// Send a control message addressed to an `AudioParam`
+    ///
+    /// The `AudioListener` params are only added to the graph along with the first panner,
+    /// their messages are queued until then.
+    fn send_audio_param_msg(&self, id: u64, message: ControlMessage) {
+        let mut queued_audio_listener_msgs = self.inner.queued_audio_listener_msgs.lock().unwrap();
+        if !queued_audio_listener_msgs.is_empty()
+            && self
+                .inner
+                .builtin_ids
+                .lock()
+                .unwrap()
+                .listener_params
+                .contains(&id)
+        {
+            queued_audio_listener_msgs.push(message);
+        } else {
+            self.send_control_msg(message);
+        }
+    }
+
+    /// Schedule a connection of an `AudioParam` or a `ControlInput` to the `AudioNode` it belongs to
     ///
     /// It is not performed immediately as the `AudioNode` is not registered at this point.
-    pub(super) fn queue_audio_param_connect(&self, param: &AudioParam, audio_node: &AudioNodeId) {
+    pub(super) fn queue_audio_param_connect(&self, param: &AudioNodeId, audio_node: &AudioNodeId) {
         let message = ControlMessage::ConnectNode {
-            from: param.registration().id().0,
+            from: param.0,
             to: audio_node.0,
             output: 0,
-            input: usize::MAX, // audio params connect to the 'hidden' input port
+            kind: EdgeKind::Param,
         };
         self.inner.queued_messages.lock().unwrap().push(message);
     }
@@ -315,67 +961,287 @@ impl ConcreteBaseAudioContext {
             from: from.0,
             to: to.0,
         };
-        self.inner.render_channel.send(message).unwrap();
+        self.send_control_msg(message);
     }
 
     /// Disconnects all outgoing connections from the audio node.
     pub(crate) fn disconnect(&self, from: &AudioNodeId) {
         let message = ControlMessage::DisconnectAll { from: from.0 };
-        self.inner.render_channel.send(message).unwrap();
+        self.send_control_msg(message);
+    }
+
+    /// Remove the modulation of a param by `source` through `gain`, when its handle is dropped
+    ///
+    /// Unlike the other changes this does not panic for a node discarded by a rolled-back
+    /// transaction: its connections never reached the render thread, nothing is sent then.
+    pub(crate) fn release_modulation(&self, source: &AudioNodeId, gain: &AudioNodeId) {
+        {
+            let discarded = self.inner.discarded_nodes.lock().unwrap();
+            if discarded.contains(&source.0) || discarded.contains(&gain.0) {
+                return;
+            }
+        }
+
+        self.disconnect_from(source, gain);
+        self.disconnect(gain);
+    }
+
+    /// Set the order in which a node is degraded when the render thread is overloaded
+    pub(crate) fn set_render_priority(&self, id: &AudioNodeId, priority: u8) {
+        let message = ControlMessage::SetRenderPriority { id: id.0, priority };
+        self.send_control_msg(message);
+    }
+
+    /// Mute or unmute the outputs of a node, with a gain ramp of the given duration in seconds
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ramp duration is negative or not finite
+    pub(crate) fn set_muted(&self, id: &AudioNodeId, muted: bool, ramp_duration: f64) {
+        assert!(
+            ramp_duration.is_finite() && ramp_duration >= 0.,
+            "RangeError - mute ramp duration must be finite and positive, got {:?}",
+            ramp_duration
+        );
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let ramp_frames = (ramp_duration * f64::from(self.inner.sample_rate)).round() as usize;
+        let message = ControlMessage::SetMuted {
+            id: id.0,
+            muted,
+            ramp_frames,
+        };
+        self.send_control_msg(message);
+    }
+
+    /// Render the nodes, connected in series, as a single step
+    pub(crate) fn freeze_chain(&self, ids: &[&AudioNodeId]) {
+        let ids = ids.iter().map(|id| id.0).collect();
+        let message = ControlMessage::FreezeChain { ids };
+        self.send_control_msg(message);
+    }
+
+    /// Set the degradations applied when the render thread is overloaded
+    pub(crate) fn set_overload_policy(&self, policy: Option<OverloadPolicy>) {
+        let message = ControlMessage::SetOverloadPolicy { policy };
+        self.send_control_msg(message);
+    }
+
+    /// Notify the late output callbacks (xruns) on the given channel
+    #[cfg(feature = "cpal")]
+    pub(crate) fn set_xrun_sender(&self, sender: Option<Sender<f64>>) {
+        let message = ControlMessage::SetXrunSender { sender };
+        self.send_control_msg(message);
+    }
+
+    /// Pass the interleaved output of each render quantum to the given tap
+    pub(crate) fn set_output_tap(&self, tap: Option<OutputTap>) {
+        let message = ControlMessage::SetOutputTap { tap };
+        self.send_control_msg(message);
+    }
+
+    /// Replace the processor of an audio node in the render thread
+    ///
+    /// The swap happens between two render quanta, the previous processor is dropped on the
+    /// render thread.
+    pub(crate) fn swap_processor(&self, id: &AudioNodeId, processor: Box<dyn AudioProcessor>) {
+        let message = ControlMessage::SwapProcessor {
+            id: id.0,
+            processor,
+        };
+        self.send_control_msg(message);
     }
 
     /// Pass an `AudioParam::AudioParamEvent` to the render thread
     ///
-    /// This clunky setup (wrapping a Sender in a message sent by another Sender) ensures
-    /// automation events will never be handled out of order.
-    pub(crate) fn pass_audio_param_event(
+    /// Events travel along with the other control messages, addressed by param id, which
+    /// ensures automation events will never be handled out of order.
+    pub(crate) fn pass_audio_param_event(&self, to: &AudioNodeId, event: AudioParamEvent) {
+        let message = ControlMessage::AudioParamEvent { to: to.0, event };
+        self.send_audio_param_msg(to.0, message);
+    }
+
+    /// Pass the AudioParam events of several params in a single control message
+    ///
+    /// The params are either all `AudioListener` params or none of them, so the events are
+    /// queued along with the listener messages if needed.
+    pub(crate) fn pass_audio_param_events(&self, events: Vec<(&AudioNodeId, AudioParamEvent)>) {
+        let id = match events.first() {
+            Some((to, _)) => to.0,
+            None => return,
+        };
+        let events = events
+            .into_iter()
+            .map(|(to, event)| (to.0, event))
+            .collect();
+        let message = ControlMessage::AudioParamEvents { events };
+        self.send_audio_param_msg(id, message);
+    }
+
+    /// Request the render thread to smoothly ramp an `AudioParam` to the `target` value
+    ///
+    /// The cancellation of scheduled values, the anchor on the current value and the ramp are
+    /// applied at once by the render thread.
+    pub(crate) fn pass_smooth_set_value(
         &self,
-        to: &Sender<AudioParamEvent>,
-        event: AudioParamEvent,
+        to: &AudioNodeId,
+        target: f32,
+        smoothing_samples: usize,
     ) {
-        let message = ControlMessage::AudioParamEvent {
-            to: to.clone(),
-            event,
+        let message = ControlMessage::SmoothSetValue {
+            to: to.0,
+            target,
+            smoothing_samples,
+        };
+        self.send_audio_param_msg(to.0, message);
+    }
+
+    /// Pass the start time of an `AudioScheduledSourceNode` to the render thread
+    ///
+    /// Start and stop times travel along with the `AudioParam` events, so calls issued
+    /// back-to-back on the control thread are applied in order, in the same render quantum.
+    ///
+    /// A negative time is clamped to zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the time is NaN or infinite, or if the source was already started
+    pub(crate) fn schedule_start(&self, scheduler: &Scheduler, when: f64) {
+        let when = Scheduler::validate_time(when, "start");
+        let frame = Scheduler::time_to_frame(when, self.sample_rate());
+        self.send_start(scheduler, when, frame);
+    }
+
+    /// Pass the start frame of an `AudioScheduledSourceNode` to the render thread
+    ///
+    /// See [`Self::schedule_start`], the frame is passed as is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the source was already started
+    pub(crate) fn schedule_start_frame(&self, scheduler: &Scheduler, frame: u64) {
+        let when = frame as f64 / f64::from(self.sample_rate());
+        self.send_start(scheduler, when, frame as f64);
+    }
+
+    fn send_start(&self, scheduler: &Scheduler, when: f64, frame: f64) {
+        scheduler.set_scheduled_start(when);
+        let message = ControlMessage::StartSource {
+            scheduler: scheduler.clone(),
+            frame,
         };
-        self.inner.render_channel.send(message).unwrap();
+        self.send_control_msg(message);
+    }
+
+    /// Pass the stop time of an `AudioScheduledSourceNode` to the render thread
+    ///
+    /// See [`Self::schedule_start`] for the ordering guarantees.
+    ///
+    /// A negative time is clamped to zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the time is NaN or infinite, if the source was not started, or was already
+    /// stopped
+    pub(crate) fn schedule_stop(&self, scheduler: &Scheduler, when: f64) {
+        let when = Scheduler::validate_time(when, "stop");
+        let frame = Scheduler::time_to_frame(when, self.sample_rate());
+        self.send_stop(scheduler, when, frame);
+    }
+
+    /// Pass the stop frame of an `AudioScheduledSourceNode` to the render thread
+    ///
+    /// See [`Self::schedule_stop`], the frame is passed as is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the source was not started, or was already stopped
+    pub(crate) fn schedule_stop_frame(&self, scheduler: &Scheduler, frame: u64) {
+        let when = frame as f64 / f64::from(self.sample_rate());
+        self.send_stop(scheduler, when, frame as f64);
+    }
+
+    fn send_stop(&self, scheduler: &Scheduler, when: f64, frame: f64) {
+        scheduler.set_scheduled_stop(when);
+        let message = ControlMessage::StopSource {
+            scheduler: scheduler.clone(),
+            frame,
+        };
+        self.send_control_msg(message);
+    }
+
+    /// Undo the scheduled start of an `AudioScheduledSourceNode`, a no-op when not scheduled
+    ///
+    /// A start that has already been reached is not undone, the source is stopped at the
+    /// current time instead, unless it stops earlier. The render thread does the same if it
+    /// reached the start time in the meantime.
+    pub(crate) fn cancel_start(&self, scheduler: &Scheduler) {
+        let when = match scheduler.scheduled_start() {
+            Some(when) => when,
+            None => return,
+        };
+
+        let frame = self.inner.frames_played.load(Ordering::Acquire);
+        let now = frame as f64 / self.inner.sample_rate as f64;
+        if when <= now {
+            if !scheduler.scheduled_stop().is_some_and(|stop| stop <= now) {
+                scheduler.advance_scheduled_stop(now);
+                let message = ControlMessage::StopSource {
+                    scheduler: scheduler.clone(),
+                    frame: frame as f64,
+                };
+                self.send_control_msg(message);
+            }
+            return;
+        }
+
+        scheduler.clear_scheduled_start();
+        let message = ControlMessage::CancelStart {
+            scheduler: scheduler.clone(),
+        };
+        self.send_control_msg(message);
     }
 
     /// Attach the 9 `AudioListener` coordinates to a `PannerNode`
     pub(crate) fn connect_listener_to_panner(&self, panner: &AudioNodeId) {
-        self.connect(&AudioNodeId(LISTENER_NODE_ID), panner, 0, 1);
-        self.connect(&AudioNodeId(LISTENER_NODE_ID), panner, 1, 2);
-        self.connect(&AudioNodeId(LISTENER_NODE_ID), panner, 2, 3);
-        self.connect(&AudioNodeId(LISTENER_NODE_ID), panner, 3, 4);
-        self.connect(&AudioNodeId(LISTENER_NODE_ID), panner, 4, 5);
-        self.connect(&AudioNodeId(LISTENER_NODE_ID), panner, 5, 6);
-        self.connect(&AudioNodeId(LISTENER_NODE_ID), panner, 6, 7);
-        self.connect(&AudioNodeId(LISTENER_NODE_ID), panner, 7, 8);
-        self.connect(&AudioNodeId(LISTENER_NODE_ID), panner, 8, 9);
-    }
-
-    /// Add the [`AudioListener`] to the audio graph (if not already)
+        let listener = self.listener_id();
+        (0..9).for_each(|i| self.connect(&listener, panner, i, i + 1));
+    }
+
+    /// Add the [`AudioListener`] to the audio graph (if not already), creating it if needed
+    ///
+    /// The listener is shared by all the panners, it is added right away when the first panner is
+    /// created in a [`Transaction`](super::Transaction).
     pub(crate) fn ensure_audio_listener_present(&self) {
+        self.without_transaction(|| self.add_audio_listener());
+    }
+
+    fn add_audio_listener(&self) {
+        drop(self.realize_listener());
+
         let mut queued_audio_listener_msgs = self.inner.queued_audio_listener_msgs.lock().unwrap();
         let mut released = false;
-        while let Some(message) = queued_audio_listener_msgs.pop() {
+        // release in order, the automation events of the params follow their registration
+        for message in queued_audio_listener_msgs.drain(..) {
             // add the AudioListenerRenderer to the graph
-            self.inner.render_channel.send(message).unwrap();
+            self.send_control_msg(message);
             released = true;
         }
 
         if released {
             // connect the AudioParamRenderers to the Listener
-            self.resolve_queued_control_msgs(LISTENER_NODE_ID);
-
-            // hack: Connect the listener to the destination node to force it to render at each
-            // quantum. Abuse the magical usize::MAX port so it acts as an AudioParam and has no side
-            // effects
-            self.connect(
-                &AudioNodeId(LISTENER_NODE_ID),
-                &AudioNodeId(DESTINATION_NODE_ID),
-                0,
-                usize::MAX,
-            );
+            let listener = self.listener_id();
+            self.resolve_queued_control_msgs(listener.0);
+
+            // Connect the listener to the destination node to force it to render at each
+            // quantum, without signal
+            let message = ControlMessage::ConnectNode {
+                from: listener.0,
+                to: self.destination_id().0,
+                output: 0,
+                kind: EdgeKind::KeepAlive,
+            };
+            self.send_control_msg(message);
         }
     }
 
@@ -0,0 +1,839 @@
+//! The `ManualAudioContext` type and its `RenderHandle`
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+
+use crate::context::{AudioContextState, BaseAudioContext, ConcreteBaseAudioContext};
+use crate::param::AudioParam;
+use crate::render::{
+    Dither, FrameConsumer, OutputSample, OutputTap, OverloadPolicy, RenderAhead, RenderThread,
+};
+use crate::{assert_valid_number_of_channels, assert_valid_sample_rate};
+
+/// An audio context without audio backend, rendered on demand by the host
+///
+/// The `ManualAudioContext` does not open an audio device. Instead, the render side is handed
+/// out as a [`RenderHandle`], to be called from the audio callback of the host: a native audio
+/// API, or the `process` method of an `AudioWorkletProcessor` in the browser (see the `wasm`
+/// feature).
+///
+/// ```
+/// use web_audio_api::context::{BaseAudioContext, ManualAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let (context, mut handle) = ManualAudioContext::new(2, 48_000.);
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&context.destination());
+/// osc.start();
+///
+/// // in the audio callback
+/// let mut output = vec![0.; 2 * 480];
+/// handle.render(&mut output);
+/// assert!(output.iter().any(|v| *v != 0.));
+/// ```
+// the naming follows the other contexts
+#[allow(clippy::module_name_repetitions)]
+pub struct ManualAudioContext {
+    /// represents the underlying `BaseAudioContext`
+    base: ConcreteBaseAudioContext,
+    /// gain applied to the output of the destination node
+    master_gain: AudioParam,
+}
+
+impl Drop for ManualAudioContext {
+    fn drop(&mut self) {
+        self.base.close();
+    }
+}
+
+impl BaseAudioContext for ManualAudioContext {
+    fn base(&self) -> &ConcreteBaseAudioContext {
+        &self.base
+    }
+}
+
+impl ManualAudioContext {
+    /// Creates a `ManualAudioContext` along with the [`RenderHandle`] producing its output
+    ///
+    /// # Arguments
+    ///
+    /// * `number_of_channels` - number of output channels, interleaved in the rendered frames
+    /// * `sample_rate` - output sample rate
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the number of channels is zero or greater than 32, or if the sample rate
+    /// is outside the supported range, see [`MIN_SAMPLE_RATE`](crate::MIN_SAMPLE_RATE)
+    #[must_use]
+    pub fn new(number_of_channels: usize, sample_rate: f32) -> (Self, RenderHandle) {
+        assert_valid_number_of_channels(number_of_channels);
+        assert_valid_sample_rate(sample_rate);
+
+        // communication channel to the render side
+        let (sender, receiver) = crossbeam_channel::unbounded();
+
+        // track number of frames - synced from render side to control side
+        let frames_played = Arc::new(AtomicU64::new(0));
+
+        let renderer = RenderThread::new(
+            sample_rate,
+            number_of_channels,
+            receiver,
+            frames_played.clone(),
+        );
+
+        let base = ConcreteBaseAudioContext::new(
+            sample_rate,
+            number_of_channels,
+            frames_played,
+            sender,
+            false,
+        );
+        // there is no dispatcher thread on wasm, the renderer runs the `ended` callbacks
+        #[cfg(feature = "wasm")]
+        let renderer = renderer.with_ended_events(base.ended_events().clone());
+
+        base.set_state(AudioContextState::Running);
+        let master_gain = base.master_gain();
+
+        let context = Self { base, master_gain };
+        let handle = RenderHandle {
+            renderer,
+            number_of_channels,
+            sample_rate,
+        };
+
+        (context, handle)
+    }
+
+    /// K-rate [`AudioParam`] scaling the output of the destination node (master volume)
+    ///
+    /// Setting the gain to `0.` silences all output without disconnecting any nodes.
+    #[must_use]
+    pub fn master_gain(&self) -> &AudioParam {
+        &self.master_gain
+    }
+
+    /// Set the degradations applied when rendering takes too long, `None` to disable them
+    ///
+    /// The load is not measured on wasm, the policy has no effect there.
+    pub fn set_overload_policy(&self, policy: Option<OverloadPolicy>) {
+        self.base.set_overload_policy(policy);
+    }
+
+    /// Register a callback receiving the interleaved output of each render quantum, see
+    /// [`AudioContext::set_output_tap`](crate::context::AudioContext::set_output_tap)
+    ///
+    /// The callback runs within [`RenderHandle::render`].
+    pub fn set_output_tap<F: FnMut(&[f32], usize) + Send + 'static>(&self, callback: F) {
+        let tap = OutputTap::new(self.base.max_channel_count(), callback);
+        self.base.set_output_tap(Some(tap));
+    }
+
+    /// Unregister the callback set with [`set_output_tap`](Self::set_output_tap)
+    pub fn clear_output_tap(&self) {
+        self.base.set_output_tap(None);
+    }
+
+    /// Suspends the progression of time in the audio context
+    ///
+    /// The next quantum rendered by the [`RenderHandle`] is processed in the `Suspended` state
+    /// (see [`RenderScope::state`](crate::render::RenderScope::state)), the following ones are
+    /// silent until the context is resumed.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the context is closed
+    pub fn suspend(&self) {
+        self.assert_not_closed();
+        self.base.set_state(AudioContextState::Suspended);
+    }
+
+    /// Resumes the progression of time in an audio context that has been suspended
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the context is closed
+    pub fn resume(&self) {
+        self.assert_not_closed();
+        self.base.set_state(AudioContextState::Running);
+    }
+
+    /// Closes the audio context
+    ///
+    /// The next quantum rendered by the [`RenderHandle`] is processed in the `Closed` state,
+    /// then all processors are finalized and freed. The handle only renders silence afterwards.
+    /// Dropping the `ManualAudioContext` closes it as well.
+    ///
+    /// The callbacks and the decodes are stopped as described for
+    /// [`AudioContext::close_sync`](crate::context::AudioContext::close_sync).
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the context is already closed
+    pub fn close(&self) {
+        self.assert_not_closed();
+        self.base.close();
+    }
+
+    fn assert_not_closed(&self) {
+        assert!(
+            self.base.state() != AudioContextState::Closed,
+            "InvalidStateError - context is closed"
+        );
+    }
+}
+
+/// Render side of a [`ManualAudioContext`]
+///
+/// The changes made to the context are applied at the start of each render quantum. While
+/// the context is suspended or closed, the handle renders silence.
+pub struct RenderHandle {
+    renderer: RenderThread,
+    number_of_channels: usize,
+    sample_rate: f32,
+}
+
+impl std::fmt::Debug for RenderHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RenderHandle")
+            .field("number_of_channels", &self.number_of_channels)
+            .field("sample_rate", &self.sample_rate)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RenderHandle {
+    /// Render the next frames into the `output` buffer, with the channels interleaved
+    ///
+    /// The length of the buffer does not need to be a multiple of the render quantum size: the
+    /// frames left over are returned by the next call.
+    pub fn render(&mut self, output: &mut [f32]) {
+        self.renderer.render_interleaved(output);
+    }
+
+    /// Render the next frames into the `output` buffer of the given sample format, with the
+    /// channels interleaved
+    ///
+    /// See [`render`](Self::render). The integer formats are dithered, unless disabled with
+    /// [`set_dither`](Self::set_dither).
+    ///
+    /// ```
+    /// use web_audio_api::context::{BaseAudioContext, ManualAudioContext};
+    /// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+    ///
+    /// let (context, mut handle) = ManualAudioContext::new(1, 48_000.);
+    ///
+    /// let src = context.create_constant_source();
+    /// src.offset().set_value(-1.);
+    /// src.connect(&context.destination());
+    /// src.start();
+    ///
+    /// // e.g. a device only accepting 16 bit integers
+    /// let mut output = [0_i16; 480];
+    /// handle.set_dither(false);
+    /// handle.render_samples(&mut output);
+    /// assert!(output.iter().all(|v| *v == i16::MIN));
+    /// ```
+    pub fn render_samples<S: OutputSample>(&mut self, output: &mut [S]) {
+        self.renderer.render_interleaved(output);
+    }
+
+    /// Enable or disable the dither applied to the integer formats, enabled by default
+    pub fn set_dither(&mut self, enabled: bool) {
+        self.renderer.set_dither(enabled);
+    }
+
+    /// Number of interleaved channels of the rendered frames
+    #[must_use]
+    pub fn number_of_channels(&self) -> usize {
+        self.number_of_channels
+    }
+
+    /// Sample rate of the rendered frames
+    #[must_use]
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Split the handle in a producer rendering `quanta` render quanta ahead into a lock-free
+    /// queue, and a consumer reading the frames from the queue
+    ///
+    /// The producer renders the graph by whole quanta, e.g. on a thread of its own, while the
+    /// consumer reads any number of frames per call from the audio callback of the host, without
+    /// ever rendering the graph. The producer keeps the queue filled with the frames of the
+    /// largest read on top of the quanta rendered ahead, `frames_per_callback` is the expected
+    /// number of frames per read.
+    ///
+    /// The time of the context follows the rendered frames: the
+    /// [`current_time`](BaseAudioContext::current_time) is ahead of the frames read by the
+    /// consumer by the [`queued_frames`](RenderAheadConsumer::queued_frames).
+    ///
+    /// ```
+    /// use web_audio_api::context::{BaseAudioContext, ManualAudioContext};
+    /// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+    ///
+    /// let (context, handle) = ManualAudioContext::new(1, 48_000.);
+    /// let (mut producer, mut consumer) = handle.render_ahead(2, 48);
+    ///
+    /// let osc = context.create_oscillator();
+    /// osc.connect(&context.destination());
+    /// osc.start();
+    ///
+    /// // e.g. on a render thread
+    /// assert_eq!(producer.fill(), 3);
+    ///
+    /// // in the audio callback, with a device period of 48 frames
+    /// let mut output = [0.; 48];
+    /// consumer.render(&mut output);
+    /// assert_eq!(consumer.underruns(), 0);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `quanta` is zero
+    #[must_use]
+    pub fn render_ahead(
+        mut self,
+        quanta: usize,
+        frames_per_callback: usize,
+    ) -> (RenderAheadProducer, RenderAheadConsumer) {
+        assert!(
+            quanta > 0,
+            "RangeError - the number of render quanta rendered ahead must be strictly positive"
+        );
+
+        let dither = self.renderer.take_dither();
+        let (render, queue) = RenderAhead::new(self.renderer, quanta, frames_per_callback);
+
+        let producer = RenderAheadProducer {
+            render,
+            number_of_channels: self.number_of_channels,
+            sample_rate: self.sample_rate,
+        };
+        let consumer = RenderAheadConsumer {
+            queue,
+            dither,
+            number_of_channels: self.number_of_channels,
+            underruns: 0,
+        };
+
+        (producer, consumer)
+    }
+}
+
+/// Render side of a [`ManualAudioContext`] rendering ahead into a queue, see
+/// [`RenderHandle::render_ahead`]
+pub struct RenderAheadProducer {
+    render: RenderAhead,
+    number_of_channels: usize,
+    sample_rate: f32,
+}
+
+impl std::fmt::Debug for RenderAheadProducer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RenderAheadProducer")
+            .field("number_of_channels", &self.number_of_channels)
+            .field("sample_rate", &self.sample_rate)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RenderAheadProducer {
+    /// Render quanta until the queue is filled ahead of the consumer, returns the number of
+    /// rendered quanta
+    ///
+    /// The changes made to the context are applied at the start of each render quantum.
+    pub fn fill(&mut self) -> usize {
+        self.render.fill()
+    }
+
+    /// True once the [`RenderAheadConsumer`] is dropped, the producer can stop
+    #[must_use]
+    pub fn is_closed(&self) -> bool {
+        self.render.is_closed()
+    }
+}
+
+/// Output side of a [`ManualAudioContext`] reading the frames of a [`RenderAheadProducer`], see
+/// [`RenderHandle::render_ahead`]
+pub struct RenderAheadConsumer {
+    queue: FrameConsumer,
+    dither: Dither,
+    number_of_channels: usize,
+    underruns: u64,
+}
+
+impl std::fmt::Debug for RenderAheadConsumer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RenderAheadConsumer")
+            .field("number_of_channels", &self.number_of_channels)
+            .field("underruns", &self.underruns)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RenderAheadConsumer {
+    /// Read the next frames into the `output` buffer, with the channels interleaved
+    ///
+    /// The length of the buffer does not need to be a multiple of the render quantum size. When
+    /// the queue holds less frames than the buffer, the remaining frames are silent and an
+    /// underrun is counted: the frames rendered afterwards are not skipped, they are read by the
+    /// next calls.
+    pub fn render(&mut self, output: &mut [f32]) {
+        self.render_samples(output);
+    }
+
+    /// Read the next frames into the `output` buffer of the given sample format, with the
+    /// channels interleaved
+    ///
+    /// See [`render`](Self::render). The integer formats are dithered, unless disabled with
+    /// [`set_dither`](Self::set_dither).
+    pub fn render_samples<S: OutputSample>(&mut self, output: &mut [S]) {
+        let number_of_frames = output.len() / self.number_of_channels;
+        if self.queue.pop(output, &mut self.dither) < number_of_frames {
+            self.underruns += 1;
+        }
+    }
+
+    /// Wake up the given thread, e.g. the thread running the producer, each time frames are read
+    pub fn set_waker(&mut self, thread: std::thread::Thread) {
+        self.queue.set_waker(thread);
+    }
+
+    /// Enable or disable the dither applied to the integer formats, enabled by default
+    pub fn set_dither(&mut self, enabled: bool) {
+        self.dither.set_enabled(enabled);
+    }
+
+    /// Number of frames rendered and not read yet
+    #[must_use]
+    pub fn queued_frames(&self) -> usize {
+        self.queue.queued_frames()
+    }
+
+    /// Number of calls which found less frames than requested in the queue
+    #[must_use]
+    pub fn underruns(&self) -> u64 {
+        self.underruns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::AudioBuffer;
+    use crate::context::OfflineAudioContext;
+    use crate::node::{AudioNode, AudioScheduledSourceNode, OscillatorType};
+    use crate::RENDER_QUANTUM_SIZE;
+
+    use float_eq::assert_float_eq;
+
+    #[test]
+    fn test_render_matches_offline() {
+        let length = RENDER_QUANTUM_SIZE * 8;
+
+        let mut offline = OfflineAudioContext::new(2, length, 44_100.);
+        let osc = offline.create_oscillator();
+        osc.connect(&offline.destination());
+        osc.start();
+        let reference = offline.start_rendering_sync();
+
+        let (context, mut handle) = ManualAudioContext::new(2, 44_100.);
+        let osc = context.create_oscillator();
+        osc.connect(&context.destination());
+        osc.start();
+
+        // pull buffers not aligned with the render quantum size
+        let mut output = vec![0.; length * 2];
+        output
+            .chunks_mut(2 * 100)
+            .for_each(|chunk| handle.render(chunk));
+
+        for c in 0..2 {
+            let channel: Vec<f32> = output.iter().skip(c).step_by(2).copied().collect();
+            assert_float_eq!(&channel[..], reference.get_channel_data(c), abs_all <= 0.);
+        }
+        assert_float_eq!(context.current_time(), length as f64 / 44_100., abs <= 0.);
+    }
+
+    /// Renders a sawtooth, so any gap or reordering of the frames shows in the output
+    fn render_ahead_reference(length: usize) -> AudioBuffer {
+        let mut offline = OfflineAudioContext::new(2, length, 44_100.);
+        let osc = offline.create_oscillator();
+        osc.set_type(OscillatorType::Sawtooth);
+        osc.connect(&offline.destination());
+        osc.start();
+        offline.start_rendering_sync()
+    }
+
+    fn render_ahead_context() -> (ManualAudioContext, RenderHandle) {
+        let (context, handle) = ManualAudioContext::new(2, 44_100.);
+        let osc = context.create_oscillator();
+        osc.set_type(OscillatorType::Sawtooth);
+        osc.connect(&context.destination());
+        osc.start();
+        (context, handle)
+    }
+
+    fn assert_interleaved_eq(output: &[f32], reference: &AudioBuffer) {
+        for c in 0..2 {
+            let channel: Vec<f32> = output.iter().skip(c).step_by(2).copied().collect();
+            assert_float_eq!(&channel[..], reference.get_channel_data(c), abs_all <= 0.);
+        }
+    }
+
+    #[test]
+    fn test_render_ahead_matches_offline() {
+        let length = RENDER_QUANTUM_SIZE * 40;
+        let reference = render_ahead_reference(length);
+
+        for pull in [48, 37, 480] {
+            let (context, handle) = render_ahead_context();
+            let (mut producer, mut consumer) = handle.render_ahead(3, pull);
+
+            let mut output = vec![0.; (length + pull) * 2];
+            let mut read = 0;
+            for chunk in output.chunks_mut(pull * 2) {
+                // the producer keeps up with the output
+                producer.fill();
+                consumer.render(chunk);
+                read += chunk.len() / 2;
+
+                // the time follows the rendered frames, not the frames read
+                let rendered = (context.current_time() * 44_100.).round() as usize;
+                assert_eq!(rendered, read + consumer.queued_frames());
+                assert_eq!(rendered % RENDER_QUANTUM_SIZE, 0);
+            }
+
+            output.truncate(length * 2);
+            assert_interleaved_eq(&output, &reference);
+            assert_eq!(consumer.underruns(), 0);
+        }
+    }
+
+    #[test]
+    fn test_render_ahead_threaded() {
+        let length = RENDER_QUANTUM_SIZE * 40;
+        let reference = render_ahead_reference(length);
+
+        let (_context, handle) = render_ahead_context();
+        let (mut producer, mut consumer) = handle.render_ahead(2, 37);
+
+        let render_thread = std::thread::spawn(move || {
+            while !producer.is_closed() {
+                producer.fill();
+                std::thread::park();
+            }
+        });
+        consumer.set_waker(render_thread.thread().clone());
+        // wake up the producer, which may have parked before the waker was set
+        render_thread.thread().unpark();
+
+        let pull = 37;
+        let mut output = vec![0.; (length + pull) * 2];
+        for chunk in output.chunks_mut(pull * 2) {
+            // a real-time callback would not wait, but underruns are not deterministic
+            while consumer.queued_frames() < chunk.len() / 2 {
+                std::thread::yield_now();
+            }
+            consumer.render(chunk);
+        }
+
+        drop(consumer);
+        render_thread.join().unwrap();
+
+        output.truncate(length * 2);
+        assert_interleaved_eq(&output, &reference);
+    }
+
+    #[test]
+    fn test_render_ahead_underrun() {
+        let length = RENDER_QUANTUM_SIZE * 6;
+        let reference = render_ahead_reference(length);
+
+        let (_context, handle) = render_ahead_context();
+        let (mut producer, mut consumer) = handle.render_ahead(2, 0);
+
+        // nothing rendered yet
+        let mut silence = [1.; 2 * 48];
+        consumer.render(&mut silence);
+        assert!(silence.iter().all(|v| *v == 0.));
+        assert_eq!(consumer.underruns(), 1);
+
+        // the queue runs dry in the middle of a pull larger than expected
+        producer.fill();
+        let queued = consumer.queued_frames();
+        assert_eq!(queued, 3 * RENDER_QUANTUM_SIZE);
+        let mut output = vec![0.; 2 * 500];
+        consumer.render(&mut output);
+        assert_eq!(consumer.underruns(), 2);
+        assert!(output[2 * queued..].iter().all(|v| *v == 0.));
+        output.truncate(2 * queued);
+
+        // the frames rendered afterwards are not skipped
+        producer.fill();
+        let mut rest = vec![0.; 2 * (length - queued)];
+        consumer.render(&mut rest);
+        output.extend_from_slice(&rest);
+
+        assert_interleaved_eq(&output, &reference);
+        assert_eq!(consumer.underruns(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "RangeError")]
+    fn test_render_ahead_zero_quanta() {
+        let (_context, handle) = ManualAudioContext::new(1, 44_100.);
+        let _ = handle.render_ahead(0, 128);
+    }
+
+    #[test]
+    fn test_handle_is_send() {
+        let (context, mut handle) = ManualAudioContext::new(1, 48_000.);
+        let src = context.create_constant_source();
+        src.connect(&context.destination());
+        src.start();
+
+        let output = std::thread::spawn(move || {
+            let mut output = [0.; RENDER_QUANTUM_SIZE];
+            handle.render(&mut output);
+            output
+        })
+        .join()
+        .unwrap();
+
+        assert_float_eq!(&output[..], &[1.; RENDER_QUANTUM_SIZE][..], abs_all <= 0.);
+        assert!(matches!(context.state(), AudioContextState::Running));
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Process(AudioContextState),
+        Finalize,
+    }
+
+    struct StateNode {
+        registration: crate::context::AudioContextRegistration,
+        channel_config: crate::node::ChannelConfig,
+    }
+
+    impl AudioNode for StateNode {
+        fn registration(&self) -> &crate::context::AudioContextRegistration {
+            &self.registration
+        }
+
+        fn channel_config(&self) -> &crate::node::ChannelConfig {
+            &self.channel_config
+        }
+
+        fn number_of_inputs(&self) -> usize {
+            0
+        }
+
+        fn number_of_outputs(&self) -> usize {
+            1
+        }
+    }
+
+    /// Records the state changes it sees, and its finalization
+    struct StateRenderer {
+        events: std::sync::Arc<std::sync::Mutex<Vec<Event>>>,
+    }
+
+    impl crate::render::AudioProcessor for StateRenderer {
+        fn process(
+            &mut self,
+            _inputs: &[crate::render::AudioRenderQuantum],
+            _outputs: &mut [crate::render::AudioRenderQuantum],
+            _params: crate::render::AudioParamValues,
+            scope: &crate::render::RenderScope,
+        ) -> bool {
+            let mut events = self.events.lock().unwrap();
+            if events.last() != Some(&Event::Process(scope.state())) {
+                events.push(Event::Process(scope.state()));
+            }
+            true
+        }
+
+        fn finalize(&mut self) {
+            self.events.lock().unwrap().push(Event::Finalize);
+        }
+    }
+
+    #[test]
+    fn test_state_transitions() {
+        let (context, mut handle) = ManualAudioContext::new(1, 48_000.);
+
+        let events = std::sync::Arc::default();
+        let recorded = std::sync::Arc::clone(&events);
+        let node = context.register(move |registration| {
+            let node = StateNode {
+                registration,
+                channel_config: crate::node::ChannelConfigOptions::default().into(),
+            };
+            (node, Box::new(StateRenderer { events: recorded }))
+        });
+        node.connect(&context.destination());
+
+        let mut output = [0.; RENDER_QUANTUM_SIZE];
+        handle.render(&mut output);
+
+        // one quantum is rendered in the suspended state, then time stops
+        context.suspend();
+        handle.render(&mut output);
+        handle.render(&mut output);
+        handle.render(&mut output);
+        assert_float_eq!(
+            context.current_time(),
+            2. * RENDER_QUANTUM_SIZE as f64 / 48_000.,
+            abs <= 0.
+        );
+
+        context.resume();
+        handle.render(&mut output);
+
+        context.close();
+        handle.render(&mut output);
+        handle.render(&mut output);
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                Event::Process(AudioContextState::Running),
+                Event::Process(AudioContextState::Suspended),
+                Event::Process(AudioContextState::Running),
+                Event::Process(AudioContextState::Closed),
+                Event::Finalize,
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError")]
+    fn test_resume_closed() {
+        let (context, _handle) = ManualAudioContext::new(1, 48_000.);
+        context.close();
+        context.resume();
+    }
+
+    #[test]
+    fn test_render_i16_scaling() {
+        let values = [1., -1., 0.5, -0.5, 0., 2., -2.];
+        let render = |dither: bool| {
+            let (context, mut handle) = ManualAudioContext::new(1, 48_000.);
+            let src = context.create_constant_source();
+            values.iter().enumerate().for_each(|(i, v)| {
+                let when = (i * RENDER_QUANTUM_SIZE) as f64 / 48_000.;
+                src.offset().set_value_at_time(*v, when);
+            });
+            src.connect(&context.destination());
+            src.start();
+
+            handle.set_dither(dither);
+            let mut output = vec![0_i16; RENDER_QUANTUM_SIZE * values.len()];
+            handle.render_samples(&mut output);
+            output
+        };
+
+        let expected = [32767, -32768, 16384, -16384, 0, 32767, -32768];
+
+        let output = render(false);
+        for (chunk, expected) in output.chunks(RENDER_QUANTUM_SIZE).zip(expected.iter()) {
+            assert!(chunk.iter().all(|v| v == expected));
+        }
+
+        // the dither moves the values by one step at most, full scale is still clamped
+        let output = render(true);
+        for (chunk, expected) in output.chunks(RENDER_QUANTUM_SIZE).zip(expected.iter()) {
+            assert!(chunk
+                .iter()
+                .all(|v| (i32::from(*v) - i32::from(*expected)).abs() <= 1));
+        }
+        assert!(output[..RENDER_QUANTUM_SIZE].iter().all(|v| *v == 32767));
+    }
+
+    #[test]
+    fn test_render_u16() {
+        let (context, mut handle) = ManualAudioContext::new(2, 48_000.);
+        let merger = context.create_channel_merger(2);
+        merger.connect(&context.destination());
+        for (input, value) in [-1., 1.].iter().enumerate() {
+            let src = context.create_constant_source();
+            src.offset().set_value(*value);
+            src.connect_at(&merger, 0, input);
+            src.start();
+        }
+
+        handle.set_dither(false);
+        let mut output = vec![1_u16; 2 * RENDER_QUANTUM_SIZE];
+        handle.render_samples(&mut output);
+        assert!(output.chunks(2).all(|frame| frame == [0, 65535]));
+    }
+
+    #[test]
+    fn test_dither_error_independent_of_signal() {
+        let length = 48_000;
+
+        // the quantization error of a constant level, in steps of the output format
+        let errors = |level: f32, dither: bool| {
+            let (context, mut handle) = ManualAudioContext::new(1, 48_000.);
+            let src = context.create_constant_source();
+            src.offset().set_value(level / 32768.);
+            src.connect(&context.destination());
+            src.start();
+
+            handle.set_dither(dither);
+            let mut output = vec![0_i16; length];
+            handle.render_samples(&mut output);
+            output
+                .iter()
+                .map(|v| f32::from(*v) - level)
+                .collect::<Vec<_>>()
+        };
+        let mean = |values: &[f32]| values.iter().sum::<f32>() / values.len() as f32;
+
+        for &level in [0., 0.25, 0.5, 0.75, 10.3].iter() {
+            // without dither, the error is a function of the level
+            let error = errors(level, false);
+            assert_float_eq!(mean(&error), level.round() - level, abs <= 1e-3);
+
+            // with TPDF dither, the error has zero mean, and a variance of 1/12 + 1/6
+            let error = errors(level, true);
+            let error_mean = mean(&error);
+            let variance =
+                error.iter().map(|e| (e - error_mean).powi(2)).sum::<f32>() / error.len() as f32;
+            assert_float_eq!(error_mean, 0., abs <= 0.02);
+            assert_float_eq!(variance, 0.25, abs <= 0.02);
+        }
+
+        // the error of a quiet sine is not correlated with the sine
+        let render_sine = |output: &mut dyn FnMut(&mut RenderHandle)| {
+            let (context, mut handle) = ManualAudioContext::new(1, 48_000.);
+            let gain = context.create_gain();
+            gain.gain().set_value(2.5 / 32768.);
+            gain.connect(&context.destination());
+            let osc = context.create_oscillator();
+            osc.frequency().set_value(1_000.);
+            osc.connect(&gain);
+            osc.start();
+            output(&mut handle);
+        };
+
+        let mut signal = vec![0_f32; length];
+        render_sine(&mut |handle| handle.render(&mut signal));
+        let mut output = vec![0_i16; length];
+        render_sine(&mut |handle| handle.render_samples(&mut output));
+
+        let signal: Vec<f32> = signal.iter().map(|v| v * 32768.).collect();
+        let error: Vec<f32> = output
+            .iter()
+            .zip(signal.iter())
+            .map(|(o, s)| f32::from(*o) - s)
+            .collect();
+        let dot = |a: &[f32], b: &[f32]| a.iter().zip(b).map(|(a, b)| a * b).sum::<f32>();
+        let correlation =
+            dot(&error, &signal) / (dot(&error, &error) * dot(&signal, &signal)).sqrt();
+        assert!(correlation.abs() < 0.02);
+    }
+}
@@ -1,48 +1,112 @@
-//! The `BaseAudioContext` interface and the `AudioContext` and `OfflineAudioContext` types
-use std::ops::Range;
-
+//! The `BaseAudioContext` interface and the `AudioContext`, `ManualAudioContext` and
+//! `OfflineAudioContext` types
 mod base;
 pub use base::*;
 
 mod concrete_base;
 pub use concrete_base::*;
 
+mod manual;
+pub use manual::*;
+
 mod offline;
 pub use offline::*;
 
+#[cfg(feature = "cpal")]
 mod online;
+#[cfg(feature = "cpal")]
 pub use online::*;
 
+mod transaction;
+pub use transaction::*;
+
 use crate::render::NodeIndex;
 
-// magic node values
-/// Destination node id is always at index 0
-const DESTINATION_NODE_ID: u64 = 0;
-/// listener node id is always at index 1
-const LISTENER_NODE_ID: u64 = 1;
-/// listener audio parameters ids are always at index 2 through 10
-const LISTENER_PARAM_IDS: Range<u64> = 2..11;
+/// id of the mock registrations, which are never part of an audio graph
+#[cfg(test)]
+pub(crate) const MOCK_NODE_ID: AudioNodeId = AudioNodeId(u64::MAX);
+
+/// Kinds of nodes that are created by the context itself
+///
+/// The destination and its master gain are created on construction, the `AudioListener` and its
+/// params on first use.
+///
+/// These are registered with [`ConcreteBaseAudioContext::register_builtin`] so the context can
+/// keep track of the ids that were assigned to them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum BuiltinNode {
+    /// The `AudioDestinationNode`
+    Destination,
+    /// The master gain `AudioParam` of the `AudioDestinationNode`
+    MasterGain,
+    /// The node wrapping the `AudioListener`
+    Listener,
+    /// One of the 9 `AudioParam`s of the `AudioListener`
+    ListenerParam,
+}
+
+/// Ids assigned to the [`BuiltinNode`]s of a context
+#[derive(Debug, Default, Clone)]
+struct BuiltinNodeIds {
+    /// id of the `AudioDestinationNode`
+    destination: Option<u64>,
+    /// id of the master gain `AudioParam`
+    master_gain: Option<u64>,
+    /// id of the `AudioListener` node
+    listener: Option<u64>,
+    /// ids of the `AudioListener` params, in order of registration
+    listener_params: Vec<u64>,
+}
+
+impl BuiltinNodeIds {
+    /// Returns true if the node with the given id was registered as a built-in node
+    fn contains(&self, id: u64) -> bool {
+        self.destination == Some(id)
+            || self.master_gain == Some(id)
+            || self.listener == Some(id)
+            || self.listener_params.contains(&id)
+    }
+}
 
 /// Unique identifier for audio nodes.
 ///
-/// Used for internal bookkeeping.
-#[derive(Debug)]
-pub(crate) struct AudioNodeId(u64);
+/// Used for internal bookkeeping. It can be retrieved from the node registration, to look up a
+/// node with [`BaseAudioContext::node_by_id`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AudioNodeId(u64);
 
 /// Unique identifier for audio params.
 ///
-/// Store these in your `AudioProcessor` to get access to `AudioParam` values.
-pub struct AudioParamId(u64);
+/// Store these in your `AudioProcessor` to get access to `AudioParam` values. They are
+/// returned by [`BaseAudioContext::create_audio_param`], along with the `AudioParam`, and
+/// cannot be cloned nor constructed otherwise.
+///
+/// In debug builds, dropping the identifier inside the `register` closure instead of moving it
+/// into the processor panics, see [`BaseAudioContext::register`].
+#[must_use = "move the AudioParamId into the AudioProcessor of the node"]
+pub struct AudioParamId {
+    id: u64,
+    /// witness of the identifier being held by the processor
+    #[cfg(debug_assertions)]
+    _held: std::sync::Arc<()>,
+}
 
 // bit contrived, but for type safety only the context mod can access the inner u64
 impl From<&AudioParamId> for NodeIndex {
     fn from(i: &AudioParamId) -> Self {
+        Self(i.id)
+    }
+}
+
+#[cfg(test)]
+impl From<&AudioNodeId> for NodeIndex {
+    fn from(i: &AudioNodeId) -> Self {
         Self(i.0)
     }
 }
 
 /// Describes the current state of the `AudioContext`
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum AudioContextState {
     /// This context is currently suspended (context time is not proceeding,
     /// audio hardware may be powered down/released).
@@ -69,7 +133,9 @@ impl From<u8> for AudioContextState {
 ///
 /// This allows for communication with the render thread and lifetime management.
 ///
-/// The only way to construct this object is by calling [`BaseAudioContext::register`]
+/// The only way to construct this object is by calling [`BaseAudioContext::register`]. It
+/// cannot be cloned: the node owns the registration it was given, and dropping it releases the
+/// processor of the node, see the lifecycle contract of `register`.
 pub struct AudioContextRegistration {
     /// the audio context in wich nodes and connections lives
     context: ConcreteBaseAudioContext,
@@ -82,7 +148,7 @@ impl AudioContextRegistration {
     // false positive: AudioContextRegistration is not const
     #[allow(clippy::missing_const_for_fn, clippy::unused_self)]
     #[must_use]
-    pub(crate) fn id(&self) -> &AudioNodeId {
+    pub fn id(&self) -> &AudioNodeId {
         &self.id
     }
 
@@ -104,7 +170,9 @@ impl Drop for AudioContextRegistration {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::node::AudioNode;
+    use crate::media::DecodeError;
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+    use crate::{AudioBuffer, RENDER_QUANTUM_SIZE};
 
     use float_eq::assert_float_eq;
 
@@ -127,6 +195,30 @@ mod tests {
         assert_eq!(context.length(), 48000);
     }
 
+    #[test]
+    fn test_frames_seconds_conversion() {
+        let context = OfflineAudioContext::new(1, 0, 44100.);
+
+        assert_eq!(context.seconds_to_frames(1.), 44100);
+        assert_float_eq!(context.frames_to_seconds(22050), 0.5, abs <= 0.);
+        assert_float_eq!(
+            context.frames_to_seconds(context.seconds_to_frames(1.)),
+            1.,
+            abs <= 1e-9
+        );
+
+        assert_eq!(context.seconds_to_quanta(1.), 345); // 344.53
+        assert_float_eq!(
+            context.quanta_to_seconds(1),
+            RENDER_QUANTUM_SIZE as f64 / 44100.,
+            abs <= 0.
+        );
+        let quantum = context.quanta_to_seconds(1);
+        assert_eq!(context.seconds_to_quanta(10. * quantum), 10);
+
+        assert_eq!(context.seconds_to_frames(-1.), 0);
+    }
+
     #[test]
     fn test_decode_audio_data() {
         let context = OfflineAudioContext::new(1, 0, 44100.);
@@ -144,6 +236,303 @@ mod tests {
         assert!(left_start != right_start);
     }
 
+    /// Minimal executor, parks the current thread until the future is woken
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake};
+        use std::thread::Thread;
+
+        struct ThreadWaker(Thread);
+
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let waker = Arc::new(ThreadWaker(std::thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_audio_data_async() {
+        let context = OfflineAudioContext::new(1, 0, 44100.);
+        let file = std::fs::File::open("samples/sample.wav").unwrap();
+        let audio_buffer = block_on(context.decode_audio_data(file)).unwrap();
+
+        let file = std::fs::File::open("samples/sample.wav").unwrap();
+        let expected = context.decode_audio_data_sync(file).unwrap();
+
+        assert_eq!(audio_buffer.length(), expected.length());
+        assert_eq!(audio_buffer.number_of_channels(), 2);
+        assert_float_eq!(
+            audio_buffer.get_channel_data(0),
+            expected.get_channel_data(0),
+            abs_all <= 0.
+        );
+
+        let file = std::fs::File::open("samples/corrupt.wav").unwrap();
+        assert!(block_on(context.decode_audio_data(file)).is_err());
+    }
+
+    /// In-memory 16 bit mono WAV file with the given number of frames
+    fn generate_wav(number_of_frames: usize, sample_rate: u32) -> Vec<u8> {
+        let data_len = number_of_frames as u32 * 2;
+        let mut wav = Vec::with_capacity(44 + data_len as usize);
+
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16_u32.to_le_bytes()); // fmt chunk size
+        wav.extend_from_slice(&1_u16.to_le_bytes()); // PCM
+        wav.extend_from_slice(&1_u16.to_le_bytes()); // mono
+        wav.extend_from_slice(&sample_rate.to_le_bytes());
+        wav.extend_from_slice(&(sample_rate * 2).to_le_bytes()); // byte rate
+        wav.extend_from_slice(&2_u16.to_le_bytes()); // block align
+        wav.extend_from_slice(&16_u16.to_le_bytes()); // bits per sample
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+
+        (0..number_of_frames).for_each(|i| {
+            let value = ((i % 100) as i16 - 50) * 100;
+            wav.extend_from_slice(&value.to_le_bytes());
+        });
+
+        wav
+    }
+
+    #[test]
+    fn test_decode_audio_data_with_progress() {
+        let context = OfflineAudioContext::new(1, 0, 44100.);
+        let number_of_frames = 44100 * 20;
+        let input = std::io::Cursor::new(generate_wav(number_of_frames, 44100));
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let handle = context.decode_audio_data_with_progress(input, move |progress| {
+            sender.send(progress).unwrap();
+        });
+
+        let audio_buffer = block_on(handle).unwrap();
+        assert_eq!(audio_buffer.length(), number_of_frames);
+
+        let progress: Vec<f64> = receiver.try_iter().collect();
+        assert!(progress.len() > 2);
+        assert!(progress.windows(2).all(|w| w[0] <= w[1]));
+        assert_float_eq!(*progress.last().unwrap(), 1., abs <= 0.);
+    }
+
+    #[test]
+    fn test_decode_audio_data_cancel() {
+        let context = OfflineAudioContext::new(1, 0, 44100.);
+        let input = std::io::Cursor::new(generate_wav(44100 * 20, 44100));
+
+        // block the decoder thread on its first progress report until the handle is cancelled
+        let (progress_sender, progress_receiver) = crossbeam_channel::unbounded();
+        let (resume_sender, resume_receiver) = crossbeam_channel::unbounded::<()>();
+        let handle = context.decode_audio_data_with_progress(input, move |progress| {
+            progress_sender.send(progress).unwrap();
+            let _ = resume_receiver.recv();
+        });
+
+        let first_progress = progress_receiver.recv().unwrap();
+        assert!(first_progress < 0.1);
+
+        handle.cancel();
+        let cancelled_at = std::time::Instant::now();
+        drop(resume_sender);
+
+        let result = block_on(handle);
+        assert!(cancelled_at.elapsed() < std::time::Duration::from_secs(1));
+
+        assert!(matches!(result, Err(DecodeError::Cancelled)));
+    }
+
+    #[test]
+    fn test_decode_audio_data_close() {
+        let (context, _handle) = ManualAudioContext::new(1, 44100.);
+        let input = std::io::Cursor::new(generate_wav(44100 * 20, 44100));
+
+        // block the decoder thread on its first progress report until the context is closed
+        let (progress_sender, progress_receiver) = crossbeam_channel::unbounded();
+        let (resume_sender, resume_receiver) = crossbeam_channel::unbounded::<()>();
+        let handle = context.decode_audio_data_with_progress(input, move |progress| {
+            progress_sender.send(progress).unwrap();
+            let _ = resume_receiver.recv();
+        });
+        progress_receiver.recv().unwrap();
+
+        // the future resolves while the decoder thread is still blocked
+        context.close();
+        assert!(matches!(block_on(handle), Err(DecodeError::Closed)));
+
+        // the decoder thread stops without reporting progress anymore
+        drop(resume_sender);
+        assert_eq!(progress_receiver.iter().count(), 0);
+
+        // the decodes started afterwards fail right away
+        let input = std::io::Cursor::new(generate_wav(100, 44100));
+        let result = block_on(context.decode_audio_data(input));
+        assert!(matches!(result, Err(DecodeError::Closed)));
+
+        let input = std::io::Cursor::new(generate_wav(100, 44100));
+        let result = context.decode_audio_data_sync(input);
+        assert!(matches!(result, Err(DecodeError::Closed)));
+    }
+
+    #[test]
+    fn test_drop_context_mid_flight() {
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::time::{Duration, Instant};
+
+        let sample_rate = 48_000;
+        let (context, mut handle) = ManualAudioContext::new(2, sample_rate as f32);
+        let wav = generate_wav(sample_rate as usize / 10, sample_rate);
+        let done = Arc::new(AtomicBool::new(false));
+        let ended = Arc::new(AtomicUsize::new(0));
+
+        // the render side outlives the context
+        let renderer = {
+            let done = Arc::clone(&done);
+            std::thread::spawn(move || {
+                let mut output = vec![0.; 2 * RENDER_QUANTUM_SIZE];
+                while !done.load(Ordering::SeqCst) {
+                    handle.render(&mut output);
+                }
+            })
+        };
+
+        // sources firing `ended` events, and decodes, before and after the context is dropped
+        let producers: Vec<_> = (0..4)
+            .map(|_| {
+                let base = context.base().clone();
+                let done = Arc::clone(&done);
+                let ended = Arc::clone(&ended);
+                let wav = wav.clone();
+                std::thread::spawn(move || {
+                    let mut decodes = vec![];
+                    while !done.load(Ordering::SeqCst) {
+                        let osc = base.create_oscillator();
+                        osc.connect(&base.destination());
+                        let ended = Arc::clone(&ended);
+                        osc.set_onended(move |_| {
+                            ended.fetch_add(1, Ordering::SeqCst);
+                        });
+                        osc.start();
+                        osc.stop_at(base.current_time() + 0.001);
+
+                        let input = std::io::Cursor::new(wav.clone());
+                        decodes.push(base.decode_audio_data(input));
+                        std::thread::sleep(Duration::from_millis(1));
+                    }
+                    decodes
+                })
+            })
+            .collect();
+
+        // drop the context once the events are flowing
+        let started = Instant::now();
+        while ended.load(Ordering::SeqCst) == 0 {
+            assert!(started.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let dropped_at = Instant::now();
+        drop(context);
+
+        std::thread::sleep(Duration::from_millis(50));
+        done.store(true, Ordering::SeqCst);
+        let decodes: Vec<_> = producers
+            .into_iter()
+            .flat_map(|producer| producer.join().unwrap())
+            .collect();
+        renderer.join().unwrap();
+
+        // the decodes completed before the drop are intact, the others are closed
+        let mut closed = 0;
+        for decode in decodes {
+            match block_on(decode) {
+                Ok(buffer) => assert_eq!(buffer.length(), sample_rate as usize / 10),
+                Err(DecodeError::Closed) => closed += 1,
+                Err(e) => panic!("unexpected decode error: {}", e),
+            }
+        }
+        assert!(closed > 0);
+        assert!(dropped_at.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_decode_audio_data_errors() {
+        let context = OfflineAudioContext::new(1, 0, 44100.);
+
+        // unknown magic bytes
+        let input = std::io::Cursor::new(b"NOPE, not an audio file at all".to_vec());
+        let result = context.decode_audio_data_sync(input);
+        assert!(matches!(
+            result,
+            Err(DecodeError::UnsupportedFormat { detected: None })
+        ));
+
+        // known but unsupported container
+        let mut aiff = b"FORM\0\0\0\x20AIFFCOMM".to_vec();
+        aiff.resize(64, 0);
+        let result = context.decode_audio_data_sync(std::io::Cursor::new(aiff));
+        match result {
+            Err(DecodeError::UnsupportedFormat { detected }) => {
+                assert_eq!(detected.as_deref(), Some("aiff"))
+            }
+            _ => panic!("expected an unsupported format error"),
+        }
+
+        // valid header, data chunk cut short
+        let mut wav = generate_wav(44100, 44100);
+        wav.truncate(44 + 1000);
+        let result = context.decode_audio_data_sync(std::io::Cursor::new(wav));
+        match result {
+            Err(DecodeError::Corrupt { at_byte, .. }) => assert_eq!(at_byte, Some(44 + 1000)),
+            _ => panic!("expected a corrupt data error"),
+        }
+
+        // zero bytes
+        let result = context.decode_audio_data_sync(std::io::Cursor::new(vec![]));
+        assert!(matches!(result, Err(DecodeError::EmptyStream)));
+
+        // same errors from the async decoding
+        let result = block_on(context.decode_audio_data(std::io::Cursor::new(vec![])));
+        assert!(matches!(result, Err(DecodeError::EmptyStream)));
+    }
+
+    #[test]
+    fn test_decode_error_source() {
+        use std::error::Error;
+
+        struct FailingInput;
+
+        impl std::io::Read for FailingInput {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::PermissionDenied,
+                    "no access",
+                ))
+            }
+        }
+
+        let context = OfflineAudioContext::new(1, 0, 44100.);
+        let error = context.decode_audio_data_sync(FailingInput).unwrap_err();
+        assert!(matches!(error, DecodeError::Io(_)), "{:?}", error);
+
+        let source = error.source().unwrap();
+        let io_error = source.downcast_ref::<std::io::Error>().unwrap();
+        assert_eq!(io_error.kind(), std::io::ErrorKind::PermissionDenied);
+    }
+
     // #[test]
     // disabled: symphonia cannot handle empty WAV-files
     #[allow(dead_code)]
@@ -181,4 +570,449 @@ mod tests {
         let dest = context.destination();
         assert!(dest.context() == context.base());
     }
+
+    #[test]
+    fn test_destination_is_never_freed() {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44100.);
+
+        // dropping all handles to the destination should not remove it from the graph
+        let id = context.destination().registration().id().0;
+        assert!(context.base().is_builtin_node(id));
+
+        let src = context.create_constant_source();
+        src.connect(&context.destination());
+        src.start();
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &[1.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
+
+    fn render_panned_source(with_dummy_builtin: bool) -> AudioBuffer {
+        let mut context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 44100.);
+
+        if with_dummy_builtin {
+            let dummy =
+                context
+                    .base()
+                    .register_builtin(BuiltinNode::ListenerParam, |registration| {
+                        let (node, proc) = crate::param::audio_param_pair(
+                            crate::spatial::PARAM_OPTS,
+                            registration,
+                        );
+                        (node, Box::new(proc))
+                    });
+            let id = dummy.registration().id().0;
+            drop(dummy);
+            assert!(context.base().is_builtin_node(id));
+        }
+
+        let panner = context.create_panner();
+        panner.connect(&context.destination());
+        panner.position_x().set_value(1.);
+
+        let src = context.create_constant_source();
+        src.connect(&panner);
+        src.start();
+
+        context.start_rendering_sync()
+    }
+
+    #[test]
+    fn test_register_builtin_keeps_listener_wiring() {
+        let reference = render_panned_source(false);
+        let output = render_panned_source(true);
+
+        // source is positioned on the right of the listener
+        assert!(reference.get_channel_data(1)[0] > reference.get_channel_data(0)[0]);
+
+        assert_float_eq!(
+            output.get_channel_data(0),
+            reference.get_channel_data(0),
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            output.get_channel_data(1),
+            reference.get_channel_data(1),
+            abs_all <= 0.
+        );
+    }
+
+    // number of nodes added to the render graph by the given control messages
+    fn count_registered_nodes(
+        receiver: &crossbeam_channel::Receiver<crate::message::ControlMessage>,
+    ) -> usize {
+        receiver
+            .try_iter()
+            .filter(|m| matches!(m, crate::message::ControlMessage::RegisterNode { .. }))
+            .count()
+    }
+
+    #[test]
+    fn test_builtin_nodes_kept_on_construction() {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let frames_played = std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let base = ConcreteBaseAudioContext::new(44_100., 1, frames_played, sender, true);
+
+        // the handles of the destination and its master gain are dropped on construction, they
+        // are recognized by the ids recorded on registration
+        let registered: Vec<u64> = receiver
+            .try_iter()
+            .filter_map(|m| match m {
+                crate::message::ControlMessage::RegisterNode { id, .. } => Some(id),
+                crate::message::ControlMessage::FreeWhenFinished { id } => {
+                    panic!("built-in node {} released on construction", id)
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(registered.len(), 2);
+        assert!(registered.iter().all(|id| base.is_builtin_node(*id)));
+        assert!(registered.contains(&base.destination().registration().id().0));
+    }
+
+    #[test]
+    fn test_listener_is_created_lazily() {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let frames_played = std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let base = ConcreteBaseAudioContext::new(44_100., 1, frames_played, sender, true);
+
+        // the destination and its master gain only
+        assert_eq!(count_registered_nodes(&receiver), 2);
+
+        // accessing the listener creates it, but it stays out of the graph without panners
+        let listener = base.listener();
+        listener.position_x().set_value(1.);
+        assert_eq!(count_registered_nodes(&receiver), 0);
+
+        // the panner, its 6 params, the listener and its 9 params
+        let _panner = base.create_panner();
+        assert_eq!(count_registered_nodes(&receiver), 1 + 6 + 1 + 9);
+
+        // the listener is only added once
+        let _panner = base.create_panner();
+        assert_eq!(count_registered_nodes(&receiver), 1 + 6);
+    }
+
+    fn render_listener_automation(automate_before_panner: bool) -> AudioBuffer {
+        let mut context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE * 2, 44_100.);
+
+        let automate = |context: &OfflineAudioContext| {
+            let listener = context.listener();
+            listener.position_x().set_value_at_time(1., 0.);
+            listener
+                .position_x()
+                .set_value_at_time(-1., RENDER_QUANTUM_SIZE as f64 / 44_100.);
+        };
+
+        if automate_before_panner {
+            automate(&context);
+        }
+
+        let panner = context.create_panner();
+        panner.connect(&context.destination());
+
+        if !automate_before_panner {
+            automate(&context);
+        }
+
+        let src = context.create_constant_source();
+        src.connect(&panner);
+        src.start();
+
+        context.start_rendering_sync()
+    }
+
+    #[test]
+    fn test_listener_automation_before_panner() {
+        let reference = render_listener_automation(false);
+        let output = render_listener_automation(true);
+
+        // the listener moves from the right of the source to its left
+        let (left, right) = (reference.get_channel_data(0), reference.get_channel_data(1));
+        assert!(left[0] > right[0]);
+        assert!(left[RENDER_QUANTUM_SIZE] < right[RENDER_QUANTUM_SIZE]);
+
+        assert_float_eq!(output.get_channel_data(0), left, abs_all <= 0.);
+        assert_float_eq!(output.get_channel_data(1), right, abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_node_by_id() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+        let gain = context.create_gain();
+        let gain_id = *gain.registration().id();
+        let mirror = context.node_by_id(gain_id).unwrap();
+        assert_eq!(mirror.id(), gain_id);
+        assert_eq!(mirror.type_name(), "GainNode");
+        assert_eq!(mirror.params().len(), 1);
+        assert_float_eq!(mirror.params()[0].default_value(), 1., abs <= 0.);
+
+        let biquad = context.create_biquad_filter();
+        let mirror = context.node_by_id(*biquad.registration().id()).unwrap();
+        assert_eq!(mirror.type_name(), "BiquadFilterNode");
+        assert_eq!(mirror.params().len(), 4);
+        assert_eq!(mirror.channel_count(), biquad.channel_count());
+
+        drop(gain);
+        assert!(context.node_by_id(gain_id).is_none());
+    }
+
+    #[test]
+    fn test_node_labels() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+        let gain = context.create_gain();
+        let osc = context.create_oscillator();
+        let delay = context.create_delay(1.);
+        assert_eq!(gain.label(), "GainNode");
+        assert_eq!(osc.label(), "OscillatorNode");
+        assert_eq!(delay.label(), "DelayNode");
+
+        gain.set_label("master bus");
+        delay.set_label(String::from("echo"));
+        assert_eq!(gain.label(), "master bus");
+        assert_eq!(delay.label(), "echo");
+        assert_eq!(osc.label(), "OscillatorNode");
+
+        let mirror = context.node_by_id(*gain.registration().id()).unwrap();
+        assert_eq!(mirror.label(), "master bus");
+        assert_eq!(mirror.type_name(), "GainNode");
+        let mirror = context.node_by_id(*osc.registration().id()).unwrap();
+        assert_eq!(mirror.label(), "OscillatorNode");
+
+        // labels of built-in nodes are shared by all their handles
+        context.destination().set_label("speakers");
+        assert_eq!(context.destination().label(), "speakers");
+    }
+
+    #[test]
+    fn test_node_label_in_panic_messages() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let gain = context.create_gain();
+        gain.set_label("master bus");
+        let osc = context.create_oscillator();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            osc.connect_at(&gain, 0, 1);
+        }));
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("master bus"), "{}", message);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            osc.connect_at(&gain, 1, 0);
+        }));
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("OscillatorNode"), "{}", message);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            gain.set_channel_count(0);
+        }));
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.starts_with("NotSupportedError"), "{}", message);
+        assert!(message.contains("master bus"), "{}", message);
+        assert_eq!(gain.channel_count(), 2);
+    }
+
+    // play a constant source, optionally automated
+    fn play_constant_source(context: &impl BaseAudioContext, automate: bool) {
+        let src = context.create_constant_source();
+        src.connect(&context.destination());
+        src.start_at(0.);
+
+        if automate {
+            src.offset().set_value_at_time(0., 0.);
+            src.offset().linear_ramp_to_value_at_time(1., 0.002);
+            src.offset().set_target_at_time(0.25, 0.003, 0.001);
+        }
+    }
+
+    // render a constant source in an offline context
+    fn render_constant_source(automate: bool) -> AudioBuffer {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 2, 44_100.);
+        play_constant_source(&context, automate);
+        context.start_rendering_sync()
+    }
+
+    #[test]
+    fn test_record_and_replay_audio_param_events() {
+        use crate::message::ControlMessage;
+
+        // record the control messages of the automated source
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let frames_played = std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let base = ConcreteBaseAudioContext::new(44_100., 1, frames_played, sender, true);
+        play_constant_source(&base, true);
+        let recorded: Vec<_> = receiver.try_iter().collect();
+
+        let events: Vec<_> = recorded
+            .iter()
+            .filter(|m| matches!(m, ControlMessage::AudioParamEvent { .. }))
+            .map(|m| format!("{:?}", m))
+            .collect();
+        // the initial offset value and the 3 automation events
+        assert_eq!(events.len(), 4);
+        assert!(events[1].contains("SetValueAtTime"));
+
+        // replay the recorded stream as is into a fresh render thread
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        recorded.into_iter().for_each(|m| sender.send(m).unwrap());
+        let mut render = crate::render::RenderThread::new(
+            44_100.,
+            1,
+            receiver,
+            std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0)),
+        );
+        let replayed = render.render_audiobuffer(RENDER_QUANTUM_SIZE * 2);
+
+        let live = render_constant_source(true);
+        assert!(live.get_channel_data(0) != render_constant_source(false).get_channel_data(0));
+        assert_float_eq!(
+            replayed.get_channel_data(0),
+            live.get_channel_data(0),
+            abs_all <= 0.
+        );
+    }
+
+    /// Source node emitting the values of its param
+    struct ParamNode {
+        registration: AudioContextRegistration,
+        channel_config: crate::node::ChannelConfig,
+        value: crate::AudioParam,
+    }
+
+    impl AudioNode for ParamNode {
+        fn registration(&self) -> &AudioContextRegistration {
+            &self.registration
+        }
+
+        fn channel_config(&self) -> &crate::node::ChannelConfig {
+            &self.channel_config
+        }
+
+        fn number_of_inputs(&self) -> usize {
+            0
+        }
+
+        fn number_of_outputs(&self) -> usize {
+            1
+        }
+    }
+
+    struct ParamRenderer {
+        value: AudioParamId,
+    }
+
+    impl crate::render::AudioProcessor for ParamRenderer {
+        fn process(
+            &mut self,
+            _inputs: &[crate::render::AudioRenderQuantum],
+            outputs: &mut [crate::render::AudioRenderQuantum],
+            params: crate::render::AudioParamValues,
+            _scope: &crate::render::RenderScope,
+        ) -> bool {
+            let values = params.get(&self.value);
+            let output = outputs[0].channel_data_mut(0);
+            output
+                .iter_mut()
+                .zip(values.iter().cycle())
+                .for_each(|(o, v)| *o = *v);
+            true
+        }
+    }
+
+    fn param_opts() -> crate::AudioParamDescriptor {
+        crate::AudioParamDescriptor {
+            min_value: -1.,
+            max_value: 1.,
+            default_value: 0.5,
+            automation_rate: crate::AutomationRate::A,
+        }
+    }
+
+    /// Register a `ParamNode`, the closure handles the id of the param
+    fn register_param_node(
+        context: &impl BaseAudioContext,
+        keep_id: impl FnOnce(AudioParamId) -> Option<AudioParamId>,
+    ) -> ParamNode {
+        context.register(move |registration| {
+            let (value, id) = context.create_audio_param(param_opts(), &registration);
+            // a second param feeds the processor when the id of the first one is dropped
+            let id = keep_id(id)
+                .unwrap_or_else(|| context.create_audio_param(param_opts(), &registration).1);
+
+            let node = ParamNode {
+                registration,
+                channel_config: crate::node::ChannelConfig::default(),
+                value,
+            };
+            (node, Box::new(ParamRenderer { value: id }))
+        })
+    }
+
+    #[test]
+    fn test_register_contract() {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let node = register_param_node(&context, Some);
+        node.value.set_value(0.25);
+        node.connect(&context.destination());
+
+        // the param is owned by the node
+        let id = *node.registration().id();
+        let mirror = context.node_by_id(id).unwrap();
+        assert_eq!(mirror.params().len(), 1);
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &[0.25; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+
+        drop(node);
+        assert!(context.node_by_id(id).is_none());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "was dropped instead of being moved into the processor")]
+    fn test_register_param_id_dropped() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        // the values of the first param would never be read
+        let _ = register_param_node(&context, |id| {
+            drop(id);
+            None
+        });
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "was dropped inside its register closure")]
+    fn test_register_registration_dropped() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let _: ParamNode = context.register(|registration| {
+            let (value, id) = context.create_audio_param(param_opts(), &registration);
+            // the node holds another registration, the processor would be released right away
+            drop(registration);
+            let node = ParamNode {
+                registration: context.mock_registration(),
+                channel_config: crate::node::ChannelConfig::default(),
+                value,
+            };
+            (node, Box::new(ParamRenderer { value: id }))
+        });
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "is not created inside its register closure")]
+    fn test_create_audio_param_outside_register() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let node = register_param_node(&context, Some);
+        let _ = context.create_audio_param(param_opts(), node.registration());
+    }
 }
@@ -1,14 +1,24 @@
 //! The `OfflineAudioContext` type
-use std::sync::atomic::AtomicU64;
-use std::sync::Arc;
+use std::any::Any;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
 
 use crate::buffer::AudioBuffer;
-use crate::context::{BaseAudioContext, ConcreteBaseAudioContext};
+use crate::context::{AudioContextState, BaseAudioContext, ConcreteBaseAudioContext};
+use crate::param::AudioParam;
 use crate::render::RenderThread;
-use crate::{assert_valid_sample_rate, AtomicF64, RENDER_QUANTUM_SIZE};
+use crate::{assert_valid_sample_rate, RENDER_QUANTUM_SIZE};
 
 /// The `OfflineAudioContext` doesn't render the audio to the device hardware; instead, it generates
 /// it, as fast as it can, and outputs the result to an `AudioBuffer`.
+///
+/// Unlike the real-time contexts, the rendering waits for the processors fed by other threads
+/// (e.g. a [buffered media stream](crate::node::MediaStreamAudioSourceNode::new_buffered)), see
+/// [`AudioProcessor::poll_ready`](crate::render::AudioProcessor::poll_ready). The output is
+/// the same as a real-time rendering without underruns.
 // the naming comes from the web audio specfication
 #[allow(clippy::module_name_repetitions)]
 pub struct OfflineAudioContext {
@@ -18,6 +28,51 @@ pub struct OfflineAudioContext {
     length: usize,
     /// the rendering 'thread', fully controlled by the offline context
     renderer: RenderThread,
+    /// gain applied to the output of the destination node
+    master_gain: AudioParam,
+    /// set when the rendering is cancelled, see [`OfflineCancelHandle`]
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Handle to cancel the rendering of an [`OfflineAudioContext`] from another thread
+///
+/// See [`OfflineAudioContext::start_rendering_sync_cancellable`]
+#[derive(Clone, Debug)]
+pub struct OfflineCancelHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl OfflineCancelHandle {
+    /// Stop the rendering at the next render quantum boundary
+    ///
+    /// A cancelled context does not render any further frames.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` once the rendering has been cancelled
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Output of a cancellable rendering of an [`OfflineAudioContext`]
+#[derive(Clone, Debug)]
+pub struct OfflineRenderOutput {
+    /// Rendered audio, `frames_rendered` frames long
+    pub buffer: AudioBuffer,
+    /// Number of frames rendered before the cancellation, or the context length
+    pub frames_rendered: usize,
+    /// Indicates if the rendering was cancelled before the end
+    pub cancelled: bool,
+}
+
+impl Drop for OfflineAudioContext {
+    fn drop(&mut self) {
+        // stop the callbacks and decodes before the renderer is released
+        self.base.close();
+    }
 }
 
 impl BaseAudioContext for OfflineAudioContext {
@@ -34,6 +89,12 @@ impl OfflineAudioContext {
     /// * `channels` - number of output channels to render
     /// * `length` - length of the rendering audio buffer
     /// * `sample_rate` - output sample rate
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the sample rate is outside the
+    /// [[`MIN_SAMPLE_RATE`](crate::MIN_SAMPLE_RATE), [`MAX_SAMPLE_RATE`](crate::MAX_SAMPLE_RATE)]
+    /// range
     #[must_use]
     pub fn new(number_of_channels: usize, length: usize, sample_rate: f32) -> Self {
         assert_valid_sample_rate(sample_rate);
@@ -45,17 +106,12 @@ impl OfflineAudioContext {
         let frames_played = Arc::new(AtomicU64::new(0));
         let frames_played_clone = frames_played.clone();
 
-        // output_latency is irrelevant for offline context, but it needs to
-        // be passed to the `RenderThread` constructor
-        let output_latency = Arc::new(AtomicF64::new(0.));
-
         // setup the render 'thread', which will run inside the control thread
         let renderer = RenderThread::new(
             sample_rate,
             number_of_channels,
             receiver,
             frames_played_clone,
-            output_latency,
         );
 
         // first, setup the base audio context
@@ -67,10 +123,18 @@ impl OfflineAudioContext {
             true,
         );
 
+        // there is no dispatcher thread on wasm, the renderer runs the `ended` callbacks
+        #[cfg(feature = "wasm")]
+        let renderer = renderer.with_ended_events(base.ended_events().clone());
+
+        let master_gain = base.master_gain();
+
         Self {
             base,
             length,
             renderer,
+            master_gain,
+            cancelled: Arc::default(),
         }
     }
 
@@ -78,16 +142,62 @@ impl OfflineAudioContext {
     ///
     /// This function will block the current thread and returns the rendered `AudioBuffer`
     /// synchronously. An async version is currently not implemented.
+    ///
+    /// The rendering stops early when it is cancelled, see
+    /// [`start_rendering_sync_cancellable`](Self::start_rendering_sync_cancellable).
     pub fn start_rendering_sync(&mut self) -> AudioBuffer {
+        self.start_rendering_sync_cancellable().buffer
+    }
+
+    /// Starts rendering audio, until the end or until it is cancelled with the
+    /// [`OfflineCancelHandle`] of [`cancel_handle`](Self::cancel_handle)
+    ///
+    /// The cancellation is checked at each render quantum boundary. The output then holds
+    /// exactly the quanta rendered before, i.e. it is a prefix of the full rendering, its
+    /// length a multiple of the render quantum size.
+    ///
+    /// This function will block the current thread.
+    pub fn start_rendering_sync_cancellable(&mut self) -> OfflineRenderOutput {
         // make buffer_size always a multiple of RENDER_QUANTUM_SIZE, so we can still render piecewise with
         // the desired number of frames.
         let buffer_size =
             (self.length + RENDER_QUANTUM_SIZE - 1) / RENDER_QUANTUM_SIZE * RENDER_QUANTUM_SIZE;
 
-        let mut buf = self.renderer.render_audiobuffer(buffer_size);
-        let _split = buf.split_off(self.length);
+        self.base.flush_channel_configs();
+        self.base.set_state(AudioContextState::Running);
+
+        let mut buffer = self
+            .renderer
+            .render_audiobuffer_cancellable(buffer_size, &self.cancelled);
+        let frames_rendered = buffer.length().min(self.length);
+        let _split = buffer.split_off(frames_rendered);
+
+        OfflineRenderOutput {
+            buffer,
+            frames_rendered,
+            cancelled: frames_rendered < self.length,
+        }
+    }
+
+    /// Handle to cancel the rendering, from another thread or from an `AudioProcessor`
+    #[must_use]
+    pub fn cancel_handle(&self) -> OfflineCancelHandle {
+        OfflineCancelHandle {
+            cancelled: self.cancelled.clone(),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn renderer(&self) -> &RenderThread {
+        &self.renderer
+    }
 
-        buf
+    /// K-rate [`AudioParam`] scaling the output of the destination node (master volume)
+    ///
+    /// Setting the gain to `0.` silences all output without disconnecting any nodes.
+    #[must_use]
+    pub fn master_gain(&self) -> &AudioParam {
+        &self.master_gain
     }
 
     /// get the length of rendering audio buffer
@@ -99,9 +209,177 @@ impl OfflineAudioContext {
     }
 }
 
+/// Rendering of an [`OfflineAudioContext`], part of a batch, see [`render_batch`]
+pub struct RenderJob {
+    number_of_channels: usize,
+    length: usize,
+    sample_rate: f32,
+    setup: Box<dyn FnOnce(&mut OfflineAudioContext) + Send>,
+}
+
+impl fmt::Debug for RenderJob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RenderJob")
+            .field("number_of_channels", &self.number_of_channels)
+            .field("length", &self.length)
+            .field("sample_rate", &self.sample_rate)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RenderJob {
+    /// Render a context created with the given arguments, see [`OfflineAudioContext::new`]
+    ///
+    /// The `setup` closure builds the audio graph of the context before the rendering starts.
+    /// It may capture [`AudioBuffer`]s shared with other jobs: cloning an `AudioBuffer` does not
+    /// copy its samples.
+    pub fn new<F>(number_of_channels: usize, length: usize, sample_rate: f32, setup: F) -> Self
+    where
+        F: FnOnce(&mut OfflineAudioContext) + Send + 'static,
+    {
+        Self {
+            number_of_channels,
+            length,
+            sample_rate,
+            setup: Box::new(setup),
+        }
+    }
+
+    /// Create the context, set it up and render it, on the calling thread
+    fn run(self) -> Result<AudioBuffer, RenderJobError> {
+        let Self {
+            number_of_channels,
+            length,
+            sample_rate,
+            setup,
+        } = self;
+
+        // the context is created and dropped within the closure
+        let render = AssertUnwindSafe(move || {
+            let mut context = OfflineAudioContext::new(number_of_channels, length, sample_rate);
+            setup(&mut context);
+            context.start_rendering_sync()
+        });
+
+        panic::catch_unwind(render)
+            .map_err(|payload| RenderJobError::Panicked(panic_message(&*payload)))
+    }
+}
+
+/// Error of a [`RenderJob`], see [`render_batch`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RenderJobError {
+    /// The job panicked, when creating the context, setting it up or rendering it. Holds the
+    /// panic message.
+    Panicked(String),
+}
+
+impl fmt::Display for RenderJobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Panicked(message) => write!(f, "render job panicked: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for RenderJobError {}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("unknown panic")
+    }
+}
+
+/// Render independent [`OfflineAudioContext`]s on a pool of `parallelism` threads
+///
+/// The outputs are returned in the order of the jobs. Each context is rendered by a single
+/// thread, the output of a job is the same as when it is rendered on its own.
+///
+/// A job that panics does not stop the batch, it returns a [`RenderJobError`] instead. The
+/// panic message is still printed by the panic hook.
+///
+/// # Example
+///
+/// ```no_run
+/// use web_audio_api::context::{render_batch, BaseAudioContext, RenderJob};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let jobs = (0..8)
+///     .map(|i| {
+///         RenderJob::new(1, 44_100, 44_100., move |context| {
+///             let osc = context.create_oscillator();
+///             osc.frequency().set_value(220. * (i + 1) as f32);
+///             osc.connect(&context.destination());
+///             osc.start();
+///         })
+///     })
+///     .collect();
+///
+/// let outputs = render_batch(jobs, 4);
+/// assert_eq!(outputs.len(), 8);
+/// ```
+///
+/// # Panics
+///
+/// Will panic if `parallelism` is zero
+#[must_use]
+pub fn render_batch(
+    jobs: Vec<RenderJob>,
+    parallelism: usize,
+) -> Vec<Result<AudioBuffer, RenderJobError>> {
+    assert!(
+        parallelism > 0,
+        "RangeError - the parallelism of a batch must be at least 1"
+    );
+
+    let count = jobs.len();
+    let queue = Arc::new(Mutex::new(jobs.into_iter().enumerate()));
+    let (sender, receiver) = crossbeam_channel::unbounded();
+
+    let workers: Vec<_> = (0..parallelism.min(count))
+        .map(|_| {
+            let queue = Arc::clone(&queue);
+            let sender = sender.clone();
+            thread::spawn(move || loop {
+                // the queue is not locked while rendering
+                let next = queue.lock().unwrap().next();
+                let (index, job) = match next {
+                    Some(next) => next,
+                    None => break,
+                };
+                // the receiver outlives the workers
+                let _ = sender.send((index, job.run()));
+            })
+        })
+        .collect();
+    drop(sender);
+
+    let mut outputs: Vec<_> = (0..count).map(|_| None).collect();
+    for (index, output) in receiver {
+        outputs[index] = Some(output);
+    }
+
+    // the panics of the jobs are caught, the workers do not panic
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    outputs.into_iter().map(Option::unwrap).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::node::{
+        AudioNode, AudioScheduledSourceNode, ChannelCountMode, ChannelInterpretation,
+        ConvolverNode, ConvolverOptions, PlaybackState,
+    };
+    use crate::render::NodeIndex;
     use float_eq::assert_float_eq;
 
     #[test]
@@ -114,4 +392,1098 @@ mod tests {
         assert_float_eq!(buffer.get_channel_data(0), &[0.; 555][..], abs_all <= 0.);
         assert_float_eq!(buffer.get_channel_data(1), &[0.; 555][..], abs_all <= 0.);
     }
+
+    fn render_oscillator(master_gain: Option<f32>) -> AudioBuffer {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 4, 44_100.);
+        if let Some(gain) = master_gain {
+            context.master_gain().set_value(gain);
+        }
+
+        let osc = context.create_oscillator();
+        osc.connect(&context.destination());
+        osc.start();
+
+        context.start_rendering_sync()
+    }
+
+    #[test]
+    fn test_master_gain() {
+        let reference = render_oscillator(None);
+        let output = render_oscillator(Some(0.5));
+
+        let expected: Vec<f32> = reference
+            .get_channel_data(0)
+            .iter()
+            .map(|v| v * 0.5)
+            .collect();
+        assert_float_eq!(output.get_channel_data(0), &expected[..], abs_all <= 0.);
+
+        let silenced = render_oscillator(Some(0.));
+        assert_float_eq!(
+            silenced.get_channel_data(0),
+            &[0.; RENDER_QUANTUM_SIZE * 4][..],
+            abs_all <= 0.
+        );
+    }
+
+    /// Connect a short burst to a convolver with an impulse response of `ir_length` frames
+    fn setup_convolver(context: &OfflineAudioContext, ir_length: usize) -> ConvolverNode {
+        let mut ir = context.create_buffer(1, ir_length, 44_100.);
+        ir.copy_to_channel(&vec![0.5; ir_length], 0);
+        let convolver = ConvolverNode::new(
+            context,
+            ConvolverOptions {
+                buffer: Some(ir),
+                disable_normalization: true,
+                ..ConvolverOptions::default()
+            },
+        );
+        convolver.connect(&context.destination());
+
+        let mut burst = context.create_buffer(1, RENDER_QUANTUM_SIZE, 44_100.);
+        burst.copy_to_channel(&[1.; RENDER_QUANTUM_SIZE], 0);
+        let src = context.create_buffer_source();
+        src.set_buffer(burst);
+        src.connect(&convolver);
+        src.start();
+
+        convolver
+    }
+
+    #[test]
+    fn test_convolver_tail_is_freed() {
+        let ir_length = RENDER_QUANTUM_SIZE * 10;
+        let mut context = OfflineAudioContext::new(1, ir_length * 2, 44_100.);
+
+        let index = {
+            let convolver = setup_convolver(&context, ir_length);
+            NodeIndex(convolver.registration().id().0)
+        }; // src and convolver nodes are dropped
+
+        let buffer = context.start_rendering_sync();
+        // the tail has been rendered
+        assert!(buffer.get_channel_data(0)[ir_length - 1] != 0.);
+        // and the node has been freed afterwards
+        assert_eq!(context.renderer().graph().node_state(index), None);
+    }
+
+    #[test]
+    fn test_convolver_tail_is_dormant() {
+        let ir_length = RENDER_QUANTUM_SIZE * 10;
+        let mut context = OfflineAudioContext::new(1, ir_length * 2, 44_100.);
+
+        let convolver = setup_convolver(&context, ir_length);
+        let index = NodeIndex(convolver.registration().id().0);
+
+        let buffer = context.start_rendering_sync();
+        assert!(buffer.get_channel_data(0)[ir_length - 1] != 0.);
+        // the node is kept while the control handle is alive, but skipped
+        assert_eq!(context.renderer().graph().node_state(index), Some(true));
+    }
+
+    #[test]
+    fn test_last_output_frame() {
+        let length = RENDER_QUANTUM_SIZE * 4;
+        let mut context = OfflineAudioContext::new(2, length, 44_100.);
+        context.subscribe_output_frames();
+        // nothing has been rendered yet
+        assert!(context.last_output_frame().is_none());
+
+        let osc = context.create_oscillator();
+        osc.connect(&context.destination());
+        osc.start();
+
+        let buffer = context.start_rendering_sync();
+        let snapshot = context.last_output_frame().unwrap();
+
+        // the snapshot holds the last rendered quantum
+        assert_eq!(snapshot.frame(), (length - RENDER_QUANTUM_SIZE) as u64);
+        assert_eq!(snapshot.number_of_channels(), 2);
+        assert_eq!(snapshot.length(), RENDER_QUANTUM_SIZE);
+        assert_float_eq!(
+            snapshot.get_channel_data(0),
+            &buffer.get_channel_data(0)[length - RENDER_QUANTUM_SIZE..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            snapshot.get_channel_data(1),
+            &buffer.get_channel_data(1)[length - RENDER_QUANTUM_SIZE..],
+            abs_all <= 0.
+        );
+
+        // reading again yields a duplicate
+        assert_eq!(
+            context.last_output_frame().unwrap().frame(),
+            snapshot.frame()
+        );
+
+        context.unsubscribe_output_frames();
+        assert!(context.last_output_frame().is_none());
+    }
+
+    #[test]
+    fn test_last_output_frame_without_subscription() {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 4, 44_100.);
+
+        let osc = context.create_oscillator();
+        osc.connect(&context.destination());
+        osc.start();
+        let _ = context.start_rendering_sync();
+
+        // the render thread did not publish its output
+        assert!(context.last_output_frame().is_none());
+        context.subscribe_output_frames();
+        assert!(context.last_output_frame().is_none());
+    }
+
+    /// Records the input it receives, as mixed according to its channel config
+    struct ProbeNode {
+        registration: crate::context::AudioContextRegistration,
+        channel_config: crate::node::ChannelConfig,
+    }
+
+    impl AudioNode for ProbeNode {
+        fn registration(&self) -> &crate::context::AudioContextRegistration {
+            &self.registration
+        }
+
+        fn channel_config(&self) -> &crate::node::ChannelConfig {
+            &self.channel_config
+        }
+
+        fn number_of_inputs(&self) -> usize {
+            1
+        }
+
+        fn number_of_outputs(&self) -> usize {
+            1
+        }
+    }
+
+    struct ProbeRenderer {
+        seen: std::sync::mpsc::Sender<Vec<f32>>,
+    }
+
+    impl crate::render::AudioProcessor for ProbeRenderer {
+        fn process(
+            &mut self,
+            inputs: &[crate::render::AudioRenderQuantum],
+            _outputs: &mut [crate::render::AudioRenderQuantum],
+            _params: crate::render::AudioParamValues,
+            _scope: &crate::render::RenderScope,
+        ) -> bool {
+            let first_samples = inputs[0].channels().iter().map(|c| c[0]).collect();
+            self.seen.send(first_samples).unwrap();
+            true
+        }
+    }
+
+    /// Records the silent channel mask of its input, which is passed through
+    struct MaskProbeRenderer {
+        seen: std::sync::mpsc::Sender<u32>,
+    }
+
+    impl crate::render::AudioProcessor for MaskProbeRenderer {
+        fn process(
+            &mut self,
+            inputs: &[crate::render::AudioRenderQuantum],
+            outputs: &mut [crate::render::AudioRenderQuantum],
+            _params: crate::render::AudioParamValues,
+            _scope: &crate::render::RenderScope,
+        ) -> bool {
+            self.seen.send(inputs[0].silent_channel_mask()).unwrap();
+            outputs[0] = inputs[0].clone();
+            true
+        }
+    }
+
+    /// Outputs 6 channels, with audio in channels 2 and 3 only
+    struct SparseSourceRenderer;
+
+    impl crate::render::AudioProcessor for SparseSourceRenderer {
+        fn process(
+            &mut self,
+            _inputs: &[crate::render::AudioRenderQuantum],
+            outputs: &mut [crate::render::AudioRenderQuantum],
+            _params: crate::render::AudioParamValues,
+            _scope: &crate::render::RenderScope,
+        ) -> bool {
+            let output = &mut outputs[0];
+            output.make_silent();
+            output.mix(6, ChannelInterpretation::Discrete);
+            output.channel_data_mut(2).iter_mut().for_each(|v| *v = 0.5);
+            output
+                .channel_data_mut(3)
+                .iter_mut()
+                .for_each(|v| *v = -0.25);
+            true
+        }
+    }
+
+    #[test]
+    fn test_sparse_multichannel_graph() {
+        let mut context = OfflineAudioContext::new(6, RENDER_QUANTUM_SIZE * 4, 44_100.);
+
+        let src = context.register(move |registration| {
+            let node = ProbeNode {
+                registration,
+                channel_config: crate::node::ChannelConfigOptions::default().into(),
+            };
+            (node, Box::new(SparseSourceRenderer))
+        });
+
+        let gain = context.create_gain();
+        gain.set_channel_count(6);
+        gain.set_channel_interpretation(ChannelInterpretation::Discrete);
+        gain.gain().set_value(2.);
+        src.connect(&gain);
+
+        let (seen, receiver) = std::sync::mpsc::channel();
+        let probe = context.register(move |registration| {
+            let channel_config = crate::node::ChannelConfigOptions {
+                count: 6,
+                mode: ChannelCountMode::Explicit,
+                interpretation: ChannelInterpretation::Discrete,
+            }
+            .into();
+            let node = ProbeNode {
+                registration,
+                channel_config,
+            };
+            (node, Box::new(MaskProbeRenderer { seen }))
+        });
+        gain.connect(&probe);
+        probe.connect(&context.destination());
+        context.master_gain().set_value(0.5);
+
+        let output = context.start_rendering_sync();
+
+        // the gain did not touch the silent channels
+        let masks: Vec<u32> = receiver.try_iter().collect();
+        assert_eq!(masks, vec![0b110011; 4]);
+
+        let expected = [0., 0., 0.5, -0.25, 0., 0.];
+        for (channel, value) in expected.iter().enumerate() {
+            assert_float_eq!(
+                output.get_channel_data(channel),
+                &[*value; RENDER_QUANTUM_SIZE * 4][..],
+                abs_all <= 0.
+            );
+        }
+    }
+
+    fn setup_probe(
+        context: &OfflineAudioContext,
+    ) -> (ProbeNode, std::sync::mpsc::Receiver<Vec<f32>>) {
+        let (seen, receiver) = std::sync::mpsc::channel();
+        let probe = context.register(move |registration| {
+            let channel_config = crate::node::ChannelConfigOptions {
+                count: 1,
+                mode: ChannelCountMode::Explicit,
+                interpretation: ChannelInterpretation::Discrete,
+            }
+            .into();
+            let node = ProbeNode {
+                registration,
+                channel_config,
+            };
+            (node, Box::new(ProbeRenderer { seen }))
+        });
+
+        // mono input
+        let src = context.create_constant_source();
+        src.connect(&probe);
+        src.start();
+        probe.connect(&context.destination());
+
+        (probe, receiver)
+    }
+
+    /// Render a single quantum, returns the input of the probe
+    fn render_quantum(
+        context: &mut OfflineAudioContext,
+        seen: &std::sync::mpsc::Receiver<Vec<f32>>,
+    ) -> Vec<f32> {
+        let _ = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE);
+        seen.try_recv().unwrap()
+    }
+
+    #[test]
+    fn test_channel_config_batched() {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 10, 44_100.);
+        let (probe, seen) = setup_probe(&context);
+        // count 1, explicit, discrete
+        assert_eq!(render_quantum(&mut context, &seen), vec![1.]);
+
+        // a partially applied config (count 4, explicit, discrete) is never observed
+        probe.set_channel_count(4);
+        assert_eq!(probe.channel_count(), 4);
+        assert_eq!(render_quantum(&mut context, &seen), vec![1.]);
+
+        probe.set_channel_interpretation(ChannelInterpretation::Speakers);
+        probe.apply_channel_config();
+        assert_eq!(render_quantum(&mut context, &seen), vec![1., 1., 0., 0.]);
+
+        // changes are sent along with the next call that talks to the render thread
+        probe.set_channel_count(1);
+        probe.set_channel_interpretation(ChannelInterpretation::Discrete);
+        assert_eq!(render_quantum(&mut context, &seen), vec![1., 1., 0., 0.]);
+        probe.disconnect_from(&context.destination());
+        assert_eq!(render_quantum(&mut context, &seen), vec![1.]);
+    }
+
+    #[test]
+    fn test_channel_config_interleaved() {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 100, 44_100.);
+        let (probe, seen) = setup_probe(&context);
+
+        for i in 0..100 {
+            // alternate between (count 1, clamped max, discrete) and (count 4, explicit,
+            // speakers), changing a single value per render quantum
+            match i % 6 {
+                0 => probe.set_channel_count(4),
+                1 => probe.set_channel_count_mode(ChannelCountMode::Explicit),
+                2 => probe.set_channel_interpretation(ChannelInterpretation::Speakers),
+                3 => probe.set_channel_count(1),
+                4 => probe.set_channel_count_mode(ChannelCountMode::ClampedMax),
+                _ => probe.set_channel_interpretation(ChannelInterpretation::Discrete),
+            }
+            // the config is complete every three changes
+            if i % 3 == 2 {
+                probe.apply_channel_config();
+            }
+
+            let observed = render_quantum(&mut context, &seen);
+            let expected = if (2..5).contains(&(i % 6)) {
+                vec![1., 1., 0., 0.]
+            } else {
+                vec![1.]
+            };
+            assert_eq!(observed, expected, "render quantum {}", i);
+        }
+    }
+
+    #[test]
+    fn test_scheduled_times() {
+        let sample_rate = 44_100.;
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 4, sample_rate);
+
+        let src = context.create_constant_source();
+        src.connect(&context.destination());
+        assert_eq!(src.scheduled_start_time(), None);
+        assert_eq!(src.scheduled_stop_time(), None);
+
+        // the values can be queried before they reach the render thread
+        let when = RENDER_QUANTUM_SIZE as f64 / sample_rate as f64;
+        src.start_at(when);
+        src.stop_at(when * 3.);
+        assert_eq!(src.scheduled_start_time(), Some(when));
+        assert_eq!(src.scheduled_stop_time(), Some(when * 3.));
+
+        src.cancel_scheduled_start();
+        assert_eq!(src.scheduled_start_time(), None);
+        assert_eq!(src.scheduled_stop_time(), Some(when * 3.));
+
+        let buffer = context.start_rendering_sync();
+        assert_float_eq!(
+            buffer.get_channel_data(0),
+            &[0.; RENDER_QUANTUM_SIZE * 4][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_current_time_interpolated() {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 4, 44_100.);
+        assert_float_eq!(context.current_time_interpolated(), 0., abs <= 0.);
+
+        let _ = context.start_rendering_sync();
+        assert_float_eq!(
+            context.current_time_interpolated(),
+            context.current_time(),
+            abs <= 0.
+        );
+    }
+
+    #[test]
+    fn test_cancel_scheduled_start_while_rendering() {
+        let sample_rate = 44_100.;
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 4, sample_rate);
+
+        let mut buffer = context.create_buffer(1, RENDER_QUANTUM_SIZE * 4, sample_rate);
+        buffer.copy_to_channel(&[1.; RENDER_QUANTUM_SIZE * 4], 0);
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&context.destination());
+        src.start_at(2. * RENDER_QUANTUM_SIZE as f64 / sample_rate as f64);
+
+        let output = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE);
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &[0.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+
+        // the start has not been reached yet
+        src.cancel_scheduled_start();
+        let output = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE * 2);
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &[0.; RENDER_QUANTUM_SIZE * 2][..],
+            abs_all <= 0.
+        );
+
+        // the source can be started again
+        src.start();
+        let output = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE);
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &[1.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_cancel_scheduled_start_after_start() {
+        let sample_rate = 44_100.;
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 4, sample_rate);
+
+        let src = context.create_constant_source();
+        src.connect(&context.destination());
+        src.start();
+
+        let output = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE);
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &[1.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+
+        // the start has been reached, the source stops at the current time
+        src.cancel_scheduled_start();
+        let now = context.current_time();
+        assert_eq!(src.scheduled_start_time(), Some(0.));
+        assert_eq!(src.scheduled_stop_time(), Some(now));
+        assert_eq!(src.playback_state(), PlaybackState::Playing);
+
+        let output = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE);
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &[0.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+        assert_eq!(src.playback_state(), PlaybackState::Finished);
+
+        // the source can not be restarted
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| src.start()));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cancel_scheduled_start_in_starting_quantum() {
+        let sample_rate = 44_100.;
+        let quantum = RENDER_QUANTUM_SIZE as f64 / sample_rate as f64;
+
+        // the cancellation is applied in the render quantum the source would start in
+        for &offset in [0., 0.5, 0.99].iter() {
+            let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 4, sample_rate);
+            let src = context.create_constant_source();
+            src.connect(&context.destination());
+            let _ = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE);
+
+            src.start_at(quantum * (1. + offset));
+            src.cancel_scheduled_start();
+            let output = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE * 3);
+            assert_float_eq!(
+                output.get_channel_data(0),
+                &[0.; RENDER_QUANTUM_SIZE * 3][..],
+                abs_all <= 0.
+            );
+            // a start at the current time has been reached, the source is stopped right away
+            let expected = if offset == 0. {
+                PlaybackState::Finished
+            } else {
+                PlaybackState::Unscheduled
+            };
+            assert_eq!(src.playback_state(), expected);
+        }
+    }
+
+    #[test]
+    fn test_buffer_source_channels_follow_buffer() {
+        let sample_rate = 44_100.;
+        let mut context = OfflineAudioContext::new(6, RENDER_QUANTUM_SIZE * 2, sample_rate);
+        context
+            .destination()
+            .set_channel_interpretation(ChannelInterpretation::Discrete);
+
+        let src = context.create_buffer_source();
+        src.connect(&context.destination());
+        src.start();
+
+        // without buffer, the output is a single channel of silence
+        let output = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE);
+        assert_eq!(output.number_of_channels(), 6);
+        for i in 0..6 {
+            assert_float_eq!(
+                output.get_channel_data(i),
+                &[0.; RENDER_QUANTUM_SIZE][..],
+                abs_all <= 0.
+            );
+        }
+
+        // the output switches to the channel count of the buffer once it is received
+        let mut buffer = context.create_buffer(6, RENDER_QUANTUM_SIZE, sample_rate);
+        for i in 0..6 {
+            buffer.copy_to_channel(&[(i + 1) as f32; RENDER_QUANTUM_SIZE], i);
+        }
+        src.set_buffer(buffer);
+        let output = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE);
+        for i in 0..6 {
+            assert_float_eq!(
+                output.get_channel_data(i),
+                &[(i + 1) as f32; RENDER_QUANTUM_SIZE][..],
+                abs_all <= 0.
+            );
+        }
+    }
+
+    /// Oscillator through a chain of effects with automation, optionally frozen
+    fn setup_effect_chain(context: &OfflineAudioContext, freeze: bool) -> Vec<Box<dyn AudioNode>> {
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(220.);
+        osc.start();
+
+        let mut chain: Vec<Box<dyn AudioNode>> = vec![];
+        for i in 0..4 {
+            let gain = context.create_gain();
+            gain.gain().set_value_at_time(0.9, 0.);
+            gain.gain()
+                .linear_ramp_to_value_at_time(0.5 + i as f32 * 0.1, 0.02);
+            let biquad = context.create_biquad_filter();
+            biquad.frequency().set_value(1000. + i as f32 * 500.);
+            let shaper = context.create_wave_shaper();
+            shaper.set_curve(vec![-0.8, 0., 0.8]);
+            chain.push(Box::new(gain));
+            chain.push(Box::new(biquad));
+            chain.push(Box::new(shaper));
+        }
+
+        osc.connect(&*chain[0]);
+        chain.windows(2).for_each(|w| {
+            w[0].connect(&*w[1]);
+        });
+        chain.last().unwrap().connect(&context.destination());
+
+        if freeze {
+            let nodes: Vec<&dyn AudioNode> = chain.iter().map(|n| &**n).collect();
+            context.freeze_chain(&nodes);
+        }
+
+        chain.insert(0, Box::new(osc));
+        chain
+    }
+
+    #[test]
+    fn test_frozen_chain_output_is_identical() {
+        let length = RENDER_QUANTUM_SIZE * 20;
+
+        let mut context = OfflineAudioContext::new(1, length, 44_100.);
+        let _chain = setup_effect_chain(&context, false);
+        let reference = context.start_rendering_sync();
+
+        let mut context = OfflineAudioContext::new(1, length, 44_100.);
+        let chain = setup_effect_chain(&context, true);
+        let output = context.start_rendering_sync();
+
+        let index = NodeIndex(chain[5].registration().id().0);
+        assert!(context.renderer().graph().is_frozen(index));
+        assert!(output.get_channel_data(0).iter().any(|v| *v != 0.));
+        assert_float_eq!(
+            output.get_channel_data(0),
+            reference.get_channel_data(0),
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_frozen_chain_is_split_on_change() {
+        let length = RENDER_QUANTUM_SIZE * 20;
+
+        let mut context = OfflineAudioContext::new(2, length, 44_100.);
+        let chain = setup_effect_chain(&context, false);
+        let _ = context.renderer.render_audiobuffer(length / 2);
+        chain[5].set_channel_count(1);
+        chain[5].apply_channel_config();
+        let reference = context.renderer.render_audiobuffer(length / 2);
+
+        let mut context = OfflineAudioContext::new(2, length, 44_100.);
+        let chain = setup_effect_chain(&context, true);
+        let index = NodeIndex(chain[5].registration().id().0);
+        let _ = context.renderer.render_audiobuffer(length / 2);
+        assert!(context.renderer().graph().is_frozen(index));
+        chain[5].set_channel_count(1);
+        chain[5].apply_channel_config();
+        let output = context.renderer.render_audiobuffer(length / 2);
+        assert!(!context.renderer().graph().is_frozen(index));
+
+        for i in 0..2 {
+            assert_float_eq!(
+                output.get_channel_data(i),
+                reference.get_channel_data(i),
+                abs_all <= 0.
+            );
+        }
+    }
+
+    /// Cancels the rendering once it reaches the given frame
+    struct CancelRenderer {
+        handle: OfflineCancelHandle,
+        at_frame: u64,
+    }
+
+    impl crate::render::AudioProcessor for CancelRenderer {
+        fn process(
+            &mut self,
+            inputs: &[crate::render::AudioRenderQuantum],
+            outputs: &mut [crate::render::AudioRenderQuantum],
+            _params: crate::render::AudioParamValues,
+            scope: &crate::render::RenderScope,
+        ) -> bool {
+            if scope.current_frame + RENDER_QUANTUM_SIZE as u64 > self.at_frame {
+                self.handle.cancel();
+            }
+            outputs[0] = inputs[0].clone();
+            true
+        }
+    }
+
+    #[test]
+    fn test_cancel_at_quantum_boundary() {
+        let length = RENDER_QUANTUM_SIZE * 10 + 10;
+        let mut context = OfflineAudioContext::new(1, length, 44_100.);
+
+        let handle = context.cancel_handle();
+        let probe = context.register(move |registration| {
+            let node = ProbeNode {
+                registration,
+                channel_config: crate::node::ChannelConfigOptions::default().into(),
+            };
+            let at_frame = RENDER_QUANTUM_SIZE as u64 * 3 + 5;
+            (node, Box::new(CancelRenderer { handle, at_frame }))
+        });
+        probe.connect(&context.destination());
+
+        let src = context.create_constant_source();
+        src.connect(&probe);
+        src.start();
+
+        // the quantum during which the rendering is cancelled is complete
+        let output = context.start_rendering_sync_cancellable();
+        assert!(output.cancelled);
+        assert_eq!(output.frames_rendered, RENDER_QUANTUM_SIZE * 4);
+        assert_eq!(output.buffer.length(), output.frames_rendered);
+        assert!(output.buffer.get_channel_data(0).iter().all(|v| *v == 1.));
+
+        // no further frames are rendered
+        let output = context.start_rendering_sync_cancellable();
+        assert!(output.cancelled);
+        assert_eq!(output.frames_rendered, 0);
+    }
+
+    #[test]
+    fn test_render_without_cancel() {
+        let length = RENDER_QUANTUM_SIZE * 2 + 10;
+        let mut context = OfflineAudioContext::new(1, length, 44_100.);
+
+        let output = context.start_rendering_sync_cancellable();
+        assert!(!output.cancelled);
+        assert_eq!(output.frames_rendered, length);
+        assert_eq!(output.buffer.length(), length);
+
+        // cancelling after the end has no effect on the output
+        context.cancel_handle().cancel();
+        assert!(context.cancel_handle().is_cancelled());
+    }
+
+    #[test]
+    fn test_sample_rate_range() {
+        use crate::{MAX_SAMPLE_RATE, MIN_SAMPLE_RATE};
+
+        [MIN_SAMPLE_RATE, MAX_SAMPLE_RATE]
+            .iter()
+            .for_each(|&sample_rate| {
+                let context = OfflineAudioContext::new(1, 1, sample_rate);
+                assert_eq!(context.sample_rate(), sample_rate);
+            });
+
+        [
+            0.,
+            -48_000.,
+            f32::NAN,
+            f32::INFINITY,
+            1e9,
+            MIN_SAMPLE_RATE - 1.,
+            MAX_SAMPLE_RATE + 1.,
+        ]
+        .iter()
+        .for_each(|&sample_rate| {
+            let result = std::panic::catch_unwind(|| OfflineAudioContext::new(1, 1, sample_rate));
+            assert!(result.is_err(), "{} should be rejected", sample_rate);
+        });
+    }
+
+    #[test]
+    fn test_finished_sources_are_freed() {
+        let sample_rate = 44_100.;
+        let quantum = RENDER_QUANTUM_SIZE as f64 / sample_rate as f64;
+        let mut context = OfflineAudioContext::new(1, 0, sample_rate);
+        let _ = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE);
+        let node_count = context.renderer().graph().node_count();
+
+        // a lone one-shot oscillator
+        {
+            let osc = context.create_oscillator();
+            osc.connect(&context.destination());
+            osc.start();
+            osc.stop_at(context.current_time() + quantum);
+        }
+        let _ = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE * 3);
+        assert_eq!(context.renderer().graph().node_count(), node_count);
+
+        // 10k one-shot oscillators, overlapping
+        for _ in 0..100 {
+            for i in 0..100 {
+                let osc = context.create_oscillator();
+                osc.connect(&context.destination());
+                osc.start();
+                osc.stop_at(context.current_time() + quantum * (1 + i % 3) as f64);
+            }
+            let _ = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE);
+        }
+        let _ = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE * 4);
+        assert_eq!(context.renderer().graph().node_count(), node_count);
+    }
+
+    #[test]
+    fn test_unstarted_sources_are_freed() {
+        let sample_rate = 44_100.;
+        let quantum = RENDER_QUANTUM_SIZE as f64 / sample_rate as f64;
+        let mut context = OfflineAudioContext::new(1, 0, sample_rate);
+        let _ = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE);
+        let node_count = context.renderer().graph().node_count();
+
+        // sources that are never started produce no sound, they are freed once dropped
+        {
+            let osc = context.create_oscillator();
+            osc.connect(&context.destination());
+            let src = context.create_constant_source();
+            src.connect(&context.destination());
+            let buffer_src = context.create_buffer_source();
+            buffer_src.connect(&context.destination());
+        }
+        let _ = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE * 2);
+        assert_eq!(context.renderer().graph().node_count(), node_count);
+
+        // a dropped source with a pending start is kept until it has played
+        {
+            let src = context.create_constant_source();
+            src.connect(&context.destination());
+            src.start_at(context.current_time() + quantum * 4.);
+            src.stop_at(context.current_time() + quantum * 5.);
+        }
+        let _ = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE * 2);
+        assert!(context.renderer().graph().node_count() > node_count);
+        let _ = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE * 6);
+        assert_eq!(context.renderer().graph().node_count(), node_count);
+    }
+
+    #[test]
+    fn test_transaction_rollback() {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 4, 44_100.);
+        let _ = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE);
+        let node_count = context.renderer().graph().node_count();
+
+        let result: Result<(), &str> = context.transaction(|txn| {
+            let sources: Vec<_> = (0..10)
+                .map(|_| {
+                    let src = txn.create_constant_source();
+                    src.connect(&txn.destination());
+                    src.start();
+                    src
+                })
+                .collect();
+            assert_eq!(sources.len(), 10);
+            Err("invalid patch")
+        });
+        assert_eq!(result, Err("invalid patch"));
+
+        let output = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE);
+        assert_eq!(context.renderer().graph().node_count(), node_count);
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &[0.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+
+        // a panic discards the staged changes as well, and closes the transaction
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            context.transaction(|txn| -> Result<(), ()> {
+                let src = txn.create_constant_source();
+                src.connect(&txn.destination());
+                src.start();
+                panic!("invalid patch");
+            })
+        }));
+        assert!(result.is_err());
+
+        let src = context
+            .transaction(|txn| -> Result<_, ()> {
+                let src = txn.create_constant_source();
+                src.connect(&txn.destination());
+                Ok(src)
+            })
+            .unwrap();
+        // the nodes are usable after the commit
+        src.start();
+
+        let output = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE);
+        // the constant source and its offset param
+        assert_eq!(context.renderer().graph().node_count(), node_count + 2);
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &[1.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError - cannot call `start` twice")]
+    fn test_transaction_rollback_existing_node() {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 4, 44_100.);
+        let osc = context.create_oscillator();
+        osc.connect(&context.destination());
+        let _ = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE);
+        let node_count = context.renderer().graph().node_count();
+
+        let result: Result<(), &str> = context.transaction(|txn| {
+            osc.start();
+            let src = txn.create_constant_source();
+            src.connect(&txn.destination());
+            src.start();
+            Err("invalid patch")
+        });
+        assert_eq!(result, Err("invalid patch"));
+
+        // the start of the existing node is passed on, the new node is discarded
+        let output = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE);
+        assert_eq!(context.renderer().graph().node_count(), node_count);
+        assert!(output.get_channel_data(0).iter().any(|v| *v != 0.));
+        assert!(output.get_channel_data(0).iter().all(|v| v.abs() <= 1.));
+
+        // the control side agrees with the render thread, the node was started
+        osc.start();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "InvalidStateError - the node was discarded by a rolled-back transaction"
+    )]
+    fn test_transaction_rollback_escaped_node() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+        let mut escaped = None;
+        let result: Result<(), ()> = context.transaction(|txn| {
+            escaped = Some(txn.create_oscillator());
+            Err(())
+        });
+        assert!(result.is_err());
+
+        escaped.unwrap().connect(&context.destination());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "InvalidStateError - the node was discarded by a rolled-back transaction"
+    )]
+    fn test_transaction_rollback_connect_to_escaped_node() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let osc = context.create_oscillator();
+
+        let mut escaped = None;
+        let result: Result<(), ()> = context.transaction(|txn| {
+            escaped = Some(txn.create_gain());
+            Err(())
+        });
+        assert!(result.is_err());
+
+        osc.connect(escaped.as_ref().unwrap());
+    }
+
+    #[test]
+    fn test_transaction_rollback_escaped_modulation() {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let osc = context.create_oscillator();
+        osc.connect(&context.destination());
+        osc.start();
+
+        let mut escaped = None;
+        let result: Result<(), ()> = context.transaction(|txn| {
+            let lfo = txn.create_oscillator();
+            escaped = Some(osc.frequency().modulate(&lfo, 10.));
+            Err(())
+        });
+        assert!(result.is_err());
+
+        // the modulation never reached the render thread, releasing it does not panic
+        drop(escaped);
+        let output = context.start_rendering_sync();
+        assert!(output.get_channel_data(0).iter().any(|v| *v != 0.));
+    }
+
+    #[test]
+    fn test_transaction_applied_at_once() {
+        let number_of_sources = 32;
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+        // build the patch while the render thread is running
+        let base = context.base().clone();
+        let builder = std::thread::spawn(move || {
+            base.transaction(|txn| -> Result<_, ()> {
+                let sources: Vec<_> = (0..number_of_sources)
+                    .map(|_| {
+                        let src = txn.create_constant_source();
+                        src.connect(&txn.destination());
+                        src.start();
+                        std::thread::sleep(std::time::Duration::from_micros(200));
+                        src
+                    })
+                    .collect();
+                Ok(sources)
+            })
+        });
+
+        let mut levels = vec![];
+        let mut render = || {
+            let output = context.renderer.render_audiobuffer(RENDER_QUANTUM_SIZE);
+            let channel = output.get_channel_data(0);
+            // the sources all start on the same frame
+            assert!(channel.iter().all(|v| *v == channel[0]));
+            levels.push(channel[0]);
+        };
+        while !builder.is_finished() {
+            render();
+        }
+        let sources = builder.join().unwrap().unwrap();
+        render();
+
+        let all = number_of_sources as f32;
+        assert!(levels.iter().all(|l| *l == 0. || *l == all), "{:?}", levels);
+        assert_eq!(levels.last(), Some(&all));
+        assert_eq!(sources.len(), number_of_sources);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError - a transaction is already open on this thread")]
+    fn test_nested_transaction() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let _ = context.transaction(|txn| txn.transaction(|_| -> Result<(), ()> { Ok(()) }));
+    }
+
+    /// Jobs playing a shared buffer at various rates, along with an oscillator
+    fn batch_jobs(buffer: &AudioBuffer) -> Vec<RenderJob> {
+        let samples = buffer.get_channel_data(0).as_ptr() as usize;
+
+        (0..16)
+            .map(|i| {
+                let buffer = buffer.clone();
+                RenderJob::new(2, RENDER_QUANTUM_SIZE * 8, 44_100., move |context| {
+                    // the job holds the same samples
+                    assert_eq!(buffer.get_channel_data(0).as_ptr() as usize, samples);
+
+                    let src = context.create_buffer_source();
+                    src.set_buffer(buffer);
+                    src.playback_rate().set_value(0.5 + i as f32 / 8.);
+                    src.connect(&context.destination());
+                    src.start();
+
+                    let osc = context.create_oscillator();
+                    osc.frequency().set_value(110. * (i + 1) as f32);
+                    osc.connect(&context.destination());
+                    osc.start_at(i as f64 / 1000.);
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_render_batch_shared_buffer() {
+        let samples: Vec<f32> = (0..RENDER_QUANTUM_SIZE * 8)
+            .map(|i| ((i * 7919) % 1000) as f32 / 1000. - 0.5)
+            .collect();
+        let buffer = AudioBuffer::from(vec![samples], 44_100.);
+
+        let jobs = batch_jobs(&buffer);
+        // the jobs share the samples
+        assert_eq!(buffer.channel_strong_count(0), 17);
+
+        let outputs = render_batch(jobs, 4);
+        // released by the contexts
+        assert_eq!(buffer.channel_strong_count(0), 1);
+
+        let serial: Vec<_> = batch_jobs(&buffer)
+            .into_iter()
+            .map(RenderJob::run)
+            .collect();
+        assert_eq!(outputs.len(), 16);
+        for (output, serial) in outputs.iter().zip(&serial) {
+            let output = output.as_ref().unwrap();
+            let serial = serial.as_ref().unwrap();
+            assert_eq!(output.number_of_channels(), 2);
+            assert_eq!(output.length(), RENDER_QUANTUM_SIZE * 8);
+            for channel in 0..2 {
+                assert_float_eq!(
+                    output.get_channel_data(channel),
+                    serial.get_channel_data(channel),
+                    abs_all <= 0.
+                );
+            }
+        }
+
+        // the jobs render different graphs
+        let first = outputs[0].as_ref().unwrap().get_channel_data(0);
+        let last = outputs[15].as_ref().unwrap().get_channel_data(0);
+        assert!(first != last);
+    }
+
+    #[test]
+    fn test_render_batch_panic() {
+        let mut jobs = batch_jobs(&AudioBuffer::from(vec![vec![0.5; 128]], 44_100.));
+        jobs.insert(
+            3,
+            RenderJob::new(1, RENDER_QUANTUM_SIZE, 44_100., |_| panic!("invalid graph")),
+        );
+        // invalid sample rate
+        jobs.push(RenderJob::new(1, RENDER_QUANTUM_SIZE, 0., |_| ()));
+
+        let outputs = render_batch(jobs, 3);
+        assert_eq!(outputs.len(), 18);
+        assert_eq!(
+            outputs[3].as_ref().unwrap_err(),
+            &RenderJobError::Panicked(String::from("invalid graph"))
+        );
+        assert!(matches!(outputs[17], Err(RenderJobError::Panicked(_))));
+        assert_eq!(outputs.iter().filter(|output| output.is_ok()).count(), 16);
+    }
+
+    #[test]
+    fn test_render_batch_empty() {
+        assert!(render_batch(vec![], 4).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "RangeError")]
+    fn test_render_batch_no_parallelism() {
+        let _ = render_batch(vec![], 0);
+    }
 }
@@ -2,7 +2,9 @@
 use crate::context::{AudioContextState, BaseAudioContext, ConcreteBaseAudioContext};
 use crate::media::MediaStream;
 use crate::node::{self, ChannelConfigOptions};
-use crate::AtomicF64;
+use crate::param::AudioParam;
+use crate::render::{CallbackClock, OutputTap, OverloadPolicy, XrunCounters, XrunStats};
+use crate::{AtomicF64, RENDER_QUANTUM_SIZE};
 
 use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
@@ -39,6 +41,29 @@ impl Default for AudioContextLatencyCategory {
     }
 }
 
+/// Bounds of the number of render quanta rendered ahead of the output callbacks
+const MIN_RENDER_AHEAD: usize = 2;
+const MAX_RENDER_AHEAD: usize = 4;
+
+impl AudioContextLatencyCategory {
+    /// Number of render quanta rendered ahead of the output callbacks, see
+    /// [`AudioContext::base_latency`]
+    // the quanta are clamped to a small positive number
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub(crate) fn render_ahead_quanta(&self, sample_rate: f32) -> usize {
+        match self {
+            Self::Interactive => MIN_RENDER_AHEAD,
+            Self::Balanced => 3,
+            Self::Playback => MAX_RENDER_AHEAD,
+            Self::Specific(t) => {
+                let quanta = (t * f64::from(sample_rate) / RENDER_QUANTUM_SIZE as f64).ceil();
+                // NaN converts to zero
+                (quanta.max(0.) as usize).clamp(MIN_RENDER_AHEAD, MAX_RENDER_AHEAD)
+            }
+        }
+    }
+}
+
 /// Specify the playback configuration for the [`AudioContext`] constructor.
 ///
 /// All fields are optional and will default to the value best suited for interactive playback on
@@ -46,13 +71,40 @@ impl Default for AudioContextLatencyCategory {
 ///
 /// Check the documentation of the [`AudioContext` constructor](AudioContext::new) for usage
 /// instructions.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct AudioContextOptions {
     /// Identify the type of playback, which affects
     /// tradeoffs between audio output latency and power consumption
     pub latency_hint: AudioContextLatencyCategory,
     /// Sample rate of the audio Context and audio output hardware
     pub sample_rate: Option<f32>,
+    /// Number of output channels to request, defaults to the channel count of the output device
+    ///
+    /// The device may not support the requested value, use
+    /// [`AudioDestinationNode::max_channels_count`](crate::node::AudioDestinationNode::max_channels_count)
+    /// to retrieve the negotiated number of channels.
+    pub max_channel_count: Option<usize>,
+    /// Degrade the rendering instead of glitching when the render thread is overloaded,
+    /// disabled by default
+    pub overload_policy: Option<OverloadPolicy>,
+    /// Apply dither when the output device only accepts integer samples (16 bit), enabled by
+    /// default
+    ///
+    /// The output is rendered in 32 bit floats, which are used when the device supports them.
+    /// The dither has no effect in that case.
+    pub dither: bool,
+}
+
+impl Default for AudioContextOptions {
+    fn default() -> Self {
+        Self {
+            latency_hint: AudioContextLatencyCategory::default(),
+            sample_rate: None,
+            max_channel_count: None,
+            overload_policy: None,
+            dither: true,
+        }
+    }
 }
 
 /// This interface represents an audio graph whose `AudioDestinationNode` is routed to a real-time
@@ -67,12 +119,32 @@ pub struct AudioContext {
     stream: Mutex<Option<Stream>>,
     /// delay between render and actual system audio output
     output_latency: Arc<AtomicF64>,
+    /// duration of the render quanta rendered ahead of the output callbacks
+    base_latency: f64,
+    /// statistics of the late output callbacks
+    xruns: Arc<XrunCounters>,
+    /// correlation between the rendered frames and the output callbacks
+    callback_clock: Arc<CallbackClock>,
+    /// gain applied to the output of the destination node
+    master_gain: AudioParam,
 }
 
 impl BaseAudioContext for AudioContext {
     fn base(&self) -> &ConcreteBaseAudioContext {
         &self.base
     }
+
+    fn current_time_interpolated(&self) -> f64 {
+        let running = self.state() == AudioContextState::Running;
+        self.callback_clock
+            .now(self.current_time(), self.sample_rate(), running)
+    }
+}
+
+impl Drop for AudioContext {
+    fn drop(&mut self) {
+        self.close_sync();
+    }
 }
 
 impl Default for AudioContext {
@@ -86,6 +158,17 @@ impl AudioContext {
     ///
     /// This will play live audio on the default output device.
     ///
+    /// The destination node is part of the audio graph from the first output callback, no
+    /// partially constructed graph is ever rendered. Nodes created after this call are added at
+    /// the next render quantum boundary.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the requested `max_channel_count` is zero or greater than 32, or if the
+    /// requested `sample_rate` is outside the
+    /// [[`MIN_SAMPLE_RATE`](crate::MIN_SAMPLE_RATE), [`MAX_SAMPLE_RATE`](crate::MAX_SAMPLE_RATE)]
+    /// range
+    ///
     /// ```no_run
     /// use web_audio_api::context::{AudioContext, AudioContextLatencyCategory, AudioContextOptions};
     ///
@@ -93,6 +176,7 @@ impl AudioContext {
     /// let opts = AudioContextOptions {
     ///     sample_rate: Some(44100.),
     ///     latency_hint: AudioContextLatencyCategory::Interactive,
+    ///     ..AudioContextOptions::default()
     /// };
     ///
     /// // Setup the audio context that will emit to your speakers
@@ -105,18 +189,37 @@ impl AudioContext {
     #[cfg(not(test))]
     #[must_use]
     pub fn new(options: AudioContextOptions) -> Self {
+        if let Some(sample_rate) = options.sample_rate {
+            crate::assert_valid_sample_rate(sample_rate);
+        }
+
         // track number of frames - synced from render thread to control thread
         let frames_played = Arc::new(AtomicU64::new(0));
         let frames_played_clone = frames_played.clone();
+        let overload_policy = options.overload_policy;
+        let latency_hint = options.latency_hint.clone();
 
         let output_latency = Arc::new(AtomicF64::new(0.));
         let output_latency_clone = output_latency.clone();
 
-        let (stream, config, sender) =
-            io::build_output(frames_played_clone, output_latency_clone, options);
+        let xruns = Arc::new(XrunCounters::default());
+        let xruns_clone = xruns.clone();
+
+        let callback_clock = Arc::new(CallbackClock::default());
+        let callback_clock_clone = callback_clock.clone();
+
+        let (stream, config, sender) = io::build_output(
+            frames_played_clone,
+            output_latency_clone,
+            xruns_clone,
+            callback_clock_clone,
+            options,
+        );
 
         let number_of_channels = usize::from(config.channels);
         let sample_rate = config.sample_rate.0 as f32;
+        let render_ahead = latency_hint.render_ahead_quanta(sample_rate);
+        let base_latency = (render_ahead * RENDER_QUANTUM_SIZE) as f64 / f64::from(sample_rate);
 
         let base = ConcreteBaseAudioContext::new(
             sample_rate,
@@ -126,11 +229,23 @@ impl AudioContext {
             false,
         );
         base.set_state(AudioContextState::Running);
+        if overload_policy.is_some() {
+            base.set_overload_policy(overload_policy);
+        }
+        let master_gain = base.master_gain();
+
+        // play the stream once the registration of the destination is sent, so the first
+        // callback renders the complete graph
+        stream.play().expect("Stream refused to play");
 
         Self {
             base,
+            master_gain,
             stream: Mutex::new(Some(stream)),
             output_latency,
+            base_latency,
+            xruns,
+            callback_clock,
         }
     }
 
@@ -139,11 +254,15 @@ impl AudioContext {
     #[allow(clippy::needless_pass_by_value)]
     pub fn new(options: AudioContextOptions) -> Self {
         let sample_rate = options.sample_rate.unwrap_or(44100.);
-        let number_of_channels = 2;
+        crate::assert_valid_sample_rate(sample_rate);
+        let number_of_channels = options.max_channel_count.unwrap_or(2);
+        crate::assert_valid_number_of_channels(number_of_channels);
 
         let (sender, _receiver) = crossbeam_channel::unbounded();
         let frames_played = Arc::new(AtomicU64::new(0));
         let output_latency = Arc::new(AtomicF64::new(0.));
+        let render_ahead = options.latency_hint.render_ahead_quanta(sample_rate);
+        let base_latency = (render_ahead * RENDER_QUANTUM_SIZE) as f64 / f64::from(sample_rate);
 
         let base = ConcreteBaseAudioContext::new(
             sample_rate,
@@ -153,22 +272,43 @@ impl AudioContext {
             false,
         );
         base.set_state(AudioContextState::Running);
+        if options.overload_policy.is_some() {
+            base.set_overload_policy(options.overload_policy);
+        }
+        let master_gain = base.master_gain();
 
         Self {
             base,
+            master_gain,
             output_latency,
+            base_latency,
+            xruns: Arc::default(),
+            callback_clock: Arc::default(),
         }
     }
 
+    /// K-rate [`AudioParam`] scaling the output of the destination node (master volume)
+    ///
+    /// Setting the gain to `0.` silences all output without disconnecting any nodes.
+    #[must_use]
+    pub fn master_gain(&self) -> &AudioParam {
+        &self.master_gain
+    }
+
     /// This represents the number of seconds of processing latency incurred by
     /// the `AudioContext` passing the audio from the `AudioDestinationNode`
     /// to the audio subsystem.
-    // We don't do any buffering between rendering the audio and sending
-    // it to the audio subsystem, so this value is zero. (see Gecko)
-    #[allow(clippy::unused_self)]
+    ///
+    /// The graph is rendered a few render quanta ahead of the output callbacks, so the output
+    /// buffers can hold any number of frames: 2 quanta for the
+    /// [`Interactive`](AudioContextLatencyCategory::Interactive) latency hint, 3 for
+    /// [`Balanced`](AudioContextLatencyCategory::Balanced) and 4 for
+    /// [`Playback`](AudioContextLatencyCategory::Playback). The
+    /// [`current_time`](BaseAudioContext::current_time) follows the rendered frames, it is ahead
+    /// of the audio reaching the output callbacks by up to this duration.
     #[must_use]
-    pub const fn base_latency(&self) -> f64 {
-        0.
+    pub fn base_latency(&self) -> f64 {
+        self.base_latency
     }
 
     /// The estimation in seconds of audio output latency, i.e., the interval
@@ -180,6 +320,66 @@ impl AudioContext {
         self.output_latency.load()
     }
 
+    /// Statistics of the late output callbacks (xruns)
+    ///
+    /// An output callback is late when the gap since the previous callback exceeds 1.5 times
+    /// the duration of the audio delivered by the previous callback. A late callback is
+    /// likely to be heard as a glitch.
+    #[must_use]
+    pub fn xrun_stats(&self) -> XrunStats {
+        self.xruns.stats()
+    }
+
+    /// Register a callback to run for each late output callback (xrun), with the gap since the
+    /// previous output callback in seconds
+    ///
+    /// The callback runs on a dedicated thread, not on the render thread. Only the last
+    /// registered callback is called. It is not called anymore once the context is closed.
+    pub fn set_onxrun<F: FnMut(f64) + Send + 'static>(&self, mut callback: F) {
+        let shutdown = Arc::clone(self.base().shutdown());
+        if shutdown.is_closed() {
+            return;
+        }
+
+        let (sender, receiver) = crossbeam_channel::bounded(32);
+        std::thread::spawn(move || {
+            // ends when the render thread drops the sender
+            for gap in receiver {
+                if shutdown.is_closed() {
+                    break;
+                }
+                callback(gap);
+            }
+        });
+
+        self.base().set_xrun_sender(Some(sender));
+    }
+
+    /// Unregister the callback set with [`set_onxrun`](Self::set_onxrun)
+    pub fn clear_onxrun(&self) {
+        self.base().set_xrun_sender(None);
+    }
+
+    /// Register a callback receiving the final output of each render quantum, as written to the
+    /// audio device: interleaved, after the master gain and the mapping to the output channels
+    ///
+    /// The callback is called with the samples and the number of channels. The quanta are
+    /// passed as they are rendered, which may be ahead of the device buffers. While the context
+    /// is suspended, the silence written to the device is passed as well.
+    ///
+    /// The callback runs on the render thread: it must not block, allocate or take locks, and
+    /// should be cheap (e.g. copy the samples to a lock-free ring buffer). Only the last
+    /// registered callback is called.
+    pub fn set_output_tap<F: FnMut(&[f32], usize) + Send + 'static>(&self, callback: F) {
+        let tap = OutputTap::new(self.base().max_channel_count(), callback);
+        self.base().set_output_tap(Some(tap));
+    }
+
+    /// Unregister the callback set with [`set_output_tap`](Self::set_output_tap)
+    pub fn clear_output_tap(&self) {
+        self.base().set_output_tap(None);
+    }
+
     /// Suspends the progression of time in the audio context.
     ///
     /// This will temporarily halt audio hardware access and reducing CPU/battery usage in the
@@ -199,10 +399,11 @@ impl AudioContext {
     pub fn suspend_sync(&self) {
         #[cfg(not(test))] // in tests, do not set up a cpal Stream
         if let Some(s) = self.stream.lock().unwrap().as_ref() {
+            // the processors see the new state if the stream renders once more before pausing
+            self.base().set_state(AudioContextState::Suspended);
             if let Err(e) = s.pause() {
                 panic!("Error suspending cpal stream: {:?}", e);
             }
-            self.base().set_state(AudioContextState::Suspended);
         }
     }
 
@@ -223,30 +424,46 @@ impl AudioContext {
     pub fn resume_sync(&self) {
         #[cfg(not(test))] // in tests, do not set up a cpal Stream
         if let Some(s) = self.stream.lock().unwrap().as_ref() {
+            self.base().set_state(AudioContextState::Running);
             if let Err(e) = s.play() {
                 panic!("Error resuming cpal stream: {:?}", e);
             }
-            self.base().set_state(AudioContextState::Running);
         }
     }
 
     /// Closes the `AudioContext`, releasing the system resources being used.
     ///
     /// This will not automatically release all `AudioContext`-created objects, but will suspend
-    /// the progression of the currentTime, and stop processing audio data.
+    /// the progression of the currentTime, and stop processing audio data. Dropping the
+    /// `AudioContext` closes it as well.
     ///
     /// This function operates synchronously and might block the current thread. An async version
     /// is currently not implemented.
     ///
+    /// # Callbacks and decodes
+    ///
+    /// Once this function is called:
+    ///
+    /// * the `ended` and `xrun` callbacks that have not started yet never run, a callback
+    ///   already running is not waited for
+    /// * the progress callbacks of the decodes are not called anymore
+    /// * the futures of the outstanding decodes resolve with a
+    ///   [`DecodeError::Closed`](crate::media::DecodeError::Closed) error, the decodes started
+    ///   afterwards fail with the same error
+    /// * the nodes and params may still be used, their changes are dropped
+    ///
+    /// The output tap is not called anymore when this function returns.
+    ///
     /// # Panics
     ///
-    /// Will panic when this function is called multiple times
+    /// Will panic if a previous call panicked while releasing the output stream
     // false positive due to #[cfg(not(test))]
     #[allow(clippy::missing_const_for_fn, clippy::unused_self)]
     pub fn close_sync(&self) {
+        // stop the callbacks and decodes before releasing the render thread
+        self.base().close();
         #[cfg(not(test))] // in tests, do not set up a cpal Stream
-        self.stream.lock().unwrap().take(); // will Drop
-        self.base().set_state(AudioContextState::Closed);
+        self.stream.lock().unwrap().take(); // will Drop, the processors are finalized
     }
 
     /// Creates a `MediaStreamAudioSourceNode` from a [`MediaStream`]
@@ -268,3 +485,96 @@ impl AudioContext {
         node::MediaStreamAudioDestinationNode::new(self, opts)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::AudioNode;
+
+    #[test]
+    fn test_render_ahead_quanta() {
+        use AudioContextLatencyCategory::*;
+
+        assert_eq!(Interactive.render_ahead_quanta(48_000.), 2);
+        assert_eq!(Balanced.render_ahead_quanta(48_000.), 3);
+        assert_eq!(Playback.render_ahead_quanta(48_000.), 4);
+
+        // 0.006 s at 48 kHz is 2.25 quanta
+        assert_eq!(Specific(0.006).render_ahead_quanta(48_000.), 3);
+        assert_eq!(Specific(0.).render_ahead_quanta(48_000.), 2);
+        assert_eq!(Specific(1.).render_ahead_quanta(48_000.), 4);
+        assert_eq!(Specific(f64::NAN).render_ahead_quanta(48_000.), 2);
+
+        let options = AudioContextOptions {
+            sample_rate: Some(48_000.),
+            latency_hint: Balanced,
+            ..AudioContextOptions::default()
+        };
+        let context = AudioContext::new(options);
+        let expected = (3 * RENDER_QUANTUM_SIZE) as f64 / 48_000.;
+        assert!((context.base_latency() - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_invalid_sample_rate_option() {
+        let options = AudioContextOptions {
+            sample_rate: Some(f32::NAN),
+            ..AudioContextOptions::default()
+        };
+        let _ = AudioContext::new(options);
+    }
+
+    #[test]
+    fn test_sample_rate_option_bounds() {
+        for sample_rate in [crate::MIN_SAMPLE_RATE, crate::MAX_SAMPLE_RATE] {
+            let options = AudioContextOptions {
+                sample_rate: Some(sample_rate),
+                ..AudioContextOptions::default()
+            };
+            let context = AudioContext::new(options);
+            assert_eq!(context.sample_rate(), sample_rate);
+        }
+    }
+
+    #[test]
+    fn test_max_channel_count_override() {
+        for max_channel_count in [2, 6, 32] {
+            let options = AudioContextOptions {
+                max_channel_count: Some(max_channel_count),
+                ..AudioContextOptions::default()
+            };
+            let context = AudioContext::new(options);
+
+            let dest = context.destination();
+            assert_eq!(dest.max_channels_count(), max_channel_count);
+            assert_eq!(dest.channel_count(), max_channel_count);
+
+            dest.set_channel_count(1);
+            assert_eq!(dest.channel_count(), 1);
+            dest.set_channel_count(max_channel_count);
+            assert_eq!(dest.channel_count(), max_channel_count);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_destination_channel_count_above_override() {
+        let options = AudioContextOptions {
+            max_channel_count: Some(6),
+            ..AudioContextOptions::default()
+        };
+        let context = AudioContext::new(options);
+        context.destination().set_channel_count(7);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_max_channel_count() {
+        let options = AudioContextOptions {
+            max_channel_count: Some(33),
+            ..AudioContextOptions::default()
+        };
+        let _ = AudioContext::new(options);
+    }
+}
@@ -0,0 +1,61 @@
+//! The `Transaction` type, to build a part of the audio graph as a whole
+
+use crate::context::{BaseAudioContext, ConcreteBaseAudioContext};
+
+/// Staged changes to an audio context, see [`BaseAudioContext::transaction`]
+///
+/// The `Transaction` is a [`BaseAudioContext`]: nodes are created and connected through it as
+/// usual. All the changes made on the calling thread while the transaction is open (node
+/// registrations, connections, automation events, start and stop times...) are held back, and
+/// submitted to the render thread at once when the transaction succeeds. They reach the audio
+/// graph in the same render quantum.
+///
+/// When the transaction fails, or panics, the nodes created through it are discarded: they never
+/// reach the audio graph, along with all the changes concerning them. Changing such a node
+/// afterwards (connecting it, starting it, automating its params...) panics. The changes made to
+/// the nodes created before the transaction are still passed on, as they are already visible on
+/// the control side.
+pub struct Transaction<'a> {
+    base: &'a ConcreteBaseAudioContext,
+    committed: bool,
+}
+
+impl std::fmt::Debug for Transaction<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Transaction")
+            .field("committed", &self.committed)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BaseAudioContext for Transaction<'_> {
+    fn base(&self) -> &ConcreteBaseAudioContext {
+        self.base
+    }
+}
+
+impl<'a> Transaction<'a> {
+    /// Open a transaction on the calling thread
+    pub(super) fn begin(base: &'a ConcreteBaseAudioContext) -> Self {
+        base.begin_transaction();
+        Self {
+            base,
+            committed: false,
+        }
+    }
+
+    /// Submit the staged changes to the render thread
+    pub(super) fn commit(mut self) {
+        self.committed = true;
+        self.base.commit_transaction();
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        // the closure returned an error or panicked
+        if !self.committed {
+            self.base.rollback_transaction();
+        }
+    }
+}
@@ -1,47 +1,261 @@
 //! Scheduler and Controller for precise timings
 
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
-use crate::AtomicF64;
+use crossbeam_channel::{Receiver, Sender, TrySendError};
+
+use crate::context::Shutdown;
+use crate::node::PlaybackState;
+use crate::{AtomicF64, RENDER_QUANTUM_SIZE};
+
+/// Playback state of a scheduled source over a render quantum, see [`Scheduler::playback`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Playback {
+    /// The source is silent during the whole quantum, it plays or ends in a next one. It must be
+    /// kept alive if it is scheduled, see [`Scheduler::pending_tail_time`]
+    Pending,
+    /// The source plays during the quantum, or a part of it
+    Playing,
+    /// The source has ended, or ends without having played, its processor can be released
+    Ended,
+}
 
 /// Helper struct to start and stop audio streams
+///
+/// The start and stop values are positions in sample frames since the creation of the
+/// context. A time scheduled in seconds is converted to a fractional position, so the sources
+/// keep their sub-sample accuracy, while a frame scheduled as such is stored exactly.
+///
+/// The control thread does not update the start and stop values directly, they are passed as
+/// control messages so they are ordered with respect to `AudioParam` events. The scheduled
+/// values are recorded separately on the control thread so they can be queried right away.
+///
+/// None of the values needs to be ordered with other atomics: `start` and `stop` are only
+/// accessed by the render thread, `scheduled_start` and `scheduled_stop` only by the control
+/// thread. The render thread applies the control messages before it advances the frame counter
+/// of the context, so the current time seen by the control thread is never ahead of the time
+/// the render thread applies a message at. A cancelled start is therefore checked again on the
+/// render thread: a start that has been reached is never undone.
+///
+/// The `has_started` and `has_stopped` flags are only accessed by the control thread, they
+/// guard against calling `start` or `stop` twice on the same source.
+///
+/// The `rendered` state is written by the render thread, through the [`SourceActivity`] of the
+/// source, and read by the control thread. It does not publish any other data.
 #[derive(Clone, Debug)]
 pub(crate) struct Scheduler {
     start: Arc<AtomicF64>,
     stop: Arc<AtomicF64>,
+    scheduled_start: Arc<AtomicF64>,
+    scheduled_stop: Arc<AtomicF64>,
+    has_started: Arc<AtomicBool>,
+    has_stopped: Arc<AtomicBool>,
+    rendered: Arc<AtomicU8>,
 }
 
+// values of `Scheduler::rendered`
+const RENDERED_NONE: u8 = 0;
+const RENDERED_PLAYING: u8 = 1;
+const RENDERED_FINISHED: u8 = 2;
+
 impl Scheduler {
     /// Create a new Scheduler. Initial playback state will be: inactive.
     pub fn new() -> Self {
         Self {
             start: Arc::new(AtomicF64::new(f64::MAX)),
             stop: Arc::new(AtomicF64::new(f64::MAX)),
+            scheduled_start: Arc::new(AtomicF64::new(f64::MAX)),
+            scheduled_stop: Arc::new(AtomicF64::new(f64::MAX)),
+            has_started: Arc::new(AtomicBool::new(false)),
+            has_stopped: Arc::new(AtomicBool::new(false)),
+            rendered: Arc::new(AtomicU8::new(RENDERED_NONE)),
         }
     }
 
-    /// Retrieve playback start value
-    pub fn get_start_at(&self) -> f64 {
+    /// Retrieve playback start frame
+    pub fn get_start_frame(&self) -> f64 {
         self.start.load()
     }
 
-    /// Schedule playback start at this timestamp
-    pub fn start_at(&self, start: f64) {
-        // todo panic on invalid values, or when already called
+    /// Check if a playback start has been scheduled
+    ///
+    /// A source that is not scheduled will never produce sound, so the render thread can free
+    /// it once its control handle is dropped.
+    pub fn is_scheduled(&self) -> bool {
+        self.get_start_frame() < f64::MAX
+    }
+
+    /// Return value of the processor of the source while its playback is [`Playback::Pending`]
+    ///
+    /// A scheduled source is kept alive by the render graph until it has played, even if its
+    /// control handle has been dropped. A source that was never started can be freed.
+    ///
+    /// Only called from the render thread
+    pub fn pending_tail_time(&self) -> bool {
+        self.is_scheduled()
+    }
+
+    /// Playback state of the source over the render quantum starting at `current_frame`
+    ///
+    /// A source plays the frames `f` such that `start <= f < stop`. A source whose stop is not
+    /// after its start thus plays nothing, it ends at its stop frame, without waiting for its
+    /// start frame.
+    ///
+    /// Only called from the render thread
+    pub fn playback(&self, current_frame: u64) -> Playback {
+        let start = self.get_start_frame();
+        let stop = self.get_stop_frame();
+        let current_frame = current_frame as f64;
+        let next_block_frame = current_frame + RENDER_QUANTUM_SIZE as f64;
+
+        if stop <= current_frame {
+            Playback::Ended
+        } else if start >= next_block_frame || stop <= start {
+            Playback::Pending
+        } else {
+            Playback::Playing
+        }
+    }
+
+    /// Validate a start or stop time passed by the control thread
+    ///
+    /// A negative time is in the past, it is clamped to zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the time is NaN or infinite
+    pub fn validate_time(when: f64, kind: &str) -> f64 {
+        assert!(
+            when.is_finite(),
+            "RangeError - {} time must be finite, got {:?}",
+            kind,
+            when
+        );
+        when.max(0.)
+    }
+
+    /// Convert a time in seconds to a frame position, see [`Self::start_at_frame`]
+    ///
+    /// A position within a millionth of a frame of a frame boundary is rounded to it, so a time
+    /// computed as `frame / sample_rate` starts on this exact frame.
+    pub fn time_to_frame(when: f64, sample_rate: f32) -> f64 {
+        let frame = when * f64::from(sample_rate);
+        let rounded = frame.round();
+        if (frame - rounded).abs() < 1e-6 {
+            rounded
+        } else {
+            frame
+        }
+    }
+
+    /// Schedule playback start at this frame position, a fractional position starts between
+    /// two frames
+    ///
+    /// Only called from the render thread, the start has been validated by
+    /// [`Self::validate_time`] and [`Self::set_scheduled_start`]
+    pub fn start_at_frame(&self, start: f64) {
+        debug_assert!(start.is_finite() && start >= 0.);
         self.start.store(start);
     }
 
-    /// Retrieve playback stop value
-    pub fn get_stop_at(&self) -> f64 {
+    /// Retrieve playback stop frame
+    pub fn get_stop_frame(&self) -> f64 {
         self.stop.load()
     }
 
-    /// Stop playback at this timestamp
-    pub fn stop_at(&self, stop: f64) {
-        // todo panic on invalid values, or when already called
+    /// Stop playback at this frame position, a fractional position stops between two frames
+    ///
+    /// Only called from the render thread, the stop has been validated by
+    /// [`Self::validate_time`] and [`Self::set_scheduled_stop`]
+    pub fn stop_at_frame(&self, stop: f64) {
+        debug_assert!(stop.is_finite() && stop >= 0.);
         self.stop.store(stop);
     }
+
+    /// Undo the playback start if it has not been reached at frame `now`, otherwise stop the
+    /// playback at frame `now` if it does not stop earlier
+    ///
+    /// Only called from the render thread
+    pub fn cancel_start(&self, now: u64) {
+        let now = now as f64;
+        if self.get_start_frame() > now {
+            self.start.store(f64::MAX);
+        } else if self.get_stop_frame() > now {
+            self.stop.store(now);
+        }
+    }
+
+    /// Start time as last scheduled by the control thread, `None` when not scheduled
+    pub fn scheduled_start(&self) -> Option<f64> {
+        let start = self.scheduled_start.load();
+        (start < f64::MAX).then_some(start)
+    }
+
+    /// Record the start time scheduled by the control thread
+    ///
+    /// # Panics
+    ///
+    /// Panics if a start has already been scheduled and not cancelled
+    pub fn set_scheduled_start(&self, start: f64) {
+        if self.has_started.swap(true, Ordering::Relaxed) {
+            panic!("InvalidStateError - cannot call `start` twice");
+        }
+
+        self.scheduled_start.store(start);
+    }
+
+    /// Check if a start has been scheduled by the control thread and not cancelled
+    pub fn has_started(&self) -> bool {
+        self.has_started.load(Ordering::Relaxed)
+    }
+
+    /// Forget the start time scheduled by the control thread, the source can be started again
+    pub fn clear_scheduled_start(&self) {
+        self.scheduled_start.store(f64::MAX);
+        self.has_started.store(false, Ordering::Relaxed);
+    }
+
+    /// Stop time as last scheduled by the control thread, `None` when not scheduled
+    pub fn scheduled_stop(&self) -> Option<f64> {
+        let stop = self.scheduled_stop.load();
+        (stop < f64::MAX).then_some(stop)
+    }
+
+    /// Record the stop time scheduled by the control thread
+    ///
+    /// # Panics
+    ///
+    /// Panics if the source has not been started, or if a stop has already been scheduled
+    pub fn set_scheduled_stop(&self, stop: f64) {
+        if !self.has_started() {
+            panic!("InvalidStateError - cannot call `stop` before `start`");
+        }
+        if self.has_stopped.swap(true, Ordering::Relaxed) {
+            panic!("InvalidStateError - cannot call `stop` twice");
+        }
+
+        self.scheduled_stop.store(stop);
+    }
+
+    /// Record an earlier stop time, when a start that has already been reached is cancelled
+    ///
+    /// Unlike [`Self::set_scheduled_stop`], a stop may have been scheduled already.
+    pub fn advance_scheduled_stop(&self, stop: f64) {
+        debug_assert!(self.has_started());
+        self.has_stopped.store(true, Ordering::Relaxed);
+        self.scheduled_stop.store(stop);
+    }
+
+    /// Playback state of the source, as seen by the control thread
+    pub fn playback_state(&self) -> PlaybackState {
+        match self.rendered.load(Ordering::Relaxed) {
+            RENDERED_FINISHED => PlaybackState::Finished,
+            RENDERED_PLAYING => PlaybackState::Playing,
+            _ if self.has_started() => PlaybackState::Scheduled,
+            _ => PlaybackState::Unscheduled,
+        }
+    }
 }
 
 impl Default for Scheduler {
@@ -54,6 +268,7 @@ impl Default for Scheduler {
 #[derive(Clone, Debug)]
 pub(crate) struct Controller {
     scheduler: Arc<Scheduler>,
+    /// independent of the loop points, so `Relaxed` ordering suffices
     loop_: Arc<AtomicBool>,
     loop_start: Arc<AtomicF64>,
     loop_end: Arc<AtomicF64>,
@@ -79,11 +294,11 @@ impl Controller {
     }
 
     pub fn loop_(&self) -> bool {
-        self.loop_.load(Ordering::SeqCst)
+        self.loop_.load(Ordering::Relaxed)
     }
 
     pub fn set_loop(&self, loop_: bool) {
-        self.loop_.store(loop_, Ordering::SeqCst);
+        self.loop_.store(loop_, Ordering::Relaxed);
     }
 
     pub fn loop_start(&self) -> f64 {
@@ -125,9 +340,281 @@ impl Default for Controller {
     }
 }
 
+/// Count of the scheduled sources that are currently producing audio
+///
+/// Shared between the control thread (reading) and the render thread (updating). The values are
+/// statistics, they do not publish any other data so `Relaxed` ordering suffices.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ActiveSourceCounter {
+    count: Arc<AtomicUsize>,
+    high_water_mark: Arc<AtomicUsize>,
+}
+
+impl ActiveSourceCounter {
+    /// Number of sources currently producing audio
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Maximum number of simultaneously active sources since the last reset
+    pub fn high_water_mark(&self) -> usize {
+        self.high_water_mark.load(Ordering::Relaxed)
+    }
+
+    /// Reset the high water mark to the current number of active sources
+    pub fn reset_high_water_mark(&self) {
+        self.high_water_mark.store(self.count(), Ordering::Relaxed);
+    }
+
+    fn increment(&self) {
+        let count = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+        self.high_water_mark.fetch_max(count, Ordering::Relaxed);
+    }
+
+    fn decrement(&self) {
+        self.count.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Activity state of a single scheduled source, held by its renderer
+///
+/// The source is counted as active from its first rendered quantum after the start time,
+/// until its processor reports it has finished. The transitions are published to the
+/// [`Scheduler`] of the source, see [`Scheduler::playback_state`].
+#[derive(Debug)]
+pub(crate) struct SourceActivity {
+    counter: ActiveSourceCounter,
+    rendered: Arc<AtomicU8>,
+    active: bool,
+}
+
+impl SourceActivity {
+    pub fn new(counter: ActiveSourceCounter, scheduler: &Scheduler) -> Self {
+        Self {
+            counter,
+            rendered: Arc::clone(&scheduler.rendered),
+            active: false,
+        }
+    }
+
+    /// Update the activity state, the shared counter only changes on transitions
+    ///
+    /// A source is only made inactive once it has ended, it is then finished even if it has
+    /// never been active.
+    pub fn set_active(&mut self, active: bool) {
+        if active && !self.active {
+            self.counter.increment();
+            self.rendered.store(RENDERED_PLAYING, Ordering::Relaxed);
+        } else if !active {
+            if self.active {
+                self.counter.decrement();
+            }
+            self.rendered.store(RENDERED_FINISHED, Ordering::Relaxed);
+        }
+
+        self.active = active;
+    }
+}
+
+impl Drop for SourceActivity {
+    fn drop(&mut self) {
+        // a dropped renderer has not necessarily ended, the playback state is left as is
+        if self.active {
+            self.counter.decrement();
+        }
+    }
+}
+
+type EndedCallback = Box<dyn FnOnce(f64) + Send>;
+
+/// Capacity of the queue of `ended` events of a context
+///
+/// A renderer finding the queue full keeps its event and sends it again in the next render
+/// quantum, so no event is lost when many sources end at once.
+const ENDED_QUEUE_CAPACITY: usize = 64;
+
+/// Callback of the `ended` event of a scheduled source, shared by both halves of the event
+#[derive(Default)]
+struct EndedSlot {
+    /// set while a callback is registered, so the renderer only queues events with a listener
+    registered: AtomicBool,
+    callback: Mutex<Option<EndedCallback>>,
+}
+
+impl EndedSlot {
+    /// Run the registered callback, at most once
+    fn call(&self, time: f64) {
+        self.registered.store(false, Ordering::Release);
+        let callback = self.callback.lock().unwrap().take();
+        if let Some(callback) = callback {
+            callback(time);
+        }
+    }
+}
+
+/// Callback slot of a source and its end time
+type EndedEvent = (Arc<EndedSlot>, f64);
+
+/// Queue of the `ended` events of the sources of a context, drained by a single dispatcher
+///
+/// The renderers push the events without blocking. The callbacks run on a dedicated thread,
+/// spawned with the first callback registered on the context. Under the `wasm` feature, where
+/// threads are not available, the renderer runs them instead, after each render quantum. The
+/// callbacks are not called anymore once the context is closed.
+#[derive(Clone)]
+pub(crate) struct EndedEvents {
+    sender: Sender<EndedEvent>,
+    /// taken by the dispatcher thread
+    #[cfg(not(feature = "wasm"))]
+    receiver: Arc<Mutex<Option<Receiver<EndedEvent>>>>,
+    #[cfg(feature = "wasm")]
+    receiver: Receiver<EndedEvent>,
+    shutdown: Arc<Shutdown>,
+}
+
+impl EndedEvents {
+    pub fn new(shutdown: Arc<Shutdown>) -> Self {
+        let (sender, receiver) = crossbeam_channel::bounded(ENDED_QUEUE_CAPACITY);
+
+        Self {
+            sender,
+            #[cfg(not(feature = "wasm"))]
+            receiver: Arc::new(Mutex::new(Some(receiver))),
+            #[cfg(feature = "wasm")]
+            receiver,
+            shutdown,
+        }
+    }
+
+    /// Spawn the dispatcher thread, if not running yet
+    #[cfg(not(feature = "wasm"))]
+    fn start_dispatcher(&self) {
+        if let Some(receiver) = self.receiver.lock().unwrap().take() {
+            let shutdown = Arc::clone(&self.shutdown);
+            std::thread::spawn(move || {
+                // ends when the context and all the sources are dropped
+                for (slot, time) in receiver {
+                    // the event may have been sent right before the context closed
+                    if shutdown.is_closed() {
+                        break;
+                    }
+                    slot.call(time);
+                }
+            });
+        }
+    }
+
+    /// Run the callbacks of the queued events on the calling thread
+    #[cfg(feature = "wasm")]
+    pub fn dispatch(&self) {
+        for (slot, time) in self.receiver.try_iter() {
+            if self.shutdown.is_closed() {
+                break;
+            }
+            slot.call(time);
+        }
+    }
+}
+
+/// Create the two halves of the `ended` event of a scheduled source
+pub(crate) fn ended_channel(events: &EndedEvents) -> (OnEnded, EndedNotifier) {
+    let slot = Arc::new(EndedSlot::default());
+
+    let on_ended = OnEnded {
+        slot: Arc::clone(&slot),
+        events: events.clone(),
+    };
+    let notifier = EndedNotifier {
+        slot: Some(slot),
+        pending_time: None,
+        sender: events.sender.clone(),
+    };
+
+    (on_ended, notifier)
+}
+
+/// Control side of the `ended` event of a scheduled source
+///
+/// The callback is run by the dispatcher of the context, see [`EndedEvents`]. Only the last
+/// registered callback is called. It is not called when the renderer is dropped before the
+/// source ended, when it is registered after the source ended, or once the context is closed.
+pub(crate) struct OnEnded {
+    slot: Arc<EndedSlot>,
+    events: EndedEvents,
+}
+
+impl OnEnded {
+    /// Register the callback, replacing the previous one if it has not been called yet
+    ///
+    /// The callback is dropped when the context is closed.
+    pub fn set<F: FnOnce(f64) + Send + 'static>(&self, callback: F) {
+        if self.events.shutdown.is_closed() {
+            return;
+        }
+
+        *self.slot.callback.lock().unwrap() = Some(Box::new(callback));
+        self.slot.registered.store(true, Ordering::Release);
+
+        #[cfg(not(feature = "wasm"))]
+        self.events.start_dispatcher();
+    }
+
+    /// Unregister the callback, a no-op when it has already been called
+    pub fn clear(&self) {
+        self.slot.registered.store(false, Ordering::Release);
+        self.slot.callback.lock().unwrap().take();
+    }
+}
+
+/// Render side of the `ended` event of a scheduled source
+pub(crate) struct EndedNotifier {
+    slot: Option<Arc<EndedSlot>>,
+    /// end time of the event waiting for room in the queue
+    pending_time: Option<f64>,
+    sender: Sender<EndedEvent>,
+}
+
+impl std::fmt::Debug for EndedNotifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EndedNotifier")
+            .field("pending", &self.slot.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl EndedNotifier {
+    /// Signal the end of the source at the given context time, only the first call is sent
+    ///
+    /// Returns true while the event is pending because the queue of the context is full, the
+    /// renderer should then call again in the next render quantum. The event keeps the time of
+    /// the first call.
+    pub fn notify(&mut self, time: f64) -> bool {
+        let slot = match self.slot.take() {
+            Some(slot) => slot,
+            None => return false,
+        };
+        let time = *self.pending_time.get_or_insert(time);
+
+        // nobody is listening, the event is dropped
+        if !slot.registered.load(Ordering::Acquire) {
+            return false;
+        }
+
+        match self.sender.try_send((slot, time)) {
+            Err(TrySendError::Full((slot, _))) => {
+                self.slot = Some(slot);
+                true
+            }
+            // the control side may be gone, nobody is listening then
+            Ok(()) | Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
 
     #[test]
     fn test_controller() {
@@ -137,4 +624,268 @@ mod tests {
         assert!(controller.loop_start() == 0.);
         assert!(controller.loop_end() == f64::MAX);
     }
+
+    #[test]
+    fn test_cancel_start() {
+        let scheduler = Scheduler::new();
+        assert_eq!(scheduler.scheduled_start(), None);
+        assert_eq!(scheduler.scheduled_stop(), None);
+
+        scheduler.set_scheduled_start(1.);
+        scheduler.start_at_frame(100.);
+        assert_eq!(scheduler.scheduled_start(), Some(1.));
+
+        // too late, the start has been reached: the source stops instead
+        scheduler.cancel_start(150);
+        assert!(scheduler.is_scheduled());
+        assert_eq!(scheduler.get_stop_frame(), 150.);
+        // an earlier stop is kept
+        scheduler.cancel_start(200);
+        assert_eq!(scheduler.get_stop_frame(), 150.);
+
+        let scheduler = Scheduler::new();
+        scheduler.set_scheduled_start(1.);
+        scheduler.start_at_frame(100.);
+        scheduler.cancel_start(50);
+        assert!(!scheduler.is_scheduled());
+        assert_eq!(scheduler.get_stop_frame(), f64::MAX);
+
+        // the source can be started again once the start is cancelled
+        scheduler.clear_scheduled_start();
+        assert_eq!(scheduler.scheduled_start(), None);
+        scheduler.set_scheduled_start(2.);
+        assert_eq!(scheduler.scheduled_start(), Some(2.));
+    }
+
+    #[test]
+    fn test_validate_time() {
+        assert_eq!(Scheduler::validate_time(1.5, "start"), 1.5);
+        assert_eq!(Scheduler::validate_time(0., "start"), 0.);
+        assert_eq!(Scheduler::validate_time(-1., "start"), 0.);
+        assert_eq!(Scheduler::validate_time(-0., "start"), 0.);
+
+        for &when in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY].iter() {
+            let result = std::panic::catch_unwind(|| Scheduler::validate_time(when, "stop"));
+            assert!(result.is_err(), "{} should be rejected", when);
+        }
+    }
+
+    #[test]
+    fn test_time_to_frame() {
+        assert_eq!(Scheduler::time_to_frame(0.5, 48_000.), 24_000.);
+        assert_eq!(Scheduler::time_to_frame(0.5 / 48_000., 48_000.), 0.5);
+
+        // the time of a frame converts back to the exact frame
+        for &sample_rate in [44_100., 48_000., 22_050.].iter() {
+            for frame in 0..100_000_u64 {
+                let when = frame as f64 / f64::from(sample_rate);
+                assert_eq!(Scheduler::time_to_frame(when, sample_rate), frame as f64);
+            }
+        }
+    }
+
+    #[test]
+    fn test_playback() {
+        let quantum = RENDER_QUANTUM_SIZE as u64;
+        let scheduler = Scheduler::new();
+        assert_eq!(scheduler.playback(0), Playback::Pending);
+
+        scheduler.start_at_frame(2. * quantum as f64);
+        scheduler.stop_at_frame(3. * quantum as f64);
+        assert_eq!(scheduler.playback(0), Playback::Pending);
+        assert_eq!(scheduler.playback(quantum), Playback::Pending);
+        assert_eq!(scheduler.playback(quantum + 1), Playback::Playing);
+        assert_eq!(scheduler.playback(2 * quantum), Playback::Playing);
+        assert_eq!(scheduler.playback(3 * quantum - 1), Playback::Playing);
+        assert_eq!(scheduler.playback(3 * quantum), Playback::Ended);
+
+        // a stop at or before the start plays nothing, and ends at the stop frame
+        for &stop in [quantum, 2 * quantum].iter() {
+            let scheduler = Scheduler::new();
+            scheduler.start_at_frame(2. * quantum as f64);
+            scheduler.stop_at_frame(stop as f64);
+            assert_eq!(scheduler.playback(0), Playback::Pending);
+            assert_eq!(scheduler.playback(stop - 1), Playback::Pending);
+            assert_eq!(scheduler.playback(stop), Playback::Ended);
+        }
+
+        // a start between two frames plays from the next frame on
+        let scheduler = Scheduler::new();
+        scheduler.start_at_frame(quantum as f64 - 0.5);
+        assert_eq!(scheduler.playback(0), Playback::Playing);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError - cannot call `start` twice")]
+    fn test_start_twice() {
+        let scheduler = Scheduler::new();
+        scheduler.set_scheduled_start(0.);
+        scheduler.set_scheduled_start(5.);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError - cannot call `stop` twice")]
+    fn test_stop_twice() {
+        let scheduler = Scheduler::new();
+        scheduler.set_scheduled_start(0.);
+        scheduler.set_scheduled_stop(1.);
+        scheduler.set_scheduled_stop(2.);
+    }
+
+    #[test]
+    fn test_advance_scheduled_stop() {
+        let scheduler = Scheduler::new();
+        scheduler.set_scheduled_start(0.);
+        scheduler.set_scheduled_stop(2.);
+        scheduler.advance_scheduled_stop(1.);
+        assert_eq!(scheduler.scheduled_stop(), Some(1.));
+
+        // the source counts as stopped
+        let scheduler = Scheduler::new();
+        scheduler.set_scheduled_start(0.);
+        scheduler.advance_scheduled_stop(1.);
+        let result = std::panic::catch_unwind(|| scheduler.set_scheduled_stop(2.));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError - cannot call `stop` before `start`")]
+    fn test_stop_before_start() {
+        let scheduler = Scheduler::new();
+        scheduler.set_scheduled_stop(1.);
+    }
+
+    #[test]
+    fn test_source_activity() {
+        let counter = ActiveSourceCounter::default();
+        let scheduler = Scheduler::new();
+        let mut a = SourceActivity::new(counter.clone(), &scheduler);
+        let mut b = SourceActivity::new(counter.clone(), &scheduler);
+
+        a.set_active(true);
+        a.set_active(true); // no double counting
+        assert_eq!(counter.count(), 1);
+
+        b.set_active(true);
+        assert_eq!(counter.count(), 2);
+        assert_eq!(counter.high_water_mark(), 2);
+
+        a.set_active(false);
+        assert_eq!(counter.count(), 1);
+        assert_eq!(counter.high_water_mark(), 2);
+
+        counter.reset_high_water_mark();
+        assert_eq!(counter.high_water_mark(), 1);
+
+        // dropping an active source releases it
+        drop(b);
+        assert_eq!(counter.count(), 0);
+        assert_eq!(counter.high_water_mark(), 1);
+    }
+
+    #[test]
+    fn test_playback_state() {
+        let scheduler = Scheduler::new();
+        let mut activity = SourceActivity::new(ActiveSourceCounter::default(), &scheduler);
+        assert_eq!(scheduler.playback_state(), PlaybackState::Unscheduled);
+
+        scheduler.set_scheduled_start(1.);
+        assert_eq!(scheduler.playback_state(), PlaybackState::Scheduled);
+        scheduler.clear_scheduled_start();
+        assert_eq!(scheduler.playback_state(), PlaybackState::Unscheduled);
+
+        scheduler.set_scheduled_start(1.);
+        activity.set_active(true);
+        assert_eq!(scheduler.playback_state(), PlaybackState::Playing);
+        activity.set_active(false);
+        assert_eq!(scheduler.playback_state(), PlaybackState::Finished);
+
+        // a dropped renderer leaves the state as is
+        let scheduler = Scheduler::new();
+        let mut activity = SourceActivity::new(ActiveSourceCounter::default(), &scheduler);
+        scheduler.set_scheduled_start(1.);
+        activity.set_active(true);
+        drop(activity);
+        assert_eq!(scheduler.playback_state(), PlaybackState::Playing);
+
+        // a source ending without having played is finished
+        let scheduler = Scheduler::new();
+        let mut activity = SourceActivity::new(ActiveSourceCounter::default(), &scheduler);
+        scheduler.set_scheduled_start(1.);
+        activity.set_active(false);
+        assert_eq!(scheduler.playback_state(), PlaybackState::Finished);
+    }
+
+    #[test]
+    fn test_ended_channel() {
+        let events = EndedEvents::new(Arc::default());
+        let (on_ended, mut notifier) = ended_channel(&events);
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let first = sender.clone();
+        on_ended.set(move |time| first.send(("first", time)).unwrap());
+        // replaces the first callback
+        on_ended.set(move |time| sender.send(("second", time)).unwrap());
+
+        notifier.notify(1.5);
+        notifier.notify(2.); // sent only once
+        #[cfg(feature = "wasm")]
+        events.dispatch();
+
+        let timeout = std::time::Duration::from_secs(1);
+        assert_eq!(receiver.recv_timeout(timeout), Ok(("second", 1.5)));
+        assert!(receiver.recv_timeout(timeout).is_err());
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn test_ended_queue_full() {
+        let events = EndedEvents::new(Arc::default());
+        let (on_ended, mut notifier) = ended_channel(&events);
+
+        // register the callback without starting the dispatcher, and fill the queue
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        *on_ended.slot.callback.lock().unwrap() =
+            Some(Box::new(move |time| sender.send(time).unwrap()));
+        on_ended.slot.registered.store(true, Ordering::Release);
+        for _ in 0..ENDED_QUEUE_CAPACITY {
+            events
+                .sender
+                .try_send((Arc::new(EndedSlot::default()), 0.))
+                .unwrap();
+        }
+
+        // the event is kept until there is room in the queue
+        assert!(notifier.notify(1.5));
+        events.start_dispatcher();
+        while notifier.notify(2.) {
+            std::thread::yield_now();
+        }
+        assert!(!notifier.notify(2.5));
+
+        let timeout = std::time::Duration::from_secs(1);
+        assert_eq!(receiver.recv_timeout(timeout), Ok(1.5));
+        assert!(receiver.recv_timeout(timeout).is_err());
+    }
+
+    #[test]
+    fn test_ended_channel_closed() {
+        let context = OfflineAudioContext::new(1, 1, 44_100.);
+        let (on_ended, mut notifier) = ended_channel(context.base().ended_events());
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        on_ended.set(move |time| sender.send(time).unwrap());
+
+        // dropping the context closes it, the pending callback never runs
+        drop(context);
+        notifier.notify(1.5);
+
+        let timeout = std::time::Duration::from_secs(1);
+        assert!(receiver.recv_timeout(timeout).is_err());
+
+        // nor the callbacks registered afterwards
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        on_ended.set(move |time| sender.send(time).unwrap());
+        assert!(receiver.recv_timeout(timeout).is_err());
+    }
 }
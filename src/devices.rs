@@ -0,0 +1,67 @@
+//! Query the audio output devices, before creating an `AudioContext`
+
+use cpal::traits::{DeviceTrait, HostTrait};
+
+/// Number of channels used when no output device can be queried
+const FALLBACK_CHANNEL_COUNT: usize = 2;
+
+/// Information about an audio output device
+#[derive(Clone, Debug)]
+pub struct OutputDeviceInfo {
+    name: String,
+    max_channel_count: usize,
+}
+
+impl OutputDeviceInfo {
+    /// Name of the device, as reported by the audio host
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Maximum number of output channels supported by the device
+    pub fn max_channel_count(&self) -> usize {
+        self.max_channel_count
+    }
+}
+
+/// Number of channels of the default output device, in its default configuration
+///
+/// This is the `max_channel_count` an [`AudioContext`](crate::context::AudioContext) will have
+/// when no explicit value is requested in its options. Returns 2 if there is no output device.
+#[must_use]
+pub fn default_output_channel_count() -> usize {
+    cpal::default_host()
+        .default_output_device()
+        .and_then(|device| device.default_output_config().ok())
+        .map_or(FALLBACK_CHANNEL_COUNT, |config| {
+            usize::from(config.channels())
+        })
+}
+
+/// List the output devices of the default audio host
+#[must_use]
+pub fn output_devices() -> Vec<OutputDeviceInfo> {
+    let devices = match cpal::default_host().output_devices() {
+        Ok(devices) => devices,
+        Err(e) => {
+            log::warn!("unable to enumerate output devices: {}", e);
+            return vec![];
+        }
+    };
+
+    devices
+        .filter_map(|device| {
+            let name = device.name().ok()?;
+            let max_channel_count = device
+                .supported_output_configs()
+                .ok()?
+                .map(|config| usize::from(config.channels()))
+                .max()?;
+
+            Some(OutputDeviceInfo {
+                name,
+                max_channel_count,
+            })
+        })
+        .collect()
+}
@@ -9,46 +9,74 @@ use crate::{AtomicF64, RENDER_QUANTUM_SIZE};
 use cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
     BuildStreamError, Device, OutputCallbackInfo, SampleFormat, SampleRate as CpalSampleRate,
-    Stream, StreamConfig, SupportedBufferSize,
+    Stream, StreamConfig, StreamError, SupportedBufferSize,
 };
 
 use crate::buffer::AudioBuffer;
 use crate::context::{AudioContextLatencyCategory, AudioContextOptions};
 use crate::media::MicrophoneRender;
-use crate::render::RenderThread;
+use crate::render::{CallbackClock, RenderThread, XrunCounters};
 
 use crossbeam_channel::{Receiver, Sender};
 
 /// Creates an output stream
 ///
+/// The graph is rendered on a thread of its own, a few render quanta ahead of the output
+/// callbacks.
+///
 /// # Arguments:
 ///
 /// * `device` - the output audio device on which the stream is created
 /// * `sample_format` - audio sample format of the stream
 /// * `config` - stream configuration
 /// * `render` - the render thread which process the audio data
+/// * `render_ahead` - number of render quanta rendered ahead of the output callbacks
 fn spawn_output_stream(
     device: &Device,
     sample_format: SampleFormat,
     config: &StreamConfig,
-    mut render: RenderThread,
+    render: RenderThread,
+    render_ahead: usize,
 ) -> Result<Stream, BuildStreamError> {
-    let err_fn = |err| log::error!("an error occurred on the output audio stream: {}", err);
+    let xruns = render.xrun_counters();
+    let err_fn = move |err: StreamError| {
+        log::error!("an error occurred on the output audio stream: {}", err);
+        // some backends report their underruns
+        if let StreamError::BackendSpecific { err } = &err {
+            let description = err.description.to_lowercase();
+            if description.contains("underrun") || description.contains("xrun") {
+                xruns.record_reported();
+            }
+        }
+    };
+
+    // the render loop stops once the output is dropped, i.e. when the stream is dropped or
+    // could not be built
+    let frames_per_callback = match config.buffer_size {
+        cpal::BufferSize::Fixed(frames) => frames as usize,
+        cpal::BufferSize::Default => 0,
+    };
+    let (render, mut output) = render.into_render_ahead(render_ahead, frames_per_callback);
+    let thread = std::thread::Builder::new()
+        .name("web-audio-api render".into())
+        .spawn(move || render.run())
+        .expect("failed to spawn the render thread");
+    output.set_waker(thread.thread().clone());
 
     match sample_format {
         SampleFormat::F32 => device.build_output_stream(
             config,
-            move |d: &mut [f32], i: &OutputCallbackInfo| render.render(d, i),
+            move |d: &mut [f32], i: &OutputCallbackInfo| output.render(d, i),
             err_fn,
         ),
         SampleFormat::U16 => device.build_output_stream(
             config,
-            move |d: &mut [u16], i: &OutputCallbackInfo| render.render(d, i),
+            move |d: &mut [u16], i: &OutputCallbackInfo| output.render(d, i),
             err_fn,
         ),
         SampleFormat::I16 => device.build_output_stream(
             config,
-            move |d: &mut [i16], i: &OutputCallbackInfo| render.render(d, i),
+            move |d: &mut [i16], i: &OutputCallbackInfo| output.render(d, i),
             err_fn,
         ),
     }
@@ -93,6 +121,10 @@ struct StreamConfigsBuilder {
     supported: cpal::SupportedStreamConfig,
     /// the prefered config is a primary config optionnaly modified by the user options `AudioContextOptions`
     prefered: cpal::StreamConfig,
+    /// dither the output when the sample format is an integer format
+    dither: bool,
+    /// latency hint, deciding the number of render quanta rendered ahead
+    latency_hint: AudioContextLatencyCategory,
 }
 
 impl StreamConfigsBuilder {
@@ -112,18 +144,51 @@ impl StreamConfigsBuilder {
             device,
             supported: supported.clone(),
             prefered: supported.into(),
+            dither: true,
+            latency_hint: AudioContextLatencyCategory::default(),
         }
     }
 
     /// returns the supported stream config from with other configs are derived
     ///
+    /// The graph is rendered in `f32`, so a `f32` config matching the default config of the
+    /// device is preferred: it is converted without loss.
+    ///
     /// # Argument
     ///
     /// * `device` - the audio device on which the stream is broadcast
     fn get_supported_config(device: &cpal::Device) -> cpal::SupportedStreamConfig {
-        device
+        let default = device
             .default_output_config()
-            .expect("error while querying configs")
+            .expect("error while querying configs");
+
+        if default.sample_format() == SampleFormat::F32 {
+            return default;
+        }
+
+        let sample_rate = default.sample_rate();
+        let float_config = device
+            .supported_output_configs()
+            .ok()
+            .and_then(|mut configs| {
+                configs.find(|c| {
+                    c.sample_format() == SampleFormat::F32
+                        && c.channels() == default.channels()
+                        && c.min_sample_rate() <= sample_rate
+                        && c.max_sample_rate() >= sample_rate
+                })
+            });
+
+        match float_config {
+            Some(config) => config.with_sample_rate(sample_rate),
+            None => {
+                log::info!(
+                    "Output in {:?} samples, the device does not support f32",
+                    default.sample_format()
+                );
+                default
+            }
+        }
     }
 
     /// set preferred sample rate
@@ -132,8 +197,18 @@ impl StreamConfigsBuilder {
         self.prefered.sample_rate.0 = v as u32;
     }
 
+    /// set preferred number of output channels
+    fn with_channel_count(&mut self, v: usize) {
+        crate::assert_valid_number_of_channels(v);
+        self.prefered.channels = u16::try_from(v).unwrap();
+    }
+
+    /// dither the output of integer formats
+    fn with_dither(&mut self, v: bool) {
+        self.dither = v;
+    }
+
     /// buffer size
-    #[allow(clippy::needless_pass_by_value)]
     fn with_latency_hint(&mut self, v: AudioContextLatencyCategory) {
         let buffer_size: u32 = u32::try_from(RENDER_QUANTUM_SIZE).unwrap();
         let default_buffer_size = match self.supported.buffer_size() {
@@ -174,6 +249,7 @@ impl StreamConfigsBuilder {
         };
 
         self.prefered.buffer_size = cpal::BufferSize::Fixed(calculated);
+        self.latency_hint = v;
     }
 
     /// builds `StreamConfigs`
@@ -190,6 +266,10 @@ struct StreamConfigs {
     sample_format: cpal::SampleFormat,
     /// the prefered config of the output stream
     prefered: cpal::StreamConfig,
+    /// dither the output when the sample format is an integer format
+    dither: bool,
+    /// latency hint, deciding the number of render quanta rendered ahead
+    latency_hint: AudioContextLatencyCategory,
     /// in case of failure to build the stream with `prefered`
     /// a fallback config is used to spawn the stream
     fallback: cpal::StreamConfig,
@@ -201,6 +281,8 @@ impl StreamConfigs {
         let StreamConfigsBuilder {
             supported,
             prefered,
+            dither,
+            latency_hint,
             ..
         } = builder;
 
@@ -209,6 +291,8 @@ impl StreamConfigs {
         Self {
             sample_format,
             prefered,
+            dither,
+            latency_hint,
             fallback: supported.into(),
         }
     }
@@ -224,6 +308,10 @@ struct OutputStreamer {
     frames_played: Arc<AtomicU64>,
     /// delay between render and actual system audio output
     output_latency: Arc<AtomicF64>,
+    /// statistics of the late output callbacks
+    xruns: Arc<XrunCounters>,
+    /// correlation between the rendered frames and the output callbacks
+    callback_clock: Arc<CallbackClock>,
     /// communication channel between control and render thread (sender part)
     sender: Option<Sender<ControlMessage>>,
     /// the output stream
@@ -239,6 +327,8 @@ impl OutputStreamer {
         configs: StreamConfigs,
         frames_played: Arc<AtomicU64>,
         output_latency: Arc<AtomicF64>,
+        xruns: Arc<XrunCounters>,
+        callback_clock: Arc<CallbackClock>,
     ) -> Self {
         let host = cpal::default_host();
         let device = host
@@ -250,6 +340,8 @@ impl OutputStreamer {
             configs,
             frames_played,
             output_latency,
+            xruns,
+            callback_clock,
             sender: None,
             stream: None,
             falled_back: false,
@@ -270,17 +362,26 @@ impl OutputStreamer {
         self.sender = Some(sender);
 
         // spawn the render thread
-        let renderer = RenderThread::new(
+        let mut renderer = RenderThread::new(
             sample_rate,
             config.channels as usize,
             receiver,
             self.frames_played.clone(),
-            self.output_latency.clone(),
         );
+        renderer.set_output_latency(self.output_latency.clone());
+        renderer.set_xrun_counters(self.xruns.clone());
+        renderer.set_callback_clock(self.callback_clock.clone());
+        renderer.set_dither(self.configs.dither);
 
         log::debug!("Attempt output stream with prefered config: {:?}", &config);
-        let spawned =
-            spawn_output_stream(&self.device, self.configs.sample_format, config, renderer);
+        let render_ahead = self.configs.latency_hint.render_ahead_quanta(sample_rate);
+        let spawned = spawn_output_stream(
+            &self.device,
+            self.configs.sample_format,
+            config,
+            renderer,
+            render_ahead,
+        );
 
         match spawned {
             Ok(stream) => {
@@ -295,16 +396,6 @@ impl OutputStreamer {
         }
     }
 
-    /// playes the output stream
-    fn play(self) -> Self {
-        self.stream
-            .as_ref()
-            .expect("Stream needs to exist to be played")
-            .play()
-            .expect("Stream refused to play");
-        self
-    }
-
     /// returns the output stream infos
     fn get_output_stream(self) -> (Stream, StreamConfig, Sender<ControlMessage>) {
         if self.falled_back {
@@ -347,19 +438,27 @@ impl OrFallback for Result<OutputStreamer, OutputStreamer> {
                 streamer.sender = Some(sender);
 
                 // spawn the render thread
-                let renderer = RenderThread::new(
+                let mut renderer = RenderThread::new(
                     sample_rate,
                     config.channels as usize,
                     receiver,
                     streamer.frames_played.clone(),
-                    streamer.output_latency.clone(),
                 );
-
+                renderer.set_output_latency(streamer.output_latency.clone());
+                renderer.set_xrun_counters(streamer.xruns.clone());
+                renderer.set_callback_clock(streamer.callback_clock.clone());
+                renderer.set_dither(streamer.configs.dither);
+
+                let render_ahead = streamer
+                    .configs
+                    .latency_hint
+                    .render_ahead_quanta(sample_rate);
                 let spawned = spawn_output_stream(
                     &streamer.device,
                     streamer.configs.sample_format,
                     config,
                     renderer,
+                    render_ahead,
                 );
                 let stream = spawned.expect("OutputStream build failed with default config");
                 streamer.stream = Some(stream);
@@ -370,10 +469,14 @@ impl OrFallback for Result<OutputStreamer, OutputStreamer> {
 }
 
 /// Builds the output
+///
+/// The stream is not played yet, so the context can send its initial control messages first.
 #[allow(clippy::redundant_pub_crate)]
 pub(crate) fn build_output(
     frames_played: Arc<AtomicU64>,
     output_latency: Arc<AtomicF64>,
+    xruns: Arc<XrunCounters>,
+    callback_clock: Arc<CallbackClock>,
     options: AudioContextOptions,
 ) -> (Stream, StreamConfig, Sender<ControlMessage>) {
     let mut builder = StreamConfigsBuilder::new();
@@ -383,26 +486,78 @@ pub(crate) fn build_output(
         builder.with_sample_rate(v);
     }
 
+    // set specific number of channels if requested, the fallback config uses the device default
+    if let Some(v) = options.max_channel_count {
+        builder.with_channel_count(v);
+    }
+
     // always try to set a decent buffer size
     builder.with_latency_hint(options.latency_hint);
 
+    builder.with_dither(options.dither);
+
     let configs = builder.build();
 
-    let streamer = OutputStreamer::new(configs, frames_played, output_latency)
-        .spawn()
-        .or_fallback()
-        .play();
+    let streamer = OutputStreamer::new(
+        configs,
+        frames_played,
+        output_latency,
+        xruns,
+        callback_clock,
+    )
+    .spawn()
+    .or_fallback();
 
     streamer.get_output_stream()
 }
 
+/// Options of an input stream
+pub(crate) struct InputOptions {
+    /// Name of the input device, the default input device if `None`
+    pub device: Option<String>,
+    /// Requested sample rate, the device default if `None`
+    pub sample_rate: Option<f32>,
+    /// Requested number of channels, the device default if `None`
+    pub channels: Option<usize>,
+    /// Number of chunks buffered between the audio thread and the consumer
+    pub capacity: usize,
+    /// Incremented for each chunk dropped because the consumer did not keep up
+    pub overruns: Arc<AtomicU64>,
+}
+
 /// Builds the input
 #[allow(clippy::needless_pass_by_value)]
 pub fn build_input(options: AudioContextOptions) -> (Stream, StreamConfig, Receiver<AudioBuffer>) {
+    let options = InputOptions {
+        device: None,
+        sample_rate: options.sample_rate,
+        channels: None,
+        capacity: 3, // todo, use buffering to smooth frame drops
+        overruns: Arc::new(AtomicU64::new(0)),
+    };
+
+    build_input_with(options)
+}
+
+/// Builds the input stream of the given device
+///
+/// # Panics
+///
+/// Will panic if the device is not available, or if no stream can be built
+pub(crate) fn build_input_with(
+    options: InputOptions,
+) -> (Stream, StreamConfig, Receiver<AudioBuffer>) {
     let host = cpal::default_host();
-    let device = host
-        .default_input_device()
-        .expect("no input device available");
+    let device = match &options.device {
+        None => host
+            .default_input_device()
+            .expect("no input device available"),
+        Some(name) => host
+            .input_devices()
+            .expect("error while querying input devices")
+            .find(|device| device.name().ok().as_ref() == Some(name))
+            .unwrap_or_else(|| panic!("NotFoundError - no input device named {:?}", name)),
+    };
     log::info!("Input device: {:?}", device.name());
 
     let mut supported_configs_range = device
@@ -434,13 +589,12 @@ pub fn build_input(options: AudioContextOptions) -> (Stream, StreamConfig, Recei
     if let Some(sample_rate) = options.sample_rate {
         config.sample_rate = CpalSampleRate(sample_rate as u32);
     }
+    if let Some(channels) = options.channels {
+        config.channels = u16::try_from(channels).unwrap_or(config.channels);
+    }
 
-    let sample_rate = config.sample_rate.0 as f32;
-    let channels = config.channels as usize;
-
-    let smoothing = 3; // todo, use buffering to smooth frame drops
-    let (sender, mut receiver) = crossbeam_channel::bounded(smoothing);
-    let renderer = MicrophoneRender::new(channels, sample_rate, sender);
+    let (sender, mut receiver) = crossbeam_channel::bounded(options.capacity);
+    let renderer = MicrophoneRender::new(&config, sender, options.overruns.clone());
 
     let maybe_stream = spawn_input_stream(&device, sample_format, &config, renderer);
     // our RENDER_QUANTUM_SIZEd config may not be supported, in that case, use the default config
@@ -448,17 +602,18 @@ pub fn build_input(options: AudioContextOptions) -> (Stream, StreamConfig, Recei
         Ok(stream) => stream,
         Err(e) => {
             log::warn!(
-                "Output stream failed to build: {:?}, retry with default config {:?}",
+                "Input stream failed to build: {:?}, retry with default config {:?}",
                 e,
                 default_config
             );
 
             // setup a new comms channel
-            let (sender, receiver2) = crossbeam_channel::bounded(smoothing);
+            let (sender, receiver2) = crossbeam_channel::bounded(options.capacity);
             receiver = receiver2; // overwrite earlier
+            config = default_config;
 
-            let renderer = MicrophoneRender::new(channels, sample_rate, sender);
-            spawn_input_stream(&device, sample_format, &default_config, renderer)
+            let renderer = MicrophoneRender::new(&config, sender, options.overruns);
+            spawn_input_stream(&device, sample_format, &config, renderer)
                 .expect("Unable to spawn input stream with default config")
         }
     };
@@ -34,10 +34,63 @@
 //! std::thread::sleep(std::time::Duration::from_secs(4));
 //! ```
 
+//!
+//! # Embedded targets
+//!
+//! The `std` feature (enabled by default) provides the audio contexts, the nodes and media
+//! decoding, the `cpal` feature (enabled by default) the `AudioContext` playing on the audio
+//! hardware. Without `std`, only the render core is built, on top of `core` and `alloc`: the
+//! [`AudioRenderQuantum`](render::AudioRenderQuantum) buffers, the
+//! [`AudioProcessor`](render::AudioProcessor) trait and the [`Graph`](render::Graph) executor,
+//! along with the DSP of the nodes that can be used on their own in a processor: the
+//! evaluation of the automation events of a param ([`AudioParamProcessor`]), the
+//! [`BiquadFilter`](node::BiquadFilter) and the band-limited [`Oscillator`](node::Oscillator).
+//! The float math is then provided by `libm`.
+//!
+//! ```toml
+//! web-audio-api = { version = "0.20", default-features = false, features = ["alloc"] }
+//! ```
+//!
+//! The graph is then driven manually, see `examples/manual_render.rs`.
+//!
+//! # WebAssembly
+//!
+//! The `wasm` feature builds for `wasm32-unknown-unknown`, without the `cpal` backend. The
+//! `OfflineAudioContext` renders on the calling thread, and a
+//! [`ManualAudioContext`](context::ManualAudioContext) can be driven by a JS `AudioWorklet`
+//! through the [`WasmRenderHandle`](wasm::WasmRenderHandle), see `examples/wasm`.
+//!
+//! ```toml
+//! web-audio-api = { version = "0.20", default-features = false, features = ["wasm"] }
+//! ```
+//!
+//! # Channels of the source nodes
+//!
+//! The channel config of a node (`channel_count`, `channel_count_mode` and
+//! `channel_interpretation`) only defines how its inputs are mixed, see
+//! [`ChannelConfigOptions`](node::ChannelConfigOptions). Source nodes have no inputs, so it is
+//! accepted and validated as for any node, but it does not change their output: the number of
+//! output channels of a source is defined by the source itself. The oscillators, the
+//! `ConstantSourceNode` and the `TestSignalNode` are mono, an `AudioBufferSourceNode` follows
+//! the channels of its buffer.
+//!
+//! A mono source is up-mixed by the nodes it is connected to. To get several identical channels
+//! at the source, e.g. to feed a `ChannelSplitterNode`, set
+//! [`OscillatorOptions::output_channels`](node::OscillatorOptions::output_channels): the
+//! waveform is computed once and shared by the output channels.
+
+#![cfg_attr(not(feature = "std"), no_std)]
 #![warn(clippy::missing_panics_doc)]
 #![deny(trivial_numeric_casts)]
 
-use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+#[cfg(not(any(feature = "std", feature = "alloc")))]
+compile_error!("web-audio-api requires the `std` or the `alloc` feature");
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::{AtomicU32, Ordering};
 
 /// Render quantum size, the audio graph is rendered in blocks of RENDER_QUANTUM_SIZE samples
 /// see. <https://webaudio.github.io/web-audio-api/#render-quantum>
@@ -46,32 +99,81 @@ pub const RENDER_QUANTUM_SIZE: usize = 128;
 /// Maximum number of channels for audio processing
 pub const MAX_CHANNELS: usize = 32;
 
+/// Lowest sample rate supported by the audio contexts and buffers, in Hz
+pub const MIN_SAMPLE_RATE: f32 = 3_000.;
+
+/// Highest sample rate supported by the audio contexts and buffers, in Hz
+pub const MAX_SAMPLE_RATE: f32 = 768_000.;
+
+#[cfg(feature = "std")]
 mod buffer;
+#[cfg(feature = "std")]
 pub use buffer::*;
 
+#[cfg(feature = "std")]
 pub mod context;
+#[cfg(feature = "std")]
 pub(crate) mod control;
+#[cfg(feature = "cpal")]
+pub mod devices;
+#[cfg(feature = "std")]
+pub mod loudness;
+#[cfg(feature = "std")]
 pub mod media;
+
+#[cfg(not(feature = "std"))]
+mod math;
+
+#[cfg(feature = "std")]
 pub mod node;
+#[cfg(not(feature = "std"))]
+pub mod node {
+    //! Channel configuration and DSP of the audio nodes, the nodes themselves require the `std`
+    //! feature
+    mod biquad_filter;
+    pub use biquad_filter::*;
+    mod channel_config;
+    pub use channel_config::*;
+    mod oscillator;
+    pub use oscillator::*;
+}
 
 mod param;
 pub use param::*;
 
+#[cfg(feature = "std")]
 mod periodic_wave;
+#[cfg(feature = "std")]
 pub use periodic_wave::*;
 
 pub mod render;
 
+#[cfg(feature = "std")]
 mod spatial;
+#[cfg(feature = "std")]
 pub use spatial::AudioListener;
 
-#[cfg(not(test))]
+#[cfg(all(feature = "std", any(test, feature = "test-utils")))]
+pub mod testing;
+
+#[cfg(feature = "std")]
+pub mod voice;
+
+#[cfg(all(feature = "cpal", not(test)))]
 mod io;
 
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "std")]
 mod analysis;
+#[cfg(feature = "std")]
 mod message;
 
 /// Atomic float 32, only `load` and `store` are supported, no arithmetics
+///
+/// Loads use `Acquire` and stores `Release` ordering: each value stands on its own, no total
+/// order with other atomics is needed.
 #[derive(Debug)]
 pub(crate) struct AtomicF32 {
     inner: AtomicU32,
@@ -85,22 +187,27 @@ impl AtomicF32 {
         }
     }
 
+    #[cfg(feature = "std")]
     pub fn load(&self) -> f32 {
-        f32::from_ne_bytes(self.inner.load(Ordering::SeqCst).to_ne_bytes())
+        f32::from_ne_bytes(self.inner.load(Ordering::Acquire).to_ne_bytes())
     }
 
     pub fn store(&self, v: f32) {
         self.inner
-            .store(u32::from_ne_bytes(v.to_ne_bytes()), Ordering::SeqCst)
+            .store(u32::from_ne_bytes(v.to_ne_bytes()), Ordering::Release)
     }
 }
 
 /// Atomic float 64, only `load` and `store` are supported, no arithmetics
+///
+/// Loads use `Acquire` and stores `Release` ordering, see [`AtomicF32`].
+#[cfg(feature = "std")]
 #[derive(Debug)]
 pub(crate) struct AtomicF64 {
     inner: AtomicU64,
 }
 
+#[cfg(feature = "std")]
 impl AtomicF64 {
     pub fn new(v: f64) -> Self {
         Self {
@@ -109,12 +216,12 @@ impl AtomicF64 {
     }
 
     pub fn load(&self) -> f64 {
-        f64::from_ne_bytes(self.inner.load(Ordering::SeqCst).to_ne_bytes())
+        f64::from_ne_bytes(self.inner.load(Ordering::Acquire).to_ne_bytes())
     }
 
     pub fn store(&self, v: f64) {
         self.inner
-            .store(u64::from_ne_bytes(v.to_ne_bytes()), Ordering::SeqCst)
+            .store(u64::from_ne_bytes(v.to_ne_bytes()), Ordering::Release)
     }
 }
 
@@ -129,17 +236,17 @@ impl AtomicF64 {
 /// # Panics
 ///
 /// This function will panic if:
-/// - the given sample rate is zero
+/// - the given sample rate is outside the [`MIN_SAMPLE_RATE`, `MAX_SAMPLE_RATE`] range, or NaN
 ///
+#[cfg(feature = "std")]
 #[track_caller]
 #[inline(always)]
 pub(crate) fn assert_valid_sample_rate(sample_rate: f32) {
-    // 1000 Hertz is a just a random cutoff, but it helps a if someone accidentally puts a
-    // timestamp in the sample_rate variable
-    if sample_rate <= 1000. {
+    // the range also catches a timestamp accidentally given as sample rate
+    if !(MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE).contains(&sample_rate) {
         panic!(
-            "NotSupportedError - Invalid sample rate: {:?}, should be greater than 1000",
-            sample_rate
+            "NotSupportedError - Invalid sample rate: {:?} is outside range [{:?}, {:?}]",
+            sample_rate, MIN_SAMPLE_RATE, MAX_SAMPLE_RATE
         );
     }
 }
@@ -171,6 +278,7 @@ pub(crate) fn assert_valid_number_of_channels(number_of_channels: usize) {
 /// This function will panic if:
 /// - the given channel number is greater than or equal to the given number of channels.
 ///
+#[cfg(feature = "std")]
 #[track_caller]
 #[inline(always)]
 pub(crate) fn assert_valid_channel_number(channel_number: usize, number_of_channels: usize) {
@@ -215,9 +323,35 @@ mod tests {
         assert_valid_sample_rate(100.);
     }
 
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_invalid_sample_rate_nan() {
+        assert_valid_sample_rate(f32::NAN);
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_invalid_sample_rate_too_large() {
+        assert_valid_sample_rate(1e9);
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_invalid_sample_rate_below_min() {
+        assert_valid_sample_rate(MIN_SAMPLE_RATE - 1.);
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_invalid_sample_rate_above_max() {
+        assert_valid_sample_rate(MAX_SAMPLE_RATE + 1.);
+    }
+
     #[test]
     fn test_valid_sample_rate() {
         assert_valid_sample_rate(48000.);
+        assert_valid_sample_rate(MIN_SAMPLE_RATE);
+        assert_valid_sample_rate(MAX_SAMPLE_RATE);
     }
 
     #[test]
@@ -0,0 +1,262 @@
+//! Loudness measurement following EBU R128 / ITU-R BS.1770
+//!
+//! - see also: [`LoudnessMeterNode`](crate::node::LoudnessMeterNode) for live metering
+
+use std::f64::consts::PI;
+
+use crate::buffer::AudioBuffer;
+use crate::node::Coefficients;
+use crate::MAX_CHANNELS;
+
+/// Duration of the gating blocks and of the momentary loudness window, in seconds
+pub(crate) const MOMENTARY_WINDOW: f64 = 0.4;
+/// Duration of the short-term loudness window, in seconds
+pub(crate) const SHORT_TERM_WINDOW: f64 = 3.;
+/// Step between two gating blocks (75% overlap), in seconds
+pub(crate) const BLOCK_STEP: f64 = 0.1;
+
+/// Absolute gating threshold, in LUFS
+const ABSOLUTE_GATE: f64 = -70.;
+/// Relative gating threshold, in LU below the absolute-gated loudness
+const RELATIVE_GATE: f64 = -10.;
+
+/// Weighting of a channel in the loudness sum, for a layout of `number_of_channels`
+///
+/// The surround channels of the 5.0 and 5.1 layouts are weighted by +1.5 dB and the LFE
+/// channel is ignored. Other layouts use a weight of 1 for all channels.
+pub(crate) fn channel_weight(channel: usize, number_of_channels: usize) -> f64 {
+    match (number_of_channels, channel) {
+        // L, R, C, SL, SR
+        (5, 3 | 4) => 1.41,
+        // L, R, C, LFE, SL, SR
+        (6, 3) => 0.,
+        (6, 4 | 5) => 1.41,
+        _ => 1.,
+    }
+}
+
+/// Loudness in LUFS of a weighted sum of channel mean squares
+pub(crate) fn mean_square_to_lufs(mean_square: f64) -> f64 {
+    -0.691 + 10. * mean_square.log10()
+}
+
+/// K-weighting filter, i.e. a high shelf followed by a high pass, for all channels
+pub(crate) struct KWeighting {
+    shelf: Coefficients,
+    high_pass: Coefficients,
+    /// filter states, as (shelf s1, shelf s2, high pass s1, high pass s2)
+    states: [[f64; 4]; MAX_CHANNELS],
+}
+
+impl KWeighting {
+    /// Create the K-weighting filters for the given sample rate
+    ///
+    /// The filter parameters are fitted to the coefficients given at 48kHz in BS.1770, so
+    /// that they can be computed for any sample rate.
+    pub(crate) fn new(sample_rate: f32) -> Self {
+        let sample_rate = f64::from(sample_rate);
+
+        // stage 1, high shelf modelling the acoustic effect of the head
+        let f0 = 1_681.974_450_955_533;
+        let gain = 3.999_843_853_973_347;
+        let q = 0.707_175_236_955_419_6;
+        let k = (PI * f0 / sample_rate).tan();
+        let vh = 10_f64.powf(gain / 20.);
+        let vb = vh.powf(0.499_666_774_154_541_6);
+        let a0 = 1. + k / q + k * k;
+        let shelf = Coefficients::from_normalized(
+            (vh + vb * k / q + k * k) / a0,
+            2. * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+            2. * (k * k - 1.) / a0,
+            (1. - k / q + k * k) / a0,
+        );
+
+        // stage 2, RLB high pass
+        let f0 = 38.135_470_876_024_44;
+        let q = 0.500_327_037_323_877_3;
+        let k = (PI * f0 / sample_rate).tan();
+        let a0 = 1. + k / q + k * k;
+        let high_pass = Coefficients::from_normalized(
+            1.,
+            -2.,
+            1.,
+            2. * (k * k - 1.) / a0,
+            (1. - k / q + k * k) / a0,
+        );
+
+        Self {
+            shelf,
+            high_pass,
+            states: [[0.; 4]; MAX_CHANNELS],
+        }
+    }
+
+    /// Filter a sample of the given channel
+    #[inline]
+    pub(crate) fn tick(&mut self, input: f32, channel: usize) -> f32 {
+        let [s1, s2, s3, s4] = &mut self.states[channel];
+        let shelved = self.shelf.tick(input, s1, s2);
+        self.high_pass.tick(shelved, s3, s4)
+    }
+}
+
+/// Integrated loudness of the buffer, in LUFS, as defined in EBU R128
+///
+/// The K-weighted signal is measured over 400ms blocks overlapping by 75%, then gated with
+/// the -70 LUFS absolute and the -10 LU relative thresholds.
+///
+/// Returns `f64::NEG_INFINITY` if the buffer is shorter than 400ms or only contains silence.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::fs::File;
+/// use web_audio_api::context::{BaseAudioContext, OfflineAudioContext};
+/// use web_audio_api::loudness;
+///
+/// let context = OfflineAudioContext::new(2, 1, 48_000.);
+/// let file = File::open("samples/sample.wav").unwrap();
+/// let buffer = context.decode_audio_data_sync(file).unwrap();
+///
+/// println!("{:.1} LUFS", loudness::integrated_lufs(&buffer));
+/// ```
+#[must_use]
+pub fn integrated_lufs(buffer: &AudioBuffer) -> f64 {
+    let number_of_channels = buffer.number_of_channels();
+    let sample_rate = f64::from(buffer.sample_rate());
+    let segment_len = (BLOCK_STEP * sample_rate).round() as usize;
+    let segments_per_block = (MOMENTARY_WINDOW / BLOCK_STEP).round() as usize;
+
+    if segment_len == 0 || buffer.length() < segment_len * segments_per_block {
+        return f64::NEG_INFINITY;
+    }
+
+    // weighted sum of the squared K-weighted samples, per 100ms segment
+    let number_of_segments = buffer.length() / segment_len;
+    let mut segments = vec![0.; number_of_segments];
+    let mut filter = KWeighting::new(buffer.sample_rate());
+
+    for channel in 0..number_of_channels {
+        let weight = channel_weight(channel, number_of_channels);
+        let data = buffer.get_channel_data(channel);
+
+        data.chunks(segment_len)
+            .zip(segments.iter_mut())
+            .for_each(|(chunk, segment)| {
+                let energy: f64 = chunk
+                    .iter()
+                    .map(|&v| f64::from(filter.tick(v, channel)).powi(2))
+                    .sum();
+                *segment += weight * energy;
+            });
+    }
+
+    // gating blocks
+    let block_len = (segment_len * segments_per_block) as f64;
+    let blocks: Vec<f64> = segments
+        .windows(segments_per_block)
+        .map(|w| w.iter().sum::<f64>() / block_len)
+        .collect();
+
+    let gated_mean = |threshold: f64| {
+        let (sum, count) = blocks
+            .iter()
+            .filter(|&&z| mean_square_to_lufs(z) > threshold)
+            .fold((0., 0_usize), |(sum, count), z| (sum + z, count + 1));
+
+        if count == 0 {
+            None
+        } else {
+            Some(sum / count as f64)
+        }
+    };
+
+    let absolute_gated = match gated_mean(ABSOLUTE_GATE) {
+        Some(z) => z,
+        None => return f64::NEG_INFINITY,
+    };
+    let relative_gate = mean_square_to_lufs(absolute_gated) + RELATIVE_GATE;
+
+    match gated_mean(relative_gate.max(ABSOLUTE_GATE)) {
+        Some(z) => mean_square_to_lufs(z),
+        None => f64::NEG_INFINITY,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use float_eq::assert_float_eq;
+
+    // sine at 1kHz with the given peak level in dBFS
+    fn sine(level: f32, duration: f32, sample_rate: f32) -> Vec<f32> {
+        let amplitude = 10_f32.powf(level / 20.);
+        let length = (duration * sample_rate) as usize;
+
+        (0..length)
+            .map(|i| amplitude * (2. * std::f32::consts::PI * 1000. * i as f32 / sample_rate).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_ebu_stereo_sine() {
+        // EBU Tech 3341, test cases 1 & 2
+        for level in [-23., -33.] {
+            let channel = sine(level, 5., 48_000.);
+            let buffer = AudioBuffer::from(vec![channel.clone(), channel], 48_000.);
+
+            assert_float_eq!(integrated_lufs(&buffer), level as f64, abs <= 0.1);
+        }
+
+        // other sample rate
+        let channel = sine(-23., 5., 44_100.);
+        let buffer = AudioBuffer::from(vec![channel.clone(), channel], 44_100.);
+        assert_float_eq!(integrated_lufs(&buffer), -23., abs <= 0.1);
+    }
+
+    #[test]
+    fn test_surround_weighting() {
+        let sample_rate = 48_000.;
+        let tone = sine(-23., 2., sample_rate);
+        let silence = vec![0.; tone.len()];
+
+        let measure = |number_of_channels: usize, channel: usize| {
+            let mut channels = vec![silence.clone(); number_of_channels];
+            channels[channel] = tone.clone();
+            integrated_lufs(&AudioBuffer::from(channels, sample_rate))
+        };
+
+        // a single front channel is 3dB below the stereo reference
+        let front = measure(6, 2);
+        assert_float_eq!(front, -26.01, abs <= 0.1);
+
+        // surround channels are weighted by +1.5dB
+        for (number_of_channels, channel) in [(5, 3), (5, 4), (6, 4), (6, 5)] {
+            let surround = measure(number_of_channels, channel);
+            assert_float_eq!(surround - front, 1.49, abs <= 0.01);
+        }
+
+        // LFE is ignored
+        assert_eq!(measure(6, 3), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_gating() {
+        let sample_rate = 48_000.;
+
+        // silence and too short buffers
+        let buffer = AudioBuffer::from(vec![vec![0.; 48_000]], sample_rate);
+        assert_eq!(integrated_lufs(&buffer), f64::NEG_INFINITY);
+        let buffer = AudioBuffer::from(vec![sine(-23., 0.3, sample_rate)], sample_rate);
+        assert_eq!(integrated_lufs(&buffer), f64::NEG_INFINITY);
+
+        // a long quiet passage, more than 10 LU below, does not lower the loudness
+        // (only the few blocks overlapping the transition are counted)
+        let mut channel = sine(-23., 5., sample_rate);
+        channel.extend(sine(-60., 20., sample_rate));
+        let buffer = AudioBuffer::from(vec![channel.clone(), channel], sample_rate);
+        assert_float_eq!(integrated_lufs(&buffer), -23., abs <= 0.2);
+    }
+}
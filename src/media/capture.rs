@@ -0,0 +1,530 @@
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crossbeam_channel::{Receiver, RecvTimeoutError};
+
+use crate::buffer::{AudioBuffer, ChannelData};
+use crate::media::Resampler;
+use crate::RENDER_QUANTUM_SIZE;
+
+#[cfg(not(test))]
+use crate::io::{self, InputOptions};
+#[cfg(not(test))]
+use crate::media::mic::private::StreamHolder;
+#[cfg(not(test))]
+use std::sync::Mutex;
+
+/// Time to wait for the first input chunk before counting an underrun
+const INITIAL_TIMEOUT: Duration = Duration::from_secs(1);
+/// Minimum time to wait for an input chunk before counting an underrun
+const MIN_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Options for recording the input of a device with [`record_input`]
+#[derive(Clone, Debug, Default)]
+pub struct CaptureOptions {
+    /// Name of the input device, the default input device if `None`
+    pub device: Option<String>,
+    /// Sample rate of the recording, the sample rate of the device if `None`
+    pub sample_rate: Option<f32>,
+    /// Number of channels of the recording, the channel count of the device if `None`
+    pub channels: Option<usize>,
+    /// Duration of the recording in seconds, the recording runs until stopped if `None`
+    pub duration: Option<f64>,
+}
+
+/// Source of input chunks, i.e. the audio thread of the input device
+pub(crate) trait InputSource: Send + 'static {
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<AudioBuffer, RecvTimeoutError>;
+}
+
+impl InputSource for Receiver<AudioBuffer> {
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<AudioBuffer, RecvTimeoutError> {
+        Receiver::recv_timeout(self, timeout)
+    }
+}
+
+/// State shared between the [`CaptureHandle`] and the collector thread
+#[derive(Default)]
+struct CaptureState {
+    stop: AtomicBool,
+    finished: AtomicBool,
+    underruns: AtomicU64,
+    overruns: Arc<AtomicU64>,
+    /// Number of frames received, at the sample rate of the recording
+    frames_in: AtomicUsize,
+}
+
+/// Turns the [`InputSource`] into a [`MediaStream`](crate::media::MediaStream) of chunks with
+/// the sample rate and channel count of the recording
+struct CaptureStream {
+    source: Box<dyn InputSource>,
+    state: Arc<CaptureState>,
+    sample_rate: f32,
+    number_of_channels: usize,
+    max_frames: usize,
+    timeout: Duration,
+}
+
+impl CaptureStream {
+    /// Adapt the channel count: mono input is copied to all channels, other layouts are
+    /// truncated or padded with silence
+    fn adapt_channels(&self, buffer: AudioBuffer) -> AudioBuffer {
+        if buffer.number_of_channels() == self.number_of_channels {
+            return buffer;
+        }
+
+        let sample_rate = buffer.sample_rate();
+        let length = buffer.length();
+        let input = buffer.channels();
+        let channels = (0..self.number_of_channels)
+            .map(|i| match input.len() {
+                1 => input[0].clone(),
+                n if i < n => input[i].clone(),
+                _ => ChannelData::new(length),
+            })
+            .collect();
+
+        AudioBuffer::from_channels(channels, sample_rate)
+    }
+}
+
+impl Iterator for CaptureStream {
+    type Item = Result<AudioBuffer, Box<dyn Error + Send + Sync>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.state.stop.load(Ordering::SeqCst)
+                || self.state.frames_in.load(Ordering::SeqCst) >= self.max_frames
+            {
+                return None;
+            }
+
+            match self.source.recv_timeout(self.timeout) {
+                Ok(buffer) => {
+                    self.timeout = Duration::from_secs_f64(4. * buffer.duration()).max(MIN_TIMEOUT);
+
+                    let mut buffer = self.adapt_channels(buffer);
+                    buffer.resample(self.sample_rate);
+                    self.state
+                        .frames_in
+                        .fetch_add(buffer.length(), Ordering::SeqCst);
+
+                    return Some(Ok(buffer));
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    log::debug!("input underrun");
+                    self.state.underruns.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+    }
+}
+
+/// Handle of a running recording, see [`record_input`]
+///
+/// The recording runs on a dedicated thread and is not tied to an audio context. Dropping the
+/// handle stops the recording.
+pub struct CaptureHandle {
+    state: Arc<CaptureState>,
+    thread: Option<JoinHandle<AudioBuffer>>,
+    sample_rate: f32,
+    number_of_channels: usize,
+
+    #[cfg(not(test))]
+    stream: Option<StreamHolder>,
+}
+
+impl CaptureHandle {
+    /// Spawn the collector thread, chunking the input in render quanta
+    fn spawn<F: FnMut(&AudioBuffer) + Send + 'static>(
+        source: Box<dyn InputSource>,
+        sample_rate: f32,
+        number_of_channels: usize,
+        duration: Option<f64>,
+        keep_buffer: bool,
+        overruns: Arc<AtomicU64>,
+        mut callback: F,
+    ) -> Self {
+        crate::assert_valid_sample_rate(sample_rate);
+        crate::assert_valid_number_of_channels(number_of_channels);
+
+        let state = Arc::new(CaptureState {
+            overruns,
+            ..CaptureState::default()
+        });
+        let max_frames = match duration {
+            Some(duration) => (duration.max(0.) * sample_rate as f64).round() as usize,
+            None => usize::MAX,
+        };
+
+        let stream = CaptureStream {
+            source,
+            state: state.clone(),
+            sample_rate,
+            number_of_channels,
+            max_frames,
+            timeout: INITIAL_TIMEOUT,
+        };
+
+        let thread_state = state.clone();
+        let thread = std::thread::spawn(move || {
+            let mut recording =
+                AudioBuffer::from(vec![Vec::new(); number_of_channels], sample_rate);
+            let mut frames_out = 0;
+
+            for chunk in Resampler::new(sample_rate, RENDER_QUANTUM_SIZE, stream) {
+                let mut chunk = chunk.unwrap(); // the capture stream never yields errors
+
+                // trim the silence padding the last chunk, and the frames beyond the duration
+                let frames_in = thread_state.frames_in.load(Ordering::SeqCst);
+                let remaining = frames_in.min(max_frames) - frames_out;
+                if remaining == 0 {
+                    break;
+                }
+                if chunk.length() > remaining {
+                    chunk.split_off(remaining);
+                }
+                frames_out += chunk.length();
+
+                callback(&chunk);
+                if keep_buffer {
+                    recording.extend(&chunk);
+                }
+
+                if frames_out >= max_frames {
+                    break;
+                }
+            }
+
+            thread_state.finished.store(true, Ordering::SeqCst);
+            recording
+        });
+
+        Self {
+            state,
+            thread: Some(thread),
+            sample_rate,
+            number_of_channels,
+            #[cfg(not(test))]
+            stream: None,
+        }
+    }
+
+    /// Sample rate of the recording
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Number of channels of the recording
+    pub fn number_of_channels(&self) -> usize {
+        self.number_of_channels
+    }
+
+    /// Number of times the input device did not deliver audio in time
+    pub fn underruns(&self) -> u64 {
+        self.state.underruns.load(Ordering::Relaxed)
+    }
+
+    /// Number of input chunks dropped because the recording did not keep up
+    pub fn overruns(&self) -> u64 {
+        self.state.overruns.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` once the recording reached its duration or the input ended
+    pub fn is_finished(&self) -> bool {
+        self.state.finished.load(Ordering::SeqCst)
+    }
+
+    /// Stop the recording and return the recorded audio
+    ///
+    /// The returned buffer is empty when the chunks are streamed to a callback, see
+    /// [`record_input_with_callback`].
+    pub fn stop(mut self) -> AudioBuffer {
+        self.state.stop.store(true, Ordering::SeqCst);
+        self.join()
+    }
+
+    /// Wait for the recording to reach its duration and return the recorded audio
+    ///
+    /// This blocks forever when the recording has no duration and the input does not end.
+    pub fn wait(mut self) -> AudioBuffer {
+        self.join()
+    }
+
+    /// Release the input device and collect the recording
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the recording thread panicked
+    fn join(&mut self) -> AudioBuffer {
+        let recording = self
+            .thread
+            .take()
+            .map(|thread| thread.join().expect("recording thread panicked"));
+
+        #[cfg(not(test))]
+        if let Some(stream) = self.stream.take() {
+            stream.close();
+        }
+
+        recording.unwrap()
+    }
+}
+
+impl Drop for CaptureHandle {
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            self.state.stop.store(true, Ordering::SeqCst);
+            self.join();
+        }
+    }
+}
+
+/// Record the input of a device, without setting up an audio context
+///
+/// The input is resampled and up/down-mixed to the requested sample rate and channel count.
+/// Call [`CaptureHandle::stop`] or [`CaptureHandle::wait`] to retrieve the recorded audio.
+///
+/// # Warning
+///
+/// This abstraction is not part of the Web Audio API and does not aim at implementing
+/// the full MediaDevices API. It is only provided for convenience reasons.
+///
+/// # Panics
+///
+/// Will panic if:
+///
+/// * The input device is not available
+/// * The requested sample rate or number of channels is invalid
+///
+/// # Example
+///
+/// ```no_run
+/// use web_audio_api::media::{record_input, CaptureOptions};
+///
+/// let options = CaptureOptions {
+///     sample_rate: Some(48_000.),
+///     channels: Some(1),
+///     duration: Some(5.),
+///     ..CaptureOptions::default()
+/// };
+/// let recording = record_input(options).wait();
+/// assert_eq!(recording.length(), 5 * 48_000);
+/// ```
+#[cfg(not(test))]
+pub fn record_input(options: CaptureOptions) -> CaptureHandle {
+    spawn_capture(options, true, |_| ())
+}
+
+/// Record the input of a device, streaming the recorded chunks to a callback
+///
+/// The callback runs on the recording thread and is called with chunks of
+/// [`RENDER_QUANTUM_SIZE`] frames, except for the last one which may be shorter. See
+/// [`record_input`] for the other details.
+///
+/// # Panics
+///
+/// Will panic if:
+///
+/// * The input device is not available
+/// * The requested sample rate or number of channels is invalid
+#[cfg(not(test))]
+pub fn record_input_with_callback<F: FnMut(&AudioBuffer) + Send + 'static>(
+    options: CaptureOptions,
+    callback: F,
+) -> CaptureHandle {
+    spawn_capture(options, false, callback)
+}
+
+#[cfg(not(test))]
+#[allow(clippy::arc_with_non_send_sync)] // the stream is only kept alive, see `StreamHolder`
+fn spawn_capture<F: FnMut(&AudioBuffer) + Send + 'static>(
+    options: CaptureOptions,
+    keep_buffer: bool,
+    callback: F,
+) -> CaptureHandle {
+    let overruns = Arc::new(AtomicU64::new(0));
+    let input_options = InputOptions {
+        device: options.device,
+        sample_rate: options.sample_rate,
+        channels: options.channels,
+        capacity: 16,
+        overruns: overruns.clone(),
+    };
+    let (stream, config, receiver) = io::build_input_with(input_options);
+    log::debug!("Capture input {:?}", config);
+
+    let sample_rate = options.sample_rate.unwrap_or(config.sample_rate.0 as f32);
+    let number_of_channels = options.channels.unwrap_or(config.channels as usize);
+
+    let mut handle = CaptureHandle::spawn(
+        Box::new(receiver),
+        sample_rate,
+        number_of_channels,
+        options.duration,
+        keep_buffer,
+        overruns,
+        callback,
+    );
+    handle.stream = Some(StreamHolder::new(Arc::new(Mutex::new(Some(stream)))));
+
+    handle
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    use float_eq::assert_float_eq;
+
+    use super::*;
+
+    /// Mocked input, yields the queued chunks, `None` entries simulate late input
+    struct MockInput(VecDeque<Option<AudioBuffer>>);
+
+    impl InputSource for MockInput {
+        fn recv_timeout(&mut self, _timeout: Duration) -> Result<AudioBuffer, RecvTimeoutError> {
+            match self.0.pop_front() {
+                Some(Some(buffer)) => Ok(buffer),
+                Some(None) => Err(RecvTimeoutError::Timeout),
+                None => Err(RecvTimeoutError::Disconnected),
+            }
+        }
+    }
+
+    fn ramp(start: usize, len: usize, sample_rate: f32) -> AudioBuffer {
+        let samples = (start..start + len).map(|i| i as f32).collect();
+        AudioBuffer::from(vec![samples], sample_rate)
+    }
+
+    fn capture(
+        chunks: Vec<Option<AudioBuffer>>,
+        sample_rate: f32,
+        number_of_channels: usize,
+        duration: Option<f64>,
+    ) -> CaptureHandle {
+        CaptureHandle::spawn(
+            Box::new(MockInput(chunks.into())),
+            sample_rate,
+            number_of_channels,
+            duration,
+            true,
+            Arc::new(AtomicU64::new(0)),
+            |_| (),
+        )
+    }
+
+    #[test]
+    fn test_chunk_assembly() {
+        let sizes = [100, 300, 7, 250];
+        let mut chunks = vec![];
+        let mut start = 0;
+        for &size in &sizes {
+            chunks.push(Some(ramp(start, size, 48_000.)));
+            start += size;
+        }
+
+        let lengths = Arc::new(Mutex::new(vec![]));
+        let lengths_clone = lengths.clone();
+        let handle = CaptureHandle::spawn(
+            Box::new(MockInput(chunks.into())),
+            48_000.,
+            1,
+            None,
+            true,
+            Arc::new(AtomicU64::new(0)),
+            move |chunk| lengths_clone.lock().unwrap().push(chunk.length()),
+        );
+        let recording = handle.wait();
+
+        // the recording is the concatenation of the input, without padding
+        assert_eq!(recording.length(), start);
+        let expected: Vec<f32> = (0..start).map(|i| i as f32).collect();
+        assert_float_eq!(recording.get_channel_data(0), &expected[..], abs_all <= 0.);
+
+        // the callback is called with render quanta, the last one is shorter
+        assert_eq!(*lengths.lock().unwrap(), vec![128, 128, 128, 128, 128, 17]);
+    }
+
+    #[test]
+    fn test_resampling() {
+        let chunks = (0..10).map(|i| Some(ramp(i * 480, 480, 48_000.))).collect();
+        let recording = capture(chunks, 24_000., 1, None).wait();
+
+        assert_eq!(recording.sample_rate(), 24_000.);
+        // each chunk of 480 frames is resampled to 240 frames
+        assert_eq!(recording.length(), 2400);
+        let data = recording.get_channel_data(0);
+        assert_float_eq!(data[0], 0., abs <= 0.);
+        assert_float_eq!(data[239], 479., abs <= 1e-3);
+        assert_float_eq!(data[240], 480., abs <= 1e-3);
+    }
+
+    #[test]
+    fn test_duration() {
+        let chunks = (0..100)
+            .map(|i| Some(ramp(i * 480, 480, 48_000.)))
+            .collect();
+        let handle = capture(chunks, 48_000., 1, Some(0.25));
+        let recording = handle.wait();
+
+        assert_eq!(recording.length(), 12_000);
+        assert_float_eq!(recording.get_channel_data(0)[11_999], 11_999., abs <= 0.);
+    }
+
+    #[test]
+    fn test_underruns() {
+        let chunks = vec![
+            Some(ramp(0, 128, 48_000.)),
+            None,
+            None,
+            Some(ramp(128, 128, 48_000.)),
+            None,
+        ];
+        let handle = capture(chunks, 48_000., 1, None);
+
+        while !handle.is_finished() {
+            std::thread::yield_now();
+        }
+        assert_eq!(handle.underruns(), 3);
+        assert_eq!(handle.overruns(), 0);
+        assert_eq!(handle.stop().length(), 256);
+    }
+
+    #[test]
+    fn test_channels() {
+        let mono = vec![Some(ramp(0, 128, 48_000.))];
+        let recording = capture(mono, 48_000., 2, None).wait();
+        assert_eq!(recording.number_of_channels(), 2);
+        assert_float_eq!(
+            recording.get_channel_data(0),
+            recording.get_channel_data(1),
+            abs_all <= 0.
+        );
+
+        let stereo = AudioBuffer::from(vec![vec![1.; 128], vec![2.; 128]], 48_000.);
+        let recording = capture(vec![Some(stereo.clone())], 48_000., 1, None).wait();
+        assert_eq!(recording.number_of_channels(), 1);
+        assert_float_eq!(recording.get_channel_data(0), &[1.; 128][..], abs_all <= 0.);
+
+        let recording = capture(vec![Some(stereo)], 48_000., 3, None).wait();
+        assert_eq!(recording.number_of_channels(), 3);
+        assert_float_eq!(recording.get_channel_data(2), &[0.; 128][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_stop() {
+        // the input never delivers audio
+        let chunks = vec![None; 1_000_000];
+        let handle = capture(chunks, 48_000., 1, None);
+
+        let recording = handle.stop();
+        assert_eq!(recording.length(), 0);
+        assert_eq!(recording.number_of_channels(), 1);
+    }
+}
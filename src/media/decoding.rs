@@ -1,5 +1,8 @@
 use std::error::Error;
+use std::fmt;
 use std::io::{Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
 use crate::buffer::{AudioBuffer, ChannelData};
 
@@ -12,23 +15,241 @@ use symphonia::core::formats::{FormatOptions, FormatReader};
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
 
+/// Number of leading bytes of the input kept to sniff its format and read its WAV header
+///
+/// The `fmt ` chunk of a WAV file usually follows the RIFF header, it may come after some
+/// metadata chunks, such as the `bext` chunk of broadcast WAV files.
+const HEADER_LENGTH: usize = 8192;
+
+/// Format tag of the WAV files with a `WAVEFORMATEXTENSIBLE` header
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// Error returned when decoding media
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The container or codec of the input is not supported
+    UnsupportedFormat {
+        /// Name of the container format recognized from the leading bytes of the input, if any
+        detected: Option<String>,
+    },
+    /// The input is malformed or truncated
+    Corrupt {
+        /// Number of bytes consumed from the input when the error occurred, if known
+        at_byte: Option<u64>,
+        /// Description of the error, as reported by the decoder
+        details: String,
+    },
+    /// Reading the input failed
+    Io(std::io::Error),
+    /// The input does not contain any byte
+    EmptyStream,
+    /// The decoding was cancelled, see [`DecodeHandle::cancel`](crate::context::DecodeHandle::cancel)
+    Cancelled,
+    /// The audio context was closed or dropped before the decoding completed
+    Closed,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedFormat {
+                detected: Some(format),
+            } => {
+                write!(f, "unsupported format: {}", format)
+            }
+            Self::UnsupportedFormat { detected: None } => write!(f, "unrecognized format"),
+            Self::Corrupt {
+                at_byte: Some(at_byte),
+                details,
+            } => write!(f, "corrupt data at byte {}: {}", at_byte, details),
+            Self::Corrupt {
+                at_byte: None,
+                details,
+            } => write!(f, "corrupt data: {}", details),
+            Self::Io(e) => write!(f, "io error: {}", e),
+            Self::EmptyStream => write!(f, "empty stream"),
+            Self::Cancelled => write!(f, "decoding was cancelled"),
+            Self::Closed => write!(f, "audio context was closed"),
+        }
+    }
+}
+
+impl Error for DecodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for DecodeError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Name of the container format, recognized by its magic bytes
+fn sniff_format(header: &[u8]) -> Option<&'static str> {
+    let format = match header {
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => "wav",
+        [b'F', b'O', b'R', b'M', _, _, _, _, b'A', b'I', b'F', _, ..] => "aiff",
+        [b'O', b'g', b'g', b'S', ..] => "ogg",
+        [b'f', b'L', b'a', b'C', ..] => "flac",
+        [b'c', b'a', b'f', b'f', ..] => "caf",
+        [0x1a, 0x45, 0xdf, 0xa3, ..] => "webm",
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => "mp4",
+        [b'I', b'D', b'3', ..] => "mp3",
+        [0xff, b, ..] if b & 0xf6 == 0xf0 => "aac",
+        [0xff, b, ..] if b & 0xe0 == 0xe0 => "mp3",
+        _ => return None,
+    };
+
+    Some(format)
+}
+
+/// Speaker positions of a WAV input with a `WAVEFORMATEXTENSIBLE` header, as stored in the
+/// `dwChannelMask` field of its `fmt ` chunk
+///
+/// Returns `None` for other inputs, or when the `fmt ` chunk is not within the given bytes.
+fn wav_channel_mask(header: &[u8]) -> Option<u32> {
+    if sniff_format(header) != Some("wav") {
+        return None;
+    }
+
+    let u16_at =
+        |bytes: &[u8], at: usize| Some(u16::from_le_bytes([*bytes.get(at)?, *bytes.get(at + 1)?]));
+    let u32_at = |bytes: &[u8], at: usize| {
+        Some(u32::from(u16_at(bytes, at)?) | u32::from(u16_at(bytes, at + 2)?) << 16)
+    };
+
+    let mut chunks = &header[12..];
+    while chunks.len() >= 8 {
+        let len = u32_at(chunks, 4)? as usize;
+        let data = &chunks[8..];
+
+        if &chunks[..4] == b"fmt " {
+            // the mask follows the extension size and the valid bits per sample
+            return match u16_at(data, 0)? {
+                WAVE_FORMAT_EXTENSIBLE if len >= 40 => u32_at(data, 20),
+                _ => None,
+            };
+        }
+
+        // chunks are padded to an even length
+        chunks = data.get(len.checked_add(len & 1)?..)?;
+    }
+
+    None
+}
+
+/// Bytes consumed from the input, shared between the input and the decoder
+#[derive(Default)]
+struct InputProgress {
+    bytes_read: AtomicU64,
+    header: Mutex<Vec<u8>>,
+    /// Symphonia may turn a failed read into an end of stream, keep the original error
+    io_error: Mutex<Option<std::io::Error>>,
+}
+
+impl InputProgress {
+    fn bytes_read(&self) -> u64 {
+        self.bytes_read.load(Ordering::Relaxed)
+    }
+
+    /// Container format recognized from the leading bytes of the input
+    fn detected_format(&self) -> Option<&'static str> {
+        sniff_format(&self.header.lock().unwrap())
+    }
+
+    /// Channel mask of a WAV input, see [`MediaDecoder::channel_mask`]
+    fn channel_mask(&self) -> Option<u32> {
+        wav_channel_mask(&self.header.lock().unwrap())
+    }
+
+    /// Map a Symphonia error raised while probing the input to a [`DecodeError`]
+    ///
+    /// The probe scans the input until it finds a known format, so running out of input is
+    /// only a sign of corruption for the formats that can be decoded.
+    fn probe_error(&self, error: SymphoniaError) -> DecodeError {
+        match self.error(error) {
+            DecodeError::Corrupt { .. }
+                if !matches!(self.detected_format(), Some("wav" | "ogg" | "flac" | "mp3")) =>
+            {
+                DecodeError::UnsupportedFormat {
+                    detected: self.detected_format().map(String::from),
+                }
+            }
+            e => e,
+        }
+    }
+
+    /// Map a Symphonia error to a [`DecodeError`], given the input consumed so far
+    fn error(&self, error: SymphoniaError) -> DecodeError {
+        if let Some(e) = self.io_error.lock().unwrap().take() {
+            return DecodeError::Io(e);
+        }
+
+        let at_byte = Some(self.bytes_read());
+
+        match error {
+            SymphoniaError::IoError(e) if e.kind() != std::io::ErrorKind::UnexpectedEof => {
+                DecodeError::Io(e)
+            }
+            _ if self.bytes_read() == 0 => DecodeError::EmptyStream,
+            SymphoniaError::Unsupported(_) => DecodeError::UnsupportedFormat {
+                detected: self.detected_format().map(String::from),
+            },
+            SymphoniaError::IoError(_) => DecodeError::Corrupt {
+                at_byte,
+                details: "unexpected end of stream".into(),
+            },
+            e => DecodeError::Corrupt {
+                at_byte,
+                details: e.to_string(),
+            },
+        }
+    }
+}
+
 /// Wrapper for `Read` implementors to be used in Symphonia decoding
 ///
 /// Symphonia requires its input to impl `Seek` - but allows non-seekable sources. Hence we
 /// implement Seek but return false for `is_seekable()`.
 struct MediaInput<R> {
     input: R,
+    progress: Arc<InputProgress>,
 }
 
 impl<R: Read> MediaInput<R> {
-    pub fn new(input: R) -> Self {
-        Self { input }
+    pub fn new(input: R, progress: Arc<InputProgress>) -> Self {
+        Self { input, progress }
     }
 }
 
 impl<R: Read> Read for MediaInput<R> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        self.input.read(buf)
+        let len = match self.input.read(buf) {
+            Ok(len) => len,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => return Err(e),
+            Err(e) => {
+                let error = std::io::Error::new(e.kind(), e.to_string());
+                *self.progress.io_error.lock().unwrap() = Some(e);
+                return Err(error);
+            }
+        };
+
+        let bytes_read = self
+            .progress
+            .bytes_read
+            .fetch_add(len as u64, Ordering::Relaxed);
+        if bytes_read < HEADER_LENGTH as u64 {
+            let missing = HEADER_LENGTH - bytes_read as usize;
+            let mut header = self.progress.header.lock().unwrap();
+            header.extend_from_slice(&buf[..len.min(missing)]);
+        }
+
+        Ok(len)
     }
 }
 
@@ -61,7 +282,9 @@ impl<R: Read + Send + Sync> symphonia::core::io::MediaSource for MediaInput<R> {
 /// will add a buffered version which will decode in a separate thread.
 /// <https://github.com/orottier/web-audio-api-rs/issues/120>
 ///
-/// The current implementation can decode FLAC, Opus, PCM, Vorbis, and Wav.
+/// The current implementation can decode FLAC, Opus, PCM, Vorbis, and Wav. WAV files may have
+/// up to 26 channels, with integer samples of up to 32 bits or floating point samples, and
+/// a `WAVEFORMATEXTENSIBLE` header.
 ///
 /// # Warning
 ///
@@ -87,6 +310,8 @@ impl<R: Read + Send + Sync> symphonia::core::io::MediaSource for MediaInput<R> {
 pub struct MediaDecoder {
     format: Box<dyn FormatReader>,
     decoder: Box<dyn Decoder>,
+    progress: Arc<InputProgress>,
+    decoded_frames: u64,
 }
 
 impl MediaDecoder {
@@ -94,23 +319,26 @@ impl MediaDecoder {
     ///
     /// # Errors
     ///
-    /// This method returns an Error in various cases (IO, mime sniffing, decoding).
+    /// This method returns a [`DecodeError`] when the input is empty, when its format is not
+    /// supported, or when it can not be read.
     ///
     /// # Example
     ///
     /// ```
     /// use std::io::Cursor;
-    /// use web_audio_api::media::MediaDecoder;
+    /// use web_audio_api::media::{DecodeError, MediaDecoder};
     ///
     /// let input = Cursor::new(vec![0; 32]); // or a File, TcpStream, ...
     /// let media = MediaDecoder::try_new(input);
     ///
-    /// assert!(media.is_err()); // the input was not a valid MIME type
+    /// // the input was not a valid MIME type
+    /// assert!(matches!(media, Err(DecodeError::UnsupportedFormat { .. })));
     pub fn try_new<R: std::io::Read + Send + Sync + 'static>(
         input: R,
-    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<Self, DecodeError> {
         // Symfonia lib needs a Box<dyn MediaSource> - use our own MediaInput
-        let input = Box::new(MediaInput::new(input));
+        let progress = Arc::new(InputProgress::default());
+        let input = Box::new(MediaInput::new(input, Arc::clone(&progress)));
 
         // Create the media source stream using the boxed media source from above.
         let mss = symphonia::core::io::MediaSourceStream::new(input, Default::default());
@@ -125,28 +353,71 @@ impl MediaDecoder {
         let decoder_opts: DecoderOptions = Default::default();
 
         // Probe the media source stream for a format.
-        let probed =
-            symphonia::default::get_probe().format(&hint, mss, &format_opts, &metadata_opts)?;
+        let probed = symphonia::default::get_probe()
+            .format(&hint, mss, &format_opts, &metadata_opts)
+            .map_err(|e| progress.probe_error(e))?;
 
         // Get the format reader yielded by the probe operation.
         let format = probed.format;
 
         // Get the default track.
-        let track = format.default_track().ok_or(SymphoniaError::Unsupported(
-            "no default media track available",
-        ))?;
+        let track = format
+            .default_track()
+            .ok_or_else(|| progress.error(SymphoniaError::Unsupported("no default track")))?;
 
         // Create a (stateful) decoder for the track.
-        let decoder = symphonia::default::get_codecs().make(&track.codec_params, &decoder_opts)?;
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &decoder_opts)
+            .map_err(|e| progress.error(e))?;
 
-        Ok(Self { format, decoder })
+        Ok(Self {
+            format,
+            decoder,
+            progress,
+            decoded_frames: 0,
+        })
     }
-}
 
-impl Iterator for MediaDecoder {
-    type Item = Result<AudioBuffer, Box<dyn Error + Send + Sync>>;
+    /// Number of channels of the default track, in the order they are stored in the input
+    pub fn number_of_channels(&self) -> usize {
+        self.format
+            .default_track()
+            .and_then(|track| track.codec_params.channels)
+            .map_or(0, |channels| channels.count())
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
+    /// Number of significant bits of the samples of the default track, if known from the
+    /// container
+    ///
+    /// The samples are converted to `f32` when decoded, this is the bit depth of the input.
+    pub fn bits_per_sample(&self) -> Option<u32> {
+        self.format
+            .default_track()
+            .and_then(|track| track.codec_params.bits_per_sample)
+    }
+
+    /// Speaker positions of the channels, for a WAV input with a `WAVEFORMATEXTENSIBLE` header
+    ///
+    /// The value is the `dwChannelMask` field of the header, as stored: each set bit is a
+    /// speaker position, assigned to the channels in their order. It may have fewer bits set
+    /// than there are channels, some files set it to 0 when the channels do not map to
+    /// speakers, e.g. ambisonic files. The decoded channels are never reordered nor mixed.
+    ///
+    /// Returns `None` for the other inputs.
+    pub fn channel_mask(&self) -> Option<u32> {
+        self.progress.channel_mask()
+    }
+
+    /// Total number of frames of the default track, if known from the container
+    pub(crate) fn number_of_frames(&self) -> Option<u64> {
+        self.format
+            .default_track()
+            .and_then(|track| track.codec_params.n_frames)
+    }
+
+    /// Decode the next packet of the default track
+    pub(crate) fn next_buffer(&mut self) -> Option<Result<AudioBuffer, DecodeError>> {
+        let number_of_frames = self.number_of_frames();
         let format = &mut self.format;
         let decoder = &mut self.decoder;
 
@@ -161,9 +432,26 @@ impl Iterator for MediaDecoder {
         loop {
             // Get the next packet from the format reader.
             let packet = match format.next_packet() {
+                Err(SymphoniaError::IoError(e))
+                    if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    // end of stream, the input is truncated if the container announced more
+                    return match number_of_frames {
+                        Some(total) if self.decoded_frames < total => {
+                            Some(Err(DecodeError::Corrupt {
+                                at_byte: Some(self.progress.bytes_read()),
+                                details: format!(
+                                    "unexpected end of stream after {} of {} frames",
+                                    self.decoded_frames, total
+                                ),
+                            }))
+                        }
+                        _ => None,
+                    };
+                }
                 Err(e) => {
                     log::error!("next packet err {:?}", e);
-                    return None;
+                    return Some(Err(self.progress.error(e)));
                 }
                 Ok(p) => p,
             };
@@ -173,32 +461,38 @@ impl Iterator for MediaDecoder {
                 continue;
             }
 
-            // Decode the packet into audio samples, ignoring any decode errors.
+            // Decode the packet into audio samples
             match decoder.decode(&packet) {
                 Ok(audio_buf) => {
                     let output = convert_buf(audio_buf, number_of_channels, input_sample_rate);
+                    self.decoded_frames += output.length() as u64;
                     return Some(Ok(output));
                 }
-                Err(SymphoniaError::DecodeError(e)) => {
-                    // Todo: treat decoding errors as fatal or move to next packet? Context:
-                    // https://github.com/RustAudio/rodio/issues/401#issuecomment-974747404
-                    log::error!("Symphonia DecodeError {:?} - abort stream", e);
-                    return Some(Err(Box::new(SymphoniaError::DecodeError(e))));
-                }
                 Err(SymphoniaError::IoError(e))
                     if e.kind() == std::io::ErrorKind::UnexpectedEof =>
                 {
                     // this happens for Wav-files, running into EOF is expected
                 }
                 Err(e) => {
-                    // do not continue processing, return error result
-                    return Some(Err(Box::new(e)));
+                    // Todo: treat decoding errors as fatal or move to next packet? Context:
+                    // https://github.com/RustAudio/rodio/issues/401#issuecomment-974747404
+                    log::error!("Symphonia error {:?} - abort stream", e);
+                    return Some(Err(self.progress.error(e)));
                 }
             };
         }
     }
 }
 
+impl Iterator for MediaDecoder {
+    type Item = Result<AudioBuffer, Box<dyn Error + Send + Sync>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_buffer()
+            .map(|result| result.map_err(|e| Box::new(e) as _))
+    }
+}
+
 /// Convert a Symphonia AudioBufferRef to our own AudioBuffer
 fn convert_buf(
     input: AudioBufferRef<'_>,
@@ -247,3 +541,149 @@ fn convert_buf(
     let channels = data.into_iter().map(ChannelData::from).collect();
     AudioBuffer::from_channels(channels, input_sample_rate)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUB_FORMAT_PCM: u16 = 1;
+    const SUB_FORMAT_IEEE_FLOAT: u16 = 3;
+
+    /// WAV file with a `WAVEFORMATEXTENSIBLE` header, the frames are encoded in `data`
+    fn extensible_wav(
+        number_of_channels: u16,
+        bits_per_sample: u16,
+        sub_format: u16,
+        channel_mask: u32,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let sample_rate = 48_000_u32;
+        let block_align = number_of_channels * bits_per_sample / 8;
+        let data_len = data.len() as u32;
+
+        let mut wav = Vec::with_capacity(68 + data.len());
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(60 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&40_u32.to_le_bytes()); // fmt chunk size
+        wav.extend_from_slice(&WAVE_FORMAT_EXTENSIBLE.to_le_bytes());
+        wav.extend_from_slice(&number_of_channels.to_le_bytes());
+        wav.extend_from_slice(&sample_rate.to_le_bytes());
+        wav.extend_from_slice(&(sample_rate * u32::from(block_align)).to_le_bytes());
+        wav.extend_from_slice(&block_align.to_le_bytes());
+        wav.extend_from_slice(&bits_per_sample.to_le_bytes());
+        wav.extend_from_slice(&22_u16.to_le_bytes()); // extension size
+        wav.extend_from_slice(&bits_per_sample.to_le_bytes()); // valid bits per sample
+        wav.extend_from_slice(&channel_mask.to_le_bytes());
+        // KSDATAFORMAT_SUBTYPE_PCM or KSDATAFORMAT_SUBTYPE_IEEE_FLOAT
+        wav.extend_from_slice(&sub_format.to_le_bytes());
+        wav.extend_from_slice(&[
+            0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71,
+        ]);
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        wav.extend_from_slice(data);
+
+        wav
+    }
+
+    /// Decode the input in full, checking the channel count reported by the decoder
+    fn decode(wav: Vec<u8>, number_of_channels: usize) -> (MediaDecoder, AudioBuffer) {
+        let mut decoder = MediaDecoder::try_new(std::io::Cursor::new(wav)).unwrap();
+        assert_eq!(decoder.number_of_channels(), number_of_channels);
+
+        let mut buffer = decoder.next_buffer().unwrap().unwrap();
+        while let Some(item) = decoder.next_buffer() {
+            buffer.extend(&item.unwrap());
+        }
+        assert_eq!(buffer.number_of_channels(), number_of_channels);
+
+        (decoder, buffer)
+    }
+
+    #[test]
+    fn test_float_extensible() {
+        let number_of_frames = 1000;
+        // distinct content per channel, as for the first order ambisonic components
+        let sample = |channel: usize, frame: usize| {
+            ((frame % 50) as f32 / 50. - 0.5) * (channel + 1) as f32 * 0.25
+        };
+
+        let data: Vec<u8> = (0..number_of_frames)
+            .flat_map(|frame| (0..4).map(move |channel| sample(channel, frame)))
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        let wav = extensible_wav(4, 32, SUB_FORMAT_IEEE_FLOAT, 0, &data);
+        let (decoder, buffer) = decode(wav, 4);
+
+        assert_eq!(decoder.channel_mask(), Some(0));
+        assert_eq!(decoder.bits_per_sample(), Some(32));
+        assert_eq!(buffer.length(), number_of_frames);
+        for channel in 0..4 {
+            let expected: Vec<f32> = (0..number_of_frames)
+                .map(|frame| sample(channel, frame))
+                .collect();
+            assert_eq!(buffer.get_channel_data(channel), &expected[..]);
+        }
+    }
+
+    #[test]
+    fn test_24_bit_extensible() {
+        let number_of_frames = 1000;
+        // spans the whole range, including both extremes
+        let sample = |channel: usize, frame: usize| {
+            ((frame * 7919 + channel * 104_729) % (1 << 24)) as i32 - (1 << 23)
+        };
+
+        let data: Vec<u8> = (0..number_of_frames)
+            .flat_map(|frame| (0..8).map(move |channel| sample(channel, frame)))
+            .flat_map(|value| {
+                let [a, b, c, _] = value.to_le_bytes();
+                [a, b, c]
+            })
+            .collect();
+        // 7.1 speaker layout
+        let wav = extensible_wav(8, 24, SUB_FORMAT_PCM, 0x63f, &data);
+        let (decoder, buffer) = decode(wav, 8);
+
+        assert_eq!(decoder.channel_mask(), Some(0x63f));
+        assert_eq!(decoder.bits_per_sample(), Some(24));
+        assert_eq!(buffer.length(), number_of_frames);
+        for channel in 0..8 {
+            // 24 bits fit in the mantissa of a `f32`, the conversion is exact
+            let expected: Vec<f32> = (0..number_of_frames)
+                .map(|frame| sample(channel, frame) as f32 / (1 << 23) as f32)
+                .collect();
+            assert_eq!(buffer.get_channel_data(channel), &expected[..]);
+        }
+        assert!(buffer.get_channel_data(0).contains(&-1.));
+    }
+
+    #[test]
+    fn test_channel_mask_not_extensible() {
+        let file = std::fs::File::open("samples/sample.wav").unwrap();
+        let decoder = MediaDecoder::try_new(file).unwrap();
+        assert_eq!(decoder.number_of_channels(), 2);
+        assert_eq!(decoder.bits_per_sample(), Some(16));
+        assert_eq!(decoder.channel_mask(), None);
+    }
+
+    #[test]
+    fn test_wav_channel_mask() {
+        let wav = extensible_wav(2, 16, SUB_FORMAT_PCM, 0x3, &[0; 4]);
+        assert_eq!(wav_channel_mask(&wav), Some(0x3));
+
+        // a padded chunk of odd length before the fmt chunk
+        let mut junk = wav[..12].to_vec();
+        junk.extend_from_slice(b"JUNK");
+        junk.extend_from_slice(&3_u32.to_le_bytes());
+        junk.extend_from_slice(&[0; 4]);
+        junk.extend_from_slice(&wav[12..]);
+        assert_eq!(wav_channel_mask(&junk), Some(0x3));
+
+        // the fmt chunk is cut short
+        assert_eq!(wav_channel_mask(&wav[..40]), None);
+        assert_eq!(wav_channel_mask(&wav[..12]), None);
+        assert_eq!(wav_channel_mask(b"OggS"), None);
+    }
+}
@@ -8,7 +8,9 @@ use crate::RENDER_QUANTUM_SIZE;
 use crate::context::AudioContextOptions;
 
 #[cfg(not(test))]
-use crossbeam_channel::Sender;
+use crossbeam_channel::{Sender, TrySendError};
+#[cfg(not(test))]
+use std::sync::atomic::{AtomicU64, Ordering};
 #[cfg(not(test))]
 use std::sync::{Arc, Mutex};
 
@@ -18,12 +20,12 @@ use crate::buffer::ChannelData;
 use crate::io;
 
 #[cfg(not(test))]
-use cpal::{traits::StreamTrait, Sample, Stream};
+use cpal::{traits::StreamTrait, Sample, Stream, StreamConfig};
 
 use crossbeam_channel::{Receiver, TryRecvError};
 
 #[cfg(not(test))]
-mod private {
+pub(crate) mod private {
     use super::*;
 
     pub struct StreamHolder(Arc<Mutex<Option<Stream>>>);
@@ -32,6 +34,11 @@ mod private {
         pub fn new(stream: Arc<Mutex<Option<Stream>>>) -> Self {
             StreamHolder(stream)
         }
+
+        /// Drop the stream, releasing the input device
+        pub fn close(&self) {
+            self.0.lock().unwrap().take();
+        }
     }
 
     // SAFETY: Stream is not Send, but we do want to have shared ownership via an Arc.
@@ -71,6 +78,7 @@ use private::StreamHolder;
 /// let opts = AudioContextOptions {
 ///     sample_rate: Some(44100.),
 ///     latency_hint: AudioContextLatencyCategory::Interactive,
+///     ..AudioContextOptions::default()
 /// };
 /// let mic = Microphone::new(opts);
 /// // or you can create Microphone with default options
@@ -227,15 +235,21 @@ pub(crate) struct MicrophoneRender {
     number_of_channels: usize,
     sample_rate: f32,
     sender: Sender<AudioBuffer>,
+    overruns: Arc<AtomicU64>,
 }
 
 #[cfg(not(test))]
 impl MicrophoneRender {
-    pub fn new(number_of_channels: usize, sample_rate: f32, sender: Sender<AudioBuffer>) -> Self {
+    pub fn new(
+        config: &StreamConfig,
+        sender: Sender<AudioBuffer>,
+        overruns: Arc<AtomicU64>,
+    ) -> Self {
         Self {
-            number_of_channels,
-            sample_rate,
+            number_of_channels: usize::from(config.channels),
+            sample_rate: config.sample_rate.0 as f32,
             sender,
+            overruns,
         }
     }
 
@@ -254,9 +268,10 @@ impl MicrophoneRender {
         }
 
         let buffer = AudioBuffer::from_channels(channels, self.sample_rate);
-        let result = self.sender.try_send(buffer); // can fail (frame dropped)
-        if result.is_err() {
+        // can fail (frame dropped), or the consumer may be gone
+        if let Err(TrySendError::Full(_)) = self.sender.try_send(buffer) {
             log::debug!("input frame dropped");
+            self.overruns.fetch_add(1, Ordering::Relaxed);
         }
     }
 }
@@ -1,14 +1,22 @@
 //! Convenience abstractions that are not part of the WebAudio API (media decoding, microphone)
 
+#[cfg(feature = "cpal")]
+mod capture;
+#[cfg(all(feature = "cpal", not(test)))]
+pub use capture::{record_input, record_input_with_callback};
+#[cfg(feature = "cpal")]
+pub use capture::{CaptureHandle, CaptureOptions};
 mod decoding;
-pub use decoding::MediaDecoder;
+pub use decoding::{DecodeError, MediaDecoder};
+#[cfg(feature = "cpal")]
 mod mic;
+#[cfg(feature = "cpal")]
 pub use mic::Microphone;
 
 mod resampling;
 pub(crate) use resampling::Resampler;
 
-#[cfg(not(test))]
+#[cfg(all(feature = "cpal", not(test)))]
 pub(crate) use mic::MicrophoneRender;
 
 use std::error::Error;
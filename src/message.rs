@@ -1,12 +1,17 @@
 //! Message passing from control to render node
 
-use crate::node::ChannelConfig;
+use crate::control::Scheduler;
+use crate::node::{ChannelConfig, ChannelConfigOptions};
 use crate::param::AudioParamEvent;
-use crate::render::AudioProcessor;
+use crate::render::{AudioProcessor, EdgeKind, OutputTap, OverloadPolicy};
 
-use crossbeam_channel::Sender;
+use std::sync::atomic::AtomicU8;
+use std::sync::Arc;
+
+use crossbeam_channel::{Receiver, Sender, TrySendError};
 
 /// Commands from the control thread to the render thread
+#[derive(Debug)]
 pub(crate) enum ControlMessage {
     /// Register a new node in the audio graph
     RegisterNode {
@@ -21,8 +26,8 @@ pub(crate) enum ControlMessage {
     ConnectNode {
         from: u64,
         to: u64,
-        input: usize,
         output: usize,
+        kind: EdgeKind,
     },
 
     /// Clear the connection between two given nodes in the audio graph
@@ -31,12 +36,179 @@ pub(crate) enum ControlMessage {
     /// Disconnect this node from the audio graph (drop all its connections)
     DisconnectAll { from: u64 },
 
+    /// Apply the channel config changes of several nodes, in the same render quantum
+    SetChannelConfig {
+        configs: Vec<(u64, ChannelConfigOptions)>,
+    },
+
     /// Notify the render thread this node is dropped in the control thread
     FreeWhenFinished { id: u64 },
 
-    /// Pass an AudioParam AutomationEvent to the relevant node
-    AudioParamEvent {
-        to: Sender<AudioParamEvent>,
-        event: AudioParamEvent,
+    /// Replace the processor of a node, the previous processor is dropped on the render thread
+    SwapProcessor {
+        id: u64,
+        processor: Box<dyn AudioProcessor>,
+    },
+
+    /// Open the channel on which the render thread delivers the automation events of an
+    /// AudioParam to its processor
+    RegisterAudioParam { id: u64 },
+
+    /// Pass an AudioParam AutomationEvent to the AudioParam with the given id
+    AudioParamEvent { to: u64, event: AudioParamEvent },
+
+    /// Pass AutomationEvents to several AudioParams, applied in the same render quantum
+    AudioParamEvents { events: Vec<(u64, AudioParamEvent)> },
+
+    /// Smoothly ramp an AudioParam from its current rendered value to the target value
+    SmoothSetValue {
+        to: u64,
+        target: f32,
+        smoothing_samples: usize,
+    },
+
+    /// Set the order in which a node is degraded when the render thread is overloaded
+    SetRenderPriority { id: u64, priority: u8 },
+
+    /// Mute or unmute the outputs of a node, with a gain ramp of the given length
+    SetMuted {
+        id: u64,
+        muted: bool,
+        ramp_frames: usize,
     },
+
+    /// Render the nodes, connected in series, as a single step
+    FreezeChain { ids: Vec<u64> },
+
+    /// Set the degradations applied when the render thread is overloaded
+    SetOverloadPolicy { policy: Option<OverloadPolicy> },
+
+    /// Share the state of the context with the render thread
+    SetContextState { state: Arc<AtomicU8> },
+
+    /// Notify the late output callbacks (xruns) on the given channel, with the gap duration
+    #[cfg(feature = "cpal")]
+    SetXrunSender { sender: Option<Sender<f64>> },
+
+    /// Pass the interleaved output of each render quantum to the given tap
+    SetOutputTap { tap: Option<OutputTap> },
+
+    /// Schedule the start of an AudioScheduledSourceNode, at a frame position
+    StartSource { scheduler: Scheduler, frame: f64 },
+
+    /// Schedule the stop of an AudioScheduledSourceNode, at a frame position
+    StopSource { scheduler: Scheduler, frame: f64 },
+
+    /// Undo the start of an AudioScheduledSourceNode, if not reached yet
+    CancelStart { scheduler: Scheduler },
+
+    /// Apply several control messages in the same render quantum, in order
+    Batch { messages: Vec<ControlMessage> },
+}
+
+impl ControlMessage {
+    /// Returns true if the message concerns one of the nodes for which `f` returns true
+    ///
+    /// The start and stop of the scheduled sources do not name their node, they concern none.
+    pub fn concerns_node(&self, f: &dyn Fn(u64) -> bool) -> bool {
+        use ControlMessage::*;
+
+        match self {
+            RegisterNode { id, .. }
+            | FreeWhenFinished { id }
+            | SwapProcessor { id, .. }
+            | RegisterAudioParam { id, .. }
+            | SetRenderPriority { id, .. }
+            | SetMuted { id, .. } => f(*id),
+            ConnectNode { from, to, .. } | DisconnectNode { from, to } => f(*from) || f(*to),
+            DisconnectAll { from } => f(*from),
+            SetChannelConfig { configs } => configs.iter().any(|(id, _)| f(*id)),
+            AudioParamEvent { to, .. } | SmoothSetValue { to, .. } => f(*to),
+            AudioParamEvents { events } => events.iter().any(|(to, _)| f(*to)),
+            FreezeChain { ids } => ids.iter().any(|id| f(*id)),
+            Batch { messages } => messages.iter().any(|message| message.concerns_node(f)),
+            SetOverloadPolicy { .. }
+            | SetContextState { .. }
+            | SetOutputTap { .. }
+            | StartSource { .. }
+            | StopSource { .. }
+            | CancelStart { .. } => false,
+            #[cfg(feature = "cpal")]
+            SetXrunSender { .. } => false,
+        }
+    }
+}
+
+/// Create a single slot channel from an audio node to its renderer, holding the latest value
+///
+/// Used for settings replaced at once (e.g. a periodic wave), which can be updated faster
+/// than the renderer reads them.
+pub(crate) fn latest_value_channel<T>() -> (LatestValueSender<T>, Receiver<T>) {
+    let (sender, receiver) = crossbeam_channel::bounded(1);
+    let sender = LatestValueSender {
+        sender,
+        receiver: receiver.clone(),
+    };
+    (sender, receiver)
+}
+
+/// Sending half of a [`latest_value_channel`]
+pub(crate) struct LatestValueSender<T> {
+    sender: Sender<T>,
+    /// Used to take back a value the renderer has not read yet
+    receiver: Receiver<T>,
+}
+
+impl<T> LatestValueSender<T> {
+    /// Replace the value waiting for the renderer, if any
+    ///
+    /// This never blocks, the replaced value is dropped on the calling thread.
+    pub fn send(&self, mut value: T) {
+        loop {
+            match self.sender.try_send(value) {
+                Ok(()) | Err(TrySendError::Disconnected(_)) => return,
+                Err(TrySendError::Full(rejected)) => {
+                    // the renderer may read the stale value in the meantime, then retry anyway
+                    drop(self.receiver.try_recv());
+                    value = rejected;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latest_value_channel() {
+        let (sender, receiver) = latest_value_channel();
+        (0..10_000).for_each(|i| sender.send(i));
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![9_999]);
+
+        sender.send(1);
+        assert_eq!(receiver.try_recv(), Ok(1));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_latest_value_channel_concurrent_senders() {
+        let (sender, receiver) = latest_value_channel();
+        let sender = std::sync::Arc::new(sender);
+
+        // the senders replace each other's pending value, none of them blocks
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let sender = sender.clone();
+                std::thread::spawn(move || (0..10_000).for_each(|i| sender.send((t, i))))
+            })
+            .collect();
+        threads.into_iter().for_each(|t| t.join().unwrap());
+
+        // a single value is left, the last one of one of the senders
+        let values: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0].1, 9_999);
+    }
 }
@@ -108,30 +108,30 @@ impl AnalyserNode {
 
     /// Half the FFT size
     pub fn frequency_bin_count(&self) -> usize {
-        self.fft_size.load(Ordering::SeqCst) / 2
+        self.fft_size.load(Ordering::Relaxed) / 2
     }
 
     /// The size of the FFT used for frequency-domain analysis (in sample-frames)
     pub fn fft_size(&self) -> usize {
-        self.fft_size.load(Ordering::SeqCst)
+        self.fft_size.load(Ordering::Relaxed)
     }
 
     /// This MUST be a power of two in the range 32 to 32768
     pub fn set_fft_size(&self, fft_size: usize) {
         // todo assert size
-        self.fft_size.store(fft_size, Ordering::SeqCst);
+        self.fft_size.store(fft_size, Ordering::Relaxed);
     }
 
     /// Time averaging parameter with the last analysis frame.
     pub fn smoothing_time_constant(&self) -> f64 {
-        self.smoothing_time_constant.load(Ordering::SeqCst) as f64 / 100.
+        self.smoothing_time_constant.load(Ordering::Relaxed) as f64 / 100.
     }
 
     /// Set smoothing time constant, this MUST be a value between 0 and 1
     pub fn set_smoothing_time_constant(&self, value: f64) {
         // todo assert range
         self.smoothing_time_constant
-            .store((value * 100.) as u32, Ordering::SeqCst);
+            .store((value * 100.) as u32, Ordering::Relaxed);
     }
 
     /// Copies the current time domain data (waveform data) into the provided buffer
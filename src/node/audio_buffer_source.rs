@@ -1,15 +1,14 @@
 use crossbeam_channel::{Receiver, Sender};
 use once_cell::sync::OnceCell;
-use std::sync::atomic::{AtomicBool, Ordering};
 
 use crate::buffer::AudioBuffer;
 use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
-use crate::control::Controller;
+use crate::control::{Controller, Playback, Scheduler, SourceActivity};
 use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 use crate::RENDER_QUANTUM_SIZE;
 
-use super::{AudioNode, AudioScheduledSourceNode, ChannelConfig};
+use super::{AudioNode, AudioScheduledSourceNode, ChannelConfig, PlaybackState};
 
 /// Options for constructing an [`AudioBufferSourceNode`]
 // dictionary AudioBufferSourceOptions {
@@ -49,6 +48,11 @@ struct AudioBufferMessage(AudioBuffer);
 /// in-memory audio source (i.e. an audio file completely loaded in memory),
 /// stored in an [`AudioBuffer`].
 ///
+/// The output has as many channels as the buffer, whatever the channel config of the node: the
+/// node has no inputs, the nodes it is connected to up/down-mix its output according to their
+/// own channel config. Before the buffer is set and once the playback has ended, the output is a
+/// single channel of silence.
+///
 /// - MDN documentation: <https://developer.mozilla.org/en-US/docs/Web/API/AudioBufferSourceNode>
 /// - specification: <https://webaudio.github.io/web-audio-api/#AudioBufferSourceNode>
 /// - see also: [`BaseAudioContext::create_buffer_source`](crate::context::BaseAudioContext::create_buffer_source)
@@ -85,7 +89,6 @@ pub struct AudioBufferSourceNode {
     detune: AudioParam,        // has constraints, no a-rate
     playback_rate: AudioParam, // has constraints, no a-rate
     buffer: OnceCell<AudioBuffer>,
-    source_started: AtomicBool,
 }
 
 impl AudioNode for AudioBufferSourceNode {
@@ -104,6 +107,13 @@ impl AudioNode for AudioBufferSourceNode {
     fn number_of_outputs(&self) -> usize {
         1
     }
+
+    fn params(&self) -> Vec<(&'static str, &AudioParam)> {
+        vec![
+            ("playbackRate", &self.playback_rate),
+            ("detune", &self.detune),
+        ]
+    }
 }
 
 impl AudioScheduledSourceNode for AudioBufferSourceNode {
@@ -122,11 +132,45 @@ impl AudioScheduledSourceNode for AudioBufferSourceNode {
     }
 
     fn stop_at(&self, when: f64) {
-        if !self.source_started.load(Ordering::SeqCst) {
-            panic!("InvalidStateError cannot stop before start");
+        self.registration
+            .context()
+            .schedule_stop(self.controller.scheduler(), when);
+    }
+
+    fn start_at_frame(&self, frame: u64) {
+        if self.controller.scheduler().has_started() {
+            panic!("InvalidStateError - cannot call `start` twice");
         }
 
-        self.controller.scheduler().stop_at(when);
+        self.controller.set_offset(0.);
+        self.controller.set_duration(f64::MAX);
+        self.registration
+            .context()
+            .schedule_start_frame(self.controller.scheduler(), frame);
+    }
+
+    fn stop_at_frame(&self, frame: u64) {
+        self.registration
+            .context()
+            .schedule_stop_frame(self.controller.scheduler(), frame);
+    }
+
+    fn scheduled_start_time(&self) -> Option<f64> {
+        self.controller.scheduler().scheduled_start()
+    }
+
+    fn scheduled_stop_time(&self) -> Option<f64> {
+        self.controller.scheduler().scheduled_stop()
+    }
+
+    fn cancel_scheduled_start(&self) {
+        self.registration
+            .context()
+            .cancel_start(self.controller.scheduler());
+    }
+
+    fn playback_state(&self) -> PlaybackState {
+        self.controller.scheduler().playback_state()
     }
 }
 
@@ -181,6 +225,10 @@ impl AudioBufferSourceNode {
                 playback_rate: pr_proc,
                 render_state: AudioBufferRendererState::default(),
                 playback_infos: [None; RENDER_QUANTUM_SIZE],
+                activity: SourceActivity::new(
+                    context.base().active_sources().clone(),
+                    controller.scheduler(),
+                ),
             };
 
             let node = Self {
@@ -191,7 +239,6 @@ impl AudioBufferSourceNode {
                 detune: d_param,
                 playback_rate: pr_param,
                 buffer: OnceCell::new(),
-                source_started: AtomicBool::new(false),
             };
 
             node.controller.set_loop(loop_);
@@ -210,7 +257,7 @@ impl AudioBufferSourceNode {
     ///
     /// # Panics
     ///
-    /// Panics if the source was already started
+    /// Panics if the start time is NaN or infinite, or if the source was already started
     pub fn start_at_with_offset(&self, start: f64, offset: f64) {
         self.start_at_with_offset_and_duration(start, offset, f64::MAX);
     }
@@ -219,15 +266,20 @@ impl AudioBufferSourceNode {
     ///
     /// # Panics
     ///
-    /// Panics if the source was already started
+    /// Panics if the start time is NaN or infinite, or if the source was already started
     pub fn start_at_with_offset_and_duration(&self, start: f64, offset: f64, duration: f64) {
-        if self.source_started.swap(true, Ordering::SeqCst) {
-            panic!("InvalidStateError: Cannot call `start` twice");
+        // reject an invalid start before the offset and duration of the first one are
+        // overwritten
+        Scheduler::validate_time(start, "start");
+        if self.controller.scheduler().has_started() {
+            panic!("InvalidStateError - cannot call `start` twice");
         }
 
         self.controller.set_offset(offset);
         self.controller.set_duration(duration);
-        self.controller.scheduler().start_at(start);
+        self.registration
+            .context()
+            .schedule_start(self.controller.scheduler(), start);
     }
 
     /// Current buffer value (nullable)
@@ -327,6 +379,8 @@ struct AudioBufferSourceRenderer {
     /// Internal buffer used to store playback infos to compute the samples
     /// according to the source buffer. (prev_sample_index, k)
     playback_infos: [Option<(usize, f32)>; RENDER_QUANTUM_SIZE],
+    /// Keeps track of the source being actively rendered
+    activity: SourceActivity,
 }
 
 impl AudioProcessor for AudioBufferSourceRenderer {
@@ -343,15 +397,15 @@ impl AudioProcessor for AudioBufferSourceRenderer {
         let sample_rate = scope.sample_rate as f64;
         let dt = 1. / sample_rate;
         let num_frames = RENDER_QUANTUM_SIZE;
-        let next_block_time = scope.current_time + dt * num_frames as f64;
+        let current_frame = scope.current_frame as f64;
 
         if let Ok(msg) = self.receiver.try_recv() {
             self.buffer = Some(msg.0);
         }
 
         // grab all timing informations
-        let mut start_time = self.controller.scheduler().get_start_at();
-        let stop_time = self.controller.scheduler().get_stop_at();
+        let mut start_frame = self.controller.scheduler().get_start_frame();
+        let stop_frame = self.controller.scheduler().get_stop_frame();
         let mut offset = self.controller.offset();
         let duration = self.controller.duration();
         let loop_ = self.controller.loop_();
@@ -362,17 +416,20 @@ impl AudioProcessor for AudioBufferSourceRenderer {
         let mut actual_loop_start = 0.;
         let mut actual_loop_end = 0.;
 
-        // return early if start_time is beyond this block
-        if start_time >= next_block_time {
+        // return early if the start is beyond this block, or if the source is scheduled to
+        // stop before it starts. The stop is handled below.
+        let playback = self.controller.scheduler().playback(scope.current_frame);
+        if playback == Playback::Pending {
             output.make_silent();
-            return true;
+            return self.controller.scheduler().pending_tail_time();
         }
 
-        // If the buffer has not been set wait for it.
+        // If the buffer has not been set wait for it. Once the control handle is dropped no
+        // buffer can be set anymore, so the node does not need to be kept alive.
         let buffer = match &self.buffer {
             None => {
                 output.make_silent();
-                return true;
+                return false;
             }
             Some(b) => b,
         };
@@ -397,8 +454,9 @@ impl AudioProcessor for AudioBufferSourceRenderer {
 
         // 1. the stop time has been reached.
         // 2. the duration has been reached.
-        if scope.current_time >= stop_time || self.render_state.buffer_time_elapsed >= duration {
+        if current_frame >= stop_frame || self.render_state.buffer_time_elapsed >= duration {
             output.make_silent(); // also converts to mono
+            self.activity.set_active(false);
             return false;
         }
 
@@ -406,27 +464,30 @@ impl AudioProcessor for AudioBufferSourceRenderer {
         if !loop_ {
             if computed_playback_rate > 0. && self.render_state.buffer_time >= buffer_duration {
                 output.make_silent(); // also converts to mono
+                self.activity.set_active(false);
                 return false;
             }
 
             if computed_playback_rate < 0. && self.render_state.buffer_time < 0. {
                 output.make_silent(); // also converts to mono
+                self.activity.set_active(false);
                 return false;
             }
         }
 
+        self.activity.set_active(true);
+
+        // the output follows the channel count of the buffer, not the channel config of the node
         output.set_number_of_channels(buffer.number_of_channels());
 
         // go through the algorithm described in the spec
         // @see <https://webaudio.github.io/web-audio-api/#playback-AudioBufferSourceNode>
-        let mut current_time = scope.current_time;
-
         // prevent scheduling in the past
         // If 0 is passed in for this value or if the value is less than
         // currentTime, then the sound will start playing immediately
         // cf. https://webaudio.github.io/web-audio-api/#dom-audioscheduledsourcenode-start-when-when
-        if !self.render_state.started && start_time < current_time {
-            start_time = current_time;
+        if !self.render_state.started && start_frame < current_frame {
+            start_frame = current_frame;
         }
 
         if loop_ {
@@ -443,19 +504,19 @@ impl AudioProcessor for AudioBufferSourceRenderer {
 
         // compute position for each sample and store into `self.positions`
         for index in 0..num_frames {
-            if current_time < start_time
-                || current_time >= stop_time
+            let frame = current_frame + index as f64;
+
+            if frame < start_frame
+                || frame >= stop_frame
                 || self.render_state.buffer_time_elapsed >= duration
             {
                 self.playback_infos[index] = None;
-                current_time += dt;
-
                 continue; // nothing more to do for this sample
             }
 
             // we have now reached start time
             if !self.render_state.started {
-                offset += current_time - start_time;
+                offset += (frame - start_frame) * dt;
 
                 if loop_ && computed_playback_rate >= 0. && offset >= actual_loop_end {
                     offset = actual_loop_end;
@@ -515,7 +576,6 @@ impl AudioProcessor for AudioBufferSourceRenderer {
             let time_incr = dt * computed_playback_rate;
             self.render_state.buffer_time += time_incr;
             self.render_state.buffer_time_elapsed += time_incr;
-            current_time += dt;
         }
 
         // fill output according to computed positions
@@ -558,6 +618,7 @@ mod tests {
     use std::f32::consts::PI;
 
     use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{ChannelCountMode, ChannelInterpretation, PlaybackState};
     use crate::RENDER_QUANTUM_SIZE;
 
     use super::*;
@@ -745,4 +806,167 @@ mod tests {
             assert_float_eq!(channel[..], expected[..], abs_all <= 1e-6);
         });
     }
+
+    /// Play a buffer holding the channel index plus one in each channel, through a discrete
+    /// destination of 6 channels
+    fn render_multichannel(number_of_channels: usize, channel_count: Option<usize>) -> AudioBuffer {
+        let mut context = OfflineAudioContext::new(6, RENDER_QUANTUM_SIZE * 2, 44_100.);
+        context
+            .destination()
+            .set_channel_interpretation(ChannelInterpretation::Discrete);
+
+        let mut buffer =
+            context.create_buffer(number_of_channels, RENDER_QUANTUM_SIZE * 2, 44_100.);
+        for i in 0..number_of_channels {
+            buffer.copy_to_channel(&[(i + 1) as f32; RENDER_QUANTUM_SIZE * 2], i);
+        }
+
+        let src = context.create_buffer_source();
+        if let Some(count) = channel_count {
+            src.set_channel_count(count);
+            src.set_channel_count_mode(ChannelCountMode::Explicit);
+        }
+        src.connect(&context.destination());
+        src.set_buffer(buffer);
+        src.start();
+
+        context.start_rendering_sync()
+    }
+
+    #[test]
+    fn test_output_follows_buffer_channels() {
+        // the channel config of the source does not affect its output
+        for channel_count in [None, Some(1), Some(2)] {
+            let result = render_multichannel(6, channel_count);
+            for i in 0..6 {
+                assert_float_eq!(
+                    result.get_channel_data(i),
+                    &[(i + 1) as f32; RENDER_QUANTUM_SIZE * 2][..],
+                    abs_all <= 0.
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_mono_buffer_output() {
+        let result = render_multichannel(1, None);
+        assert_float_eq!(
+            result.get_channel_data(0),
+            &[1.; RENDER_QUANTUM_SIZE * 2][..],
+            abs_all <= 0.
+        );
+        // discrete up-mixing fills the other channels with silence
+        for i in 1..6 {
+            assert_float_eq!(
+                result.get_channel_data(i),
+                &[0.; RENDER_QUANTUM_SIZE * 2][..],
+                abs_all <= 0.
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError - cannot call `start` twice")]
+    fn test_start_twice() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let src = context.create_buffer_source();
+        src.start();
+        src.start_at(5.);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError - cannot call `stop` twice")]
+    fn test_stop_twice() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let src = context.create_buffer_source();
+        src.start();
+        src.stop_at(1.);
+        src.stop();
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError - cannot call `stop` before `start`")]
+    fn test_stop_before_start() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let src = context.create_buffer_source();
+        src.stop();
+    }
+
+    #[test]
+    fn test_invalid_start_stop_times() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+        for &when in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY].iter() {
+            let src = context.create_buffer_source();
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                src.start_at(when);
+            }));
+            assert!(result.is_err(), "start at {} should be rejected", when);
+            // the rejected call does not count as a start
+            assert_eq!(src.scheduled_start_time(), None);
+
+            src.start();
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                src.stop_at(when);
+            }));
+            assert!(result.is_err(), "stop at {} should be rejected", when);
+            assert_eq!(src.scheduled_stop_time(), None);
+        }
+    }
+
+    #[test]
+    fn test_negative_start_stop_times() {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let src = context.create_buffer_source();
+        src.connect(&context.destination());
+        src.start_at(-1.);
+        src.stop_at(-0.5);
+        assert_eq!(src.scheduled_start_time(), Some(0.));
+        assert_eq!(src.scheduled_stop_time(), Some(0.));
+
+        let output = context.start_rendering_sync();
+        assert!(output.get_channel_data(0).iter().all(|v| *v == 0.));
+    }
+
+    #[test]
+    fn test_playback_state() {
+        // one render quantum lasts 10 ms
+        let sample_rate = 12_800.;
+        let quantum = RENDER_QUANTUM_SIZE as f64 / sample_rate as f64;
+
+        // plays from the middle of quantum 1 to the middle of quantum 3, the end of the buffer
+        let state_after = |quanta: usize| {
+            let mut context =
+                OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * quanta, sample_rate);
+            let buffer = AudioBuffer::from(vec![vec![1.; RENDER_QUANTUM_SIZE * 2]], sample_rate);
+            let src = context.create_buffer_source();
+            src.set_buffer(buffer);
+            src.connect(&context.destination());
+            assert_eq!(src.playback_state(), PlaybackState::Unscheduled);
+            src.start_at(1.5 * quantum);
+            assert_eq!(src.playback_state(), PlaybackState::Scheduled);
+
+            let _ = context.start_rendering_sync();
+            src.playback_state()
+        };
+
+        assert_eq!(state_after(1), PlaybackState::Scheduled);
+        assert_eq!(state_after(2), PlaybackState::Playing);
+        assert_eq!(state_after(4), PlaybackState::Playing);
+        assert_eq!(state_after(5), PlaybackState::Finished);
+        assert_eq!(state_after(6), PlaybackState::Finished);
+    }
+
+    #[test]
+    fn test_playback_state_cancelled() {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let src = context.create_buffer_source();
+        src.start_at(1.);
+        src.cancel_scheduled_start();
+        assert_eq!(src.playback_state(), PlaybackState::Unscheduled);
+
+        let _ = context.start_rendering_sync();
+        assert_eq!(src.playback_state(), PlaybackState::Unscheduled);
+    }
 }
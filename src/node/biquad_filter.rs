@@ -1,6 +1,7 @@
 //! The biquad filter control and renderer parts
+use core::f64::consts::{PI, SQRT_2};
+#[cfg(feature = "std")]
 use std::{
-    f64::consts::{PI, SQRT_2},
     sync::{
         atomic::{AtomicU32, Ordering},
         Arc,
@@ -8,21 +9,28 @@ use std::{
     time::Duration,
 };
 
+#[cfg(feature = "std")]
 use crossbeam_channel::{Receiver, Sender};
+#[cfg(feature = "std")]
 use num_complex::Complex;
 
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+#[cfg(feature = "std")]
 use crate::{
     context::{AudioContextRegistration, AudioParamId, BaseAudioContext},
     param::{AudioParam, AudioParamDescriptor},
-    render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope},
-    MAX_CHANNELS,
+    render::{AudioParamValues, AudioProcessor, RenderScope},
 };
+use crate::{render::AudioRenderQuantum, MAX_CHANNELS, RENDER_QUANTUM_SIZE};
 
+#[cfg(feature = "std")]
 use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
 
 /// Coefficients request
 /// This request is send by the control thread and send back by the rendering thread with
 /// current coefficients array
+#[cfg(feature = "std")]
 struct CoeffsReq(Sender<[f64; 5]>);
 
 /// Biquad filter types
@@ -80,6 +88,7 @@ impl From<u32> for BiquadFilterType {
 //   float frequency = 350;
 //   float gain = 0;
 // };
+#[cfg(feature = "std")]
 #[derive(Clone, Debug)]
 pub struct BiquadFilterOptions {
     pub q: f32,
@@ -87,9 +96,14 @@ pub struct BiquadFilterOptions {
     pub frequency: f32,
     pub gain: f32,
     pub type_: BiquadFilterType,
+    /// Number of frames between two computations of the coefficients when the params are
+    /// automated, the coefficients are linearly interpolated in between. Use 1 to compute
+    /// them for every frame.
+    pub coefficient_interval: usize,
     pub channel_config: ChannelConfigOptions,
 }
 
+#[cfg(feature = "std")]
 impl Default for BiquadFilterOptions {
     fn default() -> Self {
         Self {
@@ -98,12 +112,14 @@ impl Default for BiquadFilterOptions {
             frequency: 350.,
             gain: 0.,
             type_: BiquadFilterType::default(),
+            coefficient_interval: 8,
             channel_config: ChannelConfigOptions::default(),
         }
     }
 }
 
 /// `BiquadFilterNode` is a second order IIR filter
+#[cfg(feature = "std")]
 pub struct BiquadFilterNode {
     /// Represents the node instance and its associated audio context
     registration: AudioContextRegistration,
@@ -121,12 +137,14 @@ pub struct BiquadFilterNode {
     /// boost/attenuation (dB) - its impact on the frequency response of the filter
     /// depends on the `BiquadFilterType`
     gain: AudioParam,
-    /// `BiquadFilterType` repesented as u32
+    /// `BiquadFilterType` repesented as u32, independent of the other values so `Relaxed`
+    /// ordering suffices
     type_: Arc<AtomicU32>,
     /// sender used to send message to the rendering part of the node
     sender: Sender<CoeffsReq>,
 }
 
+#[cfg(feature = "std")]
 impl AudioNode for BiquadFilterNode {
     fn registration(&self) -> &AudioContextRegistration {
         &self.registration
@@ -143,8 +161,18 @@ impl AudioNode for BiquadFilterNode {
     fn number_of_outputs(&self) -> usize {
         1
     }
+
+    fn params(&self) -> Vec<(&'static str, &AudioParam)> {
+        vec![
+            ("frequency", &self.frequency),
+            ("detune", &self.detune),
+            ("Q", &self.q),
+            ("gain", &self.gain),
+        ]
+    }
 }
 
+#[cfg(feature = "std")]
 impl BiquadFilterNode {
     /// returns a `BiquadFilterNode` instance
     ///
@@ -152,7 +180,15 @@ impl BiquadFilterNode {
     ///
     /// * `context` - audio context in which the audio node will live.
     /// * `options` - biquad filter options
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `options.coefficient_interval` is zero
     pub fn new<C: BaseAudioContext>(context: &C, options: BiquadFilterOptions) -> Self {
+        if options.coefficient_interval == 0 {
+            panic!("RangeError - the coefficient interval should be at least 1");
+        }
+
         context.register(move |registration| {
             let q_value = options.q;
             let d_value = options.detune;
@@ -211,6 +247,7 @@ impl BiquadFilterNode {
                 frequency: f_proc,
                 q: q_proc,
                 type_: type_.clone(),
+                coefficient_interval: options.coefficient_interval,
                 receiver,
             };
 
@@ -257,7 +294,7 @@ impl BiquadFilterNode {
     /// Returns the biquad filter type
     #[must_use]
     pub fn type_(&self) -> BiquadFilterType {
-        self.type_.load(Ordering::SeqCst).into()
+        self.type_.load(Ordering::Relaxed).into()
     }
 
     /// biquad filter type setter
@@ -266,7 +303,7 @@ impl BiquadFilterNode {
     ///
     /// * `type_` - the biquad filter type (lowpass, highpass,...)
     pub fn set_type(&self, type_: BiquadFilterType) {
-        self.type_.store(type_ as u32, Ordering::SeqCst);
+        self.type_.store(type_ as u32, Ordering::Relaxed);
     }
 
     /// Returns the frequency response for the specified frequencies
@@ -386,6 +423,7 @@ struct CoeffsConfig {
 
 /// Helper struct which regroups all parameters
 /// required to build `BiquadFilterRenderer`
+#[cfg(feature = "std")]
 struct RendererConfig {
     /// quality factor - its impact on the frequency response of the filter
     /// depends on the `BiquadFilterType`
@@ -399,15 +437,18 @@ struct RendererConfig {
     /// boost/attenuation (dB) - its impact on the frequency response of the filter
     /// depends on the `BiquadFilterType`
     gain: AudioParamId,
-    /// `BiquadFilterType` repesented as u32
+    /// `BiquadFilterType` repesented as u32, independent of the other values so `Relaxed`
+    /// ordering suffices
     type_: Arc<AtomicU32>,
+    /// number of frames between two computations of the coefficients
+    coefficient_interval: usize,
     /// receiver used to receive message from the control node part
     receiver: Receiver<CoeffsReq>,
 }
 
 /// Biquad filter coefficients
 #[derive(Clone, Copy, Debug)]
-struct Coefficients {
+pub(crate) struct Coefficients {
     /// Denominator coefficient
     a1: f64,
     /// Denominator coefficient
@@ -420,7 +461,71 @@ struct Coefficients {
     b2: f64,
 }
 
+impl Coefficients {
+    /// Computes the coefficients of a biquad filter, normalized by `a0`
+    ///
+    /// # Arguments
+    ///
+    /// * `type_` - biquadfilter type
+    /// * `sample_rate` - audio context sample rate
+    /// * `computed_freq` - `computedOscFreq`
+    /// * `q` - Q factor
+    /// * `gain` - filter gain
+    pub(crate) fn new(
+        type_: BiquadFilterType,
+        sample_rate: f64,
+        computed_freq: f64,
+        q: f64,
+        gain: f64,
+    ) -> Self {
+        type R = BiquadFilter;
+
+        // compute a0 first to normalize others coeffs by a0
+        let a0 = R::a0(type_, sample_rate, computed_freq, q, gain);
+
+        Self {
+            b0: R::b0(type_, sample_rate, computed_freq, q, gain) / a0,
+            b1: R::b1(type_, sample_rate, computed_freq, gain) / a0,
+            b2: R::b2(type_, sample_rate, computed_freq, q, gain) / a0,
+            a1: R::a1(type_, sample_rate, computed_freq, gain) / a0,
+            a2: R::a2(type_, sample_rate, computed_freq, q, gain) / a0,
+        }
+    }
+
+    /// Coefficients of a filter designed elsewhere, already normalized by `a0`
+    #[cfg(feature = "std")]
+    pub(crate) const fn from_normalized(b0: f64, b1: f64, b2: f64, a1: f64, a2: f64) -> Self {
+        Self { a1, a2, b0, b1, b2 }
+    }
+
+    /// Linear interpolation between two sets of coefficients, `t` being in the `[0, 1]` range
+    #[inline]
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        Self {
+            a1: (other.a1 - self.a1).mul_add(t, self.a1),
+            a2: (other.a2 - self.a2).mul_add(t, self.a2),
+            b0: (other.b0 - self.b0).mul_add(t, self.b0),
+            b1: (other.b1 - self.b1).mul_add(t, self.b1),
+            b2: (other.b2 - self.b2).mul_add(t, self.b2),
+        }
+    }
+
+    /// Generate an output sample by filtering an input sample, updating the given filter state
+    #[inline]
+    #[allow(clippy::cast_possible_truncation)]
+    pub(crate) fn tick(&self, input: f32, s1: &mut f64, s2: &mut f64) -> f32 {
+        let input = f64::from(input);
+        let out = self.b0.mul_add(input, *s1);
+        *s1 = self.b1.mul_add(input, *s2) - self.a1 * out;
+        *s2 = self.b2 * input - self.a2 * out;
+
+        // Value truncation will not be hearable
+        out as f32
+    }
+}
+
 /// `BiquadFilterRenderer` represents the rendering part of `BiquadFilterNode`
+#[cfg(feature = "std")]
 struct BiquadFilterRenderer {
     /// quality factor - its impact on the frequency response of the filter
     /// depends on the `BiquadFilterType`
@@ -434,18 +539,16 @@ struct BiquadFilterRenderer {
     /// boost/attenuation (dB) - its impact on the frequency response of the filter
     /// depends on the `BiquadFilterType`
     gain: AudioParamId,
-    /// `BiquadFilterType` repesented as u32
+    /// `BiquadFilterType` repesented as u32, independent of the other values so `Relaxed`
+    /// ordering suffices
     type_: Arc<AtomicU32>,
-    /// First level of the biquad filter state
-    ss1: [f64; MAX_CHANNELS],
-    /// Second level of the biquad filter state
-    ss2: [f64; MAX_CHANNELS],
-    /// Biquad filter coefficients computed from freq, q, gain,...
-    coeffs: Coefficients,
+    /// the filter itself
+    filter: BiquadFilter,
     /// receiver used to receive message from the control node part
     receiver: Receiver<CoeffsReq>,
 }
 
+#[cfg(feature = "std")]
 impl AudioProcessor for BiquadFilterRenderer {
     fn process(
         &mut self,
@@ -463,7 +566,9 @@ impl AudioProcessor for BiquadFilterRenderer {
         let freq_values = params.get(&self.frequency);
         let q_values = params.get(&self.q);
 
-        self.filter(
+        self.filter
+            .set_type(self.type_.load(Ordering::Relaxed).into());
+        self.filter.filter(
             input,
             output,
             g_values,
@@ -473,10 +578,17 @@ impl AudioProcessor for BiquadFilterRenderer {
             scope.sample_rate,
         );
 
+        // Respond to request at K-rate following the specs
+        if let Ok(msg) = self.receiver.try_recv() {
+            let sender = msg.0;
+            sender.send(self.filter.coefficients()).unwrap();
+        }
+
         true // todo tail time - issue #34
     }
 }
 
+#[cfg(feature = "std")]
 impl BiquadFilterRenderer {
     /// returns an `BiquadFilterRenderer` instance
     // new cannot be qualified as const, since constant functions cannot evaluate destructors
@@ -489,9 +601,60 @@ impl BiquadFilterRenderer {
             frequency,
             gain,
             type_,
+            coefficient_interval,
             receiver,
         } = config;
 
+        let filter = BiquadFilter::new(type_.load(Ordering::Relaxed).into(), coefficient_interval);
+
+        Self {
+            gain,
+            detune,
+            frequency,
+            q,
+            type_,
+            filter,
+            receiver,
+        }
+    }
+}
+
+/// Second order IIR filter, the DSP of the `BiquadFilterNode`
+///
+/// It can be used on its own in an [`AudioProcessor`](crate::render::AudioProcessor), with the
+/// params values computed by [`AudioParamProcessor`](crate::AudioParamProcessor)s.
+pub struct BiquadFilter {
+    /// represents the biquad filter type
+    type_: BiquadFilterType,
+    /// First level of the biquad filter state
+    ss1: [f64; MAX_CHANNELS],
+    /// Second level of the biquad filter state
+    ss2: [f64; MAX_CHANNELS],
+    /// Biquad filter coefficients computed from freq, q, gain,...
+    coeffs: Coefficients,
+    /// number of frames between two computations of the coefficients
+    coefficient_interval: usize,
+    /// coefficients of each frame of the render quantum, when the params are automated
+    coeffs_buffer: [Coefficients; RENDER_QUANTUM_SIZE],
+}
+
+impl BiquadFilter {
+    /// returns a `BiquadFilter` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `type_` - the biquad filter type (lowpass, highpass,...)
+    /// * `coefficient_interval` - number of frames between two computations of the
+    ///   coefficients when the params are automated, they are linearly interpolated in between
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `coefficient_interval` is zero
+    pub fn new(type_: BiquadFilterType, coefficient_interval: usize) -> Self {
+        if coefficient_interval == 0 {
+            panic!("RangeError - the coefficient interval should be at least 1");
+        }
+
         let coeffs = Coefficients {
             a1: 0.,
             a2: 0.,
@@ -500,32 +663,46 @@ impl BiquadFilterRenderer {
             b2: 0.,
         };
 
-        let s1 = [0.; MAX_CHANNELS];
-        let s2 = [0.; MAX_CHANNELS];
-
         Self {
-            gain,
-            detune,
-            frequency,
-            q,
             type_,
-            ss1: s1,
-            ss2: s2,
+            ss1: [0.; MAX_CHANNELS],
+            ss2: [0.; MAX_CHANNELS],
             coeffs,
-            receiver,
+            coefficient_interval,
+            coeffs_buffer: [coeffs; RENDER_QUANTUM_SIZE],
         }
     }
 
+    /// Returns the biquad filter type
+    #[must_use]
+    pub fn type_(&self) -> BiquadFilterType {
+        self.type_
+    }
+
+    /// biquad filter type setter, the filter state is kept
+    pub fn set_type(&mut self, type_: BiquadFilterType) {
+        self.type_ = type_;
+    }
+
+    /// Coefficients of the last filtered frame, as `[b0, b1, b2, a1, a2]`
+    #[cfg(feature = "std")]
+    fn coefficients(&self) -> [f64; 5] {
+        let Coefficients { b0, b1, b2, a1, a2 } = self.coeffs;
+        [b0, b1, b2, a1, a2]
+    }
+
     /// Generate an output by filtering the input following the params values
     ///
     /// # Arguments
     ///
     /// * `input` - Audiobuffer input
     /// * `output` - Audiobuffer output
-    /// * `params` - biquadfilter params which resolves into biquad coeffs
+    /// * `g_values`, `det_values`, `freq_values`, `q_values` - values of the gain, detune,
+    ///   frequency and Q params for each frame of the render quantum
+    /// * `sample_rate` - sample rate of the input
     #[inline]
     #[allow(clippy::too_many_arguments)]
-    fn filter(
+    pub fn filter(
         &mut self,
         input: &AudioRenderQuantum,
         output: &mut AudioRenderQuantum,
@@ -535,35 +712,79 @@ impl BiquadFilterRenderer {
         q_values: &[f32],
         sample_rate: f32,
     ) {
-        for (channel_idx, (i_data, o_data)) in input
-            .channels()
-            .iter()
-            .zip(output.channels_mut())
-            .enumerate()
-        {
-            for (sample_idx, (&i, o)) in i_data.iter().zip(o_data.iter_mut()).enumerate() {
-                let p = CoeffsConfig {
-                    q: q_values[sample_idx],
-                    detune: det_values[sample_idx],
-                    frequency: freq_values[sample_idx],
-                    gain: g_values[sample_idx],
-                    type_: BiquadFilterType::from(self.type_.load(Ordering::SeqCst)),
-                };
-
-                // A-rate params
-                self.update_coeffs(&p, sample_rate);
-                *o = self.tick(i, channel_idx);
-            }
-        }
-
-        let Coefficients { b0, b1, b2, a1, a2 } = self.coeffs;
+        let type_ = self.type_;
+        let coeffs_at = |sample_idx: usize| {
+            let p = CoeffsConfig {
+                q: q_values[sample_idx],
+                detune: det_values[sample_idx],
+                frequency: freq_values[sample_idx],
+                gain: g_values[sample_idx],
+                type_,
+            };
+            Self::compute_coeffs(&p, sample_rate)
+        };
 
-        let coeffs_resp = [b0, b1, b2, a1, a2];
+        let is_constant = |values: &[f32]| values.iter().all(|&v| v == values[0]);
+        let constant_params = is_constant(g_values)
+            && is_constant(det_values)
+            && is_constant(freq_values)
+            && is_constant(q_values);
+
+        if constant_params {
+            // K-rate, or A-rate params without automation in this quantum
+            self.coeffs = coeffs_at(0);
+
+            for (channel_idx, (i_data, o_data)) in input
+                .channels()
+                .iter()
+                .zip(output.channels_mut())
+                .enumerate()
+            {
+                for (&i, o) in i_data.iter().zip(o_data.iter_mut()) {
+                    *o = self.tick(i, channel_idx);
+                }
+            }
+        } else {
+            // A-rate params, the transcendental functions are only evaluated every
+            // `coefficient_interval` frames (and on the last frame), the coefficients are
+            // linearly interpolated in between
+            let last = freq_values.len() - 1;
+            let mut start = 0;
+            let mut start_coeffs = coeffs_at(0);
+
+            while start < last {
+                let end = (start + self.coefficient_interval).min(last);
+                let end_coeffs = coeffs_at(end);
+                let len = (end - start) as f64;
+
+                self.coeffs_buffer[start..end]
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(i, c)| *c = start_coeffs.lerp(&end_coeffs, i as f64 / len));
+
+                start = end;
+                start_coeffs = end_coeffs;
+            }
 
-        // Respond to request at K-rate following the specs
-        if let Ok(msg) = self.receiver.try_recv() {
-            let sender = msg.0;
-            sender.send(coeffs_resp).unwrap();
+            self.coeffs_buffer[last] = start_coeffs;
+            self.coeffs = start_coeffs;
+
+            for (channel_idx, (i_data, o_data)) in input
+                .channels()
+                .iter()
+                .zip(output.channels_mut())
+                .enumerate()
+            {
+                let (s1, s2) = (&mut self.ss1[channel_idx], &mut self.ss2[channel_idx]);
+
+                for ((&i, o), coeffs) in i_data
+                    .iter()
+                    .zip(o_data.iter_mut())
+                    .zip(self.coeffs_buffer.iter())
+                {
+                    *o = coeffs.tick(i, s1, s2);
+                }
+            }
         }
     }
 
@@ -574,24 +795,18 @@ impl BiquadFilterRenderer {
     /// * `input` - Audiobuffer input
     /// * `idx` - channel index mapping to the filter state index
     #[inline]
-    #[allow(clippy::cast_possible_truncation)]
     fn tick(&mut self, input: f32, idx: usize) -> f32 {
-        let input = f64::from(input);
-        let out = self.coeffs.b0.mul_add(input, self.ss1[idx]);
-        self.ss1[idx] = self.coeffs.b1.mul_add(input, self.ss2[idx]) - self.coeffs.a1 * out;
-        self.ss2[idx] = self.coeffs.b2 * input - self.coeffs.a2 * out;
-
-        // Value truncation will not be hearable
-        out as f32
+        self.coeffs
+            .tick(input, &mut self.ss1[idx], &mut self.ss2[idx])
     }
 
-    /// updates biquad filter coefficients when params are modified
+    /// computes biquad filter coefficients from the params values
     ///
     /// # Arguments
     ///
     /// * `params` - params resolving into biquad coeffs
     #[inline]
-    fn update_coeffs(&mut self, params: &CoeffsConfig, sample_rate: f32) {
+    fn compute_coeffs(params: &CoeffsConfig, sample_rate: f32) -> Coefficients {
         let CoeffsConfig {
             q,
             detune,
@@ -607,14 +822,7 @@ impl BiquadFilterRenderer {
         let q = f64::from(*q);
         let gain = f64::from(*gain);
 
-        // compute a0 first to normalize others coeffs by a0
-        let a0 = Self::a0(*type_, sample_rate, computed_freq, q, gain);
-
-        self.coeffs.b0 = Self::b0(*type_, sample_rate, computed_freq, q, gain) / a0;
-        self.coeffs.b1 = Self::b1(*type_, sample_rate, computed_freq, gain) / a0;
-        self.coeffs.b2 = Self::b2(*type_, sample_rate, computed_freq, q, gain) / a0;
-        self.coeffs.a1 = Self::a1(*type_, sample_rate, computed_freq, gain) / a0;
-        self.coeffs.a2 = Self::a2(*type_, sample_rate, computed_freq, q, gain) / a0;
+        Coefficients::new(*type_, sample_rate, computed_freq, q, gain)
     }
 
     /// calculates `b_0` numerator coefficient
@@ -1070,6 +1278,7 @@ mod test {
     use float_eq::assert_float_eq;
 
     use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode, OscillatorType};
 
     use super::{BiquadFilterNode, BiquadFilterOptions, BiquadFilterType};
 
@@ -1207,4 +1416,75 @@ mod test {
         let ref_arr = [0., niquyst];
         assert_float_eq!(frequency_hz, ref_arr, abs_all <= 0.);
     }
+
+    // filter a sawtooth through a filter with the given coefficient interval, the
+    // `automate` callback sets up the params
+    fn render_filtered(
+        coefficient_interval: usize,
+        automate: impl Fn(&BiquadFilterNode),
+    ) -> Vec<f32> {
+        let sample_rate = 44_100.;
+        let mut context = OfflineAudioContext::new(1, sample_rate as usize, sample_rate);
+
+        let options = BiquadFilterOptions {
+            q: 5.,
+            coefficient_interval,
+            ..BiquadFilterOptions::default()
+        };
+        let biquad = BiquadFilterNode::new(&context, options);
+        automate(&biquad);
+        biquad.connect(&context.destination());
+
+        let osc = context.create_oscillator();
+        osc.set_type(OscillatorType::Sawtooth);
+        osc.frequency().set_value(110.);
+        osc.connect(&biquad);
+        osc.start();
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_decimated_coefficients_sweep() {
+        // slow sweep over the whole audible range
+        let sweep = |biquad: &BiquadFilterNode| {
+            biquad.frequency().set_value_at_time(100., 0.);
+            biquad
+                .frequency()
+                .exponential_ramp_to_value_at_time(10_000., 1.);
+            biquad.q().set_value_at_time(1., 0.);
+            biquad.q().linear_ramp_to_value_at_time(10., 1.);
+        };
+
+        let reference = render_filtered(1, sweep);
+        let result = render_filtered(BiquadFilterOptions::default().coefficient_interval, sweep);
+
+        // difference below -80dBFS
+        assert_float_eq!(result[..], reference[..], abs_all <= 1e-4);
+        // the sweep is rendered
+        assert!(reference.iter().any(|v| v.abs() > 0.1));
+    }
+
+    #[test]
+    fn test_constant_params_coefficients() {
+        let fixed = |biquad: &BiquadFilterNode| {
+            biquad.frequency().set_value(1_000.);
+        };
+
+        let reference = render_filtered(1, fixed);
+        let result = render_filtered(32, fixed);
+
+        assert_float_eq!(result[..], reference[..], abs_all <= 0.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_coefficient_interval() {
+        let context = OfflineAudioContext::new(2, LENGTH, 44_100.);
+        let options = BiquadFilterOptions {
+            coefficient_interval: 0,
+            ..BiquadFilterOptions::default()
+        };
+        let _biquad = BiquadFilterNode::new(&context, options);
+    }
 }
@@ -0,0 +1,263 @@
+//! Channel configuration of the audio nodes, shared with the render core
+
+use alloc::sync::Arc;
+#[cfg(feature = "std")]
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+#[cfg(feature = "std")]
+use std::sync::{Mutex, Weak};
+
+/// How channels must be matched between the node's inputs and outputs.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ChannelCountMode {
+    /// `computedNumberOfChannels` is the maximum of the number of channels of all connections to an
+    /// input. In this mode channelCount is ignored.
+    Max,
+    /// `computedNumberOfChannels` is determined as for "max" and then clamped to a maximum value of
+    /// the given channelCount.
+    ClampedMax,
+    /// `computedNumberOfChannels` is the exact value as specified by the channelCount.
+    Explicit,
+}
+
+impl From<u32> for ChannelCountMode {
+    fn from(i: u32) -> Self {
+        use ChannelCountMode::*;
+
+        match i {
+            0 => Max,
+            1 => ClampedMax,
+            2 => Explicit,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The meaning of the channels, defining how audio up-mixing and down-mixing will happen.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ChannelInterpretation {
+    Speakers,
+    Discrete,
+}
+
+impl From<u32> for ChannelInterpretation {
+    fn from(i: u32) -> Self {
+        use ChannelInterpretation::*;
+
+        match i {
+            0 => Speakers,
+            1 => Discrete,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Options for constructing ChannelConfig
+#[derive(Clone, Debug)]
+pub struct ChannelConfigOptions {
+    pub count: usize,
+    pub mode: ChannelCountMode,
+    pub interpretation: ChannelInterpretation,
+}
+
+impl Default for ChannelConfigOptions {
+    fn default() -> Self {
+        Self {
+            count: 2,
+            mode: ChannelCountMode::Max,
+            interpretation: ChannelInterpretation::Speakers,
+        }
+    }
+}
+
+/// Channel configs changed on the control thread, by node id, to be sent to the render thread
+/// in a single message
+#[cfg(feature = "std")]
+pub(crate) type ChannelConfigQueue = Mutex<Vec<(u64, ChannelConfig)>>;
+
+/// Node the config belongs to, once registered in a context
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+struct ChannelConfigRegistration {
+    /// id of the node and queue of its context
+    target: Mutex<Option<(u64, Weak<ChannelConfigQueue>)>>,
+    /// the config has changes that are not sent to the render thread yet
+    queued: AtomicBool,
+}
+
+/// Config for up/down-mixing of input channels for audio nodes
+///
+/// Only when implementing the [`AudioNode`](crate::node::AudioNode) trait manually, is this struct of any concern. The
+/// methods `set_channel_count`, `set_channel_count_mode` and `set_channel_interpretation` from the
+/// audio node interface will use this struct to sync the required info to the render thread.
+///
+/// Changes are batched: they are sent to the render thread all at once on the next call that
+/// talks to the render thread, or on
+/// [`AudioNode::apply_channel_config`](crate::node::AudioNode::apply_channel_config). The render
+/// thread applies them between two render quanta, so a render quantum never observes a
+/// partially applied config.
+///
+/// The only way to construct an instance is with [`ChannelConfigOptions`]
+///
+/// ```
+/// use web_audio_api::node::{ChannelConfigOptions, ChannelConfig, ChannelInterpretation, ChannelCountMode};
+///
+/// let opts = ChannelConfigOptions {
+///     count: 1,
+///     mode: ChannelCountMode::Explicit,
+///     interpretation: ChannelInterpretation::Discrete,
+/// };
+/// let _: ChannelConfig = opts.into();
+#[derive(Clone, Debug)]
+pub struct ChannelConfig {
+    // The render thread only reads the values once, when the node is registered. They use
+    // `SeqCst`, as does `queued`: a setter stores a value then sets `queued`, while the context
+    // clears `queued` then loads the values. Either the setter queues the config again, or the
+    // context reads the new value.
+    count: Arc<AtomicUsize>,
+    mode: Arc<AtomicU32>,
+    interpretation: Arc<AtomicU32>,
+    #[cfg(feature = "std")]
+    registration: Arc<ChannelConfigRegistration>,
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        ChannelConfigOptions::default().into()
+    }
+}
+
+// All methods on this struct are marked `pub(crate)` because we don't want outside users to be able to change the values directly.
+// These methods are only accessible via the AudioNode interface, so AudioNode's that have channel count/mode constraints
+// should be able to assert those.
+impl ChannelConfig {
+    /// Represents an enumerated value describing the way channels must be matched between the
+    /// node's inputs and outputs.
+    pub(crate) fn count_mode(&self) -> ChannelCountMode {
+        self.mode.load(Ordering::SeqCst).into()
+    }
+    #[cfg(feature = "std")]
+    pub(crate) fn set_count_mode(&self, v: ChannelCountMode) {
+        self.mode.store(v as u32, Ordering::SeqCst);
+        self.queue_changes();
+    }
+
+    /// Represents an enumerated value describing the meaning of the channels. This interpretation
+    /// will define how audio up-mixing and down-mixing will happen.
+    pub(crate) fn interpretation(&self) -> ChannelInterpretation {
+        self.interpretation.load(Ordering::SeqCst).into()
+    }
+    #[cfg(feature = "std")]
+    pub(crate) fn set_interpretation(&self, v: ChannelInterpretation) {
+        self.interpretation.store(v as u32, Ordering::SeqCst);
+        self.queue_changes();
+    }
+
+    /// Represents an integer used to determine how many channels are used when up-mixing and
+    /// down-mixing connections to any inputs to the node.
+    pub(crate) fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+    #[cfg(feature = "std")]
+    pub(crate) fn set_count(&self, v: usize) {
+        crate::assert_valid_number_of_channels(v);
+        self.count.store(v, Ordering::SeqCst);
+        self.queue_changes();
+    }
+
+    /// Snapshot of the current values
+    pub(crate) fn options(&self) -> ChannelConfigOptions {
+        ChannelConfigOptions {
+            count: self.count(),
+            mode: self.count_mode(),
+            interpretation: self.interpretation(),
+        }
+    }
+
+    /// Attach the config to the node with the given id, further changes are pushed to the queue
+    /// of the context
+    #[cfg(feature = "std")]
+    pub(crate) fn register(&self, id: u64, queue: &Arc<ChannelConfigQueue>) {
+        *self.registration.target.lock().unwrap() = Some((id, Arc::downgrade(queue)));
+    }
+
+    /// Mark the queued changes as sent to the render thread
+    #[cfg(feature = "std")]
+    pub(crate) fn dequeue(&self) {
+        self.registration.queued.store(false, Ordering::SeqCst);
+    }
+
+    /// Push the config to the queue of its context, if not queued already
+    #[cfg(feature = "std")]
+    fn queue_changes(&self) {
+        let target = self.registration.target.lock().unwrap();
+        if let Some((id, queue)) = target.as_ref() {
+            if !self.registration.queued.swap(true, Ordering::SeqCst) {
+                if let Some(queue) = queue.upgrade() {
+                    queue.lock().unwrap().push((*id, self.clone()));
+                }
+            }
+        }
+    }
+}
+
+/// # Panics
+///
+/// Panics if the channel count of the options is outside the [1, 32] range, 32 being defined by
+/// the `MAX_CHANNELS` constant.
+impl From<ChannelConfigOptions> for ChannelConfig {
+    #[track_caller]
+    fn from(opts: ChannelConfigOptions) -> Self {
+        crate::assert_valid_number_of_channels(opts.count);
+
+        Self {
+            count: Arc::new(AtomicUsize::from(opts.count)),
+            mode: Arc::new(AtomicU32::from(opts.mode as u32)),
+            interpretation: Arc::new(AtomicU32::from(opts.interpretation as u32)),
+            #[cfg(feature = "std")]
+            registration: Arc::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_options_zero_channels() {
+        let options = ChannelConfigOptions {
+            count: 0,
+            ..ChannelConfigOptions::default()
+        };
+        let _: ChannelConfig = options.into();
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_options_too_many_channels() {
+        let options = ChannelConfigOptions {
+            count: crate::MAX_CHANNELS + 1,
+            ..ChannelConfigOptions::default()
+        };
+        let _: ChannelConfig = options.into();
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_set_zero_channels() {
+        let config = ChannelConfig::default();
+        config.set_count(0);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_set_count_is_unchanged_on_panic() {
+        let config = ChannelConfig::default();
+        let result = std::panic::catch_unwind(|| config.set_count(0));
+        assert!(result.is_err());
+        assert_eq!(config.count(), 2);
+    }
+}
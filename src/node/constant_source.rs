@@ -1,10 +1,9 @@
 use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
-use crate::control::Scheduler;
+use crate::control::{Playback, Scheduler, SourceActivity};
 use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
-use crate::RENDER_QUANTUM_SIZE;
 
-use super::{AudioNode, AudioScheduledSourceNode, ChannelConfig};
+use super::{AudioNode, AudioScheduledSourceNode, ChannelConfig, PlaybackState};
 
 /// Options for constructing an [`ConstantSourceNode`]
 // dictionary ConstantSourceOptions {
@@ -78,6 +77,10 @@ impl AudioNode for ConstantSourceNode {
     fn number_of_outputs(&self) -> usize {
         1
     }
+
+    fn params(&self) -> Vec<(&'static str, &AudioParam)> {
+        vec![("offset", &self.offset)]
+    }
 }
 
 impl AudioScheduledSourceNode for ConstantSourceNode {
@@ -87,7 +90,9 @@ impl AudioScheduledSourceNode for ConstantSourceNode {
     }
 
     fn start_at(&self, when: f64) {
-        self.scheduler.start_at(when);
+        self.registration
+            .context()
+            .schedule_start(&self.scheduler, when);
     }
 
     fn stop(&self) {
@@ -96,7 +101,37 @@ impl AudioScheduledSourceNode for ConstantSourceNode {
     }
 
     fn stop_at(&self, when: f64) {
-        self.scheduler.stop_at(when);
+        self.registration
+            .context()
+            .schedule_stop(&self.scheduler, when);
+    }
+
+    fn start_at_frame(&self, frame: u64) {
+        self.registration
+            .context()
+            .schedule_start_frame(&self.scheduler, frame);
+    }
+
+    fn stop_at_frame(&self, frame: u64) {
+        self.registration
+            .context()
+            .schedule_stop_frame(&self.scheduler, frame);
+    }
+
+    fn scheduled_start_time(&self) -> Option<f64> {
+        self.scheduler.scheduled_start()
+    }
+
+    fn scheduled_stop_time(&self) -> Option<f64> {
+        self.scheduler.scheduled_stop()
+    }
+
+    fn cancel_scheduled_start(&self) {
+        self.registration.context().cancel_start(&self.scheduler);
+    }
+
+    fn playback_state(&self) -> PlaybackState {
+        self.scheduler.playback_state()
     }
 }
 
@@ -117,6 +152,7 @@ impl ConstantSourceNode {
             let render = ConstantSourceRenderer {
                 offset: proc,
                 scheduler: scheduler.clone(),
+                activity: SourceActivity::new(context.base().active_sources().clone(), &scheduler),
             };
 
             let node = ConstantSourceNode {
@@ -138,6 +174,7 @@ impl ConstantSourceNode {
 struct ConstantSourceRenderer {
     offset: AudioParamId,
     scheduler: Scheduler,
+    activity: SourceActivity,
 }
 
 impl AudioProcessor for ConstantSourceRenderer {
@@ -151,30 +188,32 @@ impl AudioProcessor for ConstantSourceRenderer {
         // single output node
         let output = &mut outputs[0];
 
-        let dt = 1. / scope.sample_rate as f64;
-        let next_block_time = scope.current_time + dt * RENDER_QUANTUM_SIZE as f64;
-
-        let start_time = self.scheduler.get_start_at();
-        let stop_time = self.scheduler.get_stop_at();
-
-        if start_time >= next_block_time {
-            output.make_silent();
-            return true;
-        }
+        let start_frame = self.scheduler.get_start_frame();
+        let stop_frame = self.scheduler.get_stop_frame();
 
-        if stop_time < scope.current_time {
-            output.make_silent();
-            return false;
+        match self.scheduler.playback(scope.current_frame) {
+            Playback::Pending => {
+                output.make_silent();
+                return self.scheduler.pending_tail_time();
+            }
+            Playback::Ended => {
+                output.make_silent();
+                self.activity.set_active(false);
+                return false;
+            }
+            Playback::Playing => (),
         }
 
+        self.activity.set_active(true);
         output.force_mono();
 
         let offset_values = params.get(&self.offset);
         let output_channel = output.channel_data_mut(0);
-        let mut current_time = scope.current_time;
 
         for (index, sample_value) in offset_values.iter().enumerate() {
-            if current_time < start_time || current_time >= stop_time {
+            let frame = (scope.current_frame + index as u64) as f64;
+
+            if frame < start_frame || frame >= stop_frame {
                 output_channel[index] = 0.;
             } else {
                 // as we pick values directly from the offset param which is already
@@ -182,8 +221,6 @@ impl AudioProcessor for ConstantSourceRenderer {
                 // copying the values to their right place.
                 output_channel[index] = *sample_value;
             }
-
-            current_time += dt;
         }
 
         true
@@ -194,6 +231,7 @@ impl AudioProcessor for ConstantSourceRenderer {
 mod tests {
     use crate::context::{BaseAudioContext, OfflineAudioContext};
     use crate::node::{AudioNode, AudioScheduledSourceNode};
+    use crate::RENDER_QUANTUM_SIZE;
 
     use float_eq::assert_float_eq;
 
@@ -244,4 +282,31 @@ mod tests {
         // 1rst block should be silence
         assert_float_eq!(channel[0..128], vec![1.; 128][..], abs_all <= 0.);
     }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError - cannot call `start` twice")]
+    fn test_start_twice() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let src = context.create_constant_source();
+        src.start();
+        src.start_at(5.);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError - cannot call `stop` twice")]
+    fn test_stop_twice() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let src = context.create_constant_source();
+        src.start();
+        src.stop_at(1.);
+        src.stop();
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError - cannot call `stop` before `start`")]
+    fn test_stop_before_start() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let src = context.create_constant_source();
+        src.stop();
+    }
 }
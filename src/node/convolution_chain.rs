@@ -0,0 +1,399 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::buffer::AudioBuffer;
+use crate::context::{AudioContextRegistration, BaseAudioContext};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::{
+    AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation,
+    ConvolverRendererInner,
+};
+
+/// Message to update the impulse response of a single stage of the chain
+struct StageMessage(usize, ConvolverRendererInner);
+
+/// `ConvolutionChainNode` options
+#[derive(Clone, Debug)]
+pub struct ConvolutionChainOptions {
+    /// The impulse responses, in processing order
+    pub ir_buffers: Vec<AudioBuffer>,
+    /// Perform equal power normalization on each of the impulse responses
+    pub normalize: bool,
+    /// AudioNode options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for ConvolutionChainOptions {
+    fn default() -> Self {
+        Self {
+            ir_buffers: vec![],
+            normalize: true,
+            channel_config: ChannelConfigOptions {
+                count: 2,
+                mode: ChannelCountMode::ClampedMax,
+                interpretation: ChannelInterpretation::Speakers,
+            },
+        }
+    }
+}
+
+/// Processing node which convolves its input with multiple impulse responses in series
+///
+/// This is equivalent to a chain of [`ConvolverNode`](super::ConvolverNode)s, but runs as a
+/// single node in the audio graph. It can be used for reverb designs that combine e.g. early
+/// reflections with late reverberation.
+///
+/// - see also: [`BaseAudioContext::create_convolution_chain`](crate::context::BaseAudioContext::create_convolution_chain)
+///
+/// # Usage
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+///
+/// let file = File::open("samples/sample.wav").unwrap();
+/// let audio_buffer = context.decode_audio_data_sync(file).unwrap();
+///
+/// let early_file = File::open("samples/think-mono-44100.wav").unwrap();
+/// let early = context.decode_audio_data_sync(early_file).unwrap();
+///
+/// let late_file = File::open("samples/think-stereo-44100.wav").unwrap();
+/// let late = context.decode_audio_data_sync(late_file).unwrap();
+///
+/// let src = context.create_buffer_source();
+/// src.set_buffer(audio_buffer);
+///
+/// let chain = context.create_convolution_chain(vec![early, late]);
+///
+/// src.connect(&chain);
+/// chain.connect(&context.destination());
+///
+/// src.start();
+/// ```
+pub struct ConvolutionChainNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Info about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Perform equal power normalization on the impulse responses, only used on the control
+    /// thread
+    normalize: AtomicBool,
+    /// The impulse responses, in processing order
+    ir_buffers: Mutex<Vec<AudioBuffer>>,
+    /// Channel between node and renderer (sender part)
+    sender: Sender<StageMessage>,
+}
+
+impl AudioNode for ConvolutionChainNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+
+    fn set_channel_count_mode(&self, v: ChannelCountMode) {
+        if v == ChannelCountMode::Max {
+            panic!(
+                "NotSupportedError: ConvolutionChainNode channel count mode cannot be set to max"
+            );
+        }
+        self.channel_config.set_count_mode(v);
+    }
+
+    fn set_channel_count(&self, v: usize) {
+        if v > 2 {
+            panic!(
+                "NotSupportedError: ConvolutionChainNode channel count cannot be greater than two"
+            );
+        }
+        self.channel_config.set_count(v);
+    }
+}
+
+impl ConvolutionChainNode {
+    /// returns a `ConvolutionChainNode` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `options` - convolution chain options
+    ///
+    /// # Panics
+    ///
+    /// Panics when no impulse responses are provided, or when one of the impulse responses has a
+    /// sample rate different from the audio context sample rate or a number of channels other
+    /// than 1 or 2.
+    pub fn new<C: BaseAudioContext>(context: &C, options: ConvolutionChainOptions) -> Self {
+        context.register(move |registration| {
+            let ConvolutionChainOptions {
+                ir_buffers,
+                normalize,
+                channel_config,
+            } = options;
+
+            assert!(
+                !ir_buffers.is_empty(),
+                "NotSupportedError: at least one impulse response is required"
+            );
+            assert!(
+                channel_config.count <= 2,
+                "NotSupportedError: channel count"
+            );
+            assert!(
+                channel_config.mode != ChannelCountMode::Max,
+                "NotSupportedError: count mode"
+            );
+
+            let sample_rate = context.sample_rate();
+            let stages = ir_buffers
+                .iter()
+                .map(|buffer| ConvolverRendererInner::prepare(buffer, sample_rate, normalize))
+                .collect();
+
+            // Impulse responses can be updated multiple times, the renderer picks up all of them
+            let (sender, receiver) = crossbeam_channel::unbounded();
+
+            let renderer = ConvolutionChainRenderer { receiver, stages };
+
+            let node = Self {
+                registration,
+                channel_config: channel_config.into(),
+                normalize: AtomicBool::new(normalize),
+                ir_buffers: Mutex::new(ir_buffers),
+                sender,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// The impulse responses of the chain, in processing order
+    ///
+    /// # Panics
+    ///
+    /// Panics when the lock guarding the buffers is poisoned
+    #[must_use]
+    pub fn ir_buffers(&self) -> Vec<AudioBuffer> {
+        self.ir_buffers.lock().unwrap().clone()
+    }
+
+    /// Replace the impulse response at the given position in the chain
+    ///
+    /// # Panics
+    ///
+    /// Panics when `index` is out of bounds, or when the sample rate of the provided AudioBuffer
+    /// differs from the audio context sample rate, or when the buffer does not contain 1 or 2
+    /// channels.
+    pub fn set_ir_at(&self, index: usize, buffer: AudioBuffer) {
+        let mut ir_buffers = self.ir_buffers.lock().unwrap();
+
+        if index >= ir_buffers.len() {
+            panic!(
+                "IndexSizeError: index ({}) out of bounds for chain of length {}",
+                index,
+                ir_buffers.len()
+            );
+        }
+
+        let inner = ConvolverRendererInner::prepare(
+            &buffer,
+            self.context().sample_rate(),
+            self.normalize(),
+        );
+        ir_buffers[index] = buffer;
+
+        self.sender
+            .send(StageMessage(index, inner))
+            .expect("Sending StageMessage failed");
+    }
+
+    /// Denotes if the impulse responses will be scaled with an equal-power normalization
+    #[must_use]
+    pub fn normalize(&self) -> bool {
+        self.normalize.load(Ordering::Relaxed)
+    }
+
+    /// Update the `normalize` setting. This will only have an effect when `set_ir_at` is called.
+    pub fn set_normalize(&self, value: bool) {
+        self.normalize.store(value, Ordering::Relaxed);
+    }
+}
+
+struct ConvolutionChainRenderer {
+    receiver: Receiver<StageMessage>,
+    stages: Vec<ConvolverRendererInner>,
+}
+
+impl AudioProcessor for ConvolutionChainRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        for StageMessage(index, inner) in self.receiver.try_iter() {
+            self.stages[index] = inner;
+        }
+
+        // the output of each stage is the input of the next one
+        let mut tail_time = false;
+        let mut stage_input = input.clone();
+
+        for stage in self.stages.iter_mut() {
+            tail_time |= stage.process(&stage_input, output);
+            stage_input = output.clone();
+        }
+
+        tail_time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::OfflineAudioContext;
+    use crate::node::{AudioScheduledSourceNode, ConvolverNode, ConvolverOptions};
+    use crate::RENDER_QUANTUM_SIZE;
+
+    use super::*;
+
+    const LENGTH: usize = RENDER_QUANTUM_SIZE * 6;
+    const SAMPLE_RATE: f32 = 44_100.;
+
+    /// Render an impulse through the given node
+    fn render_impulse<N: AudioNode>(context: &mut OfflineAudioContext, node: &N) -> AudioBuffer {
+        let mut impulse = vec![0.; RENDER_QUANTUM_SIZE];
+        impulse[0] = 1.;
+        let impulse = AudioBuffer::from(vec![impulse], SAMPLE_RATE);
+
+        node.connect(&context.destination());
+
+        let src = context.create_buffer_source();
+        src.set_buffer(impulse);
+        src.connect(node);
+        src.start();
+
+        context.start_rendering_sync()
+    }
+
+    #[test]
+    fn test_chain_equals_combined_impulse_response() {
+        let mut ir1 = vec![0.; 200];
+        ir1[0] = 0.5;
+        ir1[3] = -0.25;
+        ir1[150] = 0.125;
+
+        let mut ir2 = vec![0.; 300];
+        ir2[1] = 1.;
+        ir2[130] = 0.5;
+        ir2[299] = -0.75;
+
+        // direct convolution of both impulse responses
+        let mut combined = vec![0.; ir1.len() + ir2.len() - 1];
+        for (i, a) in ir1.iter().enumerate() {
+            for (j, b) in ir2.iter().enumerate() {
+                combined[i + j] += a * b;
+            }
+        }
+
+        let chained = {
+            let mut context = OfflineAudioContext::new(1, LENGTH, SAMPLE_RATE);
+            let options = ConvolutionChainOptions {
+                ir_buffers: vec![
+                    AudioBuffer::from(vec![ir1], SAMPLE_RATE),
+                    AudioBuffer::from(vec![ir2], SAMPLE_RATE),
+                ],
+                normalize: false,
+                ..ConvolutionChainOptions::default()
+            };
+            let chain = ConvolutionChainNode::new(&context, options);
+            render_impulse(&mut context, &chain)
+        };
+
+        let single = {
+            let mut context = OfflineAudioContext::new(1, LENGTH, SAMPLE_RATE);
+            let options = ConvolverOptions {
+                buffer: Some(AudioBuffer::from(vec![combined.clone()], SAMPLE_RATE)),
+                disable_normalization: true,
+                ..ConvolverOptions::default()
+            };
+            let convolver = ConvolverNode::new(&context, options);
+            render_impulse(&mut context, &convolver)
+        };
+
+        assert_float_eq!(
+            chained.get_channel_data(0)[..combined.len()],
+            combined[..],
+            abs_all <= 1E-6
+        );
+        assert_float_eq!(
+            chained.get_channel_data(0),
+            single.get_channel_data(0),
+            abs_all <= 1E-6
+        );
+    }
+
+    #[test]
+    fn test_set_ir_at() {
+        let mut context = OfflineAudioContext::new(1, LENGTH, SAMPLE_RATE);
+
+        let options = ConvolutionChainOptions {
+            ir_buffers: vec![
+                AudioBuffer::from(vec![vec![0.5]], SAMPLE_RATE),
+                AudioBuffer::from(vec![vec![0.5]], SAMPLE_RATE),
+            ],
+            normalize: false,
+            ..ConvolutionChainOptions::default()
+        };
+        let chain = ConvolutionChainNode::new(&context, options);
+
+        // delay the second stage by 10 samples
+        let mut delayed = vec![0.; 11];
+        delayed[10] = 1.;
+        chain.set_ir_at(1, AudioBuffer::from(vec![delayed], SAMPLE_RATE));
+        assert_eq!(chain.ir_buffers()[1].length(), 11);
+
+        let output = render_impulse(&mut context, &chain);
+
+        let mut expected = vec![0.; LENGTH];
+        expected[10] = 0.5;
+        assert_float_eq!(output.get_channel_data(0), &expected[..], abs_all <= 1E-6);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_set_ir_at_out_of_bounds() {
+        let context = OfflineAudioContext::new(1, LENGTH, SAMPLE_RATE);
+
+        let options = ConvolutionChainOptions {
+            ir_buffers: vec![AudioBuffer::from(vec![vec![1.]], SAMPLE_RATE)],
+            ..ConvolutionChainOptions::default()
+        };
+        let chain = ConvolutionChainNode::new(&context, options);
+
+        chain.set_ir_at(1, AudioBuffer::from(vec![vec![1.]], SAMPLE_RATE));
+    }
+}
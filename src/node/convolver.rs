@@ -0,0 +1,659 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crossbeam_channel::Receiver;
+use realfft::{num_complex::Complex, ComplexToReal, RealFftPlanner, RealToComplex};
+
+use crate::buffer::AudioBuffer;
+use crate::context::{AudioContextRegistration, BaseAudioContext};
+use crate::message::{latest_value_channel, LatestValueSender};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::RENDER_QUANTUM_SIZE;
+
+use super::{
+    AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation,
+};
+
+/// Size of the FFT used for one partition (overlap-save, 50% overlap)
+const FFT_SIZE: usize = 2 * RENDER_QUANTUM_SIZE;
+/// Number of complex bins produced by the real FFT
+const SPECTRUM_SIZE: usize = FFT_SIZE / 2 + 1;
+
+/// `ConvolverNode` options
+// dictionary ConvolverOptions : AudioNodeOptions {
+//   AudioBuffer? buffer;
+//   boolean disableNormalization = false;
+// };
+#[derive(Clone, Debug)]
+pub struct ConvolverOptions {
+    /// The desired buffer for the ConvolverNode
+    pub buffer: Option<AudioBuffer>,
+    /// The opposite of the desired initial value for the normalize attribute
+    pub disable_normalization: bool,
+    /// AudioNode options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for ConvolverOptions {
+    fn default() -> Self {
+        Self {
+            buffer: None,
+            disable_normalization: false,
+            channel_config: ChannelConfigOptions {
+                count: 2,
+                mode: ChannelCountMode::ClampedMax,
+                interpretation: ChannelInterpretation::Speakers,
+            },
+        }
+    }
+}
+
+/// Processing node which applies a linear convolution effect given an impulse response.
+///
+/// - MDN documentation: <https://developer.mozilla.org/en-US/docs/Web/API/ConvolverNode>
+/// - specification: <https://webaudio.github.io/web-audio-api/#ConvolverNode>
+/// - see also: [`BaseAudioContext::create_convolver`](crate::context::BaseAudioContext::create_convolver)
+///
+/// The input and the impulse response can both be mono or stereo:
+///
+/// - mono input, mono impulse response: mono output
+/// - mono input, stereo impulse response: the input is convolved with the left and right channel
+///   of the impulse response, to produce a stereo output
+/// - stereo input, mono impulse response: each input channel is convolved with the impulse
+///   response
+/// - stereo input, stereo impulse response: the left (right) input channel is convolved with the
+///   left (right) channel of the impulse response
+///
+/// # Usage
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+///
+/// let file = File::open("samples/sample.wav").unwrap();
+/// let audio_buffer = context.decode_audio_data_sync(file).unwrap();
+///
+/// let impulse_file = File::open("samples/think-stereo-44100.wav").unwrap();
+/// let impulse_buffer = context.decode_audio_data_sync(impulse_file).unwrap();
+///
+/// let src = context.create_buffer_source();
+/// src.set_buffer(audio_buffer);
+///
+/// let convolve = context.create_convolver();
+/// convolve.set_buffer(impulse_buffer);
+///
+/// src.connect(&convolve);
+/// convolve.connect(&context.destination());
+///
+/// src.start();
+/// ```
+pub struct ConvolverNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Info about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Perform equal power normalization on response buffer, only used on the control thread
+    normalize: AtomicBool,
+    /// The response buffer, nullable
+    buffer: Mutex<Option<AudioBuffer>>,
+    /// Channel between node and renderer (sender part)
+    sender: LatestValueSender<ConvolverRendererInner>,
+}
+
+impl AudioNode for ConvolverNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+
+    fn set_channel_count_mode(&self, v: ChannelCountMode) {
+        if v == ChannelCountMode::Max {
+            panic!("NotSupportedError: ConvolverNode channel count mode cannot be set to max");
+        }
+        self.channel_config.set_count_mode(v);
+    }
+
+    fn set_channel_count(&self, v: usize) {
+        if v > 2 {
+            panic!("NotSupportedError: ConvolverNode channel count cannot be greater than two");
+        }
+        self.channel_config.set_count(v);
+    }
+}
+
+impl ConvolverNode {
+    /// returns a `ConvolverNode` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `options` - convolver options
+    ///
+    /// # Panics
+    ///
+    /// Panics when an AudioBuffer is provided via the `ConvolverOptions` with a sample rate
+    /// different from the audio context sample rate, or with a number of channels other than 1 or
+    /// 2.
+    pub fn new<C: BaseAudioContext>(context: &C, options: ConvolverOptions) -> Self {
+        context.register(move |registration| {
+            let ConvolverOptions {
+                buffer,
+                disable_normalization,
+                channel_config,
+            } = options;
+
+            assert!(
+                channel_config.count <= 2,
+                "NotSupportedError: channel count"
+            );
+            assert!(
+                channel_config.mode != ChannelCountMode::Max,
+                "NotSupportedError: count mode"
+            );
+
+            // The buffer can be assigned multiple times, the renderer only picks up the last one
+            let (sender, receiver) = latest_value_channel();
+
+            let renderer = ConvolverRenderer {
+                receiver,
+                inner: None,
+            };
+
+            let node = Self {
+                registration,
+                channel_config: channel_config.into(),
+                normalize: AtomicBool::new(!disable_normalization),
+                buffer: Mutex::new(None),
+                sender,
+            };
+
+            if let Some(buffer) = buffer {
+                node.set_buffer(buffer);
+            }
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// Get the current impulse response buffer
+    ///
+    /// # Panics
+    ///
+    /// Panics when the lock guarding the buffer is poisoned
+    #[must_use]
+    pub fn buffer(&self) -> Option<AudioBuffer> {
+        self.buffer.lock().unwrap().clone()
+    }
+
+    /// Set or update the impulse response buffer
+    ///
+    /// # Panics
+    ///
+    /// Panics when the sample rate of the provided AudioBuffer differs from the audio context
+    /// sample rate, or when the buffer does not contain 1 or 2 channels.
+    pub fn set_buffer(&self, buffer: AudioBuffer) {
+        let inner = ConvolverRendererInner::prepare(
+            &buffer,
+            self.context().sample_rate(),
+            self.normalize(),
+        );
+        *self.buffer.lock().unwrap() = Some(buffer);
+
+        self.sender.send(inner);
+    }
+
+    /// Denotes if the response buffer will be scaled with an equal-power normalization
+    #[must_use]
+    pub fn normalize(&self) -> bool {
+        self.normalize.load(Ordering::Relaxed)
+    }
+
+    /// Update the `normalize` setting. This will only have an effect when `set_buffer` is called.
+    pub fn set_normalize(&self, value: bool) {
+        self.normalize.store(value, Ordering::Relaxed);
+    }
+}
+
+/// Check the impulse response buffer can be used in a convolution
+///
+/// If the buffer number of channels is not 1, 2, 4, or if the sample-rate of the buffer is not the
+/// same as the sample-rate of its associated BaseAudioContext, a NotSupportedError MUST be thrown.
+/// Note: 4-channel (true stereo) impulse responses are not supported yet
+#[track_caller]
+fn assert_valid_impulse_response(buffer: &AudioBuffer, sample_rate: f32) {
+    let number_of_channels = buffer.number_of_channels();
+    if number_of_channels != 1 && number_of_channels != 2 {
+        panic!(
+            "NotSupportedError - only mono and stereo impulse responses are supported, got {} channels",
+            number_of_channels
+        );
+    }
+
+    if (buffer.sample_rate() - sample_rate).abs() > f32::EPSILON {
+        panic!(
+            "NotSupportedError - buffer sample rate ({}) does not match context sample rate ({})",
+            buffer.sample_rate(),
+            sample_rate
+        );
+    }
+}
+
+/// Equal power normalization scale of the impulse response, as described in the spec
+fn normalization_scale(buffer: &AudioBuffer) -> f32 {
+    const GAIN_CALIBRATION: f32 = 0.00125;
+    const GAIN_CALIBRATION_SAMPLE_RATE: f32 = 44100.;
+    const MIN_POWER: f32 = 0.000125;
+
+    let number_of_channels = buffer.number_of_channels();
+    let length = buffer.length();
+
+    let mut power: f32 = (0..number_of_channels)
+        .map(|i| {
+            buffer
+                .get_channel_data(i)
+                .iter()
+                .map(|v| v * v)
+                .sum::<f32>()
+        })
+        .sum();
+
+    power = (power / (number_of_channels * length) as f32).sqrt();
+
+    if !power.is_finite() || power < MIN_POWER {
+        power = MIN_POWER;
+    }
+
+    let mut scale = 1. / power;
+    scale *= GAIN_CALIBRATION;
+    scale *= GAIN_CALIBRATION_SAMPLE_RATE / buffer.sample_rate();
+
+    // true stereo compensation
+    if number_of_channels == 4 {
+        scale *= 0.5;
+    }
+
+    scale
+}
+
+/// Uniformly partitioned overlap-save convolution engine
+///
+/// The impulse response is split in partitions of `RENDER_QUANTUM_SIZE` frames, which are
+/// transformed to the frequency domain upfront (on the control thread). The spectra of the input
+/// blocks are stored in a frequency domain delay line, one per input stream.
+pub(super) struct ConvolverRendererInner {
+    /// Number of channels of the impulse response (1 or 2)
+    ir_channels: usize,
+    /// Impulse response spectra, per channel, per partition
+    ir_spectra: Vec<Vec<Vec<Complex<f32>>>>,
+    /// Frequency domain delay line of the input spectra, per input stream (always 2)
+    fdl: [Vec<Vec<Complex<f32>>>; 2],
+    /// Write position in the frequency domain delay lines
+    fdl_index: usize,
+    /// Previous input block, per input stream
+    previous_input: [[f32; RENDER_QUANTUM_SIZE]; 2],
+    /// Number of input channels of the last non-silent input
+    input_channels: usize,
+    /// Number of consecutive silent input blocks, used to determine the tail time
+    silent_blocks: usize,
+    fft: Arc<dyn RealToComplex<f32>>,
+    ifft: Arc<dyn ComplexToReal<f32>>,
+    time_buffer: Vec<f32>,
+    accumulator: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+}
+
+impl ConvolverRendererInner {
+    /// Validate the impulse response and prepare the convolution engine for it
+    ///
+    /// # Panics
+    ///
+    /// Panics when the impulse response is not valid for the given context sample rate
+    pub(super) fn prepare(buffer: &AudioBuffer, sample_rate: f32, normalize: bool) -> Self {
+        assert_valid_impulse_response(buffer, sample_rate);
+
+        let scale = if normalize {
+            normalization_scale(buffer)
+        } else {
+            1.
+        };
+
+        Self::new(buffer, scale)
+    }
+
+    fn new(buffer: &AudioBuffer, scale: f32) -> Self {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(FFT_SIZE);
+        let ifft = planner.plan_fft_inverse(FFT_SIZE);
+
+        let scratch_len = fft.get_scratch_len().max(ifft.get_scratch_len());
+        let mut scratch = vec![Complex::default(); scratch_len];
+        let mut time_buffer = fft.make_input_vec();
+
+        // fold the normalization and the inverse FFT scaling into the impulse response
+        let scale = scale / FFT_SIZE as f32;
+
+        let length = buffer.length();
+        let num_partitions = length.div_ceil(RENDER_QUANTUM_SIZE).max(1);
+
+        let ir_channels = buffer.number_of_channels();
+        let ir_spectra = (0..ir_channels)
+            .map(|i| {
+                let data = buffer.get_channel_data(i);
+
+                (0..num_partitions)
+                    .map(|p| {
+                        let start = (p * RENDER_QUANTUM_SIZE).min(length);
+                        let end = ((p + 1) * RENDER_QUANTUM_SIZE).min(length);
+                        let partition = &data[start..end];
+
+                        time_buffer.iter_mut().for_each(|v| *v = 0.);
+                        time_buffer
+                            .iter_mut()
+                            .zip(partition)
+                            .for_each(|(t, s)| *t = s * scale);
+
+                        let mut spectrum = fft.make_output_vec();
+                        fft.process_with_scratch(&mut time_buffer, &mut spectrum, &mut scratch)
+                            .unwrap();
+                        spectrum
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let fdl = [
+            vec![vec![Complex::default(); SPECTRUM_SIZE]; num_partitions],
+            vec![vec![Complex::default(); SPECTRUM_SIZE]; num_partitions],
+        ];
+
+        Self {
+            ir_channels,
+            ir_spectra,
+            fdl,
+            fdl_index: 0,
+            previous_input: [[0.; RENDER_QUANTUM_SIZE]; 2],
+            input_channels: 1,
+            silent_blocks: 0,
+            fft,
+            ifft,
+            time_buffer,
+            accumulator: vec![Complex::default(); SPECTRUM_SIZE],
+            scratch,
+        }
+    }
+
+    fn num_partitions(&self) -> usize {
+        self.fdl[0].len()
+    }
+
+    /// Transform the input block of the given stream and store it in the delay line
+    fn push_input(&mut self, stream: usize, input: &[f32]) {
+        let (previous, current) = self.time_buffer.split_at_mut(RENDER_QUANTUM_SIZE);
+        previous.copy_from_slice(&self.previous_input[stream]);
+        current.copy_from_slice(input);
+        self.previous_input[stream].copy_from_slice(input);
+
+        let spectrum = &mut self.fdl[stream][self.fdl_index];
+        self.fft
+            .process_with_scratch(&mut self.time_buffer, spectrum, &mut self.scratch)
+            .unwrap();
+    }
+
+    /// Mirror the input of the first stream into the second one, so that a stereo impulse
+    /// response (or a later stereo input) sees the mono input without transforming it twice
+    fn mirror_input(&mut self) {
+        let (first, second) = self.fdl.split_at_mut(1);
+        second[0][self.fdl_index].copy_from_slice(&first[0][self.fdl_index]);
+        self.previous_input[1] = self.previous_input[0];
+    }
+
+    /// Convolve the given input stream with the given impulse response channel
+    fn convolve(&mut self, stream: usize, ir_channel: usize, output: &mut [f32]) {
+        let num_partitions = self.num_partitions();
+
+        self.accumulator
+            .iter_mut()
+            .for_each(|v| *v = Complex::default());
+
+        for (p, ir) in self.ir_spectra[ir_channel].iter().enumerate() {
+            let index = (self.fdl_index + num_partitions - p) % num_partitions;
+            let input = &self.fdl[stream][index];
+
+            self.accumulator
+                .iter_mut()
+                .zip(input.iter().zip(ir))
+                .for_each(|(a, (i, h))| *a += i * h);
+        }
+
+        // the imaginary parts of the DC and Nyquist bins must be zero for a real signal
+        self.accumulator[0].im = 0.;
+        self.accumulator[SPECTRUM_SIZE - 1].im = 0.;
+
+        self.ifft
+            .process_with_scratch(
+                &mut self.accumulator,
+                &mut self.time_buffer,
+                &mut self.scratch,
+            )
+            .unwrap();
+
+        // overlap-save: only the second half is a valid part of the linear convolution
+        output.copy_from_slice(&self.time_buffer[RENDER_QUANTUM_SIZE..]);
+    }
+
+    pub(super) fn process(
+        &mut self,
+        input: &AudioRenderQuantum,
+        output: &mut AudioRenderQuantum,
+    ) -> bool {
+        if input.channels().iter().all(|c| c.is_silent()) {
+            self.silent_blocks += 1;
+
+            // the full impulse response has been played out
+            if self.silent_blocks > self.num_partitions() {
+                output.make_silent();
+                return false;
+            }
+        } else {
+            self.silent_blocks = 0;
+            self.input_channels = input.number_of_channels();
+        }
+
+        self.fdl_index = (self.fdl_index + 1) % self.num_partitions();
+
+        // Input is clamped to 2 channels. During the tail, keep the channel layout of the last
+        // non-silent input
+        if self.input_channels == 2 && input.number_of_channels() == 2 {
+            self.push_input(0, &input.channel_data(0)[..]);
+            self.push_input(1, &input.channel_data(1)[..]);
+        } else if self.input_channels == 2 {
+            let silence = [0.; RENDER_QUANTUM_SIZE];
+            self.push_input(0, &silence);
+            self.push_input(1, &silence);
+        } else {
+            self.push_input(0, &input.channel_data(0)[..]);
+            self.mirror_input();
+        }
+
+        // - mono input + mono IR: mono output
+        // - mono input + stereo IR: input * IR-L -> left, input * IR-R -> right
+        // - stereo input + mono IR: L * IR -> left, R * IR -> right
+        // - stereo input + stereo IR: L * IR-L -> left, R * IR-R -> right
+        let output_channels = self.input_channels.max(self.ir_channels);
+        output.set_number_of_channels(output_channels);
+
+        for channel in 0..output_channels {
+            let ir_channel = if self.ir_channels == 2 { channel } else { 0 };
+            let output_channel = output.channel_data_mut(channel);
+            self.convolve(channel, ir_channel, &mut output_channel[..]);
+        }
+
+        true
+    }
+}
+
+struct ConvolverRenderer {
+    receiver: Receiver<ConvolverRendererInner>,
+    inner: Option<ConvolverRendererInner>,
+}
+
+impl AudioProcessor for ConvolverRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        // pick up the most recently assigned impulse response
+        if let Ok(inner) = self.receiver.try_recv() {
+            self.inner = Some(inner);
+        }
+
+        match &mut self.inner {
+            // no impulse response: output a single channel of silence
+            None => {
+                output.make_silent();
+                false
+            }
+            Some(inner) => inner.process(input, output),
+        }
+    }
+
+    fn can_go_dormant(&self) -> bool {
+        // once the impulse response has been played out, the output is silent until the input
+        // is not silent anymore
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::OfflineAudioContext;
+    use crate::node::AudioScheduledSourceNode;
+
+    use super::*;
+
+    const LENGTH: usize = 512;
+
+    /// Render the (1 or 2 channel) signal through a convolver with the given impulse response
+    fn convolve(signal: Vec<Vec<f32>>, impulse: Vec<Vec<f32>>) -> AudioBuffer {
+        let sample_rate = 44_100.;
+        let mut context = OfflineAudioContext::new(2, LENGTH, sample_rate);
+
+        let input = AudioBuffer::from(signal, sample_rate);
+        let impulse = AudioBuffer::from(impulse, sample_rate);
+
+        let convolver = ConvolverNode::new(
+            &context,
+            ConvolverOptions {
+                buffer: Some(impulse),
+                disable_normalization: true,
+                ..ConvolverOptions::default()
+            },
+        );
+        convolver.connect(&context.destination());
+
+        let src = context.create_buffer_source();
+        src.set_buffer(input);
+        src.connect(&convolver);
+        src.start();
+
+        context.start_rendering_sync()
+    }
+
+    /// Impulse of given value at given position, spanning multiple render quanta
+    fn dirac(position: usize, value: f32) -> Vec<f32> {
+        let mut signal = vec![0.; LENGTH];
+        signal[position] = value;
+        signal
+    }
+
+    #[test]
+    fn test_mono_input_mono_ir() {
+        let output = convolve(vec![dirac(0, 1.)], vec![dirac(150, 0.5)]);
+
+        // mono output, up-mixed to stereo by the destination
+        let expected = dirac(150, 0.5);
+        assert_float_eq!(output.get_channel_data(0), &expected[..], abs_all <= 1E-6);
+        assert_float_eq!(output.get_channel_data(1), &expected[..], abs_all <= 1E-6);
+    }
+
+    #[test]
+    fn test_mono_input_stereo_ir() {
+        let output = convolve(vec![dirac(3, 1.)], vec![dirac(0, 1.), dirac(200, 0.5)]);
+
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &dirac(3, 1.)[..],
+            abs_all <= 1E-6
+        );
+        assert_float_eq!(
+            output.get_channel_data(1),
+            &dirac(203, 0.5)[..],
+            abs_all <= 1E-6
+        );
+    }
+
+    #[test]
+    fn test_stereo_input_mono_ir() {
+        let output = convolve(vec![dirac(0, 1.), dirac(10, 0.25)], vec![dirac(130, 1.)]);
+
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &dirac(130, 1.)[..],
+            abs_all <= 1E-6
+        );
+        assert_float_eq!(
+            output.get_channel_data(1),
+            &dirac(140, 0.25)[..],
+            abs_all <= 1E-6
+        );
+    }
+
+    #[test]
+    fn test_stereo_input_stereo_ir() {
+        let output = convolve(
+            vec![dirac(0, 1.), dirac(10, 0.25)],
+            vec![dirac(5, 1.), dirac(300, 1.)],
+        );
+
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &dirac(5, 1.)[..],
+            abs_all <= 1E-6
+        );
+        assert_float_eq!(
+            output.get_channel_data(1),
+            &dirac(310, 0.25)[..],
+            abs_all <= 1E-6
+        );
+    }
+
+    #[test]
+    fn test_normalization_scale() {
+        let impulse = AudioBuffer::from(vec![vec![1.; 10]], 44_100.);
+        assert_float_eq!(normalization_scale(&impulse), 0.00125, abs <= 1E-9);
+    }
+}
@@ -3,7 +3,9 @@ use crate::param::{AudioParam, AudioParamDescriptor};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 use crate::RENDER_QUANTUM_SIZE;
 
-use super::{AudioNode, ChannelConfig, ChannelConfigOptions, ChannelInterpretation};
+use super::{
+    assert_valid_connection, AudioNode, ChannelConfig, ChannelConfigOptions, ChannelInterpretation,
+};
 
 use std::cell::{Cell, RefCell, RefMut};
 use std::rc::Rc;
@@ -116,6 +118,10 @@ impl AudioNode for DelayNode {
         1
     }
 
+    fn params(&self) -> Vec<(&'static str, &AudioParam)> {
+        vec![("delayTime", &self.delay_time)]
+    }
+
     /// Connect a specific output of this AudioNode to a specific input of another node.
     fn connect_at<'a>(
         &self,
@@ -123,15 +129,7 @@ impl AudioNode for DelayNode {
         output: usize,
         input: usize,
     ) -> &'a dyn AudioNode {
-        if self.context() != dest.context() {
-            panic!("InvalidAccessError: Attempting to connect nodes from different contexts");
-        }
-        if self.number_of_outputs() <= output {
-            panic!("IndexSizeError: output port {} is out of bounds", output);
-        }
-        if dest.number_of_inputs() <= input {
-            panic!("IndexSizeError: input port {} is out of bounds", input);
-        }
+        assert_valid_connection(self, dest, output, input);
 
         self.context().connect(
             self.reader_registration.id(),
@@ -143,6 +141,26 @@ impl AudioNode for DelayNode {
         dest
     }
 
+    fn connect_channel<'a>(
+        &self,
+        dest: &'a dyn AudioNode,
+        output: usize,
+        source_channel: usize,
+        input: usize,
+    ) -> &'a dyn AudioNode {
+        assert_valid_connection(self, dest, output, input);
+
+        self.context().connect_channel(
+            self.reader_registration.id(),
+            dest.registration().id(),
+            output,
+            source_channel,
+            input,
+        );
+
+        dest
+    }
+
     /// Disconnects all outputs of the AudioNode that go to a specific destination AudioNode.
     fn disconnect_from<'a>(&self, dest: &'a dyn AudioNode) -> &'a dyn AudioNode {
         if self.context() != dest.context() {
@@ -204,6 +222,10 @@ impl DelayNode {
         let last_written_index = Rc::new(Cell::<Option<usize>>::new(None));
         let last_written_index_clone = last_written_index.clone();
 
+        // number of consecutive silent render quanta written by the writer
+        let silent_quanta = Rc::new(Cell::new(0));
+        let silent_quanta_clone = silent_quanta.clone();
+
         context.register(move |writer_registration| {
             let node = context.register(move |reader_registration| {
                 let param_opts = AudioParamDescriptor {
@@ -221,7 +243,8 @@ impl DelayNode {
                     ring_buffer: shared_ring_buffer_clone,
                     index: 0,
                     last_written_index: last_written_index_clone,
-                    last_written_index_checked: None,
+                    silenced_index: None,
+                    silent_quanta: silent_quanta_clone,
                     // `internal_buffer` is used to compute the samples per channel at each frame.
                     // Note that the `vec` will always be resized to actual buffer
                     // number_of_channels when received on the render thread.
@@ -242,6 +265,7 @@ impl DelayNode {
                 ring_buffer: shared_ring_buffer,
                 index: 0,
                 last_written_index,
+                silent_quanta,
             };
 
             (node, Box::new(writer_render))
@@ -258,6 +282,7 @@ struct DelayWriter {
     ring_buffer: Rc<RefCell<Vec<AudioRenderQuantum>>>,
     index: usize,
     last_written_index: Rc<Cell<Option<usize>>>,
+    silent_quanta: Rc<Cell<usize>>,
 }
 
 struct DelayReader {
@@ -265,8 +290,11 @@ struct DelayReader {
     ring_buffer: Rc<RefCell<Vec<AudioRenderQuantum>>>,
     index: usize,
     last_written_index: Rc<Cell<Option<usize>>>,
-    // local copy of shared `last_written_index` so as to avoid render ordering issues
-    last_written_index_checked: Option<usize>,
+    // next ring buffer index to silence once the writer is dropped, derived from the shared
+    // `last_written_index` so as to avoid render ordering issues
+    silenced_index: Option<usize>,
+    // number of consecutive silent render quanta written by the writer
+    silent_quanta: Rc<Cell<usize>>,
     // internal buffer used to compute output per channel at each frame
     internal_buffer: Vec<f32>,
 }
@@ -337,6 +365,14 @@ impl AudioProcessor for DelayWriter {
         // side as Reader do not access the "real" input
         self.check_ring_buffer_up_down_mix(&input);
 
+        // keep track of the silent input, to let the reader skip a silent ring buffer
+        if input.channels().iter().all(|c| c.is_silent()) {
+            self.silent_quanta
+                .set(self.silent_quanta.get().saturating_add(1));
+        } else {
+            self.silent_quanta.set(0);
+        }
+
         // populate ring buffer
         let mut buffer = self.ring_buffer.borrow_mut();
         buffer[self.index] = input;
@@ -397,6 +433,7 @@ impl AudioProcessor for DelayReader {
         self.check_ring_buffer_size(output);
 
         let ring_buffer = self.ring_buffer.borrow();
+        let capacity = ring_buffer.capacity();
 
         // we need to rely on ring buffer to know the actual number of output channels
         let number_of_channels = ring_buffer[0].number_of_channels();
@@ -405,6 +442,13 @@ impl AudioProcessor for DelayReader {
             self.internal_buffer.resize(number_of_channels, 0.);
         }
 
+        // the whole ring buffer is silent, skip the interpolation
+        if self.silent_quanta.get() >= ring_buffer.capacity() {
+            output.make_silent();
+            drop(ring_buffer);
+            return self.advance(capacity);
+        }
+
         output.set_number_of_channels(number_of_channels);
 
         // shadow and cast sample_rate, we don't need the wrapper type here
@@ -452,26 +496,44 @@ impl AudioProcessor for DelayReader {
             output.set_channels_values_at(index, &self.internal_buffer);
         }
 
-        if matches!(self.last_written_index_checked, Some(index) if index == self.index) {
-            return false;
+        drop(ring_buffer);
+        self.advance(capacity)
+    }
+}
+
+impl DelayReader {
+    /// Move the ring buffer cursor to the next render quantum, returns the tail time
+    fn advance(&mut self, capacity: usize) -> bool {
+        // check if the writer has been decommisionned, from then on the reader writes the
+        // silence in its place so that the ring buffer is not played out again. We need a
+        // local copy because the writer may have been processed before or after the reader
+        // in the render quantum it has been dropped
+        if self.silenced_index.is_none() {
+            self.silenced_index = self
+                .last_written_index
+                .get()
+                .map(|index| (index + 1) % capacity);
         }
 
-        // check if the writer has been decommisionned
-        // we need this local copy because if the writer has been processed
-        // before the reader, the direct check against `self.last_written_index`
-        // would be true earlier than we want
-        let last_written_index = self.last_written_index.get();
-        if last_written_index.is_some() && self.last_written_index_checked.is_none() {
-            self.last_written_index_checked = last_written_index;
+        if let Some(index) = self.silenced_index {
+            if index == self.index {
+                self.ring_buffer.borrow_mut()[index]
+                    .channels_mut()
+                    .iter_mut()
+                    .for_each(|channel| *channel = channel.silence());
+                self.silenced_index = Some((index + 1) % capacity);
+                self.silent_quanta
+                    .set(self.silent_quanta.get().saturating_add(1));
+            }
         }
+
         // increment ring buffer cursor
-        self.index = (self.index + 1) % ring_buffer.capacity();
+        self.index = (self.index + 1) % capacity;
 
-        true
+        // the tail has ended when the writer is gone and the ring buffer is silent
+        self.silenced_index.is_none() || self.silent_quanta.get() < capacity
     }
-}
 
-impl DelayReader {
     #[inline(always)]
     // note that `position` is negative as we look into the past
     fn find_frame_adress_at_position(&self, position: f64) -> (usize, usize) {
@@ -707,6 +769,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_silent_ring_buffer() {
+        // the reader skips the interpolation while the ring buffer is silent, make sure the
+        // delayed signal is still rendered when the source starts after a long silence
+        for _ in 0..10 {
+            let sample_rate = 48000.;
+            let mut context = OfflineAudioContext::new(1, 20 * 128, sample_rate);
+
+            {
+                let delay = context.create_delay(2. * 128. / sample_rate as f64);
+                delay.delay_time.set_value(128. / sample_rate);
+                delay.connect(&context.destination());
+
+                let mut dirac = context.create_buffer(1, 1, sample_rate);
+                dirac.copy_to_channel(&[1.], 0);
+
+                let src = context.create_buffer_source();
+                src.connect(&delay);
+                src.set_buffer(dirac);
+                src.start_at(128. * 15. / sample_rate as f64);
+            } // src and delay nodes are dropped
+
+            let result = context.start_rendering_sync();
+            let mut expected = vec![0.; 20 * 128];
+            expected[16 * 128] = 1.;
+
+            assert_float_eq!(result.get_channel_data(0), &expected[..], abs_all <= 0.);
+        }
+    }
+
     #[test]
     fn test_max_delay_multiple_of_quantum_size() {
         // regression test that delay node has always enough internal buffer size when
@@ -1,5 +1,8 @@
-use crate::context::{AudioContextRegistration, BaseAudioContext};
-use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext, BuiltinNode};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{
+    AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope, SnapshotWriter,
+};
 
 use super::{
     AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation,
@@ -11,23 +14,35 @@ pub struct AudioDestinationNode {
     channel_config: ChannelConfig,
 }
 
-struct DestinationRenderer {}
+struct DestinationRenderer {
+    master_gain: AudioParamId,
+    snapshots: SnapshotWriter,
+}
 
 impl AudioProcessor for DestinationRenderer {
     fn process(
         &mut self,
         inputs: &[AudioRenderQuantum],
         outputs: &mut [AudioRenderQuantum],
-        _params: AudioParamValues,
-        _scope: &RenderScope,
+        params: AudioParamValues,
+        scope: &RenderScope,
     ) -> bool {
         // single input/output node
         let input = &inputs[0];
         let output = &mut outputs[0];
 
-        // just move input to output
+        // move input to output, scaled by the k-rate master gain
         *output = input.clone();
 
+        let gain = params.get(&self.master_gain)[0];
+        if gain != 1. {
+            output
+                .modify_non_silent_channels(|channel| channel.iter_mut().for_each(|v| *v *= gain));
+        }
+
+        // publish the final mix for visualization, if anyone subscribed
+        self.snapshots.write(output, scope.current_frame);
+
         true
     }
 }
@@ -66,22 +81,46 @@ impl AudioNode for AudioDestinationNode {
 }
 
 impl AudioDestinationNode {
-    pub(crate) fn new<C: BaseAudioContext>(context: &C, channel_count: usize) -> Self {
-        context.register(move |registration| {
+    /// Returns the `AudioDestinationNode` along with its master gain `AudioParam`
+    pub(crate) fn new<C: BaseAudioContext>(
+        context: &C,
+        channel_count: usize,
+        snapshots: SnapshotWriter,
+    ) -> (Self, AudioParam) {
+        let base = context.base();
+        let mut master_gain = None;
+
+        let node = base.register_builtin(BuiltinNode::Destination, |registration| {
             let channel_config = ChannelConfigOptions {
                 count: channel_count,
                 mode: ChannelCountMode::Explicit,
                 interpretation: ChannelInterpretation::Speakers,
             }
             .into();
+
+            let param_opts = AudioParamDescriptor {
+                min_value: f32::MIN,
+                max_value: f32::MAX,
+                default_value: 1.,
+                automation_rate: AutomationRate::K,
+            };
+            let (param, proc) =
+                base.create_builtin_audio_param(BuiltinNode::MasterGain, param_opts, &registration);
+            master_gain = Some(param);
+
             let node = Self {
                 registration,
                 channel_config,
             };
-            let proc = DestinationRenderer {};
+            let proc = DestinationRenderer {
+                master_gain: proc,
+                snapshots,
+            };
 
             (node, Box::new(proc))
-        })
+        });
+
+        (node, master_gain.unwrap())
     }
 
     pub(crate) fn into_channel_config(self) -> ChannelConfig {
@@ -0,0 +1,504 @@
+use std::sync::Arc;
+
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::{AtomicF32, RENDER_QUANTUM_SIZE};
+
+use super::{smoothing_coefficient, AudioNode, ChannelConfig, ChannelConfigOptions};
+
+/// Level reported for a silent detector input, in dB
+const MIN_DB: f32 = -100.;
+/// Exponent of the automatic makeup gain, as in the spec's compression curve
+const MAKEUP_GAIN_EXPONENT: f32 = 0.6;
+
+fn db_to_lin(value: f32) -> f32 {
+    10_f32.powf(value / 20.)
+}
+
+fn lin_to_db(value: f32) -> f32 {
+    if value > 0. {
+        (20. * value.log10()).max(MIN_DB)
+    } else {
+        MIN_DB
+    }
+}
+
+/// Static compression curve, returns the output level for the given input level, in dB
+///
+/// The transition around the threshold is a quadratic soft knee of `knee` dB.
+fn compression_curve(level: f32, threshold: f32, knee: f32, ratio: f32) -> f32 {
+    let overshoot = level - threshold;
+
+    if 2. * overshoot < -knee {
+        level
+    } else if 2. * overshoot.abs() <= knee {
+        let x = overshoot + knee / 2.;
+        level + (1. / ratio - 1.) * x * x / (2. * knee)
+    } else {
+        threshold + overshoot / ratio
+    }
+}
+
+/// `DynamicsCompressorNode` options
+#[derive(Clone, Debug)]
+pub struct DynamicsCompressorOptions {
+    /// Level above which compression starts, in dB
+    pub threshold: f32,
+    /// Width of the soft transition around the threshold, in dB
+    pub knee: f32,
+    /// Amount of dB change in input for a 1 dB change in output above the threshold
+    pub ratio: f32,
+    /// Time to reduce the gain, in seconds
+    pub attack: f32,
+    /// Time to restore the gain, in seconds
+    pub release: f32,
+    /// Detect the level on a second, sidechain, input instead of the main input
+    pub sidechain: bool,
+    /// audio node options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for DynamicsCompressorOptions {
+    fn default() -> Self {
+        Self {
+            threshold: -24.,
+            knee: 30.,
+            ratio: 12.,
+            attack: 0.003,
+            release: 0.25,
+            sidechain: false,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// `DynamicsCompressorNode` lowers the volume of the loudest parts of its input
+///
+/// The level is detected on the maximum absolute value across all channels, so the same gain
+/// is applied to every channel. Above `threshold`, the level is reduced according to `ratio`,
+/// with a soft transition of `knee` dB. The gain reduction is smoothed with the `attack` and
+/// `release` times, and an automatic makeup gain raises the output back towards full scale.
+///
+/// When created with [`DynamicsCompressorOptions::sidechain`], the node has a second input
+/// used only for level detection: the gain reduction driven by the sidechain is applied to
+/// the main input, and the sidechain signal itself never reaches the output. As long as
+/// nothing is connected to the sidechain input (or only silence reaches it), the level is
+/// detected on the main input.
+///
+/// - see also: [`BaseAudioContext::create_dynamics_compressor`](crate::context::BaseAudioContext::create_dynamics_compressor)
+///
+/// # Usage
+///
+/// ```no_run
+/// use std::fs::File;
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::node::{DynamicsCompressorNode, DynamicsCompressorOptions};
+///
+/// let context = AudioContext::default();
+///
+/// // duck the music whenever the voice is playing
+/// let options = DynamicsCompressorOptions {
+///     sidechain: true,
+///     ..DynamicsCompressorOptions::default()
+/// };
+/// let ducker = DynamicsCompressorNode::new(&context, options);
+/// ducker.connect(&context.destination());
+///
+/// let file = File::open("samples/sample.wav").unwrap();
+/// let music = context.create_buffer_source();
+/// music.set_buffer(context.decode_audio_data_sync(file).unwrap());
+/// music.connect(&ducker);
+/// music.start();
+///
+/// let voice = context.create_oscillator();
+/// voice.connect(&context.destination());
+/// voice.connect_at(&ducker, 0, 1);
+/// voice.start();
+/// ```
+pub struct DynamicsCompressorNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Compression threshold, in dB
+    threshold: AudioParam,
+    /// Width of the knee, in dB
+    knee: AudioParam,
+    /// Compression ratio
+    ratio: AudioParam,
+    /// Attack time, in seconds
+    attack: AudioParam,
+    /// Release time, in seconds
+    release: AudioParam,
+    /// Whether the node has a sidechain input
+    sidechain: bool,
+    /// Current gain reduction, in dB, written by the renderer
+    reduction: Arc<AtomicF32>,
+}
+
+impl AudioNode for DynamicsCompressorNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        if self.sidechain {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+
+    fn params(&self) -> Vec<(&'static str, &AudioParam)> {
+        vec![
+            ("threshold", &self.threshold),
+            ("knee", &self.knee),
+            ("ratio", &self.ratio),
+            ("attack", &self.attack),
+            ("release", &self.release),
+        ]
+    }
+}
+
+impl DynamicsCompressorNode {
+    /// returns a `DynamicsCompressorNode` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `options` - dynamics compressor options
+    pub fn new<C: BaseAudioContext>(context: &C, options: DynamicsCompressorOptions) -> Self {
+        context.register(move |registration| {
+            let DynamicsCompressorOptions {
+                threshold,
+                knee,
+                ratio,
+                attack,
+                release,
+                sidechain,
+                channel_config,
+            } = options;
+
+            let param_opts = |min_value, max_value| AudioParamDescriptor {
+                min_value,
+                max_value,
+                default_value: 0.,
+                automation_rate: AutomationRate::K,
+            };
+
+            let (threshold_param, threshold_proc) =
+                context.create_audio_param(param_opts(MIN_DB, 0.), &registration);
+            threshold_param.set_value(threshold);
+
+            let (knee_param, knee_proc) =
+                context.create_audio_param(param_opts(0., 40.), &registration);
+            knee_param.set_value(knee);
+
+            let (ratio_param, ratio_proc) =
+                context.create_audio_param(param_opts(1., 20.), &registration);
+            ratio_param.set_value(ratio);
+
+            let (attack_param, attack_proc) =
+                context.create_audio_param(param_opts(0., 1.), &registration);
+            attack_param.set_value(attack);
+
+            let (release_param, release_proc) =
+                context.create_audio_param(param_opts(0., 1.), &registration);
+            release_param.set_value(release);
+
+            let reduction = Arc::new(AtomicF32::new(0.));
+
+            let renderer = DynamicsCompressorRenderer {
+                threshold: threshold_proc,
+                knee: knee_proc,
+                ratio: ratio_proc,
+                attack: attack_proc,
+                release: release_proc,
+                reduction: reduction.clone(),
+                gain_reduction: 0.,
+            };
+
+            let node = Self {
+                registration,
+                channel_config: channel_config.into(),
+                threshold: threshold_param,
+                knee: knee_param,
+                ratio: ratio_param,
+                attack: attack_param,
+                release: release_param,
+                sidechain,
+                reduction,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// K-rate [`AudioParam`] defining the level above which compression starts, in dB
+    #[must_use]
+    pub fn threshold(&self) -> &AudioParam {
+        &self.threshold
+    }
+
+    /// K-rate [`AudioParam`] defining the width of the soft knee, in dB
+    #[must_use]
+    pub fn knee(&self) -> &AudioParam {
+        &self.knee
+    }
+
+    /// K-rate [`AudioParam`] defining the compression ratio
+    #[must_use]
+    pub fn ratio(&self) -> &AudioParam {
+        &self.ratio
+    }
+
+    /// K-rate [`AudioParam`] defining the time to reduce the gain, in seconds
+    #[must_use]
+    pub fn attack(&self) -> &AudioParam {
+        &self.attack
+    }
+
+    /// K-rate [`AudioParam`] defining the time to restore the gain, in seconds
+    #[must_use]
+    pub fn release(&self) -> &AudioParam {
+        &self.release
+    }
+
+    /// Whether the level is detected on a sidechain input
+    #[must_use]
+    pub fn sidechain(&self) -> bool {
+        self.sidechain
+    }
+
+    /// Gain reduction currently applied, in dB (zero or negative)
+    ///
+    /// Updated at the end of each render quantum, the makeup gain is not included.
+    #[must_use]
+    pub fn reduction(&self) -> f32 {
+        self.reduction.load()
+    }
+}
+
+/// `DynamicsCompressorRenderer` represents the rendering part of `DynamicsCompressorNode`
+struct DynamicsCompressorRenderer {
+    threshold: AudioParamId,
+    knee: AudioParamId,
+    ratio: AudioParamId,
+    attack: AudioParamId,
+    release: AudioParamId,
+    /// Gain reduction shared with the control thread
+    reduction: Arc<AtomicF32>,
+    /// Current smoothed gain reduction, in dB
+    gain_reduction: f32,
+}
+
+impl AudioProcessor for DynamicsCompressorRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        // detect on the sidechain input if it carries a signal
+        let detector_input = match inputs.get(1) {
+            Some(sidechain) if !sidechain.channels().iter().all(|c| c.is_silent()) => sidechain,
+            _ => input,
+        };
+
+        let sample_rate = scope.sample_rate;
+
+        // k-rate parameters
+        let threshold = params.get(&self.threshold)[0];
+        let knee = params.get(&self.knee)[0];
+        let ratio = params.get(&self.ratio)[0];
+        let attack = smoothing_coefficient(params.get(&self.attack)[0], sample_rate);
+        let release = smoothing_coefficient(params.get(&self.release)[0], sample_rate);
+
+        // raise a full scale input back to full scale
+        let full_range_gain = db_to_lin(compression_curve(0., threshold, knee, ratio));
+        let makeup_gain = (1. / full_range_gain).powf(MAKEUP_GAIN_EXPONENT);
+
+        let mut gains = [0.; RENDER_QUANTUM_SIZE];
+
+        for (i, g) in gains.iter_mut().enumerate() {
+            // channels are linked, detect on the loudest one
+            let peak = detector_input
+                .channels()
+                .iter()
+                .fold(0_f32, |max, channel| max.max(channel[i].abs()));
+            let level = lin_to_db(peak);
+            let target = compression_curve(level, threshold, knee, ratio) - level;
+
+            // the reduction is negative, a lower target means more compression
+            let coef = if target < self.gain_reduction {
+                attack
+            } else {
+                release
+            };
+            self.gain_reduction = target + coef * (self.gain_reduction - target);
+
+            *g = db_to_lin(self.gain_reduction) * makeup_gain;
+        }
+
+        self.reduction.store(self.gain_reduction);
+
+        *output = input.clone();
+
+        output.modify_non_silent_channels(|channel| {
+            channel
+                .iter_mut()
+                .zip(gains.iter())
+                .for_each(|(value, g)| *value *= g)
+        });
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::OfflineAudioContext;
+    use crate::node::AudioScheduledSourceNode;
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48_000.;
+
+    #[test]
+    fn test_compression_curve() {
+        // below the knee
+        assert_float_eq!(compression_curve(-40., -24., 10., 4.), -40., abs <= 0.);
+        // above the knee
+        assert_float_eq!(compression_curve(0., -24., 10., 4.), -18., abs <= 1e-6);
+        // knee edges are continuous
+        assert_float_eq!(compression_curve(-29., -24., 10., 4.), -29., abs <= 1e-6);
+        assert_float_eq!(compression_curve(-19., -24., 10., 4.), -22.75, abs <= 1e-6);
+        // hard knee
+        assert_float_eq!(compression_curve(-12., -24., 0., 2.), -18., abs <= 1e-6);
+    }
+
+    #[test]
+    fn test_static_reduction() {
+        let length = RENDER_QUANTUM_SIZE * 4;
+        let mut context = OfflineAudioContext::new(1, length, SAMPLE_RATE);
+
+        let options = DynamicsCompressorOptions {
+            threshold: -24.,
+            knee: 0.,
+            ratio: 4.,
+            attack: 0.,
+            ..DynamicsCompressorOptions::default()
+        };
+        let compressor = DynamicsCompressorNode::new(&context, options);
+        compressor.connect(&context.destination());
+
+        // full scale input
+        let src = context.create_constant_source();
+        src.connect(&compressor);
+        src.start();
+
+        let output = context.start_rendering_sync();
+
+        // 18 dB reduction, raised by a makeup gain of 0.6 * 18 dB
+        assert_float_eq!(compressor.reduction(), -18., abs <= 1e-4);
+        let expected = db_to_lin(-18. + 0.6 * 18.);
+        output
+            .get_channel_data(0)
+            .iter()
+            .for_each(|v| assert_float_eq!(*v, expected, abs <= 1e-5));
+    }
+
+    #[test]
+    fn test_sidechain_ducking() {
+        let length = (0.5 * SAMPLE_RATE) as usize;
+        let mut context = OfflineAudioContext::new(1, length, SAMPLE_RATE);
+
+        let options = DynamicsCompressorOptions {
+            threshold: -10.,
+            knee: 0.,
+            ratio: 4.,
+            attack: 0.001,
+            sidechain: true,
+            ..DynamicsCompressorOptions::default()
+        };
+        let ducker = DynamicsCompressorNode::new(&context, options);
+        assert_eq!(ducker.number_of_inputs(), 2);
+        ducker.connect(&context.destination());
+
+        // main input at -20 dB, below the threshold
+        let main = context.create_constant_source();
+        main.offset().set_value(0.1);
+        main.connect(&ducker);
+        main.start();
+
+        // full scale sidechain from 0.25 second
+        let sidechain = context.create_constant_source();
+        sidechain.connect_at(&ducker, 0, 1);
+        sidechain.start_at(0.25);
+
+        let output = context.start_rendering_sync();
+        let output = output.get_channel_data(0);
+
+        let before = output[(0.2 * SAMPLE_RATE) as usize];
+        let during = output[length - 1];
+
+        // the main input alone is left untouched, apart from the makeup gain
+        let makeup_gain = db_to_lin(0.6 * 7.5);
+        assert_float_eq!(before, 0.1 * makeup_gain, abs <= 1e-5);
+        // the sidechain drives a 7.5 dB reduction of the main input
+        assert_float_eq!(during / before, db_to_lin(-7.5), abs <= 1e-4);
+    }
+
+    #[test]
+    fn test_sidechain_does_not_leak() {
+        let length = (0.1 * SAMPLE_RATE) as usize;
+        let mut context = OfflineAudioContext::new(1, length, SAMPLE_RATE);
+
+        let options = DynamicsCompressorOptions {
+            threshold: -10.,
+            knee: 0.,
+            sidechain: true,
+            ..DynamicsCompressorOptions::default()
+        };
+        let ducker = DynamicsCompressorNode::new(&context, options);
+        ducker.connect(&context.destination());
+
+        let main = context.create_constant_source();
+        main.offset().set_value(0.1);
+        main.connect(&ducker);
+        main.start();
+
+        // sidechain below the threshold
+        let sidechain = context.create_oscillator();
+        let gain = context.create_gain();
+        gain.gain().set_value(0.2);
+        sidechain.connect(&gain);
+        gain.connect_at(&ducker, 0, 1);
+        sidechain.start();
+
+        let output = context.start_rendering_sync();
+        let output = output.get_channel_data(0);
+
+        // the output only carries the main input
+        output
+            .iter()
+            .for_each(|v| assert_float_eq!(*v, output[0], abs <= 0.));
+        assert!(output[0] > 0.1);
+    }
+}
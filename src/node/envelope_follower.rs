@@ -0,0 +1,533 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::AtomicF64;
+
+use super::{AudioNode, ChannelConfig, ChannelConfigOptions, ChannelInterpretation};
+
+/// Maximum length of the RMS window, in seconds
+const MAX_RMS_WINDOW: f64 = 1.;
+
+/// Rectification mode of the `EnvelopeFollowerNode` detector
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EnvelopeFollowerMode {
+    /// Follow the absolute value of the signal
+    #[default]
+    Peak,
+    /// Follow the root mean square of the signal, computed over the RMS window
+    Rms,
+}
+
+impl From<u32> for EnvelopeFollowerMode {
+    fn from(i: u32) -> Self {
+        match i {
+            0 => EnvelopeFollowerMode::Peak,
+            1 => EnvelopeFollowerMode::Rms,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// `EnvelopeFollowerNode` options
+#[derive(Clone, Debug)]
+pub struct EnvelopeFollowerOptions {
+    /// Attack time, in seconds
+    pub attack: f32,
+    /// Release time, in seconds
+    pub release: f32,
+    /// Rectification mode, independent of the other values so `Relaxed` ordering suffices
+    pub mode: EnvelopeFollowerMode,
+    /// Length of the RMS window, in seconds
+    pub rms_window: f64,
+    /// audio node options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for EnvelopeFollowerOptions {
+    fn default() -> Self {
+        Self {
+            attack: 0.01,
+            release: 0.1,
+            mode: EnvelopeFollowerMode::default(),
+            rms_window: 0.01,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// `EnvelopeFollowerNode` outputs the envelope of its input as a mono control signal
+///
+/// The input is down-mixed to mono and rectified (peak or RMS), then smoothed with the given
+/// `attack` and `release` times. The output can be connected to an [`AudioParam`] to build
+/// sidechain effects such as ducking or auto-wah.
+///
+/// - see also: [`BaseAudioContext::create_envelope_follower`](crate::context::BaseAudioContext::create_envelope_follower)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+///
+/// // the music is ducked when the voice is playing
+/// let voice = context.create_oscillator();
+/// voice.connect(&context.destination());
+///
+/// let music = context.create_oscillator();
+/// let music_gain = context.create_gain();
+/// music.connect(&music_gain);
+/// music_gain.connect(&context.destination());
+///
+/// let follower = context.create_envelope_follower();
+/// let depth = context.create_gain();
+/// depth.gain().set_value(-0.5);
+/// voice.connect(&follower);
+/// follower.connect(&depth);
+/// depth.connect(music_gain.gain());
+///
+/// voice.start();
+/// music.start();
+/// ```
+pub struct EnvelopeFollowerNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Attack time, in seconds
+    attack: AudioParam,
+    /// Release time, in seconds
+    release: AudioParam,
+    /// Rectification mode, independent of the other values so `Relaxed` ordering suffices
+    mode: Arc<AtomicU32>,
+    /// Length of the RMS window, in seconds
+    rms_window: Arc<AtomicF64>,
+}
+
+impl AudioNode for EnvelopeFollowerNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+
+    fn params(&self) -> Vec<(&'static str, &AudioParam)> {
+        vec![("attack", &self.attack), ("release", &self.release)]
+    }
+}
+
+impl EnvelopeFollowerNode {
+    /// returns an `EnvelopeFollowerNode` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `options` - envelope follower options
+    ///
+    /// # Panics
+    ///
+    /// Panics if `options.rms_window` is not in the ]0, 1] range
+    pub fn new<C: BaseAudioContext>(context: &C, options: EnvelopeFollowerOptions) -> Self {
+        context.register(move |registration| {
+            let EnvelopeFollowerOptions {
+                attack,
+                release,
+                mode,
+                rms_window,
+                channel_config,
+            } = options;
+
+            assert_valid_rms_window(rms_window);
+
+            let time_param_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: f32::MAX,
+                default_value: 0.,
+                automation_rate: AutomationRate::K,
+            };
+            let (attack_param, attack_proc) =
+                context.create_audio_param(time_param_opts.clone(), &registration);
+            attack_param.set_value(attack);
+
+            let (release_param, release_proc) =
+                context.create_audio_param(time_param_opts, &registration);
+            release_param.set_value(release);
+
+            let mode = Arc::new(AtomicU32::new(mode as u32));
+            let rms_window = Arc::new(AtomicF64::new(rms_window));
+
+            let sample_rate = context.sample_rate() as f64;
+            let max_window_len = (MAX_RMS_WINDOW * sample_rate).ceil() as usize;
+
+            let renderer = EnvelopeFollowerRenderer {
+                attack: attack_proc,
+                release: release_proc,
+                mode: mode.clone(),
+                rms_window: rms_window.clone(),
+                detector: EnvelopeDetector::new(max_window_len),
+                number_of_channels: 1,
+            };
+
+            let node = Self {
+                registration,
+                channel_config: channel_config.into(),
+                attack: attack_param,
+                release: release_param,
+                mode,
+                rms_window,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// K-rate [`AudioParam`] defining the attack time of the follower, in seconds
+    #[must_use]
+    pub fn attack(&self) -> &AudioParam {
+        &self.attack
+    }
+
+    /// K-rate [`AudioParam`] defining the release time of the follower, in seconds
+    #[must_use]
+    pub fn release(&self) -> &AudioParam {
+        &self.release
+    }
+
+    /// Returns the rectification mode of the detector
+    #[must_use]
+    pub fn mode(&self) -> EnvelopeFollowerMode {
+        self.mode.load(Ordering::Relaxed).into()
+    }
+
+    /// Set the rectification mode of the detector
+    pub fn set_mode(&self, mode: EnvelopeFollowerMode) {
+        self.mode.store(mode as u32, Ordering::Relaxed);
+    }
+
+    /// Returns the length of the RMS window, in seconds
+    #[must_use]
+    pub fn rms_window(&self) -> f64 {
+        self.rms_window.load()
+    }
+
+    /// Set the length of the RMS window, in seconds
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is not in the ]0, 1] range
+    pub fn set_rms_window(&self, value: f64) {
+        assert_valid_rms_window(value);
+        self.rms_window.store(value);
+    }
+}
+
+#[track_caller]
+fn assert_valid_rms_window(value: f64) {
+    if value <= 0. || value > MAX_RMS_WINDOW {
+        panic!(
+            "RangeError - RMS window ({:?}) should be in the ]0, {:?}] range",
+            value, MAX_RMS_WINDOW
+        );
+    }
+}
+
+/// Coefficient of a one-pole smoothing filter with the given time constant, in seconds
+pub(super) fn smoothing_coefficient(time: f32, sample_rate: f32) -> f32 {
+    if time > 0. {
+        (-1. / (time * sample_rate)).exp()
+    } else {
+        0.
+    }
+}
+
+/// Envelope detector, rectifies its input and smoothes it with attack and release times
+///
+/// Shared by the nodes operating on the envelope of their input.
+pub(super) struct EnvelopeDetector {
+    /// Current value of the envelope
+    envelope: f32,
+    /// Ring buffer of squared input values, for RMS detection
+    window: Vec<f32>,
+    window_index: usize,
+    /// Running sum of the values in `window`
+    window_sum: f64,
+}
+
+impl EnvelopeDetector {
+    /// Create a detector, allocating room for an RMS window of `max_window_len` samples
+    pub(super) fn new(max_window_len: usize) -> Self {
+        Self {
+            envelope: 0.,
+            window: Vec::with_capacity(max_window_len),
+            window_index: 0,
+            window_sum: 0.,
+        }
+    }
+
+    /// Current value of the envelope
+    pub(super) fn envelope(&self) -> f32 {
+        self.envelope
+    }
+
+    /// Restart detection from the given envelope value, with a cleared RMS window
+    pub(super) fn reset(&mut self, envelope: f32, window_len: usize) {
+        self.envelope = envelope;
+        self.reset_window(window_len);
+    }
+
+    /// Update the length of the RMS window, the window is cleared if the length changes
+    pub(super) fn set_window_len(&mut self, window_len: usize) {
+        if window_len != self.window.len() {
+            self.reset_window(window_len);
+        }
+    }
+
+    fn reset_window(&mut self, window_len: usize) {
+        self.window.clear();
+        self.window.resize(window_len, 0.);
+        self.window_index = 0;
+        self.window_sum = 0.;
+    }
+
+    /// Feed a single sample to the detector and return the updated envelope
+    ///
+    /// The RMS mode requires a non-empty window, see [`Self::set_window_len`]
+    pub(super) fn process(
+        &mut self,
+        value: f32,
+        mode: EnvelopeFollowerMode,
+        attack_coef: f32,
+        release_coef: f32,
+    ) -> f32 {
+        let detected = match mode {
+            EnvelopeFollowerMode::Peak => value.abs(),
+            EnvelopeFollowerMode::Rms => {
+                let square = value * value;
+                self.window_sum += (square - self.window[self.window_index]) as f64;
+                self.window[self.window_index] = square;
+                self.window_index = (self.window_index + 1) % self.window.len();
+                // prevent rounding errors from driving the sum negative
+                (self.window_sum.max(0.) / self.window.len() as f64).sqrt() as f32
+            }
+        };
+
+        let coef = if detected > self.envelope {
+            attack_coef
+        } else {
+            release_coef
+        };
+
+        self.envelope = detected + coef * (self.envelope - detected);
+        self.envelope
+    }
+}
+
+/// `EnvelopeFollowerRenderer` represents the rendering part of `EnvelopeFollowerNode`
+struct EnvelopeFollowerRenderer {
+    attack: AudioParamId,
+    release: AudioParamId,
+    mode: Arc<AtomicU32>,
+    rms_window: Arc<AtomicF64>,
+    detector: EnvelopeDetector,
+    /// Number of channels of the last non-silent input
+    number_of_channels: usize,
+}
+
+impl AudioProcessor for EnvelopeFollowerRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        let sample_rate = scope.sample_rate;
+        let mode: EnvelopeFollowerMode = self.mode.load(Ordering::Relaxed).into();
+
+        let window_len = (self.rms_window.load() * sample_rate as f64)
+            .round()
+            .max(1.) as usize;
+        self.detector.set_window_len(window_len);
+
+        // detector works on the down-mixed input
+        let is_silent = input.channels().iter().all(|c| c.is_silent());
+        let mut mono = input.clone();
+        mono.mix(1, ChannelInterpretation::Speakers);
+        let mono = mono.channel_data(0);
+
+        // re-derive the detector state from the new input after a channel count change
+        if !is_silent && input.number_of_channels() != self.number_of_channels {
+            self.number_of_channels = input.number_of_channels();
+            self.detector.reset(mono[0].abs(), window_len);
+        }
+
+        // k-rate coefficients
+        let attack = params.get(&self.attack)[0];
+        let release = params.get(&self.release)[0];
+        let attack_coef = smoothing_coefficient(attack, sample_rate);
+        let release_coef = smoothing_coefficient(release, sample_rate);
+
+        output.set_number_of_channels(1);
+        let output_channel = output.channel_data_mut(0);
+
+        for (o, i) in output_channel.iter_mut().zip(mono.iter()) {
+            *o = self.detector.process(*i, mode, attack_coef, release_coef);
+        }
+
+        // keep rendering until the envelope is fully released
+        self.detector.envelope() > 1e-6
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::OfflineAudioContext;
+    use crate::node::{AudioScheduledSourceNode, OscillatorNode, OscillatorOptions};
+    use crate::periodic_wave::{PeriodicWave, PeriodicWaveOptions};
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48_000.;
+
+    /// Index of the first sample that reaches the given value (or falls below it)
+    fn crossing(data: &[f32], from: usize, value: f32, rising: bool) -> usize {
+        data[from..]
+            .iter()
+            .position(|v| if rising { *v >= value } else { *v <= value })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_attack_release_times() {
+        let attack = 0.02;
+        let release = 0.1;
+        let length = SAMPLE_RATE as usize; // 1 second
+
+        // a constant signal for the peak detector, the RMS window smoothes out the rectified sine
+        let cases = [
+            (EnvelopeFollowerMode::Peak, 0.),
+            (EnvelopeFollowerMode::Rms, 3_000.),
+        ];
+
+        for (mode, frequency) in cases {
+            let mut context = OfflineAudioContext::new(1, length, SAMPLE_RATE);
+
+            let options = EnvelopeFollowerOptions {
+                attack,
+                release,
+                mode,
+                rms_window: 0.001,
+                ..EnvelopeFollowerOptions::default()
+            };
+            let follower = EnvelopeFollowerNode::new(&context, options);
+            follower.connect(&context.destination());
+
+            // oscillator gated between 0.1 and 0.5 seconds
+            // a cosine wave, constant at frequency 0
+            let periodic_wave = PeriodicWave::new(
+                &context,
+                PeriodicWaveOptions {
+                    real: Some(vec![0., 1.]),
+                    imag: Some(vec![0., 0.]),
+                    disable_normalization: true,
+                },
+            );
+            let osc = OscillatorNode::new(
+                &context,
+                OscillatorOptions {
+                    frequency,
+                    periodic_wave: Some(periodic_wave),
+                    ..OscillatorOptions::default()
+                },
+            );
+            osc.connect(&follower);
+            osc.start_at(0.1);
+            osc.stop_at(0.5);
+
+            let output = context.start_rendering_sync();
+            let envelope = output.get_channel_data(0);
+
+            let gate_on = (0.1 * SAMPLE_RATE) as usize;
+            let gate_off = (0.5 * SAMPLE_RATE) as usize;
+
+            assert_float_eq!(envelope[..gate_on], [0.; 4800][..], abs_all <= 0.);
+
+            // value reached at the end of the gate, after more than 10 time constants
+            let steady = envelope[gate_off - 1];
+            assert!(steady > 0.5);
+
+            // the envelope reaches 1 - 1/e of its final value after `attack` seconds
+            let rise = crossing(envelope, gate_on, steady * (1. - (-1_f32).exp()), true);
+            let rise_time = rise as f32 / SAMPLE_RATE;
+            assert_float_eq!(rise_time, attack, rmax <= 0.1);
+
+            // and decays to 1/e of its value after `release` seconds
+            let fall = crossing(envelope, gate_off, steady * (-1_f32).exp(), false);
+            let fall_time = fall as f32 / SAMPLE_RATE;
+            assert_float_eq!(fall_time, release, rmax <= 0.1);
+        }
+    }
+
+    #[test]
+    fn test_ducking() {
+        let length = SAMPLE_RATE as usize / 2;
+        let mut context = OfflineAudioContext::new(1, length, SAMPLE_RATE);
+
+        // sidechain signal: constant signal starting at 0.1 seconds
+        let sidechain = context.create_constant_source();
+        sidechain.start_at(0.1);
+
+        let follower = context.create_envelope_follower();
+        follower.attack().set_value(0.005);
+
+        let depth = context.create_gain();
+        depth.gain().set_value(-0.5);
+
+        // ducked signal: constant signal of 1
+        let music = context.create_constant_source();
+        let music_gain = context.create_gain();
+        music.connect(&music_gain);
+        music_gain.connect(&context.destination());
+        music.start();
+
+        sidechain.connect(&follower);
+        follower.connect(&depth);
+        depth.connect(music_gain.gain());
+
+        let output = context.start_rendering_sync();
+        let output = output.get_channel_data(0);
+
+        // no ducking before the sidechain starts
+        assert_float_eq!(output[4000], 1., abs <= 1e-6);
+        // ducked by half after the attack
+        assert_float_eq!(output[length - 1], 0.5, abs <= 1e-3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_rms_window() {
+        let context = OfflineAudioContext::new(1, 1, SAMPLE_RATE);
+        let follower = context.create_envelope_follower();
+        follower.set_rms_window(2.);
+    }
+}
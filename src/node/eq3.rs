@@ -0,0 +1,400 @@
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::MAX_CHANNELS;
+
+use super::{AudioNode, BiquadFilterType, ChannelConfig, ChannelConfigOptions, Coefficients};
+
+/// Options for constructing an [`Eq3Node`]
+#[derive(Clone, Debug)]
+pub struct Eq3Options {
+    /// Initial gain of the low band, in dB
+    pub low_gain: f32,
+    /// Initial gain of the mid band, in dB
+    pub mid_gain: f32,
+    /// Initial gain of the high band, in dB
+    pub high_gain: f32,
+    /// Corner frequency of the low shelf filter
+    pub low_frequency: f32,
+    /// Center frequency of the peaking filter
+    pub mid_frequency: f32,
+    /// Quality factor of the peaking filter
+    pub mid_q: f32,
+    /// Corner frequency of the high shelf filter
+    pub high_frequency: f32,
+    /// channel config options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for Eq3Options {
+    fn default() -> Self {
+        Self {
+            low_gain: 0.,
+            mid_gain: 0.,
+            high_gain: 0.,
+            low_frequency: 200.,
+            mid_frequency: 1000.,
+            mid_q: 0.7,
+            high_frequency: 4000.,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// Three band equalizer, with bass, mid and treble gain controls
+///
+/// The bands are a lowshelf, a peaking and a highshelf biquad filter in series. Their
+/// frequencies are fixed when the node is created, only the gains can be changed afterwards.
+///
+/// The three filters run inside a single renderer, so the node behaves like any other node
+/// in the graph and does not expose the underlying filters.
+///
+/// - see also: [`BaseAudioContext::create_eq3`](crate::context::BaseAudioContext::create_eq3)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+///
+/// let eq = context.create_eq3();
+/// eq.connect(&context.destination());
+/// // more bass, less treble
+/// eq.low_gain().set_value(6.);
+/// eq.high_gain().set_value(-3.);
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&eq);
+/// osc.start();
+/// ```
+pub struct Eq3Node {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    low_gain: AudioParam,
+    mid_gain: AudioParam,
+    high_gain: AudioParam,
+    low_frequency: f32,
+    mid_frequency: f32,
+    mid_q: f32,
+    high_frequency: f32,
+}
+
+impl AudioNode for Eq3Node {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+
+    fn params(&self) -> Vec<(&'static str, &AudioParam)> {
+        vec![
+            ("lowGain", &self.low_gain),
+            ("midGain", &self.mid_gain),
+            ("highGain", &self.high_gain),
+        ]
+    }
+}
+
+impl Eq3Node {
+    /// Returns an `Eq3Node`
+    ///
+    /// # Panics
+    ///
+    /// Will panic if a frequency is not within the range `(0, nyquist)`, or if `mid_q` is not
+    /// strictly positive
+    pub fn new<C: BaseAudioContext>(context: &C, options: Eq3Options) -> Self {
+        let nyquist = context.sample_rate() / 2.;
+        for (name, frequency) in [
+            ("low_frequency", options.low_frequency),
+            ("mid_frequency", options.mid_frequency),
+            ("high_frequency", options.high_frequency),
+        ] {
+            assert!(
+                frequency > 0. && frequency < nyquist,
+                "RangeError - {} must be in range (0, {}), got {:?}",
+                name,
+                nyquist,
+                frequency
+            );
+        }
+        assert!(
+            options.mid_q > 0.,
+            "RangeError - mid_q must be strictly positive, got {:?}",
+            options.mid_q
+        );
+
+        context.register(move |registration| {
+            let param_opts = AudioParamDescriptor {
+                min_value: f32::MIN,
+                max_value: f32::MAX,
+                default_value: 0.,
+                automation_rate: AutomationRate::A,
+            };
+
+            let (low_gain, low_proc) =
+                context.create_audio_param(param_opts.clone(), &registration);
+            low_gain.set_value(options.low_gain);
+            let (mid_gain, mid_proc) =
+                context.create_audio_param(param_opts.clone(), &registration);
+            mid_gain.set_value(options.mid_gain);
+            let (high_gain, high_proc) = context.create_audio_param(param_opts, &registration);
+            high_gain.set_value(options.high_gain);
+
+            let bands = [
+                (
+                    low_proc,
+                    BiquadFilterType::Lowshelf,
+                    options.low_frequency,
+                    1.,
+                ),
+                (
+                    mid_proc,
+                    BiquadFilterType::Peaking,
+                    options.mid_frequency,
+                    options.mid_q,
+                ),
+                (
+                    high_proc,
+                    BiquadFilterType::Highshelf,
+                    options.high_frequency,
+                    1.,
+                ),
+            ];
+            let render = Eq3Renderer {
+                bands: bands.map(|(gain, type_, frequency, q)| Band {
+                    gain,
+                    type_,
+                    frequency: f64::from(frequency),
+                    q: f64::from(q),
+                    current_gain: f32::NAN,
+                    coeffs: None,
+                    s1: [0.; MAX_CHANNELS],
+                    s2: [0.; MAX_CHANNELS],
+                }),
+            };
+
+            let node = Eq3Node {
+                registration,
+                channel_config: options.channel_config.into(),
+                low_gain,
+                mid_gain,
+                high_gain,
+                low_frequency: options.low_frequency,
+                mid_frequency: options.mid_frequency,
+                mid_q: options.mid_q,
+                high_frequency: options.high_frequency,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// A-rate [`AudioParam`] for the gain of the low band, in dB
+    #[must_use]
+    pub fn low_gain(&self) -> &AudioParam {
+        &self.low_gain
+    }
+
+    /// A-rate [`AudioParam`] for the gain of the mid band, in dB
+    #[must_use]
+    pub fn mid_gain(&self) -> &AudioParam {
+        &self.mid_gain
+    }
+
+    /// A-rate [`AudioParam`] for the gain of the high band, in dB
+    #[must_use]
+    pub fn high_gain(&self) -> &AudioParam {
+        &self.high_gain
+    }
+
+    /// Corner frequency of the low shelf filter
+    #[must_use]
+    pub fn low_frequency(&self) -> f32 {
+        self.low_frequency
+    }
+
+    /// Center frequency of the peaking filter
+    #[must_use]
+    pub fn mid_frequency(&self) -> f32 {
+        self.mid_frequency
+    }
+
+    /// Quality factor of the peaking filter
+    #[must_use]
+    pub fn mid_q(&self) -> f32 {
+        self.mid_q
+    }
+
+    /// Corner frequency of the high shelf filter
+    #[must_use]
+    pub fn high_frequency(&self) -> f32 {
+        self.high_frequency
+    }
+}
+
+/// Single biquad filter of the equalizer
+struct Band {
+    gain: AudioParamId,
+    type_: BiquadFilterType,
+    frequency: f64,
+    q: f64,
+    /// gain used to compute the current coefficients
+    current_gain: f32,
+    coeffs: Option<Coefficients>,
+    s1: [f64; MAX_CHANNELS],
+    s2: [f64; MAX_CHANNELS],
+}
+
+impl Band {
+    /// Returns the filter coefficients for this gain, only recomputed on gain changes
+    #[inline]
+    fn coeffs(&mut self, gain: f32, sample_rate: f64) -> Coefficients {
+        match self.coeffs {
+            Some(coeffs) if gain == self.current_gain => coeffs,
+            _ => {
+                let coeffs =
+                    Coefficients::new(self.type_, sample_rate, self.frequency, self.q, gain.into());
+                self.current_gain = gain;
+                self.coeffs = Some(coeffs);
+                coeffs
+            }
+        }
+    }
+}
+
+struct Eq3Renderer {
+    bands: [Band; 3],
+}
+
+impl AudioProcessor for Eq3Renderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        *output = input.clone();
+
+        let sample_rate = f64::from(scope.sample_rate);
+
+        for band in self.bands.iter_mut() {
+            let gain_values = params.get(&band.gain);
+
+            output
+                .channels_mut()
+                .iter_mut()
+                .enumerate()
+                .for_each(|(channel_idx, channel)| {
+                    channel
+                        .iter_mut()
+                        .zip(gain_values.iter())
+                        .for_each(|(o, &gain)| {
+                            let coeffs = band.coeffs(gain, sample_rate);
+                            *o = coeffs.tick(
+                                *o,
+                                &mut band.s1[channel_idx],
+                                &mut band.s2[channel_idx],
+                            );
+                        });
+                });
+        }
+
+        true // todo tail time - issue #34
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44_100.;
+
+    // measure the gain in dB applied by the equalizer on a sine at the given frequency
+    fn measure_gain(frequency: f32, gains: [f32; 3]) -> f32 {
+        let length = SAMPLE_RATE as usize / 4;
+        let mut context = OfflineAudioContext::new(1, length, SAMPLE_RATE);
+
+        let options = Eq3Options {
+            low_gain: gains[0],
+            mid_gain: gains[1],
+            high_gain: gains[2],
+            ..Eq3Options::default()
+        };
+        let eq = Eq3Node::new(&context, options);
+        eq.connect(&context.destination());
+
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(frequency);
+        osc.connect(&eq);
+        osc.start();
+
+        let buffer = context.start_rendering_sync();
+        // skip the filters transients
+        let channel = &buffer.get_channel_data(0)[length / 2..];
+        let rms = (channel.iter().map(|v| v * v).sum::<f32>() / channel.len() as f32).sqrt();
+
+        20. * (rms * 2_f32.sqrt()).log10()
+    }
+
+    #[test]
+    fn test_flat() {
+        for frequency in [50., 1000., 12_000.] {
+            let gain = measure_gain(frequency, [0.; 3]);
+            assert!(gain.abs() < 0.1, "{} Hz: {} dB", frequency, gain);
+        }
+    }
+
+    #[test]
+    fn test_bands() {
+        // one test frequency per band
+        let frequencies = [50., 1000., 12_000.];
+
+        for band in 0..3 {
+            for boost in [12., -12.] {
+                let mut gains = [0.; 3];
+                gains[band] = boost;
+
+                for (i, frequency) in frequencies.iter().enumerate() {
+                    let gain = measure_gain(*frequency, gains);
+
+                    if i == band {
+                        assert!((gain - boost).abs() < 1.5, "{} Hz: {} dB", frequency, gain);
+                    } else {
+                        assert!(gain.abs() < 1., "{} Hz: {} dB", frequency, gain);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_frequency() {
+        let context = OfflineAudioContext::new(1, 128, SAMPLE_RATE);
+        let options = Eq3Options {
+            high_frequency: 30_000.,
+            ..Eq3Options::default()
+        };
+        let _ = Eq3Node::new(&context, options);
+    }
+}
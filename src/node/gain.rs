@@ -46,6 +46,10 @@ impl AudioNode for GainNode {
     fn number_of_outputs(&self) -> usize {
         1
     }
+
+    fn params(&self) -> Vec<(&'static str, &AudioParam)> {
+        vec![("gain", &self.gain)]
+    }
 }
 
 impl GainNode {
@@ -98,7 +102,8 @@ impl AudioProcessor for GainRenderer {
 
         *output = input.clone();
 
-        output.modify_channels(|channel| {
+        // silent channels stay silent, skip them
+        output.modify_non_silent_channels(|channel| {
             channel
                 .iter_mut()
                 .zip(gain_values.iter())
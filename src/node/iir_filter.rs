@@ -8,6 +8,7 @@
 // )]
 use num_complex::Complex;
 use std::f64::consts::PI;
+use std::sync::Mutex;
 
 use crate::{
     context::{AudioContextRegistration, BaseAudioContext},
@@ -40,10 +41,8 @@ pub struct IIRFilterNode {
     registration: AudioContextRegistration,
     /// Infos about audio node channel configuration
     channel_config: ChannelConfig,
-    /// numerator filter's coefficients
-    feedforward: Vec<f64>,
-    /// denomintor filter's coefficients
-    feedback: Vec<f64>,
+    /// filter's coefficients as (feedforward, feedback), i.e. (numerator, denominator)
+    coeffs: Mutex<(Vec<f64>, Vec<f64>)>,
 }
 
 impl AudioNode for IIRFilterNode {
@@ -87,12 +86,7 @@ impl IIRFilterNode {
                 channel_config,
             } = options;
 
-            assert!(feedforward.len() <= MAX_IIR_COEFFS_LEN, "NotSupportedError");
-            assert!(!feedforward.is_empty(), "NotSupportedError");
-            assert!(!feedforward.iter().all(|&ff| ff == 0.), "InvalidStateError");
-            assert!(feedback.len() <= MAX_IIR_COEFFS_LEN, "NotSupportedError");
-            assert!(!feedback.is_empty(), "NotSupportedError");
-            assert!(!feedback.iter().all(|&ff| ff == 0.), "InvalidStateError");
+            assert_valid_coefficients(&feedforward, &feedback);
 
             let config = RendererConfig {
                 feedforward: feedforward.clone(),
@@ -104,14 +98,36 @@ impl IIRFilterNode {
             let node = Self {
                 registration,
                 channel_config: channel_config.into(),
-                feedforward,
-                feedback,
+                coeffs: Mutex::new((feedforward, feedback)),
             };
 
             (node, Box::new(render))
         })
     }
 
+    /// Update the filter coefficients during playback
+    ///
+    /// The renderer is swapped at the next render quantum boundary, the filter state is reset
+    /// when the new coefficients are applied.
+    ///
+    /// # Panics
+    ///
+    /// Will panic for invalid coefficients, see [`IIRFilterNode::new`]
+    pub fn set_coefficients(&self, feedforward: Vec<f64>, feedback: Vec<f64>) {
+        assert_valid_coefficients(&feedforward, &feedback);
+
+        let config = RendererConfig {
+            feedforward: feedforward.clone(),
+            feedback: feedback.clone(),
+        };
+        self.registration.context().swap_processor(
+            self.registration.id(),
+            Box::new(IirFilterRenderer::new(config)),
+        );
+
+        *self.coeffs.lock().unwrap() = (feedforward, feedback);
+    }
+
     /// Returns the frequency response for the specified frequencies
     ///
     /// # Arguments
@@ -119,6 +135,10 @@ impl IIRFilterNode {
     /// * `frequency_hz` - frequencies for which frequency response of the filter should be calculated
     /// * `mag_response` - magnitude of the frequency response of the filter
     /// * `phase_response` - phase of the frequency response of the filter
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the given slices are not of the same length
     #[allow(clippy::cast_possible_truncation)]
     pub fn get_frequency_response(
         &self,
@@ -128,6 +148,8 @@ impl IIRFilterNode {
     ) {
         self.validate_inputs(frequency_hz, mag_response, phase_response);
         let sample_rate = self.context().sample_rate() as f64;
+        let coeffs = self.coeffs.lock().unwrap();
+        let (feedforward, feedback) = &*coeffs;
 
         for (i, &f) in frequency_hz.iter().enumerate() {
             let mut num: Complex<f64> = Complex::new(0., 0.);
@@ -135,13 +157,13 @@ impl IIRFilterNode {
 
             // 0 through 20 casts without loss of precision
             #[allow(clippy::cast_precision_loss)]
-            for (idx, &ff) in self.feedforward.iter().enumerate() {
+            for (idx, &ff) in feedforward.iter().enumerate() {
                 num += Complex::from_polar(ff, idx as f64 * -2.0 * PI * f64::from(f) / sample_rate);
             }
 
             // 0 through 20 casts without loss of precision
             #[allow(clippy::cast_precision_loss)]
-            for (idx, &fb) in self.feedback.iter().enumerate() {
+            for (idx, &fb) in feedback.iter().enumerate() {
                 denom +=
                     Complex::from_polar(fb, idx as f64 * -2.0 * PI * f64::from(f) / sample_rate);
             }
@@ -183,6 +205,16 @@ impl IIRFilterNode {
     }
 }
 
+/// Panics if the filter coefficients are not valid
+fn assert_valid_coefficients(feedforward: &[f64], feedback: &[f64]) {
+    assert!(feedforward.len() <= MAX_IIR_COEFFS_LEN, "NotSupportedError");
+    assert!(!feedforward.is_empty(), "NotSupportedError");
+    assert!(!feedforward.iter().all(|&ff| ff == 0.), "InvalidStateError");
+    assert!(feedback.len() <= MAX_IIR_COEFFS_LEN, "NotSupportedError");
+    assert!(!feedback.is_empty(), "NotSupportedError");
+    assert!(!feedback.iter().all(|&ff| ff == 0.), "InvalidStateError");
+}
+
 /// `FilterRendererBuilder` helps to build `IirFilterRenderer`
 struct FilterRendererBuilder {
     /// filter's coefficients as (feedforward, feedback)[]
@@ -641,4 +673,160 @@ mod test {
             abs_all <= 1.0
         );
     }
+
+    #[test]
+    fn swap_processor_mid_stream() {
+        use std::sync::atomic::AtomicU64;
+        use std::sync::Arc;
+
+        use crate::message::ControlMessage;
+        use crate::node::ChannelConfig;
+        use crate::render::{
+            AudioParamValues, AudioProcessor, AudioRenderQuantum, EdgeKind, RenderScope,
+            RenderThread,
+        };
+        use crate::RENDER_QUANTUM_SIZE;
+
+        use super::{IirFilterRenderer, RendererConfig};
+
+        // source at the nyquist frequency, i.e. alternating 1 and -1
+        struct NyquistSource;
+
+        impl AudioProcessor for NyquistSource {
+            fn process(
+                &mut self,
+                _inputs: &[AudioRenderQuantum],
+                outputs: &mut [AudioRenderQuantum],
+                _params: AudioParamValues,
+                _scope: &RenderScope,
+            ) -> bool {
+                outputs[0].force_mono();
+                outputs[0]
+                    .channel_data_mut(0)
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(i, v)| *v = if i % 2 == 0 { 1. } else { -1. });
+                true
+            }
+        }
+
+        let iir = |feedforward: Vec<f64>, feedback: Vec<f64>| {
+            let config = RendererConfig {
+                feedforward,
+                feedback,
+            };
+            Box::new(IirFilterRenderer::new(config))
+        };
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let mut render = RenderThread::new(44_100., 1, receiver, Arc::new(AtomicU64::new(0)));
+
+        let channel_config: ChannelConfig = ChannelConfigOptions {
+            count: 1,
+            ..ChannelConfigOptions::default()
+        }
+        .into();
+
+        // the filter is the graph output, i.e. node 0, fed by the source
+        let messages = vec![
+            ControlMessage::RegisterNode {
+                id: 0,
+                node: iir(vec![1.], vec![1.]),
+                inputs: 1,
+                outputs: 1,
+                channel_config: channel_config.clone(),
+            },
+            ControlMessage::RegisterNode {
+                id: 1,
+                node: Box::new(NyquistSource),
+                inputs: 0,
+                outputs: 1,
+                channel_config,
+            },
+            ControlMessage::ConnectNode {
+                from: 1,
+                to: 0,
+                output: 0,
+                kind: EdgeKind::Audio { input: 0 },
+            },
+        ];
+        messages.into_iter().for_each(|m| sender.send(m).unwrap());
+
+        let energy = |data: &[f32]| data.iter().map(|v| v * v).sum::<f32>() / data.len() as f32;
+
+        // passthrough
+        let before = render.render_audiobuffer(RENDER_QUANTUM_SIZE * 4);
+        assert_float_eq!(energy(before.get_channel_data(0)), 1., abs <= 1e-6);
+
+        // one pole lowpass, almost removes the nyquist frequency
+        let message = ControlMessage::SwapProcessor {
+            id: 0,
+            processor: iir(vec![0.1], vec![1., -0.9]),
+        };
+        sender.send(message).unwrap();
+
+        let after = render.render_audiobuffer(RENDER_QUANTUM_SIZE * 4);
+        let after = after.get_channel_data(0);
+        // skip the filter transient
+        assert!(energy(&after[RENDER_QUANTUM_SIZE..]) < 0.01);
+    }
+
+    #[test]
+    fn set_coefficients() {
+        let context = OfflineAudioContext::new(1, LENGTH, 44_100.);
+
+        let options = IIRFilterOptions {
+            feedforward: vec![1.],
+            feedback: vec![1.],
+            channel_config: ChannelConfigOptions::default(),
+        };
+        let filter = IIRFilterNode::new(&context, options);
+
+        let mut frequency_hz = [22_050.];
+        let mut mag_response = [0.];
+        let mut phase_response = [0.];
+
+        filter.get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response);
+        assert_float_eq!(mag_response[0], 1., abs <= 1e-6);
+
+        filter.set_coefficients(vec![0.1], vec![1., -0.9]);
+        filter.get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response);
+        assert_float_eq!(mag_response[0], 0.1 / 1.9, abs <= 1e-6);
+    }
+
+    #[test]
+    fn set_coefficients_rendering() {
+        let mut context = OfflineAudioContext::new(1, LENGTH, 44_100.);
+
+        let options = IIRFilterOptions {
+            feedforward: vec![1.],
+            feedback: vec![1.],
+            channel_config: ChannelConfigOptions::default(),
+        };
+        let filter = IIRFilterNode::new(&context, options);
+        filter.connect(&context.destination());
+
+        // signal at the nyquist frequency, i.e. alternating 1 and -1
+        let signal: Vec<f32> = (0..LENGTH)
+            .map(|i| if i % 2 == 0 { 1. } else { -1. })
+            .collect();
+        let mut buffer = context.create_buffer(1, LENGTH, 44_100.);
+        buffer.copy_to_channel(&signal, 0);
+
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&filter);
+        src.start();
+
+        // one pole lowpass, almost removes the nyquist frequency
+        filter.set_coefficients(vec![0.1, 0.], vec![1., -0.9]);
+
+        let output = context.start_rendering_sync();
+        let output = output.get_channel_data(0);
+
+        // first sample is 0.1, then converges to +-0.1 / 1.9
+        assert_float_eq!(output[0], 0.1, abs <= 1e-6);
+        let energy = output[128..].iter().map(|v| v * v).sum::<f32>() / (LENGTH - 128) as f32;
+        assert_float_eq!(energy, (0.1_f32 / 1.9).powi(2), abs <= 1e-6);
+    }
 }
@@ -0,0 +1,257 @@
+use std::sync::Arc;
+
+use crate::context::{AudioContextRegistration, BaseAudioContext};
+use crate::loudness::{
+    channel_weight, mean_square_to_lufs, KWeighting, BLOCK_STEP, MOMENTARY_WINDOW,
+    SHORT_TERM_WINDOW,
+};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::AtomicF64;
+
+use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
+
+/// `LoudnessMeterNode` options
+#[derive(Clone, Debug, Default)]
+pub struct LoudnessMeterOptions {
+    /// audio node options
+    pub channel_config: ChannelConfigOptions,
+}
+
+/// `LoudnessMeterNode` measures the loudness of its input, as defined in EBU R128
+///
+/// The input is passed through unchanged. The momentary (400ms window) and short-term
+/// (3s window) loudness of the K-weighted input are updated every 100ms.
+///
+/// Until the windows are filled, the missing part is considered silent. Loudness values
+/// are given in LUFS, silence is reported as `f64::NEG_INFINITY`.
+///
+/// - see also: [`loudness::integrated_lufs`](crate::loudness::integrated_lufs) to measure a whole buffer
+/// - see also: [`BaseAudioContext::create_loudness_meter`](crate::context::BaseAudioContext::create_loudness_meter)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+///
+/// let meter = context.create_loudness_meter();
+/// meter.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&meter);
+/// osc.start();
+///
+/// loop {
+///     println!(
+///         "M: {:.1} LUFS, S: {:.1} LUFS",
+///         meter.momentary_loudness(),
+///         meter.short_term_loudness()
+///     );
+///     std::thread::sleep(std::time::Duration::from_millis(100));
+/// }
+/// ```
+pub struct LoudnessMeterNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Momentary loudness, in LUFS
+    momentary: Arc<AtomicF64>,
+    /// Short-term loudness, in LUFS
+    short_term: Arc<AtomicF64>,
+}
+
+impl AudioNode for LoudnessMeterNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl LoudnessMeterNode {
+    /// returns a `LoudnessMeterNode` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `options` - loudness meter options
+    pub fn new<C: BaseAudioContext>(context: &C, options: LoudnessMeterOptions) -> Self {
+        context.register(move |registration| {
+            let momentary = Arc::new(AtomicF64::new(f64::NEG_INFINITY));
+            let short_term = Arc::new(AtomicF64::new(f64::NEG_INFINITY));
+
+            let sample_rate = context.sample_rate();
+            let segment_len = (BLOCK_STEP * f64::from(sample_rate)).round() as usize;
+            let number_of_segments = (SHORT_TERM_WINDOW / BLOCK_STEP).round() as usize;
+
+            let renderer = LoudnessMeterRenderer {
+                momentary: momentary.clone(),
+                short_term: short_term.clone(),
+                filter: KWeighting::new(sample_rate),
+                segment_len,
+                segment_index: 0,
+                segment_energy: 0.,
+                segments: vec![0.; number_of_segments],
+                segments_index: 0,
+            };
+
+            let node = Self {
+                registration,
+                channel_config: options.channel_config.into(),
+                momentary,
+                short_term,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// Loudness of the input over the last 400ms, in LUFS
+    #[must_use]
+    pub fn momentary_loudness(&self) -> f64 {
+        self.momentary.load()
+    }
+
+    /// Loudness of the input over the last 3s, in LUFS
+    #[must_use]
+    pub fn short_term_loudness(&self) -> f64 {
+        self.short_term.load()
+    }
+}
+
+/// `LoudnessMeterRenderer` represents the rendering part of `LoudnessMeterNode`
+struct LoudnessMeterRenderer {
+    momentary: Arc<AtomicF64>,
+    short_term: Arc<AtomicF64>,
+    filter: KWeighting,
+    /// Number of samples in a 100ms segment
+    segment_len: usize,
+    /// Position in the current segment
+    segment_index: usize,
+    /// Weighted sum of the squared K-weighted samples of the current segment
+    segment_energy: f64,
+    /// Ring buffer of the energy of the last segments, covering the short-term window
+    segments: Vec<f64>,
+    segments_index: usize,
+}
+
+impl LoudnessMeterRenderer {
+    /// Loudness of the last `count` complete segments
+    fn loudness(&self, count: usize) -> f64 {
+        let len = self.segments.len();
+        let energy: f64 = (1..=count)
+            .map(|i| self.segments[(self.segments_index + len - i) % len])
+            .sum();
+
+        mean_square_to_lufs(energy / (count * self.segment_len) as f64)
+    }
+
+    fn end_segment(&mut self) {
+        self.segments[self.segments_index] = self.segment_energy;
+        self.segments_index = (self.segments_index + 1) % self.segments.len();
+        self.segment_energy = 0.;
+        self.segment_index = 0;
+
+        let momentary_segments = (MOMENTARY_WINDOW / BLOCK_STEP).round() as usize;
+        self.momentary.store(self.loudness(momentary_segments));
+        self.short_term.store(self.loudness(self.segments.len()));
+    }
+}
+
+impl AudioProcessor for LoudnessMeterRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        // pass through input
+        *output = input.clone();
+
+        let number_of_channels = input.number_of_channels();
+        let channels = input.channels();
+
+        for i in 0..input.channel_data(0).len() {
+            for (channel_idx, channel) in channels.iter().enumerate() {
+                let weight = channel_weight(channel_idx, number_of_channels);
+                let filtered = f64::from(self.filter.tick(channel[i], channel_idx));
+                self.segment_energy += weight * filtered * filtered;
+            }
+
+            self.segment_index += 1;
+            if self.segment_index == self.segment_len {
+                self.end_segment();
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+
+    #[test]
+    fn test_sine_loudness() {
+        let sample_rate = 48_000.;
+        // 3.5 seconds, so that the short-term window is filled
+        let mut context = OfflineAudioContext::new(2, 168_000, sample_rate);
+
+        let meter = context.create_loudness_meter();
+        meter.connect(&context.destination());
+        assert_eq!(meter.momentary_loudness(), f64::NEG_INFINITY);
+        assert_eq!(meter.short_term_loudness(), f64::NEG_INFINITY);
+
+        // stereo 1kHz sine at -23 dBFS
+        let merger = context.create_channel_merger(2);
+        merger.connect(&meter);
+        let gain = context.create_gain();
+        gain.gain().set_value(10_f32.powf(-23. / 20.));
+        gain.connect_at(&merger, 0, 0);
+        gain.connect_at(&merger, 0, 1);
+
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(1000.);
+        osc.connect(&gain);
+        osc.start();
+
+        let buffer = context.start_rendering_sync();
+
+        assert_float_eq!(meter.momentary_loudness(), -23., abs <= 0.1);
+        assert_float_eq!(meter.short_term_loudness(), -23., abs <= 0.1);
+
+        // input is passed through
+        assert_float_eq!(
+            buffer.get_channel_data(0)[..],
+            buffer.get_channel_data(1)[..],
+            abs_all <= 0.
+        );
+        let peak = buffer
+            .get_channel_data(0)
+            .iter()
+            .fold(0_f32, |acc, v| acc.max(v.abs()));
+        assert_float_eq!(peak, 10_f32.powf(-23. / 20.), abs <= 1e-4);
+    }
+}
@@ -1,9 +1,16 @@
+use crossbeam_channel::{Receiver, TryRecvError};
+
+use crate::buffer::AudioBuffer;
 use crate::context::{AudioContextRegistration, BaseAudioContext};
 use crate::media::{MediaStream, Resampler};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 use crate::RENDER_QUANTUM_SIZE;
 
 use super::{AudioNode, ChannelConfig, MediaStreamRenderer};
 
+/// Duration of the stream read ahead by a buffered [`MediaStreamAudioSourceNode`], in seconds
+const BUFFER_DURATION: f64 = 1.;
+
 /// Options for constructing a [`MediaStreamAudioSourceNode`]
 // dictionary MediaStreamAudioSourceOptions {
 //   required MediaStream mediaStream;
@@ -15,9 +22,8 @@ pub struct MediaStreamAudioSourceOptions<M> {
 /// An audio source from a [`MediaStream`] (e.g. microphone input)
 ///
 /// IMPORTANT: the media stream is polled on the render thread so you must ensure the media stream
-/// iterator never blocks. A later version of the library will allow you to wrap the `MediaStream`
-/// in a `MediaElement`, which buffers the stream on another thread so the render thread never
-/// blocks. <https://github.com/orottier/web-audio-api-rs/issues/120>
+/// iterator never blocks. Use [`MediaStreamAudioSourceNode::new_buffered`] for streams that may
+/// block (e.g. decoding a file), it reads the stream on another thread.
 pub struct MediaStreamAudioSourceNode {
     registration: AudioContextRegistration,
     channel_config: ChannelConfig,
@@ -64,3 +70,117 @@ impl MediaStreamAudioSourceNode {
         })
     }
 }
+
+impl MediaStreamAudioSourceNode {
+    /// Create a source reading the media stream on a dedicated thread, ahead of the render
+    /// thread
+    ///
+    /// Up to one second of the stream is buffered. When the stream does not keep up with a
+    /// real-time context, the source outputs silence for the missing render quanta (an
+    /// underrun). An [`OfflineAudioContext`](crate::context::OfflineAudioContext) waits for the
+    /// stream instead, so the offline rendering is identical to an uninterrupted real-time one.
+    pub fn new_buffered<C: BaseAudioContext, M: MediaStream>(
+        context: &C,
+        options: MediaStreamAudioSourceOptions<M>,
+    ) -> Self {
+        context.register(move |registration| {
+            let node = MediaStreamAudioSourceNode {
+                registration,
+                channel_config: ChannelConfig::default(),
+            };
+
+            let resampler = Resampler::new(
+                context.sample_rate(),
+                RENDER_QUANTUM_SIZE,
+                options.media_stream,
+            );
+
+            let capacity = (BUFFER_DURATION * f64::from(context.sample_rate())
+                / RENDER_QUANTUM_SIZE as f64)
+                .ceil() as usize;
+            let (sender, receiver) = crossbeam_channel::bounded(capacity);
+
+            // ends with the stream, or when the renderer is dropped
+            std::thread::spawn(move || {
+                for result in resampler {
+                    match result {
+                        Ok(buffer) => {
+                            if sender.send(buffer).is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            log::warn!("Error playing audio stream: {}", e);
+                            break;
+                        }
+                    }
+                }
+            });
+
+            let render = BufferedMediaStreamRenderer {
+                receiver,
+                next: None,
+                finished: false,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+}
+
+/// Renderer of a buffered [`MediaStreamAudioSourceNode`], receiving the render quanta read
+/// ahead by another thread
+struct BufferedMediaStreamRenderer {
+    receiver: Receiver<AudioBuffer>,
+    /// quantum received by `poll_ready`, rendered next
+    next: Option<AudioBuffer>,
+    finished: bool,
+}
+
+impl AudioProcessor for BufferedMediaStreamRenderer {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        // single output node
+        let output = &mut outputs[0];
+
+        if !self.poll_ready() {
+            log::debug!("Audio stream underrun");
+            output.make_silent();
+            return true;
+        }
+
+        match self.next.take() {
+            Some(buffer) => {
+                output.set_number_of_channels(buffer.number_of_channels());
+                output
+                    .channels_mut()
+                    .iter_mut()
+                    .zip(buffer.channels())
+                    .for_each(|(o, i)| o.copy_from_slice(i.as_slice()));
+            }
+            None => output.make_silent(),
+        }
+
+        !self.finished
+    }
+
+    fn poll_ready(&mut self) -> bool {
+        if self.next.is_none() && !self.finished {
+            match self.receiver.try_recv() {
+                Ok(buffer) => self.next = Some(buffer),
+                Err(TryRecvError::Empty) => return false,
+                Err(TryRecvError::Disconnected) => {
+                    log::debug!("Stream finished");
+                    self.finished = true;
+                }
+            }
+        }
+
+        true
+    }
+}
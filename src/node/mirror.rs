@@ -0,0 +1,108 @@
+use crate::context::AudioNodeId;
+use crate::param::AudioParamMirror;
+
+use super::{ChannelConfig, ChannelCountMode, ChannelInterpretation};
+
+/// Read-only view on a live [`AudioNode`](super::AudioNode), for inspection by graph tools
+///
+/// Mirrors are retrieved with
+/// [`BaseAudioContext::node_by_id`](crate::context::BaseAudioContext::node_by_id). They reflect
+/// the current state of the node, but are only valid while the node is alive.
+pub trait AudioNodeMirror: Send + Sync {
+    /// Id of the mirrored node
+    fn id(&self) -> AudioNodeId;
+
+    /// Name of the type of the mirrored node, e.g. `"GainNode"`
+    fn type_name(&self) -> &'static str;
+
+    /// Label of the mirrored node, as set with
+    /// [`AudioNode::set_label`](super::AudioNode::set_label), or its type name
+    fn label(&self) -> &str;
+
+    /// The `AudioParam`s of the mirrored node, in order of creation
+    fn params(&self) -> &[AudioParamMirror];
+
+    /// Number of channels used when up-mixing and down-mixing the node inputs
+    fn channel_count(&self) -> usize;
+
+    /// How channels are matched between the node inputs and outputs
+    fn channel_count_mode(&self) -> ChannelCountMode;
+
+    /// How audio up-mixing and down-mixing happens for the node inputs
+    fn channel_interpretation(&self) -> ChannelInterpretation;
+}
+
+/// Mirror of a node, as maintained in the registry of the context
+#[derive(Clone)]
+pub(crate) struct NodeMirror {
+    id: AudioNodeId,
+    type_name: &'static str,
+    label: Option<String>,
+    channel_config: ChannelConfig,
+    params: Vec<AudioParamMirror>,
+}
+
+impl NodeMirror {
+    /// Create the mirror of a node of type `T`
+    pub(crate) fn new<T>(
+        id: AudioNodeId,
+        channel_config: ChannelConfig,
+        params: Vec<AudioParamMirror>,
+    ) -> Self {
+        // strip the module path
+        let type_name = std::any::type_name::<T>();
+        let type_name = type_name
+            .split('<')
+            .next()
+            .and_then(|path| path.rsplit("::").next())
+            .unwrap_or(type_name);
+
+        Self {
+            id,
+            type_name,
+            label: None,
+            channel_config,
+            params,
+        }
+    }
+
+    /// Attach an `AudioParam` created after the node was registered
+    pub(crate) fn push_param(&mut self, param: AudioParamMirror) {
+        self.params.push(param);
+    }
+
+    /// Replace the label of the node, `None` restores the type name
+    pub(crate) fn set_label(&mut self, label: Option<String>) {
+        self.label = label;
+    }
+}
+
+impl AudioNodeMirror for NodeMirror {
+    fn id(&self) -> AudioNodeId {
+        self.id
+    }
+
+    fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    fn label(&self) -> &str {
+        self.label.as_deref().unwrap_or(self.type_name)
+    }
+
+    fn params(&self) -> &[AudioParamMirror] {
+        &self.params
+    }
+
+    fn channel_count(&self) -> usize {
+        self.channel_config.count()
+    }
+
+    fn channel_count_mode(&self) -> ChannelCountMode {
+        self.channel_config.count_mode()
+    }
+
+    fn channel_interpretation(&self) -> ChannelInterpretation {
+        self.channel_config.interpretation()
+    }
+}
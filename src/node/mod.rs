@@ -1,199 +1,66 @@
 //! The AudioNode interface and concrete types
-use std::f32::consts::PI;
-use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
-use std::sync::Arc;
-
-use crate::context::{AudioContextRegistration, ConcreteBaseAudioContext};
+use crate::context::{AudioContextRegistration, BaseAudioContext, ConcreteBaseAudioContext};
 use crate::media::MediaStream;
+use crate::param::AudioParam;
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 
-use lazy_static::lazy_static;
-
 mod analyser;
 pub use analyser::*;
 mod audio_buffer_source;
 pub use audio_buffer_source::*;
 mod biquad_filter;
 pub use biquad_filter::*;
+mod channel_config;
+pub use channel_config::*;
 mod channel_merger;
 pub use channel_merger::*;
 mod channel_splitter;
 pub use channel_splitter::*;
 mod constant_source;
 pub use constant_source::*;
+mod convolution_chain;
+pub use convolution_chain::*;
+mod convolver;
+pub use convolver::*;
 mod delay;
 pub use delay::*;
 mod destination;
 pub use destination::*;
+mod dynamics_compressor;
+pub use dynamics_compressor::*;
+mod envelope_follower;
+pub use envelope_follower::*;
+mod eq3;
+pub use eq3::*;
 mod gain;
 pub use gain::*;
 mod iir_filter;
 pub use iir_filter::*;
+mod loudness_meter;
+pub use loudness_meter::*;
 mod media_stream_destination;
 pub use media_stream_destination::*;
 mod media_stream_source;
 pub use media_stream_source::*;
+mod mirror;
+pub use mirror::*;
+mod morph_oscillator;
+pub use morph_oscillator::*;
+mod noise_gate;
+pub use noise_gate::*;
 mod oscillator;
 pub use oscillator::*;
+mod plucked_string;
+pub use plucked_string::*;
 mod panner;
 pub use panner::*;
 mod stereo_panner;
 pub use stereo_panner::*;
+mod test_signal;
+pub use test_signal::*;
 mod waveshaper;
 pub use waveshaper::*;
 
-pub(crate) const TABLE_LENGTH_USIZE: usize = 8192;
-pub(crate) const TABLE_LENGTH_BY_4_USIZE: usize = TABLE_LENGTH_USIZE / 4;
-
-pub(crate) const TABLE_LENGTH_F32: f32 = TABLE_LENGTH_USIZE as f32;
-pub(crate) const TABLE_LENGTH_BY_4_F32: f32 = TABLE_LENGTH_BY_4_USIZE as f32;
-
-// Compute one period sine wavetable of size TABLE_LENGTH
-lazy_static! {
-    pub(crate) static ref SINETABLE: Vec<f32> = {
-        let table: Vec<f32> = (0..TABLE_LENGTH_USIZE)
-            .map(|x| ((x as f32) * 2.0 * PI * (1. / (TABLE_LENGTH_F32))).sin())
-            .collect();
-        table
-    };
-}
-
-/// How channels must be matched between the node's inputs and outputs.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
-pub enum ChannelCountMode {
-    /// `computedNumberOfChannels` is the maximum of the number of channels of all connections to an
-    /// input. In this mode channelCount is ignored.
-    Max,
-    /// `computedNumberOfChannels` is determined as for "max" and then clamped to a maximum value of
-    /// the given channelCount.
-    ClampedMax,
-    /// `computedNumberOfChannels` is the exact value as specified by the channelCount.
-    Explicit,
-}
-
-impl From<u32> for ChannelCountMode {
-    fn from(i: u32) -> Self {
-        use ChannelCountMode::*;
-
-        match i {
-            0 => Max,
-            1 => ClampedMax,
-            2 => Explicit,
-            _ => unreachable!(),
-        }
-    }
-}
-
-/// The meaning of the channels, defining how audio up-mixing and down-mixing will happen.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
-pub enum ChannelInterpretation {
-    Speakers,
-    Discrete,
-}
-
-impl From<u32> for ChannelInterpretation {
-    fn from(i: u32) -> Self {
-        use ChannelInterpretation::*;
-
-        match i {
-            0 => Speakers,
-            1 => Discrete,
-            _ => unreachable!(),
-        }
-    }
-}
-
-/// Options for constructing ChannelConfig
-#[derive(Clone, Debug)]
-pub struct ChannelConfigOptions {
-    pub count: usize,
-    pub mode: ChannelCountMode,
-    pub interpretation: ChannelInterpretation,
-}
-
-impl Default for ChannelConfigOptions {
-    fn default() -> Self {
-        Self {
-            count: 2,
-            mode: ChannelCountMode::Max,
-            interpretation: ChannelInterpretation::Speakers,
-        }
-    }
-}
-
-/// Config for up/down-mixing of input channels for audio nodes
-///
-/// Only when implementing the [`AudioNode`] trait manually, is this struct of any concern. The
-/// methods `set_channel_count`, `set_channel_count_mode` and `set_channel_interpretation` from the
-/// audio node interface will use this struct to sync the required info to the render thread.
-///
-/// The only way to construct an instance is with [`ChannelConfigOptions`]
-///
-/// ```
-/// use web_audio_api::node::{ChannelConfigOptions, ChannelConfig, ChannelInterpretation, ChannelCountMode};
-///
-/// let opts = ChannelConfigOptions {
-///     count: 1,
-///     mode: ChannelCountMode::Explicit,
-///     interpretation: ChannelInterpretation::Discrete,
-/// };
-/// let _: ChannelConfig = opts.into();
-#[derive(Clone, Debug)]
-pub struct ChannelConfig {
-    count: Arc<AtomicUsize>,
-    mode: Arc<AtomicU32>,
-    interpretation: Arc<AtomicU32>,
-}
-
-impl Default for ChannelConfig {
-    fn default() -> Self {
-        ChannelConfigOptions::default().into()
-    }
-}
-
-// All methods on this struct are marked `pub(crate)` because we don't want outside users to be able to change the values directly.
-// These methods are only accessible via the AudioNode interface, so AudioNode's that have channel count/mode constraints
-// should be able to assert those.
-impl ChannelConfig {
-    /// Represents an enumerated value describing the way channels must be matched between the
-    /// node's inputs and outputs.
-    pub(crate) fn count_mode(&self) -> ChannelCountMode {
-        self.mode.load(Ordering::SeqCst).into()
-    }
-    fn set_count_mode(&self, v: ChannelCountMode) {
-        self.mode.store(v as u32, Ordering::SeqCst)
-    }
-
-    /// Represents an enumerated value describing the meaning of the channels. This interpretation
-    /// will define how audio up-mixing and down-mixing will happen.
-    pub(crate) fn interpretation(&self) -> ChannelInterpretation {
-        self.interpretation.load(Ordering::SeqCst).into()
-    }
-    fn set_interpretation(&self, v: ChannelInterpretation) {
-        self.interpretation.store(v as u32, Ordering::SeqCst)
-    }
-
-    /// Represents an integer used to determine how many channels are used when up-mixing and
-    /// down-mixing connections to any inputs to the node.
-    pub(crate) fn count(&self) -> usize {
-        self.count.load(Ordering::SeqCst)
-    }
-    fn set_count(&self, v: usize) {
-        crate::assert_valid_number_of_channels(v);
-        self.count.store(v, Ordering::SeqCst)
-    }
-}
-
-impl From<ChannelConfigOptions> for ChannelConfig {
-    fn from(opts: ChannelConfigOptions) -> Self {
-        Self {
-            count: Arc::new(AtomicUsize::from(opts.count)),
-            mode: Arc::new(AtomicU32::from(opts.mode as u32)),
-            interpretation: Arc::new(AtomicU32::from(opts.interpretation as u32)),
-        }
-    }
-}
-
 /// This interface represents audio sources, the audio destination, and intermediate processing
 /// modules.
 ///
@@ -213,6 +80,25 @@ pub trait AudioNode {
         self.registration().context()
     }
 
+    /// Label of the node, used in diagnostics such as panic messages
+    ///
+    /// Defaults to the name of the node type, e.g. `"GainNode"`. The label is also available on
+    /// the [`AudioNodeMirror`] of the node.
+    fn label(&self) -> String {
+        self.context()
+            .node_label(self.registration().id())
+            .unwrap_or_else(|| String::from("AudioNode"))
+    }
+
+    /// Set the label of the node, see [`label`](Self::label)
+    fn set_label(&self, label: impl Into<String>)
+    where
+        Self: Sized,
+    {
+        self.context()
+            .set_node_label(self.registration().id(), Some(label.into()));
+    }
+
     /// Connect the output of this AudioNode to the input of another node.
     ///
     /// # Panics
@@ -237,15 +123,7 @@ pub trait AudioNode {
         output: usize,
         input: usize,
     ) -> &'a dyn AudioNode {
-        if self.context() != dest.context() {
-            panic!("InvalidAccessError: Attempting to connect nodes from different contexts");
-        }
-        if self.number_of_outputs() <= output {
-            panic!("IndexSizeError: output port {} is out of bounds", output);
-        }
-        if dest.number_of_inputs() <= input {
-            panic!("IndexSizeError: input port {} is out of bounds", input);
-        }
+        assert_valid_connection(self, dest, output, input);
 
         self.context().connect(
             self.registration().id(),
@@ -256,6 +134,37 @@ pub trait AudioNode {
         dest
     }
 
+    /// Connect a single channel of a specific output of this AudioNode to a specific input of
+    /// another node, without a `ChannelSplitterNode`
+    ///
+    /// The selected channel is summed into the input as a mono signal. It is silent while the
+    /// output has fewer channels, as the channel count of an output can change over time.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic when
+    /// - the AudioContext of the source and destination does not match
+    /// - if the input port is out of bounds for the destination node
+    /// - if the output port is out of bounds for the source node
+    fn connect_channel<'a>(
+        &self,
+        dest: &'a dyn AudioNode,
+        output: usize,
+        source_channel: usize,
+        input: usize,
+    ) -> &'a dyn AudioNode {
+        assert_valid_connection(self, dest, output, input);
+
+        self.context().connect_channel(
+            self.registration().id(),
+            dest.registration().id(),
+            output,
+            source_channel,
+            input,
+        );
+        dest
+    }
+
     /// Disconnects all outputs of the AudioNode that go to a specific destination AudioNode.
     fn disconnect_from<'a>(&self, dest: &'a dyn AudioNode) -> &'a dyn AudioNode {
         if self.context() != dest.context() {
@@ -273,11 +182,62 @@ pub trait AudioNode {
         self.context().disconnect(self.registration().id());
     }
 
+    /// Set the order in which the node is degraded when the render thread is overloaded,
+    /// lowest priority first
+    ///
+    /// Defaults to [`DEFAULT_RENDER_PRIORITY`](crate::render::DEFAULT_RENDER_PRIORITY). The
+    /// `AudioParam`s of the node share its priority. See
+    /// [`OverloadPolicy`](crate::render::OverloadPolicy).
+    fn set_render_priority(&self, priority: u8) {
+        self.context()
+            .set_render_priority(self.registration().id(), priority);
+    }
+
+    /// Mute or unmute the output of the node, without clicks
+    ///
+    /// The output fades out or in with a linear gain ramp of
+    /// [`DEFAULT_MUTE_RAMP_DURATION`](crate::render::DEFAULT_MUTE_RAMP_DURATION), starting at the
+    /// next render quantum. This is independent of any `GainNode`, and a ramp in progress is
+    /// reversed from its current gain.
+    fn set_muted(&self, muted: bool) {
+        self.set_muted_with_ramp(muted, crate::render::DEFAULT_MUTE_RAMP_DURATION);
+    }
+
+    /// Mute or unmute the output of the node, with a gain ramp of the given duration in seconds
+    ///
+    /// See [`set_muted`](Self::set_muted), a full ramp takes `ramp_duration`. A duration of
+    /// zero toggles the output immediately.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ramp duration is negative or not finite
+    fn set_muted_with_ramp(&self, muted: bool, ramp_duration: f64) {
+        self.context()
+            .set_muted(self.registration().id(), muted, ramp_duration);
+    }
+
     /// The number of inputs feeding into the AudioNode. For source nodes, this will be 0.
     fn number_of_inputs(&self) -> usize;
     /// The number of outputs coming out of the AudioNode.
     fn number_of_outputs(&self) -> usize;
 
+    /// The `AudioParam`s of the node, with their Web IDL names (e.g. `"frequency"`,
+    /// `"delayTime"`)
+    ///
+    /// Allows generic access to the params, e.g. to build an inspector UI. Defaults to an empty
+    /// list, custom nodes holding `AudioParam`s should list them here.
+    fn params(&self) -> Vec<(&'static str, &AudioParam)> {
+        vec![]
+    }
+
+    /// Look up an `AudioParam` of the node by its Web IDL name, see [`params`](Self::params)
+    fn param(&self, name: &str) -> Option<&AudioParam> {
+        self.params()
+            .into_iter()
+            .find(|(param_name, _)| *param_name == name)
+            .map(|(_, param)| param)
+    }
+
     /// Represents an enumerated value describing the way channels must be matched between the
     /// node's inputs and outputs.
     fn channel_count_mode(&self) -> ChannelCountMode {
@@ -302,13 +262,86 @@ pub trait AudioNode {
         self.channel_config().count()
     }
 
+    /// Update the channel count
+    ///
+    /// # Panics
+    ///
+    /// Panics if the count is zero or greater than [`MAX_CHANNELS`](crate::MAX_CHANNELS)
     fn set_channel_count(&self, v: usize) {
+        if v == 0 || v > crate::MAX_CHANNELS {
+            panic!(
+                "NotSupportedError - {}: Invalid channel count: {:?} is outside range [1, {:?}]",
+                self.label(),
+                v,
+                crate::MAX_CHANNELS
+            );
+        }
         self.channel_config().set_count(v)
     }
+
+    /// Send the pending channel config changes to the render thread
+    ///
+    /// Changes of the channel count, count mode and interpretation are batched, and sent to
+    /// the render thread along with the next call that talks to the render thread (e.g.
+    /// `connect`). They are applied together, between two render quanta. Use this method to
+    /// send them right away.
+    fn apply_channel_config(&self) {
+        self.context().flush_channel_configs();
+    }
+}
+
+/// Assert that the given output of `source` can be connected to the given input of `dest`
+///
+/// # Panics
+///
+/// This function will panic when
+/// - the AudioContext of the source and destination does not match
+/// - if the input port is out of bounds for the destination node
+/// - if the output port is out of bounds for the source node
+pub(crate) fn assert_valid_connection<S: AudioNode + ?Sized>(
+    source: &S,
+    dest: &dyn AudioNode,
+    output: usize,
+    input: usize,
+) {
+    if source.context() != dest.context() {
+        panic!("InvalidAccessError: Attempting to connect nodes from different contexts");
+    }
+    if source.number_of_outputs() <= output {
+        panic!(
+            "IndexSizeError: output port {} of {} is out of bounds",
+            output,
+            source.label()
+        );
+    }
+    if dest.number_of_inputs() <= input {
+        panic!(
+            "IndexSizeError: input port {} of {} is out of bounds",
+            input,
+            dest.label()
+        );
+    }
+}
+
+/// Playback state of a scheduled source node, see [`AudioScheduledSourceNode::playback_state`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PlaybackState {
+    /// No start has been scheduled, or it has been cancelled
+    Unscheduled,
+    /// A start has been scheduled, the source has not produced any sample yet
+    Scheduled,
+    /// The source is producing samples
+    Playing,
+    /// The source has ended, it will not produce any sample anymore
+    Finished,
 }
 
 /// Interface of source nodes, controlling start and stop times.
 /// The node will emit silence before it is started, and after it has ended.
+///
+/// Start and stop times are passed to the render thread along with the `AudioParam` automation
+/// events, in the order they are issued. A `set_value_at_time` and a `stop_at` called
+/// back-to-back are therefore always applied in the same render quantum.
 pub trait AudioScheduledSourceNode {
     /// Play immediately
     ///
@@ -319,24 +352,125 @@ pub trait AudioScheduledSourceNode {
 
     /// Schedule playback start at given timestamp
     ///
+    /// A time in the past, including a negative time, starts the playback immediately.
+    ///
     /// # Panics
     ///
-    /// Panics if the source was already started
+    /// Panics if the time is NaN or infinite, or if the source was already started
     fn start_at(&self, when: f64);
 
     /// Stop immediately
     ///
     /// # Panics
     ///
-    /// Panics if the source was already stopped
+    /// Panics if the source was not started, or was already stopped
     fn stop(&self);
 
     /// Schedule playback stop at given timestamp
     ///
+    /// A time in the past, including a negative time, stops the playback immediately. A stop
+    /// time that is not after the start time plays nothing, the source ends at its stop time.
+    ///
     /// # Panics
     ///
-    /// Panics if the source was already stopped
+    /// Panics if the time is NaN or infinite, if the source was not started, or was already
+    /// stopped
     fn stop_at(&self, when: f64);
+
+    /// Schedule playback start at the given sample frame, counted from the creation of the
+    /// context
+    ///
+    /// The frame is passed to the render thread as is, the source starts exactly at index
+    /// `frame` of the rendered output. A frame in the past starts the playback immediately.
+    /// [`Self::start_at`] converts its time to a frame position, which may fall between two
+    /// frames.
+    ///
+    /// The default implementation converts the frame to a time and calls [`Self::start_at`],
+    /// for sources that do not store frames. It is not available on trait objects.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the source was already started
+    fn start_at_frame(&self, frame: u64)
+    where
+        Self: AudioNode + Sized,
+    {
+        self.start_at(frame as f64 / f64::from(BaseAudioContext::sample_rate(self.context())));
+    }
+
+    /// Schedule playback stop at the given sample frame, counted from the creation of the
+    /// context
+    ///
+    /// The frame at index `frame` of the rendered output is the first silent frame, see
+    /// [`Self::start_at_frame`] and [`Self::stop_at`].
+    ///
+    /// The default implementation converts the frame to a time and calls [`Self::stop_at`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the source was not started, or was already stopped
+    fn stop_at_frame(&self, frame: u64)
+    where
+        Self: AudioNode + Sized,
+    {
+        self.stop_at(frame as f64 / f64::from(BaseAudioContext::sample_rate(self.context())));
+    }
+
+    /// Start time as scheduled with [`Self::start`], [`Self::start_at`] or
+    /// [`Self::start_at_frame`], in seconds
+    ///
+    /// Returns `None` when no start has been scheduled, or when it has been cancelled.
+    ///
+    /// The default implementation returns `None`, for sources that do not keep track of their
+    /// schedule.
+    fn scheduled_start_time(&self) -> Option<f64> {
+        None
+    }
+
+    /// Stop time as scheduled with [`Self::stop`], [`Self::stop_at`] or
+    /// [`Self::stop_at_frame`], in seconds
+    ///
+    /// Returns `None` when no stop has been scheduled.
+    ///
+    /// The default implementation returns `None`, for sources that do not keep track of their
+    /// schedule.
+    fn scheduled_stop_time(&self) -> Option<f64> {
+        None
+    }
+
+    /// Undo a scheduled start that has not happened yet, a no-op when no start is scheduled
+    ///
+    /// The source can be started again afterwards. A start that has already been reached is
+    /// not undone, the source is stopped at the current time instead, as with [`Self::stop`],
+    /// unless it is scheduled to stop earlier. The same applies if the render thread reaches
+    /// the start time before it receives the cancellation: a start that falls in the render
+    /// quantum the cancellation is applied in is undone, the source plays nothing.
+    ///
+    /// # Panics
+    ///
+    /// The default implementation panics with a `NotSupportedError`, for sources that cannot
+    /// undo their start
+    fn cancel_scheduled_start(&self) {
+        panic!("NotSupportedError - the source cannot cancel its scheduled start");
+    }
+
+    /// Current playback state of the source
+    ///
+    /// The state is updated by the render thread: the source is `Playing` from the first
+    /// render quantum it produces samples in, and `Finished` once it has ended. It lags the
+    /// context time by the render quanta in flight, so it can be used for voice management but
+    /// not for sample accurate timing.
+    ///
+    /// The default implementation is for sources that are not informed by their renderer: it
+    /// never reports `Playing` nor `Finished`, only whether a start is scheduled, see
+    /// [`Self::scheduled_start_time`].
+    fn playback_state(&self) -> PlaybackState {
+        if self.scheduled_start_time().is_some() {
+            PlaybackState::Scheduled
+        } else {
+            PlaybackState::Unscheduled
+        }
+    }
 }
 
 // `MediaStreamRenderer` is internally used by `MediaElementAudioSourceNode` and
@@ -395,3 +529,157 @@ impl<R: MediaStream> AudioProcessor for MediaStreamRenderer<R> {
         !self.finished
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::OfflineAudioContext;
+
+    #[test]
+    fn test_processors_do_not_read_the_wall_clock() {
+        // the `RenderScope` is the only time source of the processors, see `AudioProcessor`
+        let calls: Vec<_> = ["Instant", "SystemTime"]
+            .iter()
+            .map(|clock| format!("{}::now", clock))
+            .collect();
+
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/src/node");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let source = std::fs::read_to_string(&path).unwrap();
+
+            for (number, line) in source.lines().enumerate() {
+                let code = line.trim_start();
+                if code.starts_with("//") {
+                    continue;
+                }
+                for call in &calls {
+                    assert!(
+                        !code.contains(call.as_str()),
+                        "{}:{} reads the wall clock",
+                        path.display(),
+                        number + 1
+                    );
+                }
+            }
+        }
+    }
+
+    /// Third-party source implementing only the required methods
+    struct MinimalSource;
+
+    impl AudioScheduledSourceNode for MinimalSource {
+        fn start(&self) {}
+        fn start_at(&self, _when: f64) {}
+        fn stop(&self) {}
+        fn stop_at(&self, _when: f64) {}
+    }
+
+    #[test]
+    fn test_scheduled_source_default_schedule() {
+        let source = MinimalSource;
+        source.start_at(1.);
+        source.stop_at(2.);
+        assert_eq!(source.scheduled_start_time(), None);
+        assert_eq!(source.scheduled_stop_time(), None);
+        assert_eq!(source.playback_state(), PlaybackState::Unscheduled);
+    }
+
+    /// Third-party source node keeping track of its start and stop times only
+    struct TimedSource {
+        registration: AudioContextRegistration,
+        channel_config: ChannelConfig,
+        start: std::cell::Cell<Option<f64>>,
+        stop: std::cell::Cell<Option<f64>>,
+    }
+
+    impl TimedSource {
+        fn new(context: &OfflineAudioContext) -> Self {
+            context.register(|registration| {
+                let node = Self {
+                    registration,
+                    channel_config: ChannelConfig::default(),
+                    start: std::cell::Cell::new(None),
+                    stop: std::cell::Cell::new(None),
+                };
+                (node, Box::new(SilentProcessor))
+            })
+        }
+    }
+
+    impl AudioNode for TimedSource {
+        fn registration(&self) -> &AudioContextRegistration {
+            &self.registration
+        }
+        fn channel_config(&self) -> &ChannelConfig {
+            &self.channel_config
+        }
+        fn number_of_inputs(&self) -> usize {
+            0
+        }
+        fn number_of_outputs(&self) -> usize {
+            1
+        }
+    }
+
+    impl AudioScheduledSourceNode for TimedSource {
+        fn start(&self) {
+            self.start_at(0.);
+        }
+        fn start_at(&self, when: f64) {
+            self.start.set(Some(when));
+        }
+        fn stop(&self) {
+            self.stop_at(0.);
+        }
+        fn stop_at(&self, when: f64) {
+            self.stop.set(Some(when));
+        }
+        fn scheduled_start_time(&self) -> Option<f64> {
+            self.start.get()
+        }
+        fn scheduled_stop_time(&self) -> Option<f64> {
+            self.stop.get()
+        }
+    }
+
+    struct SilentProcessor;
+
+    impl AudioProcessor for SilentProcessor {
+        fn process(
+            &mut self,
+            _inputs: &[AudioRenderQuantum],
+            outputs: &mut [AudioRenderQuantum],
+            _params: AudioParamValues,
+            _scope: &RenderScope,
+        ) -> bool {
+            outputs[0].make_silent();
+            false
+        }
+    }
+
+    #[test]
+    fn test_scheduled_source_default_playback_state() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let source = TimedSource::new(&context);
+        assert_eq!(source.playback_state(), PlaybackState::Unscheduled);
+        source.start_at(1.);
+        assert_eq!(source.playback_state(), PlaybackState::Scheduled);
+    }
+
+    #[test]
+    fn test_scheduled_source_default_frames() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let source = TimedSource::new(&context);
+        source.start_at_frame(22_050);
+        source.stop_at_frame(88_200);
+        assert_eq!(source.scheduled_start_time(), Some(0.5));
+        assert_eq!(source.scheduled_stop_time(), Some(2.));
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError - the source cannot cancel its scheduled start")]
+    fn test_scheduled_source_default_cancel() {
+        MinimalSource.cancel_scheduled_start();
+    }
+}
@@ -0,0 +1,597 @@
+use crossbeam_channel::Receiver;
+
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::control::{Playback, Scheduler, SourceActivity};
+use crate::message::{latest_value_channel, LatestValueSender};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::periodic_wave::{PeriodicWave, PeriodicWaveOptions};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::oscillator::{unroll_phase, wavetable_lookup};
+use super::{
+    AudioNode, AudioScheduledSourceNode, ChannelConfig, ChannelConfigOptions, PlaybackState,
+};
+
+/// Options for constructing a [`MorphOscillatorNode`]
+#[derive(Clone, Debug)]
+pub struct MorphOscillatorOptions {
+    /// Waveform rendered when `morph` is 0, defaults to a sine
+    pub wave_a: Option<PeriodicWave>,
+    /// Waveform rendered when `morph` is 1, defaults to a sine
+    pub wave_b: Option<PeriodicWave>,
+    /// The frequency of the fundamental frequency.
+    pub frequency: f32,
+    /// A detuning value (in cents) which will offset the frequency by the given amount.
+    pub detune: f32,
+    /// Initial position between the two waveforms, in the `[0, 1]` range
+    pub morph: f32,
+    /// channel config options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for MorphOscillatorOptions {
+    fn default() -> Self {
+        Self {
+            wave_a: None,
+            wave_b: None,
+            frequency: 440.,
+            detune: 0.,
+            morph: 0.,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// Oscillator morphing between two [`PeriodicWave`]s
+///
+/// Frames are rendered as `(1 - morph) * a + morph * b`, where `a` and `b` are the samples of
+/// the two wavetables at the current phase. As the wavetables are linear in their coefficients,
+/// this is equivalent to interpolating the Fourier coefficients when the normalization of the
+/// waves is disabled.
+///
+/// The `frequency` and `detune` params and the scheduling behave as for the
+/// [`OscillatorNode`](crate::node::OscillatorNode).
+///
+/// - see also: [`BaseAudioContext::create_morph_oscillator`](crate::context::BaseAudioContext::create_morph_oscillator)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::PeriodicWaveOptions;
+///
+/// let context = AudioContext::default();
+///
+/// let soft = context.create_periodic_wave(PeriodicWaveOptions {
+///     real: Some(vec![0., 0., 0.]),
+///     imag: Some(vec![0., 1., 0.2]),
+///     disable_normalization: false,
+/// });
+/// let bright = context.create_periodic_wave(PeriodicWaveOptions {
+///     real: Some(vec![0., 0., 0., 0., 0.]),
+///     imag: Some(vec![0., 1., 0.5, 0.33, 0.25]),
+///     disable_normalization: false,
+/// });
+///
+/// let osc = context.create_morph_oscillator(soft, bright);
+/// osc.connect(&context.destination());
+/// // sweep from the soft to the bright waveform
+/// osc.morph().linear_ramp_to_value_at_time(1., context.current_time() + 2.);
+/// osc.start();
+/// ```
+pub struct MorphOscillatorNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// The frequency of the fundamental frequency.
+    frequency: AudioParam,
+    /// A detuning value (in cents) which will offset the frequency by the given amount.
+    detune: AudioParam,
+    /// Position between the two waveforms
+    morph: AudioParam,
+    /// starts and stops oscillator audio streams
+    scheduler: Scheduler,
+    /// channel between control and renderer parts (sender part)
+    sender: LatestValueSender<(PeriodicWave, PeriodicWave)>,
+}
+
+impl AudioNode for MorphOscillatorNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    /// `MorphOscillatorNode` is a source node. A source node is by definition with no input
+    fn number_of_inputs(&self) -> usize {
+        0
+    }
+
+    /// `MorphOscillatorNode` is a mono source node.
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+
+    fn params(&self) -> Vec<(&'static str, &AudioParam)> {
+        vec![
+            ("frequency", &self.frequency),
+            ("detune", &self.detune),
+            ("morph", &self.morph),
+        ]
+    }
+}
+
+impl AudioScheduledSourceNode for MorphOscillatorNode {
+    fn start(&self) {
+        let when = self.registration.context().current_time();
+        self.start_at(when);
+    }
+
+    fn start_at(&self, when: f64) {
+        self.registration
+            .context()
+            .schedule_start(&self.scheduler, when);
+    }
+
+    fn stop(&self) {
+        let when = self.registration.context().current_time();
+        self.stop_at(when);
+    }
+
+    fn stop_at(&self, when: f64) {
+        self.registration
+            .context()
+            .schedule_stop(&self.scheduler, when);
+    }
+
+    fn start_at_frame(&self, frame: u64) {
+        self.registration
+            .context()
+            .schedule_start_frame(&self.scheduler, frame);
+    }
+
+    fn stop_at_frame(&self, frame: u64) {
+        self.registration
+            .context()
+            .schedule_stop_frame(&self.scheduler, frame);
+    }
+
+    fn scheduled_start_time(&self) -> Option<f64> {
+        self.scheduler.scheduled_start()
+    }
+
+    fn scheduled_stop_time(&self) -> Option<f64> {
+        self.scheduler.scheduled_stop()
+    }
+
+    fn cancel_scheduled_start(&self) {
+        self.registration.context().cancel_start(&self.scheduler);
+    }
+
+    fn playback_state(&self) -> PlaybackState {
+        self.scheduler.playback_state()
+    }
+}
+
+impl MorphOscillatorNode {
+    /// Returns a `MorphOscillatorNode`
+    ///
+    /// # Arguments:
+    ///
+    /// * `context` - The `AudioContext`
+    /// * `options` - The MorphOscillatorOptions
+    pub fn new<C: BaseAudioContext>(context: &C, options: MorphOscillatorOptions) -> Self {
+        context.register(move |registration| {
+            let sample_rate = context.sample_rate();
+            let nyquist = sample_rate / 2.;
+
+            let MorphOscillatorOptions {
+                wave_a,
+                wave_b,
+                frequency,
+                detune,
+                morph,
+                channel_config,
+            } = options;
+
+            let wave_a = wave_a
+                .unwrap_or_else(|| PeriodicWave::new(context, PeriodicWaveOptions::default()));
+            let wave_b = wave_b
+                .unwrap_or_else(|| PeriodicWave::new(context, PeriodicWaveOptions::default()));
+
+            // frequency audio parameter
+            let freq_param_opts = AudioParamDescriptor {
+                min_value: -nyquist,
+                max_value: nyquist,
+                default_value: 440.,
+                automation_rate: AutomationRate::A,
+            };
+            let (f_param, f_proc) = context.create_audio_param(freq_param_opts, &registration);
+            f_param.set_value(frequency);
+
+            // detune audio parameter
+            let det_param_opts = AudioParamDescriptor {
+                min_value: -153_600.,
+                max_value: 153_600.,
+                default_value: 0.,
+                automation_rate: AutomationRate::A,
+            };
+            let (det_param, det_proc) = context.create_audio_param(det_param_opts, &registration);
+            det_param.set_value(detune);
+
+            // morph audio parameter
+            let morph_param_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: 1.,
+                default_value: 0.,
+                automation_rate: AutomationRate::A,
+            };
+            let (morph_param, morph_proc) =
+                context.create_audio_param(morph_param_opts, &registration);
+            morph_param.set_value(morph);
+
+            let scheduler = Scheduler::new();
+            // the waves may be replaced several times before the renderer reads them, only
+            // the latest ones are kept
+            let (sender, receiver) = latest_value_channel();
+
+            let renderer = MorphOscillatorRenderer {
+                frequency: f_proc,
+                detune: det_proc,
+                morph: morph_proc,
+                scheduler: scheduler.clone(),
+                receiver,
+                phase: 0.,
+                started: false,
+                wave_a,
+                wave_b,
+                activity: SourceActivity::new(context.base().active_sources().clone(), &scheduler),
+            };
+
+            let node = Self {
+                registration,
+                channel_config: channel_config.into(),
+                frequency: f_param,
+                detune: det_param,
+                morph: morph_param,
+                scheduler,
+                sender,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// A-rate [`AudioParam`] that defines the fondamental frequency of the
+    /// oscillator, expressed in Hz
+    ///
+    /// The final frequency is calculated as follow: frequency * 2^(detune/1200)
+    #[must_use]
+    pub fn frequency(&self) -> &AudioParam {
+        &self.frequency
+    }
+
+    /// A-rate [`AudioParam`] that defines a transposition according to the
+    /// frequency, expressed in cents.
+    ///
+    /// The final frequency is calculated as follow: frequency * 2^(detune/1200)
+    #[must_use]
+    pub fn detune(&self) -> &AudioParam {
+        &self.detune
+    }
+
+    /// A-rate [`AudioParam`] that defines the position between the two waveforms, from 0
+    /// (`wave_a` only) to 1 (`wave_b` only)
+    #[must_use]
+    pub fn morph(&self) -> &AudioParam {
+        &self.morph
+    }
+
+    /// Replace the two waveforms of the oscillator
+    pub fn set_periodic_waves(&self, wave_a: PeriodicWave, wave_b: PeriodicWave) {
+        self.sender.send((wave_a, wave_b));
+    }
+}
+
+/// Rendering component of the morph oscillator node
+struct MorphOscillatorRenderer {
+    /// The frequency of the fundamental frequency.
+    frequency: AudioParamId,
+    /// A detuning value (in cents) which will offset the frequency by the given amount.
+    detune: AudioParamId,
+    /// Position between the two waveforms
+    morph: AudioParamId,
+    /// starts and stops oscillator audio streams
+    scheduler: Scheduler,
+    /// channel between control and renderer parts (receiver part)
+    receiver: Receiver<(PeriodicWave, PeriodicWave)>,
+    /// current phase of the oscillator
+    phase: f64,
+    // defines if the oscillator has started
+    started: bool,
+    /// waveform rendered when `morph` is 0
+    wave_a: PeriodicWave,
+    /// waveform rendered when `morph` is 1
+    wave_b: PeriodicWave,
+    /// keeps track of the oscillator being actively rendered
+    activity: SourceActivity,
+}
+
+impl AudioProcessor for MorphOscillatorRenderer {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single output node
+        let output = &mut outputs[0];
+        // 1 channel output
+        output.set_number_of_channels(1);
+
+        // check if any message was send from the control thread
+        if let Ok((wave_a, wave_b)) = self.receiver.try_recv() {
+            self.wave_a = wave_a;
+            self.wave_b = wave_b;
+        }
+
+        let sample_rate = scope.sample_rate as f64;
+        let nyquist = scope.sample_rate / 2.;
+
+        let mut start_frame = self.scheduler.get_start_frame();
+        let stop_frame = self.scheduler.get_stop_frame();
+
+        match self.scheduler.playback(scope.current_frame) {
+            Playback::Pending => {
+                output.make_silent();
+                return self.scheduler.pending_tail_time();
+            }
+            Playback::Ended => {
+                output.make_silent();
+                self.activity.set_active(false);
+                return false;
+            }
+            Playback::Playing => (),
+        }
+
+        self.activity.set_active(true);
+
+        let channel_data = output.channel_data_mut(0);
+        let frequency_values = params.get(&self.frequency);
+        let detune_values = params.get(&self.detune);
+        let morph_values = params.get(&self.morph);
+        let wave_a = self.wave_a.as_slice();
+        let wave_b = self.wave_b.as_slice();
+
+        let current_frame = scope.current_frame as f64;
+
+        // Prevent scheduling in the past, as for the `OscillatorNode`
+        if !self.started && start_frame < current_frame {
+            start_frame = current_frame;
+        }
+
+        for (index, output_sample) in channel_data.iter_mut().enumerate() {
+            let frame = current_frame + index as f64;
+
+            if frame < start_frame || frame >= stop_frame {
+                *output_sample = 0.;
+                continue;
+            }
+
+            let frequency = frequency_values[index];
+            let detune = detune_values[index];
+            // clamped as for the `OscillatorNode`, so the phase increment never exceeds half a
+            // cycle
+            let computed_frequency = (frequency * (detune / 1200.).exp2()).clamp(-nyquist, nyquist);
+            let phase_incr = computed_frequency as f64 / sample_rate;
+
+            // first sample to render
+            if !self.started {
+                // if start time was between last frame and current frame
+                // we need to adjust the phase first
+                if frame > start_frame {
+                    let ratio = frame - start_frame;
+                    self.phase = unroll_phase(phase_incr * ratio);
+                }
+
+                self.started = true;
+            }
+
+            let morph = morph_values[index].clamp(0., 1.);
+            let a = wavetable_lookup(wave_a, self.phase);
+            let b = wavetable_lookup(wave_b, self.phase);
+            // exactly `a` and `b` at both ends of the range
+            *output_sample = (1. - morph) * a + morph * b;
+
+            self.phase = unroll_phase(self.phase + phase_incr);
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+    use crate::periodic_wave::{PeriodicWave, PeriodicWaveOptions};
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44_100.;
+    const LENGTH: usize = 4096;
+
+    fn waves(context: &OfflineAudioContext) -> (PeriodicWave, PeriodicWave) {
+        let wave_a = PeriodicWave::new(
+            context,
+            PeriodicWaveOptions {
+                real: Some(vec![0., 0.2, 0.]),
+                imag: Some(vec![0., 0.8, 0.3]),
+                disable_normalization: true,
+            },
+        );
+        let wave_b = PeriodicWave::new(
+            context,
+            PeriodicWaveOptions {
+                real: Some(vec![0., 0., 0., 0.]),
+                imag: Some(vec![0., 0.5, 0., 0.4]),
+                disable_normalization: true,
+            },
+        );
+
+        (wave_a, wave_b)
+    }
+
+    // render the morph oscillator with the given morph automation
+    fn render_morph(automate: impl Fn(&AudioParam)) -> Vec<f32> {
+        let mut context = OfflineAudioContext::new(1, LENGTH, SAMPLE_RATE);
+        let (wave_a, wave_b) = waves(&context);
+
+        let osc = context.create_morph_oscillator(wave_a, wave_b);
+        osc.frequency().set_value(441.);
+        automate(osc.morph());
+        osc.connect(&context.destination());
+        osc.start();
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    // render a standard oscillator with the first or second wave
+    fn render_oscillator(second: bool) -> Vec<f32> {
+        let mut context = OfflineAudioContext::new(1, LENGTH, SAMPLE_RATE);
+        let (wave_a, wave_b) = waves(&context);
+
+        let osc = context.create_oscillator();
+        osc.set_periodic_wave(if second { wave_b } else { wave_a });
+        osc.frequency().set_value(441.);
+        osc.connect(&context.destination());
+        osc.start();
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_morph_bounds() {
+        let result = render_morph(|morph| {
+            morph.set_value(0.);
+        });
+        assert_float_eq!(result[..], render_oscillator(false)[..], abs_all <= 0.);
+
+        let result = render_morph(|morph| {
+            morph.set_value(1.);
+        });
+        assert_float_eq!(result[..], render_oscillator(true)[..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_morph_half() {
+        let result = render_morph(|morph| {
+            morph.set_value(0.5);
+        });
+
+        let expected: Vec<f32> = render_oscillator(false)
+            .iter()
+            .zip(render_oscillator(true).iter())
+            .map(|(a, b)| (a + b) / 2.)
+            .collect();
+
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_morph_sweep() {
+        let duration = LENGTH as f64 / SAMPLE_RATE as f64;
+        let result = render_morph(|morph| {
+            morph.set_value_at_time(0., 0.);
+            morph.linear_ramp_to_value_at_time(1., duration);
+        });
+
+        // the step between two frames never exceeds the steepest step of the two
+        // waveforms, plus the contribution of the morph increment
+        let max_step = |signal: &[f32]| {
+            signal
+                .windows(2)
+                .map(|w| (w[1] - w[0]).abs())
+                .fold(0., f32::max)
+        };
+        let bound = max_step(&render_oscillator(false)).max(max_step(&render_oscillator(true)));
+
+        assert!(max_step(&result) <= bound + 1e-3);
+        // and the sweep actually goes from a to b
+        assert_float_eq!(result[1], render_oscillator(false)[1], abs <= 1e-3);
+        assert_float_eq!(
+            result[LENGTH - 1],
+            render_oscillator(true)[LENGTH - 1],
+            abs <= 1e-3
+        );
+    }
+
+    // render the morph oscillator halfway between the two waves at the given frequency
+    fn render_frequency(frequency: f32, detune: f32) -> Vec<f32> {
+        let mut context = OfflineAudioContext::new(1, LENGTH, SAMPLE_RATE);
+        let (wave_a, wave_b) = waves(&context);
+
+        let osc = context.create_morph_oscillator(wave_a, wave_b);
+        osc.frequency().set_value(frequency);
+        osc.detune().set_value(detune);
+        osc.morph().set_value(0.5);
+        osc.connect(&context.destination());
+        osc.start();
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_negative_frequency() {
+        let forward = render_frequency(441., 0.);
+        let backward = render_frequency(-441., 0.);
+
+        // 441 Hz is a whole number of cycles over 100 frames, the reversed wave is the forward
+        // wave time-reversed around the first sample
+        let expected: Vec<f32> = (0..LENGTH)
+            .map(|i| forward[(100 - i % 100) % 100])
+            .collect();
+        assert_float_eq!(backward[..], expected[..], abs_all <= 1e-4);
+    }
+
+    #[test]
+    fn test_frequency_clamped_to_nyquist() {
+        let nyquist = SAMPLE_RATE / 2.;
+
+        // 20 kHz * 2^2 = 80 kHz, far above the Nyquist frequency
+        let result = render_frequency(20_000., 2_400.);
+        let expected = render_frequency(nyquist, 0.);
+        assert_float_eq!(result[..], expected[..], abs_all <= 0.);
+
+        let result = render_frequency(-20_000., 2_400.);
+        let expected = render_frequency(-nyquist, 0.);
+        assert_float_eq!(result[..], expected[..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_set_periodic_waves() {
+        let mut context = OfflineAudioContext::new(1, LENGTH, SAMPLE_RATE);
+        let (wave_a, wave_b) = waves(&context);
+
+        // defaults to sines
+        let osc = MorphOscillatorNode::new(&context, MorphOscillatorOptions::default());
+        osc.set_periodic_waves(wave_b, wave_a);
+        osc.frequency().set_value(441.);
+        osc.connect(&context.destination());
+        osc.start();
+
+        let result = context.start_rendering_sync();
+        assert_float_eq!(
+            result.get_channel_data(0)[..],
+            render_oscillator(true)[..],
+            abs_all <= 0.
+        );
+    }
+}
@@ -0,0 +1,423 @@
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::RENDER_QUANTUM_SIZE;
+
+use super::{
+    smoothing_coefficient, AudioNode, ChannelConfig, ChannelConfigOptions, EnvelopeDetector,
+    EnvelopeFollowerMode,
+};
+
+/// Lowest level handled by the gate, in dB
+const MIN_DB: f32 = -100.;
+/// Difference between the opening and the closing level of the gate, in dB
+const HYSTERESIS: f32 = 6.;
+/// Release time of the level detector, in seconds
+const DETECTOR_RELEASE: f32 = 0.01;
+
+fn db_to_lin(value: f32) -> f32 {
+    10_f32.powf(value / 20.)
+}
+
+/// `NoiseGateNode` options
+#[derive(Clone, Debug)]
+pub struct NoiseGateOptions {
+    /// Level above which the gate opens, in dB
+    pub threshold: f32,
+    /// Time to fully open the gate, in seconds
+    pub attack: f32,
+    /// Time to fully close the gate, in seconds
+    pub release: f32,
+    /// Time the gate stays open after the level dropped below the threshold, in seconds
+    pub hold: f32,
+    /// Attenuation applied when the gate is closed, in dB
+    pub floor: f32,
+    /// audio node options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for NoiseGateOptions {
+    fn default() -> Self {
+        Self {
+            threshold: -40.,
+            attack: 0.005,
+            release: 0.1,
+            hold: 0.05,
+            floor: -60.,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// `NoiseGateNode` attenuates its input when the level drops below a threshold
+///
+/// The level is detected on the full-band envelope of the input, taking the maximum across
+/// all channels so the same gain is applied to every channel. The gate opens when the level
+/// exceeds `threshold` and closes when it falls 6 dB below it, after the `hold` time has
+/// elapsed. When closed, the signal is attenuated by the `floor` amount. The gain moves
+/// between unity and `floor` in linear dB ramps lasting `attack` and `release` seconds.
+///
+/// - see also: [`BaseAudioContext::create_noise_gate`](crate::context::BaseAudioContext::create_noise_gate)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::media::Microphone;
+/// use web_audio_api::node::AudioNode;
+///
+/// let context = AudioContext::default();
+///
+/// let mic = Microphone::default();
+/// let stream = context.create_media_stream_source(mic.stream());
+///
+/// let gate = context.create_noise_gate();
+/// gate.threshold().set_value(-50.);
+/// stream.connect(&gate);
+/// gate.connect(&context.destination());
+/// ```
+pub struct NoiseGateNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Opening level, in dB
+    threshold: AudioParam,
+    /// Opening time, in seconds
+    attack: AudioParam,
+    /// Closing time, in seconds
+    release: AudioParam,
+    /// Hold time, in seconds
+    hold: AudioParam,
+    /// Attenuation of the closed gate, in dB
+    floor: AudioParam,
+}
+
+impl AudioNode for NoiseGateNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+
+    fn params(&self) -> Vec<(&'static str, &AudioParam)> {
+        vec![
+            ("threshold", &self.threshold),
+            ("attack", &self.attack),
+            ("release", &self.release),
+            ("hold", &self.hold),
+            ("floor", &self.floor),
+        ]
+    }
+}
+
+impl NoiseGateNode {
+    /// returns a `NoiseGateNode` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `options` - noise gate options
+    pub fn new<C: BaseAudioContext>(context: &C, options: NoiseGateOptions) -> Self {
+        context.register(move |registration| {
+            let NoiseGateOptions {
+                threshold,
+                attack,
+                release,
+                hold,
+                floor,
+                channel_config,
+            } = options;
+
+            let level_param_opts = AudioParamDescriptor {
+                min_value: MIN_DB,
+                max_value: 0.,
+                default_value: 0.,
+                automation_rate: AutomationRate::K,
+            };
+            let time_param_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: f32::MAX,
+                default_value: 0.,
+                automation_rate: AutomationRate::K,
+            };
+
+            let (threshold_param, threshold_proc) =
+                context.create_audio_param(level_param_opts.clone(), &registration);
+            threshold_param.set_value(threshold);
+
+            let (attack_param, attack_proc) =
+                context.create_audio_param(time_param_opts.clone(), &registration);
+            attack_param.set_value(attack);
+
+            let (release_param, release_proc) =
+                context.create_audio_param(time_param_opts.clone(), &registration);
+            release_param.set_value(release);
+
+            let (hold_param, hold_proc) =
+                context.create_audio_param(time_param_opts, &registration);
+            hold_param.set_value(hold);
+
+            let (floor_param, floor_proc) =
+                context.create_audio_param(level_param_opts, &registration);
+            floor_param.set_value(floor);
+
+            let renderer = NoiseGateRenderer {
+                threshold: threshold_proc,
+                attack: attack_proc,
+                release: release_proc,
+                hold: hold_proc,
+                floor: floor_proc,
+                detector: EnvelopeDetector::new(0),
+                open: false,
+                hold_remaining: 0,
+                gain: MIN_DB,
+            };
+
+            let node = Self {
+                registration,
+                channel_config: channel_config.into(),
+                threshold: threshold_param,
+                attack: attack_param,
+                release: release_param,
+                hold: hold_param,
+                floor: floor_param,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// K-rate [`AudioParam`] defining the level above which the gate opens, in dB
+    #[must_use]
+    pub fn threshold(&self) -> &AudioParam {
+        &self.threshold
+    }
+
+    /// K-rate [`AudioParam`] defining the time to fully open the gate, in seconds
+    #[must_use]
+    pub fn attack(&self) -> &AudioParam {
+        &self.attack
+    }
+
+    /// K-rate [`AudioParam`] defining the time to fully close the gate, in seconds
+    #[must_use]
+    pub fn release(&self) -> &AudioParam {
+        &self.release
+    }
+
+    /// K-rate [`AudioParam`] defining how long the gate stays open once the level dropped
+    /// below the threshold, in seconds
+    #[must_use]
+    pub fn hold(&self) -> &AudioParam {
+        &self.hold
+    }
+
+    /// K-rate [`AudioParam`] defining the attenuation applied by the closed gate, in dB
+    #[must_use]
+    pub fn floor(&self) -> &AudioParam {
+        &self.floor
+    }
+}
+
+/// `NoiseGateRenderer` represents the rendering part of `NoiseGateNode`
+struct NoiseGateRenderer {
+    threshold: AudioParamId,
+    attack: AudioParamId,
+    release: AudioParamId,
+    hold: AudioParamId,
+    floor: AudioParamId,
+    /// Level detector, fed with the maximum absolute value across channels
+    detector: EnvelopeDetector,
+    /// Current state of the gate
+    open: bool,
+    /// Number of samples the gate stays open before closing
+    hold_remaining: usize,
+    /// Current gain, in dB
+    gain: f32,
+}
+
+impl AudioProcessor for NoiseGateRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        let sample_rate = scope.sample_rate;
+
+        // k-rate parameters
+        let threshold = params.get(&self.threshold)[0];
+        let attack = params.get(&self.attack)[0];
+        let release = params.get(&self.release)[0];
+        let hold = params.get(&self.hold)[0];
+        let floor = params.get(&self.floor)[0];
+
+        let open_level = db_to_lin(threshold);
+        let close_level = db_to_lin(threshold - HYSTERESIS);
+        let hold_samples = (hold * sample_rate).round() as usize;
+        let detector_release = smoothing_coefficient(DETECTOR_RELEASE, sample_rate);
+
+        // gain slopes in dB per sample, the gain ramps between `floor` and unity
+        let attack_step = if attack > 0. {
+            -floor / (attack * sample_rate)
+        } else {
+            f32::INFINITY
+        };
+        let release_step = if release > 0. {
+            -floor / (release * sample_rate)
+        } else {
+            f32::INFINITY
+        };
+
+        // floor may have been changed
+        self.gain = self.gain.clamp(floor, 0.);
+
+        let mut gains = [0.; RENDER_QUANTUM_SIZE];
+
+        for (i, g) in gains.iter_mut().enumerate() {
+            // channels are linked, detect on the loudest one
+            let peak = input
+                .channels()
+                .iter()
+                .fold(0_f32, |max, channel| max.max(channel[i].abs()));
+            let level =
+                self.detector
+                    .process(peak, EnvelopeFollowerMode::Peak, 0., detector_release);
+
+            if level >= open_level {
+                self.open = true;
+                self.hold_remaining = hold_samples;
+            } else if self.open && level < close_level {
+                if self.hold_remaining > 0 {
+                    self.hold_remaining -= 1;
+                } else {
+                    self.open = false;
+                }
+            }
+
+            self.gain = if self.open {
+                (self.gain + attack_step).min(0.)
+            } else {
+                (self.gain - release_step).max(floor)
+            };
+
+            *g = db_to_lin(self.gain);
+        }
+
+        *output = input.clone();
+
+        output.modify_non_silent_channels(|channel| {
+            channel
+                .iter_mut()
+                .zip(gains.iter())
+                .for_each(|(value, g)| *value *= g)
+        });
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::OfflineAudioContext;
+    use crate::node::AudioScheduledSourceNode;
+    use crate::AudioBuffer;
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48_000.;
+
+    #[test]
+    fn test_tone_burst_and_noise() {
+        let length = (0.6 * SAMPLE_RATE) as usize;
+        let burst_start = (0.05 * SAMPLE_RATE) as usize;
+        let burst_end = (0.25 * SAMPLE_RATE) as usize;
+
+        // tone burst at -6 dB followed by noise at -60 dB
+        let mut seed = 1_u32;
+        let signal: Vec<f32> = (0..length)
+            .map(|i| {
+                if i < burst_start {
+                    0.
+                } else if i < burst_end {
+                    let phase = 2. * std::f32::consts::PI * 440. * i as f32 / SAMPLE_RATE;
+                    0.5 * phase.sin()
+                } else {
+                    seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                    0.001 * (seed as f32 / u32::MAX as f32 * 2. - 1.)
+                }
+            })
+            .collect();
+
+        let mut context = OfflineAudioContext::new(1, length, SAMPLE_RATE);
+
+        let options = NoiseGateOptions {
+            threshold: -40.,
+            attack: 0.01,
+            release: 0.05,
+            hold: 0.05,
+            floor: -30.,
+            ..NoiseGateOptions::default()
+        };
+        let gate = NoiseGateNode::new(&context, options);
+        gate.connect(&context.destination());
+
+        let src = context.create_buffer_source();
+        src.set_buffer(AudioBuffer::from(vec![signal.clone()], SAMPLE_RATE));
+        src.connect(&gate);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        let output = output.get_channel_data(0);
+
+        // burst is untouched once the gate is open
+        let opened = burst_start + (0.01 * SAMPLE_RATE) as usize;
+        assert_float_eq!(
+            output[opened..burst_end],
+            signal[opened..burst_end],
+            abs_all <= 0.
+        );
+
+        // noise is attenuated by the floor amount after hold and release
+        let closed = (0.45 * SAMPLE_RATE) as usize;
+        let floor = db_to_lin(-30.);
+        output[closed..]
+            .iter()
+            .zip(signal[closed..].iter())
+            .for_each(|(o, i)| assert_float_eq!(*o, *i * floor, abs <= 1e-9));
+
+        // gain never changes faster than the attack and release slopes
+        let attack_step = 30. / (0.01 * SAMPLE_RATE);
+        let release_step = 30. / (0.05 * SAMPLE_RATE);
+        let gains: Vec<Option<f32>> = output
+            .iter()
+            .zip(signal.iter())
+            .map(|(o, i)| (i.abs() > 1e-4).then(|| 20. * (o / i).log10()))
+            .collect();
+        gains.windows(2).for_each(|w| {
+            if let [Some(prev), Some(next)] = w {
+                let change = next - prev;
+                assert!(change <= attack_step + 1e-3);
+                assert!(-change <= release_step + 1e-3);
+            }
+        });
+    }
+}
@@ -1,20 +1,53 @@
-use crossbeam_channel::{self, Receiver, Sender};
-use std::fmt::Debug;
-use std::sync::atomic::{AtomicU32, Ordering};
-use std::sync::Arc;
-
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::{TryFrom, TryInto};
+use core::f64::consts::PI;
+use core::fmt;
+use core::ops::Deref;
+use core::str::FromStr;
+#[cfg(feature = "std")]
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+
+#[cfg(feature = "std")]
+use arrayvec::ArrayVec;
+#[cfg(feature = "std")]
+use crossbeam_channel::{Receiver, Sender};
+use once_cell::race::OnceBox;
+
+#[cfg(feature = "std")]
 use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
-use crate::control::Scheduler;
+#[cfg(feature = "std")]
+use crate::control::{ended_channel, EndedNotifier, OnEnded, Playback, Scheduler, SourceActivity};
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+#[cfg(feature = "std")]
+use crate::message::{latest_value_channel, LatestValueSender};
+#[cfg(feature = "std")]
 use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+#[cfg(feature = "std")]
 use crate::periodic_wave::PeriodicWave;
+#[cfg(feature = "std")]
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
-use crate::RENDER_QUANTUM_SIZE;
 
+#[cfg(feature = "std")]
 use super::{
-    AudioNode, AudioScheduledSourceNode, ChannelConfig, ChannelConfigOptions, SINETABLE,
-    TABLE_LENGTH_USIZE,
+    AudioNode, AudioScheduledSourceNode, ChannelConfig, ChannelConfigOptions, PlaybackState,
 };
 
+/// Narrowest duty cycle of the square wave
+const MIN_PULSE_WIDTH: f32 = 0.01;
+/// Widest duty cycle of the square wave
+const MAX_PULSE_WIDTH: f32 = 0.99;
+/// Longest fade out before the stop time of a declicked oscillator, in seconds
+#[cfg(feature = "std")]
+const DECLICK_DURATION: f64 = 0.003;
+/// Maximum number of scheduled type changes waiting to be applied by the renderer
+#[cfg(feature = "std")]
+const MAX_SCHEDULED_TYPES: usize = 16;
+
 /// Options for constructing an [`OscillatorNode`]
 // dictionary OscillatorOptions : AudioNodeOptions {
 //   OscillatorType type = "sine";
@@ -22,6 +55,7 @@ use super::{
 //   float detune = 0;
 //   PeriodicWave periodicWave;
 // };
+#[cfg(feature = "std")]
 #[derive(Clone, Debug)]
 pub struct OscillatorOptions {
     /// The shape of the periodic waveform
@@ -30,19 +64,35 @@ pub struct OscillatorOptions {
     pub frequency: f32,
     /// A detuning value (in cents) which will offset the frequency by the given amount.
     pub detune: f32,
+    /// Duty cycle of the square wave, only used with the `square` type
+    pub pulse_width: f32,
     /// Optionnal custom waveform, if specified (set `type` to "custom")
     pub periodic_wave: Option<PeriodicWave>,
+    /// Fade out before the stop time, see [`OscillatorNode::set_declick`]
+    pub declick: bool,
+    /// Number of output channels, all carrying the same waveform
+    ///
+    /// Not part of the Web Audio API, which defines a mono oscillator. The waveform is only
+    /// computed once, the output channels share its data.
+    pub output_channels: usize,
     /// channel config options
+    ///
+    /// They only apply to the mixing of the inputs, the oscillator has none: the channel
+    /// count does not change the number of output channels, see `output_channels`.
     pub channel_config: ChannelConfigOptions,
 }
 
+#[cfg(feature = "std")]
 impl Default for OscillatorOptions {
     fn default() -> Self {
         Self {
             type_: OscillatorType::default(),
             frequency: 440.,
             detune: 0.,
+            pulse_width: 0.5,
             periodic_wave: None,
+            declick: false,
+            output_channels: 1,
             channel_config: ChannelConfigOptions::default(),
         }
     }
@@ -69,19 +119,72 @@ impl Default for OscillatorType {
     }
 }
 
-impl From<u32> for OscillatorType {
-    fn from(i: u32) -> Self {
+#[cfg(feature = "std")]
+impl OscillatorType {
+    /// Read the type stored in an atomic, only ever written with valid types
+    fn load(type_: &AtomicU32) -> Self {
+        Self::try_from(type_.load(Ordering::Relaxed)).unwrap_or_default()
+    }
+}
+
+impl TryFrom<u32> for OscillatorType {
+    type Error = InvalidOscillatorType;
+
+    fn try_from(i: u32) -> Result<Self, Self::Error> {
         match i {
-            0 => OscillatorType::Sine,
-            1 => OscillatorType::Square,
-            2 => OscillatorType::Sawtooth,
-            3 => OscillatorType::Triangle,
-            4 => OscillatorType::Custom,
-            _ => unreachable!(),
+            0 => Ok(OscillatorType::Sine),
+            1 => Ok(OscillatorType::Square),
+            2 => Ok(OscillatorType::Sawtooth),
+            3 => Ok(OscillatorType::Triangle),
+            4 => Ok(OscillatorType::Custom),
+            _ => Err(InvalidOscillatorType(i.to_string())),
         }
     }
 }
 
+/// Parse the name of the type in the Web Audio specification, e.g. `"sawtooth"`
+impl FromStr for OscillatorType {
+    type Err = InvalidOscillatorType;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sine" => Ok(OscillatorType::Sine),
+            "square" => Ok(OscillatorType::Square),
+            "sawtooth" => Ok(OscillatorType::Sawtooth),
+            "triangle" => Ok(OscillatorType::Triangle),
+            "custom" => Ok(OscillatorType::Custom),
+            _ => Err(InvalidOscillatorType(s.to_string())),
+        }
+    }
+}
+
+/// Print the name of the type in the Web Audio specification, e.g. `"sawtooth"`
+impl fmt::Display for OscillatorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OscillatorType::Sine => "sine",
+            OscillatorType::Square => "square",
+            OscillatorType::Sawtooth => "sawtooth",
+            OscillatorType::Triangle => "triangle",
+            OscillatorType::Custom => "custom",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Error returned when converting an unknown value or name to an [`OscillatorType`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidOscillatorType(String);
+
+impl fmt::Display for InvalidOscillatorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid oscillator type: {}", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidOscillatorType {}
+
 /// `OscillatorNode` represents an audio source generating a periodic waveform.
 /// It can generate a few common waveforms (i.e. sine, square, sawtooth, triangle),
 /// or can be set to an arbitrary periodic waveform using a [`PeriodicWave`] object.
@@ -111,6 +214,7 @@ impl From<u32> for OscillatorType {
 /// - `cargo run --release --example many_oscillators_with_env`
 /// - `cargo run --release --example amplitude_modulation`
 ///
+#[cfg(feature = "std")]
 pub struct OscillatorNode {
     /// Represents the node instance and its associated audio context
     registration: AudioContextRegistration,
@@ -120,14 +224,29 @@ pub struct OscillatorNode {
     frequency: AudioParam,
     /// A detuning value (in cents) which will offset the frequency by the given amount.
     detune: AudioParam,
+    /// Duty cycle of the square wave
+    pulse_width: AudioParam,
     /// Waveform of an oscillator
+    ///
+    /// Independent of the periodic wave, which reaches the renderer through its own channel,
+    /// so `Relaxed` ordering suffices
     type_: Arc<AtomicU32>,
+    /// Whether the oscillator fades out before its stop time
+    declick: Arc<AtomicBool>,
     /// starts and stops Oscillator audio streams
     scheduler: Scheduler,
     /// channel between control and renderer parts (sender part)
-    sender: Sender<PeriodicWave>,
+    sender: Sender<OscillatorMessage>,
+    /// number of scheduled type changes not applied yet by the renderer, including the ones
+    /// still in the channel
+    pending_types: Arc<AtomicUsize>,
+    /// single slot channel passing the last set periodic wave to the renderer
+    wave_sender: LatestValueSender<PeriodicWave>,
+    /// callback of the `ended` event
+    on_ended: OnEnded,
 }
 
+#[cfg(feature = "std")]
 impl AudioNode for OscillatorNode {
     fn registration(&self) -> &AudioContextRegistration {
         &self.registration
@@ -146,8 +265,17 @@ impl AudioNode for OscillatorNode {
     fn number_of_outputs(&self) -> usize {
         1
     }
+
+    fn params(&self) -> Vec<(&'static str, &AudioParam)> {
+        vec![
+            ("frequency", &self.frequency),
+            ("detune", &self.detune),
+            ("pulseWidth", &self.pulse_width),
+        ]
+    }
 }
 
+#[cfg(feature = "std")]
 impl AudioScheduledSourceNode for OscillatorNode {
     fn start(&self) {
         let when = self.registration.context().current_time();
@@ -155,7 +283,9 @@ impl AudioScheduledSourceNode for OscillatorNode {
     }
 
     fn start_at(&self, when: f64) {
-        self.scheduler.start_at(when);
+        self.registration
+            .context()
+            .schedule_start(&self.scheduler, when);
     }
 
     fn stop(&self) {
@@ -164,10 +294,41 @@ impl AudioScheduledSourceNode for OscillatorNode {
     }
 
     fn stop_at(&self, when: f64) {
-        self.scheduler.stop_at(when);
+        self.registration
+            .context()
+            .schedule_stop(&self.scheduler, when);
+    }
+
+    fn start_at_frame(&self, frame: u64) {
+        self.registration
+            .context()
+            .schedule_start_frame(&self.scheduler, frame);
+    }
+
+    fn stop_at_frame(&self, frame: u64) {
+        self.registration
+            .context()
+            .schedule_stop_frame(&self.scheduler, frame);
+    }
+
+    fn scheduled_start_time(&self) -> Option<f64> {
+        self.scheduler.scheduled_start()
+    }
+
+    fn scheduled_stop_time(&self) -> Option<f64> {
+        self.scheduler.scheduled_stop()
+    }
+
+    fn cancel_scheduled_start(&self) {
+        self.registration.context().cancel_start(&self.scheduler);
+    }
+
+    fn playback_state(&self) -> PlaybackState {
+        self.scheduler.playback_state()
     }
 }
 
+#[cfg(feature = "std")]
 impl OscillatorNode {
     /// Returns an `OscillatorNode`
     ///
@@ -175,7 +336,14 @@ impl OscillatorNode {
     ///
     /// * `context` - The `AudioContext`
     /// * `options` - The OscillatorOptions
+    ///
+    /// # Panics
+    ///
+    /// Panics if the channel count or the number of output channels is outside the
+    /// [1, 32] range
     pub fn new<C: BaseAudioContext>(context: &C, options: OscillatorOptions) -> Self {
+        crate::assert_valid_number_of_channels(options.output_channels);
+
         context.register(move |registration| {
             let sample_rate = context.sample_rate();
             let nyquist = sample_rate / 2.;
@@ -184,8 +352,11 @@ impl OscillatorNode {
                 type_,
                 frequency,
                 detune,
+                pulse_width,
                 channel_config,
                 periodic_wave,
+                declick,
+                output_channels,
             } = options;
 
             // frequency audio parameter
@@ -208,20 +379,46 @@ impl OscillatorNode {
             let (det_param, det_proc) = context.create_audio_param(det_param_opts, &registration);
             det_param.set_value(detune);
 
+            // pulse width audio parameter
+            let pw_param_opts = AudioParamDescriptor {
+                min_value: MIN_PULSE_WIDTH,
+                max_value: MAX_PULSE_WIDTH,
+                default_value: 0.5,
+                automation_rate: AutomationRate::A,
+            };
+            let (pw_param, pw_proc) = context.create_audio_param(pw_param_opts, &registration);
+            pw_param.set_value(pulse_width);
+
             let type_ = Arc::new(AtomicU32::new(type_ as u32));
+            let declick = Arc::new(AtomicBool::new(declick));
 
             let scheduler = Scheduler::new();
-            let (sender, receiver) = crossbeam_channel::bounded(1);
+            let (sender, receiver) = crossbeam_channel::unbounded();
+            let pending_types = Arc::new(AtomicUsize::new(0));
+            // `set_periodic_wave` may be called several times before the renderer reads the
+            // wave, only the latest one is kept
+            let (wave_sender, wave_receiver) = latest_value_channel();
+            let (on_ended, ended) = ended_channel(context.base().ended_events());
 
             let renderer = OscillatorRenderer {
                 type_: type_.clone(),
+                declick: declick.clone(),
                 frequency: f_proc,
                 detune: det_proc,
+                pulse_width: pw_proc,
                 scheduler: scheduler.clone(),
                 receiver,
-                phase: 0.,
+                wave_receiver,
+                oscillator: Oscillator::new(),
                 started: false,
                 periodic_wave: None,
+                scheduled_types: ArrayVec::new(),
+                pending_types: Arc::clone(&pending_types),
+                previous_sample: 0.,
+                faded_out: false,
+                activity: SourceActivity::new(context.base().active_sources().clone(), &scheduler),
+                ended,
+                output_channels,
             };
 
             let node = Self {
@@ -229,9 +426,14 @@ impl OscillatorNode {
                 channel_config: channel_config.into(),
                 frequency: f_param,
                 detune: det_param,
+                pulse_width: pw_param,
                 type_,
+                declick,
                 scheduler,
                 sender,
+                pending_types,
+                wave_sender,
+                on_ended,
             };
 
             // if periodic wave has been given, init it
@@ -263,10 +465,20 @@ impl OscillatorNode {
         &self.detune
     }
 
+    /// A-rate [`AudioParam`] that defines the duty cycle of the square wave, i.e. the part of
+    /// the period during which the signal is high
+    ///
+    /// Only used with the `square` type, in the `[0.01, 0.99]` range. Defaults to 0.5, the
+    /// standard square wave. This is not part of the Web Audio API specification.
+    #[must_use]
+    pub fn pulse_width(&self) -> &AudioParam {
+        &self.pulse_width
+    }
+
     /// Returns the oscillator type
     #[must_use]
     pub fn type_(&self) -> OscillatorType {
-        self.type_.load(Ordering::SeqCst).into()
+        OscillatorType::load(&self.type_)
     }
 
     /// Set the oscillator type
@@ -286,47 +498,683 @@ impl OscillatorNode {
         );
 
         // if periodic wave has been set specified, type_ changes are ignored
-        if self.type_.load(Ordering::SeqCst) == OscillatorType::Custom as u32 {
+        if self.type_.load(Ordering::Relaxed) == OscillatorType::Custom as u32 {
+            return;
+        }
+
+        self.type_.store(type_ as u32, Ordering::Relaxed);
+    }
+
+    /// Schedule a change of the oscillator type at the given context time, in seconds
+    ///
+    /// The type changes at the start of the first cycle of the waveform from `when` on, where
+    /// all the built-in waveforms are zero, so the switch is free of discontinuities. At low
+    /// frequencies the change is therefore delayed by up to a period. Scheduled changes are
+    /// ignored once a `PeriodicWave` has been set.
+    ///
+    /// At most 16 changes can be waiting to be applied, further changes are dropped with a
+    /// warning until the pending ones are applied. A negative `when` is clamped to zero.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if:
+    ///
+    /// * `type_` is `OscillatorType::Custom`
+    /// * `when` is NaN or infinite
+    pub fn set_type_at(&self, type_: OscillatorType, when: f64) {
+        assert_ne!(
+            type_,
+            OscillatorType::Custom,
+            "InvalidStateError: Custom type cannot be set manually"
+        );
+        let when = Scheduler::validate_time(when, "type change");
+
+        // the renderer only switches between built-in types
+        if self.type_.load(Ordering::Relaxed) == OscillatorType::Custom as u32 {
             return;
         }
 
-        self.type_.store(type_ as u32, Ordering::SeqCst);
+        // the renderer keeps the pending changes in a fixed capacity queue
+        let reserved = self
+            .pending_types
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                (n < MAX_SCHEDULED_TYPES).then_some(n + 1)
+            });
+        if reserved.is_err() {
+            log::warn!(
+                "OscillatorNode: {} type changes are pending, the change at {} is dropped",
+                MAX_SCHEDULED_TYPES,
+                when
+            );
+            return;
+        }
+
+        let message = OscillatorMessage::ScheduleType(type_, when);
+        // the renderer is gone once the oscillator has ended
+        let _ = self.sender.send(message);
+    }
+
+    /// Whether the oscillator fades out before its stop time, see
+    /// [`set_declick`](Self::set_declick)
+    #[must_use]
+    pub fn declick(&self) -> bool {
+        self.declick.load(Ordering::Relaxed)
+    }
+
+    /// Fade out the oscillator before its stop time, to avoid the click of a waveform cut away
+    /// from a zero crossing
+    ///
+    /// The output fades out linearly over the last 3 milliseconds before the stop time, down
+    /// to zero at the stop time, or is cut at the first zero crossing of the fade if that comes
+    /// sooner. The fade is shorter if the oscillator starts less than 3 milliseconds before
+    /// its stop time.
+    ///
+    /// Defaults to `false`: the oscillator stops at its stop time, as per spec. This is not part
+    /// of the Web Audio API specification.
+    pub fn set_declick(&self, declick: bool) {
+        self.declick.store(declick, Ordering::Relaxed);
     }
 
     /// Sets a `PeriodicWave` which describes a waveform to be used by the oscillator.
     ///
     /// Calling this sets the oscillator type to `custom`, once set to `custom`
     /// the oscillator cannot be reverted back to a standard waveform.
+    ///
+    /// This never blocks. The renderer picks up the last set wave at the start of the next
+    /// render quantum, the waves set in between are skipped.
     pub fn set_periodic_wave(&self, periodic_wave: PeriodicWave) {
         self.type_
-            .store(OscillatorType::Custom as u32, Ordering::SeqCst);
+            .store(OscillatorType::Custom as u32, Ordering::Relaxed);
+
+        self.wave_sender.send(periodic_wave);
+    }
+
+    /// Register a callback to run once the oscillator has ended, with the context time of its
+    /// scheduled stop
+    ///
+    /// The callback runs on the thread dispatching the `ended` events of the context, not on the
+    /// render thread, once the render thread has stopped rendering the oscillator. Under the
+    /// `wasm` feature, it runs on the thread rendering the context, after the render quantum.
+    /// It is called at most once: never when the oscillator is not stopped, is freed before its
+    /// start, has already ended when the callback is registered, or once the context is closed.
+    /// Only the last registered callback is called.
+    pub fn set_onended<F: FnOnce(f64) + Send + 'static>(&self, callback: F) {
+        self.on_ended.set(callback);
+    }
+
+    /// Unregister the callback set with [`set_onended`](Self::set_onended)
+    pub fn clear_onended(&self) {
+        self.on_ended.clear();
+    }
+}
+
+/// Length of all the wavetables: the sine table, the `PeriodicWave`s and the band-limited
+/// tables of the oscillators
+///
+/// The tables are read with a cubic interpolation, its error decreases with the cube of the
+/// length. The length can be raised to lower the noise floor of the oscillators, at the cost of
+/// the memory of the tables. It must be a power of two.
+pub(crate) const TABLE_LENGTH_USIZE: usize = 8192;
+pub(crate) const TABLE_LENGTH_BY_4_USIZE: usize = TABLE_LENGTH_USIZE / 4;
+
+#[cfg(feature = "std")]
+pub(crate) const TABLE_LENGTH_BY_4_F32: f32 = TABLE_LENGTH_BY_4_USIZE as f32;
+
+// the reads of the tables wrap around with a mask
+const _: () = assert!(TABLE_LENGTH_USIZE.is_power_of_two());
+
+/// Table computed on first use, and shared by all its users
+///
+/// `lazy_static` requires `std`, the race of two threads computing the table at once is
+/// settled by keeping the first computed one.
+pub(crate) struct LazyTable<T> {
+    cell: OnceBox<T>,
+    init: fn() -> T,
+}
+
+impl<T> LazyTable<T> {
+    const fn new(init: fn() -> T) -> Self {
+        Self {
+            cell: OnceBox::new(),
+            init,
+        }
+    }
+}
+
+impl<T> Deref for LazyTable<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.cell.get_or_init(|| Box::new((self.init)()))
+    }
+}
+
+// Compute one period sine wavetable of size TABLE_LENGTH, shared by all contexts (and the
+// default `PeriodicWave`). Computed in double precision, the `f32` phase would be off by up to
+// a few ulps, more than the interpolation error.
+pub(crate) static SINETABLE: LazyTable<Arc<Vec<f32>>> = LazyTable::new(|| {
+    let table: Vec<f32> = (0..TABLE_LENGTH_USIZE)
+        .map(|x| (x as f64 * 2. * PI / TABLE_LENGTH_USIZE as f64).sin() as f32)
+        .collect();
+    Arc::new(table)
+});
+
+/// Bring back in the `[0, 1[` range a phase in the `[-1, 2[` range, i.e. a phase advanced
+/// by an increment of at most one cycle, forwards or backwards
+#[inline]
+pub(super) fn unroll_phase(mut phase: f64) -> f64 {
+    if phase >= 1. {
+        phase -= 1.
+    } else if phase < 0. {
+        phase += 1.;
+        // a tiny negative phase rounds up to 1
+        if phase >= 1. {
+            phase = 0.;
+        }
+    }
+
+    phase
+}
+
+/// Read a wavetable of `TABLE_LENGTH_USIZE` samples at the given phase, in the `[0, 1[` range
+#[inline]
+pub(super) fn wavetable_lookup(wavetable: &[f32], phase: f64) -> f32 {
+    // the phase is unrolled, whichever the direction it runs, so the position is never negative
+    debug_assert!((0. ..1.).contains(&phase));
+    let position = phase * TABLE_LENGTH_USIZE as f64;
+    let floored = position.floor();
+
+    // the table is periodic, its length a power of two, the masked reads are not bounds checked
+    let wavetable: &[f32; TABLE_LENGTH_USIZE] = wavetable.try_into().unwrap();
+    let mask = TABLE_LENGTH_USIZE - 1;
+    let index = floored as usize & mask;
+    let p0 = f64::from(wavetable[index.wrapping_sub(1) & mask]);
+    let p1 = f64::from(wavetable[index]);
+    let p2 = f64::from(wavetable[(index + 1) & mask]);
+    let p3 = f64::from(wavetable[(index + 2) & mask]);
+
+    // Catmull-Rom interpolation of the 4 samples around the position, in double precision so
+    // the error of the tables is not amplified
+    let t = position - floored;
+    let c1 = 0.5 * (p2 - p0);
+    let c2 = p0 - 2.5 * p1 + 2. * p2 - 0.5 * p3;
+    let c3 = 0.5 * (p3 - p0) + 1.5 * (p1 - p2);
+    (((c3 * t + c2) * t + c1) * t + p1) as f32
+}
+
+/// Number of partials of the band-limited table holding the most partials
+///
+/// The highest partial spans 8 samples of the table, so the interpolation of the tables stays
+/// accurate. A wave below `sample_rate / 2 / MAX_PARTIALS` (about 20 Hz) does not get all
+/// its partials up to the Nyquist frequency.
+const MAX_PARTIALS: usize = TABLE_LENGTH_USIZE / 8;
+/// Number of band-limited tables of a built-in type per octave
+const TABLES_PER_OCTAVE: usize = 3;
+/// Number of band-limited tables of a custom wave per octave
+///
+/// Every `PeriodicWave` holds its own tables, so they are kept to one per octave. The
+/// interpolation of the tables still makes the partials fade out smoothly.
+#[cfg(feature = "std")]
+const CUSTOM_TABLES_PER_OCTAVE: usize = 1;
+
+/// Sample a wave of the given Fourier coefficients over a table of `TABLE_LENGTH_USIZE` samples
+#[cfg(feature = "std")]
+fn synthesize_wavetable(reals: &[f32], imags: &[f32]) -> Vec<f64> {
+    PeriodicWave::synthesize_wavetable(reals, imags, TABLE_LENGTH_USIZE)
+}
+
+/// Sample a wave of the given Fourier coefficients over a table of `TABLE_LENGTH_USIZE` samples
+///
+/// The inverse FFT of `PeriodicWave` requires `std`, the partials are summed instead.
+#[cfg(not(feature = "std"))]
+fn synthesize_wavetable(reals: &[f32], imags: &[f32]) -> Vec<f64> {
+    sum_partials(reals, imags)
+}
+
+/// Sample a wave of the given Fourier coefficients by summing its partials
+///
+/// The partials fall on exact samples of a period of sine, in double precision as the FFT.
+#[cfg(any(not(feature = "std"), test))]
+fn sum_partials(reals: &[f32], imags: &[f32]) -> Vec<f64> {
+    let sines: Vec<f64> = (0..TABLE_LENGTH_USIZE)
+        .map(|x| (x as f64 * 2. * PI / TABLE_LENGTH_USIZE as f64).sin())
+        .collect();
+    let mask = TABLE_LENGTH_USIZE - 1;
+
+    let mut wavetable = vec![0.; TABLE_LENGTH_USIZE];
+    // x[n] = sum_k (Re(X_k) cos + Im(X_k) sin)(2 pi k n / N), the DC offset is ignored
+    for (k, (&real, &imag)) in reals.iter().zip(imags).enumerate().skip(1) {
+        let (real, imag) = (f64::from(real), f64::from(imag));
+        for (n, sample) in wavetable.iter_mut().enumerate() {
+            let index = k.wrapping_mul(n);
+            *sample += real * sines[index.wrapping_add(TABLE_LENGTH_BY_4_USIZE) & mask]
+                + imag * sines[index & mask];
+        }
+    }
+
+    wavetable
+}
+
+/// Peak normalization factor of a wavetable, `1.` for a silent one
+pub(crate) fn normalization(wavetable: &[f64]) -> f64 {
+    let peak = wavetable
+        .iter()
+        .fold(0., |max: f64, sample| max.max(sample.abs()));
+    if peak > 0. {
+        1. / peak
+    } else {
+        1.
+    }
+}
+
+/// Number of band-limited tables with the given resolution, down to a single partial
+fn table_count(tables_per_octave: usize) -> usize {
+    tables_per_octave * MAX_PARTIALS.trailing_zeros() as usize + 1
+}
+
+/// Number of partials of the band-limited table `index`, with the given resolution
+fn partials_at(index: usize, tables_per_octave: usize) -> usize {
+    let octaves = index as f64 / tables_per_octave as f64;
+    (MAX_PARTIALS as f64 * (-octaves).exp2()).floor() as usize
+}
+
+/// Band-limited wavetables of a built-in oscillator type, or of a custom wave
+///
+/// [spec] The built-in types are rendered from their Fourier series, without the partials above
+/// the Nyquist frequency, cf. https://webaudio.github.io/web-audio-api/#oscillator-coefficients
+///
+/// The tables hold the partials up to `MAX_PARTIALS`, down to the fundamental, in steps of a
+/// fraction of an octave: a third for the built-in types, see `TABLES_PER_OCTAVE`, and an
+/// octave for the custom waves. The tables of the built-in types only depend on the number of
+/// partials, so they are shared by all contexts whatever their sample rate.
+///
+/// The leading tables holding all the partials of a wave are identical, they are only stored
+/// once.
+pub(crate) struct BandLimitedTables {
+    /// the distinct tables, from the one holding the most partials
+    tables: Vec<Vec<f32>>,
+    /// number of tables identical to the first distinct one, which precede it
+    shared: usize,
+    /// number of tables per octave
+    tables_per_octave: usize,
+    /// normalization factor applied to all the tables
+    scale: f32,
+}
+
+impl fmt::Debug for BandLimitedTables {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BandLimitedTables")
+            .field("distinct_tables", &self.tables.len())
+            .field("tables_per_octave", &self.tables_per_octave)
+            .field("scale", &self.scale)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BandLimitedTables {
+    /// Synthesize the tables of a built-in type with the given sine coefficients
+    ///
+    /// The peak normalization factor of the table holding the most partials is applied to all
+    /// the tables, so the level of the wave does not depend on its frequency.
+    fn builtin(coefficient: impl Fn(usize) -> f64) -> Self {
+        let reals = vec![0.; MAX_PARTIALS + 1];
+        let imags: Vec<f32> = (0..=MAX_PARTIALS)
+            .map(|k| if k == 0 { 0. } else { coefficient(k) as f32 })
+            .collect();
+
+        Self::synthesize(&reals, &imags, TABLES_PER_OCTAVE, None)
+    }
+
+    /// Synthesize the tables of a custom wave, scaled by the normalization factor of the wave
+    #[cfg(feature = "std")]
+    pub(crate) fn custom(reals: &[f32], imags: &[f32], scale: f32) -> Self {
+        Self::synthesize(reals, imags, CUSTOM_TABLES_PER_OCTAVE, Some(scale))
+    }
+
+    /// Synthesize the tables, scaled by the given factor or else by the peak normalization
+    /// factor of the table holding the most partials
+    ///
+    /// The tables are scaled in double precision, the samples are only rounded once.
+    fn synthesize(
+        reals: &[f32],
+        imags: &[f32],
+        tables_per_octave: usize,
+        scale: Option<f32>,
+    ) -> Self {
+        // highest partial of the wave
+        let highest = reals
+            .iter()
+            .zip(imags)
+            .rposition(|(real, imag)| *real != 0. || *imag != 0.)
+            .unwrap_or(0);
+
+        let count = table_count(tables_per_octave);
+        let shared = (0..count)
+            .take_while(|&index| partials_at(index, tables_per_octave) >= highest)
+            .count()
+            .saturating_sub(1);
+
+        let tables: Vec<Vec<f64>> = (shared..count)
+            .map(|index| {
+                let partials = partials_at(index, tables_per_octave).min(reals.len() - 1);
+                synthesize_wavetable(&reals[..=partials], &imags[..=partials])
+            })
+            .collect();
+
+        let scale = scale.unwrap_or_else(|| normalization(&tables[0]) as f32);
+
+        let tables = tables
+            .into_iter()
+            .map(|table| {
+                table
+                    .into_iter()
+                    .map(|sample| (sample * f64::from(scale)) as f32)
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            tables,
+            shared,
+            tables_per_octave,
+            scale,
+        }
+    }
+
+    /// Tables interpolated for a wave of the given fundamental frequency, see [`Band`]
+    pub(crate) fn band(&self, frequency: f32, sample_rate: f32) -> Band {
+        Band::with_resolution(frequency, sample_rate, self.tables_per_octave)
+    }
+
+    #[inline]
+    fn table(&self, index: usize) -> &[f32] {
+        &self.tables[index.saturating_sub(self.shared)]
+    }
+
+    /// Read the tables of the given band at the given phase, in the `[0, 1[` range
+    #[inline]
+    pub(crate) fn lookup(&self, band: Band, phase: f64) -> f32 {
+        let sample = wavetable_lookup(self.table(band.index), phase);
+        if band.weight == 0. {
+            return sample;
+        }
+
+        let next = wavetable_lookup(self.table(band.next), phase);
+        band.weight.mul_add(next - sample, sample)
+    }
+}
+
+/// Pair of adjacent band-limited tables interpolated at a given frequency
+///
+/// The same selection applies to the tables of the built-in types and of the custom waves,
+/// for their respective resolutions.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Band {
+    /// table holding the most partials
+    index: usize,
+    /// table holding fewer partials
+    next: usize,
+    /// weight of the `next` table
+    weight: f32,
+}
+
+impl Band {
+    /// Tables of a built-in type interpolated for a wave of the given fundamental frequency
+    fn new(frequency: f32, sample_rate: f32) -> Self {
+        Self::with_resolution(frequency, sample_rate, TABLES_PER_OCTAVE)
+    }
+
+    /// Tables interpolated for a wave of the given fundamental frequency, out of tables with the
+    /// given number of tables per octave
+    ///
+    /// Both tables only hold partials below the Nyquist frequency, so the wave never aliases.
+    /// The weight of the tables follows the number of partials below the Nyquist frequency on
+    /// a log scale, i.e. the fractional octave position of the frequency, so the partials fade
+    /// out smoothly when the frequency rises.
+    fn with_resolution(frequency: f32, sample_rate: f32, tables_per_octave: usize) -> Self {
+        let partials = f64::from(sample_rate) / 2. / f64::from(frequency).abs();
+        let last = table_count(tables_per_octave) - 1;
+
+        // fractional index of the table holding all the partials below the Nyquist frequency
+        let position = tables_per_octave as f64 * (MAX_PARTIALS as f64 / partials).log2();
+        if position.is_nan() || position <= -1. {
+            // low frequency (or NaN), all the partials of the first table are kept
+            return Self {
+                index: 0,
+                next: 1,
+                weight: 0.,
+            };
+        }
+
+        // the tables following the fractional index hold less partials
+        let position = (position + 1.).min(last as f64);
+        let index = position.floor() as usize;
+        Self {
+            index,
+            next: (index + 1).min(last),
+            weight: (position - index as f64) as f32,
+        }
+    }
+}
+
+// [spec] sawtooth: b[k] = 2 (-1)^(k+1) / (k pi)
+// triangle: b[k] = 8 sin(k pi / 2) / (k pi)^2
+// the square wave is rendered from the sawtooth tables, see `Oscillator::pulse`
+static SAWTOOTH_TABLES: LazyTable<BandLimitedTables> = LazyTable::new(|| {
+    BandLimitedTables::builtin(|k| {
+        let sign = if k % 2 == 1 { 1. } else { -1. };
+        sign * 2. / (k as f64 * PI)
+    })
+});
+static TRIANGLE_TABLES: LazyTable<BandLimitedTables> = LazyTable::new(|| {
+    BandLimitedTables::builtin(|k| {
+        let k = k as f64;
+        8. * (k * PI / 2.).sin() / (k * PI).powi(2)
+    })
+});
+
+/// Band-limited oscillator, the DSP of the `OscillatorNode`
+///
+/// It can be used on its own in an [`AudioProcessor`](crate::render::AudioProcessor), with the
+/// frequency computed by an [`AudioParamProcessor`](crate::AudioParamProcessor). The built-in
+/// types are rendered from the same band-limited tables as the node. A `PeriodicWave` requires
+/// the node, the `Custom` type renders a sine.
+#[derive(Clone, Debug)]
+pub struct Oscillator {
+    /// current phase of the oscillator
+    phase: f64,
+    /// whether the phase has wrapped around with the last sample, i.e. a cycle starts
+    new_cycle: bool,
+    /// band-limited tables at the last computed frequency
+    band: Band,
+    /// frequency of `band`
+    band_frequency: f32,
+}
+
+impl Default for Oscillator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Oscillator {
+    /// Returns an oscillator at the start of a cycle
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phase: 0.,
+            new_cycle: true,
+            band: Band::default(),
+            band_frequency: f32::NAN,
+        }
+    }
+
+    /// Generate the next sample of a wave of the given type and frequency, in Hz
+    ///
+    /// The frequency is clamped to the Nyquist frequency, a negative frequency runs the wave
+    /// backwards. The pulse width is only used by the `Square` type.
+    pub fn next_sample(
+        &mut self,
+        type_: OscillatorType,
+        frequency: f32,
+        pulse_width: f32,
+        sample_rate: f32,
+    ) -> f32 {
+        let nyquist = sample_rate / 2.;
+        let frequency = frequency.clamp(-nyquist, nyquist);
+
+        self.select_band(frequency, sample_rate, None);
+        let sample = self.sample(type_, pulse_width, None);
+        self.advance(f64::from(frequency) / f64::from(sample_rate));
+
+        sample
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn set_phase(&mut self, phase: f64) {
+        self.phase = phase;
+    }
 
-        self.sender
-            .send(periodic_wave)
-            .expect("Sending periodic wave to the node renderer failed");
+    /// Whether the phase has wrapped around with the last sample, i.e. a cycle starts
+    #[cfg(feature = "std")]
+    pub(crate) fn new_cycle(&self) -> bool {
+        self.new_cycle
+    }
+
+    /// Recompute the band at the next sample, e.g. when the tables of the custom type change
+    #[cfg(feature = "std")]
+    pub(crate) fn reset_band(&mut self) {
+        self.band_frequency = f32::NAN;
+    }
+
+    /// Select the band-limited tables of the given frequency, out of the tables of the custom
+    /// type if any
+    #[inline]
+    pub(crate) fn select_band(
+        &mut self,
+        frequency: f32,
+        sample_rate: f32,
+        custom: Option<&BandLimitedTables>,
+    ) {
+        if frequency != self.band_frequency {
+            self.band = match custom {
+                Some(tables) => tables.band(frequency, sample_rate),
+                None => Band::new(frequency, sample_rate),
+            };
+            self.band_frequency = frequency;
+        }
+    }
+
+    /// Sample of the wave at the current phase, `custom` holds the tables of the custom type
+    #[inline]
+    pub(crate) fn sample(
+        &self,
+        type_: OscillatorType,
+        pulse_width: f32,
+        custom: Option<&BandLimitedTables>,
+    ) -> f32 {
+        match (type_, custom) {
+            (OscillatorType::Sawtooth, _) => SAWTOOTH_TABLES.lookup(self.band, self.phase),
+            (OscillatorType::Square, _) => Self::pulse(self.phase, self.band, pulse_width),
+            (OscillatorType::Triangle, _) => TRIANGLE_TABLES.lookup(self.band, self.phase),
+            (OscillatorType::Custom, Some(tables)) => tables.lookup(self.band, self.phase),
+            (OscillatorType::Sine | OscillatorType::Custom, _) => {
+                wavetable_lookup(&SINETABLE, self.phase)
+            }
+        }
+    }
+
+    /// Advance the phase by the given increment, in cycles
+    #[inline]
+    pub(crate) fn advance(&mut self, phase_incr: f64) {
+        // [spec] a negative frequency runs the phase backwards, the cycle then starts
+        // when the phase wraps below zero
+        let phase = self.phase + phase_incr;
+        self.new_cycle = !(0. ..1.).contains(&phase);
+        self.phase = unroll_phase(phase);
+    }
+
+    // pulse wave, high from the rising edge at phase 0 up to the falling edge at
+    // `pulse_width`. It is the difference of two sawtooths shifted by the width, so that a
+    // modulated width stays band-limited:
+    // pulse(phase) = saw(phase - width + 0.5) - saw(phase + 0.5) + 2 * width - 1
+    // The overshoot of the pulse edges is the one of the sawtooth, so the normalization of the
+    // sawtooth applies.
+    #[inline]
+    fn pulse(phase: f64, band: Band, pulse_width: f32) -> f32 {
+        let pulse_width = pulse_width.clamp(MIN_PULSE_WIDTH, MAX_PULSE_WIDTH);
+
+        let falling_phase = Self::wrap_phase(phase - f64::from(pulse_width) + 0.5);
+        let falling = SAWTOOTH_TABLES.lookup(band, falling_phase);
+        let rising = SAWTOOTH_TABLES.lookup(band, unroll_phase(phase + 0.5));
+
+        SAWTOOTH_TABLES
+            .scale
+            .mul_add(2. * pulse_width - 1., falling - rising)
+    }
+
+    /// Wrap a phase in the `[0, 1[` range
+    #[inline]
+    fn wrap_phase(phase: f64) -> f64 {
+        let phase = phase - phase.floor();
+        // a tiny negative phase rounds up to 1
+        if phase >= 1. {
+            0.
+        } else {
+            phase
+        }
     }
 }
 
+/// Messages from the [`OscillatorNode`] to its renderer
+#[cfg(feature = "std")]
+enum OscillatorMessage {
+    /// Switch to the given built-in type at the given context time
+    ScheduleType(OscillatorType, f64),
+}
+
 /// Rendering component of the oscillator node
+#[cfg(feature = "std")]
 struct OscillatorRenderer {
     /// The shape of the periodic waveform
     type_: Arc<AtomicU32>,
+    /// Whether the oscillator fades out before its stop time
+    declick: Arc<AtomicBool>,
     /// The frequency of the fundamental frequency.
     frequency: AudioParamId,
     /// A detuning value (in cents) which will offset the frequency by the given amount.
     detune: AudioParamId,
+    /// Duty cycle of the square wave
+    pulse_width: AudioParamId,
     /// starts and stops oscillator audio streams
     scheduler: Scheduler,
     /// channel between control and renderer parts (receiver part)
-    receiver: Receiver<PeriodicWave>,
-    /// current phase of the oscillator
-    phase: f64,
+    receiver: Receiver<OscillatorMessage>,
+    /// last periodic wave set on the control side
+    wave_receiver: Receiver<PeriodicWave>,
+    /// phase and band-limited tables of the built-in types
+    oscillator: Oscillator,
     // defines if the oscillator has started
     started: bool,
     // wavetable placeholder for custom oscillators
     periodic_wave: Option<PeriodicWave>,
+    /// type changes that have not been applied yet, sorted by time
+    scheduled_types: ArrayVec<(OscillatorType, f64), MAX_SCHEDULED_TYPES>,
+    /// number of type changes not applied yet, shared with the control side which bounds it
+    pending_types: Arc<AtomicUsize>,
+    /// last rendered sample, to find the zero crossings of the fade out
+    previous_sample: f32,
+    /// whether the fade out has been cut at a zero crossing
+    faded_out: bool,
+    /// keeps track of the oscillator being actively rendered
+    activity: SourceActivity,
+    /// signals the end of the oscillator to the control thread
+    ended: EndedNotifier,
+    /// number of output channels, sharing the data of the first one
+    output_channels: usize,
 }
 
+#[cfg(feature = "std")]
 impl AudioProcessor for OscillatorRenderer {
     fn process(
         &mut self,
@@ -340,63 +1188,99 @@ impl AudioProcessor for OscillatorRenderer {
         // 1 channel output
         output.set_number_of_channels(1);
 
-        // check if any message was send from the control thread
-        if let Ok(periodic_wave) = self.receiver.try_recv() {
+        // check if a new wave or type change was sent from the control thread
+        if let Ok(periodic_wave) = self.wave_receiver.try_recv() {
             self.periodic_wave = Some(periodic_wave);
+            // the tables of the wave have their own resolution
+            self.oscillator.reset_band();
+        }
+        for message in self.receiver.try_iter() {
+            match message {
+                OscillatorMessage::ScheduleType(type_, when) => {
+                    let index = self.scheduled_types.partition_point(|(_, t)| *t <= when);
+                    // never full, the control side does not send more changes than it holds
+                    let inserted = self.scheduled_types.try_insert(index, (type_, when));
+                    debug_assert!(inserted.is_ok());
+                }
+            }
         }
 
         let sample_rate = scope.sample_rate as f64;
-        let dt = 1. / sample_rate;
-        let num_frames = RENDER_QUANTUM_SIZE;
-        let next_block_time = scope.current_time + dt * num_frames as f64;
-
-        let mut start_time = self.scheduler.get_start_at();
-        let stop_time = self.scheduler.get_stop_at();
-
-        if start_time >= next_block_time {
-            output.make_silent();
-            return true;
-        } else if stop_time < scope.current_time {
-            output.make_silent();
-            return false;
+        let nyquist = scope.sample_rate / 2.;
+
+        let mut start_frame = self.scheduler.get_start_frame();
+        let stop_frame = self.scheduler.get_stop_frame();
+
+        match self.scheduler.playback(scope.current_frame) {
+            Playback::Pending => {
+                output.make_silent();
+                return self.scheduler.pending_tail_time();
+            }
+            Playback::Ended => {
+                output.make_silent();
+                self.activity.set_active(false);
+                // the renderer is kept until the event is queued
+                return self.ended.notify(stop_frame / sample_rate);
+            }
+            Playback::Playing => (),
         }
 
-        let type_ = self.type_.load(Ordering::SeqCst).into();
+        self.activity.set_active(true);
+
+        // a received wave implies the custom type, which cannot be reverted, so the type flag
+        // does not need to be ordered with the channel
+        let mut type_ = if self.periodic_wave.is_some() {
+            OscillatorType::Custom
+        } else {
+            match OscillatorType::load(&self.type_) {
+                // the type is set to custom before the wave is sent, render a sine until the
+                // wave is received
+                OscillatorType::Custom => OscillatorType::Sine,
+                type_ => type_,
+            }
+        };
         let channel_data = output.channel_data_mut(0);
         let frequency_values = params.get(&self.frequency);
         let detune_values = params.get(&self.detune);
+        let pulse_width_values = params.get(&self.pulse_width);
 
-        let mut current_time = scope.current_time;
+        let current_frame = scope.current_frame as f64;
 
         // Prevent scheduling in the past
         //
         // [spec] If 0 is passed in for this value or if the value is less than
         // currentTime, then the sound will start playing immediately
         // cf. https://webaudio.github.io/web-audio-api/#dom-audioscheduledsourcenode-start-when-when
-        if !self.started && start_time < current_time {
-            start_time = current_time;
+        if !self.started && start_frame < current_frame {
+            start_frame = current_frame;
         }
 
+        // the fade out of a declicked oscillator does not start before the oscillator
+        let declick = self.declick.load(Ordering::Relaxed);
+        let fade_frames = (DECLICK_DURATION * sample_rate).min(stop_frame - start_frame);
+
         for (index, output_sample) in channel_data.iter_mut().enumerate() {
-            if current_time < start_time || current_time >= stop_time {
-                *output_sample = 0.;
-                current_time += dt;
+            let frame = current_frame + index as f64;
 
+            if frame < start_frame || frame >= stop_frame {
+                *output_sample = 0.;
                 continue;
             }
 
             let frequency = frequency_values[index];
             let detune = detune_values[index];
-            let computed_frequency = frequency * (detune / 1200.).exp2();
+            // [spec] the computed frequency is clamped to the nominal range of the frequency
+            // param, so the phase increment never exceeds half a cycle
+            let computed_frequency = (frequency * (detune / 1200.).exp2()).clamp(-nyquist, nyquist);
 
             // first sample to render
             if !self.started {
                 // if start time was between last frame and current frame
                 // we need to adjust the phase first
-                if current_time > start_time {
+                if frame > start_frame {
                     let phase_incr = computed_frequency as f64 / sample_rate;
-                    let ratio = (current_time - start_time) / dt;
-                    self.phase = Self::unroll_phase(phase_incr * ratio);
+                    let ratio = frame - start_frame;
+                    self.oscillator.set_phase(unroll_phase(phase_incr * ratio));
                 }
 
                 self.started = true;
@@ -404,137 +1288,86 @@ impl AudioProcessor for OscillatorRenderer {
 
             let phase_incr = computed_frequency as f64 / sample_rate;
 
-            // @note: per spec all default oscillators should be rendered from a
-            // wavetable, define if it worth the assle...
-            // e.g. for now `generate_sine` and `generate_custom` are almost the sames
-            // cf. https://webaudio.github.io/web-audio-api/#oscillator-coefficients
-            *output_sample = match type_ {
-                OscillatorType::Sine => self.generate_sine(),
-                OscillatorType::Sawtooth => self.generate_sawtooth(phase_incr),
-                OscillatorType::Square => self.generate_square(phase_incr),
-                OscillatorType::Triangle => self.generate_triangle(),
-                OscillatorType::Custom => self.generate_custom(),
-            };
+            // scheduled type changes apply at the start of a cycle, where all the built-in
+            // waveforms are zero, or right away when the phase does not move
+            if let Some(&(next_type, when)) = self.scheduled_types.first() {
+                if frame / sample_rate >= when && (self.oscillator.new_cycle() || phase_incr == 0.)
+                {
+                    self.scheduled_types.remove(0);
+                    self.pending_types.fetch_sub(1, Ordering::Relaxed);
+                    // a periodic wave may have been set since, it cannot be reverted
+                    let custom = OscillatorType::Custom as u32;
+                    let switched =
+                        self.type_
+                            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |t| {
+                                (t != custom).then_some(next_type as u32)
+                            });
+                    if switched.is_ok() {
+                        type_ = next_type;
+                    }
+                }
+            }
 
-            current_time += dt;
+            let custom = self.periodic_wave.as_ref().map(PeriodicWave::band_limited);
+            self.oscillator
+                .select_band(computed_frequency, scope.sample_rate, custom);
+            *output_sample = self
+                .oscillator
+                .sample(type_, pulse_width_values[index], custom);
+
+            // linear fade down to zero at the stop time, cut at the first zero crossing. The
+            // frame at the stop time, up to a millionth of a frame, is zero
+            if declick {
+                let remaining = stop_frame - frame;
+                if remaining < fade_frames {
+                    if self.faded_out
+                        || *output_sample * self.previous_sample < 0.
+                        || remaining < 1e-6
+                    {
+                        self.faded_out = true;
+                        *output_sample = 0.;
+                    } else {
+                        self.previous_sample = *output_sample;
+                        *output_sample *= (remaining / fade_frames) as f32;
+                    }
+                } else {
+                    // the stop time may have been moved since the waveform was cut
+                    self.faded_out = false;
+                    self.previous_sample = *output_sample;
+                }
+            }
 
-            self.phase = Self::unroll_phase(self.phase + phase_incr);
+            self.oscillator.advance(phase_incr);
         }
 
+        // the added channels are clones of the rendered one, pointing to the same data
+        output.set_number_of_channels(self.output_channels);
+
         true
     }
 }
 
-impl OscillatorRenderer {
-    #[inline]
-    fn generate_sine(&mut self) -> f32 {
-        let position = self.phase * TABLE_LENGTH_USIZE as f64;
-        let floored = position.floor();
-
-        let prev_index = floored as usize;
-        let mut next_index = prev_index + 1;
-        if next_index == TABLE_LENGTH_USIZE {
-            next_index = 0;
-        }
-
-        // linear interpolation into lookup table
-        let k = (position - floored) as f32;
-        SINETABLE[prev_index].mul_add(1. - k, SINETABLE[next_index] * k)
-    }
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+    use std::convert::TryFrom;
+    use std::f64::consts::PI;
+    use std::sync::atomic::Ordering;
 
-    #[inline]
-    fn generate_sawtooth(&mut self, phase_incr: f64) -> f32 {
-        // offset phase to start at 0. (not -1.)
-        let phase = Self::unroll_phase(self.phase + 0.5);
-        let mut sample = 2.0 * phase - 1.0;
-        sample -= Self::poly_blep(phase, phase_incr, cfg!(test));
+    use crate::buffer::AudioBuffer;
+    use crate::context::{BaseAudioContext, ManualAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode, PlaybackState};
+    use crate::node::{ChannelConfigOptions, ChannelCountMode};
+    use crate::periodic_wave::{PeriodicWave, PeriodicWaveOptions};
+    use crate::render::NodeIndex;
+    use crate::testing::{assert_audio_eq, assert_fixture_eq, Tolerance};
+    use crate::RENDER_QUANTUM_SIZE;
 
-        sample as f32
-    }
-
-    #[inline]
-    fn generate_square(&mut self, phase_incr: f64) -> f32 {
-        let mut sample = if self.phase < 0.5 { 1.0 } else { -1.0 };
-        sample += Self::poly_blep(self.phase, phase_incr, cfg!(test));
-
-        let shift_phase = Self::unroll_phase(self.phase + 0.5);
-        sample -= Self::poly_blep(shift_phase, phase_incr, cfg!(test));
-
-        sample as f32
-    }
-
-    #[inline]
-    fn generate_triangle(&mut self) -> f32 {
-        let mut sample = -4. * self.phase + 2.;
-
-        if sample > 1. {
-            sample = 2. - sample;
-        } else if sample < -1. {
-            sample = -2. - sample;
-        }
-
-        sample as f32
-    }
-
-    #[inline]
-    fn generate_custom(&mut self) -> f32 {
-        let periodic_wave = self.periodic_wave.as_ref().unwrap().as_slice();
-        let position = self.phase * TABLE_LENGTH_USIZE as f64;
-        let floored = position.floor();
-
-        let prev_index = floored as usize;
-        let mut next_index = prev_index + 1;
-        if next_index == TABLE_LENGTH_USIZE {
-            next_index = 0;
-        }
-
-        // linear interpolation into lookup table
-        let k = (position - floored) as f32;
-        periodic_wave[prev_index].mul_add(1. - k, periodic_wave[next_index] * k)
-    }
-
-    // computes the `polyBLEP` corrections to apply to aliasing signal
-    // `polyBLEP` stands for `polyBandLimitedstEP`
-    // This basically soften the sharp edges in square and sawtooth signals
-    // to avoid infinite frequencies impulses (jumps from -1 to 1 or inverse).
-    // cf. http://www.martin-finke.de/blog/articles/audio-plugins-018-polyblep-oscillator/
-    //
-    // @note: do not apply in tests so we can avoid relying on snapshots
-    #[inline]
-    fn poly_blep(mut t: f64, dt: f64, is_test: bool) -> f64 {
-        if is_test {
-            0.
-        } else if t < dt {
-            t /= dt;
-            t + t - t * t - 1.0
-        } else if t > 1.0 - dt {
-            t = (t - 1.0) / dt;
-            t.mul_add(t, t) + t + 1.0
-        } else {
-            0.0
-        }
-    }
-
-    #[inline]
-    fn unroll_phase(mut phase: f64) -> f64 {
-        if phase >= 1. {
-            phase -= 1.
-        }
-
-        phase
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use float_eq::assert_float_eq;
-    use std::f64::consts::PI;
-
-    use crate::context::{BaseAudioContext, OfflineAudioContext};
-    use crate::node::{AudioNode, AudioScheduledSourceNode};
-    use crate::periodic_wave::{PeriodicWave, PeriodicWaveOptions};
-
-    use super::{OscillatorNode, OscillatorOptions, OscillatorRenderer, OscillatorType};
+    use super::{
+        partials_at, sum_partials, unroll_phase, Band, Oscillator, OscillatorNode,
+        OscillatorOptions, OscillatorType, MAX_SCHEDULED_TYPES, SAWTOOTH_TABLES, TABLES_PER_OCTAVE,
+        TABLE_LENGTH_USIZE, TRIANGLE_TABLES,
+    };
 
     #[test]
     fn assert_osc_default_build_with_factory_func() {
@@ -552,7 +1385,7 @@ mod tests {
         let det = osc.detune.value();
         assert_float_eq!(det, default_det, abs_all <= 0.);
 
-        let type_ = osc.type_.load(std::sync::atomic::Ordering::SeqCst);
+        let type_ = osc.type_.load(std::sync::atomic::Ordering::Relaxed);
         assert_eq!(type_, default_type as u32);
     }
 
@@ -572,10 +1405,41 @@ mod tests {
         let det = osc.detune.value();
         assert_float_eq!(det, default_det, abs_all <= 0.);
 
-        let type_ = osc.type_.load(std::sync::atomic::Ordering::SeqCst);
+        let type_ = osc.type_.load(std::sync::atomic::Ordering::Relaxed);
         assert_eq!(type_, default_type as u32);
     }
 
+    #[test]
+    fn type_round_trip() {
+        let types = [
+            (OscillatorType::Sine, "sine"),
+            (OscillatorType::Square, "square"),
+            (OscillatorType::Sawtooth, "sawtooth"),
+            (OscillatorType::Triangle, "triangle"),
+            (OscillatorType::Custom, "custom"),
+        ];
+
+        for &(type_, name) in types.iter() {
+            assert_eq!(type_.to_string(), name);
+            assert_eq!(name.parse::<OscillatorType>(), Ok(type_));
+            assert_eq!(OscillatorType::try_from(type_ as u32), Ok(type_));
+        }
+    }
+
+    #[test]
+    fn type_invalid() {
+        for name in ["", "Sine", "SQUARE", "saw", "sine ", "noise"] {
+            let error = name.parse::<OscillatorType>().unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!("invalid oscillator type: {}", name)
+            );
+        }
+
+        assert!(OscillatorType::try_from(5).is_err());
+        assert!(OscillatorType::try_from(u32::MAX).is_err());
+    }
+
     #[test]
     #[should_panic]
     fn set_type_to_custom_should_panic() {
@@ -599,7 +1463,7 @@ mod tests {
 
         let osc = OscillatorNode::new(&context, options);
 
-        let type_ = osc.type_.load(std::sync::atomic::Ordering::SeqCst);
+        let type_ = osc.type_.load(std::sync::atomic::Ordering::Relaxed);
         assert_eq!(type_, expected_type as u32);
     }
 
@@ -620,22 +1484,92 @@ mod tests {
 
         osc.set_type(OscillatorType::Sine);
 
-        let type_ = osc.type_.load(std::sync::atomic::Ordering::SeqCst);
+        let type_ = osc.type_.load(std::sync::atomic::Ordering::Relaxed);
         assert_eq!(type_, expected_type as u32);
     }
 
     // # Test waveforms
     //
-    // - for `square`, `triangle` and `sawtooth` the tests may appear a bit
-    //   tautological (and they actually are) as the code from the test is the
-    //   mostly as same as in the renderer, just written in a more compact way.
-    //   However they should help to prevent regressions, and/or allow testing
-    //   against trusted and simple implementation in case of future changes
-    //   in the renderer impl, e.g. performance improvements or spec compliance:
+    // - `square`, `triangle` and `sawtooth` are compared to their band-limited Fourier series,
+    //   with the partials weighted as the renderer interpolates between its tables:
     //   https://webaudio.github.io/web-audio-api/#oscillator-coefficients.
-    //
-    // - PolyBlep is not applied on `square` and `triangle` for tests, so we can
-    //   compare according to a crude waveforms
+
+    /// Band-limited Fourier series of a wave with the given `term(k, phase)`, as rendered by the
+    /// tables holding the partials of a wave of frequency `freq`
+    fn fourier_series(
+        freq: f32,
+        sample_rate: usize,
+        length: usize,
+        scale: f32,
+        dc: f64,
+        term: impl Fn(f64, f64) -> f64,
+    ) -> Vec<f32> {
+        let band = Band::new(freq, sample_rate as f32);
+        let partials = partials_at(band.index, TABLES_PER_OCTAVE);
+        let next_partials = partials_at(band.next, TABLES_PER_OCTAVE);
+
+        (0..length)
+            .map(|i| {
+                let phase = freq as f64 * i as f64 / sample_rate as f64;
+                let sample = (1..=partials).fold(dc, |sample, k| {
+                    let weight = if k <= next_partials {
+                        1.
+                    } else {
+                        1. - band.weight as f64
+                    };
+                    sample + weight * term(k as f64, phase)
+                });
+                (sample * scale as f64) as f32
+            })
+            .collect()
+    }
+
+    fn sawtooth_series(freq: f32, sample_rate: usize, length: usize) -> Vec<f32> {
+        let scale = SAWTOOTH_TABLES.scale;
+        fourier_series(freq, sample_rate, length, scale, 0., |k, phase| {
+            -2. * (-1_f64).powf(k) / (k * PI) * (2. * PI * k * phase).sin()
+        })
+    }
+
+    fn pulse_series(freq: f32, sample_rate: usize, length: usize, width: f64) -> Vec<f32> {
+        let scale = SAWTOOTH_TABLES.scale;
+        fourier_series(
+            freq,
+            sample_rate,
+            length,
+            scale,
+            2. * width - 1.,
+            |k, phase| {
+                2. / (k * PI)
+                    * ((2. * PI * k * phase).sin() - (2. * PI * k * (phase - width)).sin())
+            },
+        )
+    }
+
+    fn triangle_series(freq: f32, sample_rate: usize, length: usize) -> Vec<f32> {
+        let scale = TRIANGLE_TABLES.scale;
+        fourier_series(freq, sample_rate, length, scale, 0., |k, phase| {
+            8. * (k * PI / 2.).sin() / (k * PI).powi(2) * (2. * PI * k * phase).sin()
+        })
+    }
+
+    /// Render `length` samples of an oscillator of the given type
+    fn mono(samples: Vec<f32>, sample_rate: usize) -> AudioBuffer {
+        AudioBuffer::from(vec![samples], sample_rate as f32)
+    }
+
+    fn render(type_: OscillatorType, freq: f32, sample_rate: usize, length: usize) -> Vec<f32> {
+        let mut context = OfflineAudioContext::new(1, length, sample_rate as f32);
+
+        let osc = context.create_oscillator();
+        osc.connect(&context.destination());
+        osc.frequency().set_value(freq);
+        osc.set_type(type_);
+        osc.start_at(0.);
+
+        let output = context.start_rendering_sync();
+        output.get_channel_data(0).to_vec()
+    }
 
     #[test]
     fn sine_raw() {
@@ -652,7 +1586,6 @@ mod tests {
             osc.start_at(0.);
 
             let output = context.start_rendering_sync();
-            let result = output.get_channel_data(0);
 
             let mut expected = Vec::<f32>::with_capacity(sample_rate);
             let mut phase: f64 = 0.;
@@ -669,7 +1602,7 @@ mod tests {
                 }
             }
 
-            assert_float_eq!(result[..], expected[..], abs_all <= 1e-5);
+            assert_audio_eq(&output, &mono(expected, sample_rate), Tolerance::Abs(1e-7));
         }
     }
 
@@ -698,7 +1631,7 @@ mod tests {
                 expected.push(sample as f32);
             }
 
-            assert_float_eq!(result[..], expected[..], abs_all <= 1e-5);
+            assert_float_eq!(result[..], expected[..], abs_all <= 1e-7);
         }
     }
 
@@ -707,83 +1640,320 @@ mod tests {
         // 1, 10, 100, 1_000, 10_000 Hz
         for i in 0..5 {
             let freq = 10_f32.powf(i as f32);
-            let sample_rate = 44100;
+            let sample_rate = 44_100;
+            let length = RENDER_QUANTUM_SIZE * 8;
 
-            let mut context = OfflineAudioContext::new(1, sample_rate, sample_rate as f32);
+            let result = render(OscillatorType::Square, freq, sample_rate, length);
+            let expected = pulse_series(freq, sample_rate, length, 0.5);
+
+            // the linear interpolation of the tables slightly attenuates the highest partials,
+            // which add up at the edges of the low frequency waves
+            let result = mono(result, sample_rate);
+            assert_audio_eq(&result, &mono(expected, sample_rate), Tolerance::Abs(2e-2));
+        }
+    }
 
+    #[test]
+    fn square_pulse_width_raw() {
+        let freq = 100.;
+        let sample_rate = 48_000; // whole number of samples in a period
+
+        let mut context = OfflineAudioContext::new(1, sample_rate, sample_rate as f32);
+
+        let options = OscillatorOptions {
+            type_: OscillatorType::Square,
+            frequency: freq,
+            pulse_width: 0.25,
+            ..OscillatorOptions::default()
+        };
+        let osc = OscillatorNode::new(&context, options);
+        osc.connect(&context.destination());
+        osc.start_at(0.);
+
+        let output = context.start_rendering_sync();
+        let result = output.get_channel_data(0);
+
+        let expected = pulse_series(freq, sample_rate, RENDER_QUANTUM_SIZE * 8, 0.25);
+        assert_float_eq!(
+            result[..RENDER_QUANTUM_SIZE * 8],
+            expected[..],
+            abs_all <= 1e-3
+        );
+
+        // DC term of 2 * 0.25 - 1, over whole periods
+        let scale = f64::from(SAWTOOTH_TABLES.scale);
+        let mean = result.iter().map(|v| f64::from(*v)).sum::<f64>() / sample_rate as f64;
+        assert_float_eq!(mean, -0.5 * scale, abs <= 1e-3);
+
+        // even harmonics are present, unlike in the 50% square wave
+        let harmonic_level = |n: f64| {
+            let (re, im) = result
+                .iter()
+                .enumerate()
+                .fold((0., 0.), |(re, im), (i, v)| {
+                    let arg = 2. * PI * n * freq as f64 * i as f64 / sample_rate as f64;
+                    (
+                        re + f64::from(*v) * arg.cos(),
+                        im + f64::from(*v) * arg.sin(),
+                    )
+                });
+            2. * (re * re + im * im).sqrt() / sample_rate as f64
+        };
+        // amplitude of the n-th harmonic: 4 / (n pi) * |sin(n pi d)|
+        assert_float_eq!(harmonic_level(2.), scale * 4. / (2. * PI), abs <= 1e-2);
+        assert_float_eq!(harmonic_level(4.), 0., abs <= 1e-2);
+    }
+
+    #[test]
+    fn square_pulse_width_duty_cycle() {
+        // one period per render quantum
+        let sample_rate = 48_000.;
+        let frequency = sample_rate / RENDER_QUANTUM_SIZE as f32;
+
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 4, sample_rate);
+        let osc = context.create_oscillator();
+        osc.set_type(OscillatorType::Square);
+        osc.frequency().set_value(frequency);
+        osc.pulse_width().set_value(0.25);
+        osc.connect(&context.destination());
+        osc.start();
+
+        let output = context.start_rendering_sync();
+        for block in output.get_channel_data(0).chunks(RENDER_QUANTUM_SIZE) {
+            // high during a quarter of the period, up to the band-limited transitions
+            let high = block.iter().filter(|v| **v > 0.).count();
+            let expected = RENDER_QUANTUM_SIZE / 4;
+            assert!(high.abs_diff(expected) <= 1, "{} high samples", high);
+            assert!(block[4..expected - 4].iter().all(|v| *v > 0.5));
+            assert!(block[expected + 4..RENDER_QUANTUM_SIZE - 4]
+                .iter()
+                .all(|v| *v < -0.5));
+        }
+    }
+
+    #[test]
+    fn pulse_width_ignored_for_other_types() {
+        let render = |type_: OscillatorType, pulse_width: f32| {
+            let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 4, 44_100.);
             let osc = context.create_oscillator();
+            osc.set_type(type_);
+            osc.frequency().set_value(440.);
+            osc.pulse_width().set_value(pulse_width);
             osc.connect(&context.destination());
-            osc.frequency().set_value(freq);
-            osc.set_type(OscillatorType::Square);
-            osc.start_at(0.);
+            osc.start();
+            context.start_rendering_sync()
+        };
 
-            let output = context.start_rendering_sync();
-            let result = output.get_channel_data(0);
+        for &type_ in [
+            OscillatorType::Sine,
+            OscillatorType::Sawtooth,
+            OscillatorType::Triangle,
+        ]
+        .iter()
+        {
+            let reference = render(type_, 0.5);
+            let result = render(type_, 0.25);
+            assert_float_eq!(
+                result.get_channel_data(0),
+                reference.get_channel_data(0),
+                abs_all <= 0.
+            );
+        }
+    }
 
-            let mut expected = Vec::<f32>::with_capacity(sample_rate);
-            let mut phase: f64 = 0.;
-            let phase_incr = freq as f64 / sample_rate as f64;
+    #[test]
+    fn square_pulse_width_modulation() {
+        let sample_rate = 44_100;
+        let mut context = OfflineAudioContext::new(1, sample_rate, sample_rate as f32);
 
-            for _i in 0..sample_rate {
-                // 0.5 belongs to the second half of the waveform
-                let sample = if phase < 0.5 { 1. } else { -1. };
+        let osc = context.create_oscillator();
+        osc.set_type(OscillatorType::Square);
+        osc.frequency().set_value(1_000.);
+        osc.connect(&context.destination());
+        osc.start();
 
-                expected.push(sample as f32);
+        // sweep the duty cycle between 0.1 and 0.9, and beyond the bounds
+        let lfo = context.create_oscillator();
+        lfo.frequency().set_value(3.);
+        let depth = context.create_gain();
+        depth.gain().set_value(0.6);
+        lfo.connect(&depth);
+        depth.connect(osc.pulse_width());
+        lfo.start();
 
-                phase += phase_incr;
-                if phase >= 1. {
-                    phase -= 1.;
-                }
+        let output = context.start_rendering_sync();
+        let result = output.get_channel_data(0);
+        assert!(result.iter().all(|v| v.abs() <= 1.2));
+
+        // the band-limited pulse stays bounded whatever the width and frequency, the DC offset
+        // of a narrow pulse adds up with the few partials kept near the Nyquist frequency
+        for &phase_incr in [0.001, 0.01, 0.1, 0.45].iter() {
+            let band = Band::new(phase_incr as f32, 1.);
+            let mut phase = 0.;
+            for i in 0..10_000 {
+                let pulse_width = 0.5 + 0.6 * (i as f32 * 0.01).sin();
+                let sample = Oscillator::pulse(phase, band, pulse_width);
+                assert!(sample.is_finite() && sample.abs() <= 1.25);
+                phase = unroll_phase(phase + phase_incr);
             }
+        }
+    }
+
+    #[test]
+    fn computed_frequency_clamped_to_nyquist() {
+        let sample_rate = 44_100.;
+        let length = RENDER_QUANTUM_SIZE * 4;
+
+        let render = |frequency: f32, detune: f32, type_: OscillatorType| {
+            let mut context = OfflineAudioContext::new(1, length, sample_rate);
+            let osc = context.create_oscillator();
+            osc.set_type(type_);
+            osc.frequency().set_value(frequency);
+            osc.detune().set_value(detune);
+            osc.connect(&context.destination());
+            osc.start();
+            context.start_rendering_sync()
+        };
 
-            assert_float_eq!(result[..], expected[..], abs_all <= 1e-10);
+        for &type_ in [
+            OscillatorType::Sine,
+            OscillatorType::Square,
+            OscillatorType::Sawtooth,
+            OscillatorType::Triangle,
+        ]
+        .iter()
+        {
+            // 20 kHz * 2^4 = 320 kHz, far above the Nyquist frequency
+            let result = render(20_000., 4_800., type_);
+            let expected = render(sample_rate / 2., 0., type_);
+            assert_audio_eq(&result, &expected, Tolerance::Exact);
         }
     }
 
     #[test]
-    fn triangle_raw() {
-        // 1, 10, 100, 1_000, 10_000 Hz
-        for i in 0..5 {
-            let freq = 10_f32.powf(i as f32);
-            let sample_rate = 44_100;
+    fn unroll_phase_full_cycle() {
+        // an increment of exactly one cycle leaves the phase unchanged
+        for &phase in [0., 0.25, 0.5, 1. - f64::EPSILON].iter() {
+            let unrolled = unroll_phase(phase + 1.);
+            assert!((0. ..1.).contains(&unrolled));
+            assert_float_eq!(unrolled, phase, abs <= 1e-12);
+        }
 
-            let mut context = OfflineAudioContext::new(1, sample_rate, sample_rate as f32);
+        // the largest increment allowed by the clamped frequency
+        let mut phase = 0.;
+        for _ in 0..1_000 {
+            phase = unroll_phase(phase + 0.5);
+            assert!((0. ..1.).contains(&phase));
+        }
+    }
+
+    #[test]
+    fn unroll_phase_backwards() {
+        // a decrement of exactly one cycle leaves the phase unchanged
+        for &phase in [0., 0.25, 0.5, 1. - f64::EPSILON].iter() {
+            let unrolled = unroll_phase(phase - 1.);
+            assert!((0. ..1.).contains(&unrolled));
+            assert_float_eq!(unrolled, phase, abs <= 1e-12);
+        }
 
+        // a tiny negative phase wraps to 0 rather than 1
+        assert_eq!(unroll_phase(-1e-20), 0.);
+
+        // the largest decrement allowed by the clamped frequency
+        let mut phase = 0.;
+        for _ in 0..1_000 {
+            phase = unroll_phase(phase - 0.5);
+            assert!((0. ..1.).contains(&phase));
+        }
+    }
+
+    #[test]
+    fn negative_frequency() {
+        // a whole number of cycles, so the reversed wave starts at the same phase
+        let sample_rate = 44_100;
+        let length = sample_rate;
+
+        let render = |freq: f32, type_: OscillatorType| {
+            let mut context = OfflineAudioContext::new(1, length, sample_rate as f32);
             let osc = context.create_oscillator();
-            osc.connect(&context.destination());
+            if type_ == OscillatorType::Custom {
+                let options = PeriodicWaveOptions {
+                    real: Some(vec![0., 0.3, 0.1]),
+                    imag: Some(vec![0., 1., 0.5]),
+                    disable_normalization: false,
+                };
+                osc.set_periodic_wave(context.create_periodic_wave(options));
+            } else {
+                osc.set_type(type_);
+            }
             osc.frequency().set_value(freq);
-            osc.set_type(OscillatorType::Triangle);
-            osc.start_at(0.);
+            osc.connect(&context.destination());
+            osc.start();
 
-            let output = context.start_rendering_sync();
-            let result = output.get_channel_data(0);
+            context.start_rendering_sync().get_channel_data(0).to_vec()
+        };
 
-            let mut expected = Vec::<f32>::with_capacity(sample_rate);
-            let mut phase: f64 = 0.;
-            let phase_incr = freq as f64 / sample_rate as f64;
+        for type_ in [
+            OscillatorType::Sine,
+            OscillatorType::Square,
+            OscillatorType::Sawtooth,
+            OscillatorType::Triangle,
+            OscillatorType::Custom,
+        ] {
+            let forward = render(440., type_);
+            let backward = render(-440., type_);
+
+            // time-reversed around the first sample
+            let expected: Vec<f32> = (0..length)
+                .map(|i| forward[(length - i) % length])
+                .collect();
+            assert_float_eq!(backward[..], expected[..], abs_all <= 1e-4);
+        }
 
-            for _i in 0..sample_rate {
-                // triangle starts a 0.
-                // [0., 1.]  between [0, 0.25]
-                // [1., -1.] between [0.25, 0.75]
-                // [-1., 0.] between [0.75, 1]
-                let mut sample = -4. * phase + 2.;
-
-                if sample > 1. {
-                    sample = 2. - sample;
-                } else if sample < -1. {
-                    sample = -2. - sample;
-                }
+        // the morph oscillator reads the same wavetables
+        let render_morph = |freq: f32| {
+            let mut context = OfflineAudioContext::new(1, length, sample_rate as f32);
+            let wave = |imag: Vec<f32>| {
+                let options = PeriodicWaveOptions {
+                    real: Some(vec![0.; imag.len()]),
+                    imag: Some(imag),
+                    disable_normalization: false,
+                };
+                context.create_periodic_wave(options)
+            };
+            let osc =
+                context.create_morph_oscillator(wave(vec![0., 1., 0.5]), wave(vec![0., 0., 1.]));
+            osc.frequency().set_value(freq);
+            osc.morph().set_value(0.3);
+            osc.connect(&context.destination());
+            osc.start();
 
-                expected.push(sample as f32);
+            context.start_rendering_sync().get_channel_data(0).to_vec()
+        };
 
-                phase += phase_incr;
-                if phase >= 1. {
-                    phase -= 1.;
-                }
-            }
+        let forward = render_morph(440.);
+        let backward = render_morph(-440.);
+        let expected: Vec<f32> = (0..length)
+            .map(|i| forward[(length - i) % length])
+            .collect();
+        assert_float_eq!(backward[..], expected[..], abs_all <= 1e-4);
+    }
 
-            assert_float_eq!(result[..], expected[..], abs_all <= 1e-10);
+    #[test]
+    fn triangle_raw() {
+        // 1, 10, 100, 1_000, 10_000 Hz
+        for i in 0..5 {
+            let freq = 10_f32.powf(i as f32);
+            let sample_rate = 44_100;
+            let length = RENDER_QUANTUM_SIZE * 8;
+
+            let result = mono(
+                render(OscillatorType::Triangle, freq, sample_rate, length),
+                sample_rate,
+            );
+            let expected = mono(triangle_series(freq, sample_rate, length), sample_rate);
+
+            assert_audio_eq(&result, &expected, Tolerance::Abs(1e-7));
         }
     }
 
@@ -793,41 +1963,156 @@ mod tests {
         for i in 0..5 {
             let freq = 10_f32.powf(i as f32);
             let sample_rate = 44_100;
+            let length = RENDER_QUANTUM_SIZE * 8;
 
-            let mut context = OfflineAudioContext::new(1, sample_rate, sample_rate as f32);
+            let result = mono(
+                render(OscillatorType::Sawtooth, freq, sample_rate, length),
+                sample_rate,
+            );
+            let expected = mono(sawtooth_series(freq, sample_rate, length), sample_rate);
 
-            let osc = context.create_oscillator();
-            osc.connect(&context.destination());
-            osc.frequency().set_value(freq);
-            osc.set_type(OscillatorType::Sawtooth);
-            osc.start_at(0.);
+            assert_audio_eq(&result, &expected, Tolerance::Abs(1e-3));
+        }
+    }
 
-            let output = context.start_rendering_sync();
-            let result = output.get_channel_data(0);
+    #[test]
+    fn sawtooth_spectrum() {
+        // the spectrum does not depend on the phase of the partials
+        let freq = 1_000.;
+        let sample_rate = 48_000;
+        let length = 4_096;
+
+        let result = mono(
+            render(OscillatorType::Sawtooth, freq, sample_rate, length),
+            sample_rate,
+        );
+        let expected = mono(sawtooth_series(freq, sample_rate, length), sample_rate);
 
-            let mut expected = Vec::<f32>::with_capacity(sample_rate);
-            let mut phase: f64 = 0.;
-            let phase_incr = freq as f64 / sample_rate as f64;
+        assert_audio_eq(&result, &expected, Tolerance::Spectral { db_floor: -80. });
+    }
 
-            for _i in 0..sample_rate {
-                // triangle starts a 0.
-                // [0, 1] between [0, 0.5]
-                // [-1, 0] between [0.5, 1]
-                let mut offset_phase = phase + 0.5;
-                if offset_phase >= 1. {
-                    offset_phase -= 1.;
-                }
-                let sample = 2. * offset_phase - 1.;
+    // The fixture is recorded with the cubic interpolation of the wavetables. The recording with
+    // the former linear interpolation differs by up to 5.6e-5 (-85 dBFS, 6e-6 RMS), above the
+    // tolerance: the fixture must be recorded again, with `UPDATE_FIXTURES`, when the
+    // interpolation changes.
+    #[test]
+    fn pulse_width_snapshot() {
+        let sample_rate = 48_000;
+        let length = RENDER_QUANTUM_SIZE * 32;
 
-                expected.push(sample as f32);
+        let mut context = OfflineAudioContext::new(1, length, sample_rate as f32);
+        let osc = context.create_oscillator();
+        osc.set_type(OscillatorType::Square);
+        osc.frequency().set_value(300.);
+        osc.pulse_width().set_value(0.2);
+        osc.pulse_width().linear_ramp_to_value_at_time(0.8, 0.08);
+        osc.connect(&context.destination());
+        osc.start();
 
-                phase += phase_incr;
-                if phase >= 1. {
-                    phase -= 1.;
-                }
-            }
+        let output = context.start_rendering_sync();
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/snapshots/square_pulse_width.bin"
+        );
+        assert_fixture_eq(&output, path, 4, Tolerance::Abs(1e-7));
+    }
+
+    #[test]
+    fn sum_partials_matches_fft() {
+        let reals = [0., 0.3, 0., -0.2, 0.1];
+        let imags = [0., 1., 0.5, 0., -0.25];
+
+        let result = sum_partials(&reals, &imags);
+        let expected = PeriodicWave::synthesize_wavetable(&reals, &imags, TABLE_LENGTH_USIZE);
 
-            assert_float_eq!(result[..], expected[..], abs_all <= 1e-10);
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-12);
+    }
+
+    #[test]
+    fn oscillator_kernel() {
+        let sample_rate = 48_000;
+        let length = RENDER_QUANTUM_SIZE * 4;
+
+        for type_ in [
+            OscillatorType::Sine,
+            OscillatorType::Square,
+            OscillatorType::Sawtooth,
+            OscillatorType::Triangle,
+        ] {
+            let expected = render(type_, 440., sample_rate, length);
+
+            let mut oscillator = Oscillator::new();
+            let result: Vec<f32> = (0..length)
+                .map(|_| oscillator.next_sample(type_, 440., 0.5, sample_rate as f32))
+                .collect();
+
+            assert_float_eq!(result[..], expected[..], abs_all <= 0.);
+        }
+    }
+
+    #[test]
+    fn band_selection() {
+        let sample_rate = 48_000.;
+
+        // all the partials below the Nyquist frequency fit in the table 9, the following
+        // tables are used
+        assert_eq!(partials_at(9, TABLES_PER_OCTAVE), 128);
+        let freq = sample_rate / 2. / 128.;
+        let band = Band::new(freq, sample_rate);
+        assert_eq!((band.index, band.next), (10, 11));
+        assert_float_eq!(band.weight, 0., abs <= 1e-5);
+
+        // half way between two tables, on a log scale
+        let band = Band::new(freq * 2_f32.powf(1. / 6.), sample_rate);
+        assert_eq!((band.index, band.next), (10, 11));
+        assert_float_eq!(band.weight, 0.5, abs <= 1e-5);
+
+        // negative frequencies are band-limited as well
+        assert_eq!(Band::new(-freq, sample_rate), Band::new(freq, sample_rate));
+
+        // clamped to the first and last tables
+        assert_eq!(Band::new(0., sample_rate), Band::new(1., sample_rate));
+        assert_eq!(Band::new(1., sample_rate).index, 0);
+        let band = Band::new(sample_rate, sample_rate);
+        assert_eq!(band.index, band.next);
+
+        // the tables never hold partials above the Nyquist frequency
+        for i in 0..2000 {
+            let freq = 20. * 1.003_f32.powi(i);
+            let band = Band::new(freq, sample_rate);
+            assert!(partials_at(band.index, TABLES_PER_OCTAVE) as f32 * freq <= sample_rate / 2.);
+        }
+    }
+
+    #[test]
+    fn no_aliasing() {
+        let sample_rate = 44_100;
+        let freq = 5_000.;
+
+        // level of the given frequency, over a whole number of periods of the wave
+        let level = |signal: &[f32], freq: f64| {
+            let length = 44_100;
+            let (re, im) =
+                signal[..length]
+                    .iter()
+                    .enumerate()
+                    .fold((0., 0.), |(re, im), (i, v)| {
+                        let arg = 2. * PI * freq * i as f64 / sample_rate as f64;
+                        (
+                            re + f64::from(*v) * arg.cos(),
+                            im + f64::from(*v) * arg.sin(),
+                        )
+                    });
+            2. * (re * re + im * im).sqrt() / length as f64
+        };
+
+        for type_ in [OscillatorType::Sawtooth, OscillatorType::Square] {
+            let result = render(type_, freq, sample_rate, sample_rate);
+
+            // the third harmonic is below the Nyquist frequency
+            assert!(level(&result, 3. * freq as f64) > 0.1);
+            // the fifth harmonic would alias to 19.1 kHz
+            assert!(level(&result, sample_rate as f64 - 5. * freq as f64) < 1e-4);
         }
     }
 
@@ -874,14 +2159,15 @@ mod tests {
                 }
             }
 
-            assert_float_eq!(result[..], expected[..], abs_all <= 1e-5);
+            assert_float_eq!(result[..], expected[..], abs_all <= 1e-7);
         }
     }
 
     #[test]
     fn periodic_wave_2f() {
-        // 1, 10, 100, 1_000, 10_000 Hz
-        for i in 0..5 {
+        // 1, 10, 100, 1_000 Hz, the partials of a custom wave fade out in the octave below
+        // the Nyquist frequency, see `periodic_wave_sweep`
+        for i in 0..4 {
             let freq = 10_f32.powf(i as f32);
             let sample_rate = 44_100;
 
@@ -923,47 +2209,225 @@ mod tests {
                 }
             }
 
-            assert_float_eq!(result[..], expected[..], abs_all <= 1e-5);
+            assert_float_eq!(result[..], expected[..], abs_all <= 1e-7);
         }
     }
 
-    #[test]
-    fn polyblep_isolated() {
-        // @note: Only first branch of the polyblep seems to be used here.
-        // May be due on the simplicity of the test itself where everything is
-        // well aligned.
+    /// Exponential sweep of a custom wave, from 20 Hz to 10 kHz over 5 seconds
+    const SWEEP_SAMPLE_RATE: usize = 44_100;
+    const SWEEP_DURATION: f64 = 5.;
 
-        // square
-        {
-            let mut signal = [1., 1., 1., 1., -1., -1., -1., -1.];
-            let len = signal.len() as f64;
-            let dt = 1. / len;
+    fn sweep_frequency(time: f64) -> f64 {
+        20. * 500_f64.powf(time / SWEEP_DURATION)
+    }
 
-            for (index, s) in signal.iter_mut().enumerate() {
-                let phase = index as f64 / len;
+    /// Custom wave with 1024 partials of equal amplitude, its flat spectrum magnifies the
+    /// partials lost or gained when the tables change
+    fn sweep_wave(context: &impl BaseAudioContext) -> PeriodicWave {
+        let imag = (0..=1024).map(|k| if k == 0 { 0. } else { 1. }).collect();
+        context.create_periodic_wave(PeriodicWaveOptions {
+            real: Some(vec![0.; 1025]),
+            imag: Some(imag),
+            disable_normalization: false,
+        })
+    }
 
-                *s += OscillatorRenderer::poly_blep(phase, dt, false);
-                *s -= OscillatorRenderer::poly_blep((phase + 0.5) % 1., dt, false);
+    /// Render the sweep with the given lookup of the wave, from the frequency and the phase
+    fn simulate_sweep(lookup: impl Fn(f32, f64) -> f32) -> Vec<f32> {
+        let length = (SWEEP_DURATION * SWEEP_SAMPLE_RATE as f64) as usize;
+        let mut phase = 0.;
+        (0..length)
+            .map(|i| {
+                let freq = sweep_frequency(i as f64 / SWEEP_SAMPLE_RATE as f64);
+                let sample = lookup(freq as f32, phase);
+                phase = (phase + freq / SWEEP_SAMPLE_RATE as f64).fract();
+                sample
+            })
+            .collect()
+    }
+
+    /// Highest level of the aliasing, in dB relative to the frame, and highest relative change
+    /// of the spectral centroid from one frame to the next
+    fn sweep_analysis(signal: &[f32]) -> (f64, f64) {
+        let size = 2048;
+        let hop = 1024;
+        let sample_rate = SWEEP_SAMPLE_RATE as f64;
+
+        // Blackman-Harris window, low side lobes
+        let window: Vec<f32> = (0..size)
+            .map(|i| {
+                let x = 2. * PI * i as f64 / size as f64;
+                (0.35875 - 0.48829 * x.cos() + 0.14128 * (2. * x).cos() - 0.01168 * (3. * x).cos())
+                    as f32
+            })
+            .collect();
+
+        let fft = realfft::RealFftPlanner::<f32>::new().plan_fft_forward(size);
+        let mut input = fft.make_input_vec();
+        let mut spectrum = fft.make_output_vec();
+
+        let mut aliasing = f64::NEG_INFINITY;
+        let mut centroid_jump: f64 = 0.;
+        let mut prev_centroid: Option<f64> = None;
+
+        for start in (0..signal.len() - size).step_by(hop) {
+            input
+                .iter_mut()
+                .zip(&signal[start..start + size])
+                .zip(&window)
+                .for_each(|((i, s), w)| *i = s * w);
+            fft.process(&mut input, &mut spectrum).unwrap();
+
+            let power: Vec<f64> = spectrum.iter().map(|c| f64::from(c.norm_sqr())).collect();
+            let bin_width = sample_rate / size as f64;
+            let total: f64 = power.iter().sum();
+
+            // the frames hold several periods of the wave, from 200 Hz
+            let f0 = sweep_frequency(start as f64 / sample_rate);
+            if f0 >= 200. {
+                let centroid = power
+                    .iter()
+                    .enumerate()
+                    .map(|(bin, p)| bin as f64 * p)
+                    .sum::<f64>()
+                    / total;
+                if let Some(prev) = prev_centroid {
+                    centroid_jump = centroid_jump.max((centroid / prev - 1.).abs());
+                }
+                prev_centroid = Some(centroid);
             }
 
-            let expected = [0., 1., 1., 1., 0., -1., -1., -1.];
+            // no partial below half the fundamental, past the main lobe of the DC bin
+            if f0 >= 400. {
+                let below = (f0 / 2. / bin_width) as usize;
+                let level = power[4..below].iter().sum::<f64>() / total;
+                aliasing = aliasing.max(10. * level.log10());
+            }
+        }
+
+        (aliasing, centroid_jump)
+    }
+
+    #[test]
+    fn periodic_wave_sweep() {
+        let length = (SWEEP_DURATION * SWEEP_SAMPLE_RATE as f64) as usize;
+        let mut context = OfflineAudioContext::new(1, length, SWEEP_SAMPLE_RATE as f32);
+
+        let osc = context.create_oscillator();
+        osc.set_periodic_wave(sweep_wave(&context));
+        osc.frequency().set_value_at_time(20., 0.);
+        osc.frequency()
+            .exponential_ramp_to_value_at_time(10_000., SWEEP_DURATION);
+        osc.connect(&context.destination());
+        osc.start();
+
+        let output = context.start_rendering_sync();
+        let (aliasing, centroid_jump) = sweep_analysis(output.get_channel_data(0));
+        assert!(aliasing < -60., "aliasing at {} dB", aliasing);
+        assert!(centroid_jump < 0.1, "centroid jump of {}", centroid_jump);
+
+        // the full wavetable aliases
+        let wave = sweep_wave(&context);
+        let full = simulate_sweep(|_, phase| super::wavetable_lookup(wave.as_slice(), phase));
+        let (aliasing, _) = sweep_analysis(&full);
+        assert!(aliasing > -60., "aliasing at {} dB", aliasing);
+
+        // switching from one table to the next without crossfading makes the partials pop in
+        // and out
+        let tables = wave.band_limited();
+        let discrete = simulate_sweep(|freq, phase| {
+            let mut band = tables.band(freq, SWEEP_SAMPLE_RATE as f32);
+            band.weight = 0.;
+            tables.lookup(band, phase)
+        });
+        let (_, centroid_jump) = sweep_analysis(&discrete);
+        assert!(centroid_jump > 0.1, "centroid jump of {}", centroid_jump);
+    }
 
-            assert_float_eq!(signal[..], expected[..], abs_all <= 0.);
+    #[test]
+    fn custom_type_without_periodic_wave() {
+        let sample_rate = 44_100;
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, sample_rate as f32);
+
+        // the renderer sees the custom type before (here without) any wave
+        let options = OscillatorOptions {
+            type_: OscillatorType::Custom,
+            ..OscillatorOptions::default()
+        };
+        let osc = OscillatorNode::new(&context, options);
+        osc.connect(&context.destination());
+        osc.start();
+
+        let output = context.start_rendering_sync();
+        let result = output.get_channel_data(0);
+
+        // falls back to a sine
+        let phase_incr = 440. / sample_rate as f64;
+        let expected: Vec<f32> = (0..RENDER_QUANTUM_SIZE)
+            .map(|i| (i as f64 * phase_incr * 2. * PI).sin() as f32)
+            .collect();
+
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-7);
+    }
+
+    #[test]
+    fn periodic_wave_set_many_times() {
+        let sample_rate = 44_100;
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, sample_rate as f32);
+
+        let osc = context.create_oscillator();
+        osc.connect(&context.destination());
+        osc.start();
+
+        // the renderer does not pick up the waves before rendering, this must not block
+        for i in 0..1000 {
+            let options = PeriodicWaveOptions {
+                real: Some(vec![0., 0.]),
+                imag: Some(vec![0., if i == 999 { -1. } else { 1. }]),
+                disable_normalization: true,
+            };
+            osc.set_periodic_wave(context.create_periodic_wave(options));
         }
 
-        // sawtooth
-        {
-            let mut signal = [0., 0.25, 0.75, 1., -1., -0.75, -0.5, -0.25];
-            let len = signal.len() as f64;
-            let dt = 1. / len;
+        let output = context.start_rendering_sync();
+        let result = output.get_channel_data(0);
+
+        // the last wave is rendered, an inverted sine
+        let phase_incr = 440. / sample_rate as f64;
+        let expected: Vec<f32> = (0..RENDER_QUANTUM_SIZE)
+            .map(|i| -(i as f64 * phase_incr * 2. * PI).sin() as f32)
+            .collect();
 
-            for (index, s) in signal.iter_mut().enumerate() {
-                let phase = index as f64 / len;
-                *s -= OscillatorRenderer::poly_blep((phase + 0.5) % 1., dt, false);
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-7);
+    }
+
+    #[test]
+    fn periodic_wave_replaced_between_quanta() {
+        let sample_rate = 44_100.;
+        let (context, mut handle) = ManualAudioContext::new(1, sample_rate);
+
+        let osc = context.create_oscillator();
+        osc.connect(&context.destination());
+        osc.frequency().set_value(sample_rate / 4.);
+        osc.start();
+
+        // each quantum renders the last wave set before it
+        let mut output = vec![0.; RENDER_QUANTUM_SIZE];
+        for sign in &[1., -1., 1.] {
+            for i in 0..10 {
+                let amplitude = if i == 9 { *sign } else { 0.5 };
+                let options = PeriodicWaveOptions {
+                    real: Some(vec![0., 0.]),
+                    imag: Some(vec![0., amplitude]),
+                    disable_normalization: true,
+                };
+                osc.set_periodic_wave(context.create_periodic_wave(options));
             }
 
-            let expected = [0., 0.25, 0.75, 1., 0., -0.75, -0.5, -0.25];
-            assert_float_eq!(signal[..], expected[..], abs_all <= 0.);
+            handle.render(&mut output);
+
+            // a quarter of a cycle per sample, the second frame of each quantum is the peak
+            assert_float_eq!(output[1], *sign, abs <= 1e-7);
         }
     }
 
@@ -994,7 +2458,7 @@ mod tests {
             expected.push(sample as f32);
         }
 
-        assert_float_eq!(result[..], expected[..], abs_all <= 1e-5);
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-7);
     }
 
     // # Test scheduling
@@ -1028,7 +2492,7 @@ mod tests {
             expected.push(sample as f32);
         }
 
-        assert_float_eq!(result[..], expected[..], abs_all <= 1e-5);
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-7);
     }
 
     #[test]
@@ -1060,7 +2524,7 @@ mod tests {
             }
         }
 
-        assert_float_eq!(result[..], expected[..], abs_all <= 1e-5);
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-7);
     }
 
     #[test]
@@ -1092,7 +2556,92 @@ mod tests {
             }
         }
 
-        assert_float_eq!(result[..], expected[..], abs_all <= 1e-5);
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-7);
+    }
+
+    /// Render a sine stopped at `stop`, with or without declicking
+    fn render_stop(freq: f32, stop: f64, declick: bool) -> Vec<f32> {
+        let sample_rate = 48_000.;
+        let mut context = OfflineAudioContext::new(1, 6_000, sample_rate);
+
+        let options = OscillatorOptions {
+            frequency: freq,
+            declick,
+            ..OscillatorOptions::default()
+        };
+        let osc = OscillatorNode::new(&context, options);
+        osc.connect(&context.destination());
+        osc.start_at(0.);
+        osc.stop_at(stop);
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn declick_option() {
+        let context = OfflineAudioContext::new(1, 128, 48_000.);
+
+        let osc = context.create_oscillator();
+        assert!(!osc.declick());
+
+        let options = OscillatorOptions {
+            declick: true,
+            ..OscillatorOptions::default()
+        };
+        let osc = OscillatorNode::new(&context, options);
+        assert!(osc.declick());
+        osc.set_declick(false);
+        assert!(!osc.declick());
+    }
+
+    #[test]
+    fn declick_linear_fade() {
+        // stopped at a peak of the sine, the zero crossings are out of the fade
+        let stop = 0.105;
+        let stop_frame = 5_040;
+        let fade_frames = 144; // 3 ms
+
+        let hard = render_stop(50., stop, false);
+        let faded = render_stop(50., stop, true);
+
+        let fade_start = stop_frame - fade_frames;
+        assert_float_eq!(faded[..fade_start], hard[..fade_start], abs_all <= 0.);
+
+        for i in fade_start..stop_frame {
+            let gain = (stop_frame - i) as f32 / fade_frames as f32;
+            assert_float_eq!(faded[i], hard[i] * gain, abs <= 1e-6);
+        }
+
+        // down to zero at the stop sample
+        assert!(faded[stop_frame - 1].abs() <= 1. / fade_frames as f32);
+        assert!(faded[stop_frame..].iter().all(|&v| v == 0.));
+        assert!(hard[stop_frame - 1] > 0.99);
+    }
+
+    #[test]
+    fn declick_cut_at_zero_crossing() {
+        // stopped at a peak of the sine, the sine crosses zero 3 times in the fade
+        let stop = 0.100_25;
+        let stop_frame = 4_812;
+        let fade_frames = 144; // 3 ms
+
+        let hard = render_stop(1_000., stop, false);
+        let faded = render_stop(1_000., stop, true);
+
+        let fade_start = stop_frame - fade_frames;
+        assert_float_eq!(faded[..fade_start], hard[..fade_start], abs_all <= 0.);
+
+        // first zero crossing of the fade
+        let crossing = (fade_start..stop_frame)
+            .find(|&i| hard[i] * hard[i - 1] < 0.)
+            .unwrap();
+        assert!(crossing < stop_frame - 48);
+
+        for i in fade_start..crossing {
+            let gain = (stop_frame - i) as f32 / fade_frames as f32;
+            assert_float_eq!(faded[i], hard[i] * gain, abs <= 1e-6);
+        }
+        assert!(faded[crossing..].iter().all(|&v| v == 0.));
     }
 
     #[test]
@@ -1119,6 +2668,381 @@ mod tests {
             phase += phase_incr;
         }
 
-        assert_float_eq!(result[..], expected[..], abs_all <= 1e-5);
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-7);
+    }
+
+    #[test]
+    fn test_sine_peak_frequency() {
+        let sample_rate = 44_100.;
+        let mut context = OfflineAudioContext::new(1, sample_rate as usize, sample_rate);
+
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(440.);
+        osc.connect(&context.destination());
+        osc.start();
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(output.peak_frequency(0, sample_rate), 440., abs <= 1.);
+    }
+
+    #[test]
+    fn test_playback_state() {
+        // one render quantum lasts 10 ms
+        let sample_rate = 12_800.;
+        let quantum = RENDER_QUANTUM_SIZE as f64 / sample_rate as f64;
+
+        // plays from the middle of quantum 1 to the middle of quantum 3
+        let state_after = |quanta: usize| {
+            let mut context =
+                OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * quanta, sample_rate);
+            let osc = context.create_oscillator();
+            osc.connect(&context.destination());
+            assert_eq!(osc.playback_state(), PlaybackState::Unscheduled);
+            osc.start_at(1.5 * quantum);
+            osc.stop_at(3.5 * quantum);
+            assert_eq!(osc.playback_state(), PlaybackState::Scheduled);
+
+            let _ = context.start_rendering_sync();
+            osc.playback_state()
+        };
+
+        assert_eq!(state_after(1), PlaybackState::Scheduled);
+        assert_eq!(state_after(2), PlaybackState::Playing);
+        assert_eq!(state_after(4), PlaybackState::Playing);
+        assert_eq!(state_after(5), PlaybackState::Finished);
+        assert_eq!(state_after(6), PlaybackState::Finished);
+    }
+
+    #[test]
+    fn test_onended() {
+        let sample_rate = 48_000.;
+        let mut context = OfflineAudioContext::new(1, 48_000, sample_rate);
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let osc = context.create_oscillator();
+        osc.connect(&context.destination());
+        osc.start_at(0.1);
+        osc.stop_at(0.5);
+        osc.set_onended(move |time| sender.send(time).unwrap());
+
+        let _ = context.start_rendering_sync();
+
+        let timeout = std::time::Duration::from_secs(1);
+        assert_eq!(receiver.recv_timeout(timeout), Ok(0.5));
+        // called only once
+        assert!(receiver.recv_timeout(timeout).is_err());
+    }
+
+    #[test]
+    fn test_onended_many_sources() {
+        let sample_rate = 48_000.;
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 16, sample_rate);
+
+        // more sources ending in the same quantum than the queue holds
+        let count = 500;
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        for i in 0..count {
+            let osc = context.create_oscillator();
+            osc.connect(&context.destination());
+            osc.start();
+            osc.stop_at(0.001);
+            let sender = sender.clone();
+            osc.set_onended(move |time| sender.send((i, time)).unwrap());
+        }
+        drop(sender);
+
+        let _ = context.start_rendering_sync();
+
+        let timeout = std::time::Duration::from_secs(1);
+        let mut ended: Vec<_> = (0..count)
+            .map(|_| receiver.recv_timeout(timeout).unwrap())
+            .collect();
+        ended.sort_by_key(|&(i, _)| i);
+        assert!(ended.iter().enumerate().all(|(j, &(i, _))| i == j));
+        assert!(ended.iter().all(|&(_, time)| time == 0.001));
+    }
+
+    #[test]
+    fn test_onended_not_stopped() {
+        let mut context = OfflineAudioContext::new(1, 48_000, 48_000.);
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let osc = context.create_oscillator();
+        osc.connect(&context.destination());
+        osc.start();
+        osc.set_onended(move |time| sender.send(time).unwrap());
+
+        let _ = context.start_rendering_sync();
+        drop(osc);
+        drop(context);
+
+        let timeout = std::time::Duration::from_secs(1);
+        assert!(receiver.recv_timeout(timeout).is_err());
+    }
+
+    #[test]
+    fn test_set_type_at() {
+        let sample_rate = 48_000.;
+        let length = RENDER_QUANTUM_SIZE * 4;
+        // 100 samples per period
+        let frequency = 480.;
+        let when = 250. / sample_rate as f64;
+
+        let render = |types: &[(OscillatorType, Option<f64>)]| {
+            let mut context = OfflineAudioContext::new(1, length, sample_rate);
+            let osc = context.create_oscillator();
+            osc.frequency().set_value(frequency);
+            for &(type_, when) in types {
+                match when {
+                    Some(when) => osc.set_type_at(type_, when),
+                    None => osc.set_type(type_),
+                }
+            }
+            osc.connect(&context.destination());
+            osc.start();
+
+            let output = context.start_rendering_sync();
+            (output.get_channel_data(0).to_vec(), osc.type_())
+        };
+
+        let (sine, _) = render(&[]);
+        let (square, _) = render(&[(OscillatorType::Square, None)]);
+        let (result, type_) = render(&[(OscillatorType::Square, Some(when))]);
+        assert_eq!(type_, OscillatorType::Square);
+
+        // the switch happens at the start of the cycle following `when`
+        let switch = result.iter().zip(&sine).position(|(r, s)| r != s).unwrap();
+        assert!((300..=301).contains(&switch), "{}", switch);
+        assert_float_eq!(result[..switch], sine[..switch], abs_all <= 0.);
+        assert_float_eq!(result[switch..], square[switch..], abs_all <= 0.);
+        // where both waveforms are zero
+        assert_float_eq!(sine[switch], 0., abs <= 0.05);
+        assert_float_eq!(square[switch], 0., abs <= 0.05);
+    }
+
+    #[test]
+    fn test_set_type_at_sorted() {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 4, 48_000.);
+        let osc = context.create_oscillator();
+        // scheduled out of order, both switches are past by the end of the rendering
+        osc.set_type_at(OscillatorType::Triangle, 0.005);
+        osc.set_type_at(OscillatorType::Sawtooth, 0.001);
+        osc.connect(&context.destination());
+        osc.start();
+
+        let _ = context.start_rendering_sync();
+        assert_eq!(osc.type_(), OscillatorType::Triangle);
+    }
+
+    #[test]
+    fn test_set_type_at_pending_limit() {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 32, 48_000.);
+        let osc = context.create_oscillator();
+        for _ in 0..MAX_SCHEDULED_TYPES {
+            osc.set_type_at(OscillatorType::Square, 0.);
+        }
+        // dropped on the control side, the renderer queue is full
+        osc.set_type_at(OscillatorType::Triangle, 0.);
+        assert_eq!(
+            osc.pending_types.load(Ordering::Relaxed),
+            MAX_SCHEDULED_TYPES
+        );
+        osc.connect(&context.destination());
+        osc.start();
+
+        // one change is applied per cycle
+        let _ = context.start_rendering_sync();
+        assert_eq!(osc.type_(), OscillatorType::Square);
+        assert_eq!(osc.pending_types.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_set_type_at_invalid_times() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let osc = context.create_oscillator();
+
+        for &when in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY].iter() {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                osc.set_type_at(OscillatorType::Square, when);
+            }));
+            assert!(
+                result.is_err(),
+                "type change at {} should be rejected",
+                when
+            );
+        }
+        // the rejected calls are not queued
+        assert_eq!(osc.pending_types.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_set_type_at_negative_time() {
+        let render = |when: f64| {
+            let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 2, 48_000.);
+            let osc = context.create_oscillator();
+            osc.set_type_at(OscillatorType::Square, when);
+            osc.connect(&context.destination());
+            osc.start();
+
+            let output = context.start_rendering_sync();
+            assert_eq!(osc.type_(), OscillatorType::Square);
+            output.get_channel_data(0).to_vec()
+        };
+
+        // in the past, applied like a change at time zero
+        assert_float_eq!(render(-1.)[..], render(0.)[..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_set_type_at_custom() {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 4, 48_000.);
+        let osc = context.create_oscillator();
+        osc.set_type_at(OscillatorType::Square, 0.);
+        let options = PeriodicWaveOptions {
+            real: Some(vec![0., 1.]),
+            imag: Some(vec![0., 0.]),
+            disable_normalization: true,
+        };
+        osc.set_periodic_wave(PeriodicWave::new(&context, options));
+        osc.connect(&context.destination());
+        osc.start();
+
+        // the periodic wave is set after the scheduled change, it takes precedence
+        let _ = context.start_rendering_sync();
+        assert_eq!(osc.type_(), OscillatorType::Custom);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError - cannot call `start` twice")]
+    fn test_start_twice() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let osc = context.create_oscillator();
+        osc.start();
+        osc.start_at(5.);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError - cannot call `stop` twice")]
+    fn test_stop_twice() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let osc = context.create_oscillator();
+        osc.start();
+        osc.stop_at(1.);
+        osc.stop();
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError - cannot call `stop` before `start`")]
+    fn test_stop_before_start() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let osc = context.create_oscillator();
+        osc.stop();
+    }
+
+    #[test]
+    fn test_invalid_start_stop_times() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+        for &when in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY].iter() {
+            let osc = context.create_oscillator();
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                osc.start_at(when);
+            }));
+            assert!(result.is_err(), "start at {} should be rejected", when);
+            // the rejected call does not count as a start
+            assert_eq!(osc.scheduled_start_time(), None);
+
+            osc.start();
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                osc.stop_at(when);
+            }));
+            assert!(result.is_err(), "stop at {} should be rejected", when);
+            assert_eq!(osc.scheduled_stop_time(), None);
+        }
+    }
+
+    #[test]
+    fn test_negative_start_stop_times() {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let osc = context.create_oscillator();
+        osc.connect(&context.destination());
+        osc.start_at(-1.);
+        osc.stop_at(-0.5);
+        assert_eq!(osc.scheduled_start_time(), Some(0.));
+        assert_eq!(osc.scheduled_stop_time(), Some(0.));
+
+        let output = context.start_rendering_sync();
+        assert!(output.get_channel_data(0).iter().all(|v| *v == 0.));
+    }
+
+    #[test]
+    fn test_channel_count_does_not_change_output() {
+        let mut context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 44_100.);
+        let options = OscillatorOptions {
+            channel_config: ChannelConfigOptions {
+                count: 4,
+                mode: ChannelCountMode::Explicit,
+                ..ChannelConfigOptions::default()
+            },
+            ..OscillatorOptions::default()
+        };
+        let osc = OscillatorNode::new(&context, options);
+        osc.connect(&context.destination());
+        osc.start();
+        assert_eq!(osc.channel_count(), 4);
+
+        let index = NodeIndex::from(osc.registration().id());
+        let _ = context.start_rendering_sync();
+        let output = context.renderer().graph().node_output(index, 0).unwrap();
+        assert_eq!(output.number_of_channels(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_invalid_channel_count() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let options = OscillatorOptions {
+            channel_config: ChannelConfigOptions {
+                count: 0,
+                ..ChannelConfigOptions::default()
+            },
+            ..OscillatorOptions::default()
+        };
+        let _ = OscillatorNode::new(&context, options);
+    }
+
+    #[test]
+    fn test_output_channels_share_data() {
+        let mut context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 44_100.);
+        let options = OscillatorOptions {
+            output_channels: 4,
+            ..OscillatorOptions::default()
+        };
+        let osc = OscillatorNode::new(&context, options);
+        osc.connect(&context.destination());
+        osc.start();
+
+        let index = NodeIndex::from(osc.registration().id());
+        let rendered = context.start_rendering_sync();
+
+        let output = context.renderer().graph().node_output(index, 0).unwrap();
+        assert_eq!(output.number_of_channels(), 4);
+        let data = output.channel_data(0).as_ptr();
+        assert!(output.channels().iter().all(|c| c.as_ptr() == data));
+        assert!(output.channel_data(0).iter().any(|v| *v != 0.));
+
+        // the quad output is down-mixed to identical stereo channels
+        assert_eq!(rendered.get_channel_data(0), rendered.get_channel_data(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_invalid_output_channels() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let options = OscillatorOptions {
+            output_channels: 0,
+            ..OscillatorOptions::default()
+        };
+        let _ = OscillatorNode::new(&context, options);
     }
 }
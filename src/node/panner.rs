@@ -1,9 +1,11 @@
 use std::f32::consts::PI;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
 use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
-use crate::param::{AudioParam, AudioParamDescriptor};
+use crate::param::{set_values_at_time, AudioParam, AudioParamDescriptor};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::spatial::Transform;
 use crate::AtomicF64;
 
 use super::{
@@ -18,13 +20,82 @@ pub enum PanningModelType {
 }
 
 /// Algorithm to reduce the volume of an audio source as it moves away from the listener
-#[derive(Copy, Clone, Debug)]
+///
+/// With `d` the distance between the panner and the listener, `ref` the reference distance,
+/// `max` the max distance and `f` the rolloff factor:
+///
+/// - `Linear`: `1 - f * (clamp(d, ref, max) - ref) / (max - ref)`, or `1 - f` when
+///   `max <= ref`. The rolloff factor is clamped to `[0, 1]`.
+/// - `Inverse`: `ref / (ref + f * (max(d, ref) - ref))`, or 0 when `ref` is 0.
+/// - `Exponential`: `(max(d, ref) / ref) ^ -f`, or 0 when `ref` is 0.
+///
+/// A negative reference distance or rolloff factor is clamped to 0. Sources closer than the
+/// reference distance are not amplified: the distance gain is always in the `[0, 1]` range.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum DistanceModelType {
     Linear,
     Inverse,
     Exponential,
 }
 
+impl From<u32> for DistanceModelType {
+    fn from(i: u32) -> Self {
+        match i {
+            0 => DistanceModelType::Linear,
+            1 => DistanceModelType::Inverse,
+            2 => DistanceModelType::Exponential,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Gain applied to the source at `distance` of the listener, see [`DistanceModelType`]
+fn distance_gain(
+    model: DistanceModelType,
+    distance: f64,
+    ref_distance: f64,
+    max_distance: f64,
+    rolloff_factor: f64,
+) -> f64 {
+    let ref_distance = ref_distance.max(0.);
+    let rolloff_factor = rolloff_factor.max(0.);
+
+    let gain = match model {
+        DistanceModelType::Linear => {
+            let rolloff_factor = rolloff_factor.min(1.);
+            if max_distance <= ref_distance {
+                1. - rolloff_factor
+            } else {
+                let distance = distance.clamp(ref_distance, max_distance);
+                1. - rolloff_factor * (distance - ref_distance) / (max_distance - ref_distance)
+            }
+        }
+        DistanceModelType::Inverse => {
+            if ref_distance == 0. {
+                0.
+            } else {
+                let distance = distance.max(ref_distance);
+                ref_distance / (ref_distance + rolloff_factor * (distance - ref_distance))
+            }
+        }
+        DistanceModelType::Exponential => {
+            if ref_distance == 0. {
+                0.
+            } else {
+                let distance = distance.max(ref_distance);
+                (distance / ref_distance).powf(-rolloff_factor)
+            }
+        }
+    };
+
+    // e.g. infinite distances
+    if gain.is_nan() {
+        return 0.;
+    }
+
+    gain.clamp(0., 1.)
+}
+
 /// Options for constructing a [`PannerNode`]
 // dictionary PannerOptions : AudioNodeOptions {
 //   PanningModelType panningModel = "equalpower";
@@ -46,7 +117,6 @@ pub enum DistanceModelType {
 pub struct PannerOptions {
     #[allow(dead_code)]
     pub panning_model: PanningModelType,
-    #[allow(dead_code)]
     pub distance_model: DistanceModelType,
     pub position_x: f32,
     pub position_y: f32,
@@ -54,11 +124,8 @@ pub struct PannerOptions {
     pub orientation_x: f32,
     pub orientation_y: f32,
     pub orientation_z: f32,
-    #[allow(dead_code)]
     pub ref_distance: f64,
-    #[allow(dead_code)]
     pub max_distance: f64,
-    #[allow(dead_code)]
     pub rolloff_factor: f64,
     pub cone_inner_angle: f64,
     pub cone_outer_angle: f64,
@@ -94,6 +161,16 @@ impl Default for PannerOptions {
 /// - see also:
 /// [`BaseAudioContext::create_panner`](crate::context::BaseAudioContext::create_panner)
 ///
+/// # Coordinate space
+///
+/// The panner and the [`AudioListener`](crate::AudioListener) live in a right-handed cartesian
+/// coordinate space. The units are arbitrary, the distances are only compared to the
+/// `ref_distance` and `max_distance` of the panner. With the default listener orientation,
+/// i.e. looking towards -Z with +Y up, +X is on the right of the listener.
+///
+/// The distance gain, see [`DistanceModelType`], never exceeds 1: a source closer to the
+/// listener than `ref_distance` is played at its original level.
+///
 /// # Usage
 /// ```no_run
 /// use web_audio_api::context::{BaseAudioContext, AudioContext};
@@ -142,6 +219,12 @@ pub struct PannerNode {
     orientation_x: AudioParam,
     orientation_y: AudioParam,
     orientation_z: AudioParam,
+    /// `DistanceModelType` represented as u32, independent of the other values so `Relaxed`
+    /// ordering suffices
+    distance_model: Arc<AtomicU32>,
+    ref_distance: Arc<AtomicF64>,
+    max_distance: Arc<AtomicF64>,
+    rolloff_factor: Arc<AtomicF64>,
     cone_inner_angle: Arc<AtomicF64>,
     cone_outer_angle: Arc<AtomicF64>,
     cone_outer_gain: Arc<AtomicF64>,
@@ -164,6 +247,17 @@ impl AudioNode for PannerNode {
         1
     }
 
+    fn params(&self) -> Vec<(&'static str, &AudioParam)> {
+        vec![
+            ("positionX", &self.position_x),
+            ("positionY", &self.position_y),
+            ("positionZ", &self.position_z),
+            ("orientationX", &self.orientation_x),
+            ("orientationY", &self.orientation_y),
+            ("orientationZ", &self.orientation_z),
+        ]
+    }
+
     fn set_channel_count(&self, v: usize) {
         if v > 2 {
             panic!("NotSupportedError: PannerNode channel count cannot be greater than two");
@@ -204,6 +298,12 @@ impl PannerNode {
             orientation_y.set_value_at_time(options.orientation_y, 0.);
             orientation_z.set_value_at_time(options.orientation_z, 0.);
 
+            // distance attributes
+            let distance_model = Arc::new(AtomicU32::new(options.distance_model as u32));
+            let ref_distance = Arc::new(AtomicF64::new(options.ref_distance));
+            let max_distance = Arc::new(AtomicF64::new(options.max_distance));
+            let rolloff_factor = Arc::new(AtomicF64::new(options.rolloff_factor));
+
             // cone attributes
             let cone_inner_angle = Arc::new(AtomicF64::new(options.cone_inner_angle));
             let cone_outer_angle = Arc::new(AtomicF64::new(options.cone_outer_angle));
@@ -216,6 +316,10 @@ impl PannerNode {
                 orientation_x: render_ox,
                 orientation_y: render_oy,
                 orientation_z: render_oz,
+                distance_model: distance_model.clone(),
+                ref_distance: ref_distance.clone(),
+                max_distance: max_distance.clone(),
+                rolloff_factor: rolloff_factor.clone(),
                 cone_inner_angle: cone_inner_angle.clone(),
                 cone_outer_angle: cone_outer_angle.clone(),
                 cone_outer_gain: cone_outer_gain.clone(),
@@ -235,6 +339,10 @@ impl PannerNode {
                 orientation_x,
                 orientation_y,
                 orientation_z,
+                distance_model,
+                ref_distance,
+                max_distance,
+                rolloff_factor,
                 cone_inner_angle,
                 cone_outer_angle,
                 cone_outer_gain,
@@ -278,6 +386,91 @@ impl PannerNode {
         &self.orientation_z
     }
 
+    /// Set the position and orientation of the panner from a world transform
+    ///
+    /// The `matrix` is a 4x4 column-major transform, i.e. the translation is given by the
+    /// elements 12, 13 and 14. The orientation is the normalized transformed -Z axis, the
+    /// same convention as the forward vector of the [`AudioListener`](crate::AudioListener).
+    /// Scale and shear are stripped.
+    ///
+    /// All the params are scheduled at `when`, and applied in the same render quantum.
+    /// If the matrix is not invertible (e.g. zero scale), the previous orientation is kept.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `when` is negative
+    pub fn set_transform(&self, matrix: [f32; 16], when: f64) {
+        let transform = Transform::from_matrix(&matrix);
+        let mut values = Vec::with_capacity(6);
+
+        if let Some([x, y, z]) = transform.position {
+            values.extend([
+                (&self.position_x, x),
+                (&self.position_y, y),
+                (&self.position_z, z),
+            ]);
+        } else {
+            log::debug!("PannerNode transform has no valid translation, position is kept");
+        }
+
+        if let Some(([x, y, z], _up)) = transform.orientation {
+            values.extend([
+                (&self.orientation_x, x),
+                (&self.orientation_y, y),
+                (&self.orientation_z, z),
+            ]);
+        } else {
+            log::debug!("PannerNode transform is not invertible, orientation is kept");
+        }
+
+        set_values_at_time(&values, when);
+    }
+
+    pub fn distance_model(&self) -> DistanceModelType {
+        self.distance_model.load(Ordering::Relaxed).into()
+    }
+
+    pub fn set_distance_model(&self, value: DistanceModelType) {
+        self.distance_model.store(value as u32, Ordering::Relaxed);
+    }
+
+    pub fn ref_distance(&self) -> f64 {
+        self.ref_distance.load()
+    }
+
+    /// Set the distance under which the source is not attenuated
+    ///
+    /// A negative value is handled as 0. With a reference distance of 0, the `Inverse` and
+    /// `Exponential` distance models mute the source.
+    pub fn set_ref_distance(&self, value: f64) {
+        self.ref_distance.store(value);
+    }
+
+    pub fn max_distance(&self) -> f64 {
+        self.max_distance.load()
+    }
+
+    /// Set the distance beyond which the `Linear` distance model no longer attenuates the
+    /// source
+    ///
+    /// When it is not greater than the reference distance, the `Linear` distance model
+    /// applies a constant gain of `1 - rolloff_factor`.
+    pub fn set_max_distance(&self, value: f64) {
+        self.max_distance.store(value);
+    }
+
+    pub fn rolloff_factor(&self) -> f64 {
+        self.rolloff_factor.load()
+    }
+
+    /// Set how quickly the volume decreases as the source moves away from the listener
+    ///
+    /// A negative value is handled as 0, i.e. no attenuation. The `Linear` distance model
+    /// also handles values above 1 as 1.
+    pub fn set_rolloff_factor(&self, value: f64) {
+        self.rolloff_factor.store(value);
+    }
+
     pub fn cone_inner_angle(&self) -> f64 {
         self.cone_inner_angle.load()
     }
@@ -310,6 +503,10 @@ struct PannerRenderer {
     orientation_x: AudioParamId,
     orientation_y: AudioParamId,
     orientation_z: AudioParamId,
+    distance_model: Arc<AtomicU32>,
+    ref_distance: Arc<AtomicF64>,
+    max_distance: Arc<AtomicF64>,
+    rolloff_factor: Arc<AtomicF64>,
     cone_inner_angle: Arc<AtomicF64>,
     cone_outer_angle: Arc<AtomicF64>,
     cone_outer_gain: Arc<AtomicF64>,
@@ -388,7 +585,13 @@ impl AudioProcessor for PannerRenderer {
 
         // determine distance gain
         let distance = crate::spatial::distance(source_position, listener_position);
-        let dist_gain = 1. / distance; // inverse distance model is assumed (todo issue #44)
+        let dist_gain = distance_gain(
+            self.distance_model.load(Ordering::Relaxed).into(),
+            f64::from(distance),
+            self.ref_distance.load(),
+            self.max_distance.load(),
+            self.rolloff_factor.load(),
+        ) as f32;
 
         // determine cone effect gain
         let abs_inner_angle = self.cone_inner_angle.load().abs() as f32 / 2.;
@@ -431,3 +634,96 @@ impl AudioProcessor for PannerRenderer {
         false // only true for panning model HRTF
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use super::*;
+
+    const MODELS: [DistanceModelType; 3] = [
+        DistanceModelType::Linear,
+        DistanceModelType::Inverse,
+        DistanceModelType::Exponential,
+    ];
+
+    fn gains(
+        model: DistanceModelType,
+        ref_distance: f64,
+        max_distance: f64,
+        rolloff: f64,
+    ) -> Vec<f64> {
+        (0..200)
+            .map(|i| distance_gain(model, i as f64 * 0.1, ref_distance, max_distance, rolloff))
+            .collect()
+    }
+
+    fn assert_valid(gains: &[f64]) {
+        assert!(gains
+            .iter()
+            .all(|g| g.is_finite() && (0. ..=1.).contains(g)));
+        // the further, the quieter
+        assert!(gains.windows(2).all(|w| w[1] <= w[0]));
+    }
+
+    #[test]
+    fn test_distance_gain() {
+        for &model in MODELS.iter() {
+            let gains = gains(model, 1., 10., 1.);
+            assert_valid(&gains);
+
+            // below the reference distance
+            assert_float_eq!(gains[0], 1., abs <= 0.);
+            assert_float_eq!(gains[5], 1., abs <= 0.);
+            assert_float_eq!(gains[10], 1., abs <= 0.);
+        }
+
+        // between the reference and the max distance
+        let linear = |d| distance_gain(DistanceModelType::Linear, d, 1., 10., 1.);
+        assert_float_eq!(linear(5.5), 0.5, abs <= 1e-12);
+        let inverse = |d| distance_gain(DistanceModelType::Inverse, d, 1., 10., 1.);
+        assert_float_eq!(inverse(4.), 0.25, abs <= 1e-12);
+        let exponential = |d| distance_gain(DistanceModelType::Exponential, d, 1., 10., 2.);
+        assert_float_eq!(exponential(4.), 1. / 16., abs <= 1e-12);
+
+        // beyond the max distance, only the linear model stops attenuating
+        assert_float_eq!(linear(10.), 0., abs <= 0.);
+        assert_float_eq!(linear(20.), 0., abs <= 0.);
+        assert_float_eq!(inverse(20.), 0.05, abs <= 1e-12);
+    }
+
+    #[test]
+    fn test_distance_gain_degenerate() {
+        for &model in MODELS.iter() {
+            // zero reference distance
+            assert_valid(&gains(model, 0., 10., 1.));
+            // max distance not greater than the reference distance
+            assert_valid(&gains(model, 1., 1., 1.));
+            assert_valid(&gains(model, 2., 1., 0.5));
+            // negative rolloff and reference distance
+            assert_valid(&gains(model, 1., 10., -1.));
+            assert_valid(&gains(model, -1., 10., 1.));
+            // large rolloff
+            assert_valid(&gains(model, 1., 10., 10.));
+            // source on the listener
+            assert!(distance_gain(model, 0., 0., 0., 0.).is_finite());
+        }
+
+        // a negative rolloff does not attenuate
+        assert!(gains(DistanceModelType::Inverse, 1., 10., -1.)
+            .iter()
+            .all(|&g| g == 1.));
+        // the linear model handles a rolloff above 1 as 1
+        assert_float_eq!(
+            distance_gain(DistanceModelType::Linear, 20., 1., 10., 10.),
+            0.,
+            abs <= 0.
+        );
+        // the linear model with max <= ref applies 1 - rolloff
+        assert_float_eq!(
+            distance_gain(DistanceModelType::Linear, 0., 2., 1., 0.25),
+            0.75,
+            abs <= 0.
+        );
+    }
+}
@@ -0,0 +1,596 @@
+use std::f64::consts::PI;
+
+use crossbeam_channel::{self, Receiver, Sender};
+
+use crate::buffer::AudioBuffer;
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::RENDER_QUANTUM_SIZE;
+
+use super::test_signal::NoiseGenerator;
+use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
+
+/// Lowest frequency of the string, it sets the length of the delay line
+const MIN_FREQUENCY: f64 = 20.;
+/// Coefficient of the loop filter when the damping is 1
+const MAX_FILTER_COEFFICIENT: f64 = 0.9;
+/// Gain applied to the signal at each round trip in the loop
+const LOOP_GAIN: f32 = 0.996;
+/// Level under which the string stops ringing (-100 dB)
+const SILENCE_THRESHOLD: f32 = 1e-5;
+
+/// Signal injected into the string when it is plucked
+#[derive(Clone, Debug, Default)]
+pub enum PluckExcitation {
+    /// White noise burst lasting one period of the string
+    #[default]
+    Noise,
+    /// First channel of the buffer, at the sample rate of the context
+    Buffer(AudioBuffer),
+}
+
+/// Options for constructing a [`PluckedStringNode`]
+#[derive(Clone, Debug)]
+pub struct PluckedStringOptions {
+    /// Frequency of the string, in Hz
+    pub frequency: f32,
+    /// Damping of the string, in the `[0, 1]` range
+    pub damping: f32,
+    /// Signal injected into the string when it is plucked
+    pub excitation: PluckExcitation,
+    /// channel config options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for PluckedStringOptions {
+    fn default() -> Self {
+        Self {
+            frequency: 440.,
+            damping: 0.5,
+            excitation: PluckExcitation::default(),
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// Message from the control side to the renderer
+enum PluckedStringMessage {
+    Pluck { when: f64, velocity: f32 },
+    Excitation(PluckExcitation),
+}
+
+/// Plucked string physical model, after the Karplus-Strong algorithm
+///
+/// Each pluck injects a short excitation, a noise burst by default, into a feedback loop made
+/// of a delay line and a one-pole lowpass filter. The delay line sets the pitch of the string,
+/// the filter its damping: the higher the damping, the faster the string decays and the
+/// duller it sounds. The renderer owns the loop, no cycle is needed in the audio graph.
+///
+/// The node is a mono source, it can be plucked any number of times, also while it is still
+/// ringing.
+///
+/// - see also: [`BaseAudioContext::create_plucked_string`](crate::context::BaseAudioContext::create_plucked_string)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::AudioNode;
+///
+/// let context = AudioContext::default();
+///
+/// let string = context.create_plucked_string();
+/// string.connect(&context.destination());
+///
+/// // arpeggio
+/// let now = context.current_time();
+/// for (i, &frequency) in [220., 277.18, 329.63].iter().enumerate() {
+///     let when = now + i as f64 * 0.25;
+///     string.frequency().set_value_at_time(frequency, when);
+///     string.pluck_at(when, 0.8);
+/// }
+/// ```
+pub struct PluckedStringNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Frequency of the string
+    frequency: AudioParam,
+    /// Damping of the string
+    damping: AudioParam,
+    /// channel between control and renderer parts (sender part)
+    sender: Sender<PluckedStringMessage>,
+}
+
+impl AudioNode for PluckedStringNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    /// `PluckedStringNode` is a source node. A source node is by definition with no input
+    fn number_of_inputs(&self) -> usize {
+        0
+    }
+
+    /// `PluckedStringNode` is a mono source node.
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+
+    fn params(&self) -> Vec<(&'static str, &AudioParam)> {
+        vec![("frequency", &self.frequency), ("damping", &self.damping)]
+    }
+}
+
+impl PluckedStringNode {
+    /// Returns a `PluckedStringNode`
+    ///
+    /// # Arguments:
+    ///
+    /// * `context` - The `AudioContext`
+    /// * `options` - The PluckedStringOptions
+    ///
+    /// # Panics
+    ///
+    /// Panics if the excitation buffer has a sample rate different from the context sample rate
+    pub fn new<C: BaseAudioContext>(context: &C, options: PluckedStringOptions) -> Self {
+        context.register(move |registration| {
+            let sample_rate = context.sample_rate();
+
+            let PluckedStringOptions {
+                frequency,
+                damping,
+                excitation,
+                channel_config,
+            } = options;
+
+            assert_valid_excitation(&excitation, sample_rate);
+
+            // frequency audio parameter
+            let freq_param_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: sample_rate / 2.,
+                default_value: 440.,
+                automation_rate: AutomationRate::A,
+            };
+            let (f_param, f_proc) = context.create_audio_param(freq_param_opts, &registration);
+            f_param.set_value(frequency);
+
+            // damping audio parameter
+            let damping_param_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: 1.,
+                default_value: 0.5,
+                automation_rate: AutomationRate::K,
+            };
+            let (d_param, d_proc) = context.create_audio_param(damping_param_opts, &registration);
+            d_param.set_value(damping);
+
+            // The plucks are queued, the renderer picks them all up
+            let (sender, receiver) = crossbeam_channel::unbounded();
+
+            // room for the period of the lowest frequency, and the interpolation
+            let delay_line_length = (sample_rate as f64 / MIN_FREQUENCY) as usize + 2;
+
+            let renderer = PluckedStringRenderer {
+                frequency: f_proc,
+                damping: d_proc,
+                receiver,
+                pending: Vec::with_capacity(16),
+                excitation,
+                noise: NoiseGenerator::new(),
+                delay_line: vec![0.; delay_line_length],
+                write_index: 0,
+                period: 0.,
+                filter_state: 0.,
+                burst: None,
+                ringing: false,
+            };
+
+            let node = Self {
+                registration,
+                channel_config: channel_config.into(),
+                frequency: f_param,
+                damping: d_param,
+                sender,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// A-rate [`AudioParam`] that defines the frequency of the string, expressed in Hz
+    ///
+    /// The value is read at the time of each pluck, it is clamped to the `[20, sampleRate / 4]`
+    /// range.
+    #[must_use]
+    pub fn frequency(&self) -> &AudioParam {
+        &self.frequency
+    }
+
+    /// K-rate [`AudioParam`] that defines the damping of the string, from 0 (longest
+    /// ringing) to 1 (shortest and dullest)
+    #[must_use]
+    pub fn damping(&self) -> &AudioParam {
+        &self.damping
+    }
+
+    /// Replace the signal injected into the string by the next plucks
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer has a sample rate different from the context sample rate, or if
+    /// the render thread has been dropped
+    pub fn set_excitation(&self, excitation: PluckExcitation) {
+        assert_valid_excitation(&excitation, self.context().sample_rate());
+
+        self.sender
+            .send(PluckedStringMessage::Excitation(excitation))
+            .expect("Sending the excitation to the node renderer failed");
+    }
+
+    /// Pluck the string now, see [`pluck_at`](Self::pluck_at)
+    pub fn pluck(&self, velocity: f32) {
+        let when = self.context().current_time();
+        self.pluck_at(when, velocity);
+    }
+
+    /// Pluck the string at the given time, with the given velocity in the `[0, 1]` range
+    ///
+    /// The excitation is scaled by the velocity. Times in the past pluck the string
+    /// immediately.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `when` is negative or not finite, if the velocity is out of range, or if the
+    /// render thread has been dropped
+    pub fn pluck_at(&self, when: f64, velocity: f32) {
+        assert!(
+            when.is_finite() && when >= 0.,
+            "RangeError - pluck time must be finite and positive, got {:?}",
+            when
+        );
+        assert!(
+            (0. ..=1.).contains(&velocity),
+            "RangeError - velocity must be in the [0, 1] range, got {:?}",
+            velocity
+        );
+
+        self.sender
+            .send(PluckedStringMessage::Pluck { when, velocity })
+            .expect("Sending the pluck to the node renderer failed");
+    }
+}
+
+fn assert_valid_excitation(excitation: &PluckExcitation, sample_rate: f32) {
+    if let PluckExcitation::Buffer(buffer) = excitation {
+        if (buffer.sample_rate() - sample_rate).abs() > f32::EPSILON {
+            panic!(
+                "NotSupportedError - buffer sample rate ({}) does not match context sample rate ({})",
+                buffer.sample_rate(),
+                sample_rate
+            );
+        }
+    }
+}
+
+/// Phase delay, in frames, of the loop filter with coefficient `c` at the angular frequency `w`
+fn filter_phase_delay(c: f64, w: f64) -> f64 {
+    (c * w.sin()).atan2(1. - c * w.cos()) / w
+}
+
+/// Excitation being injected into the string
+struct Burst {
+    /// position in the excitation, in frames
+    position: usize,
+    /// length of the excitation, in frames
+    length: usize,
+    velocity: f32,
+}
+
+/// Rendering component of the plucked string node
+struct PluckedStringRenderer {
+    /// Frequency of the string
+    frequency: AudioParamId,
+    /// Damping of the string
+    damping: AudioParamId,
+    /// channel between control and renderer parts (receiver part)
+    receiver: Receiver<PluckedStringMessage>,
+    /// plucks to come, by increasing time
+    pending: Vec<(f64, f32)>,
+    /// signal injected into the string when it is plucked
+    excitation: PluckExcitation,
+    noise: NoiseGenerator,
+    /// ring buffer, the most recent frame is right before `write_index`
+    delay_line: Vec<f32>,
+    write_index: usize,
+    /// period of the string, in frames
+    period: f64,
+    /// state of the one-pole loop filter
+    filter_state: f32,
+    /// excitation in progress, if any
+    burst: Option<Burst>,
+    /// the string is ringing, i.e. it is not silent
+    ringing: bool,
+}
+
+impl PluckedStringRenderer {
+    /// Start a new excitation, the string keeps ringing from its current state
+    fn pluck(&mut self, velocity: f32, frequency: f32, sample_rate: f64) {
+        let frequency = (frequency as f64).clamp(MIN_FREQUENCY, sample_rate / 4.);
+        self.period = sample_rate / frequency;
+
+        let length = match &self.excitation {
+            PluckExcitation::Noise => self.period.round() as usize,
+            PluckExcitation::Buffer(buffer) => buffer.length(),
+        };
+        self.burst = Some(Burst {
+            position: 0,
+            length,
+            velocity,
+        });
+        self.ringing = true;
+    }
+
+    /// Next frame of the excitation, zero when there is none
+    fn excite(&mut self) -> f32 {
+        let Some(burst) = &mut self.burst else {
+            return 0.;
+        };
+
+        let sample = match &self.excitation {
+            PluckExcitation::Noise => self.noise.white() as f32,
+            PluckExcitation::Buffer(buffer) => buffer.get_channel_data(0)[burst.position],
+        };
+        let sample = sample * burst.velocity;
+
+        burst.position += 1;
+        if burst.position >= burst.length {
+            self.burst = None;
+        }
+
+        sample
+    }
+
+    /// Read the delay line `delay` frames in the past, with linear interpolation
+    fn read(&self, delay: f64) -> f32 {
+        let len = self.delay_line.len();
+        let whole = delay as usize;
+        let frac = (delay - whole as f64) as f32;
+
+        let a = self.delay_line[(self.write_index + len - whole) % len];
+        let b = self.delay_line[(self.write_index + len - whole - 1) % len];
+        a + frac * (b - a)
+    }
+}
+
+impl AudioProcessor for PluckedStringRenderer {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single output node
+        let output = &mut outputs[0];
+
+        // check if any message was send from the control thread
+        for message in self.receiver.try_iter() {
+            match message {
+                PluckedStringMessage::Pluck { when, velocity } => {
+                    let position = self.pending.partition_point(|(t, _)| *t <= when);
+                    self.pending.insert(position, (when, velocity));
+                }
+                PluckedStringMessage::Excitation(excitation) => {
+                    // the excitation in progress is cut short
+                    self.burst = None;
+                    self.excitation = excitation;
+                }
+            }
+        }
+
+        let sample_rate = scope.sample_rate as f64;
+        let next_block_time =
+            (scope.current_frame + RENDER_QUANTUM_SIZE as u64) as f64 / sample_rate;
+        let next_pluck = self.pending.first().map_or(f64::INFINITY, |(t, _)| *t);
+
+        if !self.ringing && next_pluck >= next_block_time {
+            output.make_silent();
+            // keep the node alive until its next pluck
+            return !self.pending.is_empty();
+        }
+
+        output.set_number_of_channels(1);
+        let channel_data = output.channel_data_mut(0);
+
+        let frequency_values = params.get(&self.frequency);
+        let c = params.get(&self.damping)[0] as f64 * MAX_FILTER_COEFFICIENT;
+        let max_delay = (self.delay_line.len() - 2) as f64;
+
+        for (index, output_sample) in channel_data.iter_mut().enumerate() {
+            let current_time = (scope.current_frame + index as u64) as f64 / sample_rate;
+            while let Some(&(when, velocity)) = self.pending.first() {
+                if when > current_time {
+                    break;
+                }
+                self.pending.remove(0);
+                self.pluck(velocity, frequency_values[index], sample_rate);
+            }
+
+            if !self.ringing {
+                *output_sample = 0.;
+                continue;
+            }
+
+            // the loop filter delays the signal too, shorten the delay line accordingly
+            let w = 2. * PI / self.period;
+            let delay = (self.period - filter_phase_delay(c, w)).clamp(1., max_delay);
+
+            let delayed = self.read(delay);
+            self.filter_state = (1. - c as f32) * delayed + c as f32 * self.filter_state;
+            let sample = self.excite() + LOOP_GAIN * self.filter_state;
+
+            self.delay_line[self.write_index] = sample;
+            self.write_index = (self.write_index + 1) % self.delay_line.len();
+            *output_sample = sample;
+        }
+
+        // stop ringing once the loop has decayed under the audible level
+        if self.ringing
+            && self.burst.is_none()
+            && self.delay_line.iter().all(|v| v.abs() < SILENCE_THRESHOLD)
+        {
+            self.delay_line.iter_mut().for_each(|v| *v = 0.);
+            self.filter_state = 0.;
+            self.ringing = false;
+        }
+
+        self.ringing || !self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioNode;
+
+    use super::*;
+
+    /// Render the string plucked at time 0
+    fn render_pluck(frequency: f32, damping: f32, length: usize) -> Vec<f32> {
+        let mut context = OfflineAudioContext::new(1, length, 44_100.);
+        let string = context.create_plucked_string();
+        string.frequency().set_value(frequency);
+        string.damping().set_value(damping);
+        string.connect(&context.destination());
+        string.pluck(1.);
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    /// Fundamental frequency of the signal, from the highest autocorrelation peak
+    fn fundamental(signal: &[f32], sample_rate: f32, lags: std::ops::Range<usize>) -> f32 {
+        let autocorrelation =
+            |lag: usize| -> f32 { signal[lag..].iter().zip(signal).map(|(a, b)| a * b).sum() };
+
+        let lag = lags
+            .clone()
+            .max_by(|a, b| autocorrelation(*a).total_cmp(&autocorrelation(*b)))
+            .unwrap();
+
+        // parabolic interpolation around the peak
+        let (y0, y1, y2) = (
+            autocorrelation(lag - 1),
+            autocorrelation(lag),
+            autocorrelation(lag + 1),
+        );
+        let offset = 0.5 * (y0 - y2) / (y0 - 2. * y1 + y2);
+
+        sample_rate / (lag as f32 + offset)
+    }
+
+    /// Time at which the signal falls for good under a tenth of its peak (-20 dB)
+    fn decay_time(signal: &[f32], sample_rate: f32) -> f32 {
+        let peak = signal.iter().fold(0., |max: f32, v| max.max(v.abs()));
+        let last = signal
+            .iter()
+            .rposition(|v| v.abs() > peak * 0.1)
+            .unwrap_or(0);
+        last as f32 / sample_rate
+    }
+
+    #[test]
+    fn test_fundamental() {
+        for damping in [0., 0.5, 1.] {
+            let output = render_pluck(220., damping, 44_100);
+
+            // skip the excitation
+            let frequency = fundamental(&output[4410..22_050], 44_100., 100..400);
+            assert_float_eq!(frequency, 220., r2nd <= 0.01);
+        }
+    }
+
+    #[test]
+    fn test_decay_shortens_with_damping() {
+        let decay_times: Vec<_> = [0., 0.25, 0.5, 0.75, 1.]
+            .iter()
+            .map(|&damping| decay_time(&render_pluck(220., damping, 44_100 * 3), 44_100.))
+            .collect();
+
+        assert!(
+            decay_times.windows(2).all(|w| w[1] < w[0]),
+            "{:?}",
+            decay_times
+        );
+    }
+
+    #[test]
+    fn test_repeated_plucks() {
+        let sample_rate = 44_100.;
+        let mut context = OfflineAudioContext::new(1, 44_100, sample_rate);
+        let string = context.create_plucked_string();
+        string.damping().set_value(1.);
+        string.connect(&context.destination());
+
+        // out of order
+        string.pluck_at(0.5, 1.);
+        string.pluck_at(0.1, 0.5);
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+
+        // silent until the first pluck, the second one is louder
+        let onset = |from: usize| channel[from..].iter().position(|v| *v != 0.).unwrap() + from;
+        assert_eq!(onset(0), 4410);
+        let peak = |range: std::ops::Range<usize>| {
+            channel[range]
+                .iter()
+                .fold(0., |max: f32, v| max.max(v.abs()))
+        };
+        assert!(peak(22_050..22_150) > 1.5 * peak(4410..4510));
+    }
+
+    #[test]
+    fn test_buffer_excitation() {
+        let sample_rate = 44_100.;
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 4, sample_rate);
+
+        let mut impulse = context.create_buffer(1, 1, sample_rate);
+        impulse.copy_to_channel(&[1.], 0);
+
+        let options = PluckedStringOptions {
+            frequency: 441.,
+            damping: 0.,
+            excitation: PluckExcitation::Buffer(impulse),
+            ..PluckedStringOptions::default()
+        };
+        let string = PluckedStringNode::new(&context, options);
+        string.connect(&context.destination());
+        string.pluck(0.5);
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+
+        // the impulse comes back once per period, attenuated by the loop
+        assert_float_eq!(channel[0], 0.5, abs <= 0.);
+        assert_float_eq!(channel[100], 0.5 * LOOP_GAIN, abs <= 1e-6);
+        assert_float_eq!(channel[200], 0.5 * LOOP_GAIN * LOOP_GAIN, abs <= 1e-6);
+    }
+
+    #[test]
+    #[should_panic(expected = "RangeError")]
+    fn test_invalid_velocity() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let string = context.create_plucked_string();
+        string.pluck(2.);
+    }
+}
@@ -72,6 +72,10 @@ impl AudioNode for StereoPannerNode {
         1
     }
 
+    fn params(&self) -> Vec<(&'static str, &AudioParam)> {
+        vec![("pan", &self.pan)]
+    }
+
     fn channel_count_mode(&self) -> ChannelCountMode {
         ChannelCountMode::ClampedMax
     }
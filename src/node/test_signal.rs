@@ -0,0 +1,486 @@
+use std::f64::consts::PI;
+
+use crate::context::{AudioContextRegistration, BaseAudioContext};
+use crate::control::{Playback, Scheduler, SourceActivity};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::{
+    AudioNode, AudioScheduledSourceNode, ChannelConfig, ChannelConfigOptions, PlaybackState,
+};
+
+/// Type of the signal rendered by a [`TestSignalNode`]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum TestSignalType {
+    /// Sine wave at the node frequency
+    #[default]
+    Sine,
+    /// Single sample of amplitude 1 at the start time, followed by silence
+    Impulse,
+    /// Uniformly distributed white noise
+    WhiteNoise,
+    /// Pink noise, i.e. noise with equal energy per octave
+    PinkNoise,
+    /// Band-limited sawtooth wave at the node frequency
+    BandLimitedSawtooth,
+    /// Linear frequency sweep from `start_hz` to `end_hz` during `duration_s` seconds
+    Chirp {
+        start_hz: f32,
+        end_hz: f32,
+        duration_s: f32,
+    },
+}
+
+/// Options for constructing a [`TestSignalNode`]
+#[derive(Clone, Debug)]
+pub struct TestSignalOptions {
+    /// The type of the test signal
+    pub signal_type: TestSignalType,
+    /// The frequency of the periodic signals, ignored by the other types
+    pub frequency: f32,
+    /// channel config options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for TestSignalOptions {
+    fn default() -> Self {
+        Self {
+            signal_type: TestSignalType::default(),
+            frequency: 1000.,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// Audio source rendering precise test signals, for measurement and testing tools
+///
+/// The `Impulse` and `Chirp` signals are rendered once, the node then renders silence and
+/// can be released by the audio graph.
+///
+/// - see also: [`BaseAudioContext::create_test_signal`](crate::context::BaseAudioContext::create_test_signal)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode, TestSignalType};
+///
+/// let context = AudioContext::default();
+///
+/// // sweep the audible range in 5 seconds
+/// let signal_type = TestSignalType::Chirp {
+///     start_hz: 20.,
+///     end_hz: 20_000.,
+///     duration_s: 5.,
+/// };
+/// let chirp = context.create_test_signal(signal_type, 0.);
+/// chirp.connect(&context.destination());
+/// chirp.start();
+/// ```
+pub struct TestSignalNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    signal_type: TestSignalType,
+    frequency: f32,
+    scheduler: Scheduler,
+}
+
+impl AudioNode for TestSignalNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        0
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl AudioScheduledSourceNode for TestSignalNode {
+    fn start(&self) {
+        let when = self.registration.context().current_time();
+        self.start_at(when);
+    }
+
+    fn start_at(&self, when: f64) {
+        self.registration
+            .context()
+            .schedule_start(&self.scheduler, when);
+    }
+
+    fn stop(&self) {
+        let when = self.registration.context().current_time();
+        self.stop_at(when);
+    }
+
+    fn stop_at(&self, when: f64) {
+        self.registration
+            .context()
+            .schedule_stop(&self.scheduler, when);
+    }
+
+    fn start_at_frame(&self, frame: u64) {
+        self.registration
+            .context()
+            .schedule_start_frame(&self.scheduler, frame);
+    }
+
+    fn stop_at_frame(&self, frame: u64) {
+        self.registration
+            .context()
+            .schedule_stop_frame(&self.scheduler, frame);
+    }
+
+    fn scheduled_start_time(&self) -> Option<f64> {
+        self.scheduler.scheduled_start()
+    }
+
+    fn scheduled_stop_time(&self) -> Option<f64> {
+        self.scheduler.scheduled_stop()
+    }
+
+    fn cancel_scheduled_start(&self) {
+        self.registration.context().cancel_start(&self.scheduler);
+    }
+
+    fn playback_state(&self) -> PlaybackState {
+        self.scheduler.playback_state()
+    }
+}
+
+impl TestSignalNode {
+    /// Returns a `TestSignalNode`
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the frequency of a `Sine` or `BandLimitedSawtooth` signal, or the
+    /// duration of a `Chirp` signal, is not strictly positive
+    pub fn new<C: BaseAudioContext>(context: &C, options: TestSignalOptions) -> Self {
+        let TestSignalOptions {
+            signal_type,
+            frequency,
+            channel_config,
+        } = options;
+
+        match signal_type {
+            TestSignalType::Sine | TestSignalType::BandLimitedSawtooth => {
+                assert!(
+                    frequency > 0.,
+                    "RangeError - frequency must be strictly positive, got {:?}",
+                    frequency
+                );
+            }
+            TestSignalType::Chirp { duration_s, .. } => {
+                assert!(
+                    duration_s > 0.,
+                    "RangeError - chirp duration must be strictly positive, got {:?}",
+                    duration_s
+                );
+            }
+            _ => (),
+        }
+
+        context.register(move |registration| {
+            let scheduler = Scheduler::new();
+
+            let render = TestSignalRenderer {
+                signal_type,
+                frequency: frequency as f64,
+                scheduler: scheduler.clone(),
+                activity: SourceActivity::new(context.base().active_sources().clone(), &scheduler),
+                frames_played: 0,
+                phase: 0.,
+                noise: NoiseGenerator::new(),
+            };
+
+            let node = TestSignalNode {
+                registration,
+                channel_config: channel_config.into(),
+                signal_type,
+                frequency,
+                scheduler,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// Type of the rendered signal
+    pub fn signal_type(&self) -> TestSignalType {
+        self.signal_type
+    }
+
+    /// Frequency of the periodic signals
+    pub fn frequency(&self) -> f32 {
+        self.frequency
+    }
+}
+
+/// Random number generator for the noise signals, with pink noise filter state
+///
+/// The render thread must not lock or allocate, so a simple xorshift generator is used
+/// instead of the thread local random generators.
+pub(super) struct NoiseGenerator {
+    state: u32,
+    pink: [f64; 7],
+}
+
+impl NoiseGenerator {
+    pub(super) fn new() -> Self {
+        Self {
+            state: 0x9E37_79B9,
+            pink: [0.; 7],
+        }
+    }
+
+    /// Uniformly distributed sample in `(-1, 1]`, the xorshift state is never 0
+    pub(super) fn white(&mut self) -> f64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+
+        (self.state as f64 / u32::MAX as f64).mul_add(2., -1.)
+    }
+
+    /// Pink noise sample, white noise filtered with a -3dB/octave slope
+    // cf. Paul Kellet's refined method, https://www.firstpr.com.au/dsp/pink-noise/
+    fn pink(&mut self) -> f64 {
+        let white = self.white();
+        let b = &mut self.pink;
+
+        b[0] = 0.99886 * b[0] + white * 0.0555179;
+        b[1] = 0.99332 * b[1] + white * 0.0750759;
+        b[2] = 0.96900 * b[2] + white * 0.1538520;
+        b[3] = 0.86650 * b[3] + white * 0.3104856;
+        b[4] = 0.55000 * b[4] + white * 0.5329522;
+        b[5] = -0.7616 * b[5] - white * 0.0168980;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+        b[6] = white * 0.115926;
+
+        // bring back to roughly [-1, 1]
+        pink * 0.11
+    }
+}
+
+struct TestSignalRenderer {
+    signal_type: TestSignalType,
+    frequency: f64,
+    scheduler: Scheduler,
+    activity: SourceActivity,
+    /// number of frames rendered since start
+    frames_played: u64,
+    /// phase of the periodic signals, in `[0, 1)`
+    phase: f64,
+    noise: NoiseGenerator,
+}
+
+impl TestSignalRenderer {
+    /// Returns true when the signal is entirely rendered, i.e. only silence remains
+    fn ended(&self, sample_rate: f64) -> bool {
+        match self.signal_type {
+            TestSignalType::Impulse => self.frames_played > 0,
+            TestSignalType::Chirp { duration_s, .. } => {
+                self.frames_played as f64 >= duration_s as f64 * sample_rate
+            }
+            _ => false,
+        }
+    }
+
+    fn generate(&mut self, sample_rate: f64) -> f64 {
+        let sample = match self.signal_type {
+            TestSignalType::Sine => {
+                let sample = (2. * PI * self.phase).sin();
+                self.advance_phase(self.frequency / sample_rate);
+                sample
+            }
+            TestSignalType::Impulse => {
+                if self.frames_played == 0 {
+                    1.
+                } else {
+                    0.
+                }
+            }
+            TestSignalType::WhiteNoise => self.noise.white(),
+            TestSignalType::PinkNoise => self.noise.pink(),
+            TestSignalType::BandLimitedSawtooth => {
+                let phase_incr = self.frequency / sample_rate;
+                // offset phase to start at 0. (not -1.)
+                let phase = (self.phase + 0.5).fract();
+                let sample = 2. * phase - 1. - poly_blep(phase, phase_incr);
+                self.advance_phase(phase_incr);
+                sample
+            }
+            TestSignalType::Chirp {
+                start_hz,
+                end_hz,
+                duration_s,
+            } => {
+                if self.ended(sample_rate) {
+                    0.
+                } else {
+                    // instantaneous frequency grows linearly from start to end
+                    let t = self.frames_played as f64 / sample_rate;
+                    let slope = (end_hz - start_hz) as f64 / duration_s as f64;
+                    let frequency = slope.mul_add(t, start_hz as f64);
+
+                    let sample = (2. * PI * self.phase).sin();
+                    self.advance_phase(frequency / sample_rate);
+                    sample
+                }
+            }
+        };
+
+        self.frames_played += 1;
+
+        sample
+    }
+
+    #[inline]
+    fn advance_phase(&mut self, phase_incr: f64) {
+        self.phase = (self.phase + phase_incr).rem_euclid(1.);
+    }
+}
+
+// cf. http://www.martin-finke.de/blog/articles/audio-plugins-018-polyblep-oscillator/
+#[inline]
+fn poly_blep(mut t: f64, dt: f64) -> f64 {
+    if t < dt {
+        t /= dt;
+        t + t - t * t - 1.
+    } else if t > 1. - dt {
+        t = (t - 1.) / dt;
+        t.mul_add(t, t) + t + 1.
+    } else {
+        0.
+    }
+}
+
+impl AudioProcessor for TestSignalRenderer {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single output node
+        let output = &mut outputs[0];
+
+        let sample_rate = scope.sample_rate as f64;
+
+        let start_frame = self.scheduler.get_start_frame();
+        let stop_frame = self.scheduler.get_stop_frame();
+
+        let playback = self.scheduler.playback(scope.current_frame);
+
+        if playback == Playback::Pending {
+            output.make_silent();
+            return self.scheduler.pending_tail_time();
+        }
+
+        if playback == Playback::Ended || self.ended(sample_rate) {
+            output.make_silent();
+            self.activity.set_active(false);
+            return false;
+        }
+
+        self.activity.set_active(true);
+        output.force_mono();
+
+        let output_channel = output.channel_data_mut(0);
+
+        for (index, output_sample) in output_channel.iter_mut().enumerate() {
+            let frame = (scope.current_frame + index as u64) as f64;
+
+            if frame < start_frame || frame >= stop_frame {
+                *output_sample = 0.;
+            } else {
+                *output_sample = self.generate(sample_rate) as f32;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+    use crate::RENDER_QUANTUM_SIZE;
+
+    use super::*;
+
+    #[test]
+    fn test_impulse() {
+        let sample_rate = 48_000.;
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 4, sample_rate);
+
+        let impulse = context.create_test_signal(TestSignalType::Impulse, 0.);
+        impulse.connect(&context.destination());
+        // start in 2nd block
+        impulse.start_at(200. / sample_rate as f64);
+
+        let buffer = context.start_rendering_sync();
+        let channel = buffer.get_channel_data(0);
+
+        let non_zero: Vec<_> = channel
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| **v != 0.)
+            .collect();
+        assert_eq!(non_zero, vec![(200, &1.)]);
+    }
+
+    #[test]
+    fn test_chirp_end() {
+        let sample_rate = 48_000.;
+        let mut context = OfflineAudioContext::new(1, 48_000, sample_rate);
+
+        let signal_type = TestSignalType::Chirp {
+            start_hz: 100.,
+            end_hz: 1000.,
+            duration_s: 0.5,
+        };
+        let chirp = context.create_test_signal(signal_type, 0.);
+        chirp.connect(&context.destination());
+        chirp.start();
+
+        let buffer = context.start_rendering_sync();
+        let channel = buffer.get_channel_data(0);
+
+        // sweep during 0.5 seconds, silence afterwards
+        let end = 24_000;
+        assert!(channel[end - 64..end].iter().any(|v| *v != 0.));
+        assert!(channel[end..].iter().all(|v| *v == 0.));
+    }
+
+    #[test]
+    fn test_white_noise() {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 8, 48_000.);
+
+        let noise = context.create_test_signal(TestSignalType::WhiteNoise, 0.);
+        noise.connect(&context.destination());
+        noise.start();
+
+        let buffer = context.start_rendering_sync();
+        let channel = buffer.get_channel_data(0);
+
+        assert!(channel.iter().all(|v| (-1. ..1.).contains(v)));
+        let mean = channel.iter().sum::<f32>() / channel.len() as f32;
+        assert!(mean.abs() < 0.1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_frequency() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 48_000.);
+        let _ = context.create_test_signal(TestSignalType::Sine, 0.);
+    }
+}
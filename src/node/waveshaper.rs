@@ -131,7 +131,7 @@ pub struct WaveShaperNode {
     channel_config: ChannelConfig,
     /// distortion curve
     curve: OnceCell<Vec<f32>>,
-    /// oversample type
+    /// oversample type, independent of the other values so `Relaxed` ordering suffices
     oversample: Arc<AtomicU32>,
     /// Channel between node and renderer (sender part)
     sender: Sender<CurveMessage>,
@@ -232,7 +232,7 @@ impl WaveShaperNode {
     /// Returns the `oversample` faactor of this node
     #[must_use]
     pub fn oversample(&self) -> OverSampleType {
-        self.oversample.load(Ordering::SeqCst).into()
+        self.oversample.load(Ordering::Relaxed).into()
     }
 
     /// set the `oversample` factor of this node
@@ -241,7 +241,7 @@ impl WaveShaperNode {
     ///
     /// * `oversample` - the desired `OversampleType` variant
     pub fn set_oversample(&self, oversample: OverSampleType) {
-        self.oversample.store(oversample as u32, Ordering::SeqCst);
+        self.oversample.store(oversample as u32, Ordering::Relaxed);
     }
 }
 
@@ -300,7 +300,7 @@ impl AudioProcessor for WaveShaperRenderer {
         *output = input.clone();
 
         if self.curve.is_some() {
-            match self.oversample.load(Ordering::SeqCst).into() {
+            match self.oversample.load(Ordering::Relaxed).into() {
                 OverSampleType::None => {
                     output.modify_channels(|channel| {
                         channel.iter_mut().for_each(|o| *o = self.apply_curve(*o));
@@ -1,15 +1,23 @@
 //! AudioParam interface
-use std::slice::{Iter, IterMut};
-use std::sync::Arc;
-
-use crate::context::AudioContextRegistration;
-use crate::node::{
-    AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation,
-};
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::slice::{Iter, IterMut};
+
+#[cfg(feature = "std")]
+use crate::context::{AudioContextRegistration, AudioNodeId, BaseAudioContext};
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+#[cfg(feature = "std")]
+use crate::node::{AudioNode, ChannelConfig, GainNode, GainOptions};
+use crate::node::{ChannelConfigOptions, ChannelCountMode, ChannelInterpretation};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 use crate::{AtomicF32, RENDER_QUANTUM_SIZE};
 
+#[cfg(feature = "std")]
 use crossbeam_channel::{Receiver, Sender};
+#[cfg(feature = "std")]
 use lazy_static::lazy_static;
 
 // arguments sanity check functions for automation methods
@@ -82,6 +90,7 @@ enum AudioParamEventType {
     SetTargetAtTime,
     CancelAndHoldAtTime,
     SetValueCurveAtTime,
+    SmoothSetValue,
 }
 
 #[derive(Debug)]
@@ -95,6 +104,140 @@ pub(crate) struct AudioParamEvent {
     values: Option<Box<[f32]>>, // populated by `SetValueCurveAtTime` events
 }
 
+#[cfg(feature = "std")]
+impl AudioParamEvent {
+    /// Ramp from the current value to `target` in `duration` seconds, cancelling all other events
+    pub(crate) fn smooth_set_value(target: f32, duration: f64) -> Self {
+        Self {
+            event_type: AudioParamEventType::SmoothSetValue,
+            value: target,
+            time: 0., // time will be defined by the render thread
+            time_constant: None,
+            cancel_time: None,
+            duration: Some(duration),
+            values: None,
+        }
+    }
+}
+
+/// Automation event of an [`AudioParam`], to schedule in batch with
+/// [`AudioParam::apply_events`]
+///
+/// Each variant mirrors the automation method of the same name.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParamEvent {
+    /// See [`AudioParam::set_value_at_time`]
+    SetValueAtTime { value: f32, start_time: f64 },
+    /// See [`AudioParam::linear_ramp_to_value_at_time`]
+    LinearRampToValueAtTime { value: f32, end_time: f64 },
+    /// See [`AudioParam::exponential_ramp_to_value_at_time`]
+    ExponentialRampToValueAtTime { value: f32, end_time: f64 },
+    /// See [`AudioParam::set_target_at_time`]
+    SetTargetAtTime {
+        value: f32,
+        start_time: f64,
+        time_constant: f64,
+    },
+    /// See [`AudioParam::set_value_curve_at_time`]
+    SetValueCurveAtTime {
+        values: Vec<f32>,
+        start_time: f64,
+        duration: f64,
+    },
+    /// See [`AudioParam::cancel_scheduled_values`]
+    CancelScheduledValues { cancel_time: f64 },
+    /// See [`AudioParam::cancel_and_hold_at_time`]
+    CancelAndHoldAtTime { cancel_time: f64 },
+}
+
+impl ParamEvent {
+    /// Validate the arguments and build the event sent to the render thread
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the arguments are invalid, as the automation method of the same name
+    #[track_caller]
+    fn into_event(self) -> AudioParamEvent {
+        let event = |event_type, value, time| AudioParamEvent {
+            event_type,
+            value,
+            time,
+            time_constant: None,
+            cancel_time: None,
+            duration: None,
+            values: None,
+        };
+
+        match self {
+            Self::SetValueAtTime { value, start_time } => {
+                assert_non_negative(start_time);
+                event(AudioParamEventType::SetValueAtTime, value, start_time)
+            }
+            Self::LinearRampToValueAtTime { value, end_time } => {
+                assert_non_negative(end_time);
+                event(
+                    AudioParamEventType::LinearRampToValueAtTime,
+                    value,
+                    end_time,
+                )
+            }
+            Self::ExponentialRampToValueAtTime { value, end_time } => {
+                assert_not_zero(value);
+                assert_non_negative(end_time);
+                event(
+                    AudioParamEventType::ExponentialRampToValueAtTime,
+                    value,
+                    end_time,
+                )
+            }
+            Self::SetTargetAtTime {
+                value,
+                start_time,
+                time_constant,
+            } => {
+                assert_non_negative(start_time);
+                assert_non_negative(time_constant);
+
+                // [spec] If timeConstant is zero, the output value jumps immediately to the final value.
+                if time_constant == 0. {
+                    event(AudioParamEventType::SetValueAtTime, value, start_time)
+                } else {
+                    AudioParamEvent {
+                        time_constant: Some(time_constant),
+                        ..event(AudioParamEventType::SetTargetAtTime, value, start_time)
+                    }
+                }
+            }
+            Self::SetValueCurveAtTime {
+                values,
+                start_time,
+                duration,
+            } => {
+                assert_sequence_length(&values);
+                assert_non_negative(start_time);
+                assert_strictly_positive(duration);
+
+                AudioParamEvent {
+                    duration: Some(duration),
+                    values: Some(values.into_boxed_slice()),
+                    // value will be defined at the end of the event
+                    ..event(AudioParamEventType::SetValueCurveAtTime, 0., start_time)
+                }
+            }
+            Self::CancelScheduledValues { cancel_time } => {
+                assert_non_negative(cancel_time);
+                // no value
+                event(AudioParamEventType::CancelScheduledValues, 0., cancel_time)
+            }
+            Self::CancelAndHoldAtTime { cancel_time } => {
+                assert_non_negative(cancel_time);
+                // value will be defined by cancel event
+                event(AudioParamEventType::CancelAndHoldAtTime, 0., cancel_time)
+            }
+        }
+    }
+}
+
 // Event queue that contains `AudioParamEvent`s, most of the time, events must be
 // ordered (using stable sort), some operation may break this ordering (e.g. `push`)
 // in which cases `sort` must be called explicitely.
@@ -147,6 +290,12 @@ impl AudioParamEventTimeline {
         self.inner[0] = item;
     }
 
+    // an empty queue is never dirty
+    fn clear(&mut self) {
+        self.inner.clear();
+        self.dirty = false;
+    }
+
     fn is_empty(&self) -> bool {
         self.inner.is_empty()
     }
@@ -186,6 +335,7 @@ impl AudioParamEventTimeline {
 }
 
 /// AudioParam controls an individual aspect of an AudioNode's functionality, such as volume.
+#[cfg(feature = "std")]
 pub struct AudioParam {
     registration: AudioContextRegistration,
     automation_rate: AutomationRate, // treat as readonly for now
@@ -193,10 +343,54 @@ pub struct AudioParam {
     min_value: f32,                  // readonly
     max_value: f32,                  // readonly
     current_value: Arc<AtomicF32>,
-    sender: Sender<AudioParamEvent>,
+    /// events of a param with a mock registration, delivered straight to its processor
+    #[cfg(test)]
+    mock_events: Option<Sender<AudioParamEvent>>,
+}
+
+/// Read-only view on an [`AudioParam`]
+///
+/// - see also: [`AudioNodeMirror`](crate::node::AudioNodeMirror)
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct AudioParamMirror {
+    automation_rate: AutomationRate,
+    default_value: f32,
+    min_value: f32,
+    max_value: f32,
+    current_value: Arc<AtomicF32>,
+}
+
+#[cfg(feature = "std")]
+impl AudioParamMirror {
+    /// Automation rate of the mirrored `AudioParam`
+    pub fn automation_rate(&self) -> AutomationRate {
+        self.automation_rate
+    }
+
+    /// Default value of the mirrored `AudioParam`
+    pub fn default_value(&self) -> f32 {
+        self.default_value
+    }
+
+    /// Minimum value of the mirrored `AudioParam`
+    pub fn min_value(&self) -> f32 {
+        self.min_value
+    }
+
+    /// Maximum value of the mirrored `AudioParam`
+    pub fn max_value(&self) -> f32 {
+        self.max_value
+    }
+
+    /// Retrieve the current value of the mirrored `AudioParam`, see [`AudioParam::value`]
+    pub fn value(&self) -> f32 {
+        self.current_value.load()
+    }
 }
 
 // helper struct to attach / detach to context (for borrow reasons)
+#[cfg(feature = "std")]
 #[derive(Clone)]
 pub(crate) struct AudioParamRaw {
     automation_rate: AutomationRate,
@@ -204,18 +398,24 @@ pub(crate) struct AudioParamRaw {
     min_value: f32,
     max_value: f32,
     current_value: Arc<AtomicF32>,
-    sender: Sender<AudioParamEvent>,
 }
 
+/// Mixing of the inputs of an `AudioParam`, also used by the render thread
+///
+/// Multi-channel inputs are mixed down to mono with the speakers rules, as per spec.
+const AUDIO_PARAM_CHANNEL_CONFIG_OPTIONS: ChannelConfigOptions = ChannelConfigOptions {
+    count: 1,
+    mode: ChannelCountMode::Explicit,
+    interpretation: ChannelInterpretation::Speakers,
+};
+
+#[cfg(feature = "std")]
 lazy_static! {
-    static ref AUDIO_PARAM_CHANNEL_CONFIG: ChannelConfig = ChannelConfigOptions {
-        count: 1,
-        mode: ChannelCountMode::Explicit,
-        interpretation: ChannelInterpretation::Discrete,
-    }
-    .into();
+    static ref AUDIO_PARAM_CHANNEL_CONFIG: ChannelConfig =
+        AUDIO_PARAM_CHANNEL_CONFIG_OPTIONS.into();
 }
 
+#[cfg(feature = "std")]
 impl AudioNode for AudioParam {
     fn registration(&self) -> &AudioContextRegistration {
         &self.registration
@@ -244,6 +444,7 @@ impl AudioNode for AudioParam {
     }
 }
 
+#[cfg(feature = "std")]
 impl AudioParam {
     pub fn automation_rate(&self) -> AutomationRate {
         self.automation_rate
@@ -276,6 +477,34 @@ impl AudioParam {
         self.current_value.load()
     }
 
+    /// Modulate the `AudioParam` with the output of `source`, scaled by `depth`
+    ///
+    /// The signal connected to an `AudioParam` is summed with its intrinsic value: the value of
+    /// the param (or its automation) is the center of the modulation, and the output of
+    /// `source` swings it by `depth`. E.g. a sine LFO, ranging in [-1, 1], with a depth of 10
+    /// applies a vibrato of ±10 Hz to the frequency of an oscillator.
+    ///
+    /// The source is connected through an internal `GainNode`, owned by the returned
+    /// [`ModulationHandle`]. Dropping the handle removes the modulation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the source belongs to another context
+    pub fn modulate(&self, source: &dyn AudioNode, depth: f32) -> ModulationHandle {
+        let options = GainOptions {
+            gain: depth,
+            ..GainOptions::default()
+        };
+        let gain = GainNode::new(self.context(), options);
+        source.connect(&gain);
+        gain.connect(self);
+
+        ModulationHandle {
+            source: *source.registration().id(),
+            gain,
+        }
+    }
+
     /// Set the value of the `AudioParam`.
     ///
     /// Is equivalent to calling the `set_value_at_time` method with the current
@@ -314,19 +543,7 @@ impl AudioParam {
     ///
     /// Will panic if `start_time` is negative
     pub fn set_value_at_time(&self, value: f32, start_time: f64) -> &Self {
-        assert_non_negative(start_time);
-
-        let event = AudioParamEvent {
-            event_type: AudioParamEventType::SetValueAtTime,
-            value,
-            time: start_time,
-            time_constant: None,
-            cancel_time: None,
-            duration: None,
-            values: None,
-        };
-
-        self.send_event(event);
+        self.send_event(ParamEvent::SetValueAtTime { value, start_time }.into_event());
 
         self
     }
@@ -338,19 +555,7 @@ impl AudioParam {
     ///
     /// Will panic if `end_time` is negative
     pub fn linear_ramp_to_value_at_time(&self, value: f32, end_time: f64) -> &Self {
-        assert_non_negative(end_time);
-
-        let event = AudioParamEvent {
-            event_type: AudioParamEventType::LinearRampToValueAtTime,
-            value,
-            time: end_time,
-            time_constant: None,
-            cancel_time: None,
-            duration: None,
-            values: None,
-        };
-
-        self.send_event(event);
+        self.send_event(ParamEvent::LinearRampToValueAtTime { value, end_time }.into_event());
 
         self
     }
@@ -364,20 +569,7 @@ impl AudioParam {
     /// - `value` is zero
     /// - `end_time` is negative
     pub fn exponential_ramp_to_value_at_time(&self, value: f32, end_time: f64) -> &Self {
-        assert_not_zero(value);
-        assert_non_negative(end_time);
-
-        let event = AudioParamEvent {
-            event_type: AudioParamEventType::ExponentialRampToValueAtTime,
-            value,
-            time: end_time,
-            time_constant: None,
-            cancel_time: None,
-            duration: None,
-            values: None,
-        };
-
-        self.send_event(event);
+        self.send_event(ParamEvent::ExponentialRampToValueAtTime { value, end_time }.into_event());
 
         self
     }
@@ -391,33 +583,12 @@ impl AudioParam {
     /// - `start_time` is negative
     /// - `time_constant` is negative
     pub fn set_target_at_time(&self, value: f32, start_time: f64, time_constant: f64) -> &Self {
-        assert_non_negative(start_time);
-        assert_non_negative(time_constant);
-
-        // [spec] If timeConstant is zero, the output value jumps immediately to the final value.
-        let event = if time_constant == 0. {
-            AudioParamEvent {
-                event_type: AudioParamEventType::SetValueAtTime,
-                value,
-                time: start_time,
-                time_constant: None,
-                cancel_time: None,
-                duration: None,
-                values: None,
-            }
-        } else {
-            AudioParamEvent {
-                event_type: AudioParamEventType::SetTargetAtTime,
-                value,
-                time: start_time,
-                time_constant: Some(time_constant),
-                cancel_time: None,
-                duration: None,
-                values: None,
-            }
+        let event = ParamEvent::SetTargetAtTime {
+            value,
+            start_time,
+            time_constant,
         };
-
-        self.send_event(event);
+        self.send_event(event.into_event());
 
         self
     }
@@ -425,23 +596,17 @@ impl AudioParam {
     /// Cancels all scheduled parameter changes with times greater than or equal
     /// to `cancel_time`.
     ///
+    /// The param then holds the value of the remaining events. If an automation in progress
+    /// is cancelled (a ramp ending at or after `cancel_time`, a `set_target_at_time` or a
+    /// `set_value_curve_at_time` starting at or after it), the value from before the
+    /// automation is restored. A `set_target_at_time` starting before `cancel_time` is kept
+    /// and continues to converge, even if a cancelled event had ended it.
+    ///
     /// # Panics
     ///
     /// Will panic if `cancel_time` is negative
     pub fn cancel_scheduled_values(&self, cancel_time: f64) -> &Self {
-        assert_non_negative(cancel_time);
-
-        let event = AudioParamEvent {
-            event_type: AudioParamEventType::CancelScheduledValues,
-            value: 0., // no value
-            time: cancel_time,
-            time_constant: None,
-            cancel_time: None,
-            duration: None,
-            values: None,
-        };
-
-        self.send_event(event);
+        self.send_event(ParamEvent::CancelScheduledValues { cancel_time }.into_event());
 
         self
     }
@@ -454,19 +619,7 @@ impl AudioParam {
     ///
     /// Will panic if `cancel_time` is negative
     pub fn cancel_and_hold_at_time(&self, cancel_time: f64) -> &Self {
-        assert_non_negative(cancel_time);
-
-        let event = AudioParamEvent {
-            event_type: AudioParamEventType::CancelAndHoldAtTime,
-            value: 0., // value will be defined by cancel event
-            time: cancel_time,
-            time_constant: None,
-            cancel_time: None,
-            duration: None,
-            values: None,
-        };
-
-        self.send_event(event);
+        self.send_event(ParamEvent::CancelAndHoldAtTime { cancel_time }.into_event());
 
         self
     }
@@ -481,30 +634,97 @@ impl AudioParam {
     /// - `start_time` is negative
     /// - `duration` is negative or equal to zero
     pub fn set_value_curve_at_time(&self, values: &[f32], start_time: f64, duration: f64) -> &Self {
-        assert_sequence_length(values);
-        assert_non_negative(start_time);
-        assert_strictly_positive(duration);
-
         // When this method is called, an internal copy of the curve is
         // created for automation purposes.
-        let copy = values.to_vec();
-        let boxed_copy = copy.into_boxed_slice();
-
-        let event = AudioParamEvent {
-            event_type: AudioParamEventType::SetValueCurveAtTime,
-            value: 0., // value will be defined at the end of the event
-            time: start_time,
-            time_constant: None,
-            cancel_time: None,
-            duration: Some(duration),
-            values: Some(boxed_copy),
+        let event = ParamEvent::SetValueCurveAtTime {
+            values: values.to_vec(),
+            start_time,
+            duration,
         };
+        self.send_event(event.into_event());
 
-        self.send_event(event);
+        self
+    }
+
+    /// Schedules a batch of automation events
+    ///
+    /// The events are applied in order, as if the corresponding automation methods were
+    /// called one by one. They are validated as a whole and passed to the render thread in a
+    /// single control message, so they are applied in the same render quantum.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if any of the events is invalid, see the automation method of the same
+    /// name. None of the events is applied in this case.
+    pub fn apply_events(&self, events: &[ParamEvent]) -> &Self {
+        let events: Vec<_> = events.iter().cloned().map(ParamEvent::into_event).collect();
+        self.send_events(events);
 
         self
     }
 
+    /// Cancels all scheduled parameter changes from the current time, then schedules a batch
+    /// of automation events
+    ///
+    /// The cancellation and the events are applied in the same render quantum, see
+    /// [`apply_events`](Self::apply_events).
+    ///
+    /// # Panics
+    ///
+    /// Will panic if any of the events is invalid, see the automation method of the same
+    /// name. Nothing is cancelled nor applied in this case.
+    pub fn clear_and_apply_events(&self, events: &[ParamEvent]) -> &Self {
+        let cancel_time = self.context().current_time();
+        let events: Vec<_> = std::iter::once(ParamEvent::CancelScheduledValues { cancel_time })
+            .chain(events.iter().cloned())
+            .map(ParamEvent::into_event)
+            .collect();
+        self.send_events(events);
+
+        self
+    }
+
+    /// Smoothly change the value of the `AudioParam` to `target` over `smoothing_ms` milliseconds
+    ///
+    /// This cancels all scheduled parameter changes and linearly ramps from the value that is
+    /// currently rendered to the target value, without introducing a discontinuity. It is
+    /// equivalent to calling `cancel_scheduled_values`, `set_value_at_time` with the current
+    /// value and `linear_ramp_to_value_at_time`, but these are applied at once by the render
+    /// thread.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `smoothing_ms` is negative
+    pub fn smooth_set_value(&self, target: f32, smoothing_ms: f32) -> &Self {
+        assert_non_negative(smoothing_ms as f64);
+
+        let sample_rate = self.context().sample_rate() as f64;
+        let smoothing_samples = (smoothing_ms as f64 / 1000. * sample_rate).round() as usize;
+
+        if let Some(mock_events) = self.mock_events() {
+            // bypass audiocontext enveloping of control messages for simpler testing
+            let duration = smoothing_ms as f64 / 1000.;
+            let event = AudioParamEvent::smooth_set_value(target, duration);
+            mock_events.send(event).unwrap();
+        } else {
+            self.context()
+                .pass_smooth_set_value(self.registration.id(), target, smoothing_samples);
+        }
+
+        self
+    }
+
+    /// Read-only view on this `AudioParam`, sharing its current value
+    pub(crate) fn mirror(&self) -> AudioParamMirror {
+        AudioParamMirror {
+            automation_rate: self.automation_rate,
+            default_value: self.default_value,
+            min_value: self.min_value,
+            max_value: self.max_value,
+            current_value: self.current_value.clone(),
+        }
+    }
+
     // helper function to detach from context (for borrow reasons)
     pub(crate) fn into_raw_parts(self) -> AudioParamRaw {
         AudioParamRaw {
@@ -513,7 +733,6 @@ impl AudioParam {
             min_value: self.min_value,
             max_value: self.max_value,
             current_value: self.current_value,
-            sender: self.sender,
         }
     }
 
@@ -529,32 +748,83 @@ impl AudioParam {
             min_value: parts.min_value,
             max_value: parts.max_value,
             current_value: parts.current_value,
-            sender: parts.sender,
+            #[cfg(test)]
+            mock_events: None,
         }
     }
 
     fn send_event(&self, event: AudioParamEvent) {
-        if cfg!(test) {
+        if let Some(mock_events) = self.mock_events() {
             // bypass audiocontext enveloping of control messages for simpler testing
-            self.sender.send(event).unwrap();
+            mock_events.send(event).unwrap();
         } else {
-            self.context().pass_audio_param_event(&self.sender, event);
+            self.context()
+                .pass_audio_param_event(self.registration.id(), event);
         }
     }
+
+    fn send_events(&self, events: Vec<AudioParamEvent>) {
+        if let Some(mock_events) = self.mock_events() {
+            // bypass audiocontext enveloping of control messages for simpler testing
+            events
+                .into_iter()
+                .for_each(|event| mock_events.send(event).unwrap());
+        } else {
+            let id = self.registration.id();
+            let events = events.into_iter().map(|event| (id, event)).collect();
+            self.context().pass_audio_param_events(events);
+        }
+    }
+
+    /// Params with a mock registration are not part of an audio graph, in tests their
+    /// processor is driven manually and receives the events on this channel
+    #[cfg(test)]
+    fn mock_events(&self) -> Option<&Sender<AudioParamEvent>> {
+        self.mock_events.as_ref()
+    }
+
+    #[cfg(not(test))]
+    #[allow(clippy::unused_self)]
+    fn mock_events(&self) -> Option<&Sender<AudioParamEvent>> {
+        None
+    }
+}
+
+/// A `SetTarget` event as it was scheduled, see `AudioParamProcessor::interrupted_set_target`
+#[derive(Debug)]
+struct InterruptedSetTarget {
+    start_time: f64,
+    /// value of the param before the event
+    start_value: f32,
+    target: f32,
+    time_constant: f64,
 }
 
+/// Rendering part of an `AudioParam`, evaluates its automation events
+///
+/// The processor can also be used on its own, see [`AudioParamProcessor::new`].
 #[derive(Debug)]
-pub(crate) struct AudioParamProcessor {
+pub struct AudioParamProcessor {
     intrisic_value: f32,
     current_value: Arc<AtomicF32>,
+    /// events sent by the control thread
+    #[cfg(feature = "std")]
     receiver: Receiver<AudioParamEvent>,
+    /// events scheduled on the processor itself, see [`AudioParamProcessor::schedule`]
+    scheduled: VecDeque<AudioParamEvent>,
     automation_rate: AutomationRate,
     default_value: f32,
     min_value: f32,
     max_value: f32,
     event_timeline: AudioParamEventTimeline,
     last_event: Option<AudioParamEvent>,
+    /// `SetTarget` event ended by the start of the next event, which resumes if the next
+    /// event is cancelled
+    interrupted_set_target: Option<InterruptedSetTarget>,
     buffer: Vec<f32>,
+    /// Compute the values at k-rate in the current render quantum, as the render thread is
+    /// overloaded
+    force_k_rate: bool,
 }
 
 impl AudioProcessor for AudioParamProcessor {
@@ -565,8 +835,7 @@ impl AudioProcessor for AudioParamProcessor {
         _params: AudioParamValues,
         scope: &RenderScope,
     ) -> bool {
-        let period = 1. / scope.sample_rate as f64;
-        let param_intrisic_values = self.tick(scope.current_time, period, RENDER_QUANTUM_SIZE);
+        let param_intrisic_values = self.compute(scope);
 
         let input = &inputs[0]; // single input mode
         let param_computed_values = &mut outputs[0];
@@ -575,14 +844,91 @@ impl AudioProcessor for AudioParamProcessor {
             .channel_data_mut(0)
             .copy_from_slice(param_intrisic_values);
 
-        param_computed_values.add(input, &AUDIO_PARAM_CHANNEL_CONFIG);
+        param_computed_values.add(input, &AUDIO_PARAM_CHANNEL_CONFIG_OPTIONS);
 
         true // has intrinsic value
     }
+
+    fn as_audio_param(&mut self) -> Option<&mut AudioParamProcessor> {
+        Some(self)
+    }
+}
+
+/// Index in the block of the first frame at or after `time`
+///
+/// Frame `i` of the block starts at `block_time + i * dt`, an event scheduled between two frames
+/// thus takes effect on the next frame. Rounding errors below a millionth of a frame are
+/// ignored, so that events scheduled exactly on a frame are not delayed.
+#[inline(always)]
+fn first_frame_index(time: f64, block_time: f64, dt: f64) -> usize {
+    let position = (time - block_time) / dt;
+    (position - 1e-6).ceil().max(0.) as usize
 }
 
 impl AudioParamProcessor {
-    pub fn intrisic_value(&self) -> f32 {
+    /// Processor evaluating the automation of a param described by `descriptor`, outside of
+    /// an audio graph
+    ///
+    /// Events are scheduled with [`Self::schedule`], the values of each render quantum are
+    /// computed with [`Self::compute`].
+    pub fn new(descriptor: AudioParamDescriptor) -> Self {
+        Self {
+            intrisic_value: descriptor.default_value,
+            current_value: Arc::new(AtomicF32::new(descriptor.default_value)),
+            #[cfg(feature = "std")]
+            receiver: crossbeam_channel::never(),
+            scheduled: VecDeque::new(),
+            automation_rate: descriptor.automation_rate,
+            default_value: descriptor.default_value,
+            min_value: descriptor.min_value,
+            max_value: descriptor.max_value,
+            event_timeline: AudioParamEventTimeline::new(),
+            last_event: None,
+            interrupted_set_target: None,
+            buffer: Vec::with_capacity(RENDER_QUANTUM_SIZE),
+            force_k_rate: false,
+        }
+    }
+
+    /// Schedule an automation event, it is applied at the next call to [`Self::compute`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if the event is invalid, with the same rules as the `AudioParam` methods.
+    #[track_caller]
+    pub fn schedule(&mut self, event: ParamEvent) {
+        self.scheduled.push_back(event.into_event());
+    }
+
+    /// Compute the values of the param for the render quantum described by `scope`
+    ///
+    /// The returned slice holds one value per frame of the quantum.
+    pub fn compute(&mut self, scope: &RenderScope) -> &[f32] {
+        let period = 1. / scope.sample_rate as f64;
+        self.force_k_rate = scope.reduced_quality();
+        self.tick(scope.current_time, period, RENDER_QUANTUM_SIZE)
+    }
+
+    /// Open the channel on which the render thread delivers the events sent by the control
+    /// thread, it replaces the previous channel
+    #[cfg(feature = "std")]
+    pub(crate) fn open_event_channel(&mut self) -> Sender<AudioParamEvent> {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        self.receiver = receiver;
+        sender
+    }
+
+    /// Next event to insert in the timeline, events sent by the control thread come first
+    fn next_event(&mut self) -> Option<AudioParamEvent> {
+        #[cfg(feature = "std")]
+        if let Ok(event) = self.receiver.try_recv() {
+            return Some(event);
+        }
+
+        self.scheduled.pop_front()
+    }
+
+    fn intrisic_value(&self) -> f32 {
         if self.intrisic_value.is_nan() {
             self.default_value
         } else {
@@ -645,11 +991,14 @@ impl AudioParamProcessor {
         values: &[f32],
         time: f64,
     ) -> f32 {
-        if time - start_time >= duration {
+        // the frame time can be slightly before `start_time` due to rounding errors
+        let elapsed = (time - start_time).max(0.);
+
+        if elapsed >= duration {
             let value = values[values.len() - 1];
             value.clamp(self.min_value, self.max_value)
         } else {
-            let position = (values.len() - 1) as f64 * (time - start_time) / duration;
+            let position = (values.len() - 1) as f64 * elapsed / duration;
             let k = position as usize;
             let phase = (position - position.floor()) as f32;
             let value = (values[k + 1] - values[k]) * phase + values[k];
@@ -670,70 +1019,74 @@ impl AudioParamProcessor {
         // automation events are added for the time range.
         let mut events_received = false;
 
-        for event in self.receiver.try_iter() {
+        while let Some(event) = self.next_event() {
             events_received = true;
 
-            // @note - the following could live in its own method just for clarity
-            // but can't get rid of this error:
-            //    for event in self.receiver.try_iter() {
-            //                 ------------------------
-            //                 |
-            //                 immutable borrow occurs here
-            //                 immutable borrow later used here
-            //
-            //        self.insert_event(event);
-            //            ^^^^^^^^^^^^^^^^^^^^^^^^ mutable borrow occurs here
-
             // handle CancelScheduledValues events
             // cf. https://www.w3.org/TR/webaudio/#dom-audioparam-cancelscheduledvalues
             if event.event_type == AudioParamEventType::CancelScheduledValues {
                 // peek current event before inserting new events, and possibly sort
                 // the queue, we need that for checking that we are (or not) in the middle
-                // of a ramp when handling `CancelScheduledValues`
+                // of an automation when handling `CancelScheduledValues`
                 // @note - probably not robust enough in some edge cases where the
                 // event is not the first received at this tick (`SetValueCurveAtTime`
                 // and `CancelAndHold` need to sort the queue)
-                let some_current_event = self.event_timeline.unsorted_peek();
-
-                match some_current_event {
-                    None => (),
-                    Some(current_event) => {
-                        // @note - The spec is not particularly clear about what to do
-                        // with on-going SetTarget events, but both Chrome and Firefox
-                        // ignore the Cancel event if at same time nor if startTime is
-                        // equal to cancelTime, this can makes sens as the event time
-                        // (which is a `startTime` for `SetTarget`) is before the `cancelTime`
-                        // (maybe this should be clarified w/ spec editors)
-
-                        match current_event.event_type {
+                let current_automation = self
+                    .event_timeline
+                    .unsorted_peek()
+                    .filter(|current_event| current_event.time >= event.time)
+                    .map(|current_event| &current_event.event_type)
+                    .filter(|event_type| {
+                        matches!(
+                            event_type,
                             AudioParamEventType::LinearRampToValueAtTime
-                            | AudioParamEventType::ExponentialRampToValueAtTime => {
-                                // we are in the middle of a ramp
-                                //
-                                // @note - Firefox and Chrome behave differently
-                                // on this: Firefox actually restore intrisic_value
-                                // from the value at the beginning of the vent, while
-                                // Chrome just keeps the current intrisic_value
-                                // The spec is not very clear there, but Firefox
-                                // seems to be the more compliant:
-                                // "Any active automations whose automation event
-                                // time is less than cancelTime are also cancelled,
-                                // and such cancellations may cause discontinuities
-                                // because the original value (**from before such
-                                // automation**) is restored immediately."
-                                //
-                                // @note - last_event cannot be `None` here, because
-                                // linear or exponential ramps are always preceded
-                                // by another event (even a set_value_at_time
-                                // inserted implicitly), so if the ramp is the next
-                                // event that means that at least one event has
-                                // already been processed.
-                                if current_event.time >= event.time {
-                                    let last_event = self.last_event.as_ref().unwrap();
-                                    self.intrisic_value = last_event.value;
+                                | AudioParamEventType::ExponentialRampToValueAtTime
+                                | AudioParamEventType::SetTargetAtTime
+                                | AudioParamEventType::SetValueCurveAtTime
+                        )
+                    });
+
+                let mut resumed = None;
+
+                // the automation in progress (or about to start) is removed
+                //
+                // @note - Firefox and Chrome behave differently on this: Firefox
+                // actually restore intrisic_value from the value at the beginning of
+                // the event, while Chrome just keeps the current intrisic_value.
+                // The spec is not very clear there, but Firefox seems to be the more
+                // compliant:
+                // "Any active automations whose automation event time is less than
+                // cancelTime are also cancelled, and such cancellations may cause
+                // discontinuities because the original value (**from before such
+                // automation**) is restored immediately."
+                //
+                // A ramp always follows another event (even a set_value_at_time
+                // inserted implicitly), as does a SetTarget, so `last_event` is only
+                // `None` if that event is still in the queue, and removed as well.
+                if current_automation.is_some() {
+                    if let Some(last_event) = self.last_event.as_mut() {
+                        self.intrisic_value = last_event.value;
+
+                        // The removed event ended a SetTarget, which started before the
+                        // cancel time and survives, continuing to converge, or is
+                        // removed as well and the value from before it is restored.
+                        if last_event.event_type == AudioParamEventType::SetTargetAtTime {
+                            if let Some(set_target) = self.interrupted_set_target.take() {
+                                *last_event = AudioParamEvent {
+                                    event_type: AudioParamEventType::SetValueAtTime,
+                                    value: set_target.start_value,
+                                    time: set_target.start_time,
+                                    time_constant: None,
+                                    cancel_time: None,
+                                    duration: None,
+                                    values: None,
+                                };
+                                self.intrisic_value = set_target.start_value;
+
+                                if set_target.start_time < event.time {
+                                    resumed = Some(set_target);
                                 }
                             }
-                            _ => (),
                         }
                     }
                 }
@@ -741,6 +1094,26 @@ impl AudioParamProcessor {
                 // remove all event in queue where cancel_time >= event.time
                 self.event_timeline
                     .retain(|queued| queued.time < event.time);
+
+                if let Some(set_target) = resumed {
+                    self.intrisic_value = self.compute_set_target_sample(
+                        set_target.start_time,
+                        set_target.time_constant,
+                        set_target.target,
+                        set_target.start_value - set_target.target,
+                        block_time,
+                    );
+                    self.event_timeline.push(AudioParamEvent {
+                        event_type: AudioParamEventType::SetTargetAtTime,
+                        value: set_target.target,
+                        time: set_target.start_time,
+                        time_constant: Some(set_target.time_constant),
+                        cancel_time: None,
+                        duration: None,
+                        values: None,
+                    });
+                }
+
                 continue; // no need to insert cancel_values events in queue
             }
 
@@ -821,6 +1194,46 @@ impl AudioParamProcessor {
                 continue; // cancel_and_hold events are not inserted timeline
             }
 
+            // handle SmoothSetValue events: cancel all scheduled events and ramp
+            // from the current intrisic value to the target value, the intrisic
+            // value being the value rendered at the end of the previous block
+            if event.event_type == AudioParamEventType::SmoothSetValue {
+                let duration = event.duration.unwrap();
+                // without smoothing, jump directly to the target value
+                if duration == 0. {
+                    self.intrisic_value = event.value;
+                }
+
+                self.event_timeline.clear();
+
+                let anchor_event = AudioParamEvent {
+                    event_type: AudioParamEventType::SetValue,
+                    value: self.intrisic_value(),
+                    // time will be replaced by the block timestamp during event processing
+                    time: 0.,
+                    time_constant: None,
+                    cancel_time: None,
+                    duration: None,
+                    values: None,
+                };
+                self.event_timeline.push(anchor_event);
+
+                if duration > 0. {
+                    let ramp_event = AudioParamEvent {
+                        event_type: AudioParamEventType::LinearRampToValueAtTime,
+                        value: event.value,
+                        time: block_time + duration,
+                        time_constant: None,
+                        cancel_time: None,
+                        duration: None,
+                        values: None,
+                    };
+                    self.event_timeline.push(ramp_event);
+                }
+
+                continue; // smooth_set_value events are not inserted in timeline
+            }
+
             // handle SetValueCurveAtTime
             // @note - These rules argue in favor of having events inserted in
             // the control thread, let's panic for now
@@ -919,7 +1332,7 @@ impl AudioParamProcessor {
         self.buffer.clear();
 
         let next_block_time = block_time + dt * count as f64;
-        let is_a_rate = self.automation_rate == AutomationRate::A;
+        let is_a_rate = self.automation_rate == AutomationRate::A && !self.force_k_rate;
         let is_k_rate = !is_a_rate;
 
         if is_k_rate {
@@ -955,7 +1368,7 @@ impl AudioParamProcessor {
 
                             // fill buffer with current intrisic value until `event.time`
                             if is_a_rate {
-                                let end_index = ((time - block_time).max(0.) / dt) as usize;
+                                let end_index = first_frame_index(time, block_time, dt);
                                 let end_index_clipped = end_index.min(count);
 
                                 for _ in self.buffer.len()..end_index_clipped {
@@ -1000,18 +1413,15 @@ impl AudioParamProcessor {
 
                             if is_a_rate {
                                 let start_index = self.buffer.len();
-                                // we need to `ceil()` because if `end_time` is between two samples
-                                // we actually want the sample before `end_time` to be computed
-                                let end_index =
-                                    ((end_time - block_time).max(0.) / dt).ceil() as usize;
+                                // the event ends at the first frame at or after `end_time`
+                                let end_index = first_frame_index(end_time, block_time, dt);
                                 let end_index_clipped = end_index.min(count);
 
                                 // compute "real" value according to `t` then clamp it
                                 // cf. Example 7 https://www.w3.org/TR/webaudio/#computation-of-value
                                 if end_index_clipped > self.buffer.len() {
-                                    let mut time = block_time + start_index as f64 * dt;
-
-                                    for _ in start_index..end_index_clipped {
+                                    for index in start_index..end_index_clipped {
+                                        let time = block_time + index as f64 * dt;
                                         let value = self.compute_linear_ramp_sample(
                                             start_time,
                                             duration,
@@ -1020,8 +1430,6 @@ impl AudioParamProcessor {
                                             time,
                                         );
                                         self.buffer.push(value);
-
-                                        time += dt;
                                         self.intrisic_value = value;
                                     }
                                 }
@@ -1106,17 +1514,13 @@ impl AudioParamProcessor {
                             } else {
                                 if is_a_rate {
                                     let start_index = self.buffer.len();
-                                    // we need to `ceil()` because if `end_time` is between two samples
-                                    // we actually want the sample before `end_time` to be computed
-                                    // @todo - more tests
-                                    let end_index =
-                                        ((end_time - block_time).max(0.) / dt).ceil() as usize;
+                                    // the event ends at the first frame at or after `end_time`
+                                    let end_index = first_frame_index(end_time, block_time, dt);
                                     let end_index_clipped = end_index.min(count);
 
                                     if end_index_clipped > self.buffer.len() {
-                                        let mut time = block_time + start_index as f64 * dt;
-
-                                        for _ in start_index..end_index_clipped {
+                                        for index in start_index..end_index_clipped {
+                                            let time = block_time + index as f64 * dt;
                                             let value = self.compute_exponential_ramp_sample(
                                                 start_time,
                                                 duration,
@@ -1127,8 +1531,6 @@ impl AudioParamProcessor {
 
                                             self.buffer.push(value);
                                             self.intrisic_value = value;
-
-                                            time += dt;
                                         }
                                     }
                                 }
@@ -1182,6 +1584,23 @@ impl AudioParamProcessor {
                         // inserted in the timeline. This could be done at k-rate.
                         // Note that Chrome has such strategy, cf. `HasSetTargetConverged`
                         AudioParamEventType::SetTargetAtTime => {
+                            let start_time = event.time;
+
+                            // fill buffer with current intrisic value until `start_time`
+                            if is_a_rate {
+                                let start_index = first_frame_index(start_time, block_time, dt);
+                                let start_index_clipped = start_index.min(count);
+
+                                for _ in self.buffer.len()..start_index_clipped {
+                                    self.buffer.push(self.intrisic_value());
+                                }
+                            }
+
+                            // event starts in a next block
+                            if start_time > next_block_time {
+                                break;
+                            }
+
                             let mut end_time = next_block_time;
                             let mut ended = false;
 
@@ -1226,7 +1645,6 @@ impl AudioParamProcessor {
                                 }
                             }
 
-                            let start_time = event.time;
                             // if SetTarget is the first event registered, we implicitely
                             // insert a SetValue event just before just as for Ramps.
                             // Therefore we are sure last_event exists
@@ -1237,17 +1655,13 @@ impl AudioParamProcessor {
 
                             if is_a_rate {
                                 let start_index = self.buffer.len();
-                                // we need to `ceil()` because if `end_time` is between two samples
-                                // we actually want the sample before `end_time` to be computed
-                                // @todo - more tests
-                                let end_index =
-                                    ((end_time - block_time).max(0.) / dt).ceil() as usize;
+                                // the event ends at the first frame at or after `end_time`
+                                let end_index = first_frame_index(end_time, block_time, dt);
                                 let end_index_clipped = end_index.min(count);
 
                                 if end_index_clipped > self.buffer.len() {
-                                    let mut time = block_time + start_index as f64 * dt;
-
-                                    for _ in start_index..end_index_clipped {
+                                    for index in start_index..end_index_clipped {
+                                        let time = block_time + index as f64 * dt;
                                         let value = self.compute_set_target_sample(
                                             start_time,
                                             time_constant,
@@ -1258,8 +1672,6 @@ impl AudioParamProcessor {
 
                                         self.buffer.push(value);
                                         self.intrisic_value = value;
-
-                                        time += dt;
                                     }
                                 }
                             }
@@ -1279,7 +1691,9 @@ impl AudioParamProcessor {
                                 break;
                             } else {
                                 // setTarget has no "real" end value, compute according
-                                // to next event start time
+                                // to next event start time. If the event has not started
+                                // yet, it ends at its start time with the previous value
+                                let end_time = end_time.max(start_time);
                                 let value = self.compute_set_target_sample(
                                     start_time,
                                     time_constant,
@@ -1289,6 +1703,18 @@ impl AudioParamProcessor {
                                 );
 
                                 self.intrisic_value = value;
+                                // the event resumes if the next event is cancelled,
+                                // unless it is held by a `CancelAndHoldAtTime`
+                                self.interrupted_set_target = if event.cancel_time.is_none() {
+                                    Some(InterruptedSetTarget {
+                                        start_time,
+                                        start_value,
+                                        target: end_value,
+                                        time_constant,
+                                    })
+                                } else {
+                                    None
+                                };
                                 // end_value and end_time must be stored for use
                                 // as start time by next event
                                 let mut event = self.event_timeline.pop().unwrap();
@@ -1299,6 +1725,22 @@ impl AudioParamProcessor {
                         }
                         AudioParamEventType::SetValueCurveAtTime => {
                             let start_time = event.time;
+
+                            // fill buffer with current intrisic value until `start_time`
+                            if is_a_rate {
+                                let start_index = first_frame_index(start_time, block_time, dt);
+                                let start_index_clipped = start_index.min(count);
+
+                                for _ in self.buffer.len()..start_index_clipped {
+                                    self.buffer.push(self.intrisic_value());
+                                }
+                            }
+
+                            // event starts in a next block
+                            if start_time > next_block_time {
+                                break;
+                            }
+
                             let duration = event.duration.unwrap();
                             let values = event.values.as_ref().unwrap();
                             let mut end_time = start_time + duration;
@@ -1312,25 +1754,19 @@ impl AudioParamProcessor {
 
                             if is_a_rate {
                                 let start_index = self.buffer.len();
-                                // we need to `ceil()` because if `end_time` is between two samples
-                                // we actually want the sample before `end_time` to be computed
-                                // @todo - more tests
-                                let end_index =
-                                    ((end_time - block_time).max(0.) / dt).ceil() as usize;
+                                // the event ends at the first frame at or after `end_time`
+                                let end_index = first_frame_index(end_time, block_time, dt);
                                 let end_index_clipped = end_index.min(count);
 
                                 if end_index_clipped > self.buffer.len() {
-                                    let mut time = block_time + start_index as f64 * dt;
-
-                                    for _ in start_index..end_index_clipped {
+                                    for index in start_index..end_index_clipped {
+                                        let time = block_time + index as f64 * dt;
                                         let value = self.compute_set_value_curve_sample(
                                             start_time, duration, values, time,
                                         );
 
                                         self.buffer.push(value);
                                         self.intrisic_value = value;
-
-                                        time += dt;
                                     }
                                 }
                             }
@@ -1394,11 +1830,60 @@ impl AudioParamProcessor {
     }
 }
 
+/// Schedule value changes of several `AudioParam`s of the same context at once
+///
+/// The events are passed to the render thread in a single control message, so they are
+/// applied in the same render quantum.
+///
+/// # Panics
+///
+/// Will panic if `start_time` is negative
+#[cfg(feature = "std")]
+pub(crate) fn set_values_at_time(values: &[(&AudioParam, f32)], start_time: f64) {
+    assert_non_negative(start_time);
+
+    let event = |value| AudioParamEvent {
+        event_type: AudioParamEventType::SetValueAtTime,
+        value,
+        time: start_time,
+        time_constant: None,
+        cancel_time: None,
+        duration: None,
+        values: None,
+    };
+
+    let first = match values.first() {
+        Some((param, _)) => param,
+        None => return,
+    };
+
+    if first.mock_events().is_some() {
+        values
+            .iter()
+            .for_each(|(param, value)| param.send_event(event(*value)));
+    } else {
+        let events = values
+            .iter()
+            .map(|(param, value)| (param.registration.id(), event(*value)))
+            .collect();
+        first.context().pass_audio_param_events(events);
+    }
+}
+
+#[cfg(feature = "std")]
 pub(crate) fn audio_param_pair(
     opts: AudioParamDescriptor,
     registration: AudioContextRegistration,
 ) -> (AudioParam, AudioParamProcessor) {
-    let (sender, receiver) = crossbeam_channel::unbounded();
+    // The render thread opens the event channel of the processor once the param is registered
+    // in the audio graph, only params with a mock registration get theirs right away
+    #[cfg(test)]
+    let (mock_events, receiver) = if *registration.id() == crate::context::MOCK_NODE_ID {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        (Some(sender), receiver)
+    } else {
+        (None, crossbeam_channel::never())
+    };
     let current_value = Arc::new(AtomicF32::new(opts.default_value));
 
     let param = AudioParam {
@@ -1408,30 +1893,135 @@ pub(crate) fn audio_param_pair(
         min_value: opts.min_value,
         max_value: opts.max_value,
         current_value: current_value.clone(),
-        sender,
+        #[cfg(test)]
+        mock_events,
+    };
+
+    let render = AudioParamProcessor {
+        current_value,
+        #[cfg(test)]
+        receiver,
+        ..AudioParamProcessor::new(opts)
+    };
+
+    (param, render)
+}
+
+/// Modulation of an [`AudioParam`], see [`AudioParam::modulate`]
+///
+/// Dropping the handle removes the modulation.
+#[cfg(feature = "std")]
+pub struct ModulationHandle {
+    /// node feeding into the internal gain
+    source: AudioNodeId,
+    /// scales the source by the depth
+    gain: GainNode,
+}
+
+#[cfg(feature = "std")]
+impl ModulationHandle {
+    /// Set the depth of the modulation, i.e. the gain applied to the source
+    pub fn set_depth(&self, depth: f32) {
+        self.gain.gain().set_value(depth);
+    }
+
+    /// The depth of the modulation, as an `AudioParam` to allow its automation
+    #[must_use]
+    pub fn depth(&self) -> &AudioParam {
+        self.gain.gain()
+    }
+
+    /// Remove the modulation, same as dropping the handle
+    pub fn disconnect(self) {}
+}
+
+#[cfg(feature = "std")]
+impl Drop for ModulationHandle {
+    fn drop(&mut self) {
+        let gain = self.gain.registration().id();
+        self.gain.context().release_modulation(&self.source, gain);
+    }
+}
+
+/// Multi-channel control signal of a custom node, not mixed down to mono
+///
+/// Unlike an [`AudioParam`], a `ControlInput` has neither an intrinsic value nor automation:
+/// its values are the sum of the signals connected to it, mixed to its channel count with the
+/// speakers rules (see [`AudioNode::set_channel_count`]). This allows e.g. per-channel gain
+/// envelopes driven by a stereo signal.
+///
+/// Create it with [`BaseAudioContext::create_control_input`] inside the `register` closure of
+/// the node, and read its values with
+/// [`AudioParamValues::get_multichannel`](crate::render::AudioParamValues::get_multichannel).
+#[cfg(feature = "std")]
+pub struct ControlInput {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+}
+
+#[cfg(feature = "std")]
+impl AudioNode for ControlInput {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+/// Rendering part of a `ControlInput`, passes its mixed input through
+#[cfg(feature = "std")]
+struct ControlInputProcessor;
+
+#[cfg(feature = "std")]
+impl AudioProcessor for ControlInputProcessor {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        outputs[0] = inputs[0].clone();
+
+        true // lives as long as the node it feeds into, like an AudioParam
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn control_input_pair(
+    number_of_channels: usize,
+    registration: AudioContextRegistration,
+) -> (ControlInput, impl AudioProcessor) {
+    let channel_config = ChannelConfigOptions {
+        count: number_of_channels,
+        mode: ChannelCountMode::Explicit,
+        interpretation: ChannelInterpretation::Speakers,
     };
 
-    let render = AudioParamProcessor {
-        intrisic_value: opts.default_value,
-        current_value,
-        receiver,
-        automation_rate: opts.automation_rate,
-        default_value: opts.default_value,
-        min_value: opts.min_value,
-        max_value: opts.max_value,
-        event_timeline: AudioParamEventTimeline::new(),
-        last_event: None,
-        buffer: Vec::with_capacity(RENDER_QUANTUM_SIZE),
+    let input = ControlInput {
+        registration,
+        channel_config: channel_config.into(),
     };
 
-    (param, render)
+    (input, ControlInputProcessor)
 }
 
 #[cfg(test)]
 mod tests {
     use float_eq::assert_float_eq;
 
-    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::context::{AudioParamId, BaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioScheduledSourceNode;
 
     use super::*;
 
@@ -1653,6 +2243,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_linear_ramp_forced_krate() {
+        let context = OfflineAudioContext::new(1, 0, 48000.);
+
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        param.set_value_at_time(0., 0.);
+        // ramp to 9 from t = 0 to t = 9
+        param.linear_ramp_to_value_at_time(9., 9.);
+
+        let vs = render.tick(0., 1., 3);
+        assert_float_eq!(vs, &[0., 1., 2.][..], abs_all <= 0.);
+
+        // the render thread is overloaded, the block is computed at k-rate
+        render.force_k_rate = true;
+        let vs = render.tick(3., 1., 3);
+        assert_float_eq!(vs, &[3.; 3][..], abs_all <= 0.);
+
+        // back to full quality, the ramp is not affected
+        render.force_k_rate = false;
+        let vs = render.tick(6., 1., 3);
+        assert_float_eq!(vs, &[6., 7., 8.][..], abs_all <= 0.);
+    }
+
     #[test]
     fn test_linear_ramp_arate_end_of_block() {
         let context = OfflineAudioContext::new(1, 0, 48000.);
@@ -2350,6 +2970,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_smooth_set_value() {
+        let context = OfflineAudioContext::new(1, 0, 48000.);
+
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: 0.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        param.set_value_at_time(0., 0.);
+        param.linear_ramp_to_value_at_time(10., 10.);
+
+        let vs = render.tick(0., 1., 5);
+        assert_float_eq!(vs, &[0., 1., 2., 3., 4.][..], abs_all <= 0.);
+
+        // cancels the ongoing ramp, and ramps from the current value
+        param.smooth_set_value(1., 2000.);
+
+        let vs = render.tick(5., 1., 5);
+        assert_float_eq!(vs, &[5., 3., 1., 1., 1.][..], abs_all <= 0.);
+
+        // no smoothing: jump to the target value
+        param.smooth_set_value(8., 0.);
+
+        let vs = render.tick(10., 1., 5);
+        assert_float_eq!(vs, &[8.; 5][..], abs_all <= 0.);
+    }
+
     #[test]
     fn test_cancel_scheduled_values_ramp() {
         let context = OfflineAudioContext::new(1, 0, 48000.);
@@ -2439,6 +3090,301 @@ mod tests {
         }
     }
 
+    type ScheduleEvent = fn(&AudioParam);
+
+    /// Name of the case, event to cancel, cancel time, and expected value at a given time
+    type CancelCase = (&'static str, ScheduleEvent, f64, fn(f64) -> f32);
+
+    // [spec] v(t) = V1 + (V0 - V1) * e^(-(t - T0) / tau), from 1 to 11 starting at 5
+    fn set_target_from_5(t: f64) -> f32 {
+        if t < 5. {
+            1.
+        } else {
+            (11. - 10. * (-(t - 5.) / 5.).exp()) as f32
+        }
+    }
+
+    #[test]
+    fn test_cancel_scheduled_values_per_event_type() {
+        let context = OfflineAudioContext::new(1, 0, 48000.);
+
+        // after a `set_value(1.)`, the event is scheduled at t = 0, cancelled at
+        // t = 10 with the given cancel time, the param is rendered until t = 30
+        let cases: [CancelCase; 16] = [
+            // instantaneous event at t = 15
+            (
+                "set value, before",
+                |p| {
+                    p.set_value_at_time(5., 15.);
+                },
+                10.,
+                |_| 1.,
+            ),
+            (
+                "set value, at",
+                |p| {
+                    p.set_value_at_time(5., 15.);
+                },
+                15.,
+                |_| 1.,
+            ),
+            (
+                "set value, after",
+                |p| {
+                    p.set_value_at_time(5., 15.);
+                },
+                20.,
+                |t| {
+                    if t < 15. {
+                        1.
+                    } else {
+                        5.
+                    }
+                },
+            ),
+            // ramps from t = 0 to t = 20, the value from before the ramp is restored
+            (
+                "linear ramp, during",
+                |p| {
+                    p.linear_ramp_to_value_at_time(11., 20.);
+                },
+                15.,
+                |t| {
+                    if t < 10. {
+                        (1. + t / 2.) as f32
+                    } else {
+                        1.
+                    }
+                },
+            ),
+            (
+                "linear ramp, at",
+                |p| {
+                    p.linear_ramp_to_value_at_time(11., 20.);
+                },
+                20.,
+                |t| {
+                    if t < 10. {
+                        (1. + t / 2.) as f32
+                    } else {
+                        1.
+                    }
+                },
+            ),
+            (
+                "linear ramp, after",
+                |p| {
+                    p.linear_ramp_to_value_at_time(11., 20.);
+                },
+                25.,
+                |t| (1. + t.min(20.) / 2.) as f32,
+            ),
+            (
+                "exponential ramp, during",
+                |p| {
+                    p.exponential_ramp_to_value_at_time(16., 20.);
+                },
+                15.,
+                |t| {
+                    if t < 10. {
+                        16_f64.powf(t / 20.) as f32
+                    } else {
+                        1.
+                    }
+                },
+            ),
+            (
+                "exponential ramp, after",
+                |p| {
+                    p.exponential_ramp_to_value_at_time(16., 20.);
+                },
+                25.,
+                |t| 16_f64.powf(t.min(20.) / 20.) as f32,
+            ),
+            // converges from t = 5, the value from before the event is restored if removed,
+            // the event survives if it starts before the cancel time
+            (
+                "set target, before",
+                |p| {
+                    p.set_target_at_time(11., 5., 5.);
+                },
+                3.,
+                |t| {
+                    if t < 10. {
+                        set_target_from_5(t)
+                    } else {
+                        1.
+                    }
+                },
+            ),
+            (
+                "set target, at",
+                |p| {
+                    p.set_target_at_time(11., 5., 5.);
+                },
+                5.,
+                |t| {
+                    if t < 10. {
+                        set_target_from_5(t)
+                    } else {
+                        1.
+                    }
+                },
+            ),
+            (
+                "set target, during",
+                |p| {
+                    p.set_target_at_time(11., 5., 5.);
+                },
+                8.,
+                set_target_from_5,
+            ),
+            (
+                "set target, after",
+                |p| {
+                    p.set_target_at_time(11., 5., 5.);
+                },
+                30.,
+                set_target_from_5,
+            ),
+            // curve from t = 5 to t = 15
+            (
+                "set value curve, before",
+                |p| {
+                    p.set_value_curve_at_time(&[1., 11.], 5., 10.);
+                },
+                3.,
+                |t| {
+                    if t < 10. {
+                        (1. + (t - 5.).max(0.)) as f32
+                    } else {
+                        1.
+                    }
+                },
+            ),
+            (
+                "set value curve, at",
+                |p| {
+                    p.set_value_curve_at_time(&[1., 11.], 5., 10.);
+                },
+                5.,
+                |t| {
+                    if t < 10. {
+                        (1. + (t - 5.).max(0.)) as f32
+                    } else {
+                        1.
+                    }
+                },
+            ),
+            (
+                "set value curve, during",
+                |p| {
+                    p.set_value_curve_at_time(&[1., 11.], 5., 10.);
+                },
+                8.,
+                |t| (1. + (t - 5.).clamp(0., 10.)) as f32,
+            ),
+            (
+                "set value curve, after",
+                |p| {
+                    p.set_value_curve_at_time(&[1., 11.], 5., 10.);
+                },
+                20.,
+                |t| (1. + (t - 5.).clamp(0., 10.)) as f32,
+            ),
+        ];
+
+        for (label, schedule, cancel_time, expected) in cases.iter() {
+            for automation_rate in [AutomationRate::A, AutomationRate::K] {
+                let opts = AudioParamDescriptor {
+                    automation_rate,
+                    default_value: 0.,
+                    min_value: 0.,
+                    max_value: 100.,
+                };
+                let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+                param.set_value(1.);
+                schedule(&param);
+
+                for block in 0..3 {
+                    if block == 1 {
+                        param.cancel_scheduled_values(*cancel_time);
+                    }
+
+                    let block_time = block as f64 * 10.;
+                    let vs = render.tick(block_time, 1., 10).to_vec();
+
+                    // the control side reads the value at the start of the block
+                    let value = expected(block_time);
+                    assert_float_eq!(param.value(), value, abs <= 1e-5, "{}", label);
+
+                    let expected: Vec<f32> = (0..10)
+                        .map(|i| match automation_rate {
+                            AutomationRate::A => expected(block_time + i as f64),
+                            AutomationRate::K => value,
+                        })
+                        .collect();
+                    assert_float_eq!(vs[..], expected[..], abs_all <= 1e-5, "{}", label);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_cancel_scheduled_values_resumes_interrupted_set_target() {
+        let context = OfflineAudioContext::new(1, 0, 48000.);
+
+        // events ending the set target from t = 5, started at t = 10 or t = 15
+        let interruptions: [(&str, ScheduleEvent); 4] = [
+            ("linear ramp", |p| {
+                p.linear_ramp_to_value_at_time(1., 25.);
+            }),
+            ("exponential ramp", |p| {
+                p.exponential_ramp_to_value_at_time(1., 25.);
+            }),
+            ("set target", |p| {
+                p.set_target_at_time(0., 15., 1.);
+            }),
+            ("set value curve", |p| {
+                p.set_value_curve_at_time(&[0., 0.], 15., 10.);
+            }),
+        ];
+
+        for (label, interrupt) in interruptions.iter() {
+            // the set target starts before the cancel time and resumes, or is removed
+            for &(cancel_time, resumed) in &[(8., true), (3., false)] {
+                let opts = AudioParamDescriptor {
+                    automation_rate: AutomationRate::A,
+                    default_value: 0.,
+                    min_value: 0.,
+                    max_value: 100.,
+                };
+                let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+                param.set_value(1.);
+                param.set_target_at_time(11., 5., 5.);
+
+                render.tick(0., 1., 10);
+                interrupt(&param);
+                render.tick(10., 1., 10);
+
+                param.cancel_scheduled_values(cancel_time);
+                let vs = render.tick(20., 1., 10).to_vec();
+
+                let expected: Vec<f32> = (20..30)
+                    .map(|t| {
+                        if resumed {
+                            set_target_from_5(t as f64)
+                        } else {
+                            1.
+                        }
+                    })
+                    .collect();
+                assert_float_eq!(param.value(), expected[0], abs <= 1e-5, "{}", label);
+                assert_float_eq!(vs[..], expected[..], abs_all <= 1e-5, "{}", label);
+            }
+        }
+    }
+
     #[test]
     fn test_cancel_and_hold() {
         let context = OfflineAudioContext::new(1, 0, 48000.);
@@ -2774,4 +3720,526 @@ mod tests {
         // @note - argues in favor of maintaining the queue in control thread
         let _vs = render.tick(0., 1., 10);
     }
+
+    // render `number_of_blocks` render quanta with the real time base of the render thread
+    fn render_frames(
+        render: &mut AudioParamProcessor,
+        sample_rate: f64,
+        number_of_blocks: usize,
+    ) -> Vec<f32> {
+        let dt = 1. / sample_rate;
+        let mut frames = vec![];
+
+        for block in 0..number_of_blocks {
+            let block_time = (block * RENDER_QUANTUM_SIZE) as f64 / sample_rate;
+            frames.extend_from_slice(render.tick(block_time, dt, RENDER_QUANTUM_SIZE));
+        }
+
+        frames
+    }
+
+    // expected values, evaluating the spec formula at the start time of each frame
+    fn expected_frames(
+        number_of_frames: usize,
+        sample_rate: f64,
+        v: impl Fn(f64) -> f32,
+    ) -> Vec<f32> {
+        (0..number_of_frames)
+            .map(|frame| v(frame as f64 / sample_rate))
+            .collect()
+    }
+
+    #[test]
+    fn test_frame_accurate_set_value_at_time() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 0, sample_rate as f32);
+
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        // between frames 47 and 48, on frame 100 and at the end of the first block
+        let t0 = 47.5 / sample_rate;
+        let t1 = 100. / sample_rate;
+        let t2 = 127.5 / sample_rate;
+        param.set_value_at_time(1., t0);
+        param.set_value_at_time(2., t1);
+        param.set_value_at_time(3., t2);
+
+        let vs = render_frames(&mut render, sample_rate, 2);
+        let expected = expected_frames(256, sample_rate, |t| {
+            if t < t0 {
+                0.
+            } else if t < t1 {
+                1.
+            } else if t < t2 {
+                2.
+            } else {
+                3.
+            }
+        });
+
+        assert_float_eq!(vs[..], expected[..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_frame_accurate_linear_ramp() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 0, sample_rate as f32);
+
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 0.5,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        // ramp spanning two blocks, ending between two frames
+        let t0 = 10. / sample_rate;
+        let t1 = 200.5 / sample_rate;
+        param.set_value_at_time(0., t0);
+        param.linear_ramp_to_value_at_time(1., t1);
+
+        let vs = render_frames(&mut render, sample_rate, 3);
+        // 𝑣(𝑡) = 𝑉0 + (𝑉1−𝑉0) * ((𝑡−𝑇0) / (𝑇1−𝑇0))
+        let expected = expected_frames(384, sample_rate, |t| {
+            if t < t0 {
+                0.5
+            } else if t < t1 {
+                ((t - t0) / (t1 - t0)) as f32
+            } else {
+                1.
+            }
+        });
+
+        // the first changed sample is the one at `t0`
+        assert_float_eq!(vs[9], 0.5, abs <= 0.);
+        assert_float_eq!(vs[10], 0., abs <= 1e-6);
+        assert_float_eq!(vs[..], expected[..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_frame_accurate_exponential_ramp() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 0, sample_rate as f32);
+
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 0.5,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        let t0 = 20. / sample_rate;
+        let t1 = 150.25 / sample_rate;
+        param.set_value_at_time(0.1, t0);
+        param.exponential_ramp_to_value_at_time(1., t1);
+
+        let vs = render_frames(&mut render, sample_rate, 2);
+        // v(t) = v1 * (v2/v1)^((t-t1) / (t2-t1))
+        let expected = expected_frames(256, sample_rate, |t| {
+            if t < t0 {
+                0.5
+            } else if t < t1 {
+                0.1 * 10_f32.powf(((t - t0) / (t1 - t0)) as f32)
+            } else {
+                1.
+            }
+        });
+
+        assert_float_eq!(vs[..], expected[..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_frame_accurate_set_target_at_time() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 0, sample_rate as f32);
+
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 0.5,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        // target starting in the middle of the first block, ended by a set value
+        // in the second block
+        let t0 = 60.5 / sample_rate;
+        let time_constant = 0.001;
+        let t1 = 200. / sample_rate;
+        param.set_target_at_time(1., t0, time_constant);
+        param.set_value_at_time(0.25, t1);
+
+        let vs = render_frames(&mut render, sample_rate, 2);
+        // 𝑣(𝑡) = 𝑉1 + (𝑉0 − 𝑉1) * 𝑒^−((𝑡−𝑇0) / 𝜏)
+        let expected = expected_frames(256, sample_rate, |t| {
+            if t < t0 {
+                0.5
+            } else if t < t1 {
+                1. + (0.5 - 1.) * (-(t - t0) / time_constant).exp() as f32
+            } else {
+                0.25
+            }
+        });
+
+        assert_float_eq!(vs[..], expected[..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_frame_accurate_set_value_curve_at_time() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 0, sample_rate as f32);
+
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 0.5,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        // curve starting on a frame of the second block
+        let t0 = 140. / sample_rate;
+        let duration = 64. / sample_rate;
+        param.set_value_curve_at_time(&[0., 1.], t0, duration);
+
+        let vs = render_frames(&mut render, sample_rate, 2);
+        let expected = expected_frames(256, sample_rate, |t| {
+            if t < t0 {
+                0.5
+            } else if t < t0 + duration {
+                ((t - t0) / duration) as f32
+            } else {
+                1.
+            }
+        });
+
+        assert_float_eq!(vs[..], expected[..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_standalone_processor() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 0, sample_rate as f32);
+
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts.clone(), context.mock_registration());
+        param.set_value_at_time(1., 0.);
+        param.linear_ramp_to_value_at_time(5., 200. / sample_rate);
+        let expected = render_frames(&mut render, sample_rate, 2);
+
+        // same automation, scheduled on a processor outside of a graph
+        let mut standalone = AudioParamProcessor::new(opts);
+        standalone.schedule(ParamEvent::SetValueAtTime {
+            value: 1.,
+            start_time: 0.,
+        });
+        standalone.schedule(ParamEvent::LinearRampToValueAtTime {
+            value: 5.,
+            end_time: 200. / sample_rate,
+        });
+
+        let mut vs = vec![];
+        for block in 0..2 {
+            let frame = (block * RENDER_QUANTUM_SIZE) as u64;
+            let scope = RenderScope::new(frame, sample_rate as f32);
+            vs.extend_from_slice(standalone.compute(&scope));
+        }
+
+        assert_float_eq!(vs[..], expected[..], abs_all <= 0.);
+    }
+
+    fn automation_clip(number_of_events: usize, sample_rate: f64) -> Vec<ParamEvent> {
+        let dt = 1. / sample_rate;
+
+        (0..number_of_events)
+            .map(|i| {
+                let time = (i * 37) as f64 * dt;
+                let value = ((i % 7) as f32 - 3.) / 2.;
+                match i % 6 {
+                    0 => ParamEvent::SetValueAtTime {
+                        value,
+                        start_time: time,
+                    },
+                    1 => ParamEvent::LinearRampToValueAtTime {
+                        value,
+                        end_time: time,
+                    },
+                    2 => ParamEvent::ExponentialRampToValueAtTime {
+                        value: value.abs() + 0.1,
+                        end_time: time,
+                    },
+                    3 => ParamEvent::SetTargetAtTime {
+                        value,
+                        start_time: time,
+                        time_constant: 10. * dt,
+                    },
+                    4 => ParamEvent::SetValueCurveAtTime {
+                        values: vec![value, -value, value],
+                        start_time: time,
+                        duration: 20. * dt,
+                    },
+                    _ => ParamEvent::CancelAndHoldAtTime {
+                        cancel_time: time + 30. * dt,
+                    },
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_apply_events() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 0, sample_rate as f32);
+
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (batch_param, mut batch_render) =
+            audio_param_pair(opts.clone(), context.mock_registration());
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        let clip = automation_clip(500, sample_rate);
+        batch_param.apply_events(&clip);
+
+        for event in clip {
+            match event {
+                ParamEvent::SetValueAtTime { value, start_time } => {
+                    param.set_value_at_time(value, start_time)
+                }
+                ParamEvent::LinearRampToValueAtTime { value, end_time } => {
+                    param.linear_ramp_to_value_at_time(value, end_time)
+                }
+                ParamEvent::ExponentialRampToValueAtTime { value, end_time } => {
+                    param.exponential_ramp_to_value_at_time(value, end_time)
+                }
+                ParamEvent::SetTargetAtTime {
+                    value,
+                    start_time,
+                    time_constant,
+                } => param.set_target_at_time(value, start_time, time_constant),
+                ParamEvent::SetValueCurveAtTime {
+                    values,
+                    start_time,
+                    duration,
+                } => param.set_value_curve_at_time(&values, start_time, duration),
+                ParamEvent::CancelScheduledValues { cancel_time } => {
+                    param.cancel_scheduled_values(cancel_time)
+                }
+                ParamEvent::CancelAndHoldAtTime { cancel_time } => {
+                    param.cancel_and_hold_at_time(cancel_time)
+                }
+            };
+        }
+
+        // 500 events of 37 frames
+        let number_of_blocks = 500 * 37 / RENDER_QUANTUM_SIZE + 2;
+        let expected = render_frames(&mut render, sample_rate, number_of_blocks);
+        let vs = render_frames(&mut batch_render, sample_rate, number_of_blocks);
+
+        assert_float_eq!(vs[..], expected[..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_apply_events_invalid_batch() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 0, sample_rate as f32);
+
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        let mut clip = automation_clip(10, sample_rate);
+        // exponential ramps cannot target zero
+        clip.push(ParamEvent::ExponentialRampToValueAtTime {
+            value: 0.,
+            end_time: 1.,
+        });
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            param.apply_events(&clip);
+        }));
+        assert!(result.is_err());
+
+        // none of the events is applied
+        let vs = render_frames(&mut render, sample_rate, 4);
+        assert_float_eq!(vs[..], [0.; 4 * RENDER_QUANTUM_SIZE][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_clear_and_apply_events() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 0, sample_rate as f32);
+
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        param.set_value_at_time(1., 0.);
+        param.set_value_at_time(2., 64. / sample_rate);
+        param.clear_and_apply_events(&[ParamEvent::SetValueAtTime {
+            value: 3.,
+            start_time: 32. / sample_rate,
+        }]);
+
+        let vs = render_frames(&mut render, sample_rate, 1);
+        let expected = expected_frames(RENDER_QUANTUM_SIZE, sample_rate, |t| {
+            if t < 32. / sample_rate {
+                0.
+            } else {
+                3.
+            }
+        });
+
+        assert_float_eq!(vs[..], expected[..], abs_all <= 0.);
+    }
+
+    struct StereoGainNode {
+        registration: AudioContextRegistration,
+        channel_config: ChannelConfig,
+    }
+
+    impl AudioNode for StereoGainNode {
+        fn registration(&self) -> &AudioContextRegistration {
+            &self.registration
+        }
+
+        fn channel_config(&self) -> &ChannelConfig {
+            &self.channel_config
+        }
+
+        fn number_of_inputs(&self) -> usize {
+            1
+        }
+
+        fn number_of_outputs(&self) -> usize {
+            1
+        }
+    }
+
+    /// Applies a per-channel gain read from a stereo control input
+    struct StereoGainRenderer {
+        gains: AudioParamId,
+    }
+
+    impl AudioProcessor for StereoGainRenderer {
+        fn process(
+            &mut self,
+            inputs: &[AudioRenderQuantum],
+            outputs: &mut [AudioRenderQuantum],
+            params: AudioParamValues,
+            _scope: &RenderScope,
+        ) -> bool {
+            let gains = params.get_multichannel(&self.gains);
+            let output = &mut outputs[0];
+            *output = inputs[0].clone();
+            output.set_number_of_channels(gains.number_of_channels());
+
+            output
+                .channels_mut()
+                .iter_mut()
+                .zip(gains.channels())
+                .for_each(|(channel, gain)| {
+                    channel
+                        .iter_mut()
+                        .zip(gain.iter())
+                        .for_each(|(v, g)| *v *= g)
+                });
+
+            false
+        }
+    }
+
+    #[test]
+    fn test_control_input_keeps_channels() {
+        let mut context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 48_000.);
+
+        let mut gains = None;
+        let node = context.register(|registration| {
+            let (input, gains_id) = context.create_control_input(2, &registration);
+            gains = Some(input);
+            let node = StereoGainNode {
+                registration,
+                channel_config: ChannelConfigOptions::default().into(),
+            };
+            (node, Box::new(StereoGainRenderer { gains: gains_id }))
+        });
+        let gains = gains.unwrap();
+        node.connect(&context.destination());
+
+        let src = context.create_constant_source();
+        src.connect(&node);
+        src.start();
+
+        // stereo control signal: 0.5 on the left, 2 on the right
+        let merger = context.create_channel_merger(2);
+        merger.connect(&gains);
+        for (channel, value) in [0.5, 2.].iter().enumerate() {
+            let src = context.create_constant_source();
+            src.offset().set_value(*value);
+            src.connect_at(&merger, 0, channel);
+            src.start();
+        }
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(
+            output.get_channel_data(0)[..],
+            [0.5; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            output.get_channel_data(1)[..],
+            [2.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_control_input_unconnected() {
+        let mut context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 48_000.);
+
+        let mut gains = None;
+        let node = context.register(|registration| {
+            let (input, gains_id) = context.create_control_input(2, &registration);
+            gains = Some(input);
+            let node = StereoGainNode {
+                registration,
+                channel_config: ChannelConfigOptions::default().into(),
+            };
+            (node, Box::new(StereoGainRenderer { gains: gains_id }))
+        });
+        node.connect(&context.destination());
+
+        let src = context.create_constant_source();
+        src.connect(&node);
+        src.start();
+
+        // silent gains
+        let output = context.start_rendering_sync();
+        assert!(output.get_channel_data(0).iter().all(|v| *v == 0.));
+        drop(gains);
+    }
 }
@@ -1,10 +1,21 @@
 //! PeriodicWave interface
-use std::f32::consts::PI;
 use std::sync::Arc;
 
-use crate::context::BaseAudioContext;
+use lazy_static::lazy_static;
+use realfft::{num_complex::Complex, ComplexToReal, RealFftPlanner};
 
-use crate::node::TABLE_LENGTH_USIZE;
+use crate::context::{decode_native, BaseAudioContext};
+use crate::media::DecodeError;
+use crate::node::{BandLimitedTables, SINETABLE, TABLE_LENGTH_USIZE};
+
+lazy_static! {
+    /// Inverse FFT synthesizing the wavetables, planned once for all waves
+    static ref WAVETABLE_IFFT: Arc<dyn ComplexToReal<f64>> =
+        RealFftPlanner::<f64>::new().plan_fft_inverse(TABLE_LENGTH_USIZE);
+    /// Band-limited tables of the default sine wave, a single table
+    static ref SINE_BAND_LIMITED: Arc<BandLimitedTables> =
+        Arc::new(BandLimitedTables::custom(&[0., 0.], &[0., 1.], 1.));
+}
 
 /// Options for constructing a [`PeriodicWave`]
 #[derive(Debug, Default, Clone)]
@@ -34,6 +45,31 @@ pub struct PeriodicWaveOptions {
     pub disable_normalization: bool,
 }
 
+/// Options for importing a [`PeriodicWave`] from a wavetable file
+///
+/// See [`PeriodicWave::from_wavetable_file`]
+#[derive(Debug, Clone)]
+pub struct WavetableImportOptions {
+    /// Number of samples of a single cycle, e.g. 2048 for Serum or 256 for WaveEdit tables.
+    /// Defaults to 2048.
+    pub frame_size: usize,
+    /// Index of the cycle to import, for tables holding several consecutive cycles.
+    /// Defaults to 0.
+    pub frame_index: usize,
+    /// See [`PeriodicWaveOptions::disable_normalization`]
+    pub disable_normalization: bool,
+}
+
+impl Default for WavetableImportOptions {
+    fn default() -> Self {
+        Self {
+            frame_size: 2048,
+            frame_index: 0,
+            disable_normalization: false,
+        }
+    }
+}
+
 /// `PeriodicWave` represents an arbitrary periodic waveform to be used with an `OscillatorNode`.
 ///
 /// - MDN documentation: <https://developer.mozilla.org/en-US/docs/Web/API/PeriodicWave>
@@ -72,6 +108,15 @@ pub struct PeriodicWaveOptions {
 #[derive(Debug, Clone)]
 pub struct PeriodicWave {
     wavetable: Arc<Vec<f32>>,
+    /// Fourier coefficients the wave was built from
+    real: Arc<Vec<f32>>,
+    imag: Arc<Vec<f32>>,
+    /// Band-limited versions of the wavetable, rendered by the oscillator
+    band_limited: Arc<BandLimitedTables>,
+    /// Normalization factor applied to the wavetable
+    scale: f32,
+    /// Whether the wave was built with normalization enabled
+    normalized: bool,
 }
 
 impl PeriodicWave {
@@ -93,9 +138,6 @@ impl PeriodicWave {
     /// * `PeriodicWave` is more than 8192 components
     //
     // @notes:
-    // - Current implementation is very naive and could be improved using inverse
-    // FFT or table lookup on SINETABLE. Such performance improvements should be
-    // however tested also against this implementation.
     // - Built-in types of the `OscillatorNode` should use periodic waves
     // c.f. https://webaudio.github.io/web-audio-api/#oscillator-coefficients
     // - The question of bandlimited oscillators should also be handled
@@ -137,15 +179,190 @@ impl PeriodicWave {
             // Defaults to sine wave
             // [spec] Note: When setting this PeriodicWave on an OscillatorNode,
             // this is equivalent to using the built-in type "sine".
-            _ => (vec![0., 0.], vec![0., 1.]),
+            _ => return Self::sine(!disable_normalization),
         };
 
-        let normalize = !disable_normalization;
+        Self::from_coefficients(real, imag, !disable_normalization)
+    }
+
+    /// Returns a `PeriodicWave` playing the given single cycle waveform
+    ///
+    /// The Fourier coefficients of the wave are computed from the waveform, its DC offset is
+    /// discarded.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the waveform contains less than 2 samples
+    pub fn from_waveform<C: BaseAudioContext>(
+        _context: &C,
+        waveform: &[f32],
+        disable_normalization: bool,
+    ) -> Self {
+        let length = waveform.len();
+        if length < 2 {
+            panic!("IndexSizeError: waveform should contain at least 2 samples");
+        }
+
+        let mut planner = RealFftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(length);
+        let mut input = waveform.to_vec();
+        let mut spectrum = fft.make_output_vec();
+        fft.process(&mut input, &mut spectrum).unwrap();
+
+        // a_k = 2 Re(X_k) / N and b_k = -2 Im(X_k) / N, the Nyquist bin is not mirrored
+        let mut real = vec![0.; spectrum.len()];
+        let mut imag = vec![0.; spectrum.len()];
+        for (k, bin) in spectrum.iter().enumerate().skip(1) {
+            let factor = if 2 * k == length { 1. } else { 2. } / length as f32;
+            real[k] = factor * bin.re;
+            imag[k] = -factor * bin.im;
+        }
+
+        Self::from_coefficients(real, imag, !disable_normalization)
+    }
+
+    /// Returns a `PeriodicWave` playing a cycle of a wavetable file
+    ///
+    /// The input is decoded like [`BaseAudioContext::decode_audio_data_sync`], but it is not
+    /// resampled. Its first channel is cut in cycles of `options.frame_size` samples, see
+    /// [`Self::wavetable_frame_count`], and the cycle at `options.frame_index` is passed to
+    /// [`Self::from_waveform`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DecodeError`] if the input cannot be decoded
+    ///
+    /// # Panics
+    ///
+    /// Will panic if:
+    ///
+    /// * `options.frame_size` is less than 2
+    /// * `options.frame_index` is not less than the number of cycles of the table
+    pub fn from_wavetable_file<C: BaseAudioContext, R: std::io::Read + Send + Sync + 'static>(
+        context: &C,
+        input: R,
+        options: WavetableImportOptions,
+    ) -> Result<Self, DecodeError> {
+        let WavetableImportOptions {
+            frame_size,
+            frame_index,
+            disable_normalization,
+        } = options;
+
+        if frame_size < 2 {
+            panic!("IndexSizeError: frame_size should be at least 2");
+        }
+
+        let table = match decode_native(input)? {
+            Some(buffer) => buffer.get_channel_data(0).to_vec(),
+            None => vec![],
+        };
+        let frame_count = table.len() / frame_size;
+        if frame_index >= frame_count {
+            panic!(
+                "IndexSizeError: frame_index ({}) is out of range, the table contains {} frames",
+                frame_index, frame_count
+            );
+        }
+
+        let start = frame_index * frame_size;
+        let cycle = &table[start..start + frame_size];
+
+        Ok(Self::from_waveform(context, cycle, disable_normalization))
+    }
+
+    /// Number of cycles of `frame_size` samples in a wavetable file
+    ///
+    /// Trailing samples that do not make a full cycle are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DecodeError`] if the input cannot be decoded
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `frame_size` is zero
+    pub fn wavetable_frame_count<R: std::io::Read + Send + Sync + 'static>(
+        input: R,
+        frame_size: usize,
+    ) -> Result<usize, DecodeError> {
+        if frame_size == 0 {
+            panic!("IndexSizeError: frame_size should not be zero");
+        }
+
+        let length = decode_native(input)?.map_or(0, |buffer| buffer.length());
+
+        Ok(length / frame_size)
+    }
+
+    /// Render a single cycle of the wave, of `length` samples, e.g. to save or display it
+    ///
+    /// The cycle is computed from the Fourier coefficients of the wave, harmonics above the
+    /// Nyquist frequency of the cycle are folded back. The normalization applied to the wave
+    /// is applied to the cycle as well.
+    pub fn to_single_cycle(&self, length: usize) -> Vec<f32> {
+        let mut cycle = Self::generate_wavetable(&self.real, &self.imag, false, length);
+        cycle.iter_mut().for_each(|v| *v *= self.scale);
+
+        cycle
+    }
+
+    /// Factor applied to the wave by the normalization, `1.` when the normalization is disabled
+    ///
+    /// The normalization is a peak normalization of the time-domain wavetable, as specified in
+    /// <https://webaudio.github.io/web-audio-api/#waveform-normalization>: the factor is the
+    /// inverse of the largest absolute sample of the table synthesized from the coefficients, so
+    /// the normalized wave peaks at `1`. A silent wave is left as is, with a factor of `1.`.
+    ///
+    /// Multiply the output of an oscillator playing an unnormalized wave by this factor (or
+    /// divide the output of a normalized one by it) to compensate for the normalization.
+    pub fn normalization_factor(&self) -> f32 {
+        self.scale
+    }
+
+    /// Whether the wave was built with normalization enabled
+    ///
+    /// See [`PeriodicWave::normalization_factor`]
+    pub fn is_normalized(&self) -> bool {
+        self.normalized
+    }
+
+    fn from_coefficients(real: Vec<f32>, imag: Vec<f32>, normalize: bool) -> Self {
         // [spec] A conforming implementation MUST support PeriodicWave up to at least 8192 elements.
-        let wavetable = Self::generate_wavetable(&real, &imag, normalize, TABLE_LENGTH_USIZE);
+        let wavetable = Self::synthesize_wavetable(&real, &imag, TABLE_LENGTH_USIZE);
+        let scale = if normalize {
+            Self::normalization(&wavetable) as f32
+        } else {
+            1.
+        };
+        let wavetable = wavetable
+            .into_iter()
+            .map(|sample| (sample * f64::from(scale)) as f32)
+            .collect();
+
+        let band_limited = BandLimitedTables::custom(&real, &imag, scale);
 
         Self {
             wavetable: Arc::new(wavetable),
+            real: Arc::new(real),
+            imag: Arc::new(imag),
+            band_limited: Arc::new(band_limited),
+            scale,
+            normalized: normalize,
+        }
+    }
+
+    /// The default sine wave, sharing the static sine table of the built-in oscillator type
+    ///
+    /// The peak of the table is 1, so the wave is the same with or without normalization.
+    fn sine(normalized: bool) -> Self {
+        Self {
+            wavetable: SINETABLE.clone(),
+            real: Arc::new(vec![0., 0.]),
+            imag: Arc::new(vec![0., 1.]),
+            band_limited: SINE_BAND_LIMITED.clone(),
+            scale: 1.,
+            normalized,
         }
     }
 
@@ -153,52 +370,76 @@ impl PeriodicWave {
         &self.wavetable[..]
     }
 
-    // cf. https://webaudio.github.io/web-audio-api/#waveform-generation
-    fn generate_wavetable(reals: &[f32], imags: &[f32], normalize: bool, size: usize) -> Vec<f32> {
-        let mut wavetable = Vec::with_capacity(size);
-        let pi_2 = 2. * PI;
-
-        for i in 0..size {
-            let mut sample = 0.;
-            let phase = pi_2 * i as f32 / size as f32;
-
-            for j in 1..reals.len() {
-                let freq = j as f32;
-                let real = reals[j];
-                let imag = imags[j];
-                let rad = phase * freq;
-                let contrib = real * rad.cos() + imag * rad.sin();
-                sample += contrib;
-            }
-
-            wavetable.push(sample);
-        }
+    /// Band-limited tables of the wave, holding the partials up to a fraction of the table
+    /// length, see [`BandLimitedTables`]
+    pub(crate) fn band_limited(&self) -> &BandLimitedTables {
+        &self.band_limited
+    }
 
-        if normalize {
-            Self::normalize(&mut wavetable);
-        }
+    // cf. https://webaudio.github.io/web-audio-api/#waveform-generation
+    pub(crate) fn generate_wavetable(
+        reals: &[f32],
+        imags: &[f32],
+        normalize: bool,
+        size: usize,
+    ) -> Vec<f32> {
+        let wavetable = Self::synthesize_wavetable(reals, imags, size);
+
+        // scale in double precision too, before rounding the samples
+        let factor = if normalize {
+            Self::normalization(&wavetable)
+        } else {
+            1.
+        };
 
         wavetable
+            .into_iter()
+            .map(|sample| (sample * factor) as f32)
+            .collect()
     }
 
-    fn normalize(wavetable: &mut [f32]) {
-        let mut max = 0.;
+    // The waveform is computed with an inverse FFT, the harmonics above the Nyquist frequency of
+    // the table alias onto the bins they would fold to when summed in the time domain. The
+    // synthesis runs in double precision, the error of the cubic interpolation of the tables is
+    // below the rounding error of a single precision transform.
+    pub(crate) fn synthesize_wavetable(reals: &[f32], imags: &[f32], size: usize) -> Vec<f64> {
+        if size == 0 {
+            return vec![];
+        }
 
-        for sample in wavetable.iter() {
-            let abs = sample.abs();
-            if abs > max {
-                max = abs;
+        let ifft = if size == TABLE_LENGTH_USIZE {
+            WAVETABLE_IFFT.clone()
+        } else {
+            RealFftPlanner::<f64>::new().plan_fft_inverse(size)
+        };
+        let mut spectrum = ifft.make_input_vec();
+
+        // x[n] = X_0 + X_{N/2} (-1)^n + 2 sum_k (Re(X_k) cos - Im(X_k) sin)
+        for (j, (&real, &imag)) in reals.iter().zip(imags).enumerate().skip(1) {
+            let (real, imag) = (f64::from(real), f64::from(imag));
+            let k = j % size;
+            if k == 0 {
+                spectrum[0].re += real;
+            } else if 2 * k == size {
+                spectrum[k].re += real;
+            } else if 2 * k < size {
+                spectrum[k] += Complex::new(real / 2., -imag / 2.);
+            } else {
+                // cos is even and sin is odd
+                spectrum[size - k] += Complex::new(real / 2., imag / 2.);
             }
         }
 
-        // prevent division by 0. (nothing to normalize anyway...)
-        if max > 0. {
-            let norm_factor = 1. / max;
+        let mut wavetable = ifft.make_output_vec();
+        // the imaginary parts of the DC and Nyquist bins are zero
+        ifft.process(&mut spectrum, &mut wavetable).unwrap();
 
-            for sample in wavetable.iter_mut() {
-                *sample *= norm_factor;
-            }
-        }
+        wavetable
+    }
+
+    /// Peak normalization factor of a wavetable, `1.` for a silent one
+    pub(crate) fn normalization(wavetable: &[f64]) -> f64 {
+        crate::node::normalization(wavetable)
     }
 }
 
@@ -207,10 +448,10 @@ mod tests {
     use float_eq::assert_float_eq;
     use std::f32::consts::PI;
 
-    use crate::context::AudioContext;
-    use crate::node::{TABLE_LENGTH_F32, TABLE_LENGTH_USIZE};
+    use crate::context::{AudioContext, BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode, SINETABLE, TABLE_LENGTH_USIZE};
 
-    use super::{PeriodicWave, PeriodicWaveOptions};
+    use super::{PeriodicWave, PeriodicWaveOptions, WavetableImportOptions};
 
     #[test]
     #[should_panic]
@@ -270,6 +511,8 @@ mod tests {
 
     #[test]
     fn wavetable_generate_sine() {
+        use std::f64::consts::PI;
+
         let reals = [0., 0.];
         let imags = [0., 1.];
 
@@ -277,15 +520,17 @@ mod tests {
         let mut expected = Vec::new();
 
         for i in 0..TABLE_LENGTH_USIZE {
-            let sample = (i as f32 / TABLE_LENGTH_F32 * 2. * PI).sin();
-            expected.push(sample);
+            let sample = (i as f64 / TABLE_LENGTH_USIZE as f64 * 2. * PI).sin();
+            expected.push(sample as f32);
         }
 
-        assert_float_eq!(result[..], expected[..], abs_all <= 1e-6);
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-7);
     }
 
     #[test]
     fn wavetable_generate_2f_not_norm() {
+        use std::f64::consts::PI;
+
         let reals = [0., 0., 0.];
         let imags = [0., 0.5, 0.5];
 
@@ -295,37 +540,29 @@ mod tests {
         for i in 0..TABLE_LENGTH_USIZE {
             let mut sample = 0.;
             // fondamental frequency
-            sample += 0.5 * (1. * i as f32 / TABLE_LENGTH_F32 * 2. * PI).sin();
+            sample += 0.5 * (1. * i as f64 / TABLE_LENGTH_USIZE as f64 * 2. * PI).sin();
             // 1rst partial
-            sample += 0.5 * (2. * i as f32 / TABLE_LENGTH_F32 * 2. * PI).sin();
+            sample += 0.5 * (2. * i as f64 / TABLE_LENGTH_USIZE as f64 * 2. * PI).sin();
 
             expected.push(sample);
         }
+        let expected: Vec<f32> = expected.into_iter().map(|s| s as f32).collect();
 
-        assert_float_eq!(result[..], expected[..], abs_all <= 1e-6);
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-7);
     }
 
     #[test]
-    fn normalize() {
-        {
-            let mut signal = [-0.5, 0.2];
-            PeriodicWave::normalize(&mut signal);
-            let expected = [-1., 0.4];
-
-            assert_float_eq!(signal[..], expected[..], abs_all <= 0.);
-        }
-
-        {
-            let mut signal = [0.5, -0.2];
-            PeriodicWave::normalize(&mut signal);
-            let expected = [1., -0.4];
-
-            assert_float_eq!(signal[..], expected[..], abs_all <= 0.);
-        }
+    fn normalization() {
+        assert_float_eq!(PeriodicWave::normalization(&[-0.5, 0.2]), 2., abs <= 0.);
+        assert_float_eq!(PeriodicWave::normalization(&[0.5, -0.2]), 2., abs <= 0.);
+        // silent wave
+        assert_float_eq!(PeriodicWave::normalization(&[0., 0.]), 1., abs <= 0.);
     }
 
     #[test]
     fn wavetable_generate_2f_norm() {
+        use std::f64::consts::PI;
+
         let reals = [0., 0., 0.];
         let imags = [0., 0.5, 0.5];
 
@@ -335,15 +572,245 @@ mod tests {
         for i in 0..TABLE_LENGTH_USIZE {
             let mut sample = 0.;
             // fondamental frequency
-            sample += 0.5 * (1. * i as f32 / TABLE_LENGTH_F32 * 2. * PI).sin();
+            sample += 0.5 * (1. * i as f64 / TABLE_LENGTH_USIZE as f64 * 2. * PI).sin();
             // 1rst partial
-            sample += 0.5 * (2. * i as f32 / TABLE_LENGTH_F32 * 2. * PI).sin();
+            sample += 0.5 * (2. * i as f64 / TABLE_LENGTH_USIZE as f64 * 2. * PI).sin();
 
             expected.push(sample);
         }
 
-        PeriodicWave::normalize(&mut expected);
+        let factor = PeriodicWave::normalization(&expected);
+        let expected: Vec<f32> = expected.into_iter().map(|s| (s * factor) as f32).collect();
+
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-7);
+    }
+
+    #[test]
+    fn default_wave_shares_static_sine_table() {
+        let context_a = OfflineAudioContext::new(1, 128, 44_100.);
+        let context_b = OfflineAudioContext::new(1, 128, 48_000.);
+
+        let wave_a = PeriodicWave::new(&context_a, PeriodicWaveOptions::default());
+        let options = PeriodicWaveOptions {
+            disable_normalization: true,
+            ..PeriodicWaveOptions::default()
+        };
+        let wave_b = PeriodicWave::new(&context_b, options);
+
+        assert_eq!(wave_a.as_slice().as_ptr(), wave_b.as_slice().as_ptr());
+        assert_eq!(wave_a.as_slice().as_ptr(), SINETABLE.as_ptr());
+
+        // same wave as the one synthesized from its coefficients
+        let expected =
+            PeriodicWave::generate_wavetable(&[0., 0.], &[0., 1.], true, TABLE_LENGTH_USIZE);
+        assert_float_eq!(wave_a.as_slice(), &expected[..], abs_all <= 1e-6);
+        assert_float_eq!(
+            wave_b.to_single_cycle(4)[..],
+            [0., 1., 0., -1.][..],
+            abs_all <= 1e-6
+        );
+    }
 
-        assert_float_eq!(result[..], expected[..], abs_all <= 1e-6);
+    #[test]
+    fn normalization_factor_single_harmonic() {
+        let context = OfflineAudioContext::new(1, 1, 44_100.);
+
+        // 0.3 cos + 0.4 sin peaks at sqrt(0.3^2 + 0.4^2) = 0.5
+        let options = PeriodicWaveOptions {
+            real: Some(vec![0., 0.3]),
+            imag: Some(vec![0., 0.4]),
+            disable_normalization: false,
+        };
+        let wave = PeriodicWave::new(&context, options.clone());
+        assert!(wave.is_normalized());
+        assert_float_eq!(wave.normalization_factor(), 2., abs <= 1e-3);
+
+        let options = PeriodicWaveOptions {
+            disable_normalization: true,
+            ..options
+        };
+        let wave = PeriodicWave::new(&context, options);
+        assert!(!wave.is_normalized());
+        assert_float_eq!(wave.normalization_factor(), 1., abs <= 0.);
+
+        // default sine and waveform paths
+        let wave = PeriodicWave::new(&context, PeriodicWaveOptions::default());
+        assert!(wave.is_normalized());
+        assert_float_eq!(wave.normalization_factor(), 1., abs <= 0.);
+
+        let waveform: Vec<f32> = (0..256)
+            .map(|i| 0.25 * (i as f32 / 256. * 2. * PI).sin())
+            .collect();
+        let wave = PeriodicWave::from_waveform(&context, &waveform, false);
+        assert!(wave.is_normalized());
+        assert_float_eq!(wave.normalization_factor(), 4., abs <= 1e-2);
+    }
+
+    #[test]
+    fn normalization_factor_compensation() {
+        let sample_rate = 48_000.;
+
+        // waves of different peak amplitudes
+        let coefficients = [
+            (vec![0., 0.3], vec![0., 0.4]),
+            (vec![0., 0., 0., 0.], vec![0., 1., 0., 1. / 3.]),
+        ];
+
+        let peaks: Vec<f32> = coefficients
+            .iter()
+            .map(|(real, imag)| {
+                let mut context = OfflineAudioContext::new(1, 4800, sample_rate);
+                let options = PeriodicWaveOptions {
+                    real: Some(real.clone()),
+                    imag: Some(imag.clone()),
+                    disable_normalization: false,
+                };
+                let factor = PeriodicWave::new(&context, options.clone()).normalization_factor();
+
+                let options = PeriodicWaveOptions {
+                    disable_normalization: true,
+                    ..options
+                };
+                let wave = PeriodicWave::new(&context, options);
+
+                let osc = context.create_oscillator();
+                osc.set_periodic_wave(wave);
+                osc.frequency().set_value(100.);
+                let gain = context.create_gain();
+                gain.gain().set_value(factor);
+                osc.connect(&gain);
+                gain.connect(&context.destination());
+                osc.start();
+
+                let output = context.start_rendering_sync();
+                output
+                    .get_channel_data(0)
+                    .iter()
+                    .fold(0., |max: f32, v| max.max(v.abs()))
+            })
+            .collect();
+
+        assert_float_eq!(peaks[0], 1., abs <= 1e-3);
+        assert_float_eq!(peaks[1], peaks[0], abs <= 1e-3);
+    }
+
+    const FRAME_SIZE: usize = 2048;
+
+    /// Frame `f` of the test wavetable: a sine at harmonic `f + 1` and a cosine at `2 * f + 3`
+    fn wavetable_frame(f: usize) -> Vec<f32> {
+        (0..FRAME_SIZE)
+            .map(|n| {
+                let phase = 2. * PI * n as f32 / FRAME_SIZE as f32;
+                0.5 * ((f + 1) as f32 * phase).sin() + 0.25 * ((2 * f + 3) as f32 * phase).cos()
+            })
+            .collect()
+    }
+
+    /// Mono 32 bits float WAV file
+    fn wav_file(samples: &[f32]) -> std::io::Cursor<Vec<u8>> {
+        let data_len = (samples.len() * 4) as u32;
+        let mut bytes = vec![];
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16_u32.to_le_bytes());
+        bytes.extend_from_slice(&3_u16.to_le_bytes()); // IEEE float
+        bytes.extend_from_slice(&1_u16.to_le_bytes()); // mono
+        bytes.extend_from_slice(&44_100_u32.to_le_bytes());
+        bytes.extend_from_slice(&(44_100_u32 * 4).to_le_bytes());
+        bytes.extend_from_slice(&4_u16.to_le_bytes());
+        bytes.extend_from_slice(&32_u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_len.to_le_bytes());
+        samples
+            .iter()
+            .for_each(|s| bytes.extend_from_slice(&s.to_le_bytes()));
+
+        std::io::Cursor::new(bytes)
+    }
+
+    fn wavetable_file() -> std::io::Cursor<Vec<u8>> {
+        let mut samples: Vec<f32> = (0..4).flat_map(wavetable_frame).collect();
+        // trailing samples which do not make a full frame
+        samples.extend_from_slice(&[0.; 100]);
+        wav_file(&samples)
+    }
+
+    #[test]
+    fn wavetable_frame_count() {
+        let count = PeriodicWave::wavetable_frame_count(wavetable_file(), FRAME_SIZE).unwrap();
+        assert_eq!(count, 4);
+
+        let count = PeriodicWave::wavetable_frame_count(wavetable_file(), 256).unwrap();
+        assert_eq!(count, (4 * FRAME_SIZE + 100) / 256);
+    }
+
+    #[test]
+    fn wavetable_frames_round_trip() {
+        let context = OfflineAudioContext::new(1, 1, 44_100.);
+
+        for f in 0..4 {
+            let options = WavetableImportOptions {
+                frame_size: FRAME_SIZE,
+                frame_index: f,
+                disable_normalization: true,
+            };
+            let wave =
+                PeriodicWave::from_wavetable_file(&context, wavetable_file(), options).unwrap();
+
+            // expected spectrum
+            for (k, (real, imag)) in wave.real.iter().zip(wave.imag.iter()).enumerate() {
+                let expected_real = if k == 2 * f + 3 { 0.25 } else { 0. };
+                let expected_imag = if k == f + 1 { 0.5 } else { 0. };
+                assert_float_eq!(*real, expected_real, abs <= 1e-5);
+                assert_float_eq!(*imag, expected_imag, abs <= 1e-5);
+            }
+
+            // the cycle is rendered back from the coefficients
+            let cycle = wave.to_single_cycle(FRAME_SIZE);
+            assert_float_eq!(cycle[..], wavetable_frame(f)[..], abs_all <= 1e-5);
+        }
+    }
+
+    #[test]
+    fn wavetable_normalized_cycle() {
+        let context = OfflineAudioContext::new(1, 1, 44_100.);
+        let wave = PeriodicWave::from_waveform(&context, &wavetable_frame(0), false);
+
+        // the cycle is normalized like the wave, at any length
+        let cycle = wave.to_single_cycle(512);
+        assert_eq!(cycle.len(), 512);
+        let peak = cycle.iter().fold(0., |max: f32, v| max.max(v.abs()));
+        assert_float_eq!(peak, 1., abs <= 1e-3);
+
+        // a table of the sine at harmonic 1 rendered at a quarter of its length
+        let sine: Vec<f32> = (0..FRAME_SIZE)
+            .map(|n| (2. * PI * n as f32 / FRAME_SIZE as f32).sin())
+            .collect();
+        let wave = PeriodicWave::from_waveform(&context, &sine, false);
+        let expected: Vec<f32> = sine.iter().step_by(4).copied().collect();
+        assert_float_eq!(
+            wave.to_single_cycle(FRAME_SIZE / 4)[..],
+            expected[..],
+            abs_all <= 1e-5
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn wavetable_frame_index_out_of_range() {
+        let context = OfflineAudioContext::new(1, 1, 44_100.);
+        let options = WavetableImportOptions {
+            frame_index: 4,
+            ..WavetableImportOptions::default()
+        };
+        let _ = PeriodicWave::from_wavetable_file(&context, wavetable_file(), options);
+    }
+
+    #[test]
+    #[should_panic]
+    fn fails_to_build_from_too_short_waveform() {
+        let context = OfflineAudioContext::new(1, 1, 44_100.);
+        let _ = PeriodicWave::from_waveform(&context, &[0.], false);
     }
 }
@@ -0,0 +1,275 @@
+//! Lock-free queue of rendered frames, between the render loop and the output callback
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::Thread;
+
+use super::{Dither, OutputSample};
+
+/// Ring of interleaved samples, written by a single producer and read by a single consumer
+///
+/// The samples are stored as the bits of their `f32` value, so the ring is shared without locks
+/// nor unsafe code.
+#[derive(Debug)]
+struct Ring {
+    samples: Box<[AtomicU32]>,
+    number_of_channels: usize,
+    /// number of samples written so far, only advanced by the producer
+    written: AtomicUsize,
+    /// number of samples read so far, only advanced by the consumer
+    read: AtomicUsize,
+    /// largest number of frames read at once
+    demand: AtomicUsize,
+    /// set when either side is dropped
+    closed: AtomicBool,
+}
+
+/// Creates a queue holding up to `capacity` frames of `number_of_channels` interleaved channels
+///
+/// `demand` is the expected number of frames read at once, raised by the larger reads.
+pub(crate) fn frame_queue(
+    number_of_channels: usize,
+    capacity: usize,
+    demand: usize,
+) -> (FrameProducer, FrameConsumer) {
+    let samples = (0..number_of_channels * capacity)
+        .map(|_| AtomicU32::new(0))
+        .collect();
+
+    let ring = Arc::new(Ring {
+        samples,
+        number_of_channels,
+        written: AtomicUsize::new(0),
+        read: AtomicUsize::new(0),
+        demand: AtomicUsize::new(demand),
+        closed: AtomicBool::new(false),
+    });
+
+    let producer = FrameProducer { ring: ring.clone() };
+    let consumer = FrameConsumer { ring, waker: None };
+
+    (producer, consumer)
+}
+
+/// Writing side of a [`frame_queue`], owned by the render loop
+#[derive(Debug)]
+pub(crate) struct FrameProducer {
+    ring: Arc<Ring>,
+}
+
+impl FrameProducer {
+    /// Number of frames that can be pushed without overwriting the frames not read yet
+    pub fn free_frames(&self) -> usize {
+        let written = self.ring.written.load(Ordering::Relaxed);
+        let read = self.ring.read.load(Ordering::Acquire);
+        (self.ring.samples.len() - (written - read)) / self.ring.number_of_channels
+    }
+
+    /// Number of frames pushed and not read yet
+    pub fn queued_frames(&self) -> usize {
+        let written = self.ring.written.load(Ordering::Relaxed);
+        let read = self.ring.read.load(Ordering::Acquire);
+        (written - read) / self.ring.number_of_channels
+    }
+
+    /// Largest number of frames read at once, or expected to be
+    pub fn demand(&self) -> usize {
+        self.ring.demand.load(Ordering::Relaxed)
+    }
+
+    /// Push `frames` frames, the sample of each channel being given by `sample(frame, channel)`
+    ///
+    /// The frames must fit in the free space of the queue.
+    pub fn push_with<F: FnMut(usize, usize) -> f32>(&mut self, frames: usize, mut sample: F) {
+        debug_assert!(frames <= self.free_frames());
+
+        let len = self.ring.samples.len();
+        let mut position = self.ring.written.load(Ordering::Relaxed);
+        for frame in 0..frames {
+            for channel in 0..self.ring.number_of_channels {
+                let value = sample(frame, channel).to_bits();
+                self.ring.samples[position % len].store(value, Ordering::Relaxed);
+                position += 1;
+            }
+        }
+
+        // publish the samples to the consumer
+        self.ring.written.store(position, Ordering::Release);
+    }
+
+    /// True once the consumer is dropped, the render loop can stop
+    pub fn is_closed(&self) -> bool {
+        self.ring.closed.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for FrameProducer {
+    fn drop(&mut self) {
+        self.ring.closed.store(true, Ordering::Release);
+    }
+}
+
+/// Reading side of a [`frame_queue`], owned by the output callback
+#[derive(Debug)]
+pub(crate) struct FrameConsumer {
+    ring: Arc<Ring>,
+    /// thread of the render loop, woken up when frames are read
+    waker: Option<Thread>,
+}
+
+impl FrameConsumer {
+    /// Wake up the given thread each time frames are read from the queue
+    pub fn set_waker(&mut self, thread: Thread) {
+        self.waker = Some(thread);
+    }
+
+    /// Number of frames pushed and not read yet
+    pub fn queued_frames(&self) -> usize {
+        let written = self.ring.written.load(Ordering::Acquire);
+        let read = self.ring.read.load(Ordering::Relaxed);
+        (written - read) / self.ring.number_of_channels
+    }
+
+    /// Read the next frames into the interleaved `output`, converting the samples to its format
+    ///
+    /// When the queue holds less frames than `output`, the remaining frames are filled with
+    /// silence. Returns the number of frames read from the queue.
+    pub fn pop<S: OutputSample>(&mut self, output: &mut [S], dither: &mut Dither) -> usize {
+        let number_of_channels = self.ring.number_of_channels;
+        self.ring
+            .demand
+            .fetch_max(output.len() / number_of_channels, Ordering::Relaxed);
+
+        let frames = self.queued_frames().min(output.len() / number_of_channels);
+        let (first, next) = output.split_at_mut(frames * number_of_channels);
+
+        let len = self.ring.samples.len();
+        let mut position = self.ring.read.load(Ordering::Relaxed);
+        for sample in first.iter_mut() {
+            let value = f32::from_bits(self.ring.samples[position % len].load(Ordering::Relaxed));
+            *sample = dither.convert(value);
+            position += 1;
+        }
+
+        // hand the space back to the producer
+        self.ring.read.store(position, Ordering::Release);
+        if let Some(thread) = &self.waker {
+            thread.unpark();
+        }
+
+        // silence is not dithered
+        next.fill(S::from_f32(0., 0.));
+
+        frames
+    }
+}
+
+impl Drop for FrameConsumer {
+    fn drop(&mut self) {
+        self.ring.closed.store(true, Ordering::Release);
+        if let Some(thread) = &self.waker {
+            thread.unpark();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_around() {
+        let (mut producer, mut consumer) = frame_queue(2, 5, 0);
+        let mut dither = Dither::new(false);
+        assert_eq!(producer.free_frames(), 5);
+
+        let mut next = 0.;
+        let mut expected = 0.;
+        for _ in 0..20 {
+            // push 3 frames, read 3 frames in two steps
+            producer.push_with(3, |frame, channel| {
+                next + frame as f32 + 100. * channel as f32
+            });
+            next += 3.;
+            assert_eq!(producer.free_frames(), 2);
+            assert_eq!(consumer.queued_frames(), 3);
+
+            let mut output = [0_f32; 4];
+            assert_eq!(consumer.pop(&mut output, &mut dither), 2);
+            assert_eq!(
+                output,
+                [expected, expected + 100., expected + 1., expected + 101.]
+            );
+            expected += 2.;
+
+            assert_eq!(consumer.pop(&mut output[..2], &mut dither), 1);
+            assert_eq!(output[..2], [expected, expected + 100.]);
+            expected += 1.;
+        }
+    }
+
+    #[test]
+    fn test_pop_empty() {
+        let (mut producer, mut consumer) = frame_queue(1, 4, 0);
+        let mut dither = Dither::new(false);
+        producer.push_with(2, |_, _| 1.);
+
+        let mut output = [0.5_f32; 3];
+        assert_eq!(consumer.pop(&mut output, &mut dither), 2);
+        assert_eq!(output, [1., 1., 0.]);
+        assert_eq!(producer.free_frames(), 4);
+        assert_eq!(producer.queued_frames(), 0);
+    }
+
+    #[test]
+    fn test_demand() {
+        let (producer, mut consumer) = frame_queue(1, 4, 2);
+        let mut dither = Dither::new(false);
+        assert_eq!(producer.demand(), 2);
+
+        consumer.pop(&mut [0_f32; 3], &mut dither);
+        assert_eq!(producer.demand(), 3);
+
+        // the smaller reads do not lower the demand
+        consumer.pop(&mut [0_f32; 1], &mut dither);
+        assert_eq!(producer.demand(), 3);
+    }
+
+    #[test]
+    fn test_closed() {
+        let (producer, consumer) = frame_queue(1, 4, 0);
+        assert!(!producer.is_closed());
+        drop(consumer);
+        assert!(producer.is_closed());
+    }
+
+    #[test]
+    fn test_threads() {
+        let (mut producer, mut consumer) = frame_queue(1, 64, 0);
+        let total = 10_000;
+
+        let handle = std::thread::spawn(move || {
+            let mut next = 0;
+            while next < total {
+                std::thread::yield_now();
+                let frames = producer.free_frames().min(total - next);
+                producer.push_with(frames, |frame, _| (next + frame) as f32);
+                next += frames;
+            }
+        });
+
+        let mut dither = Dither::new(false);
+        let mut received = Vec::with_capacity(total);
+        let mut output = [0_f32; 7];
+        while received.len() < total {
+            std::thread::yield_now();
+            let frames = consumer.pop(&mut output, &mut dither);
+            received.extend_from_slice(&output[..frames]);
+        }
+        handle.join().unwrap();
+
+        assert!(received
+            .iter()
+            .enumerate()
+            .all(|(i, value)| *value == i as f32));
+    }
+}
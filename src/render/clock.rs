@@ -0,0 +1,193 @@
+//! Correlation between the rendered frames and the wall clock, for sub-quantum time estimates
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use crate::RENDER_QUANTUM_SIZE;
+
+/// Marks the absence of a recorded output callback
+const NO_CALLBACK: u64 = u64::MAX;
+
+/// Frame count and wall clock time of the last output callback, shared between the render
+/// thread and the control thread
+///
+/// See [`BaseAudioContext::current_time_interpolated`](crate::context::BaseAudioContext::current_time_interpolated)
+#[derive(Debug)]
+pub(crate) struct CallbackClock {
+    /// time reference of the recorded callbacks
+    origin: Instant,
+    /// odd while a callback is being recorded, so readers never see a torn pair
+    sequence: AtomicU64,
+    /// frames rendered at the last output callback
+    frames: AtomicU64,
+    /// time of the last output callback, in nanoseconds since `origin`
+    nanos: AtomicU64,
+    /// bits of the largest estimate handed out so far
+    ///
+    /// For non-negative floats, the order of the bits matches the order of the values, so
+    /// `fetch_max` keeps the estimates monotonic.
+    latest: AtomicU64,
+}
+
+impl Default for CallbackClock {
+    fn default() -> Self {
+        Self {
+            origin: Instant::now(),
+            sequence: AtomicU64::new(0),
+            frames: AtomicU64::new(0),
+            nanos: AtomicU64::new(NO_CALLBACK),
+            latest: AtomicU64::new(0f64.to_bits()),
+        }
+    }
+}
+
+impl CallbackClock {
+    /// Record an output callback, after which `frames` frames were rendered
+    ///
+    /// Only called from the render thread
+    pub fn record(&self, frames: u64) {
+        let nanos = self.origin.elapsed().as_nanos() as u64;
+        self.record_at(frames, nanos);
+    }
+
+    fn record_at(&self, frames: u64, nanos: u64) {
+        self.sequence.fetch_add(1, Ordering::AcqRel);
+        self.frames.store(frames, Ordering::Release);
+        self.nanos.store(nanos, Ordering::Release);
+        self.sequence.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Estimate of the context time, given the time of the frames rendered so far
+    ///
+    /// The estimate lies between `current_time` and the next render quantum boundary, and never
+    /// decreases. It does not advance when `running` is false.
+    pub fn now(&self, current_time: f64, sample_rate: f32, running: bool) -> f64 {
+        let nanos = self.origin.elapsed().as_nanos() as u64;
+        self.now_at(current_time, sample_rate, running, nanos)
+    }
+
+    fn now_at(&self, current_time: f64, sample_rate: f32, running: bool, nanos: u64) -> f64 {
+        let quantum_duration = RENDER_QUANTUM_SIZE as f64 / sample_rate as f64;
+
+        let estimate = match self.last_callback() {
+            Some((frames, callback_nanos)) if running => {
+                let elapsed = nanos.saturating_sub(callback_nanos) as f64 * 1e-9;
+                frames as f64 / sample_rate as f64 + elapsed
+            }
+            _ => current_time,
+        };
+        let estimate = estimate
+            .min(current_time + quantum_duration)
+            .max(current_time);
+
+        let latest = self.latest.fetch_max(estimate.to_bits(), Ordering::AcqRel);
+        estimate.max(f64::from_bits(latest))
+    }
+
+    /// Frame count and time of the last recorded callback, if any
+    fn last_callback(&self) -> Option<(u64, u64)> {
+        loop {
+            let sequence = self.sequence.load(Ordering::Acquire);
+            if sequence % 2 == 1 {
+                std::hint::spin_loop();
+                continue;
+            }
+            let frames = self.frames.load(Ordering::Acquire);
+            let nanos = self.nanos.load(Ordering::Acquire);
+            if self.sequence.load(Ordering::Acquire) != sequence {
+                continue;
+            }
+            return if nanos == NO_CALLBACK {
+                None
+            } else {
+                Some((frames, nanos))
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use float_eq::assert_float_eq;
+
+    const SAMPLE_RATE: f32 = 48_000.;
+    const QUANTUM: f64 = RENDER_QUANTUM_SIZE as f64 / SAMPLE_RATE as f64;
+    const MS: u64 = 1_000_000;
+
+    fn seconds(frames: u64) -> f64 {
+        frames as f64 / SAMPLE_RATE as f64
+    }
+
+    #[test]
+    fn test_without_callback() {
+        let clock = CallbackClock::default();
+        let time = clock.now_at(seconds(256), SAMPLE_RATE, true, 10 * MS);
+        assert_float_eq!(time, seconds(256), abs <= 0.);
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let clock = CallbackClock::default();
+        clock.record_at(512, 100 * MS);
+
+        let time = clock.now_at(seconds(512), SAMPLE_RATE, true, 100 * MS);
+        assert_float_eq!(time, seconds(512), abs <= 0.);
+
+        let time = clock.now_at(seconds(512), SAMPLE_RATE, true, 101 * MS);
+        assert_float_eq!(time, seconds(512) + 0.001, abs <= 1e-12);
+    }
+
+    #[test]
+    fn test_stalled_callback_is_clamped() {
+        let clock = CallbackClock::default();
+        clock.record_at(512, 100 * MS);
+
+        // no callback for a second, the estimate stops at the next quantum boundary
+        for nanos in [110 * MS, 500 * MS, 1_100 * MS] {
+            let time = clock.now_at(seconds(512), SAMPLE_RATE, true, nanos);
+            assert_float_eq!(time, seconds(512) + QUANTUM, abs <= 1e-12);
+        }
+
+        // the stalled callback eventually comes, time resumes from the rendered frames
+        clock.record_at(640, 1_100 * MS);
+        let time = clock.now_at(seconds(640), SAMPLE_RATE, true, 1_101 * MS);
+        assert_float_eq!(time, seconds(640) + 0.001, abs <= 1e-12);
+    }
+
+    #[test]
+    fn test_monotonic() {
+        let clock = CallbackClock::default();
+        clock.record_at(512, 100 * MS);
+        let mut previous = clock.now_at(seconds(512), SAMPLE_RATE, true, 102 * MS);
+
+        // a callback renders a quantum ahead, before its correlation is recorded
+        let time = clock.now_at(seconds(640), SAMPLE_RATE, true, 102 * MS);
+        assert!(time >= previous);
+        assert_float_eq!(time, seconds(640), abs <= 0.);
+        previous = time;
+
+        // a callback comes early, without rendering new frames (leftover frames)
+        clock.record_at(640, 103 * MS);
+        for nanos in [103 * MS, 104 * MS, 106 * MS, 200 * MS] {
+            let time = clock.now_at(seconds(640), SAMPLE_RATE, true, nanos);
+            assert!(time >= previous, "{} < {}", time, previous);
+            assert!(time <= seconds(640) + QUANTUM + 1e-12);
+            previous = time;
+        }
+    }
+
+    #[test]
+    fn test_not_running() {
+        let clock = CallbackClock::default();
+        clock.record_at(512, 100 * MS);
+
+        let time = clock.now_at(seconds(512), SAMPLE_RATE, false, 102 * MS);
+        assert_float_eq!(time, seconds(512), abs <= 0.);
+
+        // the estimate given while running is kept
+        let running = clock.now_at(seconds(512), SAMPLE_RATE, true, 101 * MS);
+        let time = clock.now_at(seconds(512), SAMPLE_RATE, false, 105 * MS);
+        assert_float_eq!(time, running, abs <= 0.);
+    }
+}
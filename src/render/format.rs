@@ -0,0 +1,161 @@
+//! Conversion of the rendered samples to the sample format of the output
+
+/// Sample format of an audio output, converted from the `f32` samples of the graph
+///
+/// The `f32` samples in the `[-1, 1]` range are scaled by `32768` for the integer formats, so
+/// `-1.` is the lowest value of the format. Values out of range are clamped, `1.` maps to the
+/// highest value.
+pub trait OutputSample: Copy {
+    /// Whether the format is quantized, and benefits from dithering
+    const QUANTIZED: bool;
+
+    /// Convert a rendered sample, adding `dither` noise expressed in steps of the format
+    ///
+    /// The noise is ignored by the formats that are not quantized.
+    fn from_f32(value: f32, dither: f32) -> Self;
+}
+
+impl OutputSample for f32 {
+    const QUANTIZED: bool = false;
+
+    #[inline]
+    fn from_f32(value: f32, _dither: f32) -> Self {
+        value
+    }
+}
+
+impl OutputSample for i16 {
+    const QUANTIZED: bool = true;
+
+    #[inline]
+    fn from_f32(value: f32, dither: f32) -> Self {
+        let scaled = value * 32768. + dither;
+        // round half away from zero, the cast truncates, saturates and maps NaN to zero
+        let half = if scaled < 0. { -0.5 } else { 0.5 };
+        (scaled + half) as i16
+    }
+}
+
+impl OutputSample for u16 {
+    const QUANTIZED: bool = true;
+
+    #[inline]
+    fn from_f32(value: f32, dither: f32) -> Self {
+        // offset binary: flipping the sign bit shifts the range by 32768
+        (i16::from_f32(value, dither) as u16) ^ 0x8000
+    }
+}
+
+/// Triangular probability density function (TPDF) dither, applied to the quantized formats
+///
+/// The noise spans `]-1, 1[` steps of the output format. It makes the quantization error
+/// independent of the signal, so quiet material does not get distorted by the truncation.
+///
+/// The noise is a hash of a sample counter rather than the output of a sequential generator,
+/// so the conversion has no dependency from one sample to the next.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Dither {
+    enabled: bool,
+    counter: u32,
+}
+
+#[cfg(feature = "std")]
+impl Dither {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            counter: 0,
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Convert a rendered sample to the output format
+    #[inline]
+    pub fn convert<S: OutputSample>(&mut self, value: f32) -> S {
+        if !S::QUANTIZED || !self.enabled {
+            return S::from_f32(value, 0.);
+        }
+
+        self.counter = self.counter.wrapping_add(1);
+        S::from_f32(value, tpdf(self.counter))
+    }
+}
+
+/// TPDF noise in `]-1, 1[`, the difference of two uniform values drawn from the hash of `n`
+#[cfg(feature = "std")]
+#[inline]
+fn tpdf(n: u32) -> f32 {
+    // integer hash with a low bias, see https://nullprogram.com/blog/2018/07/31/
+    let mut x = n;
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^= x >> 16;
+
+    let a = (x & 0xffff) as f32;
+    let b = (x >> 16) as f32;
+    (a - b) / 65536.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaling() {
+        assert_eq!(f32::from_f32(0.25, 0.5), 0.25);
+
+        assert_eq!(i16::from_f32(1., 0.), i16::MAX);
+        assert_eq!(i16::from_f32(-1., 0.), i16::MIN);
+        assert_eq!(i16::from_f32(0., 0.), 0);
+        assert_eq!(i16::from_f32(0.5, 0.), 16384);
+        assert_eq!(i16::from_f32(-0.5, 0.), -16384);
+        assert_eq!(i16::from_f32(2., 0.), i16::MAX);
+        assert_eq!(i16::from_f32(-2., 0.), i16::MIN);
+        assert_eq!(i16::from_f32(f32::NAN, 0.), 0);
+
+        assert_eq!(u16::from_f32(1., 0.), u16::MAX);
+        assert_eq!(u16::from_f32(-1., 0.), u16::MIN);
+        assert_eq!(u16::from_f32(0., 0.), 32768);
+        assert_eq!(u16::from_f32(0.5, 0.), 49152);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_dither_disabled() {
+        let mut dither = Dither::new(false);
+        for _ in 0..100 {
+            assert_eq!(dither.convert::<i16>(1. / 32768.), 1);
+        }
+
+        // never applied to the float format
+        let mut dither = Dither::new(true);
+        for _ in 0..100 {
+            assert_eq!(dither.convert::<f32>(0.1), 0.1);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_tpdf() {
+        let n = 100_000;
+        let values: Vec<f32> = (0..n).map(tpdf).collect();
+        assert!(values.iter().all(|v| v.abs() < 1.));
+
+        // triangular distribution: zero mean, variance of 1/6
+        let mean = values.iter().sum::<f32>() / n as f32;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / n as f32;
+        assert!(mean.abs() < 1e-2);
+        assert!((variance - 1. / 6.).abs() < 1e-2);
+
+        // twice as many values around zero than half way to the bounds
+        let count = |center: f32| values.iter().filter(|v| (*v - center).abs() < 0.05).count();
+        let ratio = count(0.) as f32 / count(0.5) as f32;
+        assert!((ratio - 2.).abs() < 0.2);
+    }
+}
@@ -1,25 +1,62 @@
 //! The audio graph topology and render algorithm
 
-use std::collections::HashMap;
-
-use super::{Alloc, AudioParamValues, AudioProcessor, AudioRenderQuantum, NodeIndex};
-use crate::node::ChannelConfig;
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::{
+    Alloc, AudioParamValues, AudioProcessor, AudioRenderQuantum, MuteRamp, NodeIndex, NodeMap,
+    OverloadPolicy, DEFAULT_RENDER_PRIORITY,
+};
+use crate::node::{ChannelConfig, ChannelConfigOptions};
+#[cfg(feature = "std")]
+use crate::param::{AudioParamEvent, AudioParamProcessor};
 use crate::render::RenderScope;
 
+#[cfg(feature = "std")]
+use crossbeam_channel::Sender;
 use smallvec::{smallvec, SmallVec};
 
+/// Kind of a connection between two nodes of the [`Graph`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Audio signal, summed into the given input port of the destination node
+    Audio { input: usize },
+    /// A single channel of the audio signal, summed as mono into the given input port of the
+    /// destination node. The channel is silent when the output has fewer channels.
+    AudioChannel { input: usize, channel: usize },
+    /// `AudioParam` connected to the node it belongs to: its output is read by the processor of
+    /// the node instead of being summed into an input
+    Param,
+    /// No signal, the source node is only rendered along with the destination node
+    KeepAlive,
+}
+
 /// Connection between two audio nodes
 struct OutgoingEdge {
     /// index of the current Nodes output port
     self_index: usize,
     /// reference to the other Node
     other_id: NodeIndex,
-    /// index of the other Nodes input port
+    /// what the other Node does with the output
+    kind: EdgeKind,
+}
+
+/// Audio connection into a node, the inputs are summed in the order of these edges
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct IncomingEdge {
+    /// reference to the other Node
+    other_id: NodeIndex,
+    /// index of the other Nodes output port
     other_index: usize,
+    /// index of the current Nodes input port
+    self_index: usize,
+    /// channel of the other Nodes output to take as mono, or all channels if `None`
+    channel: Option<usize>,
 }
 
 /// Renderer Node in the Audio Graph
-pub struct Node {
+pub(crate) struct Node {
     /// Renderer: converts inputs to outputs
     processor: Box<dyn AudioProcessor>,
     /// Reusable input buffers
@@ -27,13 +64,39 @@ pub struct Node {
     /// Reusable output buffers, consumed by subsequent Nodes in this graph
     outputs: Vec<AudioRenderQuantum>,
     /// Channel configuration: determines up/down-mixing of inputs
-    channel_config: ChannelConfig,
-    /// Outgoing edges: tuple of outcoming node reference, our output index and their input index
+    ///
+    /// A plain copy of the values, so the render loop does not load any atomics for it
+    channel_config: ChannelConfigOptions,
+    /// Outgoing edges: outcoming node reference, our output index and the kind of connection
     outgoing_edges: SmallVec<[OutgoingEdge; 2]>,
+    /// Incoming audio edges, sorted by source node and output so the summation order of the
+    /// inputs does not depend on the iteration order of the nodes
+    incoming_edges: Vec<IncomingEdge>,
     /// Indicates if the control thread has dropped this Node
     free_when_finished: bool,
+    /// Indicates if the node is removed from the graph at the end of the render quantum
+    finished: bool,
     /// Indicates if the node has any incoming connections (for lifecycle management)
     has_inputs_connected: bool,
+    /// Order in which the nodes are degraded when the render thread is overloaded
+    priority: u8,
+    /// Indicates if the node is degraded in the current render quantum
+    degraded: bool,
+    /// Indicates if the tail of the node has ended, it is skipped while its inputs are silent
+    dormant: bool,
+    /// Gain ramp of the outputs when the node is muted or unmuted
+    mute: MuteRamp,
+    /// Nodes of a frozen chain, rendered right after this node (see [`Graph::freeze_chain`])
+    fused: Vec<(NodeIndex, Node)>,
+    /// Delivers the automation events to the processor, if this node is an AudioParam
+    #[cfg(feature = "std")]
+    param_events: Option<Sender<AudioParamEvent>>,
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        self.processor.finalize();
+    }
 }
 
 impl Node {
@@ -44,6 +107,10 @@ impl Node {
     }
 
     /// Determine if this node is done playing and can be removed from the audio graph
+    ///
+    /// `tail_time` is the return value of the processor. Scheduled source nodes keep
+    /// returning `true` while their start is pending or while they are playing, so they keep
+    /// playing after their control handle is dropped.
     fn can_free(&self, tail_time: bool) -> bool {
         // Only drop when the Control thread has dropped its handle.
         // Otherwise the node can be reconnected/restarted etc.
@@ -62,16 +129,85 @@ impl Node {
         false
     }
 
+    /// Determine if this node is an AudioParam, connected to the hidden input of its node
+    fn is_audio_param(&self) -> bool {
+        self.outgoing_edges
+            .iter()
+            .any(|edge| edge.kind == EdgeKind::Param)
+    }
+
+    /// Determine if this node is only rendering its tail, i.e. all its inputs are silent
+    fn is_tail_only(&self) -> bool {
+        !self.inputs.is_empty()
+            && !self.is_audio_param()
+            && self
+                .inputs
+                .iter()
+                .all(|input| input.channels().iter().all(|c| c.is_silent()))
+    }
+
+    /// Priority of the node when the rendering is degraded, AudioParams share the priority of
+    /// the node they belong to
+    fn render_priority(&self, nodes: &NodeMap<Node>) -> u8 {
+        self.outgoing_edges
+            .iter()
+            .find(|edge| edge.kind == EdgeKind::Param)
+            .and_then(|edge| nodes.get(&edge.other_id))
+            .map_or(self.priority, |node| node.priority)
+    }
+
+    /// Outgoing edges of the node, or of the last node of its frozen chain
+    fn chain_edges(&self) -> &[OutgoingEdge] {
+        self.fused
+            .last()
+            .map_or(&self.outgoing_edges, |(_, node)| &node.outgoing_edges)
+    }
+
     /// Get the current buffer for AudioParam values
+    #[cfg(feature = "std")]
     pub fn get_buffer(&self) -> &AudioRenderQuantum {
         self.outputs.get(0).unwrap()
     }
 }
 
-/// The audio graph
-pub(crate) struct Graph {
+/// The audio graph, renders the processors in topological order
+///
+/// The [`AudioContext`](crate::context::AudioContext) runs a graph on its render thread, but
+/// it can also be driven manually, e.g. on targets without `std`. The node with index 0 is the
+/// destination: [`Graph::render`] returns its first output.
+///
+/// ```
+/// use web_audio_api::node::ChannelConfig;
+/// use web_audio_api::render::{
+///     AudioParamValues, AudioProcessor, AudioRenderQuantum, Graph, NodeIndex, RenderScope,
+/// };
+///
+/// // outputs its input unchanged
+/// struct PassThrough;
+///
+/// impl AudioProcessor for PassThrough {
+///     fn process(
+///         &mut self,
+///         inputs: &[AudioRenderQuantum],
+///         outputs: &mut [AudioRenderQuantum],
+///         _params: AudioParamValues,
+///         _scope: &RenderScope,
+///     ) -> bool {
+///         outputs[0] = inputs[0].clone();
+///         false
+///     }
+/// }
+///
+/// let mut graph = Graph::new();
+/// graph.add_node(NodeIndex(0), Box::new(PassThrough), 1, 1, ChannelConfig::default());
+///
+/// let scope = RenderScope::new(0, 48_000.);
+/// let output = graph.render(&scope);
+/// assert!(output.channels().iter().all(|c| c.is_silent()));
+/// ```
+pub struct Graph {
     /// Processing Nodes
-    nodes: HashMap<NodeIndex, Node>,
+    nodes: NodeMap<Node>,
     /// Allocator for audio buffers
     alloc: Alloc,
 
@@ -83,20 +219,50 @@ pub(crate) struct Graph {
     marked_temp: Vec<NodeIndex>,
     /// Topological sorting helper
     in_cycle: Vec<NodeIndex>,
+
+    /// Degradations applied when the render thread is overloaded
+    overload_policy: Option<OverloadPolicy>,
+    /// Load of the last render quantum
+    load: f64,
+    /// Degradation helper, nodes sorted by priority
+    shed_order: Vec<(u8, NodeIndex)>,
+    /// Indicates if `shed_order` must be sorted again before use
+    shed_order_stale: bool,
+    /// Indicates if any node is degraded in the current render quantum
+    any_degraded: bool,
+
+    /// Nodes of the frozen chains, with the first node of their chain
+    fused_into: NodeMap<NodeIndex>,
+}
+
+impl Default for Graph {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Graph {
+    /// Create an empty graph
     pub fn new() -> Self {
         Graph {
-            nodes: HashMap::new(),
+            nodes: NodeMap::new(),
             ordered: vec![],
             marked: vec![],
             marked_temp: vec![],
             in_cycle: vec![],
             alloc: Alloc::with_capacity(64),
+            overload_policy: None,
+            load: 0.,
+            shed_order: vec![],
+            shed_order_stale: true,
+            any_degraded: false,
+            fused_into: NodeMap::new(),
         }
     }
 
+    /// Add a node rendered by the given processor
+    ///
+    /// Its inputs are up/down-mixed following the channel configuration.
     pub fn add_node(
         &mut self,
         index: NodeIndex,
@@ -112,67 +278,446 @@ impl Graph {
         let inputs = vec![AudioRenderQuantum::from(self.alloc.silence()); number_of_inputs];
         let outputs = vec![AudioRenderQuantum::from(self.alloc.silence()); number_of_outputs];
 
+        // keep room for the degradation order, it is rebuilt on the render thread
+        self.shed_order.reserve(self.nodes.len() + 1);
+        self.shed_order_stale = true;
+
         self.nodes.insert(
             index,
             Node {
                 processor,
                 inputs,
                 outputs,
-                channel_config,
+                channel_config: channel_config.options(),
                 outgoing_edges: smallvec![],
+                incoming_edges: vec![],
                 free_when_finished: false,
+                finished: false,
                 has_inputs_connected: false,
+                priority: DEFAULT_RENDER_PRIORITY,
+                degraded: false,
+                dormant: false,
+                mute: MuteRamp::default(),
+                fused: vec![],
+                #[cfg(feature = "std")]
+                param_events: None,
             },
         );
     }
 
+    /// Connect an output of the source node to an input of the destination node
+    ///
+    /// # Panics
+    ///
+    /// Panics if the source node is not part of the graph
     pub fn add_edge(&mut self, source: (NodeIndex, usize), dest: (NodeIndex, usize)) {
+        let kind = EdgeKind::Audio { input: dest.1 };
+        self.add_edge_of_kind(source, dest.0, kind);
+    }
+
+    /// Connect an output of the source node to the destination node, see [`EdgeKind`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if the source node is not part of the graph
+    pub fn add_edge_of_kind(
+        &mut self,
+        source: (NodeIndex, usize),
+        dest: NodeIndex,
+        kind: EdgeKind,
+    ) {
+        self.unfreeze_chain(source.0);
+        self.unfreeze_chain(dest);
+
         self.nodes
             .get_mut(&source.0)
-            .unwrap_or_else(|| panic!("cannot connect {:?} to {:?}", source, dest))
+            .unwrap_or_else(|| panic!("cannot connect {:?} to {:?} ({:?})", source, dest, kind))
             .outgoing_edges
             .push(OutgoingEdge {
                 self_index: source.1,
-                other_id: dest.0,
-                other_index: dest.1,
+                other_id: dest,
+                kind,
             });
 
+        let incoming = match kind {
+            EdgeKind::Audio { input } => Some((input, None)),
+            EdgeKind::AudioChannel { input, channel } => Some((input, Some(channel))),
+            EdgeKind::Param | EdgeKind::KeepAlive => None,
+        };
+        if let (Some((input, channel)), Some(node)) = (incoming, self.nodes.get_mut(&dest)) {
+            let edge = IncomingEdge {
+                other_id: source.0,
+                other_index: source.1,
+                self_index: input,
+                channel,
+            };
+            let position = node.incoming_edges.partition_point(|e| *e <= edge);
+            node.incoming_edges.insert(position, edge);
+        }
+
         self.ordered.clear(); // void current ordering
     }
 
+    /// Remove all connections from the source node to the destination node
+    ///
+    /// # Panics
+    ///
+    /// Panics if the source node is not part of the graph
     pub fn remove_edge(&mut self, source: NodeIndex, dest: NodeIndex) {
+        self.unfreeze_chain(source);
+        self.unfreeze_chain(dest);
+
         self.nodes
             .get_mut(&source)
             .unwrap_or_else(|| panic!("cannot remove the edge from {:?} to {:?}", source, dest))
             .outgoing_edges
             .retain(|edge| edge.other_id != dest);
+        if let Some(node) = self.nodes.get_mut(&dest) {
+            node.incoming_edges.retain(|edge| edge.other_id != source);
+        }
 
         self.ordered.clear(); // void current ordering
     }
 
+    /// Remove all connections from and to the node
     pub fn remove_edges_from(&mut self, source: NodeIndex) {
-        let node = self
-            .nodes
-            .get_mut(&source)
-            .unwrap_or_else(|| panic!("cannot remove edges from {:?}", source));
-        node.outgoing_edges.clear();
+        self.unfreeze_chain(source);
+
+        // Disconnecting a node that is not (or no longer) part of the graph is a no-op, there
+        // are no outgoing edges to remove. Do not assume this node still exists:
+        if let Some(node) = self.nodes.get_mut(&source) {
+            node.outgoing_edges.clear();
+            node.incoming_edges.clear();
+        }
 
         self.nodes.values_mut().for_each(|node| {
             node.outgoing_edges.retain(|edge| edge.other_id != source);
+            node.incoming_edges.retain(|edge| edge.other_id != source);
+            // the last node of a frozen chain may be connected to the source too
+            node.fused.iter_mut().for_each(|(_, node)| {
+                node.outgoing_edges.retain(|edge| edge.other_id != source);
+            });
         });
 
         self.ordered.clear(); // void current ordering
     }
 
+    /// Remove the node from the graph once its processor is done and it has no inputs connected
     pub fn mark_free_when_finished(&mut self, index: NodeIndex) {
         // Issue #92, a race condition can occur for AudioParams. They may have already been
         // removed from the audio graph if the node they feed into was dropped.
         // Therefore, do not assume this node still exists:
-        if let Some(node) = self.nodes.get_mut(&index) {
+        if let Some(node) = self.node_mut(index) {
             node.free_when_finished = true;
         }
     }
 
+    /// Set the order in which the node is degraded when the render thread is overloaded,
+    /// lowest priority first
+    pub fn set_render_priority(&mut self, index: NodeIndex, priority: u8) {
+        if let Some(node) = self.node_mut(index) {
+            node.priority = priority;
+            self.shed_order_stale = true;
+        }
+    }
+
+    /// Mute or unmute the outputs of the node, with a linear gain ramp of `ramp_frames`
+    /// sample frames
+    ///
+    /// A ramp in progress is reversed from its current gain.
+    pub fn set_muted(&mut self, index: NodeIndex, muted: bool, ramp_frames: usize) {
+        if let Some(node) = self.node_mut(index) {
+            node.mute.set_muted(muted, ramp_frames);
+        }
+    }
+
+    /// Degrade the rendering instead of glitching when the render thread is overloaded
+    ///
+    /// The load of each render quantum must then be reported with [`Graph::set_load`].
+    pub fn set_overload_policy(&mut self, policy: Option<OverloadPolicy>) {
+        self.overload_policy = policy;
+    }
+
+    /// Report the load of the last render quantum, i.e. its render time relative to the
+    /// duration of a render quantum
+    ///
+    /// The degradations of the [`OverloadPolicy`] are applied in the next render quantum.
+    pub fn set_load(&mut self, load: f64) {
+        self.load = load;
+    }
+
+    /// Flag the nodes degraded in this render quantum, according to the load of the last one
+    fn mark_degraded_nodes(&mut self) {
+        if self.any_degraded {
+            self.nodes
+                .values_mut()
+                .for_each(|node| node.degraded = false);
+            self.any_degraded = false;
+        }
+
+        let share = match self.overload_policy {
+            Some(policy) => policy.degraded_share(self.load),
+            None => return,
+        };
+        if share <= 0. {
+            return;
+        }
+
+        // lowest priority first, then in order of creation (the destination is never degraded)
+        if self.shed_order_stale {
+            let nodes = &self.nodes;
+            self.shed_order.clear();
+            self.shed_order.extend(
+                nodes
+                    .iter()
+                    .filter(|(index, _)| **index != NodeIndex(0))
+                    .map(|(index, node)| (node.render_priority(nodes), *index)),
+            );
+            self.shed_order.sort_unstable();
+            self.shed_order_stale = false;
+        }
+
+        // round up, `f64::ceil` is not available without `std`
+        let exact = share * self.shed_order.len() as f64;
+        let mut count = exact as usize;
+        if (count as f64) < exact {
+            count += 1;
+        }
+
+        let nodes = &mut self.nodes;
+        self.shed_order[..count].iter().for_each(|(_, index)| {
+            if let Some(node) = nodes.get_mut(index) {
+                node.degraded = true;
+            }
+        });
+        self.any_degraded = count > 0;
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn register_audio_param(&mut self, index: NodeIndex) {
+        if let Some(node) = self.nodes.get_mut(&index) {
+            let processor = node.processor.as_audio_param();
+            node.param_events = processor.map(AudioParamProcessor::open_event_channel);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn pass_audio_param_event(&mut self, index: NodeIndex, event: AudioParamEvent) {
+        // The AudioParam may have been removed from the graph already if the node it feeds
+        // into was dropped, the event can be discarded then.
+        if let Some(sender) = self.nodes.get(&index).and_then(|n| n.param_events.as_ref()) {
+            // the processor is owned by the node, so the receiver is alive
+            sender.send(event).unwrap();
+        }
+    }
+
+    /// Replace the processor of the node, the previous processor is dropped
+    pub fn swap_processor(&mut self, index: NodeIndex, processor: Box<dyn AudioProcessor>) {
+        // The node may have been removed from the graph in the meantime, in which case the
+        // new processor is simply dropped. Otherwise the previous processor is dropped here.
+        if let Some(node) = self.node_mut(index) {
+            node.processor.finalize();
+            node.processor = processor;
+            node.dormant = false;
+        }
+    }
+
+    /// Update the channel config of a node, all values at once
+    pub fn set_channel_config(&mut self, index: NodeIndex, options: &ChannelConfigOptions) {
+        self.unfreeze_chain(index);
+
+        if let Some(node) = self.nodes.get_mut(&index) {
+            node.channel_config = options.clone();
+        }
+    }
+
+    /// Determine if all the processors are ready to render the next quantum, see
+    /// [`AudioProcessor::poll_ready`]
+    ///
+    /// All the processors are polled, also when one of them is not ready.
+    pub fn poll_ready(&mut self) -> bool {
+        self.nodes.values_mut().fold(true, |ready, node| {
+            let fused_ready = node.fused.iter_mut().fold(true, |ready, (_, fused)| {
+                fused.processor.poll_ready() && ready
+            });
+            node.processor.poll_ready() && fused_ready && ready
+        })
+    }
+
+    /// Determine if the destination node is registered, i.e. the graph can be rendered
+    pub fn has_destination(&self) -> bool {
+        self.nodes.contains_key(&NodeIndex(0))
+    }
+
+    /// Determine if the node is part of the graph, and if it is dormant
+    #[cfg(test)]
+    pub(crate) fn node_state(&self, index: NodeIndex) -> Option<bool> {
+        match self.fused_into.get(&index) {
+            Some(first) => self.nodes[first]
+                .fused
+                .iter()
+                .find(|(i, _)| *i == index)
+                .map(|(_, node)| node.dormant),
+            None => self.nodes.get(&index).map(|node| node.dormant),
+        }
+    }
+
+    /// Last rendered output of a node, not looking into the frozen chains
+    #[cfg(test)]
+    pub(crate) fn node_output(
+        &self,
+        index: NodeIndex,
+        output: usize,
+    ) -> Option<&AudioRenderQuantum> {
+        self.nodes.get(&index).map(|node| &node.outputs[output])
+    }
+
+    /// Number of nodes in the graph, including the nodes of the frozen chains
+    #[cfg(test)]
+    pub(crate) fn node_count(&self) -> usize {
+        self.nodes.len() + self.fused_into.len()
+    }
+
+    /// Determine if the node is part of a frozen chain
+    #[cfg(test)]
+    pub(crate) fn is_frozen(&self, index: NodeIndex) -> bool {
+        self.fused_into.contains_key(&index)
+            || self
+                .nodes
+                .get(&index)
+                .is_some_and(|node| !node.fused.is_empty())
+    }
+
+    /// Look up a node, also within the frozen chains
+    fn node_mut(&mut self, index: NodeIndex) -> Option<&mut Node> {
+        match self.fused_into.get(&index) {
+            Some(first) => self
+                .nodes
+                .get_mut(first)?
+                .fused
+                .iter_mut()
+                .find(|(i, _)| *i == index)
+                .map(|(_, node)| node),
+            None => self.nodes.get_mut(&index),
+        }
+    }
+
+    /// Render the given nodes, connected in series, as a single step
+    ///
+    /// Each node of the chain must have a single input and output, and all but the last one
+    /// must only be connected to the next one, which has no other incoming connection. The
+    /// nodes are then rendered back to back and their buffers are handed over directly, saving
+    /// the lookups and the dispatch of the separate nodes. The output is identical.
+    ///
+    /// The chain is split again when one of its connections or channel configs changes, or when
+    /// its first node is freed. The nodes of the chain share the degradation of the first one
+    /// when the render thread is overloaded.
+    ///
+    /// Returns `false`, leaving the graph unchanged, if the nodes do not form such a chain.
+    pub fn freeze_chain(&mut self, chain: &[NodeIndex]) -> bool {
+        if !self.is_valid_chain(chain) {
+            return false;
+        }
+
+        let fused = chain[1..]
+            .iter()
+            .filter_map(|index| {
+                self.fused_into.insert(*index, chain[0]);
+                self.nodes.remove(index).map(|node| (*index, node))
+            })
+            .collect();
+        if let Some(head) = self.nodes.get_mut(&chain[0]) {
+            head.fused = fused;
+        }
+
+        self.ordered.clear(); // void current ordering
+        true
+    }
+
+    /// Check the conditions of [`Graph::freeze_chain`]
+    fn is_valid_chain(&self, chain: &[NodeIndex]) -> bool {
+        if chain.len() < 2
+            || chain
+                .iter()
+                .enumerate()
+                .any(|(i, n)| chain[..i].contains(n))
+        {
+            return false;
+        }
+
+        let eligible = chain.iter().all(|index| {
+            *index != NodeIndex(0)
+                && !self.fused_into.contains_key(index)
+                && self.nodes.get(index).is_some_and(|node| {
+                    node.fused.is_empty()
+                        && node.inputs.len() == 1
+                        && node.outputs.len() == 1
+                        && !node.is_audio_param()
+                })
+        });
+        if !eligible {
+            return false;
+        }
+
+        // each node is only connected to the next one
+        let linked = chain
+            .windows(2)
+            .all(|pair| match &self.nodes[&pair[0]].outgoing_edges[..] {
+                [edge] => {
+                    edge.self_index == 0
+                        && edge.other_id == pair[1]
+                        && edge.kind == EdgeKind::Audio { input: 0 }
+                }
+                _ => false,
+            });
+        // which has no other incoming audio connection
+        let single_inputs = self.nodes.iter().all(|(index, node)| {
+            node.outgoing_edges.iter().all(|edge| {
+                !matches!(
+                    edge.kind,
+                    EdgeKind::Audio { .. } | EdgeKind::AudioChannel { .. }
+                ) || !chain[1..].contains(&edge.other_id)
+                    || chain.windows(2).any(|pair| pair == [*index, edge.other_id])
+            })
+        });
+        // and the chain does not loop back to its first node
+        let last = &self.nodes[chain.last().unwrap()];
+        let open = !last.outgoing_edges.iter().any(|e| e.other_id == chain[0]);
+
+        linked && single_inputs && open
+    }
+
+    /// Split the frozen chain of the given node, if any, back into separate nodes
+    fn unfreeze_chain(&mut self, index: NodeIndex) {
+        let first = self.fused_into.get(&index).copied().unwrap_or(index);
+        if let Some(mut node) = self.nodes.remove(&first) {
+            if Self::split_chain(&mut node, &mut self.nodes, &mut self.fused_into) {
+                self.ordered.clear(); // void current ordering
+            }
+            self.nodes.insert(first, node);
+        }
+    }
+
+    /// Move the nodes of the frozen chain of `node` back into the graph, returns `false` if
+    /// there was no chain
+    fn split_chain(
+        node: &mut Node,
+        nodes: &mut NodeMap<Node>,
+        fused_into: &mut NodeMap<NodeIndex>,
+    ) -> bool {
+        if node.fused.is_empty() {
+            return false;
+        }
+
+        core::mem::take(&mut node.fused)
+            .into_iter()
+            .for_each(|(index, node)| {
+                fused_into.remove(&index);
+                nodes.insert(index, node);
+            });
+        true
+    }
+
     /// Helper function for `order_nodes` - traverse node and outgoing edges
     fn visit(
         &self,
@@ -182,6 +727,9 @@ impl Graph {
         ordered: &mut Vec<NodeIndex>,
         in_cycle: &mut Vec<NodeIndex>,
     ) {
+        // The nodes of a frozen chain are rendered along with its first node
+        let node_id = self.fused_into.get(&node_id).copied().unwrap_or(node_id);
+
         // If this node is in the cycle detection list, it is part of a cycle!
         if let Some(pos) = marked_temp.iter().position(|&m| m == node_id) {
             // Mark all nodes in the cycle
@@ -204,7 +752,7 @@ impl Graph {
         self.nodes
             .get(&node_id)
             .unwrap()
-            .outgoing_edges
+            .chain_edges()
             .iter()
             .for_each(|edge| self.visit(edge.other_id, marked, marked_temp, ordered, in_cycle));
 
@@ -231,12 +779,13 @@ impl Graph {
     fn order_nodes(&mut self) {
         // For borrowck reasons, we need the `visit` call to be &self.
         // So move out the bookkeeping Vecs, and pass them around as &mut.
-        let mut ordered = std::mem::take(&mut self.ordered);
-        let mut marked = std::mem::take(&mut self.marked);
-        let mut marked_temp = std::mem::take(&mut self.marked_temp);
-        let mut in_cycle = std::mem::take(&mut self.in_cycle);
+        let mut ordered = core::mem::take(&mut self.ordered);
+        let mut marked = core::mem::take(&mut self.marked);
+        let mut marked_temp = core::mem::take(&mut self.marked_temp);
+        let mut in_cycle = core::mem::take(&mut self.in_cycle);
 
         // Clear previous administration
+        self.shed_order_stale = true;
         ordered.clear();
         marked.clear();
         marked_temp.clear();
@@ -272,76 +821,191 @@ impl Graph {
         self.in_cycle = in_cycle;
     }
 
+    /// Lookup a node, which may be part of a frozen chain
+    fn lookup<'a>(
+        nodes: &'a NodeMap<Node>,
+        fused_into: &NodeMap<NodeIndex>,
+        index: NodeIndex,
+    ) -> Option<&'a Node> {
+        match fused_into.get(&index) {
+            Some(first) => nodes
+                .get(first)?
+                .fused
+                .iter()
+                .find(|(i, _)| *i == index)
+                .map(|(_, node)| node),
+            None => nodes.get(&index),
+        }
+    }
+
+    /// Render a node, unless it can be skipped, and return its tail time
+    fn process_node(
+        node: &mut Node,
+        nodes: &NodeMap<Node>,
+        scope: &RenderScope,
+        reduced_scope: &RenderScope,
+        policy: &OverloadPolicy,
+    ) -> bool {
+        // make sure all input buffers have the correct number of channels, this might not be
+        // the case if the node has no inputs connected or the channel count has just changed
+        let interpretation = node.channel_config.interpretation;
+        let count = node.channel_config.count;
+        node.inputs
+            .iter_mut()
+            .for_each(|i| i.mix(count, interpretation));
+
+        let tail_time = if node.dormant && node.is_tail_only() {
+            // the tail has ended and the inputs are still silent, skip the node
+            node.outputs
+                .iter_mut()
+                .for_each(AudioRenderQuantum::make_silent);
+            false
+        } else if node.degraded && policy.skip_tails && node.is_tail_only() {
+            // skip the node, output silence rather than the previous render quantum
+            node.outputs
+                .iter_mut()
+                .for_each(AudioRenderQuantum::make_silent);
+            true
+        } else {
+            let scope = if node.degraded && policy.k_rate_params {
+                reduced_scope
+            } else {
+                scope
+            };
+
+            // let the current node process
+            let params = AudioParamValues::from(nodes);
+            let tail_time = node.process(params, scope);
+            node.dormant = !tail_time && node.processor.can_go_dormant();
+            tail_time
+        };
+
+        // the ramp moves forward even when the node is skipped
+        node.mute.apply(&mut node.outputs);
+
+        tail_time
+    }
+
     /// Render a single audio quantum by traversing the node list
+    ///
+    /// # Panics
+    ///
+    /// Panics if the destination node, with index 0, is not part of the graph
     pub fn render(&mut self, scope: &RenderScope) -> &AudioRenderQuantum {
         // if the audio graph was changed, determine the new ordering
         if self.ordered.is_empty() {
             self.order_nodes();
         }
 
+        // degrade the rendering if the last render quantum took too long
+        self.mark_degraded_nodes();
+        let policy = self.overload_policy.unwrap_or_default();
+        let reduced_scope = RenderScope {
+            reduced_quality: true,
+            ..*scope
+        };
+
         // keep track of end-of-lifecyle nodes
         let mut nodes_dropped = false;
+        let mut chains_split = false;
 
         // split (mut) borrows
         let ordered = &self.ordered;
         let nodes = &mut self.nodes;
+        let fused_into = &self.fused_into;
+        let in_cycle = &self.in_cycle;
 
         // process every node, in topological sorted order
         ordered.iter().for_each(|index| {
             // remove node from graph, re-insert later (for borrowck reasons)
             let mut node = nodes.remove(index).unwrap();
 
-            // make sure all input buffers have the correct number of channels, this might not be
-            // the case if the node has no inputs connected or the channel count has just changed
-            let interpretation = node.channel_config.interpretation();
-            let count = node.channel_config.count();
-            node.inputs
-                .iter_mut()
-                .for_each(|i| i.mix(count, interpretation));
-
-            // let the current node process
-            let params = AudioParamValues::from(&*nodes);
-            let tail_time = node.process(params, scope);
-
-            // iterate all outgoing edges, lookup these nodes and add to their input
-            node.outgoing_edges
+            // sum the outputs of the incoming edges, in a stable order. The nodes in a cycle
+            // are not rendered, they are muted.
+            let Node {
+                incoming_edges,
+                inputs,
+                channel_config,
+                has_inputs_connected,
+                ..
+            } = &mut node;
+            incoming_edges
                 .iter()
-                // audio params are connected to the 'hidden' usize::MAX output, ignore them here
-                .filter(|edge| edge.other_index != usize::MAX)
+                .filter(|edge| !in_cycle.contains(&edge.other_id))
                 .for_each(|edge| {
-                    let output_node = nodes.get_mut(&edge.other_id).unwrap();
-                    output_node.has_inputs_connected = true;
-                    let signal = &node.outputs[edge.self_index];
-                    let channel_config = &output_node.channel_config;
-
-                    output_node.inputs[edge.other_index].add(signal, channel_config);
+                    if let Some(source) = Self::lookup(nodes, fused_into, edge.other_id) {
+                        let signal = &source.outputs[edge.other_index];
+                        match edge.channel {
+                            Some(channel) => inputs[edge.self_index]
+                                .add(&signal.select_channel(channel), channel_config),
+                            None => inputs[edge.self_index].add(signal, channel_config),
+                        }
+                        *has_inputs_connected = true;
+                    }
                 });
 
-            // Check if we can decommission this node (end of life)
-            if node.can_free(tail_time) {
-                // Node is dropped, we should perform a new topological sort of the audio graph
-                nodes_dropped = true;
+            let tail_time = Self::process_node(&mut node, nodes, scope, &reduced_scope, &policy);
 
-                // Nodes are only dropped when they do not have incoming connections.
-                // But they may have AudioParams feeding into them, these can de dropped too.
-                nodes.retain(|_id, n| !n.outgoing_edges.iter().any(|e| e.other_id == *index));
-            } else {
-                // Node is not dropped.
-                // Reset input buffers as they will be summed up in the next render quantum.
-                node.inputs
-                    .iter_mut()
-                    .for_each(AudioRenderQuantum::make_silent);
+            // render the rest of the frozen chain, handing over the buffers directly
+            let mut fused = core::mem::take(&mut node.fused);
+            for i in 0..fused.len() {
+                let (done, rest) = fused.split_at_mut(i);
+                let signal = done
+                    .last()
+                    .map_or(&node.outputs[0], |(_, previous)| &previous.outputs[0]);
+                let next = &mut rest[0].1;
+                next.inputs[0].add(signal, &next.channel_config);
+                next.has_inputs_connected = true;
+                next.degraded = node.degraded;
 
-                // Reset input state
-                node.has_inputs_connected = false;
+                Self::process_node(next, nodes, scope, &reduced_scope, &policy);
 
-                // Re-insert node in graph
-                nodes.insert(*index, node);
+                next.inputs[0].make_silent();
+                next.has_inputs_connected = false;
             }
+            node.fused = fused;
+
+            // Check if we can decommission this node (end of life). It is dropped at the end
+            // of the render quantum, once the nodes it feeds into have read its output.
+            node.finished = node.can_free(tail_time);
+            nodes_dropped |= node.finished;
+
+            // Reset input buffers as they will be summed up in the next render quantum.
+            node.inputs
+                .iter_mut()
+                .for_each(AudioRenderQuantum::make_silent);
+
+            // Reset input state
+            node.has_inputs_connected = false;
+
+            // Re-insert node in graph
+            nodes.insert(*index, node);
         });
 
-        // If there were any nodes decomissioned, remove from graph order
         if nodes_dropped {
+            let fused_into = &mut self.fused_into;
+            ordered.iter().for_each(|index| {
+                if !nodes.get(index).is_some_and(|node| node.finished) {
+                    return;
+                }
+                let mut node = nodes.remove(index).unwrap();
+                // The rest of its frozen chain is kept, as separate nodes
+                chains_split |= Self::split_chain(&mut node, nodes, fused_into);
+
+                // Nodes are only dropped when they do not have incoming connections.
+                // But they may have AudioParams feeding into them, these can de dropped too.
+                nodes.retain(|_id, n| !n.outgoing_edges.iter().any(|e| e.other_id == *index));
+                nodes
+                    .values_mut()
+                    .for_each(|n| n.incoming_edges.retain(|e| e.other_id != *index));
+            });
+        }
+
+        // If there were any nodes decomissioned, remove from graph order
+        self.shed_order_stale |= nodes_dropped;
+        if chains_split {
+            self.ordered.clear();
+        } else if nodes_dropped {
             let mut i = 0;
             while i < self.ordered.len() {
                 if !nodes.contains_key(&self.ordered[i]) {
@@ -509,4 +1173,563 @@ mod tests {
         // a-cyclic part should be present
         assert!(pos3.unwrap() < pos0.unwrap());
     }
+
+    #[test]
+    fn test_remove_all_unknown_node() {
+        let mut graph = Graph::new();
+
+        let node = Box::new(TestNode {});
+        graph.add_node(NodeIndex(0), node.clone(), 1, 1, config());
+        graph.add_node(NodeIndex(1), node, 1, 1, config());
+        graph.add_edge((NodeIndex(1), 0), (NodeIndex(0), 0));
+
+        // should not panic
+        graph.remove_edges_from(NodeIndex(2));
+        graph.order_nodes();
+
+        assert_eq!(graph.ordered, vec![NodeIndex(1), NodeIndex(0)]);
+    }
+
+    /// Reports tail time for the given number of render quanta, like a scheduled source
+    struct TailNode {
+        remaining: usize,
+    }
+
+    impl AudioProcessor for TailNode {
+        fn process(
+            &mut self,
+            _inputs: &[AudioRenderQuantum],
+            _outputs: &mut [AudioRenderQuantum],
+            _params: AudioParamValues,
+            _scope: &RenderScope,
+        ) -> bool {
+            self.remaining = self.remaining.saturating_sub(1);
+            self.remaining > 0
+        }
+    }
+
+    #[test]
+    fn test_free_when_finished() {
+        let mut graph = Graph::new();
+
+        graph.add_node(NodeIndex(0), Box::new(TestNode {}), 1, 1, config());
+        graph.add_node(NodeIndex(1), Box::new(TestNode {}), 1, 1, config());
+        graph.add_node(
+            NodeIndex(2),
+            Box::new(TailNode { remaining: 3 }),
+            0,
+            1,
+            config(),
+        );
+        graph.add_edge((NodeIndex(1), 0), (NodeIndex(0), 0));
+        graph.add_edge((NodeIndex(2), 0), (NodeIndex(1), 0));
+
+        // nothing is freed while the control handles are alive
+        let scope = RenderScope::new(0, 48_000.);
+        graph.render(&scope);
+        assert_eq!(graph.nodes.len(), 3);
+
+        graph.mark_free_when_finished(NodeIndex(1));
+        graph.mark_free_when_finished(NodeIndex(2));
+
+        // the source is kept alive while it reports tail time, and keeps the
+        // node it is connected to alive
+        graph.render(&scope);
+        assert!(graph.nodes.contains_key(&NodeIndex(2)));
+        assert!(graph.nodes.contains_key(&NodeIndex(1)));
+
+        // the source finishes, its input-less destination can be freed next
+        graph.render(&scope);
+        assert!(!graph.nodes.contains_key(&NodeIndex(2)));
+        assert!(graph.nodes.contains_key(&NodeIndex(1)));
+
+        graph.render(&scope);
+        assert!(!graph.nodes.contains_key(&NodeIndex(1)));
+        assert_eq!(graph.ordered, vec![NodeIndex(0)]);
+    }
+
+    /// Outputs a constant signal, ignoring its inputs, like a node rendering its tail
+    struct ConstantNode {}
+
+    impl AudioProcessor for ConstantNode {
+        fn process(
+            &mut self,
+            _inputs: &[AudioRenderQuantum],
+            outputs: &mut [AudioRenderQuantum],
+            _params: AudioParamValues,
+            _scope: &RenderScope,
+        ) -> bool {
+            outputs[0]
+                .channel_data_mut(0)
+                .iter_mut()
+                .for_each(|o| *o = 1.);
+            true
+        }
+    }
+
+    /// Outputs its input
+    struct PassThroughNode {}
+
+    impl AudioProcessor for PassThroughNode {
+        fn process(
+            &mut self,
+            inputs: &[AudioRenderQuantum],
+            outputs: &mut [AudioRenderQuantum],
+            _params: AudioParamValues,
+            _scope: &RenderScope,
+        ) -> bool {
+            outputs[0] = inputs[0].clone();
+            false
+        }
+    }
+
+    fn degraded_nodes(graph: &Graph) -> Vec<NodeIndex> {
+        let mut degraded: Vec<_> = graph
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.degraded)
+            .map(|(index, _)| *index)
+            .collect();
+        degraded.sort_unstable();
+        degraded
+    }
+
+    #[test]
+    fn test_overload_degraded_order() {
+        let mut graph = Graph::new();
+        graph.set_overload_policy(Some(OverloadPolicy::default()));
+
+        let node = Box::new(TestNode {});
+        graph.add_node(NodeIndex(0), node.clone(), 1, 1, config());
+        graph.add_node(NodeIndex(1), node.clone(), 1, 1, config());
+        graph.add_node(NodeIndex(2), node.clone(), 1, 1, config());
+        graph.add_node(NodeIndex(3), node.clone(), 1, 1, config());
+        // AudioParam of node 1
+        graph.add_node(NodeIndex(4), node, 1, 1, config());
+        graph.add_edge((NodeIndex(1), 0), (NodeIndex(0), 0));
+        graph.add_edge((NodeIndex(2), 0), (NodeIndex(0), 0));
+        graph.add_edge((NodeIndex(3), 0), (NodeIndex(0), 0));
+        graph.add_edge_of_kind((NodeIndex(4), 0), NodeIndex(1), EdgeKind::Param);
+
+        graph.set_render_priority(NodeIndex(1), 10);
+        graph.set_render_priority(NodeIndex(3), 200);
+
+        let scope = RenderScope::new(0, 48_000.);
+
+        // under the threshold
+        graph.set_load(0.7);
+        graph.render(&scope);
+        assert!(degraded_nodes(&graph).is_empty());
+
+        // a quarter of the nodes, node 1 has the lowest priority (node 4 shares it but
+        // was created later)
+        graph.set_load(1.);
+        graph.render(&scope);
+        assert_eq!(degraded_nodes(&graph), vec![NodeIndex(1)]);
+
+        // half of the nodes, then nodes with the default priority
+        graph.set_load(1.2);
+        graph.render(&scope);
+        assert_eq!(degraded_nodes(&graph), vec![NodeIndex(1), NodeIndex(4)]);
+
+        graph.set_load(1.3);
+        graph.render(&scope);
+        assert_eq!(
+            degraded_nodes(&graph),
+            vec![NodeIndex(1), NodeIndex(2), NodeIndex(4)]
+        );
+
+        // all nodes but the destination
+        graph.set_load(2.);
+        graph.render(&scope);
+        assert_eq!(
+            degraded_nodes(&graph),
+            vec![NodeIndex(1), NodeIndex(2), NodeIndex(3), NodeIndex(4)]
+        );
+
+        // full quality is restored
+        graph.set_load(0.1);
+        graph.render(&scope);
+        assert!(degraded_nodes(&graph).is_empty());
+
+        // no policy, no degradation
+        graph.set_overload_policy(None);
+        graph.set_load(2.);
+        graph.render(&scope);
+        assert!(degraded_nodes(&graph).is_empty());
+    }
+
+    #[test]
+    fn test_overload_shed_order_preallocated() {
+        let mut graph = Graph::new();
+        graph.set_overload_policy(Some(OverloadPolicy::default()));
+
+        let node = Box::new(TestNode {});
+        graph.add_node(NodeIndex(0), node.clone(), 1, 1, config());
+        graph.add_node(NodeIndex(1), node.clone(), 1, 1, config());
+        graph.add_node(NodeIndex(2), node, 1, 1, config());
+        graph.add_edge((NodeIndex(1), 0), (NodeIndex(0), 0));
+        graph.add_edge((NodeIndex(2), 0), (NodeIndex(0), 0));
+        let capacity = graph.shed_order.capacity();
+        assert!(capacity >= 3);
+
+        let scope = RenderScope::new(0, 48_000.);
+        graph.set_load(1.2);
+        graph.render(&scope);
+        assert_eq!(degraded_nodes(&graph), vec![NodeIndex(1)]);
+        assert_eq!(graph.shed_order.capacity(), capacity);
+
+        // a new priority is picked up in the next render quantum
+        graph.set_render_priority(NodeIndex(1), 200);
+        graph.render(&scope);
+        assert_eq!(degraded_nodes(&graph), vec![NodeIndex(2)]);
+        assert_eq!(graph.shed_order.capacity(), capacity);
+    }
+
+    #[test]
+    fn test_overload_skip_tails() {
+        let mut graph = Graph::new();
+        graph.set_overload_policy(Some(OverloadPolicy::default()));
+
+        graph.add_node(NodeIndex(0), Box::new(PassThroughNode {}), 1, 1, config());
+        graph.add_node(NodeIndex(1), Box::new(ConstantNode {}), 1, 1, config());
+        graph.add_edge((NodeIndex(1), 0), (NodeIndex(0), 0));
+
+        let scope = RenderScope::new(0, 48_000.);
+        let is_silent = |output: &AudioRenderQuantum| output.channel_data(0).is_silent();
+
+        let output = graph.render(&scope);
+        assert_eq!(output.channel_data(0)[0], 1.);
+
+        // the tail is skipped, its previous output is not rendered again
+        graph.set_load(2.);
+        let output = graph.render(&scope);
+        assert!(is_silent(output));
+
+        graph.set_load(0.);
+        let output = graph.render(&scope);
+        assert_eq!(output.channel_data(0)[0], 1.);
+
+        // a node with a non silent input is not skipped
+        graph.add_node(NodeIndex(2), Box::new(ConstantNode {}), 0, 1, config());
+        graph.add_edge((NodeIndex(2), 0), (NodeIndex(1), 0));
+        graph.set_load(2.);
+        let output = graph.render(&scope);
+        assert_eq!(output.channel_data(0)[0], 1.);
+        assert!(graph.nodes.get(&NodeIndex(1)).unwrap().degraded);
+
+        // skipping can be disabled
+        graph.remove_edge(NodeIndex(2), NodeIndex(1));
+        graph.set_overload_policy(Some(OverloadPolicy {
+            skip_tails: false,
+            ..OverloadPolicy::default()
+        }));
+        let output = graph.render(&scope);
+        assert_eq!(output.channel_data(0)[0], 1.);
+    }
+
+    /// Renders a tail of `remaining` render quanta after its input went silent, and counts
+    /// its calls to `process`
+    struct DormantNode {
+        tail: usize,
+        remaining: usize,
+        calls: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl AudioProcessor for DormantNode {
+        fn process(
+            &mut self,
+            inputs: &[AudioRenderQuantum],
+            outputs: &mut [AudioRenderQuantum],
+            _params: AudioParamValues,
+            _scope: &RenderScope,
+        ) -> bool {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+
+            if !inputs[0].channel_data(0).is_silent() {
+                self.remaining = self.tail;
+            }
+            if self.remaining == 0 {
+                outputs[0].make_silent();
+                return false;
+            }
+
+            self.remaining -= 1;
+            outputs[0]
+                .channel_data_mut(0)
+                .iter_mut()
+                .for_each(|o| *o = 1.);
+            true
+        }
+
+        fn can_go_dormant(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_dormant_node() {
+        let mut graph = Graph::new();
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let count = || calls.load(std::sync::atomic::Ordering::SeqCst);
+
+        graph.add_node(NodeIndex(0), Box::new(PassThroughNode {}), 1, 1, config());
+        let node = DormantNode {
+            tail: 2,
+            remaining: 0,
+            calls: calls.clone(),
+        };
+        graph.add_node(NodeIndex(1), Box::new(node), 1, 1, config());
+        graph.add_node(NodeIndex(2), Box::new(ConstantNode {}), 0, 1, config());
+        graph.add_edge((NodeIndex(1), 0), (NodeIndex(0), 0));
+        graph.add_edge((NodeIndex(2), 0), (NodeIndex(1), 0));
+
+        let scope = RenderScope::new(0, 48_000.);
+
+        // the input is not silent
+        graph.render(&scope);
+        assert_eq!(count(), 1);
+        assert_eq!(graph.node_state(NodeIndex(1)), Some(false));
+
+        // the tail is rendered after the input is disconnected
+        graph.remove_edge(NodeIndex(2), NodeIndex(1));
+        let output = graph.render(&scope);
+        assert_eq!(output.channel_data(0)[0], 1.);
+        let output = graph.render(&scope);
+        assert!(output.channel_data(0).is_silent());
+        assert_eq!(count(), 3);
+        assert_eq!(graph.node_state(NodeIndex(1)), Some(true));
+
+        // the node is skipped while its inputs are silent, but it is not freed
+        for _ in 0..10 {
+            let output = graph.render(&scope);
+            assert!(output.channel_data(0).is_silent());
+        }
+        assert_eq!(count(), 3);
+        assert_eq!(graph.node_state(NodeIndex(1)), Some(true));
+
+        // the node resumes when a non silent input is connected again
+        graph.add_edge((NodeIndex(2), 0), (NodeIndex(1), 0));
+        let output = graph.render(&scope);
+        assert_eq!(output.channel_data(0)[0], 1.);
+        assert_eq!(count(), 4);
+        assert_eq!(graph.node_state(NodeIndex(1)), Some(false));
+
+        // a dormant node is freed once its control handle is dropped
+        graph.remove_edge(NodeIndex(2), NodeIndex(1));
+        graph.mark_free_when_finished(NodeIndex(1));
+        for _ in 0..3 {
+            graph.render(&scope);
+        }
+        assert_eq!(graph.node_state(NodeIndex(1)), None);
+        assert_eq!(count(), 6);
+    }
+
+    /// Outputs its input multiplied by `.0` plus `.1`
+    struct AffineNode(f32, f32);
+
+    impl AudioProcessor for AffineNode {
+        fn process(
+            &mut self,
+            inputs: &[AudioRenderQuantum],
+            outputs: &mut [AudioRenderQuantum],
+            _params: AudioParamValues,
+            _scope: &RenderScope,
+        ) -> bool {
+            outputs[0] = inputs[0].clone();
+            outputs[0]
+                .channels_mut()
+                .iter_mut()
+                .for_each(|c| c.iter_mut().for_each(|v| *v = *v * self.0 + self.1));
+            false
+        }
+    }
+
+    /// Constant source 1 into the chain 2 -> 3 -> 4 into the destination, node 5 is unconnected
+    fn chain_graph() -> Graph {
+        let mut graph = Graph::new();
+        graph.add_node(NodeIndex(0), Box::new(PassThroughNode {}), 1, 1, config());
+        graph.add_node(NodeIndex(1), Box::new(ConstantNode {}), 0, 1, config());
+        graph.add_node(NodeIndex(2), Box::new(AffineNode(2., 1.)), 1, 1, config());
+        graph.add_node(NodeIndex(3), Box::new(AffineNode(3., 0.)), 1, 1, config());
+        graph.add_node(NodeIndex(4), Box::new(AffineNode(1., -1.)), 1, 1, config());
+        graph.add_node(NodeIndex(5), Box::new(ConstantNode {}), 0, 1, config());
+        graph.add_edge((NodeIndex(1), 0), (NodeIndex(2), 0));
+        graph.add_edge((NodeIndex(2), 0), (NodeIndex(3), 0));
+        graph.add_edge((NodeIndex(3), 0), (NodeIndex(4), 0));
+        graph.add_edge((NodeIndex(4), 0), (NodeIndex(0), 0));
+
+        graph
+    }
+
+    #[test]
+    fn test_freeze_chain_validation() {
+        let mut graph = chain_graph();
+        let chain = |ids: &[u64]| ids.iter().copied().map(NodeIndex).collect::<Vec<_>>();
+
+        // too short, not connected in series, with the destination, or with unknown nodes
+        assert!(!graph.freeze_chain(&chain(&[2])));
+        assert!(!graph.freeze_chain(&chain(&[2, 4])));
+        assert!(!graph.freeze_chain(&chain(&[4, 3])));
+        assert!(!graph.freeze_chain(&chain(&[3, 4, 0])));
+        assert!(!graph.freeze_chain(&chain(&[3, 4, 6])));
+        assert!(!graph.freeze_chain(&chain(&[3, 3])));
+
+        // fan-out in the middle of the chain
+        graph.add_edge((NodeIndex(3), 0), (NodeIndex(0), 0));
+        assert!(!graph.freeze_chain(&chain(&[2, 3, 4])));
+        graph.remove_edge(NodeIndex(3), NodeIndex(0));
+
+        // extra input in the middle of the chain, the first node may have any input
+        graph.add_edge((NodeIndex(5), 0), (NodeIndex(3), 0));
+        assert!(!graph.freeze_chain(&chain(&[2, 3, 4])));
+        graph.remove_edge(NodeIndex(5), NodeIndex(3));
+        graph.add_edge((NodeIndex(5), 0), (NodeIndex(2), 0));
+        assert!(graph.freeze_chain(&chain(&[2, 3, 4])));
+
+        // nodes cannot be part of two chains
+        assert!(!graph.freeze_chain(&chain(&[3, 4])));
+        assert!((2..5).all(|i| graph.is_frozen(NodeIndex(i))));
+    }
+
+    #[test]
+    fn test_freeze_chain_render() {
+        let mut graph = chain_graph();
+        let scope = RenderScope::new(0, 48_000.);
+        let reference = graph.render(&scope).clone();
+        // ((1 * 2 + 1) * 3) - 1
+        assert_eq!(reference.channel_data(0)[0], 8.);
+
+        assert!(graph.freeze_chain(&[NodeIndex(2), NodeIndex(3), NodeIndex(4)]));
+        let output = graph.render(&scope);
+        assert_eq!(output.number_of_channels(), reference.number_of_channels());
+        assert_eq!(&output.channel_data(0)[..], &reference.channel_data(0)[..]);
+        assert_eq!(&output.channel_data(1)[..], &reference.channel_data(1)[..]);
+
+        // the nodes of the chain can still be reached
+        graph.mark_free_when_finished(NodeIndex(3));
+        assert_eq!(graph.node_state(NodeIndex(3)), Some(false));
+        assert!(graph.is_frozen(NodeIndex(3)));
+        assert_eq!(graph.render(&scope).channel_data(0)[0], 8.);
+    }
+
+    #[test]
+    fn test_unfreeze_chain() {
+        let mut graph = chain_graph();
+        let scope = RenderScope::new(0, 48_000.);
+        let chain = [NodeIndex(2), NodeIndex(3), NodeIndex(4)];
+
+        // on a connection change
+        assert!(graph.freeze_chain(&chain));
+        graph.add_edge((NodeIndex(4), 0), (NodeIndex(0), 0));
+        assert!(!graph.is_frozen(NodeIndex(2)));
+        // the last node is connected twice to the destination now
+        assert_eq!(graph.render(&scope).channel_data(0)[0], 16.);
+        graph.remove_edge(NodeIndex(4), NodeIndex(0));
+        graph.add_edge((NodeIndex(4), 0), (NodeIndex(0), 0));
+
+        // on a channel config change
+        assert!(graph.freeze_chain(&chain));
+        let options = ChannelConfigOptions {
+            count: 1,
+            mode: crate::node::ChannelCountMode::Explicit,
+            interpretation: crate::node::ChannelInterpretation::Discrete,
+        };
+        graph.set_channel_config(NodeIndex(3), &options);
+        assert!(!graph.is_frozen(NodeIndex(2)));
+        assert_eq!(graph.render(&scope).channel_data(0)[0], 8.);
+
+        // when the first node is freed, the rest of the chain is kept
+        assert!(graph.freeze_chain(&chain));
+        graph.mark_free_when_finished(NodeIndex(2));
+        graph.remove_edge(NodeIndex(1), NodeIndex(2));
+        graph.render(&scope);
+        assert_eq!(graph.node_state(NodeIndex(2)), None);
+        assert_eq!(graph.node_state(NodeIndex(3)), Some(false));
+        assert!(!graph.is_frozen(NodeIndex(3)));
+        assert_eq!(graph.render(&scope).channel_data(0)[0], -1.);
+    }
+
+    #[test]
+    fn test_edge_kinds() {
+        let mut graph = chain_graph();
+        let scope = RenderScope::new(0, 48_000.);
+
+        // the output of params and keep-alive connections is not summed into the inputs
+        graph.add_edge_of_kind((NodeIndex(5), 0), NodeIndex(3), EdgeKind::Param);
+        graph.add_edge_of_kind((NodeIndex(5), 0), NodeIndex(0), EdgeKind::KeepAlive);
+        assert_eq!(graph.render(&scope).channel_data(0)[0], 8.);
+        assert_eq!(graph.node_state(NodeIndex(5)), Some(false));
+
+        // the chain is still single input
+        assert!(graph.freeze_chain(&[NodeIndex(2), NodeIndex(3), NodeIndex(4)]));
+        assert_eq!(graph.render(&scope).channel_data(0)[0], 8.);
+
+        // an audio connection into the chain is summed, and splits it
+        graph.add_edge_of_kind(
+            (NodeIndex(5), 0),
+            NodeIndex(3),
+            EdgeKind::Audio { input: 0 },
+        );
+        assert!(!graph.is_frozen(NodeIndex(2)));
+        assert_eq!(graph.render(&scope).channel_data(0)[0], 11.);
+    }
+
+    #[test]
+    fn test_summation_order() {
+        let mut graph = Graph::new();
+        graph.add_node(NodeIndex(0), Box::new(PassThroughNode {}), 1, 1, config());
+        // the sum depends on the order of the additions in floating point
+        let values = [1., 1e8, -1e8];
+        for (i, &value) in values.iter().enumerate() {
+            let index = NodeIndex(i as u64 + 1);
+            graph.add_node(index, Box::new(AffineNode(0., value)), 1, 1, config());
+        }
+        graph.add_edge((NodeIndex(3), 0), (NodeIndex(0), 0));
+        graph.add_edge((NodeIndex(1), 0), (NodeIndex(0), 0));
+        graph.add_edge((NodeIndex(2), 0), (NodeIndex(0), 0));
+
+        // summed by increasing node index, whatever the order of the connections
+        let scope = RenderScope::new(0, 48_000.);
+        assert_eq!(graph.render(&scope).channel_data(0)[0], 0.);
+    }
+
+    #[test]
+    fn test_poll_ready() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        // counts the polls, never ready
+        struct Waiting(Arc<AtomicUsize>);
+
+        impl AudioProcessor for Waiting {
+            fn process(
+                &mut self,
+                _inputs: &[AudioRenderQuantum],
+                _outputs: &mut [AudioRenderQuantum],
+                _params: AudioParamValues,
+                _scope: &RenderScope,
+            ) -> bool {
+                true
+            }
+
+            fn poll_ready(&mut self) -> bool {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+        }
+
+        let mut graph = Graph::new();
+        graph.add_node(NodeIndex(0), Box::new(TestNode {}), 1, 1, config());
+        assert!(graph.poll_ready());
+
+        let polls = Arc::new(AtomicUsize::new(0));
+        let waiting = || Box::new(Waiting(Arc::clone(&polls)));
+        graph.add_node(NodeIndex(1), waiting(), 1, 1, config());
+        graph.add_node(NodeIndex(2), waiting(), 1, 1, config());
+        assert!(!graph.poll_ready());
+
+        // all the processors are polled
+        assert_eq!(polls.load(Ordering::Relaxed), 2);
+    }
 }
@@ -1,18 +1,54 @@
 //! Primitives related to audio graph rendering
 
-use std::fmt::Debug;
+/// Identifier of a node in the [`Graph`]
+///
+/// The node with index 0 is the destination, its output is the output of the graph.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct NodeIndex(pub u64);
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-pub(crate) struct NodeIndex(pub u64);
-
-// private mods
-mod graph;
+/// Storage of the graph nodes, a `HashMap` when `std` is available
+#[cfg(feature = "std")]
+type NodeMap<V> = std::collections::HashMap<NodeIndex, V>;
+#[cfg(not(feature = "std"))]
+type NodeMap<V> = alloc::collections::BTreeMap<NodeIndex, V>;
 
 // pub(crate) mods
+#[cfg(feature = "std")]
+mod ahead;
+#[cfg(feature = "std")]
+pub(crate) use ahead::{frame_queue, FrameConsumer, FrameProducer};
+#[cfg(feature = "cpal")]
+mod clock;
+#[cfg(feature = "cpal")]
+pub(crate) use clock::CallbackClock;
+#[cfg(feature = "std")]
+mod snapshot;
+#[cfg(feature = "std")]
+pub use snapshot::AudioBufferSnapshot;
+#[cfg(feature = "std")]
+pub(crate) use snapshot::{snapshot_buffer, SnapshotBuffer, SnapshotWriter};
+#[cfg(feature = "std")]
 mod thread;
+#[cfg(feature = "std")]
 pub(crate) use thread::*;
+#[cfg(feature = "cpal")]
+mod xrun;
+#[cfg(feature = "cpal")]
+pub use xrun::XrunStats;
+#[cfg(feature = "cpal")]
+pub(crate) use xrun::{XrunCounters, XrunDetector};
 
 // public mods
+mod format;
+#[cfg(feature = "std")]
+pub(crate) use format::Dither;
+pub use format::OutputSample;
+mod graph;
+pub use graph::*;
+mod mute;
+pub use mute::*;
+mod overload;
+pub use overload::*;
 mod processor;
 pub use processor::*;
 mod quantum;
@@ -0,0 +1,143 @@
+//! Click-free muting of the output of the nodes
+
+use super::AudioRenderQuantum;
+
+/// Duration of the gain ramp of a mute or unmute, in seconds, unless set otherwise
+///
+/// See [`AudioNode::set_muted`](crate::node::AudioNode::set_muted)
+pub const DEFAULT_MUTE_RAMP_DURATION: f64 = 0.005;
+
+/// Linear gain ramp applied to the outputs of a node when it is muted or unmuted
+///
+/// The gain moves towards its target by a constant step per sample frame, so toggling the
+/// mute in the middle of a ramp reverses it from the current gain.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct MuteRamp {
+    /// gain applied to the last rendered sample frame
+    gain: f32,
+    /// 0. when muted, 1. otherwise
+    target: f32,
+    /// change of the gain per sample frame
+    step: f32,
+}
+
+impl Default for MuteRamp {
+    fn default() -> Self {
+        Self {
+            gain: 1.,
+            target: 1.,
+            step: f32::INFINITY,
+        }
+    }
+}
+
+impl MuteRamp {
+    /// Ramp towards the muted or unmuted state, a full ramp taking `ramp_frames` sample frames
+    pub fn set_muted(&mut self, muted: bool, ramp_frames: usize) {
+        self.target = if muted { 0. } else { 1. };
+        self.step = if ramp_frames == 0 {
+            f32::INFINITY
+        } else {
+            1. / ramp_frames as f32
+        };
+    }
+
+    /// Apply the gain to the outputs of the node, and move the ramp forward by a render quantum
+    pub fn apply(&mut self, outputs: &mut [AudioRenderQuantum]) {
+        if self.gain == self.target {
+            if self.target == 0. {
+                outputs.iter_mut().for_each(AudioRenderQuantum::make_silent);
+            }
+            return;
+        }
+
+        let Self { gain, target, step } = *self;
+        // gain of the sample frame `i`, reaching the target on the last frame of the ramp
+        let gain_at = move |i: usize| {
+            let delta = step * (i + 1) as f32;
+            if target > gain {
+                (gain + delta).min(target)
+            } else {
+                (gain - delta).max(target)
+            }
+        };
+
+        outputs.iter_mut().for_each(|output| {
+            output.modify_non_silent_channels(|channel| {
+                channel
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(i, v)| *v *= gain_at(i));
+            });
+        });
+
+        self.gain = gain_at(crate::RENDER_QUANTUM_SIZE - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Alloc;
+    use crate::RENDER_QUANTUM_SIZE;
+
+    fn ones() -> AudioRenderQuantum {
+        let alloc = Alloc::with_capacity(1);
+        let mut quantum = AudioRenderQuantum::from(alloc.silence());
+        quantum.channels_mut()[0].iter_mut().for_each(|v| *v = 1.);
+        quantum
+    }
+
+    #[test]
+    fn test_unmuted_is_transparent() {
+        let mut ramp = MuteRamp::default();
+        let mut outputs = [ones()];
+        ramp.apply(&mut outputs);
+        assert!(outputs[0].channel_data(0).iter().all(|v| *v == 1.));
+    }
+
+    #[test]
+    fn test_ramp() {
+        let mut ramp = MuteRamp::default();
+        ramp.set_muted(true, 256);
+
+        let mut outputs = [ones()];
+        ramp.apply(&mut outputs);
+        let data = outputs[0].channel_data(0);
+        assert_eq!(data[0], 1. - 1. / 256.);
+        assert_eq!(data[RENDER_QUANTUM_SIZE - 1], 0.5);
+
+        let mut outputs = [ones()];
+        ramp.apply(&mut outputs);
+        assert_eq!(outputs[0].channel_data(0)[RENDER_QUANTUM_SIZE - 1], 0.);
+
+        // fully muted outputs are silenced
+        let mut outputs = [ones()];
+        ramp.apply(&mut outputs);
+        assert!(outputs[0].channel_data(0).is_silent());
+    }
+
+    #[test]
+    fn test_reverse_mid_ramp() {
+        let mut ramp = MuteRamp::default();
+        ramp.set_muted(true, 512);
+        ramp.apply(&mut [ones()]);
+        assert_eq!(ramp.gain, 0.75);
+
+        // back up from the current gain, at the same rate
+        ramp.set_muted(false, 512);
+        let mut outputs = [ones()];
+        ramp.apply(&mut outputs);
+        assert_eq!(outputs[0].channel_data(0)[0], 0.75 + 1. / 512.);
+        assert_eq!(ramp.gain, 1.);
+    }
+
+    #[test]
+    fn test_without_ramp() {
+        let mut ramp = MuteRamp::default();
+        ramp.set_muted(true, 0);
+        let mut outputs = [ones()];
+        ramp.apply(&mut outputs);
+        assert!(outputs[0].channel_data(0).iter().all(|v| *v == 0.));
+    }
+}
@@ -0,0 +1,68 @@
+//! Graceful degradation of the rendering when the render thread is overloaded
+
+/// Render priority of the nodes, unless set otherwise
+///
+/// See [`AudioNode::set_render_priority`](crate::node::AudioNode::set_render_priority)
+pub const DEFAULT_RENDER_PRIORITY: u8 = 128;
+
+/// Degradations applied by the render thread instead of glitching when it is overloaded
+///
+/// The load of a render quantum is its render time relative to the duration of the quantum.
+/// When the load of the previous render quantum exceeds the `threshold`, a share of the
+/// nodes of the graph, growing with the excess load, is degraded in the next render quantum.
+/// All nodes are degraded once the load reaches twice the threshold.
+///
+/// The degraded nodes are picked by increasing render priority, then by order of creation, so
+/// the degradations are deterministic. An `AudioParam` shares the priority of the node it
+/// belongs to. Full quality is restored as soon as the load is back under the threshold.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OverloadPolicy {
+    /// Load above which the rendering is degraded, in the `]0, 1]` range
+    pub threshold: f64,
+    /// Skip the degraded nodes that are only rendering their tail, i.e. whose inputs are
+    /// silent. They output silence instead.
+    pub skip_tails: bool,
+    /// Compute the degraded a-rate `AudioParam`s at k-rate
+    pub k_rate_params: bool,
+}
+
+impl Default for OverloadPolicy {
+    fn default() -> Self {
+        Self {
+            threshold: 0.8,
+            skip_tails: true,
+            k_rate_params: true,
+        }
+    }
+}
+
+impl OverloadPolicy {
+    /// Share of the nodes, in the `[0, 1]` range, to degrade after a render quantum with the
+    /// given load
+    pub(crate) fn degraded_share(&self, load: f64) -> f64 {
+        if load.is_nan() || load <= self.threshold {
+            return 0.;
+        }
+
+        ((load - self.threshold) / self.threshold).min(1.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_degraded_share() {
+        let policy = OverloadPolicy::default();
+
+        assert_float_eq!(policy.degraded_share(0.), 0., abs <= 0.);
+        assert_float_eq!(policy.degraded_share(0.8), 0., abs <= 0.);
+        assert_float_eq!(policy.degraded_share(f64::NAN), 0., abs <= 0.);
+        assert_float_eq!(policy.degraded_share(1.2), 0.5, abs <= 1e-12);
+        assert_float_eq!(policy.degraded_share(1.6), 1., abs <= 0.);
+        assert_float_eq!(policy.degraded_share(10.), 1., abs <= 0.);
+    }
+}
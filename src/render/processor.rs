@@ -1,10 +1,10 @@
 //! Audio processing code that runs on the audio rendering thread
 
-use std::collections::HashMap;
+#[cfg(feature = "std")]
+use crate::context::{AudioContextState, AudioParamId};
 
-use crate::context::AudioParamId;
-
-use super::{graph::Node, AudioRenderQuantum, NodeIndex};
+use super::{graph::Node, AudioRenderQuantum, NodeMap};
+use crate::param::AudioParamProcessor;
 
 #[non_exhaustive] // we may want to add user-provided blobs to this later
 /// The execution context of all AudioProcessors in a given AudioContext
@@ -16,6 +16,49 @@ pub struct RenderScope {
     pub current_frame: u64,
     pub current_time: f64,
     pub sample_rate: f32,
+    pub(crate) reduced_quality: bool,
+    #[cfg(feature = "std")]
+    pub(crate) state: AudioContextState,
+}
+
+impl RenderScope {
+    /// Execution context of the render quantum starting at the given frame
+    ///
+    /// Only needed when driving a [`Graph`](super::Graph) manually, the audio contexts provide
+    /// the scope to their processors.
+    #[must_use]
+    pub fn new(current_frame: u64, sample_rate: f32) -> Self {
+        Self {
+            current_frame,
+            current_time: current_frame as f64 / f64::from(sample_rate),
+            sample_rate,
+            reduced_quality: false,
+            #[cfg(feature = "std")]
+            state: AudioContextState::Running,
+        }
+    }
+
+    /// Indicates the render thread is overloaded, processors should trade quality for speed
+    /// in this render quantum
+    ///
+    /// See [`OverloadPolicy`](super::OverloadPolicy). `AudioParam`s are computed at k-rate
+    /// then.
+    #[must_use]
+    pub fn reduced_quality(&self) -> bool {
+        self.reduced_quality
+    }
+
+    /// State of the audio context at the start of this render quantum
+    ///
+    /// The first quantum rendered after a real-time context is suspended or closed reports the
+    /// new state, so processors streaming from or to external sources can flush or pause their
+    /// buffers. No further quanta are rendered until the context is resumed. An offline
+    /// context is `Running` while rendering.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn state(&self) -> AudioContextState {
+        self.state
+    }
 }
 
 /// Interface for audio processing code that runs on the audio rendering thread.
@@ -24,6 +67,18 @@ pub struct RenderScope {
 /// [`AudioNode`](crate::node::AudioNode) (the user facing object that lives in the control
 /// thread). See [`BaseAudioContext::register`](crate::context::BaseAudioContext::register).
 ///
+/// Processors streaming from or to external sources can follow the state of the audio context
+/// with `RenderScope::state` (`std` feature), and release their resources in
+/// [`AudioProcessor::finalize`].
+///
+/// # Time
+///
+/// The [`RenderScope`] is the only time source of a processor. A processor must not read the
+/// wall clock (e.g. `Instant::now`) or wait for a timeout: an
+/// [`OfflineAudioContext`](crate::context::OfflineAudioContext) renders as fast as possible,
+/// while the real-time contexts render at the pace of the audio device. Processors fed by
+/// another thread report whether their data is available with [`AudioProcessor::poll_ready`].
+///
 /// Check the `examples/worklet.rs` file for example usage of this trait.
 pub trait AudioProcessor: Send {
     /// Audio processing function
@@ -52,27 +107,90 @@ pub trait AudioProcessor: Send {
         params: AudioParamValues,
         scope: &RenderScope,
     ) -> bool;
+
+    /// Whether the processor may go dormant once its tail has ended
+    ///
+    /// Return `true` when the output of the processor is silent as long as its inputs are
+    /// silent, after `process` returned `false` (e.g. a ConvolverNode whose impulse response
+    /// has been played out). The graph then skips the processor while its inputs are silent,
+    /// and resumes it as soon as one of its inputs is not silent anymore. Nodes whose control
+    /// handle is dropped are freed instead, once their inputs are disconnected.
+    ///
+    /// Defaults to `false`, processors with side effects (e.g. an AnalyserNode) must be
+    /// processed even when their inputs are silent.
+    fn can_go_dormant(&self) -> bool {
+        false
+    }
+
+    /// Whether the processor has the data it needs to render the next quantum
+    ///
+    /// Processors fed by another thread (e.g. a stream decoded in the background) return
+    /// `false` while their data is not available. The offline context waits until all the
+    /// processors are ready before rendering a quantum, so its output does not depend on the
+    /// speed of the other threads. The real-time contexts cannot wait and ignore the result:
+    /// the processor has to cope with the missing data in `process` (an underrun).
+    ///
+    /// Defaults to `true`.
+    fn poll_ready(&mut self) -> bool {
+        true
+    }
+
+    /// Called on the render thread right before the processor is freed
+    ///
+    /// This happens when the node is removed from the audio graph, when its processor is
+    /// replaced, and when the audio context is closed or dropped. Use it to release resources
+    /// deterministically, e.g. close a file or send a final packet. Defaults to a no-op.
+    fn finalize(&mut self) {}
+
+    /// The processor of an `AudioParam`, the render thread delivers the automation events of
+    /// the param to it. Defaults to `None`.
+    #[doc(hidden)]
+    fn as_audio_param(&mut self) -> Option<&mut AudioParamProcessor> {
+        None
+    }
+}
+
+impl core::fmt::Debug for dyn AudioProcessor {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // processors are opaque, only show they are present
+        f.debug_struct("AudioProcessor").finish_non_exhaustive()
+    }
 }
 
 /// Accessor for current [`crate::param::AudioParam`] values
 ///
-/// Provided to implementations of [`AudioProcessor`] in the render thread
+/// Provided to implementations of [`AudioProcessor`] in the render thread. The params are only
+/// available with the `std` feature.
 pub struct AudioParamValues<'a> {
-    nodes: &'a HashMap<NodeIndex, Node>,
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    nodes: &'a NodeMap<Node>,
 }
 
 impl<'a> AudioParamValues<'a> {
-    pub(crate) fn from(nodes: &'a HashMap<NodeIndex, Node>) -> Self {
+    pub(crate) fn from(nodes: &'a NodeMap<Node>) -> Self {
         Self { nodes }
     }
 
+    #[cfg(feature = "std")]
     pub(crate) fn get_raw(&self, index: &AudioParamId) -> &AudioRenderQuantum {
         self.nodes.get(&index.into()).unwrap().get_buffer()
     }
 
+    /// Get the computed values of all channels of the given param or control input
+    ///
+    /// The inputs of an [`AudioParam`](crate::AudioParam) are mixed down to mono as per spec,
+    /// its values are always in a single channel. A [`ControlInput`](crate::ControlInput)
+    /// keeps the channels of the signals connected to it, mixed to its channel count. Its
+    /// values are silent while nothing is connected.
+    #[cfg(feature = "std")]
+    pub fn get_multichannel(&self, index: &AudioParamId) -> &AudioRenderQuantum {
+        self.get_raw(index)
+    }
+
     /// Get the computed values for the given [`crate::param::AudioParam`]
     ///
     /// For both A & K-rate params, it will provide a slice of length [`crate::RENDER_QUANTUM_SIZE`]
+    #[cfg(feature = "std")]
     pub fn get(&self, index: &AudioParamId) -> &[f32] {
         &self.get_raw(index).channel_data(0)[..]
     }
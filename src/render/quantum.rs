@@ -1,9 +1,11 @@
 //! Optimized audio signal data structures, used in `AudioProcessors`
+use alloc::rc::Rc;
+use alloc::vec::Vec;
 use arrayvec::ArrayVec;
-use std::cell::RefCell;
-use std::rc::Rc;
+use core::cell::RefCell;
+use core::f32::consts::FRAC_1_SQRT_2;
 
-use crate::node::{ChannelConfig, ChannelCountMode, ChannelInterpretation};
+use crate::node::{ChannelConfigOptions, ChannelCountMode, ChannelInterpretation};
 
 use crate::assert_valid_number_of_channels;
 use crate::{MAX_CHANNELS, RENDER_QUANTUM_SIZE};
@@ -132,7 +134,7 @@ impl AudioRenderQuantumChannel {
     }
 }
 
-use std::ops::{Deref, DerefMut};
+use core::ops::{Deref, DerefMut};
 
 impl Deref for AudioRenderQuantumChannel {
     type Target = [f32; RENDER_QUANTUM_SIZE];
@@ -154,10 +156,10 @@ impl AsRef<[f32]> for AudioRenderQuantumChannel {
     }
 }
 
-impl std::ops::Drop for AudioRenderQuantumChannel {
+impl core::ops::Drop for AudioRenderQuantumChannel {
     fn drop(&mut self) {
         if Rc::strong_count(&self.data) == 1 {
-            let rc = std::mem::replace(&mut self.data, self.alloc.zeroes.clone());
+            let rc = core::mem::replace(&mut self.data, self.alloc.zeroes.clone());
             self.alloc.push(rc);
         }
     }
@@ -248,6 +250,20 @@ impl AudioRenderQuantum {
         &mut self.channels[..]
     }
 
+    /// Bit mask of the channels equal to the 'silence buffer', bit `i` being set when channel
+    /// `i` is silent
+    ///
+    /// This is an `O(1)` check per channel, see [`AudioRenderQuantumChannel::is_silent`].
+    /// Processors mapping silence onto silence can skip the work for these channels and leave
+    /// the silent channels in place.
+    pub fn silent_channel_mask(&self) -> u32 {
+        self.channels
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.is_silent())
+            .fold(0, |mask, (i, _)| mask | 1 << i)
+    }
+
     /// Up/Down-mix to the desired number of channels
     ///
     /// # Panics
@@ -315,7 +331,7 @@ impl AudioRenderQuantum {
                 //   output.SL = 0;
                 //   output.SR = 0;
                 (1, 6) => {
-                    let main = std::mem::replace(&mut self.channels[0], silence.clone());
+                    let main = core::mem::replace(&mut self.channels[0], silence.clone());
                     self.channels.push(silence.clone());
                     self.channels.push(main);
                     self.channels.push(silence.clone());
@@ -352,8 +368,8 @@ impl AudioRenderQuantum {
                 //   output.SL = input.SL;
                 //   output.SR = input.SR;
                 (4, 6) => {
-                    let sl = std::mem::replace(&mut self.channels[2], silence.clone());
-                    let sr = std::mem::replace(&mut self.channels[3], silence);
+                    let sl = core::mem::replace(&mut self.channels[2], silence.clone());
+                    let sr = core::mem::replace(&mut self.channels[3], silence);
                     self.channels.push(sl);
                     self.channels.push(sr);
                 }
@@ -364,49 +380,55 @@ impl AudioRenderQuantum {
                 // 2 -> 1 : stereo to mono
                 //   output = 0.5 * (input.L + input.R);
                 (2, 1) => {
-                    let right = self.channels[1].clone();
+                    if !self.all_silent(&[0, 1]) {
+                        let right = self.channels[1].clone();
 
-                    self.channels[0]
-                        .iter_mut()
-                        .zip(right.iter())
-                        .for_each(|(l, r)| *l = 0.5 * (*l + *r));
+                        self.channels[0]
+                            .iter_mut()
+                            .zip(right.iter())
+                            .for_each(|(l, r)| *l = 0.5 * (*l + *r));
+                    }
 
                     self.channels.truncate(1);
                 }
                 // 4 -> 1 : quad to mono
                 //   output = 0.25 * (input.L + input.R + input.SL + input.SR);
                 (4, 1) => {
-                    let right = self.channels[1].clone();
-                    let s_left = self.channels[2].clone();
-                    let s_right = self.channels[3].clone();
-
-                    self.channels[0]
-                        .iter_mut()
-                        .zip(right.iter())
-                        .zip(s_left.iter())
-                        .zip(s_right.iter())
-                        .for_each(|(((l, r), sl), sr)| *l = 0.25 * (*l + *r + *sl + *sr));
+                    if !self.all_silent(&[0, 1, 2, 3]) {
+                        let right = self.channels[1].clone();
+                        let s_left = self.channels[2].clone();
+                        let s_right = self.channels[3].clone();
+
+                        self.channels[0]
+                            .iter_mut()
+                            .zip(right.iter())
+                            .zip(s_left.iter())
+                            .zip(s_right.iter())
+                            .for_each(|(((l, r), sl), sr)| *l = 0.25 * (*l + *r + *sl + *sr));
+                    }
 
                     self.channels.truncate(1);
                 }
                 // 5.1 -> 1 : 5.1 to mono
                 //   output = sqrt(0.5) * (input.L + input.R) + input.C + 0.5 * (input.SL + input.SR)
                 (6, 1) => {
-                    let right = self.channels[1].clone();
-                    let center = self.channels[2].clone();
-                    let s_left = self.channels[4].clone();
-                    let s_right = self.channels[5].clone();
-                    let sqrt05 = (0.5_f32).sqrt();
-
-                    self.channels[0]
-                        .iter_mut()
-                        .zip(right.iter())
-                        .zip(center.iter())
-                        .zip(s_left.iter())
-                        .zip(s_right.iter())
-                        .for_each(|((((l, r), c), sl), sr)| {
-                            *l = sqrt05.mul_add(*l + *r, 0.5f32.mul_add(*sl + *sr, *c))
-                        });
+                    if !self.all_silent(&[0, 1, 2, 4, 5]) {
+                        let right = self.channels[1].clone();
+                        let center = self.channels[2].clone();
+                        let s_left = self.channels[4].clone();
+                        let s_right = self.channels[5].clone();
+                        let sqrt05 = FRAC_1_SQRT_2;
+
+                        self.channels[0]
+                            .iter_mut()
+                            .zip(right.iter())
+                            .zip(center.iter())
+                            .zip(s_left.iter())
+                            .zip(s_right.iter())
+                            .for_each(|((((l, r), c), sl), sr)| {
+                                *l = sqrt05 * (*l + *r) + 0.5 * (*sl + *sr) + *c
+                            });
+                    }
 
                     self.channels.truncate(1);
                 }
@@ -417,15 +439,19 @@ impl AudioRenderQuantum {
                     let s_left = self.channels[2].clone();
                     let s_right = self.channels[3].clone();
 
-                    self.channels[0]
-                        .iter_mut()
-                        .zip(s_left.iter())
-                        .for_each(|(l, sl)| *l = 0.5 * (*l + *sl));
+                    if !self.all_silent(&[0, 2]) {
+                        self.channels[0]
+                            .iter_mut()
+                            .zip(s_left.iter())
+                            .for_each(|(l, sl)| *l = 0.5 * (*l + *sl));
+                    }
 
-                    self.channels[1]
-                        .iter_mut()
-                        .zip(s_right.iter())
-                        .for_each(|(r, sr)| *r = 0.5 * (*r + *sr));
+                    if !self.all_silent(&[1, 3]) {
+                        self.channels[1]
+                            .iter_mut()
+                            .zip(s_right.iter())
+                            .for_each(|(r, sr)| *r = 0.5 * (*r + *sr));
+                    }
 
                     self.channels.truncate(2);
                 }
@@ -436,19 +462,24 @@ impl AudioRenderQuantum {
                     let center = self.channels[2].clone();
                     let s_left = self.channels[4].clone();
                     let s_right = self.channels[5].clone();
-                    let sqrt05 = (0.5_f32).sqrt();
-
-                    self.channels[0]
-                        .iter_mut()
-                        .zip(center.iter())
-                        .zip(s_left.iter())
-                        .for_each(|((l, c), sl)| *l += sqrt05 * (*c + *sl));
-
-                    self.channels[1]
-                        .iter_mut()
-                        .zip(center.iter())
-                        .zip(s_right.iter())
-                        .for_each(|((r, c), sr)| *r += sqrt05 * (*c + *sr));
+                    let sqrt05 = FRAC_1_SQRT_2;
+
+                    // the channel is left as is when nothing is added to it
+                    if !self.all_silent(&[2, 4]) {
+                        self.channels[0]
+                            .iter_mut()
+                            .zip(center.iter())
+                            .zip(s_left.iter())
+                            .for_each(|((l, c), sl)| *l += sqrt05 * (*c + *sl));
+                    }
+
+                    if !self.all_silent(&[2, 5]) {
+                        self.channels[1]
+                            .iter_mut()
+                            .zip(center.iter())
+                            .zip(s_right.iter())
+                            .for_each(|((r, c), sr)| *r += sqrt05 * (*c + *sr));
+                    }
 
                     self.channels.truncate(2)
                 }
@@ -460,17 +491,19 @@ impl AudioRenderQuantum {
                 (6, 4) => {
                     let _low_f = self.channels.swap_remove(3); // swap lr to index 3
                     let center = self.channels.swap_remove(2); // swap lf to index 2
-                    let sqrt05 = (0.5_f32).sqrt();
-
-                    self.channels[0]
-                        .iter_mut()
-                        .zip(center.iter())
-                        .for_each(|(l, c)| *l += sqrt05 * c);
-
-                    self.channels[1]
-                        .iter_mut()
-                        .zip(center.iter())
-                        .for_each(|(r, c)| *r += sqrt05 * c);
+                    let sqrt05 = FRAC_1_SQRT_2;
+
+                    if !center.is_silent() {
+                        self.channels[0]
+                            .iter_mut()
+                            .zip(center.iter())
+                            .for_each(|(l, c)| *l += sqrt05 * c);
+
+                        self.channels[1]
+                            .iter_mut()
+                            .zip(center.iter())
+                            .for_each(|(r, c)| *r += sqrt05 * c);
+                    }
                 }
 
                 _ => panic!(
@@ -487,6 +520,21 @@ impl AudioRenderQuantum {
         }
     }
 
+    /// Check if all the given channels are silent, so a mix of them is silent as well
+    fn all_silent(&self, channels: &[usize]) -> bool {
+        channels.iter().all(|&i| self.channels[i].is_silent())
+    }
+
+    /// Single channel buffer holding the given channel, silent if the channel does not exist
+    pub(crate) fn select_channel(&self, index: usize) -> Self {
+        let channel = self
+            .channels
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| self.channels[0].silence());
+        Self::from(channel)
+    }
+
     /// Convert this buffer to silence
     pub fn make_silent(&mut self) {
         let silence = self.channels[0].silence();
@@ -496,29 +544,46 @@ impl AudioRenderQuantum {
     }
 
     /// Convert to a single channel buffer, dropping excess channels
+    #[cfg(feature = "std")]
     pub(crate) fn force_mono(&mut self) {
         self.channels.truncate(1);
     }
 
     /// Modify every channel in the same way
+    #[cfg(feature = "std")]
     pub(crate) fn modify_channels<F: Fn(&mut AudioRenderQuantumChannel)>(&mut self, fun: F) {
         // todo, optimize for Rcs that are equal
         self.channels.iter_mut().for_each(fun)
     }
 
+    /// Modify every channel in the same way, leaving the silent channels untouched
+    ///
+    /// Only valid when `fun` maps silence onto silence, e.g. when applying a gain.
+    pub(crate) fn modify_non_silent_channels<F: Fn(&mut AudioRenderQuantumChannel)>(
+        &mut self,
+        fun: F,
+    ) {
+        let silent = self.silent_channel_mask();
+        self.channels
+            .iter_mut()
+            .enumerate()
+            .filter(|(i, _)| silent & 1 << i == 0)
+            .for_each(|(_, c)| fun(c))
+    }
+
     /// Sum two `AudioRenderQuantum`s
     ///
     /// Both buffers will be mixed up front according to the supplied `channel_config`
-    pub(crate) fn add(&mut self, other: &Self, channel_config: &ChannelConfig) {
+    pub(crate) fn add(&mut self, other: &Self, channel_config: &ChannelConfigOptions) {
         // gather initial channel counts
         let channels_self = self.number_of_channels();
         let channels_other = other.number_of_channels();
         let max_channels = channels_self.max(channels_other);
 
         // up/down-mix the to the desired channel count for the receiving node
-        let interpretation = channel_config.interpretation();
-        let mode = channel_config.count_mode();
-        let count = channel_config.count();
+        let interpretation = channel_config.interpretation;
+        let mode = channel_config.mode;
+        let count = channel_config.count;
 
         let new_channels = match mode {
             ChannelCountMode::Max => max_channels,
@@ -537,6 +602,7 @@ impl AudioRenderQuantum {
             .for_each(|(s, o)| s.add(o));
     }
 
+    #[cfg(feature = "std")]
     #[inline(always)]
     pub(crate) fn set_channels_values_at(&mut self, sample_index: usize, values: &[f32]) {
         for (channel_index, channel) in self.channels.iter_mut().enumerate() {
@@ -551,6 +617,22 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_set_zero_channels() {
+        let alloc = Alloc::with_capacity(1);
+        let mut quantum = AudioRenderQuantum::from(alloc.silence());
+        quantum.set_number_of_channels(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_mix_zero_channels() {
+        let alloc = Alloc::with_capacity(1);
+        let mut quantum = AudioRenderQuantum::from(alloc.silence());
+        quantum.mix(0, ChannelInterpretation::Speakers);
+    }
+
     #[test]
     fn test_pool() {
         // Create pool of size 2
@@ -1399,8 +1481,7 @@ mod tests {
             count: 2,
             mode: ChannelCountMode::Explicit,
             interpretation: ChannelInterpretation::Discrete,
-        }
-        .into();
+        };
 
         buffer.add(&buffer2, &channel_config);
 
@@ -1416,4 +1497,163 @@ mod tests {
             abs_all <= 0.
         );
     }
+
+    /// 6 channel quantum carrying a signal in channels 2 and 3 only
+    fn sparse_quantum(alloc: &Alloc) -> AudioRenderQuantum {
+        let mut quantum = AudioRenderQuantum::from(alloc.silence());
+        quantum.mix(6, ChannelInterpretation::Discrete);
+        quantum.channel_data_mut(2).iter_mut().for_each(|v| *v = 1.);
+        quantum.channel_data_mut(3).iter_mut().for_each(|v| *v = 2.);
+        quantum
+    }
+
+    #[test]
+    fn test_silent_channel_mask() {
+        let alloc = Alloc::with_capacity(2);
+        let quantum = AudioRenderQuantum::from(alloc.silence());
+        assert_eq!(quantum.silent_channel_mask(), 0b1);
+
+        let quantum = sparse_quantum(&alloc);
+        assert_eq!(quantum.silent_channel_mask(), 0b110011);
+
+        // zero samples that are not the silence buffer are not detected
+        let mut quantum = quantum;
+        quantum.channel_data_mut(3).iter_mut().for_each(|v| *v = 0.);
+        assert_eq!(quantum.silent_channel_mask(), 0b110011);
+    }
+
+    #[test]
+    fn test_modify_non_silent_channels() {
+        let alloc = Alloc::with_capacity(2);
+        let mut quantum = sparse_quantum(&alloc);
+        assert_eq!(alloc.pool_size(), 0);
+
+        alloc_counter::deny_alloc(|| {
+            quantum.modify_non_silent_channels(|c| c.iter_mut().for_each(|v| *v *= 0.5));
+        });
+
+        // the silent channels are not materialized
+        assert_eq!(alloc.pool_size(), 0);
+        assert_eq!(quantum.silent_channel_mask(), 0b110011);
+        assert_float_eq!(
+            &quantum.channel_data(2)[..],
+            &[0.5; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            &quantum.channel_data(3)[..],
+            &[1.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_mix_keeps_silence() {
+        let alloc = Alloc::with_capacity(8);
+
+        // 5.1 to stereo: L, C and SL are silent, so is the left output
+        let mut quantum = AudioRenderQuantum::from(alloc.silence());
+        quantum.mix(6, ChannelInterpretation::Discrete);
+        quantum.channel_data_mut(1).iter_mut().for_each(|v| *v = 1.);
+        quantum.channel_data_mut(5).iter_mut().for_each(|v| *v = 1.);
+        let pool_size = alloc.pool_size();
+        alloc_counter::deny_alloc(|| {
+            quantum.mix(2, ChannelInterpretation::Speakers);
+        });
+        assert_eq!(quantum.silent_channel_mask(), 0b01);
+        // no buffer is taken from the pool, the one of SR is returned
+        assert_eq!(alloc.pool_size(), pool_size + 1);
+
+        // down-mixing silent channels only
+        for (from, to) in [(4, 2), (4, 1), (2, 1), (6, 1), (6, 2), (6, 4)] {
+            let mut quantum = AudioRenderQuantum::from(alloc.silence());
+            quantum.mix(from, ChannelInterpretation::Discrete);
+            let pool_size = alloc.pool_size();
+            alloc_counter::deny_alloc(|| {
+                quantum.mix(to, ChannelInterpretation::Speakers);
+            });
+            assert_eq!(quantum.silent_channel_mask(), (1 << to) - 1);
+            assert_eq!(alloc.pool_size(), pool_size);
+        }
+    }
+
+    #[test]
+    fn test_add_mixed_silence() {
+        let alloc = Alloc::with_capacity(4);
+        let config = ChannelConfigOptions {
+            count: 2,
+            mode: ChannelCountMode::Explicit,
+            interpretation: ChannelInterpretation::Speakers,
+        };
+
+        // stereo with a silent right channel, plus stereo with a silent left channel
+        let mut left = AudioRenderQuantum::from(alloc.silence());
+        left.mix(2, ChannelInterpretation::Discrete);
+        left.channel_data_mut(0).iter_mut().for_each(|v| *v = 1.);
+        let mut right = AudioRenderQuantum::from(alloc.silence());
+        right.mix(2, ChannelInterpretation::Discrete);
+        right.channel_data_mut(1).iter_mut().for_each(|v| *v = 2.);
+
+        left.add(&right, &config);
+        assert_eq!(left.silent_channel_mask(), 0);
+        assert_float_eq!(
+            &left.channel_data(0)[..],
+            &[1.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            &left.channel_data(1)[..],
+            &[2.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
+
+    /// Buffer of `n` channels, each holding its own buffer from the pool
+    fn wide_quantum(alloc: &Alloc, n: usize) -> AudioRenderQuantum {
+        let mut quantum = AudioRenderQuantum::from(alloc.allocate());
+        quantum.set_number_of_channels(n);
+        // the clones share the first buffer, writing to them takes a buffer from the pool
+        quantum.channels_mut()[1..]
+            .iter_mut()
+            .for_each(|c| c.fill(1.));
+        quantum
+    }
+
+    #[test]
+    fn test_reduced_channels_release_buffers() {
+        let alloc = Alloc::with_capacity(16);
+
+        alloc_counter::deny_alloc(|| {
+            let mut quantum = wide_quantum(&alloc, 16);
+            assert_eq!(alloc.pool_size(), 0);
+            quantum.force_mono();
+            assert_eq!(alloc.pool_size(), 15);
+            drop(quantum);
+            assert_eq!(alloc.pool_size(), 16);
+
+            // the first channel is released as well
+            let mut quantum = wide_quantum(&alloc, 16);
+            quantum.make_silent();
+            assert_eq!(alloc.pool_size(), 16);
+            drop(quantum);
+
+            let mut quantum = wide_quantum(&alloc, 16);
+            quantum.mix(2, ChannelInterpretation::Discrete);
+            assert_eq!(alloc.pool_size(), 14);
+            drop(quantum);
+
+            let mut quantum = wide_quantum(&alloc, 6);
+            quantum.mix(2, ChannelInterpretation::Speakers);
+            assert_eq!(alloc.pool_size(), 14);
+            drop(quantum);
+
+            let mut quantum = wide_quantum(&alloc, 16);
+            quantum.set_number_of_channels(4);
+            assert_eq!(alloc.pool_size(), 12);
+            drop(quantum);
+
+            // the pool never grew
+            assert_eq!(alloc.pool_size(), 16);
+        });
+    }
 }
@@ -0,0 +1,289 @@
+//! Read access to the last rendered quantum of the destination, for visualization
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::render::AudioRenderQuantum;
+use crate::{MAX_CHANNELS, RENDER_QUANTUM_SIZE};
+
+/// Flag set on the index of the back slot when it holds a quantum the reader has not seen yet
+const FRESH: usize = 0b100;
+/// Mask extracting the slot index from the back index
+const INDEX_MASK: usize = 0b011;
+
+/// Copy of a render quantum of the `AudioDestinationNode`
+///
+/// See [`BaseAudioContext::last_output_frame`](crate::context::BaseAudioContext::last_output_frame)
+#[derive(Clone, Debug)]
+pub struct AudioBufferSnapshot {
+    frame: u64,
+    sample_rate: f32,
+    channels: Vec<Vec<f32>>,
+}
+
+impl AudioBufferSnapshot {
+    /// Position of the first frame of the render quantum, in sample frames since the start of
+    /// the rendering
+    ///
+    /// Successive snapshots are `RENDER_QUANTUM_SIZE` frames apart, unless render quanta were
+    /// missed. A snapshot with the same frame as the previous one is a duplicate.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Context time of the first frame of the render quantum, in seconds
+    #[allow(clippy::cast_precision_loss)]
+    pub fn time(&self) -> f64 {
+        self.frame as f64 / self.sample_rate as f64
+    }
+
+    /// Sample rate of the snapshot, in Hertz
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Number of channels of the snapshot
+    pub fn number_of_channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Number of samples per channel, i.e. the render quantum size
+    pub fn length(&self) -> usize {
+        RENDER_QUANTUM_SIZE
+    }
+
+    /// Samples of the given channel
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the given channel does not exist
+    pub fn get_channel_data(&self, channel_number: usize) -> &[f32] {
+        &self.channels[channel_number]
+    }
+}
+
+/// Slot of the triple buffer
+struct Slot {
+    /// position of the quantum, `None` until a quantum has been written in the slot
+    frame: Option<u64>,
+    number_of_channels: usize,
+    /// planar samples, allocated for the max number of channels up front
+    data: Vec<f32>,
+}
+
+impl Slot {
+    fn new() -> Self {
+        Self {
+            frame: None,
+            number_of_channels: 0,
+            data: vec![0.; MAX_CHANNELS * RENDER_QUANTUM_SIZE],
+        }
+    }
+}
+
+/// Triple buffer holding the last rendered quantum of the destination
+///
+/// The render thread writes in its own slot, then swaps it atomically with the back slot. The
+/// control thread swaps its own slot with the back slot when the latter holds a fresh quantum.
+/// Both sides never touch the same slot, so the render side is wait-free.
+pub(crate) struct SnapshotBuffer {
+    slots: [UnsafeCell<Slot>; 3],
+    /// index of the back slot, with the `FRESH` flag
+    back: AtomicUsize,
+    /// index of the slot owned by the reader
+    reader: Mutex<usize>,
+    /// number of subscriptions, the quanta are not copied when zero
+    subscriptions: AtomicUsize,
+    sample_rate: f32,
+}
+
+// SAFETY:
+// The slots are only accessed by the single `SnapshotWriter` and by the reader holding the
+// `reader` lock, which own distinct slots at all times.
+unsafe impl Sync for SnapshotBuffer {}
+
+impl SnapshotBuffer {
+    pub fn subscribe(&self) {
+        self.subscriptions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn unsubscribe(&self) {
+        let _ = self
+            .subscriptions
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |s| s.checked_sub(1));
+    }
+
+    fn is_subscribed(&self) -> bool {
+        self.subscriptions.load(Ordering::Relaxed) > 0
+    }
+
+    /// Copy out the most recent quantum, `None` when nobody subscribed or when no quantum has
+    /// been written yet
+    pub fn read(&self) -> Option<AudioBufferSnapshot> {
+        if !self.is_subscribed() {
+            return None;
+        }
+
+        let mut reader = self.reader.lock().unwrap();
+        if self.back.load(Ordering::Acquire) & FRESH != 0 {
+            *reader = self.back.swap(*reader, Ordering::AcqRel) & INDEX_MASK;
+        }
+
+        // SAFETY: the writer never accesses the slot owned by the reader
+        let slot = unsafe { &*self.slots[*reader].get() };
+        let channels = slot
+            .data
+            .chunks_exact(RENDER_QUANTUM_SIZE)
+            .take(slot.number_of_channels)
+            .map(<[f32]>::to_vec)
+            .collect();
+
+        slot.frame.map(|frame| AudioBufferSnapshot {
+            frame,
+            sample_rate: self.sample_rate,
+            channels,
+        })
+    }
+}
+
+/// Render side of the [`SnapshotBuffer`]
+pub(crate) struct SnapshotWriter {
+    buffer: Arc<SnapshotBuffer>,
+    /// index of the slot owned by the writer
+    index: usize,
+}
+
+impl SnapshotWriter {
+    /// Publish the quantum, a no-op when nobody subscribed
+    pub fn write(&mut self, quantum: &AudioRenderQuantum, frame: u64) {
+        if !self.buffer.is_subscribed() {
+            return;
+        }
+
+        // SAFETY: the reader never accesses the slot owned by the writer
+        let slot = unsafe { &mut *self.buffer.slots[self.index].get() };
+        let channels = quantum.channels();
+        slot.frame = Some(frame);
+        slot.number_of_channels = channels.len();
+        slot.data
+            .chunks_exact_mut(RENDER_QUANTUM_SIZE)
+            .zip(channels)
+            .for_each(|(dest, channel)| dest.copy_from_slice(&channel[..]));
+
+        self.index = self.buffer.back.swap(self.index | FRESH, Ordering::AcqRel) & INDEX_MASK;
+    }
+}
+
+/// Create the shared [`SnapshotBuffer`] along with its writer
+pub(crate) fn snapshot_buffer(sample_rate: f32) -> (Arc<SnapshotBuffer>, SnapshotWriter) {
+    let buffer = Arc::new(SnapshotBuffer {
+        slots: [
+            UnsafeCell::new(Slot::new()),
+            UnsafeCell::new(Slot::new()),
+            UnsafeCell::new(Slot::new()),
+        ],
+        back: AtomicUsize::new(1),
+        reader: Mutex::new(2),
+        subscriptions: AtomicUsize::new(0),
+        sample_rate,
+    });
+    let writer = SnapshotWriter {
+        buffer: buffer.clone(),
+        index: 0,
+    };
+
+    (buffer, writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Alloc;
+
+    fn quantum(values: &[f32]) -> AudioRenderQuantum {
+        let alloc = Alloc::with_capacity(values.len());
+        let mut quantum = AudioRenderQuantum::from(alloc.silence());
+        quantum.set_number_of_channels(values.len());
+        quantum
+            .channels_mut()
+            .iter_mut()
+            .zip(values)
+            .for_each(|(channel, v)| channel.iter_mut().for_each(|s| *s = *v));
+        quantum
+    }
+
+    #[test]
+    fn test_subscription() {
+        let (buffer, mut writer) = snapshot_buffer(48_000.);
+
+        // nothing is copied without subscription
+        writer.write(&quantum(&[1.]), 0);
+        buffer.subscribe();
+        assert!(buffer.read().is_none());
+
+        writer.write(&quantum(&[1.]), 128);
+        assert_eq!(buffer.read().unwrap().frame(), 128);
+
+        buffer.unsubscribe();
+        assert!(buffer.read().is_none());
+        // unbalanced calls are ignored
+        buffer.unsubscribe();
+        buffer.subscribe();
+        assert_eq!(buffer.read().unwrap().frame(), 128);
+    }
+
+    #[test]
+    fn test_latest_quantum() {
+        let (buffer, mut writer) = snapshot_buffer(48_000.);
+        buffer.subscribe();
+
+        writer.write(&quantum(&[1., 2.]), 0);
+        writer.write(&quantum(&[3.]), 128);
+        writer.write(&quantum(&[4., 5.]), 256);
+
+        // missed quanta are detected with the frame counter
+        let snapshot = buffer.read().unwrap();
+        assert_eq!(snapshot.frame(), 256);
+        assert_eq!(snapshot.number_of_channels(), 2);
+        assert_eq!(snapshot.get_channel_data(0), &[4.; RENDER_QUANTUM_SIZE][..]);
+        assert_eq!(snapshot.get_channel_data(1), &[5.; RENDER_QUANTUM_SIZE][..]);
+
+        // duplicate reads as well
+        assert_eq!(buffer.read().unwrap().frame(), 256);
+
+        writer.write(&quantum(&[6.]), 384);
+        let snapshot = buffer.read().unwrap();
+        assert_eq!(snapshot.frame(), 384);
+        assert_eq!(snapshot.number_of_channels(), 1);
+        assert_eq!(snapshot.get_channel_data(0), &[6.; RENDER_QUANTUM_SIZE][..]);
+    }
+
+    #[test]
+    fn test_concurrent_access() {
+        let (buffer, mut writer) = snapshot_buffer(48_000.);
+        buffer.subscribe();
+
+        let reader = {
+            let buffer = buffer.clone();
+            std::thread::spawn(move || {
+                let mut last = None;
+                for _ in 0..1000 {
+                    if let Some(snapshot) = buffer.read() {
+                        // snapshots are never torn, nor going back in time
+                        let value = snapshot.frame() as f32;
+                        assert!(snapshot.get_channel_data(0).iter().all(|v| *v == value));
+                        assert!(last <= Some(snapshot.frame()));
+                        last = Some(snapshot.frame());
+                    }
+                }
+            })
+        };
+
+        for i in 0..1000 {
+            let frame = i * RENDER_QUANTUM_SIZE as u64;
+            writer.write(&quantum(&[frame as f32]), frame);
+        }
+
+        reader.join().unwrap();
+    }
+}
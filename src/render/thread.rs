@@ -1,19 +1,45 @@
 //! Communicates with the control thread and ships audio samples to the hardware
 
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
 
-use cpal::{OutputCallbackInfo, Sample};
+#[cfg(feature = "cpal")]
+use cpal::OutputCallbackInfo;
 use crossbeam_channel::Receiver;
+#[cfg(feature = "cpal")]
+use crossbeam_channel::Sender;
 
-use super::{AudioRenderQuantum, NodeIndex};
+use super::{
+    frame_queue, AudioRenderQuantum, Dither, FrameConsumer, FrameProducer, NodeIndex, OutputSample,
+};
 use crate::buffer::{AudioBuffer, AudioBufferOptions};
+use crate::context::AudioContextState;
+#[cfg(feature = "wasm")]
+use crate::control::EndedEvents;
 use crate::message::ControlMessage;
 use crate::node::ChannelInterpretation;
 use crate::render::RenderScope;
-use crate::{AtomicF64, RENDER_QUANTUM_SIZE};
+#[cfg(feature = "cpal")]
+use crate::AtomicF64;
+use crate::RENDER_QUANTUM_SIZE;
 
 use super::graph::Graph;
+#[cfg(feature = "cpal")]
+use super::{CallbackClock, XrunCounters, XrunDetector};
+
+/// Interval at which the offline rendering polls the processors that are not ready
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Smallest room of the render-ahead queue for the frames read by an output callback, a larger
+/// callback than the room always runs the queue dry
+const MAX_OUTPUT_FRAMES: usize = 8192;
+
+/// Number of output callbacks the render loop can lag behind for the xrun detection
+#[cfg(feature = "cpal")]
+const OUTPUT_CALLBACKS_CAPACITY: usize = 64;
 
 /// Operations running off the system-level audio callback
 pub(crate) struct RenderThread {
@@ -21,9 +47,37 @@ pub(crate) struct RenderThread {
     sample_rate: f32,
     number_of_channels: usize,
     frames_played: Arc<AtomicU64>,
-    output_latency: Arc<AtomicF64>,
     receiver: Receiver<ControlMessage>,
     buffer_offset: Option<(usize, AudioRenderQuantum)>,
+    /// callback receiving the interleaved output
+    output_tap: Option<OutputTap>,
+    /// state of the context, shared with the control thread
+    state: Arc<AtomicU8>,
+    /// state of the context in the previous render quantum
+    last_state: AudioContextState,
+    /// set once the processors of a closed context are released
+    released: bool,
+    /// dither applied when converting to an integer output format
+    dither: Dither,
+    /// correlation between the rendered frames and the output callbacks
+    #[cfg(feature = "cpal")]
+    callback_clock: Option<Arc<CallbackClock>>,
+    /// time and number of frames of the output callbacks, when rendering ahead
+    #[cfg(feature = "cpal")]
+    output_callbacks: Option<Receiver<(f64, usize)>>,
+    /// latency of the output device, reported by the output callbacks
+    #[cfg(feature = "cpal")]
+    output_latency: Arc<AtomicF64>,
+    #[cfg(feature = "cpal")]
+    xruns: Arc<XrunCounters>,
+    #[cfg(feature = "cpal")]
+    xrun_detector: XrunDetector,
+    #[cfg(feature = "cpal")]
+    xrun_sender: Option<Sender<f64>>,
+    /// `ended` events dispatched after each render quantum, there is no dispatcher thread on
+    /// wasm
+    #[cfg(feature = "wasm")]
+    ended_events: Option<EndedEvents>,
 }
 
 // SAFETY:
@@ -40,61 +94,239 @@ impl RenderThread {
         number_of_channels: usize,
         receiver: Receiver<ControlMessage>,
         frames_played: Arc<AtomicU64>,
-        output_latency: Arc<AtomicF64>,
     ) -> Self {
         Self {
             graph: Graph::new(),
             sample_rate,
             number_of_channels,
             frames_played,
-            output_latency,
             receiver,
             buffer_offset: None,
+            output_tap: None,
+            state: Arc::new(AtomicU8::new(AudioContextState::Running as u8)),
+            last_state: AudioContextState::Running,
+            released: false,
+            dither: Dither::new(true),
+            #[cfg(feature = "cpal")]
+            callback_clock: None,
+            #[cfg(feature = "cpal")]
+            output_callbacks: None,
+            #[cfg(feature = "cpal")]
+            output_latency: Arc::new(AtomicF64::new(0.)),
+            #[cfg(feature = "cpal")]
+            xruns: Arc::default(),
+            #[cfg(feature = "cpal")]
+            xrun_detector: XrunDetector::default(),
+            #[cfg(feature = "cpal")]
+            xrun_sender: None,
+            #[cfg(feature = "wasm")]
+            ended_events: None,
         }
     }
 
+    /// Run the callbacks of the `ended` events of the context after each render quantum, on
+    /// the rendering thread
+    #[cfg(feature = "wasm")]
+    pub fn with_ended_events(mut self, events: EndedEvents) -> Self {
+        self.ended_events = Some(events);
+        self
+    }
+
+    /// Record the frames rendered by each output callback in the given clock
+    // only used by the cpal backend, which is not set up in tests
+    #[allow(dead_code)]
+    #[cfg(feature = "cpal")]
+    pub fn set_callback_clock(&mut self, clock: Arc<CallbackClock>) {
+        self.callback_clock = Some(clock);
+    }
+
+    /// Enable or disable the dither applied when converting to an integer output format
+    pub fn set_dither(&mut self, enabled: bool) {
+        self.dither.set_enabled(enabled);
+    }
+
+    /// Report the latency of the output device, measured by the output callbacks, in the given
+    /// value
+    // only used by the cpal backend, which is not set up in tests
+    #[allow(dead_code)]
+    #[cfg(feature = "cpal")]
+    pub fn set_output_latency(&mut self, output_latency: Arc<AtomicF64>) {
+        self.output_latency = output_latency;
+    }
+
+    /// Record the xruns of the output callbacks in the given counters
+    // only used by the cpal backend, which is not set up in tests
+    #[allow(dead_code)]
+    #[cfg(feature = "cpal")]
+    pub fn set_xrun_counters(&mut self, xruns: Arc<XrunCounters>) {
+        self.xruns = xruns;
+    }
+
+    /// Xrun statistics, updated by the output callbacks
+    // only used by the cpal backend, which is not set up in tests
+    #[allow(dead_code)]
+    #[cfg(feature = "cpal")]
+    pub fn xrun_counters(&self) -> Arc<XrunCounters> {
+        self.xruns.clone()
+    }
+
     fn handle_control_messages(&mut self) {
-        for msg in self.receiver.try_iter() {
-            use ControlMessage::*;
-
-            match msg {
-                RegisterNode {
-                    id,
-                    node,
-                    inputs,
-                    outputs,
-                    channel_config,
-                } => {
-                    self.graph
-                        .add_node(NodeIndex(id), node, inputs, outputs, channel_config);
-                }
-                ConnectNode {
-                    from,
-                    to,
-                    output,
-                    input,
-                } => {
-                    self.graph
-                        .add_edge((NodeIndex(from), output), (NodeIndex(to), input));
-                }
-                DisconnectNode { from, to } => {
-                    self.graph.remove_edge(NodeIndex(from), NodeIndex(to));
+        // the graph of a closed context is gone, the late messages are dropped
+        if self.released {
+            while self.receiver.try_recv().is_ok() {}
+            return;
+        }
+
+        while let Ok(msg) = self.receiver.try_recv() {
+            self.handle_control_message(msg);
+        }
+    }
+
+    fn handle_control_message(&mut self, msg: ControlMessage) {
+        use ControlMessage::*;
+
+        match msg {
+            RegisterNode {
+                id,
+                node,
+                inputs,
+                outputs,
+                channel_config,
+            } => {
+                self.graph
+                    .add_node(NodeIndex(id), node, inputs, outputs, channel_config);
+            }
+            ConnectNode {
+                from,
+                to,
+                output,
+                kind,
+            } => {
+                self.graph
+                    .add_edge_of_kind((NodeIndex(from), output), NodeIndex(to), kind);
+            }
+            DisconnectNode { from, to } => {
+                self.graph.remove_edge(NodeIndex(from), NodeIndex(to));
+            }
+            DisconnectAll { from } => {
+                self.graph.remove_edges_from(NodeIndex(from));
+            }
+            SetChannelConfig { configs } => {
+                for (id, options) in configs {
+                    self.graph.set_channel_config(NodeIndex(id), &options);
                 }
-                DisconnectAll { from } => {
-                    self.graph.remove_edges_from(NodeIndex(from));
+            }
+            FreeWhenFinished { id } => {
+                self.graph.mark_free_when_finished(NodeIndex(id));
+            }
+            SwapProcessor { id, processor } => {
+                self.graph.swap_processor(NodeIndex(id), processor);
+            }
+            RegisterAudioParam { id } => {
+                self.graph.register_audio_param(NodeIndex(id));
+            }
+            AudioParamEvent { to, event } => {
+                self.graph.pass_audio_param_event(NodeIndex(to), event);
+            }
+            AudioParamEvents { events } => {
+                for (to, event) in events {
+                    self.graph.pass_audio_param_event(NodeIndex(to), event);
                 }
-                FreeWhenFinished { id } => {
-                    self.graph.mark_free_when_finished(NodeIndex(id));
+            }
+            SmoothSetValue {
+                to,
+                target,
+                smoothing_samples,
+            } => {
+                let duration = smoothing_samples as f64 / self.sample_rate as f64;
+                let event = crate::param::AudioParamEvent::smooth_set_value(target, duration);
+                self.graph.pass_audio_param_event(NodeIndex(to), event);
+            }
+            SetRenderPriority { id, priority } => {
+                self.graph.set_render_priority(NodeIndex(id), priority);
+            }
+            SetMuted {
+                id,
+                muted,
+                ramp_frames,
+            } => {
+                self.graph.set_muted(NodeIndex(id), muted, ramp_frames);
+            }
+            FreezeChain { ids } => {
+                let chain: Vec<_> = ids.into_iter().map(NodeIndex).collect();
+                if !self.graph.freeze_chain(&chain) {
+                    log::warn!(
+                        "Cannot freeze the nodes {:?}, they do not form a chain",
+                        chain
+                    );
                 }
-                AudioParamEvent { to, event } => {
-                    to.send(event).expect("Audioparam disappeared unexpectedly")
+            }
+            SetOverloadPolicy { policy } => {
+                self.graph.set_overload_policy(policy);
+            }
+            SetContextState { state } => {
+                self.state = state;
+            }
+            #[cfg(feature = "cpal")]
+            SetXrunSender { sender } => {
+                self.xrun_sender = sender;
+            }
+            SetOutputTap { tap } => {
+                self.output_tap = tap;
+            }
+            StartSource { scheduler, frame } => {
+                scheduler.start_at_frame(frame);
+            }
+            StopSource { scheduler, frame } => {
+                scheduler.stop_at_frame(frame);
+            }
+            CancelStart { scheduler } => {
+                scheduler.cancel_start(self.frames_played.load(Ordering::Relaxed));
+            }
+            Batch { messages } => {
+                for message in messages {
+                    self.handle_control_message(message);
                 }
             }
         }
     }
 
-    // render method of the OfflineAudioContext
+    #[cfg(test)]
+    pub(crate) fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    /// Detect a late output callback, by comparing the gap since the previous callback to the
+    /// duration of the audio it delivered
+    #[cfg(feature = "cpal")]
+    fn detect_xrun(&mut self, now: f64, number_of_frames: usize) {
+        let duration = number_of_frames as f64 / self.sample_rate as f64;
+
+        if let Some(gap) = self.xrun_detector.callback(now, duration) {
+            let time = self.frames_played.load(Ordering::Relaxed) as f64 / self.sample_rate as f64;
+            self.xruns.record(gap, time);
+
+            if let Some(sender) = &self.xrun_sender {
+                // the notification is dropped if the dispatcher does not keep up
+                let _ = sender.try_send(gap);
+            }
+        }
+    }
+
+    #[cfg(test)]
     pub fn render_audiobuffer(&mut self, length: usize) -> AudioBuffer {
+        self.render_audiobuffer_cancellable(length, &AtomicBool::new(false))
+    }
+
+    /// Render method of the OfflineAudioContext: render up to `length` frames, stopping at the
+    /// first render quantum boundary after the `cancelled` flag is set
+    ///
+    /// The returned buffer only holds fully rendered quanta.
+    pub fn render_audiobuffer_cancellable(
+        &mut self,
+        length: usize,
+        cancelled: &AtomicBool,
+    ) -> AudioBuffer {
         // assert input was properly sized
         debug_assert_eq!(length % RENDER_QUANTUM_SIZE, 0);
 
@@ -106,43 +338,108 @@ impl RenderThread {
 
         let mut buf = AudioBuffer::new(options);
 
-        for _ in 0..length / RENDER_QUANTUM_SIZE {
+        'render: for _ in 0..length / RENDER_QUANTUM_SIZE {
+            if cancelled.load(Ordering::Relaxed) {
+                break;
+            }
+
             // handle addition/removal of nodes/edges
             self.handle_control_messages();
 
+            // offline rendering is not bound to real time: wait for the processors fed by other
+            // threads, instead of rendering an underrun
+            while !self.graph.poll_ready() {
+                if cancelled.load(Ordering::Relaxed) {
+                    break 'render;
+                }
+                std::thread::sleep(READY_POLL_INTERVAL);
+            }
+
             // update time
             let current_frame = self
                 .frames_played
-                .fetch_add(RENDER_QUANTUM_SIZE as u64, Ordering::SeqCst);
-            let current_time = current_frame as f64 / self.sample_rate as f64;
+                .fetch_add(RENDER_QUANTUM_SIZE as u64, Ordering::Release);
 
-            let scope = RenderScope {
-                current_frame,
-                current_time,
-                sample_rate: self.sample_rate,
-            };
+            let mut scope = RenderScope::new(current_frame, self.sample_rate);
+            scope.state = self.state.load(Ordering::Acquire).into();
 
             // render audio graph
             let rendered = self.graph.render(&scope);
 
             buf.extend_alloc(rendered);
+
+            #[cfg(feature = "wasm")]
+            self.dispatch_ended_events();
         }
 
         buf
     }
 
+    /// Split the render thread in a render loop, rendering `quanta` render quanta ahead, and the
+    /// output callback of the cpal backend, reading the rendered frames
+    ///
+    /// `frames_per_callback` is the buffer size of the output stream, zero if unknown.
     // This code is not dead: false positive from clippy
     // due to the use of #[cfg(not(test))]
     #[allow(dead_code)]
-    pub fn render<S: Sample>(&mut self, mut buffer: &mut [S], infos: &OutputCallbackInfo) {
-        // update output latency, this value might change while running (e.g. sound card heat)
-        let timestamp = infos.timestamp();
-        let delta = timestamp
-            .playback
-            .duration_since(&timestamp.callback)
-            .unwrap();
-        let output_latency = delta.as_secs() as f64 + delta.subsec_nanos() as f64 * 1e-9;
-        self.output_latency.store(output_latency);
+    #[cfg(feature = "cpal")]
+    pub fn into_render_ahead(
+        mut self,
+        quanta: usize,
+        frames_per_callback: usize,
+    ) -> (RenderAhead, RenderAheadOutput) {
+        let (sender, receiver) = crossbeam_channel::bounded(OUTPUT_CALLBACKS_CAPACITY);
+        self.output_callbacks = Some(receiver);
+
+        let dither = self.take_dither();
+        let callback_clock = self.callback_clock.take();
+        let sample_rate = self.sample_rate;
+        let number_of_channels = self.number_of_channels;
+        let frames_played = self.frames_played.clone();
+        let output_latency = self.output_latency.clone();
+        let xruns = self.xruns.clone();
+
+        let (render, queue) = RenderAhead::new(self, quanta, frames_per_callback);
+        let output = RenderAheadOutput {
+            queue,
+            sample_rate,
+            number_of_channels,
+            frames_played,
+            output_latency,
+            xruns,
+            dither,
+            created_at: Instant::now(),
+            callback_clock,
+            callbacks: sender,
+        };
+
+        (render, output)
+    }
+
+    /// Run the xrun detection on the output callbacks reported by the [`RenderAheadOutput`]
+    #[cfg(feature = "cpal")]
+    fn handle_output_callbacks(&mut self) {
+        if let Some(receiver) = self.output_callbacks.take() {
+            while let Ok((now, number_of_frames)) = receiver.try_recv() {
+                self.detect_xrun(now, number_of_frames);
+            }
+            self.output_callbacks = Some(receiver);
+        }
+    }
+
+    /// Take the dither applied by this render thread, for an output reading the frames rendered
+    /// ahead
+    pub fn take_dither(&mut self) -> Dither {
+        std::mem::replace(&mut self.dither, Dither::new(false))
+    }
+
+    /// Render the next frames into the interleaved `buffer`, converting the samples to its format
+    ///
+    /// The buffer does not need to be a multiple of the render quantum size, the frames left over
+    /// are kept for the next call. The conversion does not allocate.
+    pub fn render_interleaved<S: OutputSample>(&mut self, mut buffer: &mut [S]) {
+        // silence is not dithered, so a suspended context outputs digital silence
+        let silence = S::from_f32(0., 0.);
 
         // There may be audio frames left over from the previous render call,
         // if the cpal buffer size did not align with our internal RENDER_QUANTUM_SIZE
@@ -156,8 +453,7 @@ impl RenderThread {
                 let output = first.iter_mut().skip(i).step_by(self.number_of_channels);
                 let channel = prev_rendered.channel_data(i)[offset..].iter();
                 for (sample, input) in output.zip(channel) {
-                    let value = Sample::from::<f32>(input);
-                    *sample = value;
+                    *sample = self.dither.convert(*input);
                 }
             }
 
@@ -179,36 +475,23 @@ impl RenderThread {
         let chunk_size = RENDER_QUANTUM_SIZE * self.number_of_channels;
 
         for data in buffer.chunks_mut(chunk_size) {
-            // handle addition/removal of nodes/edges
-            self.handle_control_messages();
-
-            // update time
-            let current_frame = self
-                .frames_played
-                .fetch_add(RENDER_QUANTUM_SIZE as u64, Ordering::SeqCst);
-            let current_time = current_frame as f64 / self.sample_rate as f64;
-
-            let scope = RenderScope {
-                current_frame,
-                current_time,
-                sample_rate: self.sample_rate,
+            let rendered = match self.render_quantum() {
+                Some(rendered) => rendered,
+                None => {
+                    data.fill(silence);
+                    if let Some(tap) = &mut self.output_tap {
+                        tap.push_silence(data.len());
+                    }
+                    continue;
+                }
             };
 
-            // render audio graph
-            let mut rendered = self.graph.render(&scope).clone();
-
-            // online AudioContext allows channel count to be less than no of hardware channels
-            if rendered.number_of_channels() != self.number_of_channels {
-                rendered.mix(self.number_of_channels, ChannelInterpretation::Discrete);
-            }
-
             // copy rendered audio into output slice
             for i in 0..self.number_of_channels {
                 let output = data.iter_mut().skip(i).step_by(self.number_of_channels);
                 let channel = rendered.channel_data(i).iter();
                 for (sample, input) in output.zip(channel) {
-                    let value = Sample::from::<f32>(input);
-                    *sample = value;
+                    *sample = self.dither.convert(*input);
                 }
             }
 
@@ -220,6 +503,284 @@ impl RenderThread {
             }
         }
     }
+
+    /// Render quanta into the `queue` until it holds `ahead` frames more than the output reads at
+    /// once, the output reads the frames from the other end at its own pace
+    ///
+    /// Returns the number of rendered quanta.
+    pub fn render_ahead(&mut self, queue: &mut FrameProducer, ahead: usize) -> usize {
+        #[cfg(feature = "cpal")]
+        self.handle_output_callbacks();
+
+        let mut count = 0;
+        while queue.queued_frames() < ahead + queue.demand()
+            && queue.free_frames() >= RENDER_QUANTUM_SIZE
+        {
+            match self.render_quantum() {
+                Some(rendered) => {
+                    queue.push_with(RENDER_QUANTUM_SIZE, |frame, channel| {
+                        rendered.channel_data(channel)[frame]
+                    });
+                }
+                None => {
+                    if let Some(tap) = &mut self.output_tap {
+                        tap.push_silence(RENDER_QUANTUM_SIZE * self.number_of_channels);
+                    }
+                    queue.push_with(RENDER_QUANTUM_SIZE, |_, _| 0.);
+                }
+            }
+            count += 1;
+        }
+
+        count
+    }
+
+    /// Render the next quantum, mixed to the output channels
+    ///
+    /// Returns `None` when the quantum is silent and the time does not advance: before the
+    /// registration of the destination, and while the context is suspended or closed.
+    fn render_quantum(&mut self) -> Option<AudioRenderQuantum> {
+        // handle addition/removal of nodes/edges
+        self.handle_control_messages();
+
+        // The output stream may start before the registration of the destination reaches
+        // the render thread: output silence until then, without advancing the time, so the
+        // first rendered quantum is quantum zero.
+        if !self.graph.has_destination() {
+            return None;
+        }
+
+        // The quantum in which the context is suspended or closed is still rendered, so the
+        // processors can react to the new state. Time does not proceed afterwards.
+        let state: AudioContextState = self.state.load(Ordering::Acquire).into();
+        let entered = state != self.last_state;
+        self.last_state = state;
+        if state != AudioContextState::Running && !entered {
+            return None;
+        }
+
+        // update time
+        let current_frame = self
+            .frames_played
+            .fetch_add(RENDER_QUANTUM_SIZE as u64, Ordering::Release);
+
+        let mut scope = RenderScope::new(current_frame, self.sample_rate);
+        scope.state = state;
+
+        // render audio graph, and measure the load for the overload policy
+        // (there is no monotonic clock on wasm, the policy is not applied there)
+        #[cfg(not(target_arch = "wasm32"))]
+        let render_start = Instant::now();
+        let mut rendered = self.graph.render(&scope).clone();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let quantum_duration = RENDER_QUANTUM_SIZE as f64 / self.sample_rate as f64;
+            self.graph
+                .set_load(render_start.elapsed().as_secs_f64() / quantum_duration);
+        }
+
+        // online AudioContext allows channel count to be less than no of hardware channels
+        if rendered.number_of_channels() != self.number_of_channels {
+            rendered.mix(self.number_of_channels, ChannelInterpretation::Discrete);
+        }
+
+        if let Some(tap) = &mut self.output_tap {
+            tap.push(&rendered);
+        }
+
+        #[cfg(feature = "wasm")]
+        self.dispatch_ended_events();
+
+        // release the processors of a closed context, they are finalized on this thread
+        if state == AudioContextState::Closed {
+            self.graph = Graph::new();
+            self.released = true;
+        }
+
+        Some(rendered)
+    }
+
+    #[cfg(feature = "wasm")]
+    fn dispatch_ended_events(&self) {
+        if let Some(events) = &self.ended_events {
+            events.dispatch();
+        }
+    }
+}
+
+/// Render loop filling a queue a few render quanta ahead of the output reading it
+///
+/// Decoupling the rendering from the output callbacks allows for output buffers of any size:
+/// the graph is always rendered by whole quanta, while the output reads the frames it needs,
+/// e.g. 48 frames per callback. The queue holds the frames of the largest output callback on top
+/// of the quanta rendered ahead. The time of the context follows the rendered frames, so
+/// `current_time` is ahead of the output by the frames waiting in the queue.
+pub(crate) struct RenderAhead {
+    renderer: RenderThread,
+    queue: FrameProducer,
+    /// number of frames rendered ahead of the output
+    ahead: usize,
+}
+
+impl RenderAhead {
+    /// Creates a render loop rendering `quanta` render quanta ahead of an output reading
+    /// `frames_per_callback` frames at once, along with the consumer of the queue
+    pub fn new(
+        renderer: RenderThread,
+        quanta: usize,
+        frames_per_callback: usize,
+    ) -> (Self, FrameConsumer) {
+        let ahead = quanta * RENDER_QUANTUM_SIZE;
+        let capacity = ahead + MAX_OUTPUT_FRAMES.max(frames_per_callback) + RENDER_QUANTUM_SIZE;
+        let (queue, consumer) =
+            frame_queue(renderer.number_of_channels, capacity, frames_per_callback);
+
+        (
+            Self {
+                renderer,
+                queue,
+                ahead,
+            },
+            consumer,
+        )
+    }
+
+    /// Render quanta until the queue is filled ahead of the output, returns the number of
+    /// rendered quanta
+    pub fn fill(&mut self) -> usize {
+        self.renderer.render_ahead(&mut self.queue, self.ahead)
+    }
+
+    /// True once the consumer of the queue is dropped
+    pub fn is_closed(&self) -> bool {
+        self.queue.is_closed()
+    }
+
+    /// Fill the queue each time the output reads from it, until the output is dropped
+    // This code is not dead: false positive from clippy
+    // due to the use of #[cfg(not(test))]
+    #[allow(dead_code)]
+    #[cfg(feature = "cpal")]
+    pub fn run(mut self) {
+        // the output wakes the loop up, the timeout is a safety net for a missed wake up
+        let quantum_duration = RENDER_QUANTUM_SIZE as f64 / self.renderer.sample_rate as f64;
+        let timeout = Duration::from_secs_f64(quantum_duration);
+
+        while !self.is_closed() {
+            self.fill();
+            std::thread::park_timeout(timeout);
+        }
+    }
+}
+
+/// Output callback of the cpal backend, reading the frames of a [`RenderAhead`] loop
+#[cfg(feature = "cpal")]
+pub(crate) struct RenderAheadOutput {
+    queue: FrameConsumer,
+    sample_rate: f32,
+    number_of_channels: usize,
+    frames_played: Arc<AtomicU64>,
+    output_latency: Arc<AtomicF64>,
+    xruns: Arc<XrunCounters>,
+    dither: Dither,
+    /// time reference of the output callbacks
+    created_at: Instant,
+    /// correlation between the rendered frames and the output callbacks
+    callback_clock: Option<Arc<CallbackClock>>,
+    /// time and number of frames of the output callbacks, for the xrun detection of the render
+    /// loop
+    callbacks: Sender<(f64, usize)>,
+}
+
+#[cfg(feature = "cpal")]
+impl RenderAheadOutput {
+    /// Wake up the given thread, running the render loop, each time frames are read
+    // This code is not dead: false positive from clippy
+    // due to the use of #[cfg(not(test))]
+    #[allow(dead_code)]
+    pub fn set_waker(&mut self, thread: std::thread::Thread) {
+        self.queue.set_waker(thread);
+    }
+
+    // This code is not dead: false positive from clippy
+    // due to the use of #[cfg(not(test))]
+    #[allow(dead_code)]
+    pub fn render<S: OutputSample>(&mut self, buffer: &mut [S], infos: &OutputCallbackInfo) {
+        // update output latency, this value might change while running (e.g. sound card heat)
+        // the frames waiting in the queue add to the latency
+        let timestamp = infos.timestamp();
+        let delta = timestamp
+            .playback
+            .duration_since(&timestamp.callback)
+            .unwrap();
+        let queued = self.queue.queued_frames() as f64 / self.sample_rate as f64;
+        let output_latency = delta.as_secs() as f64 + delta.subsec_nanos() as f64 * 1e-9 + queued;
+        self.output_latency.store(output_latency);
+
+        // the xrun detection runs on the render loop, the report is dropped if it lags behind
+        let now = self.created_at.elapsed().as_secs_f64();
+        let number_of_frames = buffer.len() / self.number_of_channels;
+        let _ = self.callbacks.try_send((now, number_of_frames));
+
+        // the render loop did not keep up, the missing frames are silent
+        if self.queue.pop(buffer, &mut self.dither) < number_of_frames {
+            self.xruns.record_underrun();
+        }
+
+        if let Some(clock) = &self.callback_clock {
+            clock.record(self.frames_played.load(Ordering::Acquire));
+        }
+    }
+}
+
+/// Callback of an [`OutputTap`], with the interleaved samples and the number of channels
+type TapCallback = Box<dyn FnMut(&[f32], usize) + Send>;
+
+/// Callback receiving the interleaved output of the render thread, see
+/// [`AudioContext::set_output_tap`](crate::context::AudioContext::set_output_tap)
+pub(crate) struct OutputTap {
+    callback: TapCallback,
+    number_of_channels: usize,
+    /// interleaved frames of a render quantum, allocated on the control thread
+    buffer: Vec<f32>,
+}
+
+impl std::fmt::Debug for OutputTap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OutputTap")
+            .field("number_of_channels", &self.number_of_channels)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OutputTap {
+    pub fn new<F: FnMut(&[f32], usize) + Send + 'static>(
+        number_of_channels: usize,
+        callback: F,
+    ) -> Self {
+        Self {
+            callback: Box::new(callback),
+            number_of_channels,
+            buffer: vec![0.; RENDER_QUANTUM_SIZE * number_of_channels],
+        }
+    }
+
+    /// Pass a rendered quantum, with the channel count of the output
+    fn push(&mut self, rendered: &AudioRenderQuantum) {
+        let number_of_channels = self.number_of_channels;
+        for (i, channel) in rendered.channels().iter().enumerate() {
+            let output = self.buffer.iter_mut().skip(i).step_by(number_of_channels);
+            output.zip(channel.iter()).for_each(|(o, s)| *o = *s);
+        }
+        (self.callback)(&self.buffer, number_of_channels);
+    }
+
+    /// Pass the silence written while the context is suspended or closed
+    fn push_silence(&mut self, len: usize) {
+        let silence = &mut self.buffer[..len];
+        silence.fill(0.);
+        (self.callback)(silence, self.number_of_channels);
+    }
 }
 
 impl Drop for RenderThread {
@@ -227,3 +788,137 @@ impl Drop for RenderThread {
         log::info!("Audio render thread has been dropped");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "cpal")]
+    use float_eq::assert_float_eq;
+
+    use super::*;
+    use crate::control::Scheduler;
+
+    #[test]
+    #[cfg(feature = "cpal")]
+    fn test_xrun_delayed_pull() {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let frames_played = Arc::new(AtomicU64::new(0));
+        let mut render = RenderThread::new(48_000., 1, receiver, frames_played.clone());
+        let xruns = render.xrun_counters();
+
+        let (xrun_sender, xrun_receiver) = crossbeam_channel::unbounded();
+        sender
+            .send(ControlMessage::SetXrunSender {
+                sender: Some(xrun_sender),
+            })
+            .unwrap();
+        render.handle_control_messages();
+
+        // pull buffers of 480 frames, i.e. 10 ms, on time
+        let frames_per_pull = 480;
+        let mut now = 0.;
+        for _ in 0..10 {
+            render.detect_xrun(now, frames_per_pull);
+            frames_played.fetch_add(frames_per_pull as u64, Ordering::SeqCst);
+            now += 0.01;
+        }
+        assert_eq!(xruns.stats().count, 0);
+        assert!(xrun_receiver.try_recv().is_err());
+
+        // delay a pull by 10 ms
+        now += 0.01;
+        render.detect_xrun(now, frames_per_pull);
+
+        let stats = xruns.stats();
+        assert_eq!(stats.count, 1);
+        assert_float_eq!(stats.worst_gap, 0.02, abs <= 1e-9);
+        let last_time = stats.last_time.unwrap();
+        assert_float_eq!(
+            last_time,
+            (10 * frames_per_pull) as f64 / 48_000.,
+            abs <= 1e-9
+        );
+        assert_float_eq!(xrun_receiver.try_recv().unwrap(), 0.02, abs <= 1e-9);
+    }
+
+    #[test]
+    fn test_render_before_destination() {
+        let (_sender, receiver) = crossbeam_channel::unbounded();
+        let frames_played = Arc::new(AtomicU64::new(0));
+        let mut render = RenderThread::new(48_000., 2, receiver, frames_played.clone());
+
+        // the registration of the destination has not reached the render thread yet
+        let mut buffer = vec![1_f32; RENDER_QUANTUM_SIZE * 2 * 3 + 10];
+        render.render_interleaved(&mut buffer);
+        assert!(buffer.iter().all(|v| *v == 0.));
+        assert_eq!(frames_played.load(Ordering::Acquire), 0);
+    }
+
+    #[test]
+    fn test_cancel_start_stress() {
+        let sample_rate = 48_000.;
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let frames_played = Arc::new(AtomicU64::new(0));
+        let mut render = RenderThread::new(sample_rate, 1, receiver, frames_played.clone());
+
+        // apply the messages and advance the time as fast as possible, like the render loop
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let handle = {
+            let frames_played = frames_played.clone();
+            let done = done.clone();
+            std::thread::spawn(move || {
+                while !done.load(Ordering::Relaxed) {
+                    render.handle_control_messages();
+                    frames_played.fetch_add(RENDER_QUANTUM_SIZE as u64, Ordering::Release);
+                }
+                render.handle_control_messages();
+            })
+        };
+
+        let mut last_frame = 0;
+        let mut scheduled = vec![];
+        for i in 0..2000 {
+            let now = frames_played.load(Ordering::Acquire);
+            assert!(now >= last_frame);
+            last_frame = now;
+
+            // a few quanta ahead, the render thread may or may not reach the start frame before
+            // it receives the cancellation, or far ahead
+            let frame = if i % 5 == 0 {
+                (now + 1000 * sample_rate as u64) as f64
+            } else {
+                (now + (i % 5) * RENDER_QUANTUM_SIZE as u64) as f64
+            };
+            let scheduler = Scheduler::new();
+            sender
+                .send(ControlMessage::StartSource {
+                    scheduler: scheduler.clone(),
+                    frame,
+                })
+                .unwrap();
+            sender
+                .send(ControlMessage::CancelStart {
+                    scheduler: scheduler.clone(),
+                })
+                .unwrap();
+            scheduled.push((scheduler, frame, i % 5 == 0));
+        }
+
+        done.store(true, Ordering::Relaxed);
+        handle.join().unwrap();
+
+        for (scheduler, frame, far_ahead) in scheduled {
+            let start = scheduler.get_start_frame();
+            let stop = scheduler.get_stop_frame();
+            if far_ahead {
+                assert!(!scheduler.is_scheduled());
+            } else if start == f64::MAX {
+                // cancelled before the start was reached
+                assert_eq!(stop, f64::MAX);
+            } else {
+                // the start was reached, the source is stopped when the cancellation arrived
+                assert_eq!(start, frame);
+                assert!(stop >= frame && stop < f64::MAX);
+            }
+        }
+    }
+}
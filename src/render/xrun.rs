@@ -0,0 +1,189 @@
+//! Detection of the late output callbacks (xruns)
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::AtomicF64;
+
+/// An output callback is late when the gap since the previous callback exceeds the duration
+/// of the audio delivered by the previous callback by this factor
+pub(crate) const XRUN_THRESHOLD: f64 = 1.5;
+
+/// Statistics of the late output callbacks (xruns)
+///
+/// See [`AudioContext::xrun_stats`](crate::context::AudioContext::xrun_stats)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrunStats {
+    /// Number of output callbacks that came late
+    pub count: u64,
+    /// Number of underruns reported by the audio backend
+    pub reported: u64,
+    /// Number of output callbacks which found less frames than requested in the render-ahead
+    /// queue, the missing frames were output as silence
+    pub underruns: u64,
+    /// Largest gap between two output callbacks, when the latter came late, in seconds
+    pub worst_gap: f64,
+    /// Context time of the last late callback, in seconds
+    pub last_time: Option<f64>,
+}
+
+/// Xrun statistics shared between the render thread and the control thread
+#[derive(Debug)]
+pub(crate) struct XrunCounters {
+    count: AtomicU64,
+    reported: AtomicU64,
+    underruns: AtomicU64,
+    worst_gap: AtomicF64,
+    last_time: AtomicF64,
+}
+
+impl Default for XrunCounters {
+    fn default() -> Self {
+        Self {
+            count: AtomicU64::new(0),
+            reported: AtomicU64::new(0),
+            underruns: AtomicU64::new(0),
+            worst_gap: AtomicF64::new(0.),
+            last_time: AtomicF64::new(f64::NAN),
+        }
+    }
+}
+
+impl XrunCounters {
+    /// Record a late callback, `gap` seconds after the previous one, at context time `time`
+    ///
+    /// Only called from the render thread
+    pub fn record(&self, gap: f64, time: f64) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        if gap > self.worst_gap.load() {
+            self.worst_gap.store(gap);
+        }
+        self.last_time.store(time);
+    }
+
+    /// Record an underrun reported by the audio backend
+    pub fn record_reported(&self) {
+        self.reported.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record an output callback which found the render-ahead queue short of frames
+    ///
+    /// Only called from the output callback
+    pub fn record_underrun(&self) {
+        self.underruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> XrunStats {
+        let last_time = self.last_time.load();
+
+        XrunStats {
+            count: self.count.load(Ordering::Relaxed),
+            reported: self.reported.load(Ordering::Relaxed),
+            underruns: self.underruns.load(Ordering::Relaxed),
+            worst_gap: self.worst_gap.load(),
+            last_time: if last_time.is_nan() {
+                None
+            } else {
+                Some(last_time)
+            },
+        }
+    }
+}
+
+/// Compares the gaps between the output callbacks with the duration of the audio they deliver
+#[derive(Debug, Default)]
+pub(crate) struct XrunDetector {
+    /// Time of the previous callback, in seconds
+    last_callback: Option<f64>,
+    /// Duration of the audio delivered by the previous callback, in seconds
+    last_duration: f64,
+}
+
+impl XrunDetector {
+    /// Register an output callback at `now` delivering `duration` seconds of audio
+    ///
+    /// Returns the gap since the previous callback if this callback is late.
+    pub fn callback(&mut self, now: f64, duration: f64) -> Option<f64> {
+        let previous = self.last_callback.replace(now);
+        let expected = std::mem::replace(&mut self.last_duration, duration);
+
+        match previous {
+            Some(previous) if now - previous > XRUN_THRESHOLD * expected => Some(now - previous),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_detector_regular_callbacks() {
+        let mut detector = XrunDetector::default();
+        let duration = 256. / 48_000.;
+
+        for i in 0..100 {
+            // a bit of jitter is tolerated
+            let jitter = if i % 2 == 0 { 0.2 } else { -0.2 } * duration;
+            let now = i as f64 * duration + jitter;
+            assert_eq!(detector.callback(now, duration), None);
+        }
+    }
+
+    #[test]
+    fn test_detector_late_callback() {
+        let mut detector = XrunDetector::default();
+        let duration = 0.01;
+
+        assert_eq!(detector.callback(0., duration), None);
+        assert_eq!(detector.callback(0.01, duration), None);
+        // the callback is late by more than half a buffer
+        let gap = detector.callback(0.026, duration).unwrap();
+        assert_float_eq!(gap, 0.016, abs <= 1e-12);
+        // back to normal
+        assert_eq!(detector.callback(0.036, duration), None);
+    }
+
+    #[test]
+    fn test_detector_buffer_size_change() {
+        let mut detector = XrunDetector::default();
+
+        // the expected gap is the duration of the previous buffer
+        assert_eq!(detector.callback(0., 0.04), None);
+        assert_eq!(detector.callback(0.04, 0.01), None);
+        assert!(detector.callback(0.06, 0.01).is_some());
+    }
+
+    #[test]
+    fn test_detector_delayed_pull() {
+        let mut detector = XrunDetector::default();
+        let start = std::time::Instant::now();
+        let duration = 0.002;
+
+        assert_eq!(detector.callback(0., duration), None);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let now = start.elapsed().as_secs_f64();
+        let gap = detector.callback(now, duration).unwrap();
+        assert!(gap >= 0.02);
+    }
+
+    #[test]
+    fn test_counters() {
+        let counters = XrunCounters::default();
+        assert_eq!(counters.stats(), XrunStats::default());
+
+        counters.record(0.02, 1.);
+        counters.record(0.01, 2.);
+        counters.record_reported();
+        counters.record_underrun();
+        counters.record_underrun();
+
+        let stats = counters.stats();
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.reported, 1);
+        assert_eq!(stats.underruns, 2);
+        assert_float_eq!(stats.worst_gap, 0.02, abs <= 0.);
+        assert_eq!(stats.last_time, Some(2.));
+    }
+}
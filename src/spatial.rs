@@ -2,11 +2,15 @@
 //!
 //! Required for panning algorithm, distance and cone effects of panner nodes
 
-use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::context::{
+    AudioContextRegistration, AudioNodeId, AudioParamId, BaseAudioContext, BuiltinNode,
+};
 use crate::node::{
     AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation,
 };
-use crate::param::{AudioParam, AudioParamDescriptor, AudioParamRaw, AutomationRate};
+use crate::param::{
+    set_values_at_time, AudioParam, AudioParamDescriptor, AudioParamRaw, AutomationRate,
+};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 
 use lazy_static::lazy_static;
@@ -24,6 +28,10 @@ pub(crate) const PARAM_OPTS: AudioParamDescriptor = AudioParamDescriptor {
 ///
 /// All [`PannerNode`](crate::node::PannerNode) objects spatialize in relation to the [BaseAudioContext's](crate::context::BaseAudioContext) listener.
 ///
+/// The listener is positioned in the right-handed coordinate space of the panners, see
+/// [`PannerNode`](crate::node::PannerNode#coordinate-space). By default it stands at the origin,
+/// looking towards -Z (`forward`) with +Y up (`up`).
+///
 /// # Usage
 ///
 /// For example usage, check the [`PannerNode`](crate::node::PannerNode) docs.
@@ -67,6 +75,92 @@ impl AudioListener {
     pub fn up_z(&self) -> &AudioParam {
         &self.up_z
     }
+
+    /// Set the position and orientation of the listener from a camera transform
+    ///
+    /// The `matrix` is a 4x4 column-major transform, i.e. the translation is given by the
+    /// elements 12, 13 and 14. The listener looks along the transformed -Z axis, and the
+    /// transformed +Y axis is its up vector. Scale and shear are stripped, only the
+    /// directions are used.
+    ///
+    /// All the params are scheduled at `when`, and applied in the same render quantum.
+    /// If the matrix is not invertible (e.g. zero scale), the previous orientation is kept.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `when` is negative
+    pub fn set_transform(&self, matrix: [f32; 16], when: f64) {
+        let transform = Transform::from_matrix(&matrix);
+        let mut values = Vec::with_capacity(9);
+
+        if let Some([x, y, z]) = transform.position {
+            values.extend([
+                (&self.position_x, x),
+                (&self.position_y, y),
+                (&self.position_z, z),
+            ]);
+        } else {
+            log::debug!("AudioListener transform has no valid translation, position is kept");
+        }
+
+        if let Some(([fx, fy, fz], [ux, uy, uz])) = transform.orientation {
+            values.extend([
+                (&self.forward_x, fx),
+                (&self.forward_y, fy),
+                (&self.forward_z, fz),
+                (&self.up_x, ux),
+                (&self.up_y, uy),
+                (&self.up_z, uz),
+            ]);
+        } else {
+            log::debug!("AudioListener transform is not invertible, orientation is kept");
+        }
+
+        set_values_at_time(&values, when);
+    }
+}
+
+/// Position and orientation extracted from a 4x4 column-major transform matrix
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Transform {
+    /// Translation, `None` if not finite
+    pub position: Option<Vector3<f32>>,
+    /// Normalized forward (transformed -Z axis) and up (transformed +Y axis, orthogonalized
+    /// against forward) vectors, `None` if the matrix is not invertible
+    pub orientation: Option<(Vector3<f32>, Vector3<f32>)>,
+}
+
+impl Transform {
+    pub fn from_matrix(matrix: &[f32; 16]) -> Self {
+        let column = |i: usize| [matrix[4 * i], matrix[4 * i + 1], matrix[4 * i + 2]];
+        let (x_axis, y_axis, z_axis, translation) = (column(0), column(1), column(2), column(3));
+
+        let position = if translation.iter().all(|v| v.is_finite()) {
+            Some(translation)
+        } else {
+            None
+        };
+
+        // The determinant, relative to the scale of the axes, measures how far the matrix is
+        // from being singular. This also catches non-finite values.
+        let scale = vec3_len(x_axis) * vec3_len(y_axis) * vec3_len(z_axis);
+        let determinant = vec3_dot(x_axis, vec3_cross(y_axis, z_axis));
+        let invertible = scale.is_finite() && (determinant / scale).abs() > 1e-6;
+
+        let orientation = if invertible {
+            let forward = vec3_normalized(vec3_scale(z_axis, -1.));
+            // Gram-Schmidt, removes the shear between the axes
+            let up = vec3_sub(y_axis, vec3_scale(forward, vec3_dot(y_axis, forward)));
+            Some((forward, vec3_normalized(up)))
+        } else {
+            None
+        };
+
+        Self {
+            position,
+            orientation,
+        }
+    }
 }
 
 /// Wrapper for the [`AudioListener`] so it can be placed in the audio graph.
@@ -120,7 +214,8 @@ impl AudioNode for AudioListenerNode {
 
 impl AudioListenerNode {
     pub fn new<C: BaseAudioContext>(context: &C) -> Self {
-        context.register(move |registration| {
+        let base = context.base();
+        base.register_builtin(BuiltinNode::Listener, move |registration| {
             let forward_z_opts = AudioParamDescriptor {
                 default_value: -1.,
                 ..PARAM_OPTS
@@ -130,15 +225,51 @@ impl AudioListenerNode {
                 ..PARAM_OPTS
             };
 
-            let (p1, v1) = context.create_audio_param(PARAM_OPTS, &registration);
-            let (p2, v2) = context.create_audio_param(PARAM_OPTS, &registration);
-            let (p3, v3) = context.create_audio_param(PARAM_OPTS, &registration);
-            let (p4, v4) = context.create_audio_param(PARAM_OPTS, &registration);
-            let (p5, v5) = context.create_audio_param(PARAM_OPTS, &registration);
-            let (p6, v6) = context.create_audio_param(forward_z_opts, &registration);
-            let (p7, v7) = context.create_audio_param(PARAM_OPTS, &registration);
-            let (p8, v8) = context.create_audio_param(up_y_opts, &registration);
-            let (p9, v9) = context.create_audio_param(PARAM_OPTS, &registration);
+            let (p1, v1) = base.create_builtin_audio_param(
+                BuiltinNode::ListenerParam,
+                PARAM_OPTS,
+                &registration,
+            );
+            let (p2, v2) = base.create_builtin_audio_param(
+                BuiltinNode::ListenerParam,
+                PARAM_OPTS,
+                &registration,
+            );
+            let (p3, v3) = base.create_builtin_audio_param(
+                BuiltinNode::ListenerParam,
+                PARAM_OPTS,
+                &registration,
+            );
+            let (p4, v4) = base.create_builtin_audio_param(
+                BuiltinNode::ListenerParam,
+                PARAM_OPTS,
+                &registration,
+            );
+            let (p5, v5) = base.create_builtin_audio_param(
+                BuiltinNode::ListenerParam,
+                PARAM_OPTS,
+                &registration,
+            );
+            let (p6, v6) = base.create_builtin_audio_param(
+                BuiltinNode::ListenerParam,
+                forward_z_opts,
+                &registration,
+            );
+            let (p7, v7) = base.create_builtin_audio_param(
+                BuiltinNode::ListenerParam,
+                PARAM_OPTS,
+                &registration,
+            );
+            let (p8, v8) = base.create_builtin_audio_param(
+                BuiltinNode::ListenerParam,
+                up_y_opts,
+                &registration,
+            );
+            let (p9, v9) = base.create_builtin_audio_param(
+                BuiltinNode::ListenerParam,
+                PARAM_OPTS,
+                &registration,
+            );
 
             let node = Self {
                 registration,
@@ -212,6 +343,8 @@ impl AudioProcessor for ListenerRenderer {
 
 /// Data holder for the BaseAudioContext so it can reconstruct the AudioListener on request
 pub(crate) struct AudioListenerParams {
+    /// ids of the params, in the order of the fields below
+    pub ids: [AudioNodeId; 9],
     pub position_x: AudioParamRaw,
     pub position_y: AudioParamRaw,
     pub position_z: AudioParamRaw,
@@ -223,6 +356,48 @@ pub(crate) struct AudioListenerParams {
     pub up_z: AudioParamRaw,
 }
 
+impl From<AudioListener> for AudioListenerParams {
+    fn from(listener: AudioListener) -> Self {
+        let AudioListener {
+            position_x,
+            position_y,
+            position_z,
+            forward_x,
+            forward_y,
+            forward_z,
+            up_x,
+            up_y,
+            up_z,
+        } = listener;
+
+        let ids = [
+            &position_x,
+            &position_y,
+            &position_z,
+            &forward_x,
+            &forward_y,
+            &forward_z,
+            &up_x,
+            &up_y,
+            &up_z,
+        ]
+        .map(|param| *param.registration().id());
+
+        Self {
+            ids,
+            position_x: position_x.into_raw_parts(),
+            position_y: position_y.into_raw_parts(),
+            position_z: position_z.into_raw_parts(),
+            forward_x: forward_x.into_raw_parts(),
+            forward_y: forward_y.into_raw_parts(),
+            forward_z: forward_z.into_raw_parts(),
+            up_x: up_x.into_raw_parts(),
+            up_y: up_y.into_raw_parts(),
+            up_z: up_z.into_raw_parts(),
+        }
+    }
+}
+
 use vecmath::{
     vec3_cross, vec3_dot, vec3_len, vec3_normalized, vec3_scale, vec3_square_len, vec3_sub, Vector3,
 };
@@ -400,6 +575,90 @@ mod tests {
         assert_float_eq!(angle, 0., abs <= 0.);
     }
 
+    // column-major transform, rotation of `angle` radians about the Y axis, then uniform
+    // `scale` and `translation`
+    fn transform(angle: f32, scale: f32, translation: [f32; 3]) -> [f32; 16] {
+        let (sin, cos) = angle.sin_cos();
+        [
+            scale * cos,
+            0.,
+            -scale * sin,
+            0.,
+            0.,
+            scale,
+            0.,
+            0.,
+            scale * sin,
+            0.,
+            scale * cos,
+            0.,
+            translation[0],
+            translation[1],
+            translation[2],
+            1.,
+        ]
+    }
+
+    #[test]
+    fn test_transform_translation() {
+        let t = Transform::from_matrix(&transform(0., 1., [1., 2., 3.]));
+        assert_eq!(t.position, Some([1., 2., 3.]));
+
+        let (forward, up) = t.orientation.unwrap();
+        assert_float_eq!(forward, LF, abs_all <= 0.);
+        assert_float_eq!(up, LU, abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_transform_rotation() {
+        // a quarter turn about Y (counter clockwise seen from above) looks to the left
+        let t = Transform::from_matrix(&transform(PI / 2., 1., [0.; 3]));
+        assert_eq!(t.position, Some([0.; 3]));
+
+        let (forward, up) = t.orientation.unwrap();
+        assert_float_eq!(forward, [-1., 0., 0.], abs_all <= 1e-6);
+        assert_float_eq!(up, LU, abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_transform_scale_and_shear() {
+        // scale is stripped
+        let t = Transform::from_matrix(&transform(PI / 2., 5., [1., 0., 0.]));
+        assert_eq!(t.position, Some([1., 0., 0.]));
+
+        let (forward, up) = t.orientation.unwrap();
+        assert_float_eq!(forward, [-1., 0., 0.], abs_all <= 1e-6);
+        assert_float_eq!(up, LU, abs_all <= 1e-6);
+
+        // non uniform scale and shear of the up axis along the forward axis
+        let mut matrix = transform(0., 1., [0.; 3]);
+        matrix[0] = 2.;
+        matrix[6] = 0.5;
+        matrix[10] = 3.;
+        let (forward, up) = Transform::from_matrix(&matrix).orientation.unwrap();
+        assert_float_eq!(forward, LF, abs_all <= 1e-6);
+        assert_float_eq!(up, LU, abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_transform_not_invertible() {
+        let t = Transform::from_matrix(&transform(PI / 4., 0., [1., 2., 3.]));
+        assert_eq!(t.position, Some([1., 2., 3.]));
+        assert_eq!(t.orientation, None);
+
+        // flattened on the Y axis
+        let mut matrix = transform(0., 1., [0.; 3]);
+        matrix[5] = 0.;
+        assert_eq!(Transform::from_matrix(&matrix).orientation, None);
+
+        let mut matrix = transform(0., 1., [0.; 3]);
+        matrix[0] = f32::NAN;
+        matrix[12] = f32::INFINITY;
+        let t = Transform::from_matrix(&matrix);
+        assert_eq!(t.position, None);
+        assert_eq!(t.orientation, None);
+    }
+
     #[test]
     fn test_angle() {
         let pos = [1., 0., 0.];
@@ -0,0 +1,495 @@
+//! Assertions and fixtures for testing rendered audio
+//!
+//! Available with the `test-utils` feature, and in the unit tests of this crate.
+//!
+//! # Workflow
+//!
+//! Tests of a node render a small graph with an
+//! [`OfflineAudioContext`](crate::context::OfflineAudioContext), and compare the output with
+//! [`assert_audio_eq`] against either:
+//!
+//! - a reference computed in the test (e.g. a Fourier series for an oscillator), or
+//! - a fixture of a previous rendering, with [`assert_fixture_eq`], when there is no simple
+//!   reference. Fixtures live in the `snapshots/` directory.
+//!
+//! Pick the strictest [`Tolerance`] that holds: `Exact` for sample copies and gains, `Abs` for
+//! computations that may round differently, `Spectral` for signals where only the frequency
+//! content matters (e.g. band-limited waveforms, whose phase may shift between
+//! implementations).
+//!
+//! To record a new fixture, or update one after an intended change of the output, run the
+//! test with the `UPDATE_FIXTURES` environment variable set and review the diff of the fixture
+//! along with the code:
+//!
+//! ```text
+//! UPDATE_FIXTURES=1 cargo test --features test-utils my_node
+//! ```
+//!
+//! Decimate long renderings to keep the fixtures small: the fixture then holds every n-th
+//! frame only, and the rendering is decimated the same way before the comparison.
+//!
+//! ```
+//! use web_audio_api::context::{BaseAudioContext, OfflineAudioContext};
+//! use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+//! use web_audio_api::testing::{assert_audio_eq, Tolerance};
+//! use web_audio_api::AudioBuffer;
+//!
+//! let mut context = OfflineAudioContext::new(1, 128, 48_000.);
+//! let src = context.create_constant_source();
+//! let gain = context.create_gain();
+//! gain.gain().set_value(0.5);
+//! src.connect(&gain);
+//! gain.connect(&context.destination());
+//! src.start();
+//!
+//! let output = context.start_rendering_sync();
+//! let expected = AudioBuffer::from(vec![vec![0.5; 128]], 48_000.);
+//! assert_audio_eq(&output, &expected, Tolerance::Exact);
+//! ```
+use std::convert::TryFrom;
+use std::f32::consts::PI;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use realfft::RealFftPlanner;
+
+use crate::buffer::AudioBuffer;
+
+/// Maximum deviation of the spectra compared with [`Tolerance::Spectral`], in dB
+pub const SPECTRAL_TOLERANCE_DB: f32 = 1.;
+
+/// Environment variable to record the fixtures instead of comparing them, see
+/// [`assert_fixture_eq`]
+pub const UPDATE_FIXTURES_VAR: &str = "UPDATE_FIXTURES";
+
+/// Identifies the fixture files
+const FIXTURE_MAGIC: &[u8; 4] = b"WAAF";
+/// Version of the fixture file format
+const FIXTURE_VERSION: u32 = 1;
+/// Size of the header of the fixture files: magic, version, sample rate, number of channels,
+/// length and decimation
+const FIXTURE_HEADER_SIZE: usize = 24;
+
+/// How close a rendering must be to the expected output, see [`assert_audio_eq`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tolerance {
+    /// Identical samples
+    Exact,
+    /// Samples within the given absolute difference
+    Abs(f32),
+    /// Magnitude spectra within [`SPECTRAL_TOLERANCE_DB`] of each other, the phase is ignored
+    ///
+    /// The spectra are taken over the whole channels with a Hann window, in dB relative to the
+    /// peak of the expected spectrum. The bins below `db_floor` (e.g. `-80.`) are raised to it,
+    /// so the noise floor of the signals is not compared.
+    Spectral { db_floor: f32 },
+}
+
+/// Assert that the rendered audio matches the expected audio within the given tolerance
+///
+/// # Panics
+///
+/// Panics if the sample rates, number of channels or lengths differ, or if the samples do not
+/// match. The message tells the channel, the first mismatching frame (or frequency bin for
+/// `Spectral`) and the maximum error.
+pub fn assert_audio_eq(actual: &AudioBuffer, expected: &AudioBuffer, profile: Tolerance) {
+    assert_eq!(
+        actual.sample_rate(),
+        expected.sample_rate(),
+        "audio mismatch: sample rate"
+    );
+    assert_eq!(
+        actual.number_of_channels(),
+        expected.number_of_channels(),
+        "audio mismatch: number of channels"
+    );
+    assert_eq!(actual.length(), expected.length(), "audio mismatch: length");
+
+    for channel in 0..actual.number_of_channels() {
+        let actual_data = actual.get_channel_data(channel);
+        let expected_data = expected.get_channel_data(channel);
+
+        match profile {
+            Tolerance::Exact => {
+                let same = |a: f32, e: f32| a == e || (a.is_nan() && e.is_nan());
+                assert_samples(channel, actual_data, expected_data, same, profile);
+            }
+            Tolerance::Abs(tolerance) => {
+                let close = |a: f32, e: f32| (a - e).abs() <= tolerance;
+                assert_samples(channel, actual_data, expected_data, close, profile);
+            }
+            Tolerance::Spectral { db_floor } => {
+                let sample_rate = actual.sample_rate();
+                assert_spectra(channel, actual_data, expected_data, sample_rate, db_floor);
+            }
+        }
+    }
+}
+
+fn assert_samples<F: Fn(f32, f32) -> bool>(
+    channel: usize,
+    actual: &[f32],
+    expected: &[f32],
+    matches: F,
+    profile: Tolerance,
+) {
+    let first = match actual
+        .iter()
+        .zip(expected)
+        .position(|(a, e)| !matches(*a, *e))
+    {
+        Some(first) => first,
+        None => return,
+    };
+
+    let (max_frame, max_error) = actual
+        .iter()
+        .zip(expected)
+        .map(|(a, e)| (a - e).abs())
+        .enumerate()
+        .fold((first, 0.), |(frame, max), (i, error)| {
+            if error > max || error.is_nan() && !max.is_nan() {
+                (i, error)
+            } else {
+                (frame, max)
+            }
+        });
+
+    panic!(
+        "audio mismatch in channel {} ({:?}): first mismatching frame {} (actual {}, expected {}), \
+         max error {} at frame {}",
+        channel, profile, first, actual[first], expected[first], max_error, max_frame
+    );
+}
+
+fn assert_spectra(
+    channel: usize,
+    actual: &[f32],
+    expected: &[f32],
+    sample_rate: f32,
+    db_floor: f32,
+) {
+    let actual = magnitude_spectrum(actual);
+    let expected = magnitude_spectrum(expected);
+
+    // dB relative to the peak of the expected spectrum, raised to the floor
+    let reference = expected
+        .iter()
+        .fold(f32::MIN_POSITIVE, |max, m| max.max(*m));
+    let db = |m: f32| (20. * (m / reference).log10()).max(db_floor);
+
+    let deviations: Vec<f32> = actual
+        .iter()
+        .zip(&expected)
+        .map(|(a, e)| (db(*a) - db(*e)).abs())
+        .collect();
+
+    let first = match deviations.iter().position(|d| *d > SPECTRAL_TOLERANCE_DB) {
+        Some(first) => first,
+        None => return,
+    };
+
+    let (max_bin, max_deviation) =
+        deviations
+            .iter()
+            .enumerate()
+            .fold(
+                (first, 0.),
+                |(bin, max), (i, d)| {
+                    if *d > max {
+                        (i, *d)
+                    } else {
+                        (bin, max)
+                    }
+                },
+            );
+
+    // the spectra have the same size, from 0 Hz up to the Nyquist frequency
+    let bin_width = sample_rate / (2 * (expected.len() - 1)) as f32;
+    panic!(
+        "audio mismatch in channel {} (spectral, floor {} dB): first mismatching bin at {} Hz \
+         (actual {:.1} dB, expected {:.1} dB), max deviation {:.1} dB at {} Hz",
+        channel,
+        db_floor,
+        first as f32 * bin_width,
+        db(actual[first]),
+        db(expected[first]),
+        max_deviation,
+        max_bin as f32 * bin_width
+    );
+}
+
+/// Magnitudes of the spectrum of the Hann windowed data, zero padded to a power of two
+fn magnitude_spectrum(data: &[f32]) -> Vec<f32> {
+    let length = data.len();
+    let fft_size = length.next_power_of_two().max(2);
+    let mut planner = RealFftPlanner::<f32>::new();
+    let fft = planner.plan_fft_forward(fft_size);
+    let mut input = fft.make_input_vec();
+    let mut spectrum = fft.make_output_vec();
+
+    input
+        .iter_mut()
+        .zip(data)
+        .enumerate()
+        .for_each(|(i, (o, v))| {
+            let w = 0.5 - 0.5 * (2. * PI * i as f32 / length as f32).cos();
+            *o = v * w;
+        });
+
+    fft.process(&mut input, &mut spectrum).unwrap();
+
+    spectrum.iter().map(|c| c.norm()).collect()
+}
+
+/// Keep every `factor`-th frame of the buffer, starting with the first one
+///
+/// The samples are not filtered: this is meant to shrink fixtures, not to resample. The sample
+/// rate of the result is divided by `factor`.
+///
+/// # Panics
+///
+/// Panics if `factor` is zero
+pub fn decimate(buffer: &AudioBuffer, factor: usize) -> AudioBuffer {
+    assert!(
+        factor > 0,
+        "RangeError - Decimation factor must be positive"
+    );
+
+    let channels = buffer
+        .channels()
+        .iter()
+        .map(|channel| channel.as_slice().iter().step_by(factor).copied().collect())
+        .collect();
+
+    AudioBuffer::from(channels, buffer.sample_rate() / factor as f32)
+}
+
+/// Expected audio stored by [`store_fixture`]
+#[derive(Clone, Debug)]
+pub struct Fixture {
+    /// The stored frames, see [`decimate`]
+    pub buffer: AudioBuffer,
+    /// The decimation factor applied to the rendering, 1 if it was stored in full
+    pub decimation: usize,
+}
+
+/// Store the buffer as a fixture file, keeping every `decimation`-th frame
+///
+/// The file holds a small header followed by the planar samples, as little endian `f32`.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written
+///
+/// # Panics
+///
+/// Panics if `decimation` is zero
+pub fn store_fixture<P: AsRef<Path>>(
+    path: P,
+    buffer: &AudioBuffer,
+    decimation: usize,
+) -> io::Result<()> {
+    let buffer = decimate(buffer, decimation);
+
+    let number_of_samples = buffer.number_of_channels() * buffer.length();
+    let mut bytes = Vec::with_capacity(FIXTURE_HEADER_SIZE + 4 * number_of_samples);
+    bytes.extend_from_slice(FIXTURE_MAGIC);
+    bytes.extend_from_slice(&FIXTURE_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&buffer.sample_rate().to_le_bytes());
+    for value in [buffer.number_of_channels(), buffer.length(), decimation] {
+        let value = u32::try_from(value)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Fixture too large"))?;
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    for channel in buffer.channels() {
+        channel
+            .as_slice()
+            .iter()
+            .for_each(|v| bytes.extend_from_slice(&v.to_le_bytes()));
+    }
+
+    fs::write(path, bytes)
+}
+
+/// Load a fixture file written by [`store_fixture`]
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or is not a valid fixture
+pub fn load_fixture<P: AsRef<Path>>(path: P) -> io::Result<Fixture> {
+    let bytes = fs::read(path)?;
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+    if bytes.len() < FIXTURE_HEADER_SIZE || &bytes[..4] != FIXTURE_MAGIC {
+        return Err(invalid("Not an audio fixture"));
+    }
+    let word = |i: usize| {
+        let offset = 4 * i;
+        [
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ]
+    };
+    if u32::from_le_bytes(word(1)) != FIXTURE_VERSION {
+        return Err(invalid("Unsupported audio fixture version"));
+    }
+    let sample_rate = f32::from_le_bytes(word(2));
+    let number_of_channels = u32::from_le_bytes(word(3)) as usize;
+    let length = u32::from_le_bytes(word(4)) as usize;
+    let decimation = u32::from_le_bytes(word(5)) as usize;
+
+    let samples = &bytes[FIXTURE_HEADER_SIZE..];
+    if samples.len() != 4 * number_of_channels * length || decimation == 0 {
+        return Err(invalid("Corrupt audio fixture"));
+    }
+
+    let channels = samples
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect::<Vec<_>>()
+        .chunks(length.max(1))
+        .take(number_of_channels)
+        .map(<[f32]>::to_vec)
+        .collect();
+
+    Ok(Fixture {
+        buffer: AudioBuffer::from(channels, sample_rate),
+        decimation,
+    })
+}
+
+/// Assert that the rendered audio matches the fixture file, see [`assert_audio_eq`]
+///
+/// The rendering is decimated by `decimation` before the comparison. When the
+/// [`UPDATE_FIXTURES_VAR`] environment variable is set, the rendering is stored as the fixture
+/// instead.
+///
+/// # Panics
+///
+/// Panics if the fixture cannot be read or written, if it was stored with another decimation,
+/// or if the audio does not match
+pub fn assert_fixture_eq<P: AsRef<Path>>(
+    actual: &AudioBuffer,
+    path: P,
+    decimation: usize,
+    profile: Tolerance,
+) {
+    let path = path.as_ref();
+
+    if std::env::var_os(UPDATE_FIXTURES_VAR).is_some() {
+        store_fixture(path, actual, decimation)
+            .unwrap_or_else(|e| panic!("Cannot write fixture {}: {}", path.display(), e));
+        return;
+    }
+
+    let fixture = load_fixture(path).unwrap_or_else(|e| {
+        panic!(
+            "Cannot read fixture {}: {} (set {} to record it)",
+            path.display(),
+            e,
+            UPDATE_FIXTURES_VAR
+        )
+    });
+    assert_eq!(
+        fixture.decimation,
+        decimation,
+        "fixture {} was stored with another decimation",
+        path.display()
+    );
+
+    assert_audio_eq(&decimate(actual, decimation), &fixture.buffer, profile);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f32, sample_rate: f32, length: usize) -> Vec<f32> {
+        (0..length)
+            .map(|i| (2. * PI * freq * i as f32 / sample_rate).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_exact_and_abs() {
+        let expected = AudioBuffer::from(vec![vec![0., 0.5, 1.]], 48_000.);
+        assert_audio_eq(&expected, &expected, Tolerance::Exact);
+
+        let actual = AudioBuffer::from(vec![vec![0., 0.5001, 1.]], 48_000.);
+        assert_audio_eq(&actual, &expected, Tolerance::Abs(1e-3));
+    }
+
+    #[test]
+    #[should_panic(expected = "first mismatching frame 1")]
+    fn test_exact_mismatch() {
+        let expected = AudioBuffer::from(vec![vec![0., 0.5, 1.]], 48_000.);
+        let actual = AudioBuffer::from(vec![vec![0., 0.5001, 1.]], 48_000.);
+        assert_audio_eq(&actual, &expected, Tolerance::Exact);
+    }
+
+    #[test]
+    #[should_panic(expected = "audio mismatch: length")]
+    fn test_length_mismatch() {
+        let expected = AudioBuffer::from(vec![vec![0.; 3]], 48_000.);
+        let actual = AudioBuffer::from(vec![vec![0.; 4]], 48_000.);
+        assert_audio_eq(&actual, &expected, Tolerance::Exact);
+    }
+
+    #[test]
+    fn test_spectral() {
+        let sample_rate = 48_000.;
+        let expected = AudioBuffer::from(vec![sine(1_000., sample_rate, 4_096)], sample_rate);
+
+        // a phase shift does not change the spectrum
+        let shifted = (0..4_096)
+            .map(|i| (2. * PI * 1_000. * i as f32 / sample_rate + 1.).sin())
+            .collect();
+        let actual = AudioBuffer::from(vec![shifted], sample_rate);
+        assert_audio_eq(&actual, &expected, Tolerance::Spectral { db_floor: -60. });
+    }
+
+    #[test]
+    #[should_panic(expected = "first mismatching bin")]
+    fn test_spectral_mismatch() {
+        let sample_rate = 48_000.;
+        let expected = AudioBuffer::from(vec![sine(1_000., sample_rate, 4_096)], sample_rate);
+        let actual = AudioBuffer::from(vec![sine(2_000., sample_rate, 4_096)], sample_rate);
+        assert_audio_eq(&actual, &expected, Tolerance::Spectral { db_floor: -60. });
+    }
+
+    #[test]
+    fn test_fixture_roundtrip() {
+        let sample_rate = 48_000.;
+        let buffer = AudioBuffer::from(
+            vec![
+                sine(440., sample_rate, 1_000),
+                sine(880., sample_rate, 1_000),
+            ],
+            sample_rate,
+        );
+
+        let path = std::env::temp_dir().join("web_audio_api_fixture_roundtrip.bin");
+        store_fixture(&path, &buffer, 4).unwrap();
+        let fixture = load_fixture(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(fixture.decimation, 4);
+        assert_eq!(fixture.buffer.number_of_channels(), 2);
+        assert_eq!(fixture.buffer.length(), 250);
+        assert_audio_eq(&decimate(&buffer, 4), &fixture.buffer, Tolerance::Exact);
+    }
+
+    #[test]
+    fn test_invalid_fixture() {
+        let path = std::env::temp_dir().join("web_audio_api_fixture_invalid.bin");
+        fs::write(&path, b"not a fixture").unwrap();
+        let result = load_fixture(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,588 @@
+//! Voice allocation for polyphonic instruments built from the audio nodes
+//!
+//! A [`VoicePool`] plays notes on a fixed number of voices. The node graph of a voice is
+//! described by a [`VoiceGraph`], built by a user closure together with the hooks scheduling
+//! the attack and the release of a note. The pool claims a free voice for each note, steals a
+//! playing voice when all of them are in use, and keeps track of the release tails to know
+//! when a voice is free again.
+
+use crate::context::{BaseAudioContext, ConcreteBaseAudioContext, Transaction};
+use crate::node::{
+    AudioNode, AudioScheduledSourceNode, GainNode, LoudnessMeterNode, PlaybackState,
+};
+use crate::render::DEFAULT_MUTE_RAMP_DURATION;
+use crate::RENDER_QUANTUM_SIZE;
+
+/// Accessor to the output node of a voice
+type OutputFn<N> = fn(&N) -> &dyn AudioNode;
+/// Accessor to a scheduled source of a voice
+type SourceFn<N> = fn(&N) -> &dyn AudioScheduledSourceNode;
+/// Hook scheduling the attack of a note on a voice
+type TriggerFn<N, T> = Box<dyn Fn(&N, &T, f64) + Send>;
+/// Hook scheduling the release of a note on a voice, returning the duration of the tail
+type ReleaseFn<N> = Box<dyn Fn(&N, f64) -> f64 + Send>;
+/// Closure building the graph of a voice
+type BuildFn<N, T> = Box<dyn Fn(&Transaction<'_>) -> VoiceGraph<N, T> + Send>;
+
+/// The audio nodes of a voice of a [`VoicePool`], and the hooks playing a note on them
+///
+/// `N` holds the nodes of the voice, usually a struct or a tuple, and `T` the description of a
+/// note passed to [`VoicePool::note_on`], e.g. its frequency. The nodes are given to the hooks
+/// together with the time of the event, so the hooks can schedule the automation of the params.
+///
+/// The scheduled sources listed with [`with_source`](Self::with_source) are started and stopped
+/// by the pool, they must not be started by the hooks.
+pub struct VoiceGraph<N, T> {
+    nodes: N,
+    output: OutputFn<N>,
+    sources: Vec<SourceFn<N>>,
+    trigger: TriggerFn<N, T>,
+    release: ReleaseFn<N>,
+}
+
+impl<N, T> std::fmt::Debug for VoiceGraph<N, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VoiceGraph")
+            .field("sources", &self.sources.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<N, T> VoiceGraph<N, T> {
+    /// Describe a voice made of the given nodes, `output` returning the node connected to the
+    /// output of the pool
+    ///
+    /// Without hooks, a note only starts the sources at note on and stops them at note off.
+    pub fn new(nodes: N, output: OutputFn<N>) -> Self {
+        Self {
+            nodes,
+            output,
+            sources: vec![],
+            trigger: Box::new(|_, _, _| ()),
+            release: Box::new(|_, _| 0.),
+        }
+    }
+
+    /// Add a scheduled source of the voice, started at note on and stopped at the end of the
+    /// release tail
+    #[must_use]
+    pub fn with_source(mut self, source: SourceFn<N>) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Set the hook scheduling the attack of a note, given the note and its start time
+    #[must_use]
+    pub fn on_trigger<F: Fn(&N, &T, f64) + Send + 'static>(mut self, trigger: F) -> Self {
+        self.trigger = Box::new(trigger);
+        self
+    }
+
+    /// Set the hook scheduling the release of a note, given the note off time
+    ///
+    /// The hook returns the duration of the release tail in seconds. The sources of the voice
+    /// are stopped, and the voice is free again, at the end of the tail.
+    #[must_use]
+    pub fn on_release<F: Fn(&N, f64) -> f64 + Send + 'static>(mut self, release: F) -> Self {
+        self.release = Box::new(release);
+        self
+    }
+
+    /// The audio nodes of the voice
+    pub fn nodes(&self) -> &N {
+        &self.nodes
+    }
+
+    fn output(&self) -> &dyn AudioNode {
+        (self.output)(&self.nodes)
+    }
+
+    fn sources(&self) -> impl Iterator<Item = &dyn AudioScheduledSourceNode> {
+        self.sources.iter().map(move |source| source(&self.nodes))
+    }
+}
+
+/// Choice of the voice to steal when a note is played while all the voices are in use
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum StealPolicy {
+    /// Steal the voice of the oldest note
+    #[default]
+    Oldest,
+    /// Steal the voice with the lowest momentary loudness, measured by a
+    /// [`LoudnessMeterNode`] at the output of each voice
+    ///
+    /// The loudness of a note is measured after 100ms only, until then the voice is considered
+    /// silent.
+    Quietest,
+}
+
+/// Identifier of a note played by a [`VoicePool`], used to release it
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VoiceId(u64);
+
+/// A note playing, or in its release tail, on a voice
+struct Voice<N, T> {
+    id: VoiceId,
+    graph: VoiceGraph<N, T>,
+    meter: Option<LoudnessMeterNode>,
+    /// End of the release tail, once the note is released or stolen
+    end: Option<f64>,
+}
+
+impl<N, T> Voice<N, T> {
+    fn is_finished(&self) -> bool {
+        let mut sources = self.graph.sources().peekable();
+        sources.peek().is_some()
+            && sources.all(|source| source.playback_state() == PlaybackState::Finished)
+    }
+
+    fn loudness(&self) -> f64 {
+        self.meter
+            .as_ref()
+            .map_or(f64::NEG_INFINITY, LoudnessMeterNode::momentary_loudness)
+    }
+}
+
+/// A fixed number of voices playing the notes of a polyphonic instrument
+///
+/// The graph of a voice is built by a closure, called on each note on inside a
+/// [`transaction`](BaseAudioContext::transaction) together with the scheduling of the note, so
+/// the whole voice reaches the audio graph at once. Source nodes can only be started once, so
+/// a voice is built anew for each note and the previous graph of the voice is released.
+///
+/// A note is played on a free voice when there is one. Otherwise a voice is stolen: a voice in
+/// its release tail if the tail ends soon enough, or a held voice chosen by the
+/// [`StealPolicy`]. The stolen voice is muted with a
+/// [`DEFAULT_MUTE_RAMP_DURATION`] ramp to avoid clicks, and the note starts once the ramp has
+/// completed. The sources of the previous note are always stopped before the sources of the
+/// next note start, so the pool never plays more sources than `voice_count` times the sources
+/// of a voice.
+///
+/// A voice is free again at the end of its release tail, or as soon as the render thread
+/// reports that all its sources have finished, e.g. a non-looping `AudioBufferSourceNode`
+/// that played until the end of its buffer.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, GainNode, OscillatorNode};
+/// use web_audio_api::voice::{VoiceGraph, VoicePool};
+///
+/// let context = AudioContext::default();
+///
+/// let mut pool = VoicePool::new(&context, 8, |ctx| {
+///     let osc = ctx.create_oscillator();
+///     let env = ctx.create_gain();
+///     env.gain().set_value(0.);
+///     osc.connect(&env);
+///
+///     VoiceGraph::new((osc, env), |(_, env): &(OscillatorNode, GainNode)| env)
+///         .with_source(|(osc, _)| osc)
+///         .on_trigger(|(osc, env), frequency: &f32, when| {
+///             osc.frequency().set_value_at_time(*frequency, when);
+///             env.gain().set_target_at_time(0.2, when, 0.01);
+///         })
+///         .on_release(|(_, env), when| {
+///             env.gain().cancel_and_hold_at_time(when);
+///             env.gain().set_target_at_time(0., when, 0.1);
+///             0.5
+///         })
+/// });
+/// pool.output().connect(&context.destination());
+///
+/// let now = context.current_time();
+/// let note = pool.note_on(440., now);
+/// pool.note_off(note, now + 1.);
+/// ```
+pub struct VoicePool<N, T> {
+    context: ConcreteBaseAudioContext,
+    build: BuildFn<N, T>,
+    voices: Vec<Option<Voice<N, T>>>,
+    output: GainNode,
+    steal_policy: StealPolicy,
+    next_id: u64,
+}
+
+impl<N, T> std::fmt::Debug for VoicePool<N, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VoicePool")
+            .field("voice_count", &self.voices.len())
+            .field("steal_policy", &self.steal_policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<N, T> VoicePool<N, T> {
+    /// Create a pool of `voice_count` voices, each built by `build`, stealing the voice of the
+    /// oldest note when all of them are in use
+    ///
+    /// # Panics
+    ///
+    /// Panics if `voice_count` is zero
+    pub fn new<C, F>(context: &C, voice_count: usize, build: F) -> Self
+    where
+        C: BaseAudioContext,
+        F: Fn(&Transaction<'_>) -> VoiceGraph<N, T> + Send + 'static,
+    {
+        Self::with_steal_policy(context, voice_count, StealPolicy::default(), build)
+    }
+
+    /// Create a pool of `voice_count` voices, each built by `build`, stealing voices following
+    /// the given policy
+    ///
+    /// # Panics
+    ///
+    /// Panics if `voice_count` is zero
+    pub fn with_steal_policy<C, F>(
+        context: &C,
+        voice_count: usize,
+        steal_policy: StealPolicy,
+        build: F,
+    ) -> Self
+    where
+        C: BaseAudioContext,
+        F: Fn(&Transaction<'_>) -> VoiceGraph<N, T> + Send + 'static,
+    {
+        assert!(
+            voice_count > 0,
+            "NotSupportedError - a voice pool needs at least one voice"
+        );
+
+        Self {
+            context: context.base().clone(),
+            build: Box::new(build),
+            voices: (0..voice_count).map(|_| None).collect(),
+            output: context.create_gain(),
+            steal_policy,
+            next_id: 0,
+        }
+    }
+
+    /// The node mixing the output of all the voices, to be connected to the rest of the graph
+    #[must_use]
+    pub fn output(&self) -> &GainNode {
+        &self.output
+    }
+
+    /// Number of voices of the pool
+    #[must_use]
+    pub fn voice_count(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// The policy choosing the voice to steal
+    #[must_use]
+    pub fn steal_policy(&self) -> StealPolicy {
+        self.steal_policy
+    }
+
+    /// Play a note at the given time, in the same time coordinate system as the
+    /// `AudioContext`'s `currentTime`
+    ///
+    /// The note starts later when its voice has to be released first, see [`VoicePool`].
+    /// Returns the id of the note, to release it with [`note_off`](Self::note_off).
+    ///
+    /// # Panics
+    ///
+    /// Panics if a transaction is already open on the calling thread
+    pub fn note_on(&mut self, note: T, when: f64) -> VoiceId {
+        let now = self.context.current_time();
+        let (index, start) = self.claim(now, when);
+
+        let id = VoiceId(self.next_id);
+        self.next_id += 1;
+
+        let voice = {
+            let Self {
+                context,
+                build,
+                voices,
+                output,
+                steal_policy,
+                ..
+            } = self;
+            let previous = voices[index].as_mut();
+
+            let result: Result<_, std::convert::Infallible> = context.transaction(|txn| {
+                // a held voice is stolen, its sources stop once it is muted
+                if let Some(previous) = previous.filter(|voice| voice.end.is_none()) {
+                    let stop = now + Self::quantum_duration(txn) + DEFAULT_MUTE_RAMP_DURATION;
+                    previous.graph.output().set_muted(true);
+                    previous
+                        .graph
+                        .sources()
+                        .for_each(|source| source.stop_at(stop));
+                    previous.end = Some(stop);
+                }
+
+                let graph = build(txn);
+                let meter = match steal_policy {
+                    StealPolicy::Oldest => {
+                        graph.output().connect(output);
+                        None
+                    }
+                    StealPolicy::Quietest => {
+                        let meter = txn.create_loudness_meter();
+                        graph.output().connect(&meter);
+                        meter.connect(output);
+                        Some(meter)
+                    }
+                };
+
+                graph.sources().for_each(|source| source.start_at(start));
+                (graph.trigger)(&graph.nodes, &note, start);
+
+                Ok(Voice {
+                    id,
+                    graph,
+                    meter,
+                    end: None,
+                })
+            });
+            result.unwrap_or_else(|never| match never {})
+        };
+
+        // the previous graph of the voice is released, its nodes are freed once they stop
+        self.voices[index] = Some(voice);
+
+        id
+    }
+
+    /// Release a note at the given time, in the same time coordinate system as the
+    /// `AudioContext`'s `currentTime`
+    ///
+    /// Calls the release hook of the voice, its sources are stopped at the end of the release
+    /// tail. Does nothing if the note was already released, or if its voice was stolen.
+    pub fn note_off(&mut self, id: VoiceId, when: f64) {
+        let voice = self
+            .voices
+            .iter_mut()
+            .flatten()
+            .find(|voice| voice.id == id && voice.end.is_none());
+
+        if let Some(voice) = voice {
+            let tail = (voice.graph.release)(&voice.graph.nodes, when);
+            let end = when + tail.max(0.);
+            voice.graph.sources().for_each(|source| source.stop_at(end));
+            voice.end = Some(end);
+        }
+    }
+
+    /// Number of voices holding a note, or playing its release tail, at the given time
+    #[must_use]
+    pub fn busy_voice_count(&self, when: f64) -> usize {
+        self.voices
+            .iter()
+            .flatten()
+            .filter(|voice| !voice.end.is_some_and(|end| end <= when) && !voice.is_finished())
+            .count()
+    }
+
+    /// Choose the voice of a note played at `when`, returns its index and the start time of
+    /// the note
+    fn claim(&self, now: f64, when: f64) -> (usize, f64) {
+        let free = self.voices.iter().position(|voice| match voice {
+            None => true,
+            Some(voice) => {
+                voice.is_finished() || voice.end.is_some_and(|end| self.reuse_time(end) <= when)
+            }
+        });
+        if let Some(index) = free {
+            return (index, when);
+        }
+
+        // all voices are in use, every slot holds a voice from here on
+        let voices = self.voices.iter().map(|voice| voice.as_ref().unwrap());
+
+        let released = voices
+            .clone()
+            .enumerate()
+            .filter_map(|(index, voice)| voice.end.map(|end| (index, self.reuse_time(end))))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        let steal_time = self
+            .reuse_time(now + Self::quantum_duration(&self.context) + DEFAULT_MUTE_RAMP_DURATION);
+
+        let held = voices.enumerate().filter(|(_, voice)| voice.end.is_none());
+        let victim = match self.steal_policy {
+            // the ids are increasing with the note on calls
+            StealPolicy::Oldest => held.min_by_key(|(_, voice)| voice.id.0),
+            StealPolicy::Quietest => held.min_by(|(_, a), (_, b)| {
+                a.loudness()
+                    .total_cmp(&b.loudness())
+                    .then(a.id.0.cmp(&b.id.0))
+            }),
+        };
+
+        match (released, victim) {
+            (Some((index, reuse)), _) if reuse <= steal_time => (index, when.max(reuse)),
+            (_, Some((index, _))) => (index, when.max(steal_time)),
+            (Some((index, reuse)), None) => (index, when.max(reuse)),
+            (None, None) => unreachable!(),
+        }
+    }
+
+    /// Earliest start time of a note on a voice whose sources stop at `stop`
+    ///
+    /// The start lies in the middle of the render quantum following the one in which the
+    /// sources have ended, so both notes never count as active sources in the same quantum.
+    fn reuse_time(&self, stop: f64) -> f64 {
+        let quantum = Self::quantum_duration(&self.context);
+        ((stop / quantum).ceil() + 1.5) * quantum
+    }
+
+    fn quantum_duration<C: BaseAudioContext>(context: &C) -> f64 {
+        RENDER_QUANTUM_SIZE as f64 / context.sample_rate() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::ManualAudioContext;
+    use crate::node::{ConstantSourceNode, GainNode};
+
+    type Nodes = (ConstantSourceNode, GainNode);
+
+    /// Voice playing a constant level, with an attack and release of 10ms
+    fn constant_voice(txn: &Transaction<'_>) -> VoiceGraph<Nodes, f32> {
+        let src = txn.create_constant_source();
+        let env = txn.create_gain();
+        env.gain().set_value(0.);
+        src.connect(&env);
+
+        VoiceGraph::new((src, env), |(_, env): &Nodes| env)
+            .with_source(|(src, _)| src)
+            .on_trigger(|(src, env), level, when| {
+                src.offset().set_value_at_time(*level, when);
+                env.gain()
+                    .set_value_at_time(0., when)
+                    .linear_ramp_to_value_at_time(1., when + 0.01);
+            })
+            .on_release(|(_, env), when| {
+                env.gain()
+                    .cancel_and_hold_at_time(when)
+                    .linear_ramp_to_value_at_time(0., when + 0.01);
+                0.01
+            })
+    }
+
+    #[test]
+    fn test_stealing_limits_active_sources() {
+        let sample_rate = 48_000.;
+        let (context, mut handle) = ManualAudioContext::new(1, sample_rate);
+        let mut pool = VoicePool::new(&context, 16, constant_voice);
+        pool.output().connect(&context.destination());
+
+        let mut output = vec![0.; RENDER_QUANTUM_SIZE];
+        let mut rendered = vec![];
+        let mut max_active = 0;
+
+        // 64 overlapping notes, one per render quantum, never released
+        for _ in 0..64 {
+            pool.note_on(0.05, context.current_time());
+            handle.render(&mut output);
+            rendered.extend_from_slice(&output);
+            max_active = max_active.max(context.active_source_count());
+        }
+        for _ in 0..100 {
+            handle.render(&mut output);
+            rendered.extend_from_slice(&output);
+        }
+
+        assert_eq!(max_active, 16);
+        assert_eq!(context.max_active_source_count(), 16);
+        assert_eq!(context.active_source_count(), 16);
+        assert_eq!(pool.busy_voice_count(context.current_time()), 16);
+
+        // the 16 voices are playing at the end
+        let last = *rendered.last().unwrap();
+        assert!((last - 0.8).abs() < 1e-4, "{}", last);
+
+        // no clicks: cutting a voice would change the output by 0.05 at once, the attacks and
+        // mute ramps in progress change it by 0.05 / 240 per frame each
+        let max_step = rendered
+            .windows(2)
+            .map(|w| (w[1] - w[0]).abs())
+            .fold(0., f32::max);
+        assert!(max_step < 0.005, "{}", max_step);
+    }
+
+    #[test]
+    fn test_released_voices_are_reused() {
+        let sample_rate = 48_000.;
+        let (context, mut handle) = ManualAudioContext::new(1, sample_rate);
+        let mut pool = VoicePool::new(&context, 2, constant_voice);
+        pool.output().connect(&context.destination());
+
+        let a = pool.note_on(0.5, 0.);
+        let b = pool.note_on(0.25, 0.);
+        pool.note_off(a, 0.1);
+        pool.note_off(b, 0.1);
+        assert_eq!(pool.busy_voice_count(0.105), 2);
+        assert_eq!(pool.busy_voice_count(0.2), 0);
+
+        // both voices are free after their tail, the notes start on time
+        pool.note_on(0.5, 0.2);
+        pool.note_on(0.25, 0.2);
+
+        let mut output = vec![0.; RENDER_QUANTUM_SIZE * 100];
+        handle.render(&mut output);
+        assert_eq!(context.max_active_source_count(), 2);
+
+        // 0.2s + 10ms attack
+        let frame = (0.25 * sample_rate) as usize;
+        assert!((output[frame] - 0.75).abs() < 1e-4, "{}", output[frame]);
+    }
+
+    #[test]
+    fn test_note_off_of_stolen_voice() {
+        let (context, _handle) = ManualAudioContext::new(1, 48_000.);
+        let mut pool = VoicePool::new(&context, 1, constant_voice);
+
+        let a = pool.note_on(1., 0.);
+        let b = pool.note_on(1., 0.);
+        assert_eq!(pool.busy_voice_count(0.), 1);
+
+        // the note was stolen, releasing it leaves the new note alone
+        pool.note_off(a, 0.);
+        assert_eq!(pool.busy_voice_count(1.), 1);
+        pool.note_off(b, 0.);
+        assert_eq!(pool.busy_voice_count(1.), 0);
+    }
+
+    #[test]
+    fn test_steal_quietest() {
+        let sample_rate = 48_000.;
+        let (context, mut handle) = ManualAudioContext::new(1, sample_rate);
+        let mut pool =
+            VoicePool::with_steal_policy(&context, 2, StealPolicy::Quietest, constant_voice);
+        pool.output().connect(&context.destination());
+
+        let loud = pool.note_on(0.5, 0.);
+        let quiet = pool.note_on(0.01, 0.);
+
+        // fill the momentary loudness window
+        let mut output = vec![0.; RENDER_QUANTUM_SIZE * 200];
+        handle.render(&mut output);
+
+        pool.note_on(0.5, context.current_time());
+        handle.render(&mut output);
+
+        // the quiet note was stolen
+        let last = *output.last().unwrap();
+        assert!((last - 1.).abs() < 1e-4, "{}", last);
+
+        // releasing the stolen note leaves the new note alone
+        pool.note_off(quiet, context.current_time());
+        pool.note_off(loud, context.current_time());
+        assert_eq!(pool.busy_voice_count(context.current_time() + 1.), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError - a voice pool needs at least one voice")]
+    fn test_no_voices() {
+        let (context, _handle) = ManualAudioContext::new(1, 48_000.);
+        let _ = VoicePool::new(&context, 0, constant_voice);
+    }
+}
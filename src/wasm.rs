@@ -0,0 +1,58 @@
+//! Adapter driving a [`ManualAudioContext`] from a JS `AudioWorkletProcessor`
+//!
+//! The audio graph is built in Rust, then the [`RenderHandle`] is wrapped in a
+//! [`WasmRenderHandle`] and returned to JS, where the `process` method of the worklet pulls the
+//! output:
+//!
+//! ```js
+//! process(inputs, outputs) {
+//!     const output = outputs[0];
+//!     this.handle.render(this.interleaved); // Float32Array of 128 * output.length samples
+//!     output.forEach((channel, c) => {
+//!         for (let i = 0; i < channel.length; i++) {
+//!             channel[i] = this.interleaved[i * output.length + c];
+//!         }
+//!     });
+//!     return true;
+//! }
+//! ```
+//!
+//! [`ManualAudioContext`]: crate::context::ManualAudioContext
+use wasm_bindgen::prelude::*;
+
+use crate::context::RenderHandle;
+
+/// [`RenderHandle`] exported to JS
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct WasmRenderHandle {
+    handle: RenderHandle,
+}
+
+impl From<RenderHandle> for WasmRenderHandle {
+    fn from(handle: RenderHandle) -> Self {
+        Self { handle }
+    }
+}
+
+#[wasm_bindgen]
+impl WasmRenderHandle {
+    /// Render the next frames into the `output` buffer, with the channels interleaved
+    ///
+    /// See [`RenderHandle::render`]
+    pub fn render(&mut self, output: &mut [f32]) {
+        self.handle.render(output);
+    }
+
+    /// Number of interleaved channels of the rendered frames
+    #[wasm_bindgen(getter, js_name = numberOfChannels)]
+    pub fn number_of_channels(&self) -> usize {
+        self.handle.number_of_channels()
+    }
+
+    /// Sample rate of the rendered frames
+    #[wasm_bindgen(getter, js_name = sampleRate)]
+    pub fn sample_rate(&self) -> f32 {
+        self.handle.sample_rate()
+    }
+}
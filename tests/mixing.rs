@@ -104,3 +104,41 @@ fn test_stereo_to_discrete_mono() {
     assert_eq!(output.number_of_channels(), 1);
     assert_float_eq!(output.get_channel_data(0), ONES, abs_all <= 0.);
 }
+
+#[test]
+fn test_zero_channel_count_panics_at_construction() {
+    use web_audio_api::node::{ChannelConfigOptions, GainNode, GainOptions};
+
+    let mut context = OfflineAudioContext::new(1, 128, 44_100.);
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let options = GainOptions {
+            channel_config: ChannelConfigOptions {
+                count: 0,
+                ..ChannelConfigOptions::default()
+            },
+            ..GainOptions::default()
+        };
+        GainNode::new(&context, options)
+    }));
+    let message = result.err().unwrap();
+    let message = message.downcast_ref::<String>().unwrap();
+    assert!(message.starts_with("NotSupportedError"));
+    assert!(message.contains("0"));
+
+    let gain = context.create_gain();
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        gain.set_channel_count(0);
+    }));
+    assert!(result.is_err());
+    assert_eq!(gain.channel_count(), 2);
+
+    // nothing reached the render thread, which renders fine
+    let constant = context.create_constant_source();
+    constant.connect(&gain);
+    gain.connect(&context.destination());
+    constant.start();
+
+    let output = context.start_rendering_sync();
+    assert_float_eq!(output.get_channel_data(0), &[1.; 128][..], abs_all <= 0.);
+}
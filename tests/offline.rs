@@ -1,10 +1,18 @@
 use float_eq::assert_float_eq;
 use web_audio_api::context::BaseAudioContext;
-use web_audio_api::context::OfflineAudioContext;
+use web_audio_api::context::{
+    AudioContextRegistration, ManualAudioContext, OfflineAudioContext, OfflineCancelHandle,
+};
+use web_audio_api::media::MediaDecoder;
 use web_audio_api::node::{
-    AudioNode, AudioScheduledSourceNode, OscillatorNode, OscillatorOptions, OscillatorType,
+    AudioNode, AudioScheduledSourceNode, ChannelConfig, MediaStreamAudioSourceNode,
+    MediaStreamAudioSourceOptions, OscillatorNode, OscillatorOptions,
+};
+use web_audio_api::render::{
+    AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope, DEFAULT_MUTE_RAMP_DURATION,
 };
 use web_audio_api::RENDER_QUANTUM_SIZE;
+use web_audio_api::{AudioBuffer, PeriodicWave, PeriodicWaveOptions};
 
 #[test]
 fn test_offline_render() {
@@ -44,6 +52,16 @@ fn test_offline_render() {
     );
 }
 
+// cosine wave, constant 1 at frequency 0
+fn constant_wave<C: BaseAudioContext>(context: &C) -> PeriodicWave {
+    let options = PeriodicWaveOptions {
+        real: Some(vec![0., 1.]),
+        imag: Some(vec![0., 0.]),
+        disable_normalization: true,
+    };
+    PeriodicWave::new(context, options)
+}
+
 #[test]
 fn test_start_stop() {
     let len = RENDER_QUANTUM_SIZE * 4;
@@ -54,7 +72,7 @@ fn test_start_stop() {
 
     {
         let opts = OscillatorOptions {
-            type_: OscillatorType::Square,
+            periodic_wave: Some(constant_wave(&context)),
             frequency: 0., // constant signal
             ..Default::default()
         };
@@ -79,6 +97,40 @@ fn test_start_stop() {
     assert_float_eq!(channel_data, expected.as_slice(), abs_all <= 0.);
 }
 
+#[test]
+fn test_dropped_source_keeps_playing() {
+    // one render quantum lasts 10 ms
+    let sample_rate = 12_800.;
+    let len = RENDER_QUANTUM_SIZE * 200; // 2 seconds
+
+    let mut context = OfflineAudioContext::new(1, len, sample_rate);
+
+    {
+        let opts = OscillatorOptions {
+            periodic_wave: Some(constant_wave(&context)),
+            frequency: 0., // constant signal
+            ..Default::default()
+        };
+        let osc = OscillatorNode::new(&context, opts);
+        osc.connect(&context.destination());
+        osc.start_at(0.5);
+        osc.stop_at(1.0);
+        // control handle is dropped before the scheduled start
+    }
+
+    let output = context.start_rendering_sync();
+    let channel_data = output.get_channel_data(0);
+
+    // silence until 0.5 s, signal until 1 s, silence again
+    let start = (0.5 * sample_rate) as usize;
+    let stop = (1.0 * sample_rate) as usize;
+    let mut expected = vec![0.; start];
+    expected.append(&mut vec![1.; stop - start]);
+    expected.append(&mut vec![0.; len - stop]);
+
+    assert_float_eq!(channel_data, expected.as_slice(), abs_all <= 0.);
+}
+
 #[test]
 fn test_delayed_constant_source() {
     let len = RENDER_QUANTUM_SIZE * 4;
@@ -287,3 +339,1080 @@ fn test_spatial() {
         abs_all <= 0.001
     );
 }
+
+// column-major transform, rotation of `angle` radians about the Y axis, then uniform
+// `scale` and `translation`
+fn transform(angle: f32, scale: f32, translation: [f32; 3]) -> [f32; 16] {
+    let (sin, cos) = angle.sin_cos();
+    #[rustfmt::skip]
+    let matrix = [
+        scale * cos, 0., -scale * sin, 0.,
+        0., scale, 0., 0.,
+        scale * sin, 0., scale * cos, 0.,
+        translation[0], translation[1], translation[2], 1.,
+    ];
+    matrix
+}
+
+// render a constant source through a panner, returns the (left, right) levels
+fn render_panner_transform(panner_matrix: [f32; 16], listener_matrix: [f32; 16]) -> (f32, f32) {
+    let mut context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 48_000.);
+
+    let panner = context.create_panner();
+    panner.set_transform(panner_matrix, 0.);
+    panner.connect(&context.destination());
+    context.listener().set_transform(listener_matrix, 0.);
+
+    let constant = context.create_constant_source();
+    constant.connect(&panner);
+    constant.start();
+
+    let output = context.start_rendering_sync();
+    (output.get_channel_data(0)[0], output.get_channel_data(1)[0])
+}
+
+#[test]
+fn test_panner_transform_params() {
+    use std::f32::consts::PI;
+
+    // the param values are updated at the start of the second render quantum
+    let mut context = OfflineAudioContext::new(1, 2 * RENDER_QUANTUM_SIZE, 48_000.);
+    let panner = context.create_panner();
+    panner.connect(&context.destination());
+    // quarter turn about Y, scaled
+    panner.set_transform(transform(PI / 2., 4., [1., 2., 3.]), 0.);
+    let listener = context.listener();
+    listener.set_transform(transform(PI, 0.5, [-1., 0., 0.]), 0.);
+    context.start_rendering_sync();
+
+    let position = [
+        panner.position_x().value(),
+        panner.position_y().value(),
+        panner.position_z().value(),
+    ];
+    assert_float_eq!(position, [1., 2., 3.], abs_all <= 0.);
+    let orientation = [
+        panner.orientation_x().value(),
+        panner.orientation_y().value(),
+        panner.orientation_z().value(),
+    ];
+    assert_float_eq!(orientation, [-1., 0., 0.], abs_all <= 1e-6);
+
+    let position = [
+        listener.position_x().value(),
+        listener.position_y().value(),
+        listener.position_z().value(),
+    ];
+    assert_float_eq!(position, [-1., 0., 0.], abs_all <= 0.);
+    let forward = [
+        listener.forward_x().value(),
+        listener.forward_y().value(),
+        listener.forward_z().value(),
+    ];
+    assert_float_eq!(forward, [0., 0., 1.], abs_all <= 1e-6);
+    let up = [
+        listener.up_x().value(),
+        listener.up_y().value(),
+        listener.up_z().value(),
+    ];
+    assert_float_eq!(up, [0., 1., 0.], abs_all <= 1e-6);
+}
+
+#[test]
+fn test_panner_transform_balance() {
+    use std::f32::consts::PI;
+
+    let identity = transform(0., 1., [0.; 3]);
+
+    // pure translation, in front of the listener
+    let (left, right) = render_panner_transform(transform(0., 1., [0., 0., -1.]), identity);
+    assert_float_eq!(left, right, abs <= 1e-6);
+    assert!(left > 0.);
+
+    // to the right of the listener, scale does not change the position
+    let (left, right) = render_panner_transform(transform(PI / 3., 3., [1., 0., 0.]), identity);
+    assert!(right > left);
+
+    // the listener turns a quarter to the left, the source in front is now on the right
+    let listener = transform(PI / 2., 1., [0.; 3]);
+    let (left, right) = render_panner_transform(transform(0., 1., [0., 0., -1.]), listener);
+    assert!(right > 0.);
+    assert_float_eq!(left, 0., abs <= 1e-6);
+
+    // zero scale listener, the previous orientation is kept (no NaN)
+    let listener = transform(PI / 2., 0., [0.; 3]);
+    let (left, right) = render_panner_transform(transform(0., 1., [1., 0., 0.]), listener);
+    assert!(!left.is_nan() && !right.is_nan());
+    assert!(right > left);
+}
+
+#[test]
+fn test_play_audio_buffer() {
+    let sample_rate = 48_000.;
+    let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 2, sample_rate);
+
+    let buffer = AudioBuffer::from(vec![vec![1.; RENDER_QUANTUM_SIZE]], sample_rate);
+    // start at frame 100
+    let src = context.play_audio_buffer(buffer, 100. / sample_rate as f64);
+    assert!(src.buffer().is_some());
+
+    let output = context.start_rendering_sync();
+    let channel = output.get_channel_data(0);
+
+    assert_float_eq!(channel[..100], [0.; 100][..], abs_all <= 0.);
+    assert_float_eq!(
+        channel[100..100 + RENDER_QUANTUM_SIZE],
+        [1.; RENDER_QUANTUM_SIZE][..],
+        abs_all <= 0.
+    );
+    assert_float_eq!(
+        channel[100 + RENDER_QUANTUM_SIZE..],
+        [0.; RENDER_QUANTUM_SIZE - 100][..],
+        abs_all <= 0.
+    );
+}
+
+#[test]
+fn test_play_audio_buffer_with_offset() {
+    let sample_rate = 48_000.;
+    let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, sample_rate);
+
+    let samples: Vec<f32> = (0..RENDER_QUANTUM_SIZE).map(|i| i as f32).collect();
+    let buffer = AudioBuffer::from(vec![samples], sample_rate);
+    // skip the first 28 frames of the buffer
+    let _src = context.play_audio_buffer_with_offset(buffer, 0., 28. / sample_rate as f64);
+
+    let output = context.start_rendering_sync();
+    let channel = output.get_channel_data(0);
+
+    let expected: Vec<f32> = (28..RENDER_QUANTUM_SIZE).map(|i| i as f32).collect();
+    assert_float_eq!(channel[..100], expected[..], abs_all <= 1e-6);
+    assert_float_eq!(channel[100..], [0.; 28][..], abs_all <= 0.);
+}
+
+#[test]
+fn test_active_source_count() {
+    // the duration of a render quantum is a power of two, the buffers end exactly at the
+    // quantum boundaries
+    let sample_rate = 16_384.;
+    let quantum = RENDER_QUANTUM_SIZE as f64 / sample_rate as f64;
+
+    let (context, mut handle) = ManualAudioContext::new(1, sample_rate);
+    assert_eq!(context.active_source_count(), 0);
+
+    // each buffer plays for two render quanta
+    let buffer = AudioBuffer::from(vec![vec![0.1; RENDER_QUANTUM_SIZE * 2]], sample_rate);
+
+    // active in quanta 0 and 1
+    context.play_audio_buffer(buffer.clone(), 0.);
+    // active in quanta 1 and 2
+    context.play_audio_buffer(buffer.clone(), quantum);
+    // active in quanta 1, 2 and 3
+    context.play_audio_buffer(buffer.clone(), 1.5 * quantum);
+    // active in quanta 4 and 5
+    context.play_audio_buffer(buffer, 4. * quantum);
+    // active from quantum 6 until the end
+    let osc = context.create_oscillator();
+    osc.connect(&context.destination());
+    osc.start_at(6. * quantum);
+
+    // nothing is playing before rendering
+    assert_eq!(context.active_source_count(), 0);
+    assert_eq!(context.max_active_source_count(), 0);
+
+    // render quantum by quantum, the count follows the schedule: (active count, high-water mark)
+    // after each quantum
+    let mut output = vec![0.; RENDER_QUANTUM_SIZE];
+    let expected = [
+        (1, 1),
+        (3, 3),
+        (2, 3),
+        (1, 3),
+        (1, 3),
+        (1, 3),
+        (1, 3),
+        (1, 3),
+    ];
+    for (index, &(count, max)) in expected.iter().enumerate() {
+        handle.render(&mut output);
+        assert_eq!(context.active_source_count(), count, "quantum {}", index);
+        assert_eq!(context.max_active_source_count(), max, "quantum {}", index);
+    }
+
+    // only the oscillator is still playing
+    context.reset_max_active_source_count();
+    assert_eq!(context.max_active_source_count(), 1);
+
+    osc.stop();
+    handle.render(&mut output);
+    assert_eq!(context.active_source_count(), 0);
+    assert_eq!(context.max_active_source_count(), 1);
+}
+
+#[test]
+fn test_stop_not_after_start() {
+    // one render quantum lasts 10 ms
+    let sample_rate = 12_800.;
+    let frame = |index: f64| index / sample_rate as f64;
+
+    // the start is mid-frame, half a sample after it no frame is played
+    let start = frame(300.25);
+    for &stop in [frame(200.25), start, frame(300.75)].iter() {
+        let (context, mut handle) = ManualAudioContext::new(1, sample_rate);
+
+        let osc = context.create_oscillator();
+        osc.connect(&context.destination());
+        osc.start_at(start);
+        osc.stop_at(stop);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        osc.set_onended(move |time| sender.send(time).unwrap());
+
+        let src = context.create_constant_source();
+        src.connect(&context.destination());
+        src.start_at(start);
+        src.stop_at(stop);
+
+        let buffer = AudioBuffer::from(vec![vec![1.; RENDER_QUANTUM_SIZE * 8]], sample_rate);
+        let buffer_src = context.create_buffer_source();
+        buffer_src.set_buffer(buffer);
+        buffer_src.connect(&context.destination());
+        buffer_src.start_at(start);
+        buffer_src.stop_at(stop);
+
+        // the sources end in the first render quantum after their stop time
+        let ended = (stop * sample_rate as f64 / RENDER_QUANTUM_SIZE as f64).ceil() as usize;
+        for _ in 0..=ended {
+            assert!(receiver.try_recv().is_err(), "ended early, stop {}", stop);
+
+            let mut output = vec![1.; RENDER_QUANTUM_SIZE];
+            handle.render(&mut output);
+            assert!(output.iter().all(|v| *v == 0.), "stop {}", stop);
+        }
+
+        let timeout = std::time::Duration::from_secs(1);
+        assert_eq!(receiver.recv_timeout(timeout), Ok(stop));
+        assert_eq!(context.active_source_count(), 0);
+
+        // nothing plays afterwards either
+        let mut output = vec![1.; RENDER_QUANTUM_SIZE * 4];
+        handle.render(&mut output);
+        assert!(output.iter().all(|v| *v == 0.), "stop {}", stop);
+    }
+}
+
+#[test]
+fn test_smooth_set_value() {
+    let sample_rate = 48_000.;
+    let length = RENDER_QUANTUM_SIZE * 5;
+    let mut context = OfflineAudioContext::new(1, length, sample_rate);
+
+    let gain = context.create_gain();
+    gain.connect(&context.destination());
+    // this ramp is cancelled by the smooth_set_value call
+    gain.gain().linear_ramp_to_value_at_time(10., 1.);
+    // 10 ms smoothing equals 480 samples
+    gain.gain().smooth_set_value(0., 10.);
+
+    let src = context.create_constant_source();
+    src.connect(&gain);
+    src.start();
+
+    let output = context.start_rendering_sync();
+    let channel = output.get_channel_data(0);
+
+    let expected: Vec<f32> = (0..length)
+        .map(|i| (1. - i as f32 / 480.).max(0.))
+        .collect();
+    assert_float_eq!(channel, &expected[..], abs_all <= 1e-5);
+
+    // no discontinuities
+    let max_step = channel
+        .windows(2)
+        .map(|w| (w[1] - w[0]).abs())
+        .fold(0., f32::max);
+    assert!(max_step <= 1. / 480. + 1e-5);
+}
+
+#[test]
+fn test_deterministic_summation() {
+    let render = || {
+        let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 4, 44_100.);
+        let gain = context.create_gain();
+        gain.connect(&context.destination());
+
+        for i in 0..50 {
+            let src = context.create_oscillator();
+            src.frequency().set_value(110. + 17.3 * i as f32);
+            let amplitude = context.create_gain();
+            amplitude.gain().set_value(1. / (i + 1) as f32);
+            src.connect(&amplitude);
+            amplitude.connect(&gain);
+            src.start();
+        }
+
+        context.start_rendering_sync()
+    };
+
+    let reference = render();
+    for _ in 0..5 {
+        let output = render();
+        let bits = |buffer: &AudioBuffer| -> Vec<u32> {
+            buffer
+                .get_channel_data(0)
+                .iter()
+                .map(|v| v.to_bits())
+                .collect()
+        };
+        assert_eq!(bits(&output), bits(&reference));
+    }
+}
+
+/// Source node cancelling the rendering of its context from the given frame on
+struct CancelNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+}
+
+impl AudioNode for CancelNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        0
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl CancelNode {
+    fn new(context: &OfflineAudioContext, frame: u64) -> Self {
+        let handle = context.cancel_handle();
+        context.register(move |registration| {
+            let node = Self {
+                registration,
+                channel_config: ChannelConfig::default(),
+            };
+            (node, Box::new(CancelProcessor { handle, frame }))
+        })
+    }
+}
+
+struct CancelProcessor {
+    handle: OfflineCancelHandle,
+    frame: u64,
+}
+
+impl AudioProcessor for CancelProcessor {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        outputs[0].make_silent();
+        if scope.current_frame >= self.frame {
+            self.handle.cancel();
+        }
+        true
+    }
+}
+
+#[test]
+fn test_cancel_rendering() {
+    use rand::{Rng, SeedableRng};
+
+    let sample_rate = 44_100.;
+    let quanta = 100;
+    let length = RENDER_QUANTUM_SIZE * quanta;
+    let render = |cancel_at: Option<u64>| {
+        let mut context = OfflineAudioContext::new(1, length, sample_rate);
+
+        // known ramp signal
+        let src = context.create_constant_source();
+        src.offset().set_value(0.);
+        src.offset()
+            .linear_ramp_to_value_at_time(1., length as f64 / sample_rate as f64);
+        src.connect(&context.destination());
+        src.start();
+
+        // the cancellation is triggered from the render thread, at a known quantum
+        if let Some(frame) = cancel_at {
+            let canceller = CancelNode::new(&context, frame);
+            canceller.connect(&context.destination());
+        }
+
+        context.start_rendering_sync_cancellable()
+    };
+
+    let reference = render(None);
+    assert!(!reference.cancelled);
+    assert_eq!(reference.frames_rendered, length);
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    for _ in 0..5 {
+        // cancel in any quantum but the last one
+        let quantum = rng.gen_range(0..quanta - 1);
+        let output = render(Some((quantum * RENDER_QUANTUM_SIZE) as u64));
+
+        // the quantum in which the cancellation happens is completed
+        assert!(output.cancelled);
+        assert_eq!(output.frames_rendered, (quantum + 1) * RENDER_QUANTUM_SIZE);
+        assert_eq!(output.buffer.length(), output.frames_rendered);
+
+        // exactly the prefix of the full rendering
+        let prefix = &reference.buffer.get_channel_data(0)[..output.frames_rendered];
+        assert_float_eq!(output.buffer.get_channel_data(0), prefix, abs_all <= 0.);
+    }
+}
+
+/// Largest difference between two consecutive samples
+fn max_step(samples: &[f32]) -> f32 {
+    samples
+        .windows(2)
+        .map(|w| (w[1] - w[0]).abs())
+        .fold(0., f32::max)
+}
+
+#[test]
+fn test_mute_node() {
+    let sample_rate = 48_000.;
+    let ramp = (DEFAULT_MUTE_RAMP_DURATION * sample_rate as f64) as usize;
+    let (context, mut handle) = ManualAudioContext::new(1, sample_rate);
+
+    let src = context.create_constant_source();
+    src.connect(&context.destination());
+    src.start();
+
+    let mut before = vec![0.; RENDER_QUANTUM_SIZE * 2];
+    handle.render(&mut before);
+    assert!(before.iter().all(|v| *v == 1.));
+
+    // mid-tone
+    src.set_muted(true);
+    let mut output = vec![0.; RENDER_QUANTUM_SIZE * 4];
+    handle.render(&mut output);
+
+    let expected: Vec<f32> = (0..output.len())
+        .map(|i| (1. - (i + 1) as f32 / ramp as f32).max(0.))
+        .collect();
+    assert_float_eq!(&output[..], &expected[..], abs_all <= 1e-5);
+    assert!(output.windows(2).all(|w| w[1] <= w[0]));
+    assert!(max_step(&output) <= 1. / ramp as f32 + 1e-5);
+    assert!(output[ramp - 1..].iter().all(|v| *v == 0.));
+}
+
+#[test]
+fn test_first_quantum_after_construction() {
+    let sample_rate = 48_000.;
+
+    // pulled immediately, the graph is complete
+    let (context, mut handle) = ManualAudioContext::new(1, sample_rate);
+    let mut output = vec![1.; RENDER_QUANTUM_SIZE];
+    handle.render(&mut output);
+    assert!(output.iter().all(|v| *v == 0.));
+    assert_float_eq!(
+        context.current_time(),
+        RENDER_QUANTUM_SIZE as f64 / 48_000.,
+        abs <= 0.
+    );
+
+    // a source started at 0 before the first pull is audible in quantum zero
+    let (context, mut handle) = ManualAudioContext::new(1, sample_rate);
+    let osc = context.create_oscillator();
+    osc.connect(&context.destination());
+    osc.start_at(0.);
+
+    let mut output = vec![0.; RENDER_QUANTUM_SIZE];
+    handle.render(&mut output);
+    assert_float_eq!(output[0], 0., abs <= 0.);
+    assert!(output[1] > 0.);
+
+    let mut offline = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, sample_rate);
+    let osc = offline.create_oscillator();
+    osc.connect(&offline.destination());
+    osc.start_at(0.);
+    let expected = offline.start_rendering_sync();
+    assert_float_eq!(output[..], expected.get_channel_data(0)[..], abs_all <= 0.);
+}
+
+#[test]
+fn test_mute_node_double_toggle() {
+    let sample_rate = 48_000.;
+    let ramp = (DEFAULT_MUTE_RAMP_DURATION * sample_rate as f64) as usize;
+    let (context, mut handle) = ManualAudioContext::new(1, sample_rate);
+
+    let src = context.create_constant_source();
+    src.connect(&context.destination());
+    src.start();
+
+    // unmute within the ramp
+    src.set_muted(true);
+    let mut output = vec![0.; RENDER_QUANTUM_SIZE * 4];
+    handle.render(&mut output[..RENDER_QUANTUM_SIZE]);
+    src.set_muted(false);
+    handle.render(&mut output[RENDER_QUANTUM_SIZE..]);
+
+    // back to unity along the same slope, from the lowest gain reached
+    let lowest = 1. - RENDER_QUANTUM_SIZE as f32 / ramp as f32;
+    assert_float_eq!(output[RENDER_QUANTUM_SIZE - 1], lowest, abs <= 1e-5);
+    assert!(output[..RENDER_QUANTUM_SIZE]
+        .windows(2)
+        .all(|w| w[1] <= w[0]));
+    assert!(output[RENDER_QUANTUM_SIZE..]
+        .windows(2)
+        .all(|w| w[1] >= w[0]));
+    assert!(max_step(&output) <= 1. / ramp as f32 + 1e-5);
+    assert!(output[RENDER_QUANTUM_SIZE * 2..].iter().all(|v| *v == 1.));
+}
+
+#[test]
+fn test_mute_context() {
+    let sample_rate = 48_000.;
+    let length = RENDER_QUANTUM_SIZE * 5;
+    let mut context = OfflineAudioContext::new(1, length, sample_rate);
+
+    let src = context.create_constant_source();
+    src.connect(&context.destination());
+    src.start();
+
+    // 10 ms ramp equals 480 samples
+    context.set_muted_with_ramp(true, 0.01);
+
+    let output = context.start_rendering_sync();
+    let channel = output.get_channel_data(0);
+
+    let expected: Vec<f32> = (0..length)
+        .map(|i| (1. - (i + 1) as f32 / 480.).max(0.))
+        .collect();
+    assert_float_eq!(channel, &expected[..], abs_all <= 1e-5);
+    assert!(max_step(channel) <= 1. / 480. + 1e-5);
+}
+
+#[test]
+#[should_panic(expected = "RangeError")]
+fn test_mute_negative_ramp() {
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44100.);
+    context.set_muted_with_ramp(true, -1.);
+}
+
+#[test]
+fn test_disconnect_unconnected_node() {
+    let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44100.);
+
+    let osc = context.create_oscillator();
+    osc.disconnect();
+    osc.start();
+
+    let output = context.start_rendering_sync();
+    assert_float_eq!(
+        output.get_channel_data(0),
+        &[0.; RENDER_QUANTUM_SIZE][..],
+        abs_all <= 0.
+    );
+}
+
+#[test]
+#[should_panic(expected = "IndexSizeError")]
+fn test_connect_to_out_of_bounds_input() {
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44100.);
+
+    // AudioParams are connected with `connect(param)`, no input port stands for them
+    let osc = context.create_oscillator();
+    osc.connect_at(&context.destination(), 0, usize::MAX);
+}
+
+#[test]
+fn test_gain_mute_and_stop_ordering() {
+    use rand::Rng;
+
+    let sample_rate = 44_100.;
+    let length = RENDER_QUANTUM_SIZE * 16;
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..20 {
+        let mut context = OfflineAudioContext::new(1, length, sample_rate);
+
+        // mute and stop at the same random time, issued back-to-back
+        let frame = rng.gen_range(0..length);
+        let when = frame as f64 / sample_rate as f64;
+
+        let gain = context.create_gain();
+        gain.connect(&context.destination());
+
+        let src = context.create_constant_source();
+        src.connect(&gain);
+        src.start();
+
+        gain.gain().set_value_at_time(0., when);
+        src.stop_at(when);
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+
+        // no audio leaks after the gain hit zero
+        assert_float_eq!(
+            channel[frame..],
+            vec![0.; length - frame][..],
+            abs_all <= 0.
+        );
+    }
+}
+
+#[test]
+fn test_stereo_param_input_is_downmixed() {
+    let sample_rate = 48_000.;
+    let length = RENDER_QUANTUM_SIZE * 16;
+
+    // reference: plain 200 Hz sine
+    let mut context = OfflineAudioContext::new(1, length, sample_rate);
+    let osc = context.create_oscillator();
+    osc.frequency().set_value(200.);
+    osc.connect(&context.destination());
+    osc.start();
+    let expected = context.start_rendering_sync();
+
+    // FM with a stereo modulator, 100 Hz on the left and 300 Hz on the right, mixed down to
+    // mono as (L + R) / 2 by the frequency param
+    let mut context = OfflineAudioContext::new(1, length, sample_rate);
+    let carrier = context.create_oscillator();
+    carrier.frequency().set_value(0.);
+    carrier.connect(&context.destination());
+    carrier.start();
+
+    let merger = context.create_channel_merger(2);
+    merger.connect(carrier.frequency());
+    for (channel, value) in [100., 300.].iter().enumerate() {
+        let src = context.create_constant_source();
+        src.offset().set_value(*value);
+        src.connect_at(&merger, 0, channel);
+        src.start();
+    }
+
+    let output = context.start_rendering_sync();
+
+    assert_float_eq!(
+        output.get_channel_data(0),
+        expected.get_channel_data(0),
+        abs_all <= 1e-4
+    );
+}
+
+// stereo signal from a merger, 0.25 on the left and 0.75 on the right
+fn stereo_constant(context: &OfflineAudioContext) -> web_audio_api::node::ChannelMergerNode {
+    let merger = context.create_channel_merger(2);
+    for (channel, value) in [0.25, 0.75].iter().enumerate() {
+        let src = context.create_constant_source();
+        src.offset().set_value(*value);
+        src.connect_at(&merger, 0, channel);
+        src.start();
+    }
+    merger
+}
+
+#[test]
+fn test_connect_channel() {
+    let length = RENDER_QUANTUM_SIZE * 2;
+    let mut context = OfflineAudioContext::new(2, length, 44_100.);
+
+    let merger = stereo_constant(&context);
+    let gain = context.create_gain();
+    gain.connect(&context.destination());
+    merger.connect_channel(&gain, 0, 1, 0);
+
+    let output = context.start_rendering_sync();
+
+    // the right channel arrives as mono, up-mixed to both channels of the destination
+    let expected = vec![0.75; length];
+    assert_float_eq!(output.get_channel_data(0), &expected[..], abs_all <= 0.);
+    assert_float_eq!(output.get_channel_data(1), &expected[..], abs_all <= 0.);
+}
+
+#[test]
+fn test_connect_channel_out_of_range() {
+    let length = RENDER_QUANTUM_SIZE * 2;
+    let mut context = OfflineAudioContext::new(2, length, 44_100.);
+
+    let merger = stereo_constant(&context);
+    let gain = context.create_gain();
+    gain.connect(&context.destination());
+    merger.connect_channel(&gain, 0, 5, 0);
+
+    let output = context.start_rendering_sync();
+
+    let expected = vec![0.; length];
+    assert_float_eq!(output.get_channel_data(0), &expected[..], abs_all <= 0.);
+    assert_float_eq!(output.get_channel_data(1), &expected[..], abs_all <= 0.);
+}
+
+#[test]
+fn test_connect_channel_of_delay() {
+    let sample_rate = 44_100.;
+    let length = RENDER_QUANTUM_SIZE * 4;
+    let mut context = OfflineAudioContext::new(1, length, sample_rate);
+
+    let merger = stereo_constant(&context);
+    let delay = context.create_delay(1.);
+    delay
+        .delay_time()
+        .set_value(RENDER_QUANTUM_SIZE as f32 / sample_rate);
+    merger.connect(&delay);
+    delay.connect_channel(&context.destination(), 0, 0, 0);
+
+    let output = context.start_rendering_sync();
+
+    let mut expected = vec![0.25; length];
+    expected[..RENDER_QUANTUM_SIZE].fill(0.);
+    assert_float_eq!(output.get_channel_data(0), &expected[..], abs_all <= 0.);
+}
+
+#[test]
+fn test_output_tap() {
+    let sample_rate = 48_000.;
+    let length = RENDER_QUANTUM_SIZE * 8;
+
+    // reference: the mono oscillator
+    let mut context = OfflineAudioContext::new(1, length, sample_rate);
+    let osc = context.create_oscillator();
+    osc.connect(&context.destination());
+    osc.start();
+    let reference = context.start_rendering_sync();
+
+    // the mono oscillator is up-mixed to the 2 output channels
+    let (context, mut handle) = ManualAudioContext::new(2, sample_rate);
+    let osc = context.create_oscillator();
+    osc.connect(&context.destination());
+    osc.start();
+
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    context.set_output_tap(move |samples: &[f32], channels: usize| {
+        assert_eq!(channels, 2);
+        sender.send(samples.to_vec()).unwrap();
+    });
+
+    // device buffers that are not aligned with the render quanta
+    let mut device = vec![];
+    let mut output = vec![0.; 2 * 300];
+    while device.len() < 2 * length {
+        handle.render(&mut output);
+        device.extend_from_slice(&output);
+    }
+    device.truncate(2 * length);
+
+    let tapped: Vec<f32> = receiver.try_iter().flatten().take(2 * length).collect();
+    assert_eq!(tapped.len(), 2 * length);
+    assert_float_eq!(&tapped[..], &device[..], abs_all <= 0.);
+
+    let expected: Vec<f32> = reference
+        .get_channel_data(0)
+        .iter()
+        .flat_map(|s| [*s, *s])
+        .collect();
+    assert_float_eq!(&tapped[..], &expected[..], abs_all <= 0.);
+
+    // no more calls once cleared
+    receiver.try_iter().for_each(drop);
+    context.clear_output_tap();
+    handle.render(&mut output);
+    assert!(receiver.try_iter().next().is_none());
+}
+
+// frequency of each period of the signal, from its rising zero crossings
+fn zero_crossing_frequencies(signal: &[f32], sample_rate: f32) -> Vec<f32> {
+    let crossings: Vec<f32> = signal
+        .windows(2)
+        .enumerate()
+        .filter(|(_, w)| w[0] < 0. && w[1] >= 0.)
+        .map(|(i, w)| i as f32 + w[0] / (w[0] - w[1]))
+        .collect();
+    crossings
+        .windows(2)
+        .map(|w| sample_rate / (w[1] - w[0]))
+        .collect()
+}
+
+fn render_vibrato(drop_modulation: bool) -> Vec<f32> {
+    let sample_rate = 48_000.;
+    let mut context = OfflineAudioContext::new(1, sample_rate as usize, sample_rate);
+
+    let osc = context.create_oscillator();
+    osc.frequency().set_value(440.);
+    osc.connect(&context.destination());
+    osc.start();
+
+    let lfo = context.create_oscillator();
+    lfo.frequency().set_value(5.);
+    lfo.start();
+
+    let modulation = osc.frequency().modulate(&lfo, 10.);
+    if drop_modulation {
+        drop(modulation);
+    }
+
+    let output = context.start_rendering_sync();
+    zero_crossing_frequencies(output.get_channel_data(0), sample_rate)
+}
+
+#[test]
+fn test_modulate_param() {
+    let frequencies = render_vibrato(false);
+    let max = frequencies.iter().copied().fold(f32::MIN, f32::max);
+    let min = frequencies.iter().copied().fold(f32::MAX, f32::min);
+    assert_float_eq!(max, 450., abs <= 0.1);
+    assert_float_eq!(min, 430., abs <= 0.1);
+
+    let frequencies = render_vibrato(true);
+    assert_float_eq!(
+        frequencies[..],
+        vec![440.; frequencies.len()][..],
+        abs_all <= 0.05
+    );
+}
+
+#[test]
+fn test_modulation_depth() {
+    let sample_rate = 48_000.;
+    let mut context = OfflineAudioContext::new(1, sample_rate as usize, sample_rate);
+
+    let osc = context.create_oscillator();
+    osc.connect(&context.destination());
+    osc.start();
+
+    let lfo = context.create_constant_source();
+    lfo.start();
+
+    let modulation = osc.frequency().modulate(&lfo, 10.);
+    modulation.set_depth(-40.);
+    assert_float_eq!(modulation.depth().value(), -40., abs <= 0.);
+
+    let output = context.start_rendering_sync();
+    let frequencies = zero_crossing_frequencies(output.get_channel_data(0), sample_rate);
+    assert_float_eq!(
+        frequencies[..],
+        vec![400.; frequencies.len()][..],
+        abs_all <= 0.05
+    );
+    drop(modulation);
+}
+
+#[test]
+fn test_set_periodic_wave_hammering() {
+    let sample_rate = 48_000.;
+    let quanta = 200;
+
+    // sine wave of the n-th harmonic
+    fn harmonic<C: BaseAudioContext>(context: &C, n: usize) -> PeriodicWave {
+        let mut imag = vec![0.; n + 1];
+        imag[n] = 1.;
+        context.create_periodic_wave(PeriodicWaveOptions {
+            real: Some(vec![0.; n + 1]),
+            imag: Some(imag),
+            disable_normalization: false,
+        })
+    }
+
+    let (context, mut handle) = ManualAudioContext::new(1, sample_rate);
+    let osc = context.create_oscillator();
+    osc.connect(&context.destination());
+    osc.start();
+
+    let waves: Vec<_> = (1..=4).map(|n| harmonic(&context, n)).collect();
+
+    // replace the wave much faster than the renderer reads it
+    let renderer = std::thread::spawn(move || {
+        let mut output = [0.; RENDER_QUANTUM_SIZE];
+        (0..quanta).for_each(|_| handle.render(&mut output));
+        handle
+    });
+    (0..10_000).for_each(|i| osc.set_periodic_wave(waves[i % 4].clone()));
+    let mut handle = renderer.join().unwrap();
+
+    let mut output = [0.; RENDER_QUANTUM_SIZE];
+    handle.render(&mut output);
+
+    // the last quantum is rendered with the last wave
+    let length = (quanta + 1) * RENDER_QUANTUM_SIZE;
+    let mut reference = OfflineAudioContext::new(1, length, sample_rate);
+    let osc = reference.create_oscillator();
+    osc.set_periodic_wave(harmonic(&reference, 4));
+    osc.connect(&reference.destination());
+    osc.start();
+    let expected = reference.start_rendering_sync();
+
+    assert_float_eq!(
+        output[..],
+        expected.get_channel_data(0)[length - RENDER_QUANTUM_SIZE..],
+        abs_all <= 1e-6
+    );
+}
+
+#[test]
+fn test_buffered_stream_offline() {
+    let sample_rate = 48_000.;
+    let length = RENDER_QUANTUM_SIZE * 150;
+
+    let decoder = || {
+        let file = std::fs::File::open("samples/think-mono-48000.wav").unwrap();
+        MediaDecoder::try_new(file).unwrap()
+    };
+
+    // live reference, rendered once the stream has been buffered
+    let (context, mut handle) = ManualAudioContext::new(1, sample_rate);
+    let options = MediaStreamAudioSourceOptions {
+        media_stream: decoder(),
+    };
+    let src = MediaStreamAudioSourceNode::new_buffered(&context, options);
+    src.connect(&context.destination());
+    std::thread::sleep(std::time::Duration::from_millis(500));
+
+    let mut expected = vec![0.; length];
+    handle.render(&mut expected);
+    assert!(expected.iter().any(|v| *v != 0.));
+
+    // the offline rendering waits for a stream slower than the rendering
+    let mut context = OfflineAudioContext::new(1, length, sample_rate);
+    let slow = decoder().inspect(|_| std::thread::sleep(std::time::Duration::from_millis(5)));
+    let options = MediaStreamAudioSourceOptions { media_stream: slow };
+    let src = MediaStreamAudioSourceNode::new_buffered(&context, options);
+    src.connect(&context.destination());
+
+    let output = context.start_rendering_sync();
+    assert_float_eq!(output.get_channel_data(0), &expected[..], abs_all <= 0.);
+}
+
+#[test]
+fn test_params_by_name() {
+    let sample_rate = 48_000.;
+    let length = 48_000;
+
+    // oscillator, frequency and detune set by name
+    let mut context = OfflineAudioContext::new(1, length, sample_rate);
+    let osc = context.create_oscillator();
+    let names: Vec<_> = osc.params().iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, ["frequency", "detune", "pulseWidth"]);
+
+    osc.param("frequency").unwrap().set_value(1_000.);
+    osc.param("detune").unwrap().set_value(1_200.);
+    assert!(osc.param("gain").is_none());
+    osc.connect(&context.destination());
+    osc.start();
+
+    let output = context.start_rendering_sync();
+    assert_float_eq!(output.peak_frequency(0, sample_rate), 2_000., abs <= 1.);
+
+    // gain set by name
+    let mut context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, sample_rate);
+    let src = context.create_constant_source();
+    let gain = context.create_gain();
+    let names: Vec<_> = gain.params().iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, ["gain"]);
+
+    gain.param("gain").unwrap().set_value(0.5);
+    src.param("offset").unwrap().set_value(0.8);
+    src.connect(&gain);
+    gain.connect(&context.destination());
+    src.start();
+
+    let output = context.start_rendering_sync();
+    assert_float_eq!(
+        output.get_channel_data(0),
+        &[0.4; RENDER_QUANTUM_SIZE][..],
+        abs_all <= 0.
+    );
+
+    // biquad, the named params are the params of the node
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, sample_rate);
+    let biquad = context.create_biquad_filter();
+    let names: Vec<_> = biquad.params().iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, ["frequency", "detune", "Q", "gain"]);
+
+    biquad.param("Q").unwrap().set_value(4.);
+    assert_float_eq!(biquad.q().value(), 4., abs <= 0.);
+    assert!(std::ptr::eq(biquad.param("gain").unwrap(), biquad.gain()));
+}
+
+#[test]
+fn test_start_stop_at_frame() {
+    for &sample_rate in [44_100., 48_000., 22_050.].iter() {
+        for &(start, stop) in [(0, 1), (1, 128), (127, 129), (300, 1001), (555, 1023)].iter() {
+            let mut context = OfflineAudioContext::new(4, 1024, sample_rate);
+            let merger = context.create_channel_merger(4);
+            merger.connect(&context.destination());
+
+            let constant = context.create_constant_source();
+            constant.connect_at(&merger, 0, 0);
+            constant.start_at_frame(start);
+            constant.stop_at_frame(stop);
+
+            let mut ones = context.create_buffer(1, 1024, sample_rate);
+            ones.copy_to_channel(&[1.; 1024], 0);
+            let src = context.create_buffer_source();
+            src.set_buffer(ones);
+            src.connect_at(&merger, 0, 1);
+            src.start_at_frame(start);
+            src.stop_at_frame(stop);
+
+            let osc = context.create_oscillator();
+            osc.connect_at(&merger, 0, 2);
+            osc.start_at_frame(start);
+
+            assert_eq!(
+                constant.scheduled_start_time(),
+                Some(start as f64 / sample_rate as f64)
+            );
+
+            let output = context.start_rendering_sync();
+            for channel in 0..2 {
+                let data = output.get_channel_data(channel);
+                for (index, value) in data.iter().enumerate() {
+                    let expected = if (start as usize..stop as usize).contains(&index) {
+                        1.
+                    } else {
+                        0.
+                    };
+                    assert_eq!(
+                        *value, expected,
+                        "channel {} at {} Hz, frame {}",
+                        channel, sample_rate, index
+                    );
+                }
+            }
+
+            let osc = output.get_channel_data(2);
+            assert!(osc[..start as usize + 1].iter().all(|v| *v == 0.));
+            assert!(osc[start as usize + 1] != 0.);
+        }
+    }
+}
+
+#[test]
+fn test_start_at_time_of_frame() {
+    // a time computed from a frame starts on this exact frame
+    for &sample_rate in [44_100., 48_000., 96_000.].iter() {
+        let mut context = OfflineAudioContext::new(1, 4096, sample_rate);
+        let frames: Vec<u64> = (0..4096).step_by(37).collect();
+        for &frame in frames.iter() {
+            let src = context.create_constant_source();
+            src.connect(&context.destination());
+            src.start_at(frame as f64 / sample_rate as f64);
+            src.stop_at((frame + 1) as f64 / sample_rate as f64);
+        }
+
+        let output = context.start_rendering_sync();
+        for (index, value) in output.get_channel_data(0).iter().enumerate() {
+            let expected = if frames.contains(&(index as u64)) {
+                1.
+            } else {
+                0.
+            };
+            assert_eq!(*value, expected, "{} Hz, frame {}", sample_rate, index);
+        }
+    }
+}
@@ -0,0 +1,635 @@
+//! Offline render scenarios ported from the Web Platform Tests of the WebAudio API
+//!
+//! Each file of `tests/wpt/fixtures` describes one scenario: the graph to build in an
+//! `OfflineAudioContext` and the expected rendered samples. The expected values are derived
+//! from the formulas of the specification (or from the WPT test they are taken from), not from
+//! the output of this crate. Adding a scenario is adding a fixture file.
+//!
+//! # Fixture format
+//!
+//! One directive per line, a line starting with whitespace continues the previous directive.
+//! Lines starting with `#` are comments, the first one describes the scenario. Times are in
+//! seconds, or in sample frames when suffixed with `f` (e.g. `64f`).
+//!
+//! ```text
+//! context <channels> <length> <sample_rate>
+//! buffer <name> <channel 0 samples> [| <channel 1 samples> ...]
+//! node <name> <kind> [<key>=<value> ...]
+//! channel destination [<key>=<value> ...]
+//! connect <node>[:<output>] <node>[:<input>] | <node>.<param> | destination
+//! param <node>.<param> <method> <args...>
+//! start <node> [<when> [<offset> [<duration>]]]
+//! stop <node> [<when>]
+//! expect <channel> <frame> <tolerance> <samples...>
+//! fill <channel> <from frame> <to frame> <value> <tolerance>
+//! known_failure <reason>
+//! ```
+//!
+//! The node kinds are `oscillator`, `gain`, `constant`, `buffer_source`, `splitter` and
+//! `merger`. All nodes, and `destination` in a `channel` directive, accept the
+//! `channel_count`, `channel_count_mode` and `channel_interpretation` keys.
+//!
+//! A scenario marked as `known_failure` documents a deviation from the specification: its
+//! failure is reported but does not fail the test run, while an unexpected pass does.
+//!
+//! Set `WPT_FILTER` to only run the fixtures whose name contains the given string.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use web_audio_api::context::{BaseAudioContext, OfflineAudioContext};
+use web_audio_api::node::{
+    AudioBufferSourceNode, AudioNode, AudioScheduledSourceNode, ChannelCountMode,
+    ChannelInterpretation, ChannelMergerNode, ChannelMergerOptions, ChannelSplitterNode,
+    ChannelSplitterOptions, ConstantSourceNode, GainNode, OscillatorNode, OscillatorType,
+};
+use web_audio_api::{AudioBuffer, AudioParam};
+
+/// A fixture line, split in whitespace separated tokens
+struct Directive {
+    line: usize,
+    tokens: Vec<String>,
+}
+
+struct Fixture {
+    name: String,
+    description: String,
+    known_failure: Option<String>,
+    directives: Vec<Directive>,
+}
+
+enum Outcome {
+    Pass,
+    Fail(String),
+}
+
+enum Node {
+    Oscillator(OscillatorNode),
+    Gain(GainNode),
+    Constant(ConstantSourceNode),
+    BufferSource(AudioBufferSourceNode),
+    Splitter(ChannelSplitterNode),
+    Merger(ChannelMergerNode),
+}
+
+impl Node {
+    fn audio_node(&self) -> &dyn AudioNode {
+        match self {
+            Node::Oscillator(n) => n,
+            Node::Gain(n) => n,
+            Node::Constant(n) => n,
+            Node::BufferSource(n) => n,
+            Node::Splitter(n) => n,
+            Node::Merger(n) => n,
+        }
+    }
+
+    fn scheduled_source(&self) -> Result<&dyn AudioScheduledSourceNode, String> {
+        match self {
+            Node::Oscillator(n) => Ok(n),
+            Node::Constant(n) => Ok(n),
+            Node::BufferSource(n) => Ok(n),
+            _ => Err("not a scheduled source node".into()),
+        }
+    }
+
+    fn param(&self, name: &str) -> Result<&AudioParam, String> {
+        let param = match (self, name) {
+            (Node::Oscillator(n), "frequency") => n.frequency(),
+            (Node::Oscillator(n), "detune") => n.detune(),
+            (Node::Gain(n), "gain") => n.gain(),
+            (Node::Constant(n), "offset") => n.offset(),
+            (Node::BufferSource(n), "playback_rate") => n.playback_rate(),
+            (Node::BufferSource(n), "detune") => n.detune(),
+            _ => return Err(format!("unknown param `{}`", name)),
+        };
+
+        Ok(param)
+    }
+}
+
+/// State of a scenario while its directives are applied
+struct Scenario {
+    context: OfflineAudioContext,
+    buffers: HashMap<String, AudioBuffer>,
+    nodes: HashMap<String, Node>,
+}
+
+fn parse<T: std::str::FromStr>(token: &str) -> Result<T, String> {
+    token
+        .parse()
+        .map_err(|_| format!("invalid value `{}`", token))
+}
+
+fn arg(tokens: &[String], index: usize) -> Result<&str, String> {
+    tokens
+        .get(index)
+        .map(String::as_str)
+        .ok_or_else(|| format!("missing argument {}", index))
+}
+
+/// `<key>=<value>` arguments
+fn options(tokens: &[String]) -> Result<HashMap<&str, &str>, String> {
+    tokens
+        .iter()
+        .map(|t| {
+            let mut split = t.splitn(2, '=');
+            match (split.next(), split.next()) {
+                (Some(key), Some(value)) => Ok((key, value)),
+                _ => Err(format!("expected <key>=<value>, got `{}`", t)),
+            }
+        })
+        .collect()
+}
+
+impl Scenario {
+    fn time(&self, token: &str) -> Result<f64, String> {
+        match token.strip_suffix('f') {
+            Some(frames) => Ok(parse::<f64>(frames)? / f64::from(self.context.sample_rate())),
+            None => parse(token),
+        }
+    }
+
+    fn node(&self, name: &str) -> Result<&Node, String> {
+        self.nodes
+            .get(name)
+            .ok_or_else(|| format!("unknown node `{}`", name))
+    }
+
+    /// Resolve `<node>.<param>`
+    fn param(&self, target: &str) -> Result<&AudioParam, String> {
+        let mut split = target.splitn(2, '.');
+        let node = self.node(split.next().unwrap())?;
+        let param = split
+            .next()
+            .ok_or_else(|| format!("expected <node>.<param>, got `{}`", target))?;
+        node.param(param)
+    }
+
+    /// Resolve `<node>[:<port>]`
+    fn port<'a>(&self, target: &'a str) -> Result<(&'a str, usize), String> {
+        let mut split = target.splitn(2, ':');
+        let name = split.next().unwrap();
+        let port = split.next().map(parse).transpose()?.unwrap_or(0);
+        Ok((name, port))
+    }
+
+    fn create_node(&mut self, tokens: &[String]) -> Result<(), String> {
+        let name = arg(tokens, 0)?;
+        let kind = arg(tokens, 1)?;
+        let mut opts = options(&tokens[2..])?;
+        let context = &self.context;
+
+        let node = match kind {
+            "oscillator" => {
+                let node = context.create_oscillator();
+                if let Some(type_) = opts.remove("type") {
+                    node.set_type(match type_ {
+                        "sine" => OscillatorType::Sine,
+                        "square" => OscillatorType::Square,
+                        "sawtooth" => OscillatorType::Sawtooth,
+                        "triangle" => OscillatorType::Triangle,
+                        _ => return Err(format!("unknown oscillator type `{}`", type_)),
+                    });
+                }
+                if let Some(v) = opts.remove("frequency") {
+                    node.frequency().set_value(parse(v)?);
+                }
+                if let Some(v) = opts.remove("detune") {
+                    node.detune().set_value(parse(v)?);
+                }
+                Node::Oscillator(node)
+            }
+            "gain" => {
+                let node = context.create_gain();
+                if let Some(v) = opts.remove("gain") {
+                    node.gain().set_value(parse(v)?);
+                }
+                Node::Gain(node)
+            }
+            "constant" => {
+                let node = context.create_constant_source();
+                if let Some(v) = opts.remove("offset") {
+                    node.offset().set_value(parse(v)?);
+                }
+                Node::Constant(node)
+            }
+            "buffer_source" => {
+                let node = context.create_buffer_source();
+                if let Some(v) = opts.remove("buffer") {
+                    let buffer = self
+                        .buffers
+                        .get(v)
+                        .ok_or_else(|| format!("unknown buffer `{}`", v))?;
+                    node.set_buffer(buffer.clone());
+                }
+                if let Some(v) = opts.remove("loop") {
+                    node.set_loop(parse(v)?);
+                }
+                if let Some(v) = opts.remove("loop_start") {
+                    node.set_loop_start(self.time(v)?);
+                }
+                if let Some(v) = opts.remove("loop_end") {
+                    node.set_loop_end(self.time(v)?);
+                }
+                if let Some(v) = opts.remove("playback_rate") {
+                    node.playback_rate().set_value(parse(v)?);
+                }
+                Node::BufferSource(node)
+            }
+            "splitter" => {
+                let number_of_outputs = opts.remove("outputs").map(parse).transpose()?;
+                let options = ChannelSplitterOptions {
+                    number_of_outputs: number_of_outputs.unwrap_or(6),
+                    ..Default::default()
+                };
+                Node::Splitter(ChannelSplitterNode::new(context, options))
+            }
+            "merger" => {
+                let number_of_inputs = opts.remove("inputs").map(parse).transpose()?;
+                let options = ChannelMergerOptions {
+                    number_of_inputs: number_of_inputs.unwrap_or(6),
+                    ..Default::default()
+                };
+                Node::Merger(ChannelMergerNode::new(context, options))
+            }
+            _ => return Err(format!("unknown node kind `{}`", kind)),
+        };
+
+        set_channel_config(node.audio_node(), &mut opts)?;
+        if let Some(key) = opts.keys().next() {
+            return Err(format!("unknown option `{}` for {}", key, kind));
+        }
+
+        self.nodes.insert(name.to_string(), node);
+        Ok(())
+    }
+
+    fn connect(&self, tokens: &[String]) -> Result<(), String> {
+        let (source, output) = self.port(arg(tokens, 0)?)?;
+        let source = self.node(source)?.audio_node();
+        let target = arg(tokens, 1)?;
+
+        if target == "destination" {
+            source.connect_at(&self.context.destination(), output, 0);
+        } else if target.contains('.') {
+            source.connect_at(self.param(target)?, output, 0);
+        } else {
+            let (dest, input) = self.port(target)?;
+            source.connect_at(self.node(dest)?.audio_node(), output, input);
+        }
+
+        Ok(())
+    }
+
+    fn automate(&self, tokens: &[String]) -> Result<(), String> {
+        let param = self.param(arg(tokens, 0)?)?;
+        let method = arg(tokens, 1)?;
+        let value = |i| arg(tokens, i).and_then(parse::<f32>);
+        let time = |i| arg(tokens, i).and_then(|t| self.time(t));
+
+        match method {
+            "set_value" => {
+                param.set_value(value(2)?);
+            }
+            "set_value_at_time" => {
+                param.set_value_at_time(value(2)?, time(3)?);
+            }
+            "linear_ramp_to_value_at_time" => {
+                param.linear_ramp_to_value_at_time(value(2)?, time(3)?);
+            }
+            "exponential_ramp_to_value_at_time" => {
+                param.exponential_ramp_to_value_at_time(value(2)?, time(3)?);
+            }
+            "set_target_at_time" => {
+                param.set_target_at_time(value(2)?, time(3)?, time(4)?);
+            }
+            "set_value_curve_at_time" => {
+                let values = tokens[4..]
+                    .iter()
+                    .map(|t| parse(t))
+                    .collect::<Result<Vec<f32>, _>>()?;
+                param.set_value_curve_at_time(&values, time(2)?, time(3)?);
+            }
+            _ => return Err(format!("unknown automation method `{}`", method)),
+        }
+
+        Ok(())
+    }
+
+    fn apply(&mut self, directive: &Directive) -> Result<(), String> {
+        let (name, args) = directive.tokens.split_first().unwrap();
+
+        match name.as_str() {
+            "buffer" => {
+                let name = arg(args, 0)?;
+                let channels = args[1..]
+                    .split(|t| t == "|")
+                    .map(|c| c.iter().map(|t| parse(t)).collect())
+                    .collect::<Result<Vec<Vec<f32>>, _>>()?;
+                let buffer = AudioBuffer::from(channels, self.context.sample_rate());
+                self.buffers.insert(name.to_string(), buffer);
+            }
+            "channel" => {
+                if arg(args, 0)? != "destination" {
+                    return Err("only the destination is configured with `channel`".into());
+                }
+                let mut opts = options(&args[1..])?;
+                set_channel_config(&self.context.destination(), &mut opts)?;
+            }
+            "node" => self.create_node(args)?,
+            "connect" => self.connect(args)?,
+            "param" => self.automate(args)?,
+            "start" => {
+                let node = self.node(arg(args, 0)?)?;
+                let times = args[1..]
+                    .iter()
+                    .map(|t| self.time(t))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                match (node, times.as_slice()) {
+                    (node, []) => node.scheduled_source()?.start(),
+                    (node, [when]) => node.scheduled_source()?.start_at(*when),
+                    (Node::BufferSource(n), [when, offset]) => {
+                        n.start_at_with_offset(*when, *offset)
+                    }
+                    (Node::BufferSource(n), [when, offset, duration]) => {
+                        n.start_at_with_offset_and_duration(*when, *offset, *duration)
+                    }
+                    _ => return Err("invalid start arguments".into()),
+                }
+            }
+            "stop" => {
+                let node = self.node(arg(args, 0)?)?.scheduled_source()?;
+                match args.get(1) {
+                    Some(when) => node.stop_at(self.time(when)?),
+                    None => node.stop(),
+                }
+            }
+            // checked once rendered
+            "expect" | "fill" | "known_failure" => (),
+            _ => return Err(format!("unknown directive `{}`", name)),
+        }
+
+        Ok(())
+    }
+}
+
+fn set_channel_config(node: &dyn AudioNode, opts: &mut HashMap<&str, &str>) -> Result<(), String> {
+    if let Some(v) = opts.remove("channel_count") {
+        node.set_channel_count(parse(v)?);
+    }
+    if let Some(v) = opts.remove("channel_count_mode") {
+        node.set_channel_count_mode(match v {
+            "max" => ChannelCountMode::Max,
+            "clamped-max" => ChannelCountMode::ClampedMax,
+            "explicit" => ChannelCountMode::Explicit,
+            _ => return Err(format!("unknown channel count mode `{}`", v)),
+        });
+    }
+    if let Some(v) = opts.remove("channel_interpretation") {
+        node.set_channel_interpretation(match v {
+            "speakers" => ChannelInterpretation::Speakers,
+            "discrete" => ChannelInterpretation::Discrete,
+            _ => return Err(format!("unknown channel interpretation `{}`", v)),
+        });
+    }
+
+    Ok(())
+}
+
+/// Compare the rendered samples of a channel with the expected ones, starting at `frame`
+fn check_samples(
+    output: &AudioBuffer,
+    channel: usize,
+    frame: usize,
+    tolerance: f32,
+    expected: &[f32],
+) -> Result<(), String> {
+    if channel >= output.number_of_channels() {
+        return Err(format!("no channel {} in the output", channel));
+    }
+    let rendered = output.get_channel_data(channel);
+    if frame + expected.len() > rendered.len() {
+        return Err(format!(
+            "expected frames {}..{} but only {} frames were rendered",
+            frame,
+            frame + expected.len(),
+            rendered.len()
+        ));
+    }
+
+    let actual = &rendered[frame..frame + expected.len()];
+    match actual
+        .iter()
+        .zip(expected)
+        .position(|(a, e)| a.is_nan() || (a - e).abs() > tolerance)
+    {
+        None => Ok(()),
+        Some(i) => Err(format!(
+            "channel {} frame {}: expected {} (+/- {}), got {}",
+            channel,
+            frame + i,
+            expected[i],
+            tolerance,
+            actual[i]
+        )),
+    }
+}
+
+fn check(output: &AudioBuffer, directive: &Directive) -> Result<(), String> {
+    let (name, args) = directive.tokens.split_first().unwrap();
+
+    match name.as_str() {
+        "expect" => {
+            let expected = args[3..]
+                .iter()
+                .map(|t| parse(t))
+                .collect::<Result<Vec<f32>, _>>()?;
+            check_samples(
+                output,
+                parse(arg(args, 0)?)?,
+                parse(arg(args, 1)?)?,
+                parse(arg(args, 2)?)?,
+                &expected,
+            )
+        }
+        "fill" => {
+            let from: usize = parse(arg(args, 1)?)?;
+            let to: usize = parse(arg(args, 2)?)?;
+            let expected = vec![parse(arg(args, 3)?)?; to.saturating_sub(from)];
+            check_samples(
+                output,
+                parse(arg(args, 0)?)?,
+                from,
+                parse(arg(args, 4)?)?,
+                &expected,
+            )
+        }
+        _ => Ok(()),
+    }
+}
+
+fn load(path: &Path) -> Result<Fixture, String> {
+    let source = fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+    let mut description = None;
+    let mut known_failure = None;
+    let mut directives: Vec<Directive> = vec![];
+
+    for (i, line) in source.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            if description.is_none() {
+                description = Some(comment.trim().to_string());
+            }
+            continue;
+        }
+
+        let tokens = trimmed.split_whitespace().map(String::from);
+        if line.starts_with(char::is_whitespace) {
+            match directives.last_mut() {
+                Some(directive) => directive.tokens.extend(tokens),
+                None => return Err(format!("line {}: continuation of nothing", i + 1)),
+            }
+        } else {
+            directives.push(Directive {
+                line: i + 1,
+                tokens: tokens.collect(),
+            });
+        }
+    }
+
+    for directive in &directives {
+        if directive.tokens[0] == "known_failure" {
+            known_failure = Some(directive.tokens[1..].join(" "));
+        }
+    }
+
+    Ok(Fixture {
+        name: path.file_stem().unwrap().to_string_lossy().into_owned(),
+        description: description.unwrap_or_default(),
+        known_failure,
+        directives,
+    })
+}
+
+/// Prefix an error with the line of the directive
+fn at_line(directive: &Directive) -> impl Fn(String) -> String + '_ {
+    move |e| format!("line {}: {}", directive.line, e)
+}
+
+fn run(fixture: &Fixture) -> Outcome {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let (context, rest) = match fixture.directives.split_first() {
+            Some((d, rest)) if d.tokens[0] == "context" => {
+                let args = &d.tokens[1..];
+                let channels = arg(args, 0).and_then(parse).map_err(at_line(d))?;
+                let length = arg(args, 1).and_then(parse).map_err(at_line(d))?;
+                let sample_rate = arg(args, 2).and_then(parse).map_err(at_line(d))?;
+                (
+                    OfflineAudioContext::new(channels, length, sample_rate),
+                    rest,
+                )
+            }
+            _ => return Err("the first directive should be `context`".to_string()),
+        };
+
+        let mut scenario = Scenario {
+            context,
+            buffers: HashMap::new(),
+            nodes: HashMap::new(),
+        };
+        for directive in rest {
+            scenario.apply(directive).map_err(at_line(directive))?;
+        }
+
+        let output = scenario.context.start_rendering_sync();
+        for directive in rest {
+            check(&output, directive).map_err(at_line(directive))?;
+        }
+
+        Ok(())
+    }));
+
+    match result {
+        Ok(Ok(())) => Outcome::Pass,
+        Ok(Err(e)) => Outcome::Fail(e),
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_default();
+            Outcome::Fail(format!("panicked: {}", message))
+        }
+    }
+}
+
+fn fixtures() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/wpt/fixtures");
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .expect("missing fixtures directory")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension() == Some(OsStr::new("txt")))
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn web_platform_tests() {
+    let filter = std::env::var("WPT_FILTER").unwrap_or_default();
+    let mut errors = vec![];
+    let mut count = 0;
+
+    for path in fixtures() {
+        let fixture = match load(&path) {
+            Ok(fixture) => fixture,
+            Err(e) => {
+                errors.push(format!("{}: {}", path.display(), e));
+                continue;
+            }
+        };
+        if !fixture.name.contains(&filter) {
+            continue;
+        }
+        count += 1;
+
+        match (run(&fixture), &fixture.known_failure) {
+            (Outcome::Pass, None) => println!("PASS  {} - {}", fixture.name, fixture.description),
+            (Outcome::Fail(e), None) => {
+                println!(
+                    "FAIL  {} - {}\n      {}",
+                    fixture.name, fixture.description, e
+                );
+                errors.push(format!("{}: {}", fixture.name, e));
+            }
+            (Outcome::Fail(e), Some(reason)) => {
+                println!("XFAIL {} - {}\n      {}", fixture.name, reason, e);
+            }
+            (Outcome::Pass, Some(_)) => {
+                println!("XPASS {} - {}", fixture.name, fixture.description);
+                errors.push(format!(
+                    "{}: passes, remove its `known_failure` directive",
+                    fixture.name
+                ));
+            }
+        }
+    }
+
+    assert!(count > 0, "no fixture matches WPT_FILTER={}", filter);
+    assert!(
+        errors.is_empty(),
+        "{} of {} scenarios failed:\n{}",
+        errors.len(),
+        count,
+        errors.join("\n")
+    );
+}